@@ -0,0 +1,37 @@
+CREATE TABLE orders
+(
+	order_id          Uuid,
+	strategy_id       Uuid NOT NULL,
+	broker            Text NOT NULL,
+	broker_order_id   Text,
+	ticker			  Text NOT NULL,
+	side			  Text NOT NULL,
+	quantity		  Decimal(20, 8) NOT NULL,
+	filled_quantity   Decimal(20, 8) NOT NULL DEFAULT 0,
+	filled_avg_price  Decimal(20, 8),
+	status            Text NOT NULL,
+  	created_at        Timestamptz NOT NULL,
+  	modified_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (order_id)
+);
+
+CREATE INDEX idx_orders_strategy_id ON orders (strategy_id);
+CREATE INDEX idx_orders_status ON orders (status);
+
+CREATE TABLE fills
+(
+	fill_id           Uuid,
+	order_id          Uuid NOT NULL REFERENCES orders (order_id),
+	strategy_id       Uuid NOT NULL,
+	ticker			  Text NOT NULL,
+	side			  Text NOT NULL,
+	quantity		  Decimal(20, 8) NOT NULL,
+	price			  Decimal(20, 8) NOT NULL,
+	fee				  Decimal(20, 8) NOT NULL DEFAULT 0,
+	filled_at         Timestamptz NOT NULL,
+
+  	PRIMARY KEY (fill_id)
+);
+
+CREATE INDEX idx_fills_strategy_id_filled_at ON fills (strategy_id, filled_at);
@@ -0,0 +1,12 @@
+CREATE TABLE usage_events
+(
+	usage_event_id    Uuid,
+	tenant_id         Text NOT NULL,
+	kind              Text NOT NULL,
+	strategy_id       Uuid,
+	recorded_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (usage_event_id)
+);
+
+CREATE INDEX idx_usage_events_tenant_id_recorded_at ON usage_events (tenant_id, recorded_at);
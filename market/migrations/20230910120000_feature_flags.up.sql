@@ -0,0 +1,16 @@
+CREATE TABLE feature_flags
+(
+	name              Text,
+	enabled           Boolean NOT NULL,
+	description       Text,
+  	modified_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (name)
+);
+
+INSERT INTO
+  feature_flags (name, enabled, description, modified_at)
+VALUES
+  ('enable_shorting', true, 'Allow strategies to open short positions', NOW()),
+  ('enable_new_sizing_engine', false, 'Size orders with the new sizing engine', NOW()),
+  ('dual_write_orders_v2', false, 'Write orders to both the current and the v2 order storage', NOW());
@@ -0,0 +1,13 @@
+CREATE TABLE portfolio_snapshots
+(
+	snapshot_id       Uuid,
+	broker            Text NOT NULL,
+	equity            Decimal(20, 8) NOT NULL,
+	cash              Decimal(20, 8) NOT NULL,
+	positions         Jsonb NOT NULL,
+	taken_at          Timestamptz NOT NULL,
+
+  	PRIMARY KEY (snapshot_id)
+);
+
+CREATE INDEX idx_portfolio_snapshots_broker_taken_at ON portfolio_snapshots (broker, taken_at);
@@ -0,0 +1,5 @@
+DROP INDEX idx_orders_execution_path;
+
+ALTER TABLE orders DROP COLUMN execution_path;
+
+ALTER TABLE feature_flags DROP COLUMN rollout_percentage;
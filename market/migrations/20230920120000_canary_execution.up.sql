@@ -0,0 +1,6 @@
+ALTER TABLE feature_flags ADD COLUMN rollout_percentage Smallint NOT NULL DEFAULT 100
+	CHECK (rollout_percentage BETWEEN 0 AND 100);
+
+ALTER TABLE orders ADD COLUMN execution_path Text NOT NULL DEFAULT 'stable';
+
+CREATE INDEX idx_orders_execution_path ON orders (execution_path);
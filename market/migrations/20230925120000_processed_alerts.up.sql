@@ -0,0 +1,10 @@
+CREATE TABLE processed_alerts
+(
+	idempotency_key   Text,
+	strategy_id       Uuid NOT NULL,
+	received_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (idempotency_key)
+);
+
+CREATE INDEX idx_processed_alerts_received_at ON processed_alerts (received_at);
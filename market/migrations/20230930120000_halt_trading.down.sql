@@ -0,0 +1 @@
+DELETE FROM feature_flags WHERE name = 'halt_trading';
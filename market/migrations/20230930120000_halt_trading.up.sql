@@ -0,0 +1,4 @@
+INSERT INTO
+  feature_flags (name, enabled, description, modified_at)
+VALUES
+  ('halt_trading', false, 'Stop processing trade signals, set by the drawdown kill switch', NOW());
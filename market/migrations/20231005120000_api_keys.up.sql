@@ -0,0 +1,12 @@
+CREATE TABLE api_keys
+(
+	api_key_id        Uuid,
+	name              Text NOT NULL,
+	key_hash          Text NOT NULL,
+	role              Text NOT NULL,
+  	created_at        Timestamptz NOT NULL,
+  	revoked_at        Timestamptz,
+
+  	PRIMARY KEY (api_key_id),
+  	UNIQUE (key_hash)
+);
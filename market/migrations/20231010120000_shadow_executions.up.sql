@@ -0,0 +1,9 @@
+CREATE TABLE shadow_executions
+(
+	order_id          Uuid REFERENCES orders (order_id),
+	quantity		  Decimal(20, 8) NOT NULL,
+	avg_price		  Decimal(20, 8) NOT NULL,
+	simulated_at      Timestamptz NOT NULL,
+
+  	PRIMARY KEY (order_id)
+);
@@ -0,0 +1,4 @@
+DROP INDEX idx_orders_request_id;
+
+ALTER TABLE processed_alerts DROP COLUMN request_id;
+ALTER TABLE orders DROP COLUMN request_id;
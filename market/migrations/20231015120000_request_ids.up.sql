@@ -0,0 +1,4 @@
+ALTER TABLE orders ADD COLUMN request_id Text;
+ALTER TABLE processed_alerts ADD COLUMN request_id Text;
+
+CREATE INDEX idx_orders_request_id ON orders (request_id);
@@ -0,0 +1,14 @@
+CREATE TABLE broker_interactions
+(
+	interaction_id    Uuid,
+	broker            Text NOT NULL,
+	operation         Text NOT NULL,
+	request           Jsonb NOT NULL,
+	response          Jsonb,
+	error             Text,
+	recorded_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (interaction_id)
+);
+
+CREATE INDEX idx_broker_interactions_recorded_at ON broker_interactions (recorded_at);
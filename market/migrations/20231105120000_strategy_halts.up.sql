@@ -0,0 +1,9 @@
+CREATE TABLE strategy_halts
+(
+	strategy_id       Uuid NOT NULL,
+	trading_day       Date NOT NULL,
+	reason            Text NOT NULL,
+	halted_at         Timestamptz NOT NULL,
+
+  	PRIMARY KEY (strategy_id, trading_day)
+);
@@ -0,0 +1,13 @@
+CREATE TABLE risk_violations
+(
+	violation_id      Uuid NOT NULL,
+	strategy_id       Uuid NOT NULL,
+	ticker            Text NOT NULL,
+	rule              Text NOT NULL,
+	details           Text NOT NULL,
+	created_at        Timestamptz NOT NULL,
+
+  	PRIMARY KEY (violation_id)
+);
+
+CREATE INDEX idx_risk_violations_created_at ON risk_violations (created_at);
@@ -0,0 +1 @@
+ALTER TABLE orders ADD COLUMN notional Decimal(20, 8);
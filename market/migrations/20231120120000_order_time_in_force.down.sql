@@ -0,0 +1 @@
+ALTER TABLE orders DROP COLUMN time_in_force;
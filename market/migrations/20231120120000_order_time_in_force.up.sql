@@ -0,0 +1 @@
+ALTER TABLE orders ADD COLUMN time_in_force Text;
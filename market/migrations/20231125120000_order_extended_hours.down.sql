@@ -0,0 +1,2 @@
+ALTER TABLE orders DROP COLUMN extended_hours;
+ALTER TABLE orders DROP COLUMN limit_price;
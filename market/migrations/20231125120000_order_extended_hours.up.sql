@@ -0,0 +1,2 @@
+ALTER TABLE orders ADD COLUMN limit_price Decimal(20, 8);
+ALTER TABLE orders ADD COLUMN extended_hours Boolean NOT NULL DEFAULT false;
@@ -0,0 +1,8 @@
+CREATE TABLE strategy_switches
+(
+	strategy_id       Uuid NOT NULL,
+	reason            Text,
+	disabled_at       Timestamptz NOT NULL,
+
+  	PRIMARY KEY (strategy_id)
+);
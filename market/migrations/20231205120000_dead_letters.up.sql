@@ -0,0 +1,14 @@
+CREATE TABLE dead_letters
+(
+	dead_letter_id    Uuid NOT NULL,
+	strategy_id       Uuid NOT NULL,
+	request_id        Text,
+	alert             Jsonb NOT NULL,
+	error             Text NOT NULL,
+	failed_at         Timestamptz NOT NULL,
+	replayed_at       Timestamptz,
+
+  	PRIMARY KEY (dead_letter_id)
+);
+
+CREATE INDEX idx_dead_letters_failed_at ON dead_letters (failed_at);
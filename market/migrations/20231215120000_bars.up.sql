@@ -0,0 +1,14 @@
+CREATE TABLE bars
+(
+	symbol            Text NOT NULL,
+	timeframe         Text NOT NULL,
+	bar_time          Timestamptz NOT NULL,
+	open              Decimal(20, 8) NOT NULL,
+	high              Decimal(20, 8) NOT NULL,
+	low               Decimal(20, 8) NOT NULL,
+	close             Decimal(20, 8) NOT NULL,
+	volume            Decimal(20, 8) NOT NULL,
+	updated_at        Timestamptz NOT NULL,
+
+  	PRIMARY KEY (symbol, timeframe, bar_time)
+);
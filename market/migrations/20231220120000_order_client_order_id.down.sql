@@ -0,0 +1 @@
+ALTER TABLE orders DROP COLUMN client_order_id;
@@ -0,0 +1,6 @@
+-- Orders submitted before were sent with their order id as client order id
+ALTER TABLE orders ADD COLUMN client_order_id Text;
+UPDATE orders SET client_order_id = order_id::text;
+ALTER TABLE orders ALTER COLUMN client_order_id SET NOT NULL;
+
+CREATE UNIQUE INDEX idx_orders_client_order_id ON orders (client_order_id);
@@ -0,0 +1 @@
+ALTER TABLE orders DROP COLUMN last_filled_at;
@@ -0,0 +1,6 @@
+-- Time of the latest execution of an order, to recognize partial fills which went stale
+ALTER TABLE orders ADD COLUMN last_filled_at Timestamptz;
+UPDATE orders
+SET last_filled_at = fills.last_filled_at
+FROM (SELECT order_id, MAX(filled_at) AS last_filled_at FROM fills GROUP BY order_id) AS fills
+WHERE orders.order_id = fills.order_id;
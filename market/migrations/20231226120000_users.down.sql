@@ -0,0 +1,2 @@
+ALTER TABLE api_keys DROP COLUMN user_id;
+DROP TABLE users;
@@ -0,0 +1,14 @@
+-- Users own strategies by the tenant id of their configuration
+CREATE TABLE users
+(
+	user_id           Text,
+	name              Text NOT NULL,
+  	created_at        Timestamptz NOT NULL,
+
+  	PRIMARY KEY (user_id)
+);
+
+INSERT INTO users (user_id, name, created_at) VALUES ('default', 'default', NOW());
+
+-- Keys without a user belong to operators, who see the data of every user
+ALTER TABLE api_keys ADD COLUMN user_id Text REFERENCES users (user_id);
@@ -0,0 +1,8 @@
+CREATE TABLE daily_reports
+(
+	report_date       Date NOT NULL,
+	report            Jsonb NOT NULL,
+  	created_at        Timestamptz NOT NULL,
+
+  	PRIMARY KEY (report_date)
+);
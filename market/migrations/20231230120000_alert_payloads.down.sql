@@ -0,0 +1,3 @@
+DROP INDEX idx_alerts_created_at;
+ALTER TABLE alerts DROP COLUMN payload;
+ALTER TABLE alerts DROP COLUMN strategy_id;
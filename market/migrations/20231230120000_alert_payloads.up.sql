@@ -0,0 +1,5 @@
+-- Alerts as received, so past traffic can be replayed. Alerts stored before have none.
+ALTER TABLE alerts ADD COLUMN strategy_id Uuid;
+ALTER TABLE alerts ADD COLUMN payload Jsonb;
+
+CREATE INDEX idx_alerts_created_at ON alerts (created_at);
@@ -0,0 +1,8 @@
+CREATE TABLE strategy_leases
+(
+	strategy_id       Uuid NOT NULL,
+	holder            Uuid NOT NULL,
+	expires_at        Timestamptz NOT NULL,
+
+  	PRIMARY KEY (strategy_id)
+);
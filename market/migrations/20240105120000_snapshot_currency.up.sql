@@ -0,0 +1,2 @@
+-- Currency of the equity and cash of snapshots, the ones taken before were all in USD
+ALTER TABLE portfolio_snapshots ADD COLUMN currency Text NOT NULL DEFAULT 'USD';
@@ -0,0 +1,4 @@
+-- Entry order a take profit order of a ladder exits the position of
+ALTER TABLE orders ADD COLUMN parent_order_id Uuid REFERENCES orders (order_id);
+
+CREATE INDEX idx_orders_parent_order_id ON orders (parent_order_id);
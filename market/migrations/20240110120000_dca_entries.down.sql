@@ -0,0 +1,2 @@
+ALTER TABLE orders DROP COLUMN entry_id;
+DROP TABLE dca_entries;
@@ -0,0 +1,24 @@
+CREATE TABLE dca_entries
+(
+	entry_id          Uuid NOT NULL,
+	strategy_id       Uuid NOT NULL,
+	ticker            Text NOT NULL,
+	side              Text NOT NULL,
+	quantity          Decimal(20, 8) NOT NULL,
+	chunk_quantity    Decimal(20, 8) NOT NULL,
+	chunks            Integer NOT NULL,
+	chunks_sent       Integer NOT NULL,
+	chunk_interval    BigInt NOT NULL,
+	stop_loss_price   Decimal(20, 8),
+	status            Text NOT NULL,
+	next_at           Timestamptz NOT NULL,
+	created_at        Timestamptz NOT NULL,
+	modified_at       Timestamptz NOT NULL,
+
+	PRIMARY KEY (entry_id)
+);
+
+CREATE INDEX idx_dca_entries_status_next_at ON dca_entries (status, next_at);
+
+-- Time-sliced entry an order is a chunk of
+ALTER TABLE orders ADD COLUMN entry_id Uuid REFERENCES dca_entries (entry_id);
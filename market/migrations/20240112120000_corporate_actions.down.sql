@@ -0,0 +1 @@
+DROP TABLE corporate_actions;
@@ -0,0 +1,11 @@
+-- Splits and symbol changes applied to the stored fills, orders and bars, each once
+CREATE TABLE corporate_actions
+(
+	action_id         Text NOT NULL,
+	kind              Text NOT NULL,
+	symbol            Text NOT NULL,
+	effective_on      Date NOT NULL,
+	applied_at        Timestamptz NOT NULL,
+
+	PRIMARY KEY (action_id)
+);
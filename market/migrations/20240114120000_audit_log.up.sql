@@ -0,0 +1,15 @@
+-- Changes made to the service at runtime, who made them and why
+CREATE TABLE audit_log
+(
+	audit_id          Uuid NOT NULL,
+	actor             Text NOT NULL,
+	action            Text NOT NULL,
+	subject           Text NOT NULL,
+	details           Jsonb NOT NULL,
+	request_id        Text,
+	created_at        Timestamptz NOT NULL,
+
+	PRIMARY KEY (audit_id)
+);
+
+CREATE INDEX audit_log_subject_idx ON audit_log (subject, created_at DESC);
@@ -0,0 +1,9 @@
+-- Nonces of signed webhook alerts, kept as long as an alert with them could be accepted
+CREATE TABLE webhook_nonces
+(
+	strategy_id       Uuid NOT NULL,
+	nonce             Text NOT NULL,
+	received_at       Timestamptz NOT NULL,
+
+	PRIMARY KEY (strategy_id, nonce)
+);
@@ -0,0 +1,10 @@
+-- Notes of the pre-trade hooks on the orders of trade signals, see `hooks`
+CREATE TABLE order_annotations
+(
+	order_id          Uuid NOT NULL REFERENCES orders (order_id),
+	hook              Text NOT NULL,
+	note              Text NOT NULL,
+	created_at        Timestamptz NOT NULL
+);
+
+CREATE INDEX order_annotations_order_idx ON order_annotations (order_id, created_at);
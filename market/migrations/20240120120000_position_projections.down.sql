@@ -0,0 +1 @@
+DROP TABLE position_projections;
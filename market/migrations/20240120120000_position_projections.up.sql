@@ -0,0 +1,25 @@
+-- Positions of the strategies projected from their fills, see `projections`
+CREATE TABLE position_projections
+(
+	strategy_id       Uuid NOT NULL,
+	ticker            Text NOT NULL,
+	quantity          Decimal(20, 8) NOT NULL,
+	fills             BigInt NOT NULL,
+	last_filled_at    Timestamptz NOT NULL,
+	updated_at        Timestamptz NOT NULL,
+
+	PRIMARY KEY (strategy_id, ticker)
+);
+
+CREATE INDEX position_projections_ticker_idx ON position_projections (ticker);
+
+INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+SELECT
+	strategy_id,
+	ticker,
+	SUM(CASE WHEN side = 'sell' THEN -quantity ELSE quantity END),
+	COUNT(*),
+	MAX(filled_at),
+	NOW()
+FROM fills
+GROUP BY strategy_id, ticker;
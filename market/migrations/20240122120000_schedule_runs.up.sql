@@ -0,0 +1,11 @@
+-- Scheduled actions of the strategies run so far, claimed by the instance running them, see
+-- `schedules::claim_run`
+CREATE TABLE schedule_runs
+(
+	strategy_id  Uuid NOT NULL,
+	action       Text NOT NULL,
+	due_at       Timestamptz NOT NULL,
+	ran_at       Timestamptz NOT NULL,
+
+	PRIMARY KEY (strategy_id, action, due_at)
+);
@@ -0,0 +1,24 @@
+-- Activities of the broker accounts, kept beyond the retention of the brokers, see
+-- `activities::sync`
+CREATE TABLE account_activities
+(
+	broker         Text NOT NULL,
+	broker_account Text,
+	activity_id    Text NOT NULL,
+	kind           Text NOT NULL,
+	activity_type  Text NOT NULL,
+	symbol         Text,
+	quantity       Numeric,
+	price          Numeric,
+	amount         Numeric NOT NULL,
+	currency       Text NOT NULL,
+	occurred_at    Timestamptz NOT NULL,
+	details        Jsonb NOT NULL,
+	recorded_at    Timestamptz NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_account_activities_broker_activity_id
+	ON account_activities (broker, COALESCE(broker_account, ''), activity_id);
+
+CREATE INDEX idx_account_activities_broker_occurred_at
+	ON account_activities (broker, broker_account, occurred_at);
@@ -0,0 +1 @@
+ALTER TABLE dca_entries DROP COLUMN timeframe;
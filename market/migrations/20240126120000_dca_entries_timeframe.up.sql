@@ -0,0 +1,3 @@
+-- Timeframe of the signal of the entry, its chunks are held back by the cooldown of the strategy
+-- measured in bars of it
+ALTER TABLE dca_entries ADD COLUMN timeframe Text;
@@ -0,0 +1,2 @@
+ALTER TABLE schedule_runs DROP COLUMN error;
+ALTER TABLE schedule_runs DROP COLUMN status;
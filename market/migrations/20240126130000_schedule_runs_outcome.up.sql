@@ -0,0 +1,3 @@
+-- Outcome of the scheduled actions, failed runs are claimed again by their retry
+ALTER TABLE schedule_runs ADD COLUMN status Text NOT NULL DEFAULT 'succeeded';
+ALTER TABLE schedule_runs ADD COLUMN error Text;
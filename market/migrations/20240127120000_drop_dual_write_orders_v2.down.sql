@@ -0,0 +1,5 @@
+INSERT INTO
+  feature_flags (name, enabled, description, modified_at)
+VALUES
+  ('dual_write_orders_v2', false, 'Write orders to both the current and the v2 order storage', NOW())
+ON CONFLICT (name) DO NOTHING;
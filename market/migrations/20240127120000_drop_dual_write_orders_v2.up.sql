@@ -0,0 +1,2 @@
+-- There is no v2 order storage the flag could gate
+DELETE FROM feature_flags WHERE name = 'dual_write_orders_v2';
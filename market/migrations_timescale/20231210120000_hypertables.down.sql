@@ -0,0 +1,14 @@
+CREATE TABLE alerts_plain (LIKE alerts INCLUDING DEFAULTS);
+INSERT INTO alerts_plain SELECT * FROM alerts;
+DROP TABLE alerts;
+ALTER TABLE alerts_plain RENAME TO alerts;
+ALTER TABLE alerts ADD PRIMARY KEY (alert_id);
+CREATE INDEX idx_alerts_ticker ON alerts (ticker);
+CREATE INDEX idx_alerts_alert_type ON alerts (alert_type);
+
+CREATE TABLE portfolio_snapshots_plain (LIKE portfolio_snapshots INCLUDING DEFAULTS);
+INSERT INTO portfolio_snapshots_plain SELECT * FROM portfolio_snapshots;
+DROP TABLE portfolio_snapshots;
+ALTER TABLE portfolio_snapshots_plain RENAME TO portfolio_snapshots;
+ALTER TABLE portfolio_snapshots ADD PRIMARY KEY (snapshot_id);
+CREATE INDEX idx_portfolio_snapshots_broker_taken_at ON portfolio_snapshots (broker, taken_at);
@@ -0,0 +1,10 @@
+CREATE EXTENSION IF NOT EXISTS timescaledb;
+
+-- Unique indexes of hypertables have to include the time column
+ALTER TABLE alerts DROP CONSTRAINT alerts_pkey;
+ALTER TABLE alerts ADD PRIMARY KEY (alert_id, alert_fire_time);
+SELECT create_hypertable('alerts', 'alert_fire_time', migrate_data => true);
+
+ALTER TABLE portfolio_snapshots DROP CONSTRAINT portfolio_snapshots_pkey;
+ALTER TABLE portfolio_snapshots ADD PRIMARY KEY (snapshot_id, taken_at);
+SELECT create_hypertable('portfolio_snapshots', 'taken_at', migrate_data => true);
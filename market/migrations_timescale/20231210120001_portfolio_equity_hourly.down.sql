@@ -0,0 +1 @@
+DROP MATERIALIZED VIEW portfolio_equity_hourly;
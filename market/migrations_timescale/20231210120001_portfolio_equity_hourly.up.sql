@@ -0,0 +1,20 @@
+-- NOTE: migrations run in a transaction, continuous aggregates can only be created empty there.
+-- The refresh policy fills it in.
+CREATE MATERIALIZED VIEW portfolio_equity_hourly
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+	broker,
+	time_bucket(INTERVAL '1 hour', taken_at) AS bucket,
+	last(equity, taken_at) AS equity,
+	last(cash, taken_at) AS cash,
+	MAX(taken_at) AS taken_at
+FROM portfolio_snapshots
+GROUP BY broker, bucket
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy(
+	'portfolio_equity_hourly',
+	start_offset => NULL,
+	end_offset => INTERVAL '1 hour',
+	schedule_interval => INTERVAL '1 hour'
+);
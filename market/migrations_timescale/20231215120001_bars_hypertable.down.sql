@@ -0,0 +1,5 @@
+CREATE TABLE bars_plain (LIKE bars INCLUDING DEFAULTS);
+INSERT INTO bars_plain SELECT * FROM bars;
+DROP TABLE bars;
+ALTER TABLE bars_plain RENAME TO bars;
+ALTER TABLE bars ADD PRIMARY KEY (symbol, timeframe, bar_time);
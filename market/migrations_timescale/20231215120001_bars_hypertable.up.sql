@@ -0,0 +1,2 @@
+-- The primary key of bars already includes the time column
+SELECT create_hypertable('bars', 'bar_time', migrate_data => true);
@@ -0,0 +1,435 @@
+use apca::api::v2::account_activities::{
+    Activity as AlpacaActivity, ActivityReq, ActivityType, Direction, Side,
+};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, FromRow, PgPool, Postgres, QueryBuilder};
+use strum_macros::AsRefStr;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        error::ApiError,
+        objects::{Activity, Broker},
+    },
+    clients::{num_to_decimal, BrokerClient},
+};
+
+/// Largest page of activities Alpaca serves
+const PAGE_SIZE: usize = 100;
+
+/// Pages fetched at most by one sync, the next sync continues where it stopped
+const MAX_PAGES: usize = 50;
+
+/// Namespace of the ids of Tradier events, which have none themselves
+const TRADIER_EVENTS: Uuid = Uuid::from_u128(0x5f1c_2a7e_83d4_4b0e_9a61_0c3e_7d28_b4f5);
+
+/// What an activity did to the cash balance of the account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Fill,
+    Dividend,
+    Fee,
+    /// Deposits, withdrawals and journals between accounts
+    Transfer,
+    /// Interest and financing of positions held overnight
+    Interest,
+    Other,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ActivityQuery {
+    #[serde(default = "default_broker")]
+    pub broker: Broker,
+    /// Credential set of the broker, the global one when `None`
+    pub account: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Kind of the activities, all kinds when `None`
+    #[serde(rename = "type")]
+    pub kind: Option<ActivityKind>,
+}
+
+fn default_broker() -> Broker {
+    Broker::Alpaca
+}
+
+/// Activity of a broker account as stored in the `account_activities` table, in the same shape
+/// for every broker.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AccountActivity {
+    pub broker: String,
+    /// Credential set of the broker, the global one when `None`
+    pub broker_account: Option<String>,
+    /// Id at the broker, derived from the event itself for Tradier
+    pub activity_id: String,
+    /// See `ActivityKind`
+    pub kind: String,
+    /// Type of the activity at the broker, e.g. `DIV` or `DAILY_FINANCING`
+    pub activity_type: String,
+    pub symbol: Option<String>,
+    pub quantity: Option<Decimal>,
+    pub price: Option<Decimal>,
+    /// Change of the cash balance, negative for debits
+    pub amount: Decimal,
+    pub currency: String,
+    pub occurred_at: DateTime<Utc>,
+    /// Activity in the wire format of the broker
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub details: Json<Value>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Total amount of the activities of one kind and currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CashFlow {
+    /// See `ActivityKind`
+    pub kind: String,
+    pub currency: String,
+    pub activities: i64,
+    pub amount: Decimal,
+}
+
+/// Fields of an activity which differ between brokers.
+struct Normalized {
+    activity_id: String,
+    kind: ActivityKind,
+    activity_type: String,
+    symbol: Option<String>,
+    quantity: Option<Decimal>,
+    price: Option<Decimal>,
+    amount: Decimal,
+    occurred_at: DateTime<Utc>,
+}
+
+impl AccountActivity {
+    /// Activity of the broker in the shape it is stored in. `None` for activities without an id
+    /// or time.
+    pub fn normalize(
+        activity: &Activity,
+        broker: &Broker,
+        account: Option<&str>,
+        currency: &str,
+    ) -> Option<Self> {
+        let (normalized, details) = match activity {
+            Activity::AlpacaActivity(activity) => (
+                alpaca_activity(activity),
+                serde_json::to_value(activity).ok()?,
+            ),
+            Activity::OandaTransaction(transaction) => {
+                (oanda_transaction(transaction)?, transaction.clone())
+            }
+            Activity::TradierEvent(event) => (tradier_event(event)?, event.clone()),
+        };
+
+        Some(Self {
+            broker: broker.as_ref().to_owned(),
+            broker_account: account.map(str::to_owned),
+            activity_id: normalized.activity_id,
+            kind: normalized.kind.as_ref().to_owned(),
+            activity_type: normalized.activity_type,
+            symbol: normalized.symbol,
+            quantity: normalized.quantity,
+            price: normalized.price,
+            amount: normalized.amount,
+            currency: currency.to_owned(),
+            occurred_at: normalized.occurred_at,
+            details: Json(details),
+            recorded_at: Utc::now(),
+        })
+    }
+}
+
+fn alpaca_activity(activity: &AlpacaActivity) -> Normalized {
+    match activity {
+        AlpacaActivity::Trade(trade) => {
+            let quantity = num_to_decimal(&trade.quantity);
+            let price = num_to_decimal(&trade.price);
+            let amount = match trade.side {
+                Side::Buy => -(quantity * price),
+                Side::Sell | Side::ShortSell => quantity * price,
+            };
+            Normalized {
+                activity_id: trade.id.clone(),
+                kind: ActivityKind::Fill,
+                activity_type: "FILL".to_owned(),
+                symbol: Some(trade.symbol.clone()),
+                quantity: Some(quantity),
+                price: Some(price),
+                amount,
+                occurred_at: trade.transaction_time,
+            }
+        }
+        AlpacaActivity::NonTrade(non_trade) => {
+            let kind = match non_trade.type_ {
+                ActivityType::Fill => ActivityKind::Fill,
+                ActivityType::Dividend
+                | ActivityType::CapitalGainLongTerm
+                | ActivityType::CapitalGainShortTerm
+                | ActivityType::DividendAdjusted
+                | ActivityType::DividendAdjustedNraWithheld
+                | ActivityType::DividendReturnOfCapital
+                | ActivityType::DividendAdjustedTefraWithheld
+                | ActivityType::DividendTaxExtempt => ActivityKind::Dividend,
+                ActivityType::DividendFee
+                | ActivityType::Fee
+                | ActivityType::PassThruCharge
+                | ActivityType::PassThruRebate => ActivityKind::Fee,
+                ActivityType::CashDeposit
+                | ActivityType::CashWithdrawal
+                | ActivityType::AcatsInOutCash
+                | ActivityType::JournalEntry
+                | ActivityType::JournalEntryCash
+                | ActivityType::Transaction => ActivityKind::Transfer,
+                ActivityType::Interest
+                | ActivityType::InterestAdjustedNraWithheld
+                | ActivityType::InterestAdjustedTefraWithheld => ActivityKind::Interest,
+                _ => ActivityKind::Other,
+            };
+            let activity_type = serde_json::to_value(non_trade.type_)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_owned))
+                .unwrap_or_default();
+            Normalized {
+                activity_id: non_trade.id.clone(),
+                kind,
+                activity_type,
+                symbol: non_trade.symbol.clone(),
+                quantity: non_trade.quantity.as_ref().map(num_to_decimal),
+                price: non_trade.price.as_ref().map(num_to_decimal),
+                amount: num_to_decimal(&non_trade.net_amount),
+                occurred_at: non_trade.date,
+            }
+        }
+    }
+}
+
+/// Transactions of the v20 API. Transactions which don't change the balance, e.g. orders being
+/// created, are kept as `ActivityKind::Other` with an amount of zero.
+fn oanda_transaction(transaction: &Value) -> Option<Normalized> {
+    let field = |name: &str| transaction.get(name).and_then(decimal);
+    let activity_type = transaction["type"].as_str()?.to_owned();
+    let (kind, amount) = match activity_type.as_str() {
+        // NOTE: commissions are positive, but reduce the balance
+        "ORDER_FILL" => (
+            ActivityKind::Fill,
+            field("pl").unwrap_or_default() + field("financing").unwrap_or_default()
+                - field("commission").unwrap_or_default(),
+        ),
+        "DAILY_FINANCING" => (
+            ActivityKind::Interest,
+            field("financing").unwrap_or_default(),
+        ),
+        "TRANSFER_FUNDS" => (ActivityKind::Transfer, field("amount").unwrap_or_default()),
+        "DIVIDEND_ADJUSTMENT" => (
+            ActivityKind::Dividend,
+            field("dividendAdjustment").unwrap_or_default(),
+        ),
+        _ => (ActivityKind::Other, Decimal::ZERO),
+    };
+
+    Some(Normalized {
+        activity_id: transaction["id"].as_str()?.to_owned(),
+        kind,
+        activity_type,
+        symbol: transaction["instrument"].as_str().map(str::to_owned),
+        quantity: field("units"),
+        price: field("price"),
+        amount,
+        occurred_at: timestamp(&transaction["time"])?,
+    })
+}
+
+/// Events of the account history of Tradier, detailed in the field named after their type.
+fn tradier_event(event: &Value) -> Option<Normalized> {
+    let activity_type = event["type"].as_str()?.to_owned();
+    let kind = match activity_type.as_str() {
+        "trade" => ActivityKind::Fill,
+        "dividend" => ActivityKind::Dividend,
+        "fee" | "tax" => ActivityKind::Fee,
+        "ach" | "wire" | "journal" | "transfer" => ActivityKind::Transfer,
+        "interest" => ActivityKind::Interest,
+        _ => ActivityKind::Other,
+    };
+    let details = &event[activity_type.as_str()];
+
+    Some(Normalized {
+        // NOTE: identical events of the same day are stored once
+        activity_id: Uuid::new_v5(&TRADIER_EVENTS, event.to_string().as_bytes()).to_string(),
+        kind,
+        symbol: details["symbol"].as_str().map(str::to_owned),
+        quantity: decimal(&details["quantity"]),
+        price: decimal(&details["price"]),
+        amount: decimal(&event["amount"]).unwrap_or_default(),
+        occurred_at: timestamp(&event["date"])?,
+        activity_type,
+    })
+}
+
+/// Amounts sent as strings, like OANDA does, or as numbers.
+fn decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::String(amount) => amount.parse().ok(),
+        Value::Number(amount) => amount.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.as_str()?)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Store activities, activities stored already are left as they are. Returns the number of
+/// activities stored.
+pub async fn upsert(db: &PgPool, activities: &[AccountActivity]) -> Result<u64, sqlx::Error> {
+    if activities.is_empty() {
+        return Ok(0);
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        r#"
+        INSERT INTO account_activities (broker, broker_account, activity_id, kind, activity_type,
+            symbol, quantity, price, amount, currency, occurred_at, details, recorded_at)
+        "#,
+    );
+    query.push_values(activities, |mut row, activity| {
+        row.push_bind(&activity.broker)
+            .push_bind(&activity.broker_account)
+            .push_bind(&activity.activity_id)
+            .push_bind(&activity.kind)
+            .push_bind(&activity.activity_type)
+            .push_bind(&activity.symbol)
+            .push_bind(activity.quantity)
+            .push_bind(activity.price)
+            .push_bind(activity.amount)
+            .push_bind(&activity.currency)
+            .push_bind(activity.occurred_at)
+            .push_bind(&activity.details)
+            .push_bind(activity.recorded_at);
+    });
+    query.push(" ON CONFLICT (broker, COALESCE(broker_account, ''), activity_id) DO NOTHING");
+
+    Ok(query.build().execute(db).await?.rows_affected())
+}
+
+/// Time of the latest stored activity of the credential set of the broker.
+async fn latest(
+    db: &PgPool,
+    broker: &Broker,
+    account: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT MAX(occurred_at) FROM account_activities
+        WHERE broker = $1 AND broker_account IS NOT DISTINCT FROM $2
+        "#,
+    )
+    .bind(broker.as_ref())
+    .bind(account)
+    .fetch_one(db)
+    .await
+}
+
+/// Fetch the activities of the broker since the latest stored one and store them. Returns the
+/// number of new activities.
+pub async fn sync<C>(
+    db: &PgPool,
+    client: &C,
+    broker: &Broker,
+    account: Option<&str>,
+    currency: &str,
+) -> Result<u64, ApiError>
+where
+    C: BrokerClient<ActivitiesRequest = ActivityReq>,
+{
+    // NOTE: activities of the same time as the latest ones are fetched again, e.g. dividends of
+    // the same date split over two pages, and only stored once
+    let since = |time: DateTime<Utc>| time - Duration::seconds(1);
+    let mut after = latest(db, broker, account).await?.map(since);
+    let mut stored = 0;
+    for _ in 0..MAX_PAGES {
+        let page = client
+            .get_activities(ActivityReq {
+                direction: Direction::Ascending,
+                after,
+                page_size: Some(PAGE_SIZE),
+                ..Default::default()
+            })
+            .await?;
+        let activities: Vec<AccountActivity> = page
+            .iter()
+            .filter_map(|activity| AccountActivity::normalize(activity, broker, account, currency))
+            .collect();
+        stored += upsert(db, &activities).await?;
+
+        let next = activities
+            .iter()
+            .map(|activity| activity.occurred_at)
+            .max()
+            .map(since);
+        if page.len() < PAGE_SIZE || next <= after {
+            break;
+        }
+        after = next;
+    }
+
+    Ok(stored)
+}
+
+/// Stored activities matching the query, oldest first.
+pub async fn fetch(
+    db: &PgPool,
+    query: &ActivityQuery,
+) -> Result<Vec<AccountActivity>, sqlx::Error> {
+    sqlx::query_as::<_, AccountActivity>(
+        r#"
+        SELECT * FROM account_activities
+        WHERE broker = $1 AND broker_account IS NOT DISTINCT FROM $2
+            AND ($3::timestamptz IS NULL OR occurred_at >= $3)
+            AND ($4::timestamptz IS NULL OR occurred_at <= $4)
+            AND ($5::text IS NULL OR kind = $5)
+        ORDER BY occurred_at, activity_id
+        "#,
+    )
+    .bind(query.broker.as_ref())
+    .bind(query.account.as_deref())
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.kind.as_ref().map(AsRef::<str>::as_ref))
+    .fetch_all(db)
+    .await
+}
+
+/// Totals of the stored activities matching the query by kind and currency.
+pub async fn cash_flow(db: &PgPool, query: &ActivityQuery) -> Result<Vec<CashFlow>, sqlx::Error> {
+    sqlx::query_as::<_, CashFlow>(
+        r#"
+        SELECT kind, currency, COUNT(*) AS activities, SUM(amount) AS amount
+        FROM account_activities
+        WHERE broker = $1 AND broker_account IS NOT DISTINCT FROM $2
+            AND ($3::timestamptz IS NULL OR occurred_at >= $3)
+            AND ($4::timestamptz IS NULL OR occurred_at <= $4)
+            AND ($5::text IS NULL OR kind = $5)
+        GROUP BY kind, currency
+        ORDER BY kind, currency
+        "#,
+    )
+    .bind(query.broker.as_ref())
+    .bind(query.account.as_deref())
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.kind.as_ref().map(AsRef::<str>::as_ref))
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,116 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{api::objects::Broker, risk, strategy::Strategy};
+
+/// State of a configured strategy. Strategies enabled by the config can be disabled at runtime,
+/// signals of disabled strategies are rejected.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StrategyStatus {
+    pub id: Uuid,
+    pub name: String,
+    pub broker: Broker,
+    pub enabled: bool,
+    /// Why the strategy was disabled at runtime
+    pub disabled_reason: Option<String>,
+    /// Daily loss limit reached today
+    pub halted: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StrategySwitch {
+    pub enabled: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Body of a request disabling a strategy.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StrategyDisable {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+pub async fn strategy_statuses(
+    db: &PgPool,
+    strategies: &[Strategy],
+    day: NaiveDate,
+) -> Result<Vec<StrategyStatus>, sqlx::Error> {
+    let mut statuses = Vec::with_capacity(strategies.len());
+    for strategy in strategies {
+        statuses.push(strategy_status(db, strategy, day).await?);
+    }
+
+    Ok(statuses)
+}
+
+pub async fn strategy_status(
+    db: &PgPool,
+    strategy: &Strategy,
+    day: NaiveDate,
+) -> Result<StrategyStatus, sqlx::Error> {
+    let disabled_reason: Option<Option<String>> =
+        sqlx::query_scalar("SELECT reason FROM strategy_switches WHERE strategy_id = $1")
+            .bind(strategy.id)
+            .fetch_optional(db)
+            .await?;
+
+    Ok(StrategyStatus {
+        id: strategy.id,
+        name: strategy.name.clone(),
+        broker: strategy.broker.clone(),
+        enabled: strategy.enabled && disabled_reason.is_none(),
+        disabled_reason: disabled_reason.flatten(),
+        halted: risk::is_strategy_halted(db, strategy.id, day).await?,
+    })
+}
+
+/// Disable a strategy until it's enabled again, or lift a runtime disable. Strategies disabled
+/// by the config stay disabled.
+pub async fn switch_strategy(
+    db: &PgPool,
+    strategy: &Strategy,
+    switch: &StrategySwitch,
+) -> Result<StrategyStatus, sqlx::Error> {
+    if switch.enabled {
+        sqlx::query("DELETE FROM strategy_switches WHERE strategy_id = $1")
+            .bind(strategy.id)
+            .execute(db)
+            .await?;
+    } else {
+        sqlx::query(
+            r#"
+            INSERT INTO strategy_switches (strategy_id, reason, disabled_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (strategy_id) DO UPDATE SET reason = EXCLUDED.reason
+            "#,
+        )
+        .bind(strategy.id)
+        .bind(&switch.reason)
+        .execute(db)
+        .await?;
+    }
+
+    tracing::warn!(
+        "Strategy {} {}",
+        strategy.name,
+        if switch.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    strategy_status(db, strategy, Utc::now().date_naive()).await
+}
+
+pub async fn is_strategy_disabled(db: &PgPool, strategy_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM strategy_switches WHERE strategy_id = $1)")
+        .bind(strategy_id)
+        .fetch_one(db)
+        .await
+}
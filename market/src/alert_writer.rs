@@ -0,0 +1,307 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool, Postgres, QueryBuilder};
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    time::{timeout, timeout_at, Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        alert::WebhookAlertData,
+        error::ApiError,
+        pagination::{ListSource, Listed},
+    },
+    bars::{self, normalize_timeframe, Bar},
+    supervisor::TaskSupervisor,
+};
+
+/// Rows of one insert at most, each binds 16 parameters of the 65535 postgres allows.
+pub const MAX_BATCH_SIZE: usize = 4095;
+
+/// Buffering of received alerts before they're written to the `alerts` table, and their bars to
+/// the `bars` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WriterConfig {
+    /// Alerts waiting to be written at most, webhooks wait for room once it's full
+    #[serde(default = "default_buffer")]
+    pub buffer: usize,
+    /// Alerts written by one insert at most
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Milliseconds a batch collects alerts before it's written, also when not full
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: u64,
+    /// Milliseconds a webhook waits for room in a full buffer before it's answered with
+    /// `503 Service Unavailable`
+    #[serde(default = "default_enqueue_timeout")]
+    pub enqueue_timeout: u64,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        Self {
+            buffer: default_buffer(),
+            batch_size: default_batch_size(),
+            flush_interval: default_flush_interval(),
+            enqueue_timeout: default_enqueue_timeout(),
+        }
+    }
+}
+
+fn default_buffer() -> usize {
+    10_000
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval() -> u64 {
+    200
+}
+
+fn default_enqueue_timeout() -> u64 {
+    1000
+}
+
+/// Alert as stored in the `alerts` table.
+#[derive(Debug, Clone)]
+struct AlertRow {
+    alert_id: Uuid,
+    ticker: String,
+    timeframe: String,
+    exchange: String,
+    alert_type: String,
+    bar_time: DateTime<Utc>,
+    bar_open: Decimal,
+    bar_high: Decimal,
+    bar_low: Decimal,
+    bar_close: Decimal,
+    bar_volume: Decimal,
+    alert_fire_time: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+    /// Symbol of the ticker at the broker, bars are stored under it
+    symbol: String,
+    strategy_id: Uuid,
+    /// Alert as received, see `replay`
+    payload: WebhookAlertData,
+}
+
+impl AlertRow {
+    fn new(alert: &WebhookAlertData, symbol: &str) -> Self {
+        Self {
+            alert_id: uuid7::uuid7().into(),
+            ticker: alert.ticker.clone(),
+            timeframe: alert.timeframe.clone(),
+            exchange: alert.exchange.clone(),
+            alert_type: alert.signal_type.as_ref().to_owned(),
+            bar_time: alert.bar_data.time,
+            bar_open: *alert.bar_data.open.as_ref(),
+            bar_high: *alert.bar_data.high.as_ref(),
+            bar_low: *alert.bar_data.low.as_ref(),
+            bar_close: *alert.bar_data.close.as_ref(),
+            bar_volume: alert.bar_data.volume,
+            alert_fire_time: alert.time,
+            received_at: Utc::now(),
+            symbol: symbol.to_owned(),
+            strategy_id: alert.strategy_id,
+            payload: alert.clone(),
+        }
+    }
+}
+
+/// Alert of the `alerts` table as listed by `GET /alerts`, without its payload.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AlertRecord {
+    pub alert_id: Uuid,
+    /// Strategy of the alert, `None` for alerts stored before it was kept
+    pub strategy_id: Option<Uuid>,
+    pub ticker: String,
+    pub timeframe: String,
+    pub exchange: String,
+    pub alert_type: String,
+    pub bar_time: DateTime<Utc>,
+    pub bar_open: Decimal,
+    pub bar_high: Decimal,
+    pub bar_low: Decimal,
+    pub bar_close: Decimal,
+    pub bar_volume: Decimal,
+    pub alert_fire_time: DateTime<Utc>,
+    /// Time the alert was received
+    pub created_at: DateTime<Utc>,
+}
+
+impl Listed for AlertRecord {
+    const SOURCE: ListSource = ListSource {
+        table: "alerts",
+        id: "alert_id",
+        time: "created_at",
+        sort_fields: &["created_at", "alert_fire_time", "bar_time"],
+    };
+
+    fn id(&self) -> Uuid {
+        self.alert_id
+    }
+
+    fn sort_key(&self, field: &str) -> DateTime<Utc> {
+        match field {
+            "alert_fire_time" => self.alert_fire_time,
+            "bar_time" => self.bar_time,
+            _ => self.created_at,
+        }
+    }
+}
+
+impl From<&AlertRow> for Bar {
+    fn from(alert: &AlertRow) -> Self {
+        Self {
+            symbol: alert.symbol.clone(),
+            timeframe: normalize_timeframe(&alert.timeframe),
+            time: alert.bar_time,
+            open: alert.bar_open,
+            high: alert.bar_high,
+            low: alert.bar_low,
+            close: alert.bar_close,
+            volume: alert.bar_volume,
+            updated_at: alert.received_at,
+        }
+    }
+}
+
+/// Hands received alerts to a background task writing them in batches, so webhooks don't wait
+/// for an insert each.
+pub struct AlertWriter {
+    sender: Sender<AlertRow>,
+    enqueue_timeout: Duration,
+}
+
+impl AlertWriter {
+    /// Start the background task writing to `db` on `tasks`. It stops once the writer is
+    /// dropped, after writing the alerts buffered until then.
+    pub fn spawn(db: PgPool, config: &WriterConfig, tasks: &TaskSupervisor) -> Self {
+        let (sender, receiver) = mpsc::channel(config.buffer);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let batch_size = config.batch_size;
+        let flush_interval = Duration::from_millis(config.flush_interval);
+        tasks.spawn("alert_writer", move || {
+            let db = db.clone();
+            let receiver = Arc::clone(&receiver);
+            async move {
+                let mut receiver = receiver.lock().await;
+                run(db, &mut receiver, batch_size, flush_interval).await
+            }
+        });
+
+        Self {
+            sender,
+            enqueue_timeout: Duration::from_millis(config.enqueue_timeout),
+        }
+    }
+
+    /// Buffer an alert to be written, its bar under the broker `symbol` of its ticker. Waits while
+    /// the buffer is full and gives up with `ServiceUnavailable` after the enqueue timeout.
+    pub async fn write(&self, alert: &WebhookAlertData, symbol: &str) -> Result<(), ApiError> {
+        let permit = match timeout(self.enqueue_timeout, self.sender.reserve()).await {
+            Ok(Ok(permit)) => permit,
+            Ok(Err(_)) => {
+                tracing::error!("Alert writer stopped, alert of {} not stored", alert.ticker);
+                return Err(ApiError::ServiceUnavailable);
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Alert buffer full for {}ms, alert of {} rejected",
+                    self.enqueue_timeout.as_millis(),
+                    alert.ticker
+                );
+                return Err(ApiError::ServiceUnavailable);
+            }
+        };
+        permit.send(AlertRow::new(alert, symbol));
+        Ok(())
+    }
+}
+
+/// Collect alerts until the batch is full or the flush interval passed since its first alert,
+/// then write the batch. Failed batches are logged and dropped, alerts are processed regardless.
+async fn run(
+    db: PgPool,
+    receiver: &mut Receiver<AlertRow>,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    while let Some(alert) = receiver.recv().await {
+        batch.push(alert);
+
+        let deadline = Instant::now() + flush_interval;
+        while batch.len() < batch_size {
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(alert)) => batch.push(alert),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        if let Err(err) = insert(&db, &batch).await {
+            tracing::error!("Failed to store {} alerts, error: {:?}", batch.len(), err);
+        }
+        batch.clear();
+    }
+}
+
+async fn insert(db: &PgPool, batch: &[AlertRow]) -> Result<(), sqlx::Error> {
+    let mut query = QueryBuilder::<Postgres>::new(
+        r#"
+        INSERT INTO alerts (
+            alert_id,
+            ticker,
+            timeframe,
+            exchange,
+            alert_type,
+            bar_time,
+            bar_open,
+            bar_high,
+            bar_low,
+            bar_close,
+            bar_volume,
+            alert_fire_time,
+            created_at,
+            modified_at,
+            strategy_id,
+            payload
+        )
+        "#,
+    );
+    query.push_values(batch, |mut row, alert| {
+        row.push_bind(alert.alert_id)
+            .push_bind(&alert.ticker)
+            .push_bind(&alert.timeframe)
+            .push_bind(&alert.exchange)
+            .push_bind(&alert.alert_type)
+            .push_bind(alert.bar_time)
+            .push_bind(alert.bar_open)
+            .push_bind(alert.bar_high)
+            .push_bind(alert.bar_low)
+            .push_bind(alert.bar_close)
+            .push_bind(alert.bar_volume)
+            .push_bind(alert.alert_fire_time)
+            .push_bind(alert.received_at)
+            .push_bind(alert.received_at)
+            .push_bind(alert.strategy_id)
+            .push_bind(Json(&alert.payload));
+    });
+    let mut tx = db.begin().await?;
+    query.build().execute(&mut *tx).await?;
+
+    let batch: Vec<Bar> = batch.iter().map(Bar::from).collect();
+    bars::upsert(&mut tx, &batch).await?;
+
+    tx.commit().await
+}
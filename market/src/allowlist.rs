@@ -0,0 +1,84 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, RwLock},
+};
+
+use axum::http::HeaderMap;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::app_config::{AppConfig, Webhook};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Networks webhook alerts are accepted from. The list can be replaced at runtime, see
+/// `reload_on_hangup`.
+pub struct IpAllowlist {
+    config: RwLock<Webhook>,
+}
+
+impl IpAllowlist {
+    pub fn new(config: Webhook) -> Self {
+        Self {
+            config: RwLock::new(config),
+        }
+    }
+
+    pub fn reload(&self, config: Webhook) {
+        *self.config.write().unwrap_or_else(|err| err.into_inner()) = config;
+    }
+
+    /// Address of the client which sent the request. With `trust_forwarded_for` it's the last
+    /// `X-Forwarded-For` entry, the one appended by the proxy, as the earlier ones can be forged.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        let trust_forwarded_for = self
+            .config
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .trust_forwarded_for;
+
+        if trust_forwarded_for {
+            if let Some(forwarded) = headers
+                .get(FORWARDED_FOR_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+            {
+                return forwarded.trim().parse().ok();
+            }
+        }
+
+        peer.map(|peer| peer.ip())
+    }
+
+    pub fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        let config = self.config.read().unwrap_or_else(|err| err.into_inner());
+        if config.allowed_ips.is_empty() {
+            return true;
+        }
+
+        ip.is_some_and(|ip| config.allowed_ips.iter().any(|net| net.contains(&ip)))
+    }
+}
+
+/// Reload the allowlist from the config files every time the process receives `SIGHUP`.
+pub async fn reload_on_hangup(allowlist: Arc<IpAllowlist>) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            tracing::error!("Failed to listen for SIGHUP, error: {:?}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match AppConfig::build() {
+            Ok(config) => {
+                tracing::info!(
+                    "Webhook allowlist reloaded, {} networks allowed",
+                    config.webhook.allowed_ips.len()
+                );
+                allowlist.reload(config.webhook);
+            }
+            Err(err) => tracing::error!("Failed to reload webhook allowlist, error: {:?}", err),
+        }
+    }
+}
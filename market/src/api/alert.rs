@@ -1,11 +1,23 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Serialize,
+};
+use sha2::{Digest, Sha256};
 use strum_macros::{AsRefStr, EnumString};
 use uuid::Uuid;
 
 use super::{error::ApiError, price::Price};
-use crate::{app_config::AppConfig, clients::BrokerClient, strategy::Strategy};
+use crate::{
+    app_config::{AppConfig, Webhook},
+    clients::BrokerClient,
+    options::OrderLeg,
+    order::TimeInForce,
+    strategy::Strategy,
+};
 
 // NOTE: Webhook body example:
 // {
@@ -26,6 +38,9 @@ use crate::{app_config::AppConfig, clients::BrokerClient, strategy::Strategy};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct WebhookAlertData {
+    /// Explicit idempotency key of the alert, a fingerprint of its content is used when missing
+    #[serde(default)]
+    pub alert_id: Option<String>,
     pub strategy_id: Uuid,
     pub ticker: String,
     pub timeframe: String,
@@ -34,6 +49,209 @@ pub struct WebhookAlertData {
     pub trail_stop_price: Option<Decimal>,
     pub bar_data: BarData,
     pub time: DateTime<Utc>,
+    /// Quantity to trade instead of the one sized for the strategy, fractional for fractionable
+    /// assets
+    #[serde(default)]
+    pub quantity: Option<Decimal>,
+    /// Dollar amount to trade instead of a quantity, only for fractionable assets
+    #[serde(default)]
+    pub notional: Option<Decimal>,
+    /// Time in force overriding the one of the strategy
+    #[serde(default)]
+    pub time_in_force: Option<TimeInForce>,
+    /// Submit a limit order at this price instead of a market order
+    #[serde(default)]
+    pub limit_price: Option<Decimal>,
+    /// Whether the order may execute in extended hours, overriding the strategy
+    #[serde(default)]
+    pub extended_hours: Option<bool>,
+    /// Option contracts on the ticker to trade instead of the ticker itself, more than one for
+    /// multi-leg orders
+    #[serde(default)]
+    pub legs: Vec<OrderLeg>,
+}
+
+impl WebhookAlertData {
+    /// Key identifying retries of the same alert. The fingerprint leaves out the alert time, which
+    /// differs between retries.
+    pub fn idempotency_key(&self) -> String {
+        if let Some(alert_id) = &self.alert_id {
+            return format!("{}:{}", self.strategy_id, alert_id);
+        }
+
+        let fingerprint = Sha256::new()
+            .chain_update(self.ticker.as_bytes())
+            .chain_update(self.timeframe.as_bytes())
+            .chain_update(self.exchange.as_bytes())
+            .chain_update(self.signal_type.as_ref())
+            .chain_update(self.signal_type.trail_stop_price().to_string())
+            .chain_update(self.bar_data.time.to_rfc3339())
+            .finalize();
+        format!("{}:{}", self.strategy_id, hex::encode(fingerprint))
+    }
+}
+
+/// Rule of `WebhookAlertData::validate` an alert breaks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Tickers are uppercase alphanumeric, optionally with a `.`, `-` or a single `/` separating the
+/// quote currency of crypto pairs.
+fn is_valid_ticker(ticker: &str) -> bool {
+    let (base, quote) = match ticker.split_once('/') {
+        Some((base, quote)) => (base, Some(quote)),
+        None => (ticker, None),
+    };
+    let is_valid = |part: &str| {
+        !part.is_empty()
+            && part.len() <= 12
+            && part
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '.' || c == '-')
+    };
+
+    is_valid(base) && quote.is_none_or(is_valid)
+}
+
+impl WebhookAlertData {
+    /// Check the alert beyond what deserialization does. All violations are reported, not only
+    /// the first one.
+    pub fn validate(&self, webhook: &Webhook, now: DateTime<Utc>) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+
+        if !is_valid_ticker(&self.ticker) {
+            violations.push(Violation::new(
+                "ticker",
+                format!("{} is not a valid symbol", self.ticker),
+            ));
+        }
+
+        if self.signal_type.trail_stop_price() <= Decimal::ZERO
+            || self
+                .trail_stop_price
+                .is_some_and(|price| price <= Decimal::ZERO)
+        {
+            violations.push(Violation::new("trail_stop_price", "must be positive"));
+        }
+
+        for (field, amount) in [
+            ("quantity", self.quantity),
+            ("notional", self.notional),
+            ("limit_price", self.limit_price),
+        ] {
+            if amount.is_some_and(|amount| amount <= Decimal::ZERO) {
+                violations.push(Violation::new(field, "must be positive"));
+            }
+        }
+        if self.quantity.is_some() && self.notional.is_some() {
+            violations.push(Violation::new(
+                "notional",
+                "must not be set together with quantity",
+            ));
+        }
+
+        if self.extended_hours == Some(true) {
+            if self.limit_price.is_none() {
+                violations.push(Violation::new(
+                    "limit_price",
+                    "is required for extended hours orders",
+                ));
+            }
+            if self
+                .time_in_force
+                .is_some_and(|time_in_force| time_in_force != TimeInForce::Day)
+            {
+                violations.push(Violation::new(
+                    "time_in_force",
+                    "must be day for extended hours orders",
+                ));
+            }
+        }
+
+        for leg in &self.legs {
+            if leg.contract.underlying != self.ticker {
+                violations.push(Violation::new(
+                    "legs",
+                    format!("{} is not an option on {}", leg.contract, self.ticker),
+                ));
+            }
+            if leg.ratio == 0 {
+                violations.push(Violation::new("legs", "ratio must be positive"));
+            }
+        }
+
+        let bar = &self.bar_data;
+        let (open, high, low, close) = (
+            *bar.open.as_ref(),
+            *bar.high.as_ref(),
+            *bar.low.as_ref(),
+            *bar.close.as_ref(),
+        );
+        for (field, price) in [
+            ("bar_data.open", open),
+            ("bar_data.high", high),
+            ("bar_data.low", low),
+            ("bar_data.close", close),
+        ] {
+            if price <= Decimal::ZERO {
+                violations.push(Violation::new(field, "must be positive"));
+            }
+        }
+        if bar.volume < Decimal::ZERO {
+            violations.push(Violation::new("bar_data.volume", "must not be negative"));
+        }
+        if high < open.max(close).max(low) {
+            violations.push(Violation::new(
+                "bar_data.high",
+                "must not be below open, close or low",
+            ));
+        }
+        if low > open.min(close) {
+            violations.push(Violation::new(
+                "bar_data.low",
+                "must not be above open or close",
+            ));
+        }
+
+        let max_age = chrono::Duration::seconds(webhook.max_alert_age as i64);
+        let max_skew = chrono::Duration::seconds(webhook.max_clock_skew as i64);
+        if self.time < now - max_age {
+            violations.push(Violation::new(
+                "time",
+                format!("is more than {}s in the past", webhook.max_alert_age),
+            ));
+        }
+        if self.time > now + max_skew {
+            violations.push(Violation::new(
+                "time",
+                format!("is more than {}s in the future", webhook.max_clock_skew),
+            ));
+        }
+        if bar.time > self.time + max_skew {
+            violations.push(Violation::new(
+                "bar_data.time",
+                "must not be after the alert time",
+            ));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, AsRefStr)]
@@ -47,6 +265,14 @@ pub enum SignalType {
     StopLossUpdate(TrailStopPrice),
 }
 
+impl SignalType {
+    pub fn trail_stop_price(&self) -> Decimal {
+        match self {
+            Self::OpenLong(price) | Self::OpenShort(price) | Self::StopLossUpdate(price) => price.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct TrailStopPrice(pub Decimal);
 
@@ -55,7 +281,7 @@ impl<'de> Deserialize<'de> for TrailStopPrice {
     where
         D: serde::Deserializer<'de>,
     {
-        let decimal = Decimal::deserialize(deserializer)?;
+        let decimal = <Decimal as Deserialize>::deserialize(deserializer)?;
         Ok(TrailStopPrice(decimal))
     }
 }
@@ -65,24 +291,55 @@ impl<'de> Deserialize<'de> for SignalType {
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        struct SignalTypeWrapper {
-            #[serde(rename = "signal_type")]
-            signal_type: String,
-            trail_stop_price: TrailStopPrice,
-        }
+        const VARIANTS: &[&str] = &["open_long", "open_short", "stop_loss_update"];
 
-        let value = SignalTypeWrapper::deserialize(deserializer)?;
+        struct SignalTypeVisitor;
 
-        match value.signal_type.as_str() {
-            "open_long" => Ok(SignalType::OpenLong(value.trail_stop_price)),
-            "open_short" => Ok(SignalType::OpenShort(value.trail_stop_price)),
-            "stop_loss_update" => Ok(SignalType::StopLossUpdate(value.trail_stop_price)),
-            _ => Err(serde::de::Error::unknown_variant(
-                &value.signal_type,
-                &["open_long", "open_short", "stop_loss_update"],
-            )),
+        impl<'de> Visitor<'de> for SignalTypeVisitor {
+            type Value = SignalType;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a signal type with its trail stop price")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut signal_type: Option<String> = None;
+                let mut trail_stop_price: Option<TrailStopPrice> = None;
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "signal_type" => signal_type = Some(map.next_value()?),
+                        "trail_stop_price" => trail_stop_price = Some(map.next_value()?),
+                        // NOTE: signal types are serialized as `{"open_long": "95"}`, accepted so
+                        // stored alerts can be read back
+                        variant if VARIANTS.contains(&variant) => {
+                            trail_stop_price = Some(map.next_value()?);
+                            signal_type = Some(key);
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                let signal_type =
+                    signal_type.ok_or_else(|| de::Error::missing_field("signal_type"))?;
+                let trail_stop_price =
+                    trail_stop_price.ok_or_else(|| de::Error::missing_field("trail_stop_price"))?;
+
+                match signal_type.as_str() {
+                    "open_long" => Ok(SignalType::OpenLong(trail_stop_price)),
+                    "open_short" => Ok(SignalType::OpenShort(trail_stop_price)),
+                    "stop_loss_update" => Ok(SignalType::StopLossUpdate(trail_stop_price)),
+                    _ => Err(de::Error::unknown_variant(&signal_type, VARIANTS)),
+                }
+            }
         }
+
+        deserializer.deserialize_map(SignalTypeVisitor)
     }
 }
 
@@ -5,16 +5,89 @@ use axum::{
     http::{Request, StatusCode},
     response::{IntoResponse, Json, Response},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 use tracing::error;
 
-use crate::clients::BrokerClientError;
+use super::alert::Violation;
+use crate::{
+    clients::BrokerClientError, core::TradeError, dashboard::DashboardError,
+    signal_source::SourceError,
+};
 
 pub const INTERNAL_SERVER_ERROR: &str = "Internal server error occurred...";
 pub const PAYLOAD_TOO_LARGE: &str = "Request payload too large...";
 pub const DATABASE_UNAVAILABLE: &str = "Database is unavailable...";
 
+/// Machine readable kind of an error response. Codes are stable, clients branch on them rather
+/// than on statuses or messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    NotFound,
+    ConstraintViolation,
+    IoError,
+    PayloadTooLarge,
+    Unauthorized,
+    Forbidden,
+    RateLimited,
+    InternalError,
+    ServiceUnavailable,
+    InvalidJson,
+    BrokerError,
+    ValidationFailed,
+}
+
+/// Field of the request an error response is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    /// Path of the field, e.g. `bar_data.high`
+    pub field: String,
+    pub message: String,
+}
+
+impl From<&Violation> for FieldError {
+    fn from(violation: &Violation) -> Self {
+        Self {
+            field: violation.field.to_owned(),
+            message: violation.message.clone(),
+        }
+    }
+}
+
+/// JSON body of every error response:
+///
+/// ```json
+/// {
+///   "code": "validation_failed",
+///   "message": "Payload validation failed",
+///   "request_id": "6f1c2a4e-…",
+///   "details": [{ "field": "ticker", "message": "must not be empty" }]
+/// }
+/// ```
+///
+/// `request_id` is the one of the `x-request-id` header, `details` is left out unless the error
+/// is about particular fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+impl IntoResponse for ErrorBody {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, ThisError)]
 pub enum ConstraintError {
     Unknown(String),
@@ -60,6 +133,18 @@ pub enum ApiError {
     #[error("{0}")]
     Unauthorized(String), // Added Unauthorized variant
 
+    /// Forbidden error.
+    ///
+    /// HTTP status code 403
+    #[error("{0}")]
+    Forbidden(String),
+
+    /// Too many requests error.
+    ///
+    /// HTTP status code 429
+    #[error("{0}")]
+    TooManyRequests(String),
+
     /// Failed to deserialize json.
     ///
     /// HTTP status code 422
@@ -89,11 +174,24 @@ pub enum ApiError {
 
     #[error(transparent)]
     TradingClientError(#[from] BrokerClientError),
+
+    /// Payload breaking validation rules.
+    ///
+    /// HTTP status code 422
+    #[error("Payload validation failed")]
+    ValidationError(Vec<Violation>),
 }
 
+/// Responds with the status of the error and its `ErrorBody`. The body is also attached as an
+/// extension of the response, so the request id middleware can add the id of the request.
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
+        let code = self.code();
+        let details = match &self {
+            Self::ValidationError(violations) => violations.iter().map(FieldError::from).collect(),
+            _ => Vec::new(),
+        };
+        let (status, message) = match self {
             Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             Self::IOError(msg) => (StatusCode::BAD_REQUEST, msg),
@@ -111,18 +209,25 @@ impl IntoResponse for ApiError {
             ),
             Self::ConstraintError(err) => (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()),
             Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            Self::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
 
             // Apca crate doesn't allow to get status code from it's response and deserialize error
             // message properly. We get error message as debug string of entire result and
             // hardcoded status code. Real status code should be shown in debug message.
             Self::TradingClientError(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+            err @ Self::ValidationError(_) => (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()),
         };
 
-        let body = Json(serde_json::json!({
-            "Error": error_message,
-        }));
-
-        (status, body).into_response()
+        let body = ErrorBody {
+            code,
+            message,
+            request_id: None,
+            details,
+        };
+        let mut response = (status, body.clone()).into_response();
+        response.extensions_mut().insert(body);
+        response
     }
 }
 
@@ -135,6 +240,8 @@ impl From<StatusCode> for ApiError {
             StatusCode::INTERNAL_SERVER_ERROR => Self::InternalServerError,
             StatusCode::SERVICE_UNAVAILABLE => Self::ServiceUnavailable,
             StatusCode::UNAUTHORIZED => Self::Unauthorized("Unauthorized".to_owned()),
+            StatusCode::FORBIDDEN => Self::Forbidden("Forbidden".to_owned()),
+            StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests("Too many requests".to_owned()),
             _ => Self::InternalServerError,
         }
     }
@@ -149,15 +256,36 @@ impl ApiError {
             | Self::IOError(_)
             | Self::BadRequest(_)
             | Self::NotFound(_) => StatusCode::BAD_REQUEST,
-            Self::ConstraintError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::ConstraintError(_) | Self::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
             Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::TradingClientError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::BadRequest(_) => ErrorCode::BadRequest,
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::ConstraintError(_) => ErrorCode::ConstraintViolation,
+            Self::IOError(_) => ErrorCode::IoError,
+            Self::PayloadTooLarge => ErrorCode::PayloadTooLarge,
+            Self::Unauthorized(_) => ErrorCode::Unauthorized,
+            Self::Forbidden(_) => ErrorCode::Forbidden,
+            Self::TooManyRequests(_) => ErrorCode::RateLimited,
+            Self::InternalServerError => ErrorCode::InternalError,
+            Self::ServiceUnavailable => ErrorCode::ServiceUnavailable,
+            Self::JsonExtractorRejection(_) => ErrorCode::InvalidJson,
+            Self::TradingClientError(_) => ErrorCode::BrokerError,
+            Self::ValidationError(_) => ErrorCode::ValidationFailed,
+        }
+    }
+
     pub fn internal_error<E>(err: E) -> Self
     where
         E: Display,
@@ -190,6 +318,38 @@ impl TryFrom<sqlx::Error> for ConstraintError {
     }
 }
 
+impl From<TradeError> for ApiError {
+    fn from(err: TradeError) -> Self {
+        match err {
+            TradeError::BrokerClientError(err) => Self::TradingClientError(err),
+            TradeError::DatabaseError(err) => err.into(),
+            err @ (TradeError::UnknownBroker(_) | TradeError::MaxRetriesReached(..)) => {
+                error!("Trade error: {}", err);
+                Self::InternalServerError
+            }
+            err => Self::BadRequest(err.to_string()),
+        }
+    }
+}
+
+impl From<SourceError> for ApiError {
+    fn from(err: SourceError) -> Self {
+        match err {
+            SourceError::Unauthorized(message) => Self::Unauthorized(message),
+            err @ SourceError::InvalidAlert(..) => Self::BadRequest(err.to_string()),
+        }
+    }
+}
+
+impl From<DashboardError> for ApiError {
+    fn from(err: DashboardError) -> Self {
+        match err {
+            DashboardError::BrokerClientError(err) => Self::TradingClientError(err),
+            DashboardError::DatabaseError(err) => err.into(),
+        }
+    }
+}
+
 impl From<sqlx::Error> for ApiError {
     fn from(err: sqlx::Error) -> Self {
         error!("sqlx error: {}", err);
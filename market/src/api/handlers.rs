@@ -1,84 +1,171 @@
-use std::sync::Arc;
+use std::{marker::PhantomData, sync::Arc};
 
 use axum::{
-    extract::{Path, Query, State},
-    Json,
+    body::{Bytes, StreamBody},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response as HttpResponse},
+    Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{NaiveDate, Utc};
+use futures::{StreamExt, TryStreamExt};
+use rand_core::OsRng;
 use serde::Deserialize;
-use tracing::error;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
 use uuid::Uuid;
 
 use super::{
     error::ApiError,
     objects::{
-        Account, ActivitiesRequest, Activity, Asset, AssetClass, Broker, Order, OrdersRequest,
-        Position,
+        Account, ActivitiesRequest, Activity, Asset, AssetClass, Broker, Clock, Order,
+        OrdersRequest, Position,
     },
+    pagination::{ListParams, Page},
+    payload::{AlertPayload, PayloadVersion},
     Response,
 };
-use crate::{alert::WebhookAlertData, clients::BrokerClient, trade_signal::TradeSignal, App};
+use crate::{
+    activities::{self, AccountActivity, ActivityQuery, CashFlow},
+    admin::{self, StrategyDisable, StrategyStatus, StrategySwitch},
+    alert_writer::AlertRecord,
+    api_keys::{self, ApiKey, CreatedApiKey, NewApiKey},
+    backtest::{self, BacktestReport, BacktestRequest, HistoricalBar},
+    bars,
+    clients::BrokerClient,
+    control,
+    dashboard::{self, DashboardSummary},
+    dead_letters::{DeadLetter, DeadLetterQuery},
+    divergence::{self, DivergenceQuery, DivergenceReport},
+    events::{Event, EventQuery},
+    export::{self, ExportFormat, ExportQuery, ExportStream},
+    exposure::{compute_exposure, ExposureQuery, StrategyExposure},
+    feature_flags::{FeatureFlag, UpdateFeatureFlag},
+    health::{self, CheckStatus},
+    market_data::{self, BarsQuery, LiveQuote},
+    middleware::{self, RequestId},
+    order::{Fill, OrderRecord},
+    pnl::{self, PnlQuery, StrategyPnl},
+    portfolio::{self, EquityCurve, EquityCurveQuery},
+    preview::{OrderPreview, OrderPreviewRequest},
+    rate_limit::GroupUsage,
+    rebalance::{RebalanceReport, RebalanceRequest},
+    reports::{self, DailyReport},
+    retry::RetryCounts,
+    simulation::AlertSimulation,
+    stats::{self, StatsQuery, StrategyStats},
+    status::{self, PublicStatus},
+    trade_executor::QueueMetrics,
+    usage::{self, TenantUsage, UsageKind, UsageQuery},
+    users::{self, Caller, NewUser, User},
+    App,
+};
 
 pub async fn receive_webhook_alert(
     State(app): State<Arc<App>>,
-    WithRejection(alert_data, _): WithRejection<Json<WebhookAlertData>, ApiError>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    WithRejection(payload, _): WithRejection<Json<serde_json::Value>, ApiError>,
 ) -> Response<()> {
-    let trade_signal = TradeSignal::from_alert_data(alert_data.0.clone(), &app.config)?;
-    let core = Arc::clone(&app.core);
-    let client = match &trade_signal.strategy.broker {
-        Broker::Alpaca => Arc::clone(&app.clients.alpaca),
-    };
-
-    tokio::spawn(async move {
-        if let Err(err) = core.process_trade_signal(client, trade_signal).await {
-            error!("Failed to process trade signal, error: {:?}", err);
-        };
-    });
+    let alert_data = AlertPayload::from_value(payload.0, None)?.into_alert_data(&app.config)?;
+    app.accept_alert(alert_data, request_id).await?;
 
     // app.strategy_manager.
 
-    // _ = sqlx::query!(
-    //     r#"
-    //     INSERT INTO alerts (
-    //         alert_id,
-    //         ticker,
-    //         timeframe,
-    //         exchange, 
-    //         alert_type,
-    //         bar_time,
-    //         bar_open,
-    //         bar_high,
-    //         bar_low,
-    //         bar_close,
-    //         bar_volume,
-    //         alert_fire_time,
-    //         created_at,
-    //         modified_at
-    //     )
-    //     VALUES (
-    //         $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW(), NOW()
-    //     )
-    //     "#,
-    //     uuid7::new_v7(),
-    //     alert_data.ticker,
-    //     alert_data.timeframe,
-    //     alert_data.exchange,
-    //     alert_data.signal_type.as_ref(),
-    //     alert_data.bar_data.time,
-    //     alert_data.bar_data.open.as_ref(),
-    //     alert_data.bar_data.high.as_ref(),
-    //     alert_data.bar_data.low.as_ref(),
-    //     alert_data.bar_data.close.as_ref(),
-    //     alert_data.bar_data.volume,
-    //     alert_data.time
-    // )
-    // .execute(&app.db)
-    // .await?;
+    Ok(Json::default())
+}
 
+/// Alerts of the schema named by the path instead of the `version` field of the body.
+pub async fn receive_versioned_alert(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Path(version): Path<PayloadVersion>,
+    WithRejection(payload, _): WithRejection<Json<serde_json::Value>, ApiError>,
+) -> Response<()> {
+    let alert_data =
+        AlertPayload::from_value(payload.0, Some(version))?.into_alert_data(&app.config)?;
+    app.accept_alert(alert_data, request_id).await?;
+    Ok(Json::default())
+}
+
+/// Run the alert through the whole pipeline as a dry run and report what it would do, for
+/// checking new alert payloads.
+pub async fn simulate_alert(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    WithRejection(payload, _): WithRejection<Json<serde_json::Value>, ApiError>,
+) -> Response<AlertSimulation> {
+    let alert_data = AlertPayload::from_value(payload.0, None)?.into_alert_data(&app.config)?;
+    Ok(Json(app.simulate_alert(alert_data, request_id).await?))
+}
+
+/// Alerts of the signal source named by the path, authenticated and parsed by its adapter. The
+/// nonce and the quotas of the strategy are checked here, as the strategy is only known once
+/// parsed.
+pub async fn receive_source_alert(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response<()> {
+    let source = app
+        .config
+        .webhook
+        .sources
+        .iter()
+        .find(|source| source.name == name)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown signal source - {name}")))?;
+    let adapter = source.adapter();
+    let alert_data = adapter.receive(&headers, &body, &app.config)?;
+
+    let strategy = app
+        .config
+        .strategies
+        .iter()
+        .find(|strategy| strategy.id == alert_data.strategy_id)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("Unknown strategy - {}", alert_data.strategy_id))
+        })?;
+    if !source.strategies.contains(&strategy.id) {
+        tracing::warn!(
+            "Alert of signal source {} for strategy {} rejected, the source can't trade it",
+            source.name,
+            strategy.name
+        );
+        return Err(ApiError::Forbidden(format!(
+            "Signal source {} can't trade strategy {}",
+            source.name, strategy.id
+        )));
+    }
+    middleware::verify_nonce(&app, strategy, adapter.nonce(&body)?).await?;
+    usage::check_quotas(&app.db, &app.config, &strategy.tenant_id).await?;
+
+    tracing::info!("Alert received from signal source {}", source.name);
+    app.accept_alert(alert_data, request_id).await?;
+
+    if let Err(err) = usage::record(
+        &app.db,
+        &strategy.tenant_id,
+        UsageKind::Signal,
+        Some(strategy.id),
+    )
+    .await
+    {
+        tracing::error!("Failed to record usage, error: {:?}", err);
+    }
     Ok(Json::default())
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct BrokerQuery {
     broker: Broker,
 }
@@ -92,22 +179,160 @@ pub async fn check_health() -> Response<()> {
     Ok(Json::default())
 }
 
+/// Status and latency of every dependency, `503 Service Unavailable` when any is down.
+pub async fn check_deep_health(State(app): State<Arc<App>>) -> HttpResponse {
+    let health = health::deep_health(&app).await;
+    let status = match health.status {
+        CheckStatus::Up => StatusCode::OK,
+        CheckStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(health)).into_response()
+}
+
+/// Liveness probe, answers as long as the process serves requests.
+pub async fn check_liveness() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe, `503 Service Unavailable` while starting up or draining.
+pub async fn check_readiness(State(app): State<Arc<App>>) -> HttpResponse {
+    let readiness = health::readiness(&app).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness)).into_response()
+}
+
+pub async fn get_public_status(State(app): State<Arc<App>>) -> Response<PublicStatus> {
+    Ok(Json(
+        status::public_status(&app.db, &app.feature_flags, &app.config.session, Utc::now()).await,
+    ))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/account",
+        params(BrokerQuery),
+        responses(
+            (status = 200, body = Account),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "account",
+    )
+)]
 pub async fn get_account(
     State(app): State<Arc<App>>,
     Query(broker_query): Query<BrokerQuery>,
 ) -> Response<Account> {
-    let client = broker_query.broker.get_client(&app);
+    let client = broker_query.broker.get_client(&app)?;
     Ok(Json(client.get_account().await?))
 }
 
+/// Market clock of the broker, served from the cache like account lookups.
+pub async fn get_clock(
+    State(app): State<Arc<App>>,
+    Query(broker_query): Query<BrokerQuery>,
+) -> Response<Clock> {
+    let client = broker_query.broker.get_client(&app)?;
+    Ok(Json(client.get_clock().await?))
+}
+
 #[axum::debug_handler]
+/// Requests of the calling API key since startup and its remaining quota, by route group.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/account/usage",
+        responses(
+            (status = 200, body = [GroupUsage]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "account",
+    )
+)]
+pub async fn get_account_usage(
+    State(app): State<Arc<App>>,
+    headers: HeaderMap,
+) -> Response<Vec<GroupUsage>> {
+    let api_key = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    Ok(Json(app.rate_limiter.usage(api_key)))
+}
+
+/// Activities of the broker account, synced from the broker first. The stored activities are
+/// served when the broker is unavailable, also those beyond its retention.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/account/activities",
+        params(ActivityQuery),
+        responses(
+            (status = 200, body = [AccountActivity]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "account",
+    )
+)]
+pub async fn get_account_activities(
+    State(app): State<Arc<App>>,
+    Query(query): Query<ActivityQuery>,
+) -> Response<Vec<AccountActivity>> {
+    sync_activities(&app, &query).await?;
+    Ok(Json(activities::fetch(&app.db, &query).await?))
+}
+
+/// Totals of the activities of the broker account by kind and currency, see
+/// `get_account_activities`.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/account/cash-flow",
+        params(ActivityQuery),
+        responses(
+            (status = 200, body = [CashFlow]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "account",
+    )
+)]
+pub async fn get_account_cash_flow(
+    State(app): State<Arc<App>>,
+    Query(query): Query<ActivityQuery>,
+) -> Response<Vec<CashFlow>> {
+    sync_activities(&app, &query).await?;
+    Ok(Json(activities::cash_flow(&app.db, &query).await?))
+}
+
+async fn sync_activities(app: &App, query: &ActivityQuery) -> Result<(), ApiError> {
+    let account = query.account.as_deref();
+    let (client, _) = app.clients.venue(&query.broker, account)?;
+    let currency = app.config.account_currency(&query.broker, account);
+    if let Err(err) = activities::sync(&app.db, &client, &query.broker, account, currency).await {
+        tracing::warn!(
+            "Failed to sync activities of {}, serving stored ones, error: {:?}",
+            query.broker.as_ref(),
+            err
+        );
+    }
+    Ok(())
+}
+
 pub async fn get_activities(
     State(app): State<Arc<App>>,
     WithRejection(activities_req, _): WithRejection<Json<ActivitiesRequest>, ApiError>,
 ) -> Response<Vec<Activity>> {
     let activities: Vec<Activity> = match activities_req.0 {
         ActivitiesRequest::AlpacaActivitiesReq(req) => {
-            app.clients.alpaca.get_activities(req).await?
+            app.clients.scheduled_alpaca().get_activities(req).await?
         }
     };
 
@@ -119,7 +344,7 @@ pub async fn get_asset(
     Query(broker_query): Query<BrokerQuery>,
     Path(symbol): Path<String>,
 ) -> Response<Asset> {
-    let client = broker_query.broker.get_client(&app);
+    let client = broker_query.broker.get_client(&app)?;
     Ok(Json(client.get_asset(symbol.to_uppercase()).await?))
 }
 
@@ -128,30 +353,79 @@ pub async fn get_assets(
     Query(broker_query): Query<BrokerQuery>,
     Query(asset_type): Query<AssetTypeQuery>,
 ) -> Response<Vec<Asset>> {
-    let client = broker_query.broker.get_client(&app);
+    let client = broker_query.broker.get_client(&app)?;
     Ok(Json(client.get_assets(asset_type.class).await?))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/order/{id}",
+        params(BrokerQuery, ("id" = Uuid, Path, description = "Client order id")),
+        responses(
+            (status = 200, body = Order),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "orders",
+    )
+)]
 pub async fn get_order(
     State(app): State<Arc<App>>,
     Query(broker_query): Query<BrokerQuery>,
     Path(id): Path<Uuid>,
 ) -> Response<Order> {
-    let client = broker_query.broker.get_client(&app);
+    let client = broker_query.broker.get_client(&app)?;
     let order = client.get_order_by_client_id(id.to_string()).await?;
     Ok(Json(order))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/orders",
+        request_body = OrdersRequest,
+        responses(
+            (status = 200, body = [Order]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "orders",
+    )
+)]
 pub async fn get_orders(
     State(app): State<Arc<App>>,
     WithRejection(orders_req, _): WithRejection<Json<OrdersRequest>, ApiError>,
 ) -> Response<Vec<Order>> {
     let orders: Vec<Order> = match orders_req.0 {
-        OrdersRequest::AlpacaOrders(req) => app.clients.alpaca.get_orders(req).await?,
+        OrdersRequest::AlpacaOrders(req) => app.clients.scheduled_alpaca().get_orders(req).await?,
     };
     Ok(Json(orders))
 }
 
+/// Orders placed by the strategies of the caller, as recorded locally.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/orders",
+        params(ListParams),
+        responses(
+            (status = 200, body = crate::api::pagination::OrderPage),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "orders",
+    )
+)]
+pub async fn get_order_history(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    params: ListParams,
+) -> Response<Page<OrderRecord>> {
+    let strategy_ids = caller.scope(&app.config, params.strategy_id)?;
+    Ok(Json(params.fetch(&app.db, strategy_ids.as_deref()).await?))
+}
+
 // NOTE: Algorithmically create orders
 // pub async fn create_order(
 //     State(app): State<Arc<App>>,
@@ -184,26 +458,813 @@ pub async fn get_position(
     Path(symbol): Path<String>,
     Query(query): Query<BrokerQuery>,
 ) -> Response<Position> {
-    let client = query.broker.get_client(&app);
+    let client = query.broker.get_client(&app)?;
     let position = client.get_position(symbol).await?;
     Ok(Json(position))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/positions",
+        params(BrokerQuery),
+        responses(
+            (status = 200, body = [Position]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "positions",
+    )
+)]
 pub async fn get_positions(
     State(app): State<Arc<App>>,
     Query(query): Query<BrokerQuery>,
 ) -> Response<Vec<Position>> {
-    let client = query.broker.get_client(&app);
+    let client = query.broker.get_client(&app)?;
     let positions = client.get_positions().await?;
     Ok(Json(positions))
 }
 
+/// Fills of the strategies of the caller, which their positions are built of.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/positions/history",
+        params(ListParams),
+        responses(
+            (status = 200, body = crate::api::pagination::FillPage),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "positions",
+    )
+)]
+pub async fn get_position_history(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    params: ListParams,
+) -> Response<Page<Fill>> {
+    let strategy_ids = caller.scope(&app.config, params.strategy_id)?;
+    Ok(Json(params.fetch(&app.db, strategy_ids.as_deref()).await?))
+}
+
 pub async fn delete_position(
     State(app): State<Arc<App>>,
     Query(broker_query): Query<BrokerQuery>,
     Path(symbol): Path<String>,
 ) -> Response<Order> {
-    let client = broker_query.broker.get_client(&app);
+    let client = broker_query.broker.get_client(&app)?;
     let delete_position_order = client.delete_position(symbol).await?;
     Ok(Json(delete_position_order))
 }
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/strategies/{id}/pnl",
+        params(("id" = Uuid, Path, description = "Strategy id"), PnlQuery),
+        responses(
+            (status = 200, body = StrategyPnl),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "strategies",
+    )
+)]
+pub async fn get_strategy_pnl(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PnlQuery>,
+) -> Response<StrategyPnl> {
+    let strategy = caller.strategy(&app.config, id)?;
+
+    let client = strategy
+        .broker
+        .get_account_client(&app, strategy.account.as_deref())?;
+    let mut pnl = pnl::strategy_pnl(&app.db, &client, id).await?;
+    pnl.retain_days(&query);
+    Ok(Json(pnl))
+}
+
+pub async fn run_backtest(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    WithRejection(request, _): WithRejection<Json<BacktestRequest>, ApiError>,
+) -> Response<BacktestReport> {
+    let strategy = caller.strategy(&app.config, request.strategy_id)?;
+    if request.from >= request.to {
+        return Err(ApiError::BadRequest(
+            "Backtest period must end after it starts".to_string(),
+        ));
+    }
+
+    let bars = if request.stored_bars {
+        bars::fetch(
+            &app.db,
+            &request.ticker,
+            request.timeframe,
+            request.from,
+            request.to,
+        )
+        .await?
+    } else {
+        match strategy.broker {
+            // NOTE: equities of Tradier strategies are backtested on the bars of Alpaca
+            Broker::Alpaca | Broker::Tradier => {
+                backtest::fetch_bars(
+                    &app.clients.alpaca(),
+                    &request.ticker,
+                    request.timeframe,
+                    request.from,
+                    request.to,
+                )
+                .await?
+            }
+            Broker::Oanda => {
+                return Err(ApiError::BadRequest(
+                    "Strategies on OANDA are backtested on stored bars".to_string(),
+                ))
+            }
+        }
+    };
+
+    Ok(Json(backtest::run(
+        strategy,
+        &request,
+        &bars,
+        &app.config.paper,
+        &mut OsRng,
+    )))
+}
+
+pub async fn get_quote(
+    State(app): State<Arc<App>>,
+    Path(symbol): Path<String>,
+) -> Response<LiveQuote> {
+    let quote = market_data::fetch_quote(&app.clients.alpaca(), &symbol)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No quote for {symbol}")))?;
+    Ok(Json(quote))
+}
+
+pub async fn get_bars(
+    State(app): State<Arc<App>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<BarsQuery>,
+) -> Response<Vec<HistoricalBar>> {
+    if query.from >= query.to {
+        return Err(ApiError::BadRequest(
+            "Bars period must end after it starts".to_string(),
+        ));
+    }
+
+    let bars = backtest::fetch_bars(
+        &app.clients.alpaca(),
+        &symbol,
+        query.timeframe,
+        query.from,
+        query.to,
+    )
+    .await?;
+    Ok(Json(bars))
+}
+
+pub async fn get_strategy_exposure(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExposureQuery>,
+) -> Response<StrategyExposure> {
+    let strategy = caller.strategy(&app.config, id)?;
+
+    let fills = Fill::fetch_for_strategy(&app.db, id).await?;
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query
+        .from
+        .or_else(|| fills.first().map(|fill| fill.filled_at))
+        .unwrap_or(to);
+
+    Ok(Json(compute_exposure(
+        id,
+        &fills,
+        strategy.session(&app.config.session),
+        from,
+        to,
+    )))
+}
+
+pub async fn get_strategy_divergence(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DivergenceQuery>,
+) -> Response<DivergenceReport> {
+    caller.strategy(&app.config, id)?;
+
+    Ok(Json(divergence::report(&app.db, id, &query).await?))
+}
+
+pub async fn get_strategy_stats(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<StatsQuery>,
+) -> Response<StrategyStats> {
+    let strategy = caller.strategy(&app.config, id)?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(ApiError::BadRequest(
+                "Stats period must end after it starts".to_string(),
+            ));
+        }
+    }
+
+    Ok(Json(
+        stats::strategy_stats(&app.db, strategy, &query).await?,
+    ))
+}
+
+pub async fn get_usage(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Query(mut query): Query<UsageQuery>,
+) -> Response<Vec<TenantUsage>> {
+    if let Some(user_id) = caller.user_id {
+        query.tenant_id = Some(user_id);
+    }
+    Ok(Json(usage::export(&app.db, &app.config, &query).await?))
+}
+
+/// Retries of order submissions since startup.
+pub async fn get_retry_metrics(State(app): State<Arc<App>>) -> Response<RetryCounts> {
+    Ok(Json(app.core.retry_metrics().counts()))
+}
+
+/// Depth and throughput of the execution queues of the brokers since startup.
+pub async fn get_queue_metrics(State(app): State<Arc<App>>) -> Response<Vec<QueueMetrics>> {
+    Ok(Json(app.executor.metrics()))
+}
+
+pub async fn get_feature_flags(State(app): State<Arc<App>>) -> Response<Vec<FeatureFlag>> {
+    Ok(Json(app.feature_flags.list().await?))
+}
+
+pub async fn update_feature_flag(
+    State(app): State<Arc<App>>,
+    Path(name): Path<String>,
+    WithRejection(update, _): WithRejection<Json<UpdateFeatureFlag>, ApiError>,
+) -> Response<FeatureFlag> {
+    if let Some(rollout) = update
+        .rollout_percentage
+        .filter(|rollout| !(0..=100).contains(rollout))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Rollout percentage must be between 0 and 100, got {rollout}"
+        )));
+    }
+
+    let flag = app.feature_flags.set(&name, &update.0).await?;
+    tracing::info!(
+        "Feature flag {} set to {}, rollout {}%",
+        flag.name,
+        flag.enabled,
+        flag.rollout_percentage
+    );
+    Ok(Json(flag))
+}
+
+pub async fn get_equity_curve(
+    State(app): State<Arc<App>>,
+    Query(query): Query<EquityCurveQuery>,
+) -> Response<EquityCurve> {
+    Ok(Json(portfolio::equity_curve(&app.db, &query).await?))
+}
+
+/// Account, positions, today's P&L, recent alerts and failures of the day of a broker in one
+/// call, what the bundled dashboard shows.
+pub async fn get_dashboard_summary(
+    State(app): State<Arc<App>>,
+    Query(query): Query<BrokerQuery>,
+) -> Response<DashboardSummary> {
+    let client = query.broker.get_client(&app)?;
+    Ok(Json(
+        dashboard::summary(&app.db, &query.broker, &client, Utc::now()).await?,
+    ))
+}
+
+/// Rebalance the account of a strategy to target weights, or preview the orders with `dry_run`.
+pub async fn rebalance(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Extension(caller): Extension<Caller>,
+    WithRejection(request, _): WithRejection<Json<RebalanceRequest>, ApiError>,
+) -> Response<RebalanceReport> {
+    let strategy = caller.strategy(&app.config, request.strategy_id)?;
+    if !strategy.enabled {
+        return Err(ApiError::BadRequest(format!(
+            "Strategy {} is disabled",
+            strategy.name
+        )));
+    }
+    request.validate().map_err(ApiError::BadRequest)?;
+
+    let client = app
+        .clients
+        .venue(&strategy.broker, strategy.account.as_deref())?
+        .0;
+    let report = app
+        .core
+        .rebalance(client, strategy, &request, Some(request_id))
+        .await?;
+    tracing::info!(
+        "Rebalance of strategy {} planned {} orders, dry run: {}",
+        strategy.name,
+        report.orders.len(),
+        report.dry_run
+    );
+    Ok(Json(report))
+}
+
+/// Order a strategy would place for the request, its cost and the broker request, without
+/// placing it.
+pub async fn preview_order(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Extension(caller): Extension<Caller>,
+    WithRejection(request, _): WithRejection<Json<OrderPreviewRequest>, ApiError>,
+) -> Response<OrderPreview> {
+    let strategy = caller.strategy(&app.config, request.strategy_id)?;
+    request.validate().map_err(ApiError::BadRequest)?;
+
+    let client = app
+        .clients
+        .venue(&strategy.broker, strategy.account.as_deref())?
+        .0;
+    let mut preview = app
+        .core
+        .preview_order(client, strategy, &request, Some(request_id))
+        .await?;
+    if !caller.is_operator() {
+        preview.redact_account();
+    }
+    Ok(Json(preview))
+}
+
+pub async fn export_trades(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let entries = export::spawn_trade_journal(Arc::clone(&app), query.clone(), caller);
+
+    let (export, mut content_type, mut filename): (ExportStream, _, _) = match query.format {
+        ExportFormat::Csv => (
+            export::csv_stream(entries).boxed(),
+            "text/csv",
+            "trades.csv".to_string(),
+        ),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let parquet = export::to_parquet(entries)
+                .await
+                .map_err(ApiError::internal_error)?;
+            (
+                futures::stream::once(async { Ok(axum::body::Bytes::from(parquet)) }).boxed(),
+                "application/vnd.apache.parquet",
+                "trades.parquet".to_string(),
+            )
+        }
+        #[cfg(not(feature = "parquet"))]
+        ExportFormat::Parquet => {
+            return Err(ApiError::BadRequest(
+                "Parquet export is not enabled".to_owned(),
+            ))
+        }
+    };
+
+    let export = export::compress(export, query.compression);
+    if let Some(compression) = query.compression {
+        content_type = compression.content_type();
+        filename = format!("{filename}.{}", compression.extension());
+    }
+    let disposition = format!("attachment; filename=\"{filename}\"");
+
+    if !query.sign {
+        return Ok((
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            StreamBody::new(export),
+        )
+            .into_response());
+    }
+
+    let Some(signing) = &app.config.export.signing else {
+        return Err(ApiError::BadRequest(
+            "Export signing is not configured".to_owned(),
+        ));
+    };
+
+    // NOTE: the signature covers the downloaded bytes, after compression
+    let data = export
+        .try_fold(Vec::new(), |mut data, chunk| async move {
+            data.extend_from_slice(&chunk);
+            Ok(data)
+        })
+        .await
+        .map_err(ApiError::internal_error)?;
+    let signature = signing
+        .sign(&data)
+        .await
+        .map_err(ApiError::internal_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (
+                header::HeaderName::from_static(export::SIGNATURE_HEADER),
+                BASE64_STANDARD.encode(signature),
+            ),
+            (
+                header::HeaderName::from_static(export::SIGNATURE_FILE_HEADER),
+                format!("{filename}.{}", signing.extension()),
+            ),
+        ],
+        axum::body::Full::from(data),
+    )
+        .into_response())
+}
+
+pub async fn get_api_keys(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+) -> Response<Vec<ApiKey>> {
+    Ok(Json(
+        api_keys::list(&app.db, caller.user_id.as_deref()).await?,
+    ))
+}
+
+/// Keys created by users are issued for themselves.
+pub async fn create_api_key(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    WithRejection(Json(mut new_key), _): WithRejection<Json<NewApiKey>, ApiError>,
+) -> Response<CreatedApiKey> {
+    if caller.user_id.is_some() {
+        new_key.user_id = caller.user_id;
+    }
+    let created = api_keys::create(&app.db, &new_key).await?;
+    tracing::info!(
+        "API key {} created with {} role",
+        created.api_key.name,
+        created.api_key.role
+    );
+    Ok(Json(created))
+}
+
+pub async fn revoke_api_key(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+) -> Response<ApiKey> {
+    let api_key = api_keys::revoke(&app.db, id, caller.user_id.as_deref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown or revoked API key - {id}")))?;
+    tracing::info!("API key {} revoked", api_key.name);
+    Ok(Json(api_key))
+}
+
+/// Drop the cached account, asset and clock lookups of the broker, of all its credential sets.
+pub async fn invalidate_broker_cache(
+    State(app): State<Arc<App>>,
+    Query(query): Query<BrokerQuery>,
+) -> Response<()> {
+    for cache in app.clients.caches(&query.broker) {
+        cache.invalidate_all().await;
+    }
+    tracing::info!("Cache of {} invalidated", query.broker.as_ref());
+    Ok(Json::default())
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/strategies",
+        responses(
+            (status = 200, body = [StrategyStatus]),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "strategies",
+    )
+)]
+pub async fn get_strategies(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+) -> Response<Vec<StrategyStatus>> {
+    let strategies: Vec<_> = app
+        .config
+        .strategies
+        .iter()
+        .filter(|strategy| caller.owns(strategy))
+        .cloned()
+        .collect();
+    let statuses = admin::strategy_statuses(&app.db, &strategies, Utc::now().date_naive()).await?;
+    Ok(Json(statuses))
+}
+
+/// Enable or disable a strategy at runtime. The switch is persisted and audited, disabling
+/// dead-letters the signals of the strategy still queued.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        put,
+        path = "/strategies/{id}/enabled",
+        params(("id" = Uuid, Path, description = "Strategy id")),
+        request_body = StrategySwitch,
+        responses(
+            (status = 200, body = StrategyStatus),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "strategies",
+    )
+)]
+pub async fn switch_strategy(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+    WithRejection(switch, _): WithRejection<Json<StrategySwitch>, ApiError>,
+) -> Response<StrategyStatus> {
+    let strategy = caller.strategy(&app.config, id)?;
+
+    Ok(Json(
+        app.switch_strategy(&caller, strategy, &switch, Some(&request_id))
+            .await?,
+    ))
+}
+
+/// Enable a strategy, an alias of `PUT /strategies/{id}/enabled`.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/strategies/{id}/enable",
+        params(("id" = Uuid, Path, description = "Strategy id")),
+        responses(
+            (status = 200, body = StrategyStatus),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "strategies",
+    )
+)]
+pub async fn enable_strategy(
+    state: State<Arc<App>>,
+    request_id: Extension<RequestId>,
+    caller: Extension<Caller>,
+    id: Path<Uuid>,
+) -> Response<StrategyStatus> {
+    let switch = StrategySwitch {
+        enabled: true,
+        reason: None,
+    };
+    switch_strategy(
+        state,
+        request_id,
+        caller,
+        id,
+        WithRejection(Json(switch), PhantomData),
+    )
+    .await
+}
+
+/// Disable a strategy immediately, its signals still queued are dead-lettered. An alias of
+/// `PUT /strategies/{id}/enabled`, the body with the reason is optional.
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/strategies/{id}/disable",
+        params(("id" = Uuid, Path, description = "Strategy id")),
+        request_body = StrategyDisable,
+        responses(
+            (status = 200, body = StrategyStatus),
+            (status = "default", body = crate::api::error::ErrorBody),
+        ),
+        tag = "strategies",
+    )
+)]
+pub async fn disable_strategy(
+    state: State<Arc<App>>,
+    request_id: Extension<RequestId>,
+    caller: Extension<Caller>,
+    id: Path<Uuid>,
+    disable: Option<Json<StrategyDisable>>,
+) -> Response<StrategyStatus> {
+    let switch = StrategySwitch {
+        enabled: false,
+        reason: disable.and_then(|Json(disable)| disable.reason),
+    };
+    switch_strategy(
+        state,
+        request_id,
+        caller,
+        id,
+        WithRejection(Json(switch), PhantomData),
+    )
+    .await
+}
+
+pub async fn get_daily_report(
+    State(app): State<Arc<App>>,
+    Path(date): Path<NaiveDate>,
+) -> Response<DailyReport> {
+    let report = reports::fetch(&app.db, date)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No daily report of {date}")))?;
+    Ok(Json(report))
+}
+
+/// Alerts received for the strategies of the caller.
+pub async fn get_alerts(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    params: ListParams,
+) -> Response<Page<AlertRecord>> {
+    let strategy_ids = caller.scope(&app.config, params.strategy_id)?;
+    Ok(Json(params.fetch(&app.db, strategy_ids.as_deref()).await?))
+}
+
+pub async fn get_dead_letters(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Response<Vec<DeadLetter>> {
+    let strategy_ids = caller.scope(&app.config, query.strategy_id)?;
+    Ok(Json(
+        DeadLetter::fetch_recent_for(&app.db, &query, strategy_ids.as_deref()).await?,
+    ))
+}
+
+pub async fn replay_dead_letter(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<Uuid>,
+) -> Response<DeadLetter> {
+    let dead_letter = app.replay_dead_letter(&caller, id, request_id).await?;
+    tracing::info!("Dead letter {} replayed", id);
+    Ok(Json(dead_letter))
+}
+
+/// Reconcile orders with the broker now instead of at the next periodic sync, orders missing
+/// locally are recovered first.
+pub async fn reconcile(State(app): State<Arc<App>>) -> Response<()> {
+    app.reconcile().await?;
+    Ok(Json::default())
+}
+
+pub async fn get_users(State(app): State<Arc<App>>) -> Response<Vec<User>> {
+    Ok(Json(users::list(&app.db).await?))
+}
+
+pub async fn create_user(
+    State(app): State<Arc<App>>,
+    WithRejection(new_user, _): WithRejection<Json<NewUser>, ApiError>,
+) -> Response<User> {
+    let user = users::create(&app.db, &new_user).await?;
+    tracing::info!("User {} created", user.user_id);
+    Ok(Json(user))
+}
+
+/// Live feed of alerts, submitted orders and fills as JSON text messages.
+pub async fn stream_events(
+    State(app): State<Arc<App>>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<EventQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<HttpResponse, ApiError> {
+    let strategy_ids = caller.scope(&app.config, query.strategy_id)?;
+    // Subscribed before the upgrade, so no event is missed once the handshake completes
+    let events = app.core.events().subscribe();
+    Ok(ws.on_upgrade(move |socket| forward_events(events, query, strategy_ids, socket)))
+}
+
+/// Forward events matching the query, of the `strategy_ids` the subscriber sees when set.
+async fn forward_events(
+    mut events: broadcast::Receiver<Event>,
+    query: EventQuery,
+    strategy_ids: Option<Vec<Uuid>>,
+    mut socket: WebSocket,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Event subscriber lagged, {} events skipped", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let visible = strategy_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&event.strategy_id()));
+                if !query.matches(&event) || !visible {
+                    continue;
+                }
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Subscribers only listen, anything they send is ignored
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Control channel of operator consoles. Every text message is a `control::ControlRequest`, run
+/// as it's received and acknowledged with a `control::ControlAck` once it's done, so the acks of
+/// slow commands may come after the ones of later commands. The channel is closed once the API key
+/// isn't valid anymore, see `control::admit`.
+pub async fn control_channel(
+    State(app): State<Arc<App>>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> HttpResponse {
+    // Only requests with a valid key pass `auth`
+    let secret = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    ws.on_upgrade(move |socket| run_commands(app, secret, request_id, socket))
+}
+
+async fn run_commands(app: Arc<App>, secret: String, request_id: String, mut socket: WebSocket) {
+    // NOTE: commands still running when the channel closes run to the end, their acks are dropped
+    let (acks, mut done) = mpsc::unbounded_channel();
+    loop {
+        let ack = tokio::select! {
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // Pings are answered by axum
+                    Some(Ok(_)) => continue,
+                };
+                let request = match control::parse(&text, &request_id) {
+                    Ok(request) => request,
+                    Err(ack) => {
+                        if send_ack(&mut socket, &ack).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                match control::admit(&app, &secret).await {
+                    Ok(caller) => {
+                        let (app, acks, request_id) =
+                            (Arc::clone(&app), acks.clone(), request_id.clone());
+                        tokio::spawn(async move {
+                            let ack = control::handle(&app, &caller, request, &request_id).await;
+                            let _ = acks.send(ack);
+                        });
+                        continue;
+                    }
+                    Err(err) => {
+                        let revoked = matches!(err, ApiError::Unauthorized(_));
+                        let command = Some(request.command.as_ref().to_owned());
+                        let ack = control::rejected(request.id, command, err, &request_id);
+                        if send_ack(&mut socket, &ack).await.is_err() || revoked {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+            }
+            Some(ack) = done.recv() => ack,
+        };
+        if send_ack(&mut socket, &ack).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn send_ack(socket: &mut WebSocket, ack: &control::ControlAck) -> Result<(), axum::Error> {
+    let Ok(ack) = serde_json::to_string(ack) else {
+        return Ok(());
+    };
+    socket.send(Message::Text(ack)).await
+}
@@ -1,31 +1,83 @@
+// Objects of Alpaca are far larger than the ones of OANDA, they're passed by value like the apca
+// types they wrap
+#![allow(clippy::large_enum_variant)]
+
 use apca::api::v2::{
     account::Account as AlpacaAccount,
     account_activities::{Activity as AlpacaActivity, ActivityReq as AlpacaActivitiesReq},
     asset::Asset as AlpacaAsset,
-    order::{ChangeReq as AlpacaOrderUpdateReq, Order as AlpacaOrder, OrderReq as AlpacaNewOrder},
+    clock::Clock as AlpacaClock,
+    order::{
+        Amount as AlpacaAmount, ChangeReq as AlpacaOrderUpdateReq, Order as AlpacaOrder,
+        OrderReq as AlpacaNewOrder, Side as AlpacaSide, Status as AlpacaStatus,
+        TimeInForce as AlpacaTimeInForce, Type as AlpacaType,
+    },
     orders::OrdersReq as AlpacOrdersReq,
     position::Position as AlpacaPosition,
 };
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+use crate::{
+    cache::CachedClient,
+    clients::{num_to_decimal, order_uuid, BrokerClient, BrokerClientError},
+    oanda::{OandaAccount, OandaClock, OandaInstrument, OandaOrder, OandaPosition},
+    order::{OrderSide, TimeInForce},
+    strategy::CurrencyType,
+    tradier::{TradierAccount, TradierClock, TradierOrder, TradierPosition, TradierSecurity},
+    App,
+};
 
-use crate::{clients::BrokerClient, App};
+/// Currency of the assets traded at Alpaca and Tradier
+pub const USD: &str = "USD";
 
 pub trait GetBroker {
     fn broker(&self) -> Broker;
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Amount of money in the currency it's denominated in.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Money {
+    pub amount: Decimal,
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: impl Into<String>) -> Self {
+        Self {
+            amount,
+            currency: currency.into(),
+        }
+    }
+
+    fn usd(amount: Decimal) -> Self {
+        Self::new(amount, USD)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, AsRefStr, EnumString)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[strum(serialize_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
 pub enum Broker {
     Alpaca,
+    Oanda,
+    Tradier,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum Account {
-    AlpacaAccount(AlpacaAccount),
+    AlpacaAccount(#[cfg_attr(feature = "openapi", schema(value_type = Object))] AlpacaAccount),
+    OandaAccount(#[cfg_attr(feature = "openapi", schema(value_type = Object))] OandaAccount),
+    TradierAccount(#[cfg_attr(feature = "openapi", schema(value_type = Object))] TradierAccount),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub enum ActivitiesRequest {
     AlpacaActivitiesReq(AlpacaActivitiesReq),
 }
@@ -33,29 +85,49 @@ pub enum ActivitiesRequest {
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Activity {
     AlpacaActivity(AlpacaActivity),
+    /// Transaction of the account in the wire format of the v20 API
+    OandaTransaction(serde_json::Value),
+    /// Event of the account history in the wire format of the Tradier API
+    TradierEvent(serde_json::Value),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum AssetClass {
     #[serde(rename = "us_equity")]
     UsEquity,
     #[serde(rename = "crypto")]
     Crypto,
+    #[serde(rename = "forex")]
+    Forex,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub enum Asset {
     AlpacaAsset(AlpacaAsset),
+    OandaInstrument(OandaInstrument),
+    TradierSecurity(TradierSecurity),
+}
+
+/// Market clock of the broker.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum Clock {
+    AlpacaClock(AlpacaClock),
+    OandaClock(OandaClock),
+    TradierClock(TradierClock),
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum Order {
-    AlpacaOrder(AlpacaOrder),
+    AlpacaOrder(#[cfg_attr(feature = "openapi", schema(value_type = Object))] AlpacaOrder),
+    OandaOrder(#[cfg_attr(feature = "openapi", schema(value_type = Object))] OandaOrder),
+    TradierOrder(#[cfg_attr(feature = "openapi", schema(value_type = Object))] TradierOrder),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum OrdersRequest {
-    AlpacaOrders(AlpacOrdersReq),
+    AlpacaOrders(#[cfg_attr(feature = "openapi", schema(value_type = Object))] AlpacOrdersReq),
 }
 
 // NOTE: Algorithmically create orders
@@ -71,31 +143,429 @@ pub enum OrdersRequest {
 // }
 
 #[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum Position {
-    AlpacaPosition(AlpacaPosition),
+    AlpacaPosition(#[cfg_attr(feature = "openapi", schema(value_type = Object))] AlpacaPosition),
+    OandaPosition(#[cfg_attr(feature = "openapi", schema(value_type = Object))] OandaPosition),
+    TradierPosition(#[cfg_attr(feature = "openapi", schema(value_type = Object))] TradierPosition),
 }
 
-impl From<AssetClass> for apca::api::v2::asset::Class {
-    fn from(value: AssetClass) -> Self {
+impl TryFrom<AssetClass> for apca::api::v2::asset::Class {
+    type Error = BrokerClientError;
+
+    fn try_from(value: AssetClass) -> Result<Self, Self::Error> {
         match value {
-            AssetClass::UsEquity => apca::api::v2::asset::Class::UsEquity,
-            AssetClass::Crypto => apca::api::v2::asset::Class::Crypto,
+            AssetClass::UsEquity => Ok(apca::api::v2::asset::Class::UsEquity),
+            AssetClass::Crypto => Ok(apca::api::v2::asset::Class::Crypto),
+            AssetClass::Forex => Err(BrokerClientError::AlpacaError(
+                "Alpaca trades no forex".to_owned(),
+            )),
         }
     }
 }
 
-impl Broker {
-    pub fn get_client<'a>(&self, app: &'a App) -> &'a impl BrokerClient {
-        match self {
-            Broker::Alpaca => &app.clients.alpaca,
+impl From<CurrencyType> for AssetClass {
+    fn from(value: CurrencyType) -> Self {
+        match value {
+            CurrencyType::Stock => AssetClass::UsEquity,
+            CurrencyType::Crypto => AssetClass::Crypto,
+            CurrencyType::Forex => AssetClass::Forex,
         }
     }
+}
+
+impl Broker {
+    /// Client of the broker, account, asset and clock lookups are served from the cache. Fails
+    /// for brokers which aren't configured.
+    pub fn get_client<'a>(
+        &self,
+        app: &'a App,
+    ) -> Result<impl BrokerClient + 'a, BrokerClientError> {
+        self.get_account_client(app, None)
+    }
+
+    /// Client of the credential set `account` of the broker, see `Strategy::account`.
+    pub fn get_account_client<'a>(
+        &self,
+        app: &'a App,
+        account: Option<&str>,
+    ) -> Result<impl BrokerClient + 'a, BrokerClientError> {
+        let (client, cache) = app.clients.venue(self, account)?;
+        Ok(CachedClient::new(client, cache))
+    }
 
     pub async fn create_order_request(&self) -> Result<(), ()> {
         Ok(())
     }
 }
 
+impl Account {
+    /// Currency the account is denominated in.
+    pub fn currency(&self) -> &str {
+        match self {
+            Account::AlpacaAccount(account) => &account.currency,
+            Account::OandaAccount(account) => &account.currency,
+            Account::TradierAccount(_) => USD,
+        }
+    }
+
+    pub fn equity(&self) -> Money {
+        match self {
+            Account::AlpacaAccount(account) => {
+                Money::new(num_to_decimal(&account.equity), self.currency())
+            }
+            Account::OandaAccount(account) => Money::new(account.nav, self.currency()),
+            Account::TradierAccount(account) => Money::usd(account.total_equity),
+        }
+    }
+
+    pub fn cash(&self) -> Money {
+        match self {
+            Account::AlpacaAccount(account) => {
+                Money::new(num_to_decimal(&account.cash), self.currency())
+            }
+            Account::OandaAccount(account) => Money::new(account.balance, self.currency()),
+            Account::TradierAccount(account) => Money::usd(account.total_cash),
+        }
+    }
+
+    /// Value of the positions the account can open, the available margin at the margin rate of
+    /// the account for OANDA.
+    pub fn buying_power(&self) -> Money {
+        match self {
+            Account::AlpacaAccount(account) => {
+                Money::new(num_to_decimal(&account.buying_power), self.currency())
+            }
+            Account::OandaAccount(account) => Money::new(
+                account
+                    .margin_available
+                    .checked_div(account.margin_rate)
+                    .unwrap_or_default(),
+                self.currency(),
+            ),
+            Account::TradierAccount(account) => Money::usd(account.buying_power()),
+        }
+    }
+}
+
+impl Asset {
+    /// Whether fractional quantities and notional orders of the asset are accepted.
+    pub fn fractionable(&self) -> bool {
+        match self {
+            Asset::AlpacaAsset(asset) => asset.fractionable,
+            Asset::OandaInstrument(instrument) => instrument.trade_units_precision > 0,
+            Asset::TradierSecurity(_) => false,
+        }
+    }
+
+    /// Whether the asset can be traded at the broker.
+    pub fn tradable(&self) -> bool {
+        match self {
+            Asset::AlpacaAsset(asset) => asset.tradable,
+            // NOTE: instruments are only listed when the account can trade them
+            Asset::OandaInstrument(_) => true,
+            // NOTE: securities are only known to Tradier when they have quotes
+            Asset::TradierSecurity(_) => true,
+        }
+    }
+
+    /// Whether the broker allows selling the asset short.
+    pub fn shortable(&self) -> bool {
+        match self {
+            Asset::AlpacaAsset(asset) => asset.shortable,
+            Asset::OandaInstrument(_) => true,
+            Asset::TradierSecurity(security) => security.easy_to_borrow,
+        }
+    }
+
+    /// Whether shares to sell short are available without a locate.
+    pub fn easy_to_borrow(&self) -> bool {
+        match self {
+            Asset::AlpacaAsset(asset) => asset.easy_to_borrow,
+            Asset::OandaInstrument(_) => true,
+            Asset::TradierSecurity(security) => security.easy_to_borrow,
+        }
+    }
+}
+
+impl Clock {
+    /// Whether the market is open at the time of the clock.
+    pub fn is_open(&self) -> bool {
+        match self {
+            Clock::AlpacaClock(clock) => clock.open,
+            Clock::OandaClock(clock) => clock.open,
+            Clock::TradierClock(clock) => clock.open,
+        }
+    }
+
+    pub fn next_open(&self) -> DateTime<Utc> {
+        match self {
+            Clock::AlpacaClock(clock) => clock.next_open,
+            Clock::OandaClock(clock) => clock.next_open,
+            Clock::TradierClock(clock) => clock.next_open,
+        }
+    }
+
+    pub fn next_close(&self) -> DateTime<Utc> {
+        match self {
+            Clock::AlpacaClock(clock) => clock.next_close,
+            Clock::OandaClock(clock) => clock.next_close,
+            Clock::TradierClock(clock) => clock.next_close,
+        }
+    }
+}
+
+impl Order {
+    pub fn broker_order_id(&self) -> String {
+        match self {
+            Order::AlpacaOrder(order) => order.id.0.to_string(),
+            Order::OandaOrder(order) => order.id.clone(),
+            Order::TradierOrder(order) => order.id.to_string(),
+        }
+    }
+
+    /// Order status as reported by the broker, in its snake case wire format.
+    pub fn status(&self) -> String {
+        match self {
+            Order::AlpacaOrder(order) => serde_json::to_value(order.status)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_owned))
+                .unwrap_or_else(|| "unknown".to_owned()),
+            Order::OandaOrder(order) => order.status().to_owned(),
+            Order::TradierOrder(order) => order.status().to_owned(),
+        }
+    }
+
+    pub fn filled_quantity(&self) -> Decimal {
+        match self {
+            Order::AlpacaOrder(order) => num_to_decimal(&order.filled_quantity),
+            Order::OandaOrder(order) => order.filled_units.abs(),
+            Order::TradierOrder(order) => order.filled_quantity,
+        }
+    }
+
+    pub fn filled_avg_price(&self) -> Option<Money> {
+        match self {
+            Order::AlpacaOrder(order) => order
+                .average_fill_price
+                .as_ref()
+                .map(|price| Money::usd(num_to_decimal(price))),
+            Order::OandaOrder(order) => order
+                .average_fill_price
+                .map(|price| Money::new(price, quote_currency(&order.instrument))),
+            Order::TradierOrder(order) => order.average_fill_price.map(Money::usd),
+        }
+    }
+
+    /// Time the broker executed the latest fill of the order, when it reports one.
+    pub fn filled_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            // NOTE: Alpaca sets `filled_at` once the order is filled, partial fills only update
+            // `updated_at`
+            Order::AlpacaOrder(order) => order.filled_at.or(order.updated_at),
+            Order::OandaOrder(order) => order.filled_time,
+            Order::TradierOrder(order) => order.transaction_date,
+        }
+    }
+
+    /// Id the order was submitted with, see `order::client_order_id`.
+    pub fn client_order_id(&self) -> &str {
+        match self {
+            Order::AlpacaOrder(order) => &order.client_order_id,
+            Order::OandaOrder(order) => &order.client_order_id,
+            Order::TradierOrder(order) => &order.client_order_id,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            Order::AlpacaOrder(order) => &order.symbol,
+            Order::OandaOrder(order) => &order.instrument,
+            Order::TradierOrder(order) => &order.symbol,
+        }
+    }
+
+    pub fn side(&self) -> OrderSide {
+        match self {
+            Order::AlpacaOrder(order) => match order.side {
+                AlpacaSide::Buy => OrderSide::Buy,
+                AlpacaSide::Sell => OrderSide::Sell,
+            },
+            Order::OandaOrder(order) => order.side(),
+            Order::TradierOrder(order) => order.side(),
+        }
+    }
+
+    /// Ordered quantity, the filled quantity of notional orders.
+    pub fn quantity(&self) -> Decimal {
+        match self {
+            Order::AlpacaOrder(order) => match &order.amount {
+                AlpacaAmount::Quantity { quantity } => num_to_decimal(quantity),
+                AlpacaAmount::Notional { .. } => num_to_decimal(&order.filled_quantity),
+            },
+            Order::OandaOrder(order) => order.units.abs(),
+            Order::TradierOrder(order) => order.quantity,
+        }
+    }
+
+    pub fn notional(&self) -> Option<Money> {
+        match self {
+            Order::AlpacaOrder(order) => match &order.amount {
+                AlpacaAmount::Quantity { .. } => None,
+                AlpacaAmount::Notional { notional } => Some(Money::usd(num_to_decimal(notional))),
+            },
+            Order::OandaOrder(_) | Order::TradierOrder(_) => None,
+        }
+    }
+
+    pub fn time_in_force(&self) -> TimeInForce {
+        match self {
+            Order::AlpacaOrder(order) => match order.time_in_force {
+                AlpacaTimeInForce::Day => TimeInForce::Day,
+                AlpacaTimeInForce::UntilCanceled => TimeInForce::Gtc,
+                AlpacaTimeInForce::ImmediateOrCancel => TimeInForce::Ioc,
+                AlpacaTimeInForce::FillOrKill => TimeInForce::Fok,
+                AlpacaTimeInForce::UntilMarketOpen => TimeInForce::Opg,
+                AlpacaTimeInForce::UntilMarketClose => TimeInForce::Cls,
+            },
+            Order::OandaOrder(order) => match order.time_in_force.as_str() {
+                "GTC" | "GTD" => TimeInForce::Gtc,
+                "IOC" => TimeInForce::Ioc,
+                "FOK" => TimeInForce::Fok,
+                _ => TimeInForce::Day,
+            },
+            Order::TradierOrder(order) => match order.duration.as_str() {
+                "gtc" => TimeInForce::Gtc,
+                _ => TimeInForce::Day,
+            },
+        }
+    }
+
+    pub fn limit_price(&self) -> Option<Money> {
+        match self {
+            Order::AlpacaOrder(order) => order
+                .limit_price
+                .as_ref()
+                .map(|price| Money::usd(num_to_decimal(price))),
+            Order::OandaOrder(order) => order
+                .price
+                .map(|price| Money::new(price, quote_currency(&order.instrument))),
+            Order::TradierOrder(order) => order.price.map(Money::usd),
+        }
+    }
+
+    pub fn extended_hours(&self) -> bool {
+        match self {
+            Order::AlpacaOrder(order) => order.extended_hours,
+            Order::OandaOrder(_) => false,
+            Order::TradierOrder(order) => matches!(order.duration.as_str(), "pre" | "post"),
+        }
+    }
+
+    /// Broker id and stop price of the stop loss leg the order was submitted with, `None` once
+    /// it triggered or was canceled.
+    pub fn stop_loss_leg(&self) -> Option<(Uuid, Decimal)> {
+        match self {
+            Order::AlpacaOrder(order) => order
+                .legs
+                .iter()
+                .filter(|leg| leg.type_ == AlpacaType::Stop)
+                .filter(|leg| {
+                    matches!(
+                        leg.status,
+                        AlpacaStatus::New
+                            | AlpacaStatus::Accepted
+                            | AlpacaStatus::PendingNew
+                            | AlpacaStatus::Held
+                    )
+                })
+                .find_map(|leg| Some((leg.id.0, num_to_decimal(leg.stop_price.as_ref()?)))),
+            Order::OandaOrder(order) => order
+                .stop_loss
+                .as_ref()
+                .filter(|stop_loss| stop_loss.state == "PENDING")
+                .and_then(|stop_loss| Some((order_uuid(&stop_loss.id)?, stop_loss.price))),
+            Order::TradierOrder(order) => order
+                .stop_loss
+                .as_ref()
+                .filter(|stop_loss| matches!(stop_loss.status.as_str(), "open" | "pending"))
+                .and_then(|stop_loss| {
+                    Some((order_uuid(&stop_loss.id.to_string())?, stop_loss.stop_price))
+                }),
+        }
+    }
+
+    /// Whether the stop loss leg of the order executed.
+    pub fn stopped_out(&self) -> bool {
+        match self {
+            Order::AlpacaOrder(order) => order
+                .legs
+                .iter()
+                .any(|leg| leg.type_ == AlpacaType::Stop && leg.status == AlpacaStatus::Filled),
+            Order::OandaOrder(order) => order
+                .stop_loss
+                .as_ref()
+                .is_some_and(|stop_loss| stop_loss.state == "FILLED"),
+            Order::TradierOrder(order) => order
+                .stop_loss
+                .as_ref()
+                .is_some_and(|stop_loss| stop_loss.status == "filled"),
+        }
+    }
+}
+
+impl Position {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Position::AlpacaPosition(position) => &position.symbol,
+            Position::OandaPosition(position) => &position.instrument,
+            Position::TradierPosition(position) => &position.symbol,
+        }
+    }
+
+    /// Quantity held, negative for short positions.
+    pub fn quantity(&self) -> Decimal {
+        match self {
+            Position::AlpacaPosition(position) => num_to_decimal(&position.quantity),
+            Position::OandaPosition(position) => position.units(),
+            Position::TradierPosition(position) => position.quantity,
+        }
+    }
+
+    pub fn average_entry_price(&self) -> Money {
+        match self {
+            Position::AlpacaPosition(position) => {
+                Money::usd(num_to_decimal(&position.average_entry_price))
+            }
+            Position::OandaPosition(position) => Money::new(
+                position.average_price().unwrap_or_default(),
+                quote_currency(&position.instrument),
+            ),
+            Position::TradierPosition(position) => Money::usd(position.average_price()),
+        }
+    }
+
+    pub fn current_price(&self) -> Option<Money> {
+        match self {
+            Position::AlpacaPosition(position) => position
+                .current_price
+                .as_ref()
+                .map(|price| Money::usd(num_to_decimal(price))),
+            // NOTE: OANDA reports the unrealized P&L of positions, not their price
+            Position::OandaPosition(_) => None,
+            // NOTE: Tradier reports the cost basis of positions, their quotes are looked up apart
+            Position::TradierPosition(_) => None,
+        }
+    }
+}
+
+/// Currency prices of a currency pair are quoted in, e.g. `USD` for `EUR_USD`.
+fn quote_currency(instrument: &str) -> &str {
+    instrument
+        .split_once(['_', '/'])
+        .map_or(USD, |(_, quote)| quote)
+}
+
 impl GetBroker for OrdersRequest {
     fn broker(&self) -> Broker {
         match self {
@@ -1,6 +1,19 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
 
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgRow, FromRow, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use super::error::ApiError;
+#[cfg(feature = "openapi")]
+use crate::order::{Fill, OrderRecord};
 
 #[derive(Debug, Deserialize)]
 pub struct PaginationQuery {
@@ -40,3 +53,239 @@ where
         }
     }
 }
+
+/// Items of a list page unless `limit` is set
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// Query of the list endpoints, e.g. `?sort=-created_at&limit=50&ticker=AAPL`. Further pages are
+/// read with the `next_cursor` of the previous page and otherwise the same query, they stay
+/// consistent while new items are added.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct ListParams {
+    /// `next_cursor` of the previous page, the first page when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Items of the page at most, 100 by default and 1000 at most
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// Field to sort by, descending when prefixed with `-`. Newest first when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+    /// Items at or after this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    /// Items before this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<Self>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| ApiError::BadRequest(err.body_text()))?;
+        Ok(params)
+    }
+}
+
+/// Page of a list endpoint.
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(
+    feature = "openapi",
+    aliases(OrderPage = Page<OrderRecord>, FillPage = Page<Fill>)
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page, `None` on the last one
+    pub next_cursor: Option<String>,
+}
+
+/// Table read by a list endpoint. It needs `strategy_id` and `ticker` columns for the filters.
+pub struct ListSource {
+    pub table: &'static str,
+    /// Unique column ordering the items of the same sort field value
+    pub id: &'static str,
+    /// Column `from` and `to` filter on, also the default sort field
+    pub time: &'static str,
+    /// Fields the items can be sorted by, timestamp columns which can't be null
+    pub sort_fields: &'static [&'static str],
+}
+
+/// Items of a list endpoint.
+pub trait Listed: for<'r> FromRow<'r, PgRow> + Send + Unpin {
+    const SOURCE: ListSource;
+
+    fn id(&self) -> Uuid;
+
+    /// Value of the sort field `field`, one of the `sort_fields` of the source
+    fn sort_key(&self, field: &str) -> DateTime<Utc>;
+}
+
+struct Sort {
+    field: &'static str,
+    descending: bool,
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "-{}", self.field)
+        } else {
+            f.write_str(self.field)
+        }
+    }
+}
+
+/// Position after the last item of a page, encoded with the sort it was read with.
+struct Cursor {
+    key: DateTime<Utc>,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self, sort: &Sort) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{sort}|{}|{}",
+            self.key.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.id
+        ))
+    }
+
+    fn decode(cursor: &str, sort: &Sort) -> Result<Self, ApiError> {
+        let invalid = || ApiError::BadRequest("Invalid cursor".to_owned());
+        let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let mut parts = decoded.splitn(3, '|');
+        let (Some(cursor_sort), Some(key), Some(id)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if cursor_sort != sort.to_string() {
+            return Err(ApiError::BadRequest(format!(
+                "Cursor of a page sorted by {cursor_sort}, not {sort}"
+            )));
+        }
+
+        Ok(Self {
+            key: DateTime::parse_from_rfc3339(key)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl ListParams {
+    fn sort(&self, source: &ListSource) -> Result<Sort, ApiError> {
+        let Some(sort) = &self.sort else {
+            return Ok(Sort {
+                field: source.time,
+                descending: true,
+            });
+        };
+        let (name, descending) = match sort.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (sort.as_str(), false),
+        };
+        let field = source
+            .sort_fields
+            .iter()
+            .find(|field| **field == name)
+            .ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Can't sort by {name}, expected one of {}",
+                    source.sort_fields.join(", ")
+                ))
+            })?;
+
+        Ok(Sort { field, descending })
+    }
+
+    /// Page of the items matching the query, of the strategies `strategy_ids` only when set. The
+    /// `strategy_id` filter is taken into account by the scope, see `Caller::scope`.
+    pub async fn fetch<T: Listed>(
+        &self,
+        db: &PgPool,
+        strategy_ids: Option<&[Uuid]>,
+    ) -> Result<Page<T>, ApiError> {
+        let source = T::SOURCE;
+        let sort = self.sort(&source)?;
+        let cursor = self
+            .cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(cursor, &sort))
+            .transpose()?;
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut query =
+            QueryBuilder::<Postgres>::new(format!("SELECT * FROM {} WHERE TRUE", source.table));
+        if let Some(strategy_ids) = strategy_ids {
+            query
+                .push(" AND strategy_id = ANY(")
+                .push_bind(strategy_ids)
+                .push(")");
+        }
+        if let Some(ticker) = &self.ticker {
+            query.push(" AND ticker = ").push_bind(ticker);
+        }
+        if let Some(from) = self.from {
+            query
+                .push(format!(" AND {} >= ", source.time))
+                .push_bind(from);
+        }
+        if let Some(to) = self.to {
+            query.push(format!(" AND {} < ", source.time)).push_bind(to);
+        }
+        // NOTE: the id breaks ties of the sort field, so no item is skipped or read twice
+        if let Some(cursor) = &cursor {
+            let comparison = if sort.descending { "<" } else { ">" };
+            query
+                .push(format!(
+                    " AND ({}, {}) {comparison} (",
+                    sort.field, source.id
+                ))
+                .push_bind(cursor.key)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+        query
+            .push(format!(
+                " ORDER BY {field} {direction}, {id} {direction} LIMIT ",
+                field = sort.field,
+                id = source.id
+            ))
+            .push_bind(limit + 1);
+
+        let mut items: Vec<T> = query.build_query_as().fetch_all(db).await?;
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|item| {
+                Cursor {
+                    key: item.sort_key(sort.field),
+                    id: item.id(),
+                }
+                .encode(&sort)
+            })
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+}
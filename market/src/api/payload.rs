@@ -0,0 +1,302 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+use super::{
+    alert::{BarData, SignalType, TrailStopPrice, WebhookAlertData},
+    error::ApiError,
+    price::Price,
+};
+use crate::{app_config::AppConfig, options::OrderLeg, order::TimeInForce};
+
+/// Schema of a webhook alert, given by the `version` field of the body or by the
+/// `/webhook/alert/:version` path. Bodies without a version are `v1` alerts.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadVersion {
+    /// Legacy alerts, see `WebhookAlertData`
+    #[default]
+    V1,
+    /// Flat signal with the order parameters grouped, see `AlertV2`
+    V2,
+    /// Raw alerts of TradingView strategies, see `TradingViewAlert`
+    TradingView,
+    /// Alerts of any shape read with the `alert_mapping` of their strategy
+    Mapped,
+}
+
+/// Webhook alert in any of the supported schemas.
+#[derive(Debug, Clone)]
+pub enum AlertPayload {
+    V1(WebhookAlertData),
+    V2(AlertV2),
+    TradingView(TradingViewAlert),
+    Mapped(serde_json::Value),
+}
+
+impl AlertPayload {
+    /// Parse the body as an alert of `version`, or of the version named by its `version` field.
+    pub fn from_value(
+        body: serde_json::Value,
+        version: Option<PayloadVersion>,
+    ) -> Result<Self, ApiError> {
+        let version = match version {
+            Some(version) => version,
+            None => match body.get("version") {
+                None => PayloadVersion::default(),
+                Some(version) => version
+                    .as_str()
+                    .and_then(|version| version.parse().ok())
+                    .ok_or_else(|| {
+                        ApiError::BadRequest(format!("Unknown alert payload version - {version}"))
+                    })?,
+            },
+        };
+
+        let invalid = |err: serde_json::Error| {
+            ApiError::BadRequest(format!("Invalid {} alert: {err}", version.as_ref()))
+        };
+        Ok(match version {
+            PayloadVersion::V1 => Self::V1(serde_json::from_value(body).map_err(invalid)?),
+            PayloadVersion::V2 => Self::V2(serde_json::from_value(body).map_err(invalid)?),
+            PayloadVersion::TradingView => {
+                Self::TradingView(serde_json::from_value(body).map_err(invalid)?)
+            }
+            PayloadVersion::Mapped => Self::Mapped(body),
+        })
+    }
+
+    /// Convert the alert into the internal alert every version is validated and traded as.
+    pub fn into_alert_data(self, config: &AppConfig) -> Result<WebhookAlertData, ApiError> {
+        match self {
+            Self::V1(alert_data) => Ok(alert_data),
+            Self::V2(alert) => Ok(alert.into()),
+            Self::TradingView(alert) => alert.try_into(),
+            Self::Mapped(body) => {
+                #[derive(Deserialize)]
+                struct MappedStrategy {
+                    strategy_id: Uuid,
+                }
+
+                let strategy_id = MappedStrategy::deserialize(&body)
+                    .map_err(|err| ApiError::BadRequest(format!("Invalid mapped alert: {err}")))?
+                    .strategy_id;
+                let strategy = config
+                    .strategies
+                    .iter()
+                    .find(|strategy| strategy.id == strategy_id)
+                    .ok_or_else(|| {
+                        ApiError::BadRequest(format!("Unknown strategy - {strategy_id}"))
+                    })?;
+                let mapping = strategy.alert_mapping.as_ref().ok_or_else(|| {
+                    ApiError::BadRequest(format!("Strategy {} has no alert mapping", strategy.name))
+                })?;
+
+                mapping
+                    .map(strategy_id, &body, Utc::now())
+                    .map_err(|err| ApiError::BadRequest(err.to_string()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalAction {
+    OpenLong,
+    OpenShort,
+    StopLossUpdate,
+}
+
+/// Order parameters of a `v2` alert, all of them optional.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OrderParams {
+    pub quantity: Option<Decimal>,
+    pub notional: Option<Decimal>,
+    pub time_in_force: Option<TimeInForce>,
+    pub limit_price: Option<Decimal>,
+    pub extended_hours: Option<bool>,
+    #[serde(default)]
+    pub legs: Vec<OrderLeg>,
+}
+
+// NOTE: `v2` body example:
+// {
+// 	"version": "v2",
+// 	"strategy_id": "C6557FC3-0D9A-447A-9D87-E417D98F2114",
+// 	"time": "{{timenow}}",
+// 	"ticker": "{{ticker}}",
+// 	"exchange": "{{exchange}}",
+// 	"timeframe": "{{interval}}",
+// 	"action": "open_long",
+// 	"stop_loss": "{{plot_0}}",
+// 	"bar": {"time": "{{time}}", "open": "{{open}}", ...},
+// 	"order": {"limit_price": "{{close}}", "time_in_force": "gtc"},
+// 	"comment": "breakout"
+// }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertV2 {
+    #[serde(default)]
+    pub alert_id: Option<String>,
+    pub strategy_id: Uuid,
+    pub time: DateTime<Utc>,
+    pub ticker: String,
+    pub exchange: String,
+    pub timeframe: String,
+    pub action: SignalAction,
+    pub stop_loss: Decimal,
+    #[serde(default)]
+    pub trail_stop_price: Option<Decimal>,
+    pub bar: BarData,
+    #[serde(default)]
+    pub order: OrderParams,
+    /// Free text of the alert, logged with it
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl From<AlertV2> for WebhookAlertData {
+    fn from(alert: AlertV2) -> Self {
+        if let Some(comment) = &alert.comment {
+            tracing::info!("Alert for {} commented: {}", alert.ticker, comment);
+        }
+
+        let stop_loss = TrailStopPrice(alert.stop_loss);
+        Self {
+            alert_id: alert.alert_id,
+            strategy_id: alert.strategy_id,
+            ticker: alert.ticker,
+            timeframe: alert.timeframe,
+            exchange: alert.exchange,
+            signal_type: match alert.action {
+                SignalAction::OpenLong => SignalType::OpenLong(stop_loss),
+                SignalAction::OpenShort => SignalType::OpenShort(stop_loss),
+                SignalAction::StopLossUpdate => SignalType::StopLossUpdate(stop_loss),
+            },
+            trail_stop_price: alert.trail_stop_price,
+            bar_data: alert.bar,
+            time: alert.time,
+            quantity: alert.order.quantity,
+            notional: alert.order.notional,
+            time_in_force: alert.order.time_in_force,
+            limit_price: alert.order.limit_price,
+            extended_hours: alert.order.extended_hours,
+            legs: alert.order.legs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OrderAction {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MarketPosition {
+    Long,
+    Short,
+    Flat,
+}
+
+// NOTE: TradingView strategy alert message example:
+// {
+// 	"version": "tradingview",
+// 	"strategy_id": "C6557FC3-0D9A-447A-9D87-E417D98F2114",
+// 	"alert_id": "{{strategy.order.id}}",
+// 	"ticker": "{{ticker}}",
+// 	"exchange": "{{exchange}}",
+// 	"interval": "{{interval}}",
+// 	"time": "{{timenow}}",
+// 	"bar_time": "{{time}}",
+// 	"open": "{{open}}",
+// 	"high": "{{high}}",
+// 	"low": "{{low}}",
+// 	"close": "{{close}}",
+// 	"volume": "{{volume}}",
+// 	"action": "{{strategy.order.action}}",
+// 	"market_position": "{{strategy.market_position}}",
+// 	"contracts": "{{strategy.order.contracts}}",
+// 	"stop_loss": "{{plot_0}}"
+// }
+
+/// Alert of a TradingView strategy, filled with its `{{strategy.*}}` placeholders. Only entries
+/// are traded, exits and partial closes are rejected.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TradingViewAlert {
+    pub strategy_id: Uuid,
+    #[serde(default)]
+    pub alert_id: Option<String>,
+    pub ticker: String,
+    pub exchange: String,
+    pub interval: String,
+    pub time: DateTime<Utc>,
+    pub bar_time: DateTime<Utc>,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Decimal,
+    pub action: OrderAction,
+    pub market_position: MarketPosition,
+    /// Traded instead of the quantity sized for the strategy when set
+    #[serde(default)]
+    pub contracts: Option<Decimal>,
+    /// Plotted by the script, TradingView has no placeholder for the stop loss of an order
+    pub stop_loss: Decimal,
+}
+
+impl TryFrom<TradingViewAlert> for WebhookAlertData {
+    type Error = ApiError;
+
+    fn try_from(alert: TradingViewAlert) -> Result<Self, Self::Error> {
+        let stop_loss = TrailStopPrice(alert.stop_loss);
+        let signal_type = match (alert.action, alert.market_position) {
+            (OrderAction::Buy, MarketPosition::Long) => SignalType::OpenLong(stop_loss),
+            (OrderAction::Sell, MarketPosition::Short) => SignalType::OpenShort(stop_loss),
+            (action, position) => {
+                return Err(ApiError::BadRequest(format!(
+                    "TradingView {} orders leaving the strategy {} are not supported, only \
+                     entries are",
+                    action.as_ref(),
+                    position.as_ref()
+                )))
+            }
+        };
+
+        Ok(Self {
+            alert_id: alert.alert_id,
+            strategy_id: alert.strategy_id,
+            ticker: alert.ticker,
+            timeframe: alert.interval,
+            exchange: alert.exchange,
+            signal_type,
+            trail_stop_price: None,
+            bar_data: BarData {
+                time: alert.bar_time,
+                open: alert.open,
+                high: alert.high,
+                low: alert.low,
+                close: alert.close,
+                volume: alert.volume,
+            },
+            time: alert.time,
+            quantity: alert.contracts,
+            notional: None,
+            time_in_force: None,
+            limit_price: None,
+            extended_hours: None,
+            legs: Vec::new(),
+        })
+    }
+}
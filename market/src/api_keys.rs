@@ -0,0 +1,148 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool};
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+use crate::users::Caller;
+
+/// Permissions of an API key. Every role includes the permissions of the roles before it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    ReadOnly,
+    Trade,
+    Admin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub api_key_id: Uuid,
+    pub name: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    pub role: String,
+    /// User the key sees the data of, every user for operator keys
+    pub user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub role: Role,
+    /// User the key is issued for, an operator key when unset
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+/// Newly created key. The secret is only returned once, the database keeps its hash.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub secret: String,
+}
+
+/// Role a route requires. Admin routes manage the service, trade routes change broker state and
+/// everything else only reads.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    let segment = path
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    match (segment, method) {
+        ("api-keys" | "broker-cache" | "reconcile" | "usage" | "users", _) => Role::Admin,
+        ("feature-flags" | "strategies", &Method::PUT) | ("strategies", &Method::POST) => {
+            Role::Admin
+        }
+        ("dead-letters", &Method::POST) => Role::Trade,
+        // NOTE: commands of the control channel requiring more check the role themselves
+        ("control", _) => Role::Trade,
+        ("order" | "position", &Method::DELETE | &Method::PATCH) => Role::Trade,
+        ("order" | "rebalance", &Method::POST) => Role::Trade,
+        _ => Role::ReadOnly,
+    }
+}
+
+fn hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+pub async fn create(db: &PgPool, new_key: &NewApiKey) -> Result<CreatedApiKey, sqlx::Error> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = hex::encode(bytes);
+
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_keys (api_key_id, name, key_hash, role, user_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(&new_key.name)
+    .bind(hash(&secret))
+    .bind(new_key.role.as_ref())
+    .bind(&new_key.user_id)
+    .fetch_one(db)
+    .await?;
+
+    Ok(CreatedApiKey { api_key, secret })
+}
+
+/// Keys of user `user_id`, keys of every user when `None`.
+pub async fn list(db: &PgPool, user_id: Option<&str>) -> Result<Vec<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE $1::text IS NULL OR user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Returns `None` when the key doesn't exist, is already revoked or belongs to a user other than
+/// `user_id`.
+pub async fn revoke(
+    db: &PgPool,
+    api_key_id: Uuid,
+    user_id: Option<&str>,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(
+        r#"
+        UPDATE api_keys SET revoked_at = NOW()
+        WHERE api_key_id = $1 AND revoked_at IS NULL AND ($2::text IS NULL OR user_id = $2)
+        RETURNING *
+        "#,
+    )
+    .bind(api_key_id)
+    .bind(user_id)
+    .fetch_optional(db)
+    .await
+}
+
+/// Role and user of an active key with the given secret.
+pub async fn find_caller(db: &PgPool, secret: &str) -> Result<Option<Caller>, sqlx::Error> {
+    let key: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT role, user_id FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+    )
+    .bind(hash(secret))
+    .fetch_optional(db)
+    .await?;
+
+    Ok(key.and_then(|(role, user_id)| {
+        Some(Caller {
+            role: role.parse().ok()?,
+            user_id,
+        })
+    }))
+}
@@ -1,48 +1,808 @@
-use std::env;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env, fmt,
+    net::SocketAddr,
+    time::Duration,
+};
 
-use config::{Config, ConfigError, File};
-use serde::Deserialize;
+use chrono::NaiveTime;
+use config::{Config, ConfigError, Environment, File};
+use ipnet::IpNet;
+use jsonwebtoken::Algorithm;
+use reqwest::Url;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use strum_macros::AsRefStr;
+use thiserror::Error as ThisError;
 
-use crate::strategy::Strategy;
+use crate::{
+    alert_writer::{WriterConfig, MAX_BATCH_SIZE},
+    api::objects::{Broker, USD},
+    api_keys::Role,
+    cooldown::Cooldown,
+    corporate_actions::CorporateActionsConfig,
+    executions::Kafka,
+    export::Signing,
+    fill_model::FillModel,
+    filters::SignalFilter,
+    fx::{self, FxConfig},
+    leases::Leases,
+    logging::LoggingConfig,
+    market_data::Feed,
+    notifications::Channel,
+    order::{OrderTtl, PartialFills, TimeInForce, CRYPTO_TIME_IN_FORCE},
+    precision::PrecisionConfig,
+    rate_limit::RateLimit,
+    recorder::Recording,
+    risk::DuplicatePositions,
+    secrets::Secrets,
+    signal_source::{SignalSourceConfig, SourceKind},
+    stops::TrailingStop,
+    strategy::{CurrencyType, Strategy},
+    trade_executor::ExecutorConfig,
+};
+
+const BROKER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+pub const ALPACA_PAPER_BASE_URL: &str = "https://paper-api.alpaca.markets";
+pub const ALPACA_LIVE_BASE_URL: &str = "https://api.alpaca.markets";
+pub const OANDA_PRACTICE_BASE_URL: &str = "https://api-fxpractice.oanda.com";
+pub const OANDA_LIVE_BASE_URL: &str = "https://api-fxtrade.oanda.com";
+pub const TRADIER_SANDBOX_BASE_URL: &str = "https://sandbox.tradier.com";
+pub const TRADIER_LIVE_BASE_URL: &str = "https://api.tradier.com";
+/// Orders per symbol and minute of live trading when no throttle is configured
+const LIVE_ORDERS_PER_SYMBOL_PER_MINUTE: usize = 5;
+/// Orders per strategy and hour of live trading when no throttle is configured
+const LIVE_ORDERS_PER_STRATEGY_PER_HOUR: usize = 20;
+/// Drawdown halting live trading when no level of the escalation ladder does, 20%
+const LIVE_KILL_SWITCH_DRAWDOWN: Decimal = Decimal::from_parts(2, 0, 0, false, 1);
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Database {
     pub url: String,
+    /// Connections the pool opens at most
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// Connections the pool keeps open while idle
+    #[serde(default = "default_min_connections")]
+    pub min_connections: u32,
+    /// Seconds to wait for a free connection of the pool
+    #[serde(default = "default_acquire_timeout")]
+    pub acquire_timeout: u64,
+    /// Seconds a statement may run before the database cancels it, unlimited when not set
+    #[serde(default)]
+    pub statement_timeout: Option<u64>,
+    /// Milliseconds a statement may run before it's logged as slow
+    #[serde(default = "default_slow_statement_threshold")]
+    pub slow_statement_threshold: u64,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_min_connections() -> u32 {
+    1
+}
+
+fn default_acquire_timeout() -> u64 {
+    5
+}
+
+fn default_slow_statement_threshold() -> u64 {
+    1000
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Brokers {
     pub alpaca: Alpaca,
+    /// Account forex strategies trade on, see `CurrencyType::Forex`
+    #[serde(default)]
+    pub oanda: Option<Oanda>,
+    /// Account strategies trade equities and their options on when their broker is tradier
+    #[serde(default)]
+    pub tradier: Option<Tradier>,
+    /// Credential sets of strategies trading on their own accounts, by the name strategies
+    /// reference them with, see `Strategy::account`
+    #[serde(default)]
+    pub accounts: BTreeMap<String, BrokerAccount>,
+    /// Credential set of `accounts` each family of strategies trades with, by family name, see
+    /// `Strategy::family`
+    #[serde(default)]
+    pub families: BTreeMap<String, String>,
+}
+
+/// Credentials of a broker account next to the global one of its broker.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "broker", rename_all = "lowercase")]
+pub enum BrokerAccount {
+    Alpaca(Alpaca),
+}
+
+impl BrokerAccount {
+    pub fn broker(&self) -> Broker {
+        match self {
+            BrokerAccount::Alpaca(_) => Broker::Alpaca,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Alpaca {
     pub apca_api_key_id: String,
     pub apca_api_secret_key: String,
-    pub apca_api_base_url: String,
+    /// Overrides the API of the environment, see `base_url`
+    #[serde(default)]
+    pub apca_api_base_url: Option<String>,
+    /// Request budget of the account, see `RequestScheduler`
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u32,
+    /// Currency the account is denominated in, its P&L is converted from it for reporting
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_requests_per_minute() -> u32 {
+    // Documented limit of the trading API
+    200
+}
+
+fn default_currency() -> String {
+    USD.to_owned()
+}
+
+impl Alpaca {
+    /// Missing credentials and a base url which isn't one of the environment, `field` is the
+    /// path of the credentials in the configuration.
+    fn violations(&self, field: &str, environment: TradingEnvironment) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+        for (name, value) in [
+            ("apca_api_key_id", &self.apca_api_key_id),
+            ("apca_api_secret_key", &self.apca_api_secret_key),
+        ] {
+            if value.trim().is_empty() {
+                violations.push(ConfigViolation::new(
+                    format!("{field}.{name}"),
+                    "is missing",
+                ));
+            }
+        }
+        if !fx::is_currency_code(&self.currency) {
+            violations.push(ConfigViolation::new(
+                format!("{field}.currency"),
+                format!("{} is not an ISO 4217 currency code", self.currency),
+            ));
+        }
+        match Url::parse(self.base_url(environment)) {
+            Ok(url) => {
+                // Guard against trading live with a paper configuration and the other way round
+                let (expected, other) = match environment {
+                    TradingEnvironment::Paper => (ALPACA_PAPER_BASE_URL, ALPACA_LIVE_BASE_URL),
+                    TradingEnvironment::Live => (ALPACA_LIVE_BASE_URL, ALPACA_PAPER_BASE_URL),
+                };
+                let other_host = Url::parse(other).ok();
+                let other_host = other_host.as_ref().and_then(Url::host_str);
+                if url.host_str().is_some() && url.host_str() == other_host {
+                    violations.push(ConfigViolation::new(
+                        format!("{field}.apca_api_base_url"),
+                        format!(
+                            "is not an API of the {} environment, expected {expected}",
+                            environment.as_ref()
+                        ),
+                    ));
+                }
+            }
+            Err(err) => violations.push(ConfigViolation::new(
+                format!("{field}.apca_api_base_url"),
+                format!("is not a valid url, {err}"),
+            )),
+        }
+        violations
+    }
+
+    /// Configured base url, the paper or live API of the environment when unset.
+    pub fn base_url(&self, environment: TradingEnvironment) -> &str {
+        self.apca_api_base_url
+            .as_deref()
+            .unwrap_or(match environment {
+                TradingEnvironment::Paper => ALPACA_PAPER_BASE_URL,
+                TradingEnvironment::Live => ALPACA_LIVE_BASE_URL,
+            })
+    }
+}
+
+/// Credentials of an OANDA account, trading currency pairs over the v20 REST API.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Oanda {
+    pub account_id: String,
+    /// Personal access token of the account
+    pub api_token: String,
+    /// Overrides the API of the environment, see `base_url`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Request budget of the account, see `RequestScheduler`
+    #[serde(default = "default_oanda_requests_per_minute")]
+    pub requests_per_minute: u32,
+    /// Home currency of the account, pip values are converted to it when sizing
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_oanda_requests_per_minute() -> u32 {
+    // Documented limit of 100 requests per second, kept well below for the shared connection
+    1200
+}
+
+impl Oanda {
+    /// Configured base url, the practice or live API of the environment when unset.
+    pub fn base_url(&self, environment: TradingEnvironment) -> &str {
+        self.base_url.as_deref().unwrap_or(match environment {
+            TradingEnvironment::Paper => OANDA_PRACTICE_BASE_URL,
+            TradingEnvironment::Live => OANDA_LIVE_BASE_URL,
+        })
+    }
+
+    /// Missing credentials and a base url of the other environment, see `Alpaca::violations`.
+    fn violations(&self, field: &str, environment: TradingEnvironment) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+        for (name, value) in [
+            ("account_id", &self.account_id),
+            ("api_token", &self.api_token),
+        ] {
+            if value.trim().is_empty() {
+                violations.push(ConfigViolation::new(
+                    format!("{field}.{name}"),
+                    "is missing",
+                ));
+            }
+        }
+        if !fx::is_currency_code(&self.currency) {
+            violations.push(ConfigViolation::new(
+                format!("{field}.currency"),
+                format!("{} is not an ISO 4217 currency code", self.currency),
+            ));
+        }
+        let other = match environment {
+            TradingEnvironment::Paper => OANDA_LIVE_BASE_URL,
+            TradingEnvironment::Live => OANDA_PRACTICE_BASE_URL,
+        };
+        violations.extend(base_url_violation(
+            format!("{field}.base_url"),
+            self.base_url(environment),
+            other,
+            environment,
+        ));
+        violations
+    }
+}
+
+/// Credentials of a Tradier brokerage account, trading equities and single-leg options.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Tradier {
+    pub account_id: String,
+    /// Access token of the account, the sandbox has tokens of its own
+    pub access_token: String,
+    /// Overrides the API of the environment, see `base_url`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Request budget of the account, see `RequestScheduler`
+    #[serde(default = "default_tradier_requests_per_minute")]
+    pub requests_per_minute: u32,
+}
+
+fn default_tradier_requests_per_minute() -> u32 {
+    // Documented limit of the account and trading endpoints, the sandbox allows half of it
+    120
+}
+
+impl Tradier {
+    /// Configured base url, the sandbox or live API of the environment when unset.
+    pub fn base_url(&self, environment: TradingEnvironment) -> &str {
+        self.base_url.as_deref().unwrap_or(match environment {
+            TradingEnvironment::Paper => TRADIER_SANDBOX_BASE_URL,
+            TradingEnvironment::Live => TRADIER_LIVE_BASE_URL,
+        })
+    }
+
+    /// Missing credentials and a base url of the other environment, see `Alpaca::violations`.
+    fn violations(&self, field: &str, environment: TradingEnvironment) -> Vec<ConfigViolation> {
+        let mut violations = Vec::new();
+        for (name, value) in [
+            ("account_id", &self.account_id),
+            ("access_token", &self.access_token),
+        ] {
+            if value.trim().is_empty() {
+                violations.push(ConfigViolation::new(
+                    format!("{field}.{name}"),
+                    "is missing",
+                ));
+            }
+        }
+        let other = match environment {
+            TradingEnvironment::Paper => TRADIER_LIVE_BASE_URL,
+            TradingEnvironment::Live => TRADIER_SANDBOX_BASE_URL,
+        };
+        violations.extend(base_url_violation(
+            format!("{field}.base_url"),
+            self.base_url(environment),
+            other,
+            environment,
+        ));
+        violations
+    }
+}
+
+/// Violation of a `base_url` which is invalid or the API of `other`, the environment not traded.
+fn base_url_violation(
+    field: String,
+    base_url: &str,
+    other: &str,
+    environment: TradingEnvironment,
+) -> Option<ConfigViolation> {
+    let other_host = Url::parse(other).ok();
+    let other_host = other_host.as_ref().and_then(Url::host_str);
+    match Url::parse(base_url) {
+        Ok(url) if url.host_str().is_some() && url.host_str() == other_host => {
+            Some(ConfigViolation::new(
+                field,
+                format!("is not an API of the {} environment", environment.as_ref()),
+            ))
+        }
+        Ok(_) => None,
+        Err(err) => Some(ConfigViolation::new(
+            field,
+            format!("is not a valid url, {err}"),
+        )),
+    }
+}
+
+/// Whether orders trade with real money. Paper trading is the default, so live trading always
+/// has to be chosen explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum TradingEnvironment {
+    #[default]
+    Paper,
+    Live,
+}
+
+/// Usage limits of a tenant. Missing values mean no limit.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Quotas {
+    pub signals_per_day: Option<i64>,
+    pub max_strategies: Option<usize>,
+    pub max_brokers: Option<usize>,
+}
+
+/// Limits applied to orders of all strategies together. Missing values mean no limit. Only
+/// orders entering a position count, the ones of signals and the chunks of their entries, exits
+/// are never held back. Each instance counts the orders it places on its own, with several
+/// instances the limits apply per instance.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Throttle {
+    pub orders_per_symbol_per_minute: Option<usize>,
+    /// Applied to every strategy on its own
+    pub orders_per_strategy_per_hour: Option<usize>,
+}
+
+/// Loss limits of a UTC day, see `Strategy::max_daily_loss` for the limits of strategies.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DailyLoss {
+    /// Decline of the account equity since the first snapshot of the day tripping the kill
+    /// switch
+    pub max_loss: Option<Decimal>,
+    /// Channels notified when the account or a strategy reaches its limit
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+}
+
+/// Caps of the notional exposure per symbol, summed over the positions and open orders of all
+/// strategies. Missing values mean no limit.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ExposureLimits {
+    /// Cap of symbols without their own one
+    pub max_notional_per_symbol: Option<Decimal>,
+    /// Caps keyed by symbol
+    #[serde(default)]
+    pub symbols: HashMap<String, Decimal>,
+}
+
+impl ExposureLimits {
+    pub fn limit_for(&self, ticker: &str) -> Option<Decimal> {
+        self.symbols
+            .get(ticker)
+            .copied()
+            .or(self.max_notional_per_symbol)
+    }
+}
+
+/// Risk budget shared by the strategies grouped into a portfolio, see `Strategy::portfolio`.
+/// Missing values mean no limit.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RiskBudget {
+    /// Cap of the notional exposure of the strategies together, summed over the positions and
+    /// open orders in all their symbols
+    pub max_exposure: Option<Decimal>,
+    /// Loss of a UTC day of the strategies together, realized and unrealized, disabling all of
+    /// them for the rest of the day
+    pub max_daily_loss: Option<Decimal>,
+}
+
+/// Sources webhook alerts are accepted from. Alerts from any address are accepted when
+/// `allowed_ips` is empty.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Webhook {
+    #[serde(default)]
+    pub allowed_ips: Vec<IpNet>,
+    /// Take the client address from `X-Forwarded-For` set by a reverse proxy in front of the app
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// Seconds within which a repeated alert is considered a duplicate
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: u64,
+    /// Seconds an alert may be older than the server time
+    #[serde(default = "default_max_alert_age")]
+    pub max_alert_age: u64,
+    /// Seconds an alert may be ahead of the server time
+    #[serde(default = "default_max_clock_skew")]
+    pub max_clock_skew: u64,
+    /// Seconds the `timestamp` of a signed alert may differ from the server time. Its `nonce` is
+    /// remembered for twice as long, so a captured alert can't be posted again
+    #[serde(default = "default_max_signature_skew")]
+    pub max_signature_skew: u64,
+    /// Alert producers other than TradingView, each served at `/webhook/source/:name`
+    #[serde(default)]
+    pub sources: Vec<SignalSourceConfig>,
+    /// Buffered writes of received alerts to the `alerts` table
+    #[serde(default)]
+    pub persistence: WriterConfig,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self {
+            allowed_ips: Vec::new(),
+            trust_forwarded_for: false,
+            dedup_window: default_dedup_window(),
+            max_alert_age: default_max_alert_age(),
+            max_clock_skew: default_max_clock_skew(),
+            max_signature_skew: default_max_signature_skew(),
+            sources: Vec::new(),
+            persistence: WriterConfig::default(),
+        }
+    }
+}
+
+fn default_dedup_window() -> u64 {
+    300
+}
+
+fn default_max_alert_age() -> u64 {
+    300
+}
+
+fn default_max_clock_skew() -> u64 {
+    60
+}
+
+fn default_max_signature_skew() -> u64 {
+    300
+}
+
+/// Identity provider whose tokens are accepted as an alternative to API keys.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Jwt {
+    pub issuer: String,
+    pub jwks_url: String,
+    /// Audience tokens must be issued for, not validated when unset
+    pub audience: Option<String>,
+    /// Claim listing the roles of the token, a string or an array of strings
+    #[serde(default = "default_role_claim")]
+    pub role_claim: String,
+    /// Roles granted for identity provider roles. Claim values are read as roles when empty
+    #[serde(default)]
+    pub roles: HashMap<String, Role>,
+    /// Claim naming the user whose data the token sees, tokens see the data of every user when
+    /// unset
+    #[serde(default)]
+    pub user_claim: Option<String>,
+    /// Signing algorithms accepted for keys of the JWKS which don't name their own
+    #[serde(default = "default_jwt_algorithms")]
+    pub algorithms: Vec<Algorithm>,
+}
+
+fn default_role_claim() -> String {
+    "role".to_string()
+}
+
+fn default_jwt_algorithms() -> Vec<Algorithm> {
+    vec![Algorithm::RS256]
+}
+
+/// Regular trading session in UTC. Exposure held outside of it counts as overnight or weekend
+/// exposure.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Session {
+    #[serde(default = "default_session_open")]
+    pub open: NaiveTime,
+    #[serde(default = "default_session_close")]
+    pub close: NaiveTime,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            open: default_session_open(),
+            close: default_session_close(),
+        }
+    }
+}
+
+fn default_session_open() -> NaiveTime {
+    NaiveTime::from_hms_opt(13, 30, 0).unwrap_or_default()
+}
+
+fn default_session_close() -> NaiveTime {
+    NaiveTime::from_hms_opt(20, 0, 0).unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Telegram {
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Pushover {
+    pub api_token: String,
+    pub user_key: String,
+}
+
+/// Twilio account SMS are sent with.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Sms {
+    pub account_sid: String,
+    pub auth_token: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Step of the drawdown escalation ladder, reached once the portfolio drawdown is at least
+/// `drawdown` (a fraction of the equity peak).
+#[derive(Debug, Deserialize, Clone)]
+pub struct EscalationLevel {
+    pub drawdown: Decimal,
+    pub channels: Vec<Channel>,
+    /// Halt trading once the level is reached
+    #[serde(default)]
+    pub kill_switch: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Notifications {
+    pub slack_webhook_url: Option<String>,
+    pub telegram: Option<Telegram>,
+    pub pushover: Option<Pushover>,
+    pub sms: Option<Sms>,
+    #[serde(default)]
+    pub escalation: Vec<EscalationLevel>,
+    /// Channels told when a strategy is enabled or disabled at runtime
+    #[serde(default)]
+    pub strategy_switches: Vec<Channel>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Cache {
+    /// Seconds broker lookups are cached for
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: u64,
+    /// Share the cache through Redis instead of keeping it in memory, requires the `redis` feature
+    pub redis_url: Option<String>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            ttl: default_cache_ttl(),
+            redis_url: None,
+        }
+    }
+}
+
+fn default_cache_ttl() -> u64 {
+    30
+}
+
+/// Bus the live events of `/ws` are published on. Instances sharing a Redis or NATS bus see each
+/// other's events, an in-memory bus only the events of its instance.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum Events {
+    #[default]
+    InMemory,
+    /// Redis stream, requires the `redis` feature
+    Redis {
+        url: String,
+        #[serde(default = "default_events_stream")]
+        stream: String,
+    },
+    /// NATS subject, requires the `nats` feature
+    Nats {
+        url: String,
+        #[serde(default = "default_events_subject")]
+        subject: String,
+    },
+}
+
+fn default_events_stream() -> String {
+    "market:events".to_owned()
+}
+
+fn default_events_subject() -> String {
+    "market.events".to_owned()
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MarketData {
+    #[serde(default)]
+    pub feed: Feed,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Grpc {
+    pub addr: SocketAddr,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Export {
+    /// Key export signatures are made with, exports can't be signed when unset
+    pub signing: Option<Signing>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Snapshots {
+    /// Seconds between two portfolio snapshots
+    #[serde(default = "default_snapshot_interval")]
+    pub interval: u64,
+}
+
+impl Default for Snapshots {
+    fn default() -> Self {
+        Self {
+            interval: default_snapshot_interval(),
+        }
+    }
+}
+
+fn default_snapshot_interval() -> u64 {
+    300
+}
+
+/// End of day summary of trades, P&L, risk violations and failed signals, see `reports`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Reports {
+    /// UTC time the report of the day is compiled at
+    #[serde(default = "default_report_time")]
+    pub time: NaiveTime,
+    /// Channels the report is sent to, it's only stored when empty
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+}
+
+fn default_report_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(20, 30, 0).unwrap_or_default()
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
+    /// Trade on paper or live accounts, orders and logs are stamped with it
+    #[serde(default)]
+    pub environment: TradingEnvironment,
+    /// Bootstrap admin key, further keys are managed through `/api-keys`
     pub api_key: String,
     pub database: Database,
     pub brokers: Brokers,
     pub strategies: Vec<Strategy>,
+    /// Quotas keyed by tenant id
+    #[serde(default)]
+    pub quotas: HashMap<String, Quotas>,
+    #[serde(default)]
+    pub snapshots: Snapshots,
+    /// Compile a daily summary report at the end of every day
+    #[serde(default)]
+    pub reports: Option<Reports>,
+    #[serde(default)]
+    pub throttle: Throttle,
+    #[serde(default)]
+    pub daily_loss: DailyLoss,
+    #[serde(default)]
+    pub exposure_limits: ExposureLimits,
+    /// Risk budgets keyed by portfolio, see `Strategy::portfolio`
+    #[serde(default)]
+    pub portfolios: BTreeMap<String, RiskBudget>,
+    #[serde(default)]
+    pub webhook: Webhook,
+    #[serde(default)]
+    pub session: Session,
+    #[serde(default)]
+    pub notifications: Notifications,
+    /// Rate limits per API key, keyed by route group
+    #[serde(default)]
+    pub rate_limits: HashMap<String, RateLimit>,
+    /// Execution model of simulated orders in paper mode
+    #[serde(default)]
+    pub paper: FillModel,
+    #[serde(default)]
+    pub export: Export,
+    /// Cache of broker account, asset and clock lookups
+    #[serde(default)]
+    pub cache: Cache,
+    /// Bus of the live events, shared between instances unless it's kept in memory
+    #[serde(default)]
+    pub events: Events,
+    /// Publish the fills and position changes of the strategies to Kafka, requires the `kafka`
+    /// feature
+    #[serde(default)]
+    pub kafka: Option<Kafka>,
+    /// Leases keeping instances sharing the database from processing signals of a strategy at
+    /// the same time
+    #[serde(default)]
+    pub leases: Leases,
+    /// Queues and workers processing the trade signals of every broker
+    #[serde(default)]
+    pub executor: ExecutorConfig,
+    /// Exchange rates P&L of accounts in different currencies is aggregated with
+    #[serde(default)]
+    pub fx: FxConfig,
+    /// Price and quantity increments orders are rounded to before they're submitted
+    #[serde(default)]
+    pub precision: PrecisionConfig,
+    /// Accept identity provider tokens next to API keys
+    #[serde(default)]
+    pub jwt: Option<Jwt>,
+    /// Record broker interactions of the core for debugging
+    #[serde(default)]
+    pub recording: Option<Recording>,
+    /// Format, levels and destinations of the logs
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Serve the gRPC API next to the REST API, requires the `grpc` feature
+    #[serde(default)]
+    pub grpc: Option<Grpc>,
+    /// Stream live quotes of strategy symbols, used for sizing and shadow executions
+    #[serde(default)]
+    pub market_data: Option<MarketData>,
+    /// Stream order updates of the global Alpaca account, so executions are recorded as they
+    /// happen instead of at the next order sync
+    #[serde(default)]
+    pub trade_updates: bool,
+    /// Load credentials from a secrets manager instead of the configuration files
+    #[serde(default)]
+    pub secrets: Option<Secrets>,
+    /// Adjust stored positions, orders and bars to splits and symbol changes
+    #[serde(default)]
+    pub corporate_actions: Option<CorporateActionsConfig>,
 }
 
 impl AppConfig {
+    /// Configuration of `market/config`, overridden by `MARKET__` prefixed environment variables.
+    /// Nested keys are separated by `__` as well, e.g. `MARKET__DATABASE__URL`.
     pub fn build() -> Result<Self, ConfigError> {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
 
         let config = Config::builder()
             .add_source(File::with_name("market/config/default"))
             .add_source(File::with_name(&format!("market/config/{}", run_mode)).required(false))
+            .add_source(environment())
             .build()?;
 
         println!("debug: {:?}", config.get_bool("debug"));
         println!("database: {:?}", config.get::<String>("database.url"));
 
-        config.try_deserialize()
+        config
+            .try_deserialize()
+            .map(Self::with_environment_defaults)
+            .map(Self::with_family_accounts)
     }
 
     pub fn build_for_test() -> Result<Self, ConfigError> {
@@ -51,11 +811,876 @@ impl AppConfig {
         let config = Config::builder()
             .add_source(File::with_name("../market/config/default"))
             .add_source(File::with_name(&format!("../market/config/{}", run_mode)).required(false))
+            .add_source(environment())
             .build()?;
 
         println!("debug: {:?}", config.get_bool("debug"));
         println!("database: {:?}", config.get::<String>("database.url"));
 
-        config.try_deserialize()
+        config
+            .try_deserialize()
+            .map(Self::with_environment_defaults)
+            .map(Self::with_family_accounts)
+    }
+
+    /// Stricter risk settings of live trading, applied where nothing is configured.
+    fn with_environment_defaults(mut self) -> Self {
+        if self.environment == TradingEnvironment::Live {
+            self.throttle
+                .orders_per_symbol_per_minute
+                .get_or_insert(LIVE_ORDERS_PER_SYMBOL_PER_MINUTE);
+            self.throttle
+                .orders_per_strategy_per_hour
+                .get_or_insert(LIVE_ORDERS_PER_STRATEGY_PER_HOUR);
+            if !self
+                .notifications
+                .escalation
+                .iter()
+                .any(|level| level.kill_switch)
+            {
+                self.notifications.escalation.push(EscalationLevel {
+                    drawdown: LIVE_KILL_SWITCH_DRAWDOWN,
+                    channels: Vec::new(),
+                    kill_switch: true,
+                });
+            }
+        }
+        self
+    }
+
+    /// Credential sets of the families of strategies without an account of their own.
+    fn with_family_accounts(mut self) -> Self {
+        for strategy in &mut self.strategies {
+            if strategy.account.is_none() {
+                strategy.account = strategy
+                    .family
+                    .as_ref()
+                    .and_then(|family| self.brokers.families.get(family))
+                    .cloned();
+            }
+        }
+        self
+    }
+
+    /// Currency of the account the strategy trades with, see `Strategy::account`.
+    pub fn currency(&self, strategy: &Strategy) -> &str {
+        self.account_currency(&strategy.broker, strategy.account.as_deref())
+    }
+
+    /// Currency of the credential set `account` of the broker, the global one when `None`.
+    pub fn account_currency(&self, broker: &Broker, account: Option<&str>) -> &str {
+        match account.and_then(|account| self.brokers.accounts.get(account)) {
+            Some(BrokerAccount::Alpaca(alpaca)) => &alpaca.currency,
+            None => match broker {
+                Broker::Alpaca => &self.brokers.alpaca.currency,
+                Broker::Oanda => self
+                    .brokers
+                    .oanda
+                    .as_ref()
+                    .map_or(USD, |oanda| &oanda.currency),
+                Broker::Tradier => USD,
+            },
+        }
+    }
+
+    /// Violation of `field` referencing `broker` when it has no credentials configured.
+    fn broker_violation(&self, field: String, broker: &Broker) -> Option<ConfigViolation> {
+        match broker {
+            Broker::Alpaca => None,
+            Broker::Oanda => self.brokers.oanda.is_none().then(|| {
+                ConfigViolation::new(field, "is oanda but brokers.oanda is not configured")
+            }),
+            Broker::Tradier => self.brokers.tradier.is_none().then(|| {
+                ConfigViolation::new(field, "is tradier but brokers.tradier is not configured")
+            }),
+        }
+    }
+
+    /// Check the configuration and its strategies for mistakes deserialization doesn't catch, so
+    /// they're reported at boot instead of at the first trade. All violations are reported, not
+    /// only the first one.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        let mut violations = Vec::new();
+
+        for (field, value) in [
+            ("api_key", &self.api_key),
+            ("database.url", &self.database.url),
+        ] {
+            if value.trim().is_empty() {
+                violations.push(ConfigViolation::new(field, "is missing"));
+            }
+        }
+        if self.database.max_connections == 0 {
+            violations.push(ConfigViolation::new(
+                "database.max_connections",
+                "must be positive",
+            ));
+        }
+        if self.database.min_connections > self.database.max_connections {
+            violations.push(ConfigViolation::new(
+                "database.min_connections",
+                format!(
+                    "must not exceed database.max_connections of {}",
+                    self.database.max_connections
+                ),
+            ));
+        }
+        if self.database.statement_timeout == Some(0) {
+            violations.push(ConfigViolation::new(
+                "database.statement_timeout",
+                "must be positive, leave it out for no timeout",
+            ));
+        }
+        violations.extend(
+            self.brokers
+                .alpaca
+                .violations("brokers.alpaca", self.environment),
+        );
+        for (name, account) in &self.brokers.accounts {
+            match account {
+                BrokerAccount::Alpaca(alpaca) => violations.extend(
+                    alpaca.violations(&format!("brokers.accounts.{name}"), self.environment),
+                ),
+            }
+        }
+        if let Some(oanda) = &self.brokers.oanda {
+            violations.extend(oanda.violations("brokers.oanda", self.environment));
+        }
+        if let Some(tradier) = &self.brokers.tradier {
+            violations.extend(tradier.violations("brokers.tradier", self.environment));
+        }
+        for (family, account) in &self.brokers.families {
+            if !self.brokers.accounts.contains_key(account) {
+                violations.push(ConfigViolation::new(
+                    format!("brokers.families.{family}"),
+                    format!("{account} is not a credential set of brokers.accounts"),
+                ));
+            }
+        }
+
+        if self
+            .daily_loss
+            .max_loss
+            .is_some_and(|max_loss| max_loss <= Decimal::ZERO)
+        {
+            violations.push(ConfigViolation::new(
+                "daily_loss.max_loss",
+                "must be positive",
+            ));
+        }
+
+        if self
+            .exposure_limits
+            .max_notional_per_symbol
+            .is_some_and(|limit| limit <= Decimal::ZERO)
+        {
+            violations.push(ConfigViolation::new(
+                "exposure_limits.max_notional_per_symbol",
+                "must be positive",
+            ));
+        }
+        for (ticker, limit) in &self.exposure_limits.symbols {
+            if *limit <= Decimal::ZERO {
+                violations.push(ConfigViolation::new(
+                    format!("exposure_limits.symbols.{ticker}"),
+                    "must be positive",
+                ));
+            }
+        }
+
+        for (name, budget) in &self.portfolios {
+            for (limit, value) in [
+                ("max_exposure", budget.max_exposure),
+                ("max_daily_loss", budget.max_daily_loss),
+            ] {
+                if value.is_some_and(|value| value <= Decimal::ZERO) {
+                    violations.push(ConfigViolation::new(
+                        format!("portfolios.{name}.{limit}"),
+                        "must be positive",
+                    ));
+                }
+            }
+        }
+
+        for (field, err) in self.logging.invalid_levels() {
+            violations.push(ConfigViolation::new(field, err));
+        }
+
+        if self.leases.ttl == 0 {
+            violations.push(ConfigViolation::new("leases.ttl", "must be positive"));
+        }
+
+        if self.executor.capacity == 0 {
+            violations.push(ConfigViolation::new(
+                "executor.capacity",
+                "must be positive",
+            ));
+        }
+        if self.executor.concurrency == 0 {
+            violations.push(ConfigViolation::new(
+                "executor.concurrency",
+                "must be positive",
+            ));
+        }
+        if self.executor.exit_capacity == 0 {
+            violations.push(ConfigViolation::new(
+                "executor.exit_capacity",
+                "must be positive",
+            ));
+        }
+        if self.executor.exit_concurrency == 0 {
+            violations.push(ConfigViolation::new(
+                "executor.exit_concurrency",
+                "must be positive",
+            ));
+        }
+        for (broker, concurrency) in &self.executor.brokers {
+            if *concurrency == 0 {
+                violations.push(ConfigViolation::new(
+                    format!("executor.brokers.{broker}"),
+                    "must be positive",
+                ));
+            }
+        }
+
+        if !fx::is_currency_code(&self.fx.base) {
+            violations.push(ConfigViolation::new(
+                "fx.base",
+                format!("{} is not an ISO 4217 currency code", self.fx.base),
+            ));
+        }
+        if let Some(url) = &self.fx.url {
+            if let Err(err) = Url::parse(url) {
+                violations.push(ConfigViolation::new(
+                    "fx.url",
+                    format!("is not a valid url, {err}"),
+                ));
+            }
+        }
+        if self.fx.ttl == 0 {
+            violations.push(ConfigViolation::new("fx.ttl", "must be positive"));
+        }
+        for (currency, rate) in &self.fx.rates {
+            if *rate <= Decimal::ZERO {
+                violations.push(ConfigViolation::new(
+                    format!("fx.rates.{currency}"),
+                    "must be positive",
+                ));
+            }
+        }
+        // NOTE: without an API every account currency needs a fixed rate to be reported in
+        if self.fx.url.is_none() {
+            let missing: BTreeSet<&str> = self
+                .strategies
+                .iter()
+                .map(|strategy| self.currency(strategy))
+                .filter(|currency| {
+                    *currency != self.fx.base && !self.fx.rates.contains_key(*currency)
+                })
+                .collect();
+            for currency in missing {
+                violations.push(ConfigViolation::new(
+                    format!("fx.rates.{currency}"),
+                    format!("is missing, strategies trade in {currency} and fx.url is not set"),
+                ));
+            }
+        }
+
+        for (symbol, increments) in &self.precision.symbols {
+            for (field, increment) in [
+                ("tick_size", increments.tick_size),
+                ("lot_size", increments.lot_size),
+            ] {
+                if increment <= Decimal::ZERO {
+                    violations.push(ConfigViolation::new(
+                        format!("precision.symbols.{symbol}.{field}"),
+                        "must be positive",
+                    ));
+                }
+            }
+        }
+
+        if let Some(corporate_actions) = &self.corporate_actions {
+            if corporate_actions.interval == 0 {
+                violations.push(ConfigViolation::new(
+                    "corporate_actions.interval",
+                    "must be positive",
+                ));
+            }
+            if corporate_actions.lookback_days <= 0 {
+                violations.push(ConfigViolation::new(
+                    "corporate_actions.lookback_days",
+                    "must be positive",
+                ));
+            }
+        }
+
+        let persistence = &self.webhook.persistence;
+        if persistence.buffer == 0 {
+            violations.push(ConfigViolation::new(
+                "webhook.persistence.buffer",
+                "must be positive",
+            ));
+        }
+        if !(1..=MAX_BATCH_SIZE).contains(&persistence.batch_size) {
+            violations.push(ConfigViolation::new(
+                "webhook.persistence.batch_size",
+                format!("must be between 1 and {MAX_BATCH_SIZE}"),
+            ));
+        }
+
+        let mut source_names = HashMap::new();
+        for (index, source) in self.webhook.sources.iter().enumerate() {
+            let field = |name: &str| format!("webhook.sources[{index}].{name}");
+
+            if source.name.is_empty()
+                || !source
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                violations.push(ConfigViolation::new(
+                    field("name"),
+                    "must be a non-empty path segment of letters, digits, - and _",
+                ));
+            }
+            if let Some(first) = source_names.insert(source.name.as_str(), index) {
+                violations.push(ConfigViolation::new(
+                    field("name"),
+                    format!(
+                        "{} is already the name of webhook.sources[{first}]",
+                        source.name
+                    ),
+                ));
+            }
+            if source.secret.trim().is_empty() {
+                violations.push(ConfigViolation::new(field("secret"), "is missing"));
+            }
+            if source.strategies.is_empty() {
+                violations.push(ConfigViolation::new(
+                    field("strategies"),
+                    "must list the ids of the strategies the source can trade",
+                ));
+            }
+            for strategy_id in &source.strategies {
+                if !self
+                    .strategies
+                    .iter()
+                    .any(|strategy| strategy.id == *strategy_id)
+                {
+                    violations.push(ConfigViolation::new(
+                        field("strategies"),
+                        format!("{strategy_id} is not the id of a strategy"),
+                    ));
+                }
+            }
+            if !source.allowed_senders.is_empty() && source.kind != SourceKind::EmailBridge {
+                violations.push(ConfigViolation::new(
+                    field("allowed_senders"),
+                    format!(
+                        "is only supported by email_bridge sources, not {}",
+                        source.kind.as_ref()
+                    ),
+                ));
+            }
+        }
+
+        let mut strategy_ids = HashMap::new();
+        for (index, strategy) in self.strategies.iter().enumerate() {
+            let field = |name: &str| format!("strategies[{index}].{name}");
+
+            if let Some(first) = strategy_ids.insert(strategy.id, index) {
+                violations.push(ConfigViolation::new(
+                    field("id"),
+                    format!("{} is already the id of strategies[{first}]", strategy.id),
+                ));
+            }
+            if let Some(family) = &strategy.family {
+                if !self.brokers.families.contains_key(family) {
+                    violations.push(ConfigViolation::new(
+                        field("family"),
+                        format!("{family} is not a family of brokers.families"),
+                    ));
+                }
+            }
+            violations.extend(self.broker_violation(field("broker"), &strategy.broker));
+            if let Some(portfolio) = &strategy.portfolio {
+                if !self.portfolios.contains_key(portfolio) {
+                    violations.push(ConfigViolation::new(
+                        field("portfolio"),
+                        format!("{portfolio} is not a portfolio of portfolios"),
+                    ));
+                }
+            }
+            if let Some(name) = &strategy.account {
+                match self.brokers.accounts.get(name) {
+                    None => violations.push(ConfigViolation::new(
+                        field("account"),
+                        format!("{name} is not a credential set of brokers.accounts"),
+                    )),
+                    Some(account) if account.broker().as_ref() != strategy.broker.as_ref() => {
+                        violations.push(ConfigViolation::new(
+                            field("account"),
+                            format!(
+                                "{name} is an account of {}, not of the strategy broker {}",
+                                account.broker().as_ref(),
+                                strategy.broker.as_ref()
+                            ),
+                        ));
+                    }
+                    Some(_) => {}
+                }
+            }
+            if let Some(routing) = &strategy.routing {
+                if routing.venues.is_empty() {
+                    violations.push(ConfigViolation::new(
+                        field("routing.venues"),
+                        "must not be empty, remove the routing to use the strategy broker",
+                    ));
+                }
+                for (venue_index, venue) in routing.venues.iter().enumerate() {
+                    let field =
+                        |name: &str| field(&format!("routing.venues[{venue_index}].{name}"));
+                    violations.extend(self.broker_violation(field("broker"), &venue.broker));
+                    if venue.fee_bps.is_sign_negative() {
+                        violations.push(ConfigViolation::new(
+                            field("fee_bps"),
+                            "must not be negative",
+                        ));
+                    }
+                    if let Some(name) = &venue.account {
+                        match self.brokers.accounts.get(name) {
+                            None => violations.push(ConfigViolation::new(
+                                field("account"),
+                                format!("{name} is not a credential set of brokers.accounts"),
+                            )),
+                            Some(account) if account.broker().as_ref() != venue.broker.as_ref() => {
+                                violations.push(ConfigViolation::new(
+                                    field("account"),
+                                    format!(
+                                        "{name} is an account of {}, not of the venue broker {}",
+                                        account.broker().as_ref(),
+                                        venue.broker.as_ref()
+                                    ),
+                                ));
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+            if let Some(failover) = &strategy.failover {
+                violations
+                    .extend(self.broker_violation(field("failover.broker"), &failover.broker));
+                if failover.broker.as_ref() == strategy.broker.as_ref()
+                    && failover.account == strategy.account
+                {
+                    violations.push(ConfigViolation::new(
+                        field("failover"),
+                        "must be another account than the one of the strategy",
+                    ));
+                }
+                if let Some(name) = &failover.account {
+                    match self.brokers.accounts.get(name) {
+                        None => violations.push(ConfigViolation::new(
+                            field("failover.account"),
+                            format!("{name} is not a credential set of brokers.accounts"),
+                        )),
+                        Some(account) if account.broker().as_ref() != failover.broker.as_ref() => {
+                            violations.push(ConfigViolation::new(
+                                field("failover.account"),
+                                format!(
+                                    "{name} is an account of {}, not of the failover broker {}",
+                                    account.broker().as_ref(),
+                                    failover.broker.as_ref()
+                                ),
+                            ));
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if strategy.max_order_retries > 0
+                && !(strategy.order_retry_delay.is_finite() && strategy.order_retry_delay > 0.0)
+            {
+                violations.push(ConfigViolation::new(
+                    field("order_retry_delay"),
+                    "must be positive when orders are retried",
+                ));
+            }
+            if strategy.order_quantity <= Decimal::ZERO {
+                violations.push(ConfigViolation::new(
+                    field("order_quantity"),
+                    "must be positive",
+                ));
+            }
+            if strategy.risk_per_trade <= Decimal::ZERO || strategy.risk_per_trade > Decimal::ONE {
+                violations.push(ConfigViolation::new(
+                    field("risk_per_trade"),
+                    "must be above 0 and at most 1",
+                ));
+            }
+            if let Some(sizing) = &strategy.volatility_sizing {
+                if sizing.risk_per_trade <= Decimal::ZERO || sizing.risk_per_trade > Decimal::ONE {
+                    violations.push(ConfigViolation::new(
+                        field("volatility_sizing.risk_per_trade"),
+                        "must be above 0 and at most 1",
+                    ));
+                }
+                if sizing.atr_multiple <= Decimal::ZERO {
+                    violations.push(ConfigViolation::new(
+                        field("volatility_sizing.atr_multiple"),
+                        "must be positive",
+                    ));
+                }
+                if sizing.atr_period == 0 {
+                    violations.push(ConfigViolation::new(
+                        field("volatility_sizing.atr_period"),
+                        "must be positive",
+                    ));
+                }
+            }
+            if let Some(management) = &strategy.stop_management {
+                if management
+                    .break_even_gain
+                    .is_some_and(|gain| gain <= Decimal::ZERO)
+                {
+                    violations.push(ConfigViolation::new(
+                        field("stop_management.break_even_gain"),
+                        "must be positive",
+                    ));
+                }
+                match &management.trailing {
+                    Some(TrailingStop::Percent { percent })
+                        if *percent <= Decimal::ZERO || *percent >= Decimal::ONE =>
+                    {
+                        violations.push(ConfigViolation::new(
+                            field("stop_management.trailing.percent"),
+                            "must be above 0 and below 1",
+                        ));
+                    }
+                    Some(TrailingStop::Atr {
+                        multiple, period, ..
+                    }) => {
+                        if *multiple <= Decimal::ZERO {
+                            violations.push(ConfigViolation::new(
+                                field("stop_management.trailing.multiple"),
+                                "must be positive",
+                            ));
+                        }
+                        if *period == 0 {
+                            violations.push(ConfigViolation::new(
+                                field("stop_management.trailing.period"),
+                                "must be positive",
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(dca) = &strategy.dca {
+                if dca.chunks < 2 {
+                    violations.push(ConfigViolation::new(
+                        field("dca.chunks"),
+                        "must be at least 2",
+                    ));
+                }
+                if dca.interval == 0 {
+                    violations.push(ConfigViolation::new(
+                        field("dca.interval"),
+                        "must be positive",
+                    ));
+                }
+            }
+            if let Some(ladder) = &strategy.take_profit_ladder {
+                if ladder.targets.is_empty() {
+                    violations.push(ConfigViolation::new(
+                        field("take_profit_ladder.targets"),
+                        "must not be empty",
+                    ));
+                }
+                for (target_index, target) in ladder.targets.iter().enumerate() {
+                    let field = |name: &str| {
+                        field(&format!(
+                            "take_profit_ladder.targets[{target_index}].{name}"
+                        ))
+                    };
+                    if target.r_multiple <= Decimal::ZERO {
+                        violations.push(ConfigViolation::new(
+                            field("r_multiple"),
+                            "must be positive",
+                        ));
+                    }
+                    if target.share <= Decimal::ZERO {
+                        violations.push(ConfigViolation::new(field("share"), "must be positive"));
+                    }
+                }
+                let shares: Decimal = ladder.targets.iter().map(|target| target.share).sum();
+                if shares > Decimal::ONE {
+                    violations.push(ConfigViolation::new(
+                        field("take_profit_ladder.targets"),
+                        format!("shares add up to {shares}, more than the whole position"),
+                    ));
+                }
+            }
+            if let Some(sizing) = &strategy.kelly_sizing {
+                if strategy.volatility_sizing.is_some() {
+                    violations.push(ConfigViolation::new(
+                        field("kelly_sizing"),
+                        "can't be combined with volatility_sizing",
+                    ));
+                }
+                if sizing.multiplier <= Decimal::ZERO || sizing.multiplier > Decimal::ONE {
+                    violations.push(ConfigViolation::new(
+                        field("kelly_sizing.multiplier"),
+                        "must be above 0 and at most 1",
+                    ));
+                }
+                if sizing.max_fraction <= Decimal::ZERO || sizing.max_fraction > Decimal::ONE {
+                    violations.push(ConfigViolation::new(
+                        field("kelly_sizing.max_fraction"),
+                        "must be above 0 and at most 1",
+                    ));
+                }
+                if sizing.lookback_trades == 0 {
+                    violations.push(ConfigViolation::new(
+                        field("kelly_sizing.lookback_trades"),
+                        "must be positive",
+                    ));
+                }
+                if sizing.min_trades > sizing.lookback_trades {
+                    violations.push(ConfigViolation::new(
+                        field("kelly_sizing.min_trades"),
+                        "must be at most lookback_trades",
+                    ));
+                }
+            }
+            if strategy
+                .max_daily_loss
+                .is_some_and(|max_loss| max_loss <= Decimal::ZERO)
+            {
+                violations.push(ConfigViolation::new(
+                    field("max_daily_loss"),
+                    "must be positive",
+                ));
+            }
+            let time_in_force = strategy.order_time_in_force();
+            match strategy.currency_type {
+                CurrencyType::Stock => {
+                    if strategy.extended_hours && time_in_force != TimeInForce::Day {
+                        violations.push(ConfigViolation::new(
+                            field("time_in_force"),
+                            "must be day for extended hours orders",
+                        ));
+                    }
+                }
+                CurrencyType::Crypto => {
+                    if !CRYPTO_TIME_IN_FORCE.contains(&time_in_force) {
+                        violations.push(ConfigViolation::new(
+                            field("time_in_force"),
+                            "must be gtc or ioc for crypto",
+                        ));
+                    }
+                    if strategy.extended_hours {
+                        violations.push(ConfigViolation::new(
+                            field("extended_hours"),
+                            "doesn't apply to crypto, which trades around the clock",
+                        ));
+                    }
+                }
+                CurrencyType::Forex => {
+                    if !matches!(strategy.broker, Broker::Oanda) {
+                        violations.push(ConfigViolation::new(
+                            field("broker"),
+                            "must be oanda for forex",
+                        ));
+                    }
+                    if matches!(time_in_force, TimeInForce::Opg | TimeInForce::Cls) {
+                        violations.push(ConfigViolation::new(
+                            field("time_in_force"),
+                            "must be day, gtc, ioc or fok for forex, which has no auctions",
+                        ));
+                    }
+                    if strategy.extended_hours {
+                        violations.push(ConfigViolation::new(
+                            field("extended_hours"),
+                            "doesn't apply to forex, which trades around the clock on weekdays",
+                        ));
+                    }
+                }
+            }
+            if matches!(strategy.broker, Broker::Oanda)
+                && strategy.currency_type != CurrencyType::Forex
+            {
+                violations.push(ConfigViolation::new(
+                    field("currency_type"),
+                    "must be forex for strategies on oanda",
+                ));
+            }
+            if matches!(strategy.broker, Broker::Tradier) {
+                if strategy.currency_type != CurrencyType::Stock {
+                    violations.push(ConfigViolation::new(
+                        field("currency_type"),
+                        "must be stock for strategies on tradier",
+                    ));
+                }
+                if !matches!(time_in_force, TimeInForce::Day | TimeInForce::Gtc) {
+                    violations.push(ConfigViolation::new(
+                        field("time_in_force"),
+                        "must be day or gtc for tradier",
+                    ));
+                }
+                if strategy.extended_hours {
+                    violations.push(ConfigViolation::new(
+                        field("extended_hours"),
+                        "is not supported for tradier, orders trade in the regular session",
+                    ));
+                }
+            }
+            if strategy.max_open_positions == Some(0) {
+                violations.push(ConfigViolation::new(
+                    field("max_open_positions"),
+                    "must be positive",
+                ));
+            }
+            if let DuplicatePositions::ScaleIn { max_quantity } = strategy.duplicate_positions {
+                if max_quantity <= Decimal::ZERO {
+                    violations.push(ConfigViolation::new(
+                        field("duplicate_positions.max_quantity"),
+                        "must be positive",
+                    ));
+                }
+            }
+            if matches!(
+                strategy.cooldown,
+                Some(Cooldown::Minutes(0) | Cooldown::Bars(0))
+            ) {
+                violations.push(ConfigViolation::new(
+                    field("cooldown"),
+                    "must be positive, remove it to accept entries after losses",
+                ));
+            }
+            if matches!(
+                strategy.partial_fills,
+                PartialFills::CancelRemainder { stale_after: 0 }
+                    | PartialFills::Reprice { stale_after: 0 }
+            ) {
+                violations.push(ConfigViolation::new(
+                    field("partial_fills.stale_after"),
+                    "must be positive",
+                ));
+            }
+            if matches!(strategy.order_ttl, Some(OrderTtl { seconds: 0, .. })) {
+                violations.push(ConfigViolation::new(
+                    field("order_ttl.seconds"),
+                    "must be positive, remove it to leave orders open",
+                ));
+            }
+            if strategy.debounce_window == Some(0) {
+                violations.push(ConfigViolation::new(
+                    field("debounce_window"),
+                    "must be positive, remove it to process every signal",
+                ));
+            }
+            for (filter_index, filter) in strategy.filters.iter().enumerate() {
+                let message = match filter {
+                    SignalFilter::MinVolume { volume } if volume.is_sign_negative() => {
+                        "volume must not be negative"
+                    }
+                    SignalFilter::PriceRange {
+                        min: Some(min),
+                        max: Some(max),
+                    } if min > max => "min must not be above max",
+                    SignalFilter::Timeframes { timeframes } if timeframes.is_empty() => {
+                        "timeframes must not be empty"
+                    }
+                    _ => continue,
+                };
+                violations.push(ConfigViolation::new(
+                    field(&format!("filters[{filter_index}]")),
+                    message,
+                ));
+            }
+            if strategy
+                .webhook_secret
+                .as_ref()
+                .is_some_and(|secret| secret.trim().is_empty())
+            {
+                violations.push(ConfigViolation::new(
+                    field("webhook_secret"),
+                    "is empty, remove it to accept unsigned alerts",
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidConfig(violations))
+        }
+    }
+
+    /// `validate` and check that the broker APIs can be reached.
+    pub async fn check(&self) -> Result<(), InvalidConfig> {
+        let mut violations = self.validate().err().map(|err| err.0).unwrap_or_default();
+
+        let base_url = self.brokers.alpaca.base_url(self.environment);
+        if Url::parse(base_url).is_ok() {
+            let client = reqwest::Client::builder()
+                .timeout(BROKER_CHECK_TIMEOUT)
+                .build()
+                .map_err(|err| {
+                    InvalidConfig(vec![ConfigViolation::new("brokers", err.to_string())])
+                })?;
+            // Any response will do, the request isn't authenticated
+            if let Err(err) = client.get(base_url).send().await {
+                violations.push(ConfigViolation::new(
+                    "brokers.alpaca.apca_api_base_url",
+                    format!("{base_url} is unreachable, {err}"),
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidConfig(violations))
+        }
     }
 }
+
+/// Setting of the configuration `AppConfig::validate` rejects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigViolation {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigViolation {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub struct InvalidConfig(pub Vec<ConfigViolation>);
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Configuration is invalid:")?;
+        for violation in &self.0 {
+            write!(f, "\n  - {}: {}", violation.field, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+fn environment() -> Environment {
+    Environment::with_prefix("MARKET")
+        .prefix_separator("__")
+        .separator("__")
+        .try_parsing(true)
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::users::Caller;
+
+/// Change made to the service at runtime, e.g. a strategy disabled through the API.
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct AuditEntry {
+    pub audit_id: Uuid,
+    /// User or role of the key that made the change
+    pub actor: String,
+    pub action: String,
+    /// What was changed, e.g. `strategy:<id>`
+    pub subject: String,
+    pub details: Json<Value>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub async fn record(
+        db: &PgPool,
+        caller: &Caller,
+        action: &str,
+        subject: &str,
+        details: Value,
+        request_id: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        let actor = caller
+            .user_id
+            .clone()
+            .unwrap_or_else(|| caller.role.as_ref().to_owned());
+        Self::record_by(db, &actor, action, subject, details, request_id).await
+    }
+
+    /// Record a change made by the service itself, e.g. a scheduled action.
+    pub async fn record_by(
+        db: &PgPool,
+        actor: &str,
+        action: &str,
+        subject: &str,
+        details: Value,
+        request_id: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            INSERT INTO audit_log (audit_id, actor, action, subject, details, request_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(actor)
+        .bind(action)
+        .bind(subject)
+        .bind(Json(details))
+        .bind(request_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Changes made to `subject`, newest first.
+    pub async fn fetch_for(db: &PgPool, subject: &str) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM audit_log WHERE subject = $1 ORDER BY created_at DESC",
+        )
+        .bind(subject)
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,437 @@
+use apca::{
+    data::v2::bars::{self as apca_bars, BarsReqInit, TimeFrame},
+    Client as AlpacaClient,
+};
+use chrono::{DateTime, Utc};
+use rand_core::RngCore;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    api::alert::SignalType,
+    clients::{num_to_decimal, BrokerClientError},
+    fill_model::{FillModel, Quote},
+    order::OrderSide,
+    pnl::TradeStatistics,
+    portfolio::drawdowns,
+    sizing,
+    strategy::Strategy,
+};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timeframe {
+    OneMinute,
+    OneHour,
+    OneDay,
+}
+
+impl Timeframe {
+    /// Notation of the timeframe in alerts and stored bars.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OneMinute => "1m",
+            Self::OneHour => "1h",
+            Self::OneDay => "1d",
+        }
+    }
+
+    pub fn duration(self) -> chrono::Duration {
+        match self {
+            Self::OneMinute => chrono::Duration::minutes(1),
+            Self::OneHour => chrono::Duration::hours(1),
+            Self::OneDay => chrono::Duration::days(1),
+        }
+    }
+}
+
+impl From<Timeframe> for TimeFrame {
+    fn from(timeframe: Timeframe) -> Self {
+        match timeframe {
+            Timeframe::OneMinute => TimeFrame::OneMinute,
+            Timeframe::OneHour => TimeFrame::OneHour,
+            Timeframe::OneDay => TimeFrame::OneDay,
+        }
+    }
+}
+
+/// Historical bar replayed by a backtest, `time` is the start of the bar.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoricalBar {
+    pub time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+/// Alert of the strategy as it would have fired at `time`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BacktestSignal {
+    pub time: DateTime<Utc>,
+    pub signal_type: SignalType,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BacktestRequest {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub timeframe: Timeframe,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(default = "default_initial_equity")]
+    pub initial_equity: Decimal,
+    /// Size orders with the new sizing engine instead of the fixed strategy quantity
+    #[serde(default)]
+    pub risk_based_sizing: bool,
+    /// Replay the bars stored from alerts instead of fetching them from the broker
+    #[serde(default)]
+    pub stored_bars: bool,
+    pub signals: Vec<BacktestSignal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    /// Closed by an opposite signal
+    Signal,
+    StopLoss,
+    /// Still open at the last bar, closed at its close
+    EndOfData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestTrade {
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub entry_time: DateTime<Utc>,
+    pub entry_price: Decimal,
+    pub exit_time: DateTime<Utc>,
+    pub exit_price: Decimal,
+    /// Commissions of the entry and the exit
+    pub fees: Decimal,
+    /// P&L net of `fees`
+    pub pnl: Decimal,
+    pub exit_reason: ExitReason,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BacktestPoint {
+    pub time: DateTime<Utc>,
+    pub equity: Decimal,
+    /// Decline from the running equity peak as a fraction of the peak
+    pub drawdown: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub initial_equity: Decimal,
+    pub final_equity: Decimal,
+    /// Change of the equity as a fraction of the initial equity
+    pub total_return: Decimal,
+    pub max_drawdown: Decimal,
+    /// Share of trades closed with a profit, `None` without trades
+    pub win_rate: Option<Decimal>,
+    /// Commissions of all executions, included in the equity and the P&L of the trades
+    pub fees: Decimal,
+    pub trades: Vec<BacktestTrade>,
+    /// Equity marked at the close of every bar
+    pub equity_curve: Vec<BacktestPoint>,
+}
+
+fn default_initial_equity() -> Decimal {
+    Decimal::from(100_000)
+}
+
+struct OpenTrade {
+    side: OrderSide,
+    quantity: Decimal,
+    entry_time: DateTime<Utc>,
+    entry_price: Decimal,
+    entry_fees: Decimal,
+    stop_loss: Decimal,
+}
+
+impl OpenTrade {
+    fn signed_quantity(&self) -> Decimal {
+        match self.side {
+            OrderSide::Buy => self.quantity,
+            OrderSide::Sell => -self.quantity,
+        }
+    }
+
+    fn close(
+        self,
+        time: DateTime<Utc>,
+        price: Decimal,
+        exit_fees: Decimal,
+        reason: ExitReason,
+    ) -> BacktestTrade {
+        let fees = self.entry_fees + exit_fees;
+        BacktestTrade {
+            side: self.side,
+            quantity: self.quantity,
+            entry_time: self.entry_time,
+            entry_price: self.entry_price,
+            exit_time: time,
+            exit_price: price,
+            fees,
+            pnl: (price - self.entry_price) * self.signed_quantity() - fees,
+            exit_reason: reason,
+        }
+    }
+}
+
+/// Account of a backtest, cash moves with every simulated execution.
+struct SimulatedAccount<'a, R> {
+    fill_model: &'a FillModel,
+    rng: &'a mut R,
+    cash: Decimal,
+    position: Option<OpenTrade>,
+    trades: Vec<BacktestTrade>,
+}
+
+impl<R: RngCore> SimulatedAccount<'_, R> {
+    fn equity(&self, price: Decimal) -> Decimal {
+        self.cash
+            + self
+                .position
+                .as_ref()
+                .map_or(Decimal::ZERO, |trade| trade.signed_quantity() * price)
+    }
+
+    /// Average price the fill model executes the order at and its commissions.
+    fn execute(
+        &mut self,
+        side: OrderSide,
+        quantity: Decimal,
+        price: Decimal,
+        bar: &HistoricalBar,
+    ) -> (Decimal, Decimal) {
+        let quote = Quote {
+            price,
+            spread: Decimal::ZERO,
+            volume: bar.volume,
+        };
+        let fills = self.fill_model.simulate(side, quantity, &quote, self.rng);
+        let notional: Decimal = fills.iter().map(|fill| fill.quantity * fill.price).sum();
+        let avg_price = notional / quantity;
+        let fees: Decimal = fills.iter().map(|fill| fill.fee).sum();
+
+        match side {
+            OrderSide::Buy => self.cash -= notional,
+            OrderSide::Sell => self.cash += notional,
+        }
+        self.cash -= fees;
+        (avg_price, fees)
+    }
+
+    fn open(
+        &mut self,
+        side: OrderSide,
+        quantity: Decimal,
+        stop_loss: Decimal,
+        bar: &HistoricalBar,
+    ) {
+        let (entry_price, entry_fees) = self.execute(side, quantity, bar.open, bar);
+        self.position = Some(OpenTrade {
+            side,
+            quantity,
+            entry_time: bar.time,
+            entry_price,
+            entry_fees,
+            stop_loss,
+        });
+    }
+
+    fn close(&mut self, price: Decimal, bar: &HistoricalBar, reason: ExitReason) {
+        let Some(trade) = self.position.take() else {
+            return;
+        };
+        let side = match trade.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        let (exit_price, exit_fees) = self.execute(side, trade.quantity, price, bar);
+        self.trades
+            .push(trade.close(bar.time, exit_price, exit_fees, reason));
+    }
+
+    /// Exit price when the bar reaches the stop loss of the open position. Bars opening beyond
+    /// the stop exit at their open.
+    fn stop_price(&self, bar: &HistoricalBar) -> Option<Decimal> {
+        let trade = self.position.as_ref()?;
+        match trade.side {
+            OrderSide::Buy => (bar.low <= trade.stop_loss).then(|| bar.open.min(trade.stop_loss)),
+            OrderSide::Sell => (bar.high >= trade.stop_loss).then(|| bar.open.max(trade.stop_loss)),
+        }
+    }
+}
+
+/// Replay bars through the signal handling of the core: signals open a position at the open of
+/// the next bar, an opposite signal reverses it and stop losses exit it. Executions are priced by
+/// the paper fill model, net of its commissions. Stop loss updates are ignored like the core does.
+pub fn run<R: RngCore>(
+    strategy: &Strategy,
+    request: &BacktestRequest,
+    bars: &[HistoricalBar],
+    fill_model: &FillModel,
+    rng: &mut R,
+) -> BacktestReport {
+    let mut signals = request.signals.clone();
+    signals.sort_by_key(|signal| signal.time);
+    let mut signals = signals.into_iter().peekable();
+
+    let mut account = SimulatedAccount {
+        fill_model,
+        rng,
+        cash: request.initial_equity,
+        position: None,
+        trades: Vec::new(),
+    };
+    let mut curve = Vec::with_capacity(bars.len());
+
+    for (index, bar) in bars.iter().enumerate() {
+        while let Some(signal) = signals.next_if(|signal| signal.time < bar.time) {
+            let (side, stop_loss) = match signal.signal_type {
+                SignalType::OpenLong(stop_loss) => (OrderSide::Buy, stop_loss.0),
+                SignalType::OpenShort(stop_loss) => (OrderSide::Sell, stop_loss.0),
+                SignalType::StopLossUpdate(_) => continue,
+            };
+            if account
+                .position
+                .as_ref()
+                .is_some_and(|trade| trade.side == side)
+            {
+                continue;
+            }
+            account.close(bar.open, bar, ExitReason::Signal);
+
+            let quantity = if request.risk_based_sizing {
+                sizing::risk_based_quantity(strategy, account.equity(bar.open), bar.open, stop_loss)
+            } else if let Some(volatility) = &strategy.volatility_sizing {
+                // Only the bars closed before the entry are known to the sizing
+                sizing::average_true_range(&bars[..index], volatility.atr_period).and_then(|atr| {
+                    sizing::volatility_quantity(strategy, volatility, account.equity(bar.open), atr)
+                })
+            } else if let Some(kelly) = &strategy.kelly_sizing {
+                // Only the trades closed before the entry are known to the sizing
+                let trades: Vec<Decimal> = account.trades.iter().map(|trade| trade.pnl).collect();
+                let statistics = TradeStatistics::from_trades(
+                    &trades[trades.len().saturating_sub(kelly.lookback_trades)..],
+                );
+                if statistics.trades < kelly.min_trades {
+                    Some(strategy.order_quantity)
+                } else {
+                    sizing::kelly_quantity(
+                        strategy,
+                        kelly,
+                        &statistics,
+                        account.equity(bar.open),
+                        bar.open,
+                    )
+                }
+            } else {
+                Some(strategy.order_quantity)
+            };
+            let Some(quantity) = quantity.and_then(|quantity| {
+                sizing::weekend_adjusted_quantity(strategy, quantity, signal.time)
+            }) else {
+                continue;
+            };
+            account.open(side, quantity, stop_loss, bar);
+        }
+
+        if let Some(stop_price) = account.stop_price(bar) {
+            account.close(stop_price, bar, ExitReason::StopLoss);
+        }
+        curve.push((bar.time, account.equity(bar.close)));
+    }
+
+    if let Some(bar) = bars.last() {
+        account.close(bar.close, bar, ExitReason::EndOfData);
+        if let Some(last) = curve.last_mut() {
+            last.1 = account.cash;
+        }
+    }
+
+    let equities: Vec<Decimal> = curve.iter().map(|(_, equity)| *equity).collect();
+    let drawdowns = drawdowns(&equities);
+    let final_equity = account.cash;
+    let trades = account.trades;
+    // NOTE: entries still open when the replay ends are closed, so every fee belongs to a trade
+    let fees = trades.iter().map(|trade| trade.fees).sum();
+
+    BacktestReport {
+        strategy_id: strategy.id,
+        ticker: request.ticker.clone(),
+        initial_equity: request.initial_equity,
+        final_equity,
+        total_return: if request.initial_equity.is_zero() {
+            Decimal::ZERO
+        } else {
+            (final_equity - request.initial_equity) / request.initial_equity
+        },
+        max_drawdown: drawdowns.iter().copied().max().unwrap_or_default(),
+        win_rate: (!trades.is_empty()).then(|| {
+            let wins = trades
+                .iter()
+                .filter(|trade| trade.pnl > Decimal::ZERO)
+                .count();
+            Decimal::from(wins) / Decimal::from(trades.len())
+        }),
+        fees,
+        trades,
+        equity_curve: curve
+            .into_iter()
+            .zip(drawdowns)
+            .map(|((time, equity), drawdown)| BacktestPoint {
+                time,
+                equity,
+                drawdown,
+            })
+            .collect(),
+    }
+}
+
+/// Historical bars of the ticker between `from` and `to`, following every page.
+pub async fn fetch_bars(
+    client: &AlpacaClient,
+    ticker: &str,
+    timeframe: Timeframe,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<HistoricalBar>, BrokerClientError> {
+    let mut bars = Vec::new();
+    let mut page_token = None;
+
+    loop {
+        let request = BarsReqInit {
+            page_token,
+            ..Default::default()
+        }
+        .init(ticker, from, to, timeframe.into());
+        let page = client
+            .issue::<apca_bars::Get>(&request)
+            .await
+            .map_err(|err| BrokerClientError::AlpacaError(format!("{err:?}")))?;
+
+        bars.extend(page.bars.into_iter().map(|bar| HistoricalBar {
+            time: bar.time,
+            open: num_to_decimal(&bar.open),
+            high: num_to_decimal(&bar.high),
+            low: num_to_decimal(&bar.low),
+            close: num_to_decimal(&bar.close),
+            volume: Decimal::from(bar.volume),
+        }));
+
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(bars),
+        }
+    }
+}
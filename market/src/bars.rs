@@ -0,0 +1,152 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+
+use crate::backtest::{HistoricalBar, Timeframe};
+
+/// Bar of an alert as stored in the `bars` table, keyed by symbol, timeframe and time.
+#[derive(Debug, Clone)]
+pub struct Bar {
+    /// Symbol at the broker, see `normalize_symbol`
+    pub symbol: String,
+    /// See `normalize_timeframe`
+    pub timeframe: String,
+    pub time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Timeframe of an alert in the notation of `Timeframe::as_str`. TradingView sends intervals in
+/// minutes, e.g. `60`, and days or weeks as `D` or `1W`. Other notations are kept as sent.
+pub fn normalize_timeframe(timeframe: &str) -> String {
+    let timeframe = timeframe.trim();
+    if let Ok(minutes) = timeframe.parse::<u32>() {
+        return match minutes {
+            minutes if minutes > 0 && minutes % 60 == 0 => format!("{}h", minutes / 60),
+            minutes => format!("{minutes}m"),
+        };
+    }
+
+    match timeframe {
+        "D" | "1D" => "1d".to_owned(),
+        "W" | "1W" => "1w".to_owned(),
+        timeframe => timeframe.to_owned(),
+    }
+}
+
+/// Store bars, replacing stored bars of the same symbol, timeframe and time. Of bars repeated
+/// within `bars` the last one is kept.
+pub async fn upsert(conn: &mut PgConnection, bars: &[Bar]) -> Result<(), sqlx::Error> {
+    let mut unique: Vec<&Bar> = Vec::with_capacity(bars.len());
+    let mut positions = HashMap::new();
+    for bar in bars {
+        match positions.entry((bar.symbol.as_str(), bar.timeframe.as_str(), bar.time)) {
+            Entry::Occupied(position) => unique[*position.get()] = bar,
+            Entry::Vacant(position) => {
+                position.insert(unique.len());
+                unique.push(bar);
+            }
+        }
+    }
+    if unique.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        "INSERT INTO bars (symbol, timeframe, bar_time, open, high, low, close, volume, \
+         updated_at) ",
+    );
+    query.push_values(unique, |mut row, bar| {
+        row.push_bind(&bar.symbol)
+            .push_bind(&bar.timeframe)
+            .push_bind(bar.time)
+            .push_bind(bar.open)
+            .push_bind(bar.high)
+            .push_bind(bar.low)
+            .push_bind(bar.close)
+            .push_bind(bar.volume)
+            .push_bind(bar.updated_at);
+    });
+    query.push(
+        r#"
+        ON CONFLICT (symbol, timeframe, bar_time) DO UPDATE
+        SET open = EXCLUDED.open,
+            high = EXCLUDED.high,
+            low = EXCLUDED.low,
+            close = EXCLUDED.close,
+            volume = EXCLUDED.volume,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    );
+    query.build().execute(conn).await?;
+
+    Ok(())
+}
+
+/// Latest `count` stored bars of the symbol, oldest first.
+pub async fn fetch_latest(
+    db: &PgPool,
+    symbol: &str,
+    timeframe: Timeframe,
+    count: usize,
+) -> Result<Vec<HistoricalBar>, sqlx::Error> {
+    let rows: Vec<BarRow> = sqlx::query_as(
+        r#"
+        SELECT bar_time, open, high, low, close, volume FROM bars
+        WHERE symbol = $1 AND timeframe = $2
+        ORDER BY bar_time DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(symbol)
+    .bind(timeframe.as_str())
+    .bind(count as i64)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().rev().map(historical_bar).collect())
+}
+
+/// Stored bars of the symbol between `from` and `to`, oldest first.
+pub async fn fetch(
+    db: &PgPool,
+    symbol: &str,
+    timeframe: Timeframe,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<HistoricalBar>, sqlx::Error> {
+    let rows: Vec<BarRow> = sqlx::query_as(
+        r#"
+        SELECT bar_time, open, high, low, close, volume FROM bars
+        WHERE symbol = $1 AND timeframe = $2 AND bar_time >= $3 AND bar_time <= $4
+        ORDER BY bar_time
+        "#,
+    )
+    .bind(symbol)
+    .bind(timeframe.as_str())
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(historical_bar).collect())
+}
+
+type BarRow = (DateTime<Utc>, Decimal, Decimal, Decimal, Decimal, Decimal);
+
+fn historical_bar((time, open, high, low, close, volume): BarRow) -> HistoricalBar {
+    HistoricalBar {
+        time,
+        open,
+        high,
+        low,
+        close,
+        volume,
+    }
+}
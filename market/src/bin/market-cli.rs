@@ -0,0 +1,254 @@
+//! Operational tasks against a running server over its API, or against its database and brokers
+//! directly.
+
+use std::{env, error::Error, sync::Arc};
+
+use market::{
+    admin::{self, StrategyStatus, StrategySwitch},
+    api_keys::Role,
+    app_config::AppConfig,
+    build_app, build_clients,
+    client::MarketClient,
+    dead_letters::{DeadLetter, DeadLetterQuery},
+    feature_flags::{FeatureFlag, UpdateFeatureFlag, HALT_TRADING},
+    replay::{self, ReplayQuery, ReplaySummary},
+    secrets,
+    users::Caller,
+    App,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+const USAGE: &str = "\
+Usage: market-cli [--url <url>] [--api-key <key>] [--db] <command>
+
+Commands:
+    strategies list
+    strategies enable <strategy id>
+    strategies disable <strategy id> [reason]
+    dead-letters list [--strategy <strategy id>] [--pending] [--limit <n>]
+    dead-letters replay <dead letter id>
+    alerts replay [--strategy <strategy id>] [--from <time>] [--to <time>]
+    kill-switch trip|reset
+    reconcile
+
+The API is reached at --url or MARKET_URL, authenticated with --api-key or MARKET_API_KEY. With
+--db the server config is loaded instead and its database and brokers are used directly.
+Stored alerts are replayed through the processing of this build as dry runs, which requires
+--db. Every outcome is printed as a JSON line, followed by a summary.";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+enum Backend {
+    Api(MarketClient),
+    Db(Arc<App>),
+}
+
+impl Backend {
+    async fn connect(args: &mut Vec<String>) -> CliResult<Self> {
+        let url = take_option(args, "--url")?.or_else(|| env::var("MARKET_URL").ok());
+        let api_key = take_option(args, "--api-key")?.or_else(|| env::var("MARKET_API_KEY").ok());
+
+        if take_flag(args, "--db") {
+            let mut config = AppConfig::build()?;
+            if let Some(secrets) = &config.secrets {
+                secrets::fetch(secrets).await?.apply(&mut config)?;
+            }
+            let clients = build_clients(&config)?;
+            return Ok(Self::Db(build_app(config, clients).await?.into()));
+        }
+
+        match (url, api_key) {
+            (Some(url), Some(api_key)) => Ok(Self::Api(MarketClient::new(url, api_key))),
+            _ => Err("API url and key are required unless --db is given".into()),
+        }
+    }
+
+    async fn strategies(&self) -> CliResult<Vec<StrategyStatus>> {
+        Ok(match self {
+            Self::Api(client) => client.strategies().await?,
+            Self::Db(app) => {
+                admin::strategy_statuses(
+                    &app.db,
+                    &app.config.strategies,
+                    chrono::Utc::now().date_naive(),
+                )
+                .await?
+            }
+        })
+    }
+
+    async fn switch_strategy(
+        &self,
+        id: Uuid,
+        switch: &StrategySwitch,
+    ) -> CliResult<StrategyStatus> {
+        Ok(match self {
+            Self::Api(client) => client.switch_strategy(id, switch).await?,
+            Self::Db(app) => {
+                let strategy = app
+                    .config
+                    .strategies
+                    .iter()
+                    .find(|strategy| strategy.id == id)
+                    .ok_or_else(|| format!("Unknown strategy - {id}"))?;
+                admin::switch_strategy(&app.db, strategy, switch).await?
+            }
+        })
+    }
+
+    async fn dead_letters(&self, query: &DeadLetterQuery) -> CliResult<Vec<DeadLetter>> {
+        Ok(match self {
+            Self::Api(client) => client.dead_letters(query).await?,
+            Self::Db(app) => DeadLetter::fetch_recent(&app.db, query).await?,
+        })
+    }
+
+    async fn replay_dead_letter(&self, id: Uuid) -> CliResult<DeadLetter> {
+        Ok(match self {
+            Self::Api(client) => client.replay_dead_letter(id).await?,
+            Self::Db(app) => {
+                let operator = Caller::operator(Role::Admin);
+                app.replay_dead_letter(&operator, id, Uuid::new_v4().to_string())
+                    .await?
+            }
+        })
+    }
+
+    async fn replay_alerts(&self, query: &ReplayQuery) -> CliResult<ReplaySummary> {
+        let Self::Db(app) = self else {
+            return Err("Replaying alerts requires --db".into());
+        };
+        Ok(replay::replay(app, query, |replayed| {
+            if let Ok(line) = serde_json::to_string(replayed) {
+                println!("{line}");
+            }
+        })
+        .await?)
+    }
+
+    async fn kill_switch(&self, halt: bool) -> CliResult<FeatureFlag> {
+        let update = UpdateFeatureFlag {
+            enabled: halt,
+            description: None,
+            rollout_percentage: None,
+        };
+        Ok(match self {
+            Self::Api(client) => client.update_feature_flag(HALT_TRADING, &update).await?,
+            Self::Db(app) => app.feature_flags.set(HALT_TRADING, &update).await?,
+        })
+    }
+
+    async fn reconcile(&self) -> CliResult<()> {
+        match self {
+            Self::Api(client) => client.reconcile().await?,
+            Self::Db(app) => app.reconcile().await?,
+        }
+        Ok(())
+    }
+}
+
+/// Remove `--name <value>` from the arguments.
+fn take_option(args: &mut Vec<String>, name: &str) -> CliResult<Option<String>> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(format!("{name} requires a value").into());
+    }
+    args.remove(index);
+    Ok(Some(args.remove(index)))
+}
+
+/// Remove `--name` from the arguments.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let index = args.iter().position(|arg| arg == name);
+    index.map(|index| args.remove(index)).is_some()
+}
+
+fn parse_id(id: Option<&String>) -> CliResult<Uuid> {
+    let id = id.ok_or("An id is required")?;
+    Ok(id.parse().map_err(|_| format!("Invalid id - {id}"))?)
+}
+
+fn print(value: &impl Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn run(mut args: Vec<String>) -> CliResult<()> {
+    if args.is_empty() || take_flag(&mut args, "--help") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let query = DeadLetterQuery {
+        strategy_id: take_option(&mut args, "--strategy")?
+            .map(|id| parse_id(Some(&id)))
+            .transpose()?,
+        pending: take_flag(&mut args, "--pending"),
+        limit: take_option(&mut args, "--limit")?
+            .map(|limit| limit.parse())
+            .transpose()?,
+    };
+    let replay_query = ReplayQuery {
+        from: take_option(&mut args, "--from")?
+            .map(|time| time.parse())
+            .transpose()?,
+        to: take_option(&mut args, "--to")?
+            .map(|time| time.parse())
+            .transpose()?,
+        strategy_id: query.strategy_id,
+    };
+    let backend = Backend::connect(&mut args).await?;
+
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        ["strategies", "list"] => print(&backend.strategies().await?),
+        ["strategies", "enable", ..] => {
+            let switch = StrategySwitch {
+                enabled: true,
+                reason: None,
+            };
+            print(
+                &backend
+                    .switch_strategy(parse_id(args.get(2))?, &switch)
+                    .await?,
+            )
+        }
+        ["strategies", "disable", ..] => {
+            let switch = StrategySwitch {
+                enabled: false,
+                reason: (args.len() > 3).then(|| args[3..].join(" ")),
+            };
+            print(
+                &backend
+                    .switch_strategy(parse_id(args.get(2))?, &switch)
+                    .await?,
+            )
+        }
+        ["dead-letters", "list"] => print(&backend.dead_letters(&query).await?),
+        ["dead-letters", "replay", ..] => {
+            print(&backend.replay_dead_letter(parse_id(args.get(2))?).await?)
+        }
+        ["alerts", "replay"] => print(&backend.replay_alerts(&replay_query).await?),
+        ["kill-switch", "trip"] => print(&backend.kill_switch(true).await?),
+        ["kill-switch", "reset"] => print(&backend.kill_switch(false).await?),
+        ["reconcile"] => backend.reconcile().await,
+        _ => Err(format!("Unknown command\n\n{USAGE}").into()),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    // NOTE: stdout is kept for the command output
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    if let Err(err) = run(env::args().skip(1).collect()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,432 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    sync::{Mutex, RwLock},
+    time::{sleep, Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Clock, Order, Position},
+    clients::{BrokerClient, BrokerClientError},
+    order::NewOrder,
+};
+
+const ACCOUNT_KEY: &str = "account";
+const CLOCK_KEY: &str = "clock";
+/// Time an instance holds the load lock of a key at most, waiting instances load the key
+/// themselves afterwards
+const LOAD_LOCK_TTL: Duration = Duration::from_secs(5);
+/// Interval instances waiting for the load of another instance check the cache at
+const LOAD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+enum Store {
+    Memory(RwLock<HashMap<String, (Instant, String)>>),
+    #[cfg(feature = "redis")]
+    Redis(redis_store::RedisStore),
+}
+
+/// TTL cache of broker lookups which rarely change, values are stored serialized so the same
+/// entries can be kept in memory or shared through Redis between instances.
+pub struct BrokerCache {
+    /// Entries of the broker are stored under this prefix
+    prefix: String,
+    ttl: Duration,
+    store: Store,
+    /// Keys being loaded by this instance, further misses of a key wait for the load
+    loading: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl BrokerCache {
+    pub fn in_memory(prefix: &str, ttl: Duration) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            ttl,
+            store: Store::Memory(RwLock::new(HashMap::new())),
+            loading: Default::default(),
+        }
+    }
+
+    /// Cache shared through the Redis server at `url`, connected on first use.
+    #[cfg(feature = "redis")]
+    pub fn redis(prefix: &str, ttl: Duration, url: &str) -> Result<Self, redis::RedisError> {
+        Ok(Self {
+            prefix: prefix.to_owned(),
+            ttl,
+            store: Store::Redis(redis_store::RedisStore::open(url)?),
+            loading: Default::default(),
+        })
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("market:{}:{}", self.prefix, key)
+    }
+
+    /// Cached value of `key`, `None` when it's missing, expired or can't be read.
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let key = self.key(key);
+        let value = match &self.store {
+            Store::Memory(entries) => entries
+                .read()
+                .await
+                .get(&key)
+                .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+                .map(|(_, value)| value.clone()),
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.get(&key).await,
+        }?;
+        serde_json::from_str(&value).ok()
+    }
+
+    async fn set<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_string(value) else {
+            return;
+        };
+        let key = self.key(key);
+        match &self.store {
+            Store::Memory(entries) => {
+                entries.write().await.insert(key, (Instant::now(), value));
+            }
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.set(&key, &value, self.ttl).await,
+        }
+    }
+
+    /// Drop the cached account, after anything changing its balances.
+    pub async fn invalidate_account(&self) {
+        let key = self.key(ACCOUNT_KEY);
+        match &self.store {
+            Store::Memory(entries) => {
+                entries.write().await.remove(&key);
+            }
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.delete(&[key]).await,
+        }
+    }
+
+    /// Drop every cached entry of the broker.
+    pub async fn invalidate_all(&self) {
+        match &self.store {
+            Store::Memory(entries) => entries.write().await.clear(),
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.delete_matching(&self.key("*")).await,
+        }
+    }
+
+    /// Cached value of `key`, loaded with `load` and cached when missing. Errors are not cached.
+    ///
+    /// Concurrent misses of a key load it once: lookups of this instance wait for the load in
+    /// flight, and instances sharing Redis wait for the instance holding the load lock of the key,
+    /// so an expired entry doesn't send every replica to the broker at once.
+    async fn get_or_load<T, F>(&self, key: &str, load: F) -> Result<T, BrokerClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: std::future::Future<Output = Result<T, BrokerClientError>>,
+    {
+        if let Some(value) = self.get(key).await {
+            return Ok(value);
+        }
+
+        let loading = Arc::clone(
+            self.loading
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .entry(key.to_owned())
+                .or_default(),
+        );
+        let _loading = loading.lock().await;
+        let result = self.load_once(key, load).await;
+        self.loading
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(key);
+
+        result
+    }
+
+    /// Load `key` unless a load of this instance or the instance holding its load lock cached it
+    /// meanwhile.
+    async fn load_once<T, F>(&self, key: &str, load: F) -> Result<T, BrokerClientError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: std::future::Future<Output = Result<T, BrokerClientError>>,
+    {
+        if let Some(value) = self.get(key).await {
+            return Ok(value);
+        }
+
+        let locked = self.lock(key).await;
+        if !locked {
+            if let Some(value) = self.wait_for_load(key).await {
+                return Ok(value);
+            }
+        }
+        let result = load.await;
+        if let Ok(value) = &result {
+            self.set(key, value).await;
+        }
+        if locked {
+            self.unlock(key).await;
+        }
+
+        result
+    }
+
+    /// Take the load lock of `key` shared between instances, `false` while another instance
+    /// holds it. Instances which can't reach the lock load the key regardless.
+    #[cfg_attr(not(feature = "redis"), allow(unused_variables))]
+    async fn lock(&self, key: &str) -> bool {
+        match &self.store {
+            Store::Memory(_) => true,
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => {
+                store
+                    .try_lock(&self.key(&format!("lock:{key}")), LOAD_LOCK_TTL)
+                    .await
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "redis"), allow(unused_variables))]
+    async fn unlock(&self, key: &str) {
+        match &self.store {
+            Store::Memory(_) => {}
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.delete(&[self.key(&format!("lock:{key}"))]).await,
+        }
+    }
+
+    /// Value of `key` cached by the instance holding its load lock, `None` when it's not cached
+    /// before the lock expires.
+    async fn wait_for_load<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let deadline = Instant::now() + LOAD_LOCK_TTL;
+        while Instant::now() < deadline {
+            sleep(LOAD_POLL_INTERVAL).await;
+            if let Some(value) = self.get(key).await {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+/// Broker client serving account, asset and clock lookups from the cache. Order and position
+/// changes invalidate the cached account.
+pub struct CachedClient<'a, C> {
+    inner: C,
+    cache: &'a BrokerCache,
+}
+
+impl<'a, C> CachedClient<'a, C> {
+    pub fn new(inner: C, cache: &'a BrokerCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[axum::async_trait]
+impl<C: BrokerClient> BrokerClient for CachedClient<'_, C> {
+    type ActivitiesRequest = C::ActivitiesRequest;
+    type NewOrderRequest = C::NewOrderRequest;
+    type OrdersRequest = C::OrdersRequest;
+    type OrderUdateRequest = C::OrderUdateRequest;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        self.inner.order_request(new_order)
+    }
+
+    fn supports_options(&self) -> bool {
+        self.inner.supports_options()
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        self.cache
+            .get_or_load(ACCOUNT_KEY, self.inner.get_account())
+            .await
+    }
+
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        self.inner.get_activities(activities_req).await
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        let key = format!("asset:{symbol}");
+        self.cache
+            .get_or_load(&key, self.inner.get_asset(symbol))
+            .await
+    }
+
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        let key = format!(
+            "assets:{}",
+            serde_json::to_string(&class).unwrap_or_default()
+        );
+        self.cache
+            .get_or_load(&key, self.inner.get_assets(class))
+            .await
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        self.cache
+            .get_or_load(CLOCK_KEY, self.inner.get_clock())
+            .await
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        self.inner.get_position(symbol).await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        self.inner.get_positions().await
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        let result = self.inner.delete_position(symbol).await;
+        self.cache.invalidate_account().await;
+        result
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        self.inner.get_order_by_client_id(client_id).await
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        self.inner.get_orders(orders_req).await
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let result = self.inner.create_order(new_order_req).await;
+        self.cache.invalidate_account().await;
+        result
+    }
+
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let result = self.inner.update_order(order_id, update_req).await;
+        self.cache.invalidate_account().await;
+        result
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        let result = self.inner.delete_order(order_id).await;
+        self.cache.invalidate_account().await;
+        result
+    }
+}
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    use futures::StreamExt;
+    use redis::{aio::ConnectionManager, AsyncCommands, Client, RedisError};
+    use tokio::{sync::OnceCell, time::Duration};
+
+    /// Redis connection shared by all cache operations. Failing operations are logged and treated
+    /// as cache misses, so an unavailable Redis only costs broker requests.
+    pub(super) struct RedisStore {
+        client: Client,
+        connection: OnceCell<ConnectionManager>,
+    }
+
+    impl RedisStore {
+        pub(super) fn open(url: &str) -> Result<Self, RedisError> {
+            Ok(Self {
+                client: Client::open(url)?,
+                connection: OnceCell::new(),
+            })
+        }
+
+        async fn connection(&self) -> Option<ConnectionManager> {
+            let connection = self
+                .connection
+                .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+                .await;
+            match connection {
+                Ok(connection) => Some(connection.clone()),
+                Err(err) => {
+                    tracing::warn!("Failed to connect to Redis, error: {}", err);
+                    None
+                }
+            }
+        }
+
+        pub(super) async fn get(&self, key: &str) -> Option<String> {
+            let mut connection = self.connection().await?;
+            connection
+                .get::<_, Option<String>>(key)
+                .await
+                .map_err(|err| tracing::warn!("Failed to read {} from Redis, error: {}", key, err))
+                .ok()
+                .flatten()
+        }
+
+        pub(super) async fn set(&self, key: &str, value: &str, ttl: Duration) {
+            let Some(mut connection) = self.connection().await else {
+                return;
+            };
+            let ttl = ttl.as_secs().max(1) as usize;
+            if let Err(err) = connection.set_ex::<_, _, ()>(key, value, ttl).await {
+                tracing::warn!("Failed to write {} to Redis, error: {}", key, err);
+            }
+        }
+
+        /// Set `key` unless it's set already, it expires after `ttl`. Redis errors are logged and
+        /// count as taking the lock.
+        pub(super) async fn try_lock(&self, key: &str, ttl: Duration) -> bool {
+            let Some(mut connection) = self.connection().await else {
+                return true;
+            };
+            let locked: Result<Option<String>, RedisError> = redis::cmd("SET")
+                .arg(key)
+                .arg(1)
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl.as_millis() as u64)
+                .query_async(&mut connection)
+                .await;
+            match locked {
+                Ok(locked) => locked.is_some(),
+                Err(err) => {
+                    tracing::warn!("Failed to lock {} in Redis, error: {}", key, err);
+                    true
+                }
+            }
+        }
+
+        pub(super) async fn delete(&self, keys: &[String]) {
+            let Some(mut connection) = self.connection().await else {
+                return;
+            };
+            if let Err(err) = connection.del::<_, ()>(keys).await {
+                tracing::warn!("Failed to delete Redis keys, error: {}", err);
+            }
+        }
+
+        pub(super) async fn delete_matching(&self, pattern: &str) {
+            let Some(mut connection) = self.connection().await else {
+                return;
+            };
+            let keys: Vec<String> = match connection.scan_match::<_, String>(pattern).await {
+                Ok(keys) => keys.collect().await,
+                Err(err) => {
+                    tracing::warn!("Failed to scan Redis keys, error: {}", err);
+                    return;
+                }
+            };
+            if !keys.is_empty() {
+                self.delete(&keys).await;
+            }
+        }
+    }
+}
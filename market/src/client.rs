@@ -0,0 +1,438 @@
+//! Typed async client of the API, see the routes of `build_routes`.
+
+use axum::body::Bytes;
+use chrono::NaiveDate;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error as ThisError;
+use uuid::Uuid;
+
+use crate::{
+    activities::{AccountActivity, ActivityQuery, CashFlow},
+    admin::{StrategyDisable, StrategyStatus, StrategySwitch},
+    alert_writer::AlertRecord,
+    api::{
+        error::{ErrorBody, ErrorCode},
+        objects::{Account, ActivitiesRequest, Activity, Broker, Order, OrdersRequest, Position},
+        pagination::{ListParams, Page},
+    },
+    api_keys::{ApiKey, CreatedApiKey, NewApiKey},
+    backtest::{BacktestReport, BacktestRequest, HistoricalBar},
+    dead_letters::{DeadLetter, DeadLetterQuery},
+    divergence::{DivergenceQuery, DivergenceReport},
+    export::ExportQuery,
+    exposure::{ExposureQuery, StrategyExposure},
+    feature_flags::{FeatureFlag, UpdateFeatureFlag},
+    health::DeepHealth,
+    market_data::{BarsQuery, LiveQuote},
+    order::{Fill, OrderRecord},
+    pnl::{PnlQuery, StrategyPnl},
+    portfolio::{EquityCurve, EquityCurveQuery},
+    preview::{OrderPreview, OrderPreviewRequest},
+    rate_limit::GroupUsage,
+    rebalance::{RebalanceReport, RebalanceRequest},
+    reports::DailyReport,
+    retry::RetryCounts,
+    stats::{StatsQuery, StrategyStats},
+    status::PublicStatus,
+    trade_executor::QueueMetrics,
+    usage::{TenantUsage, UsageQuery},
+    users::{NewUser, User},
+};
+
+#[derive(Debug, ThisError)]
+pub enum ClientError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    /// Error response of the API, with its code unless it didn't come with an `ErrorBody`
+    #[error("{status}: {message}")]
+    ApiError {
+        status: StatusCode,
+        code: Option<ErrorCode>,
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct BrokerQuery<'a> {
+    broker: &'a Broker,
+}
+
+pub struct MarketClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl MarketClient {
+    /// Client of the API at `base_url`, authenticated with an API key or a `Bearer` token.
+    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            api_key: api_key.into(),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header(reqwest::header::AUTHORIZATION, &self.api_key)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<reqwest::Response, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        Err(api_error(status, response.text().await?))
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    pub async fn health(&self) -> Result<(), ClientError> {
+        self.send(self.request(Method::GET, "/health")).await?;
+        Ok(())
+    }
+
+    /// Health of every dependency, also when some are down.
+    pub async fn deep_health(&self) -> Result<DeepHealth, ClientError> {
+        let response = self.request(Method::GET, "/health/deep").send().await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => Ok(response.json().await?),
+            status => Err(api_error(status, response.text().await?)),
+        }
+    }
+
+    pub async fn public_status(&self) -> Result<PublicStatus, ClientError> {
+        self.json(self.request(Method::GET, "/public/status")).await
+    }
+
+    pub async fn account(&self, broker: &Broker) -> Result<Account, ClientError> {
+        self.json(
+            self.request(Method::GET, "/account")
+                .query(&BrokerQuery { broker }),
+        )
+        .await
+    }
+
+    /// Requests of the API key of the client and its remaining quota, by route group.
+    pub async fn account_usage(&self) -> Result<Vec<GroupUsage>, ClientError> {
+        self.json(self.request(Method::GET, "/account/usage")).await
+    }
+
+    /// Activities of the broker account, kept by the server beyond the retention of the broker.
+    pub async fn account_activities(
+        &self,
+        query: &ActivityQuery,
+    ) -> Result<Vec<AccountActivity>, ClientError> {
+        self.json(
+            self.request(Method::GET, "/account/activities")
+                .query(query),
+        )
+        .await
+    }
+
+    /// Totals of the activities of the broker account by kind and currency.
+    pub async fn account_cash_flow(
+        &self,
+        query: &ActivityQuery,
+    ) -> Result<Vec<CashFlow>, ClientError> {
+        self.json(self.request(Method::GET, "/account/cash-flow").query(query))
+            .await
+    }
+
+    pub async fn activities(
+        &self,
+        request: &ActivitiesRequest,
+    ) -> Result<Vec<Activity>, ClientError> {
+        self.json(self.request(Method::POST, "/activities").json(request))
+            .await
+    }
+
+    pub async fn orders(&self, request: &OrdersRequest) -> Result<Vec<Order>, ClientError> {
+        self.json(self.request(Method::POST, "/orders").json(request))
+            .await
+    }
+
+    /// Orders of the strategies as recorded locally, see `orders` for the orders of the broker.
+    pub async fn order_history(
+        &self,
+        params: &ListParams,
+    ) -> Result<Page<OrderRecord>, ClientError> {
+        self.json(self.request(Method::GET, "/orders").query(params))
+            .await
+    }
+
+    /// Order by the id it was submitted with.
+    pub async fn order(&self, broker: &Broker, id: Uuid) -> Result<Order, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/order/{id}"))
+                .query(&BrokerQuery { broker }),
+        )
+        .await
+    }
+
+    pub async fn positions(&self, broker: &Broker) -> Result<Vec<Position>, ClientError> {
+        self.json(
+            self.request(Method::GET, "/positions")
+                .query(&BrokerQuery { broker }),
+        )
+        .await
+    }
+
+    pub async fn position_history(&self, params: &ListParams) -> Result<Page<Fill>, ClientError> {
+        self.json(
+            self.request(Method::GET, "/positions/history")
+                .query(params),
+        )
+        .await
+    }
+
+    pub async fn strategy_pnl(
+        &self,
+        strategy_id: Uuid,
+        query: &PnlQuery,
+    ) -> Result<StrategyPnl, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/strategies/{strategy_id}/pnl"))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn strategy_exposure(
+        &self,
+        strategy_id: Uuid,
+        query: &ExposureQuery,
+    ) -> Result<StrategyExposure, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/strategies/{strategy_id}/exposure"))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn strategy_divergence(
+        &self,
+        strategy_id: Uuid,
+        query: &DivergenceQuery,
+    ) -> Result<DivergenceReport, ClientError> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/strategies/{strategy_id}/divergence"),
+            )
+            .query(query),
+        )
+        .await
+    }
+
+    pub async fn strategy_stats(
+        &self,
+        strategy_id: Uuid,
+        query: &StatsQuery,
+    ) -> Result<StrategyStats, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/strategies/{strategy_id}/stats"))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn run_backtest(
+        &self,
+        request: &BacktestRequest,
+    ) -> Result<BacktestReport, ClientError> {
+        self.json(self.request(Method::POST, "/backtests").json(request))
+            .await
+    }
+
+    pub async fn quote(&self, symbol: &str) -> Result<LiveQuote, ClientError> {
+        self.json(self.request(Method::GET, &format!("/marketdata/quote/{symbol}")))
+            .await
+    }
+
+    pub async fn bars(
+        &self,
+        symbol: &str,
+        query: &BarsQuery,
+    ) -> Result<Vec<HistoricalBar>, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/marketdata/bars/{symbol}"))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn equity_curve(&self, query: &EquityCurveQuery) -> Result<EquityCurve, ClientError> {
+        self.json(self.request(Method::GET, "/portfolio/equity").query(query))
+            .await
+    }
+
+    pub async fn rebalance(
+        &self,
+        request: &RebalanceRequest,
+    ) -> Result<RebalanceReport, ClientError> {
+        self.json(self.request(Method::POST, "/rebalance").json(request))
+            .await
+    }
+
+    /// Order a strategy would place for the request, nothing is placed.
+    pub async fn preview_order(
+        &self,
+        request: &OrderPreviewRequest,
+    ) -> Result<OrderPreview, ClientError> {
+        self.json(self.request(Method::POST, "/orders/preview").json(request))
+            .await
+    }
+
+    /// Reconcile open orders with the broker now.
+    pub async fn reconcile(&self) -> Result<(), ClientError> {
+        self.json(self.request(Method::POST, "/reconcile")).await
+    }
+
+    pub async fn strategies(&self) -> Result<Vec<StrategyStatus>, ClientError> {
+        self.json(self.request(Method::GET, "/strategies")).await
+    }
+
+    pub async fn switch_strategy(
+        &self,
+        id: Uuid,
+        switch: &StrategySwitch,
+    ) -> Result<StrategyStatus, ClientError> {
+        self.json(
+            self.request(Method::PUT, &format!("/strategies/{id}/enabled"))
+                .json(switch),
+        )
+        .await
+    }
+
+    pub async fn enable_strategy(&self, id: Uuid) -> Result<StrategyStatus, ClientError> {
+        self.json(self.request(Method::POST, &format!("/strategies/{id}/enable")))
+            .await
+    }
+
+    pub async fn disable_strategy(
+        &self,
+        id: Uuid,
+        reason: Option<&str>,
+    ) -> Result<StrategyStatus, ClientError> {
+        self.json(
+            self.request(Method::POST, &format!("/strategies/{id}/disable"))
+                .json(&StrategyDisable {
+                    reason: reason.map(ToOwned::to_owned),
+                }),
+        )
+        .await
+    }
+
+    pub async fn daily_report(&self, date: NaiveDate) -> Result<DailyReport, ClientError> {
+        self.json(self.request(Method::GET, &format!("/reports/daily/{date}")))
+            .await
+    }
+
+    pub async fn alerts(&self, params: &ListParams) -> Result<Page<AlertRecord>, ClientError> {
+        self.json(self.request(Method::GET, "/alerts").query(params))
+            .await
+    }
+
+    pub async fn dead_letters(
+        &self,
+        query: &DeadLetterQuery,
+    ) -> Result<Vec<DeadLetter>, ClientError> {
+        self.json(self.request(Method::GET, "/dead-letters").query(query))
+            .await
+    }
+
+    pub async fn replay_dead_letter(&self, id: Uuid) -> Result<DeadLetter, ClientError> {
+        self.json(self.request(Method::POST, &format!("/dead-letters/{id}/replay")))
+            .await
+    }
+
+    pub async fn usage(&self, query: &UsageQuery) -> Result<Vec<TenantUsage>, ClientError> {
+        self.json(self.request(Method::GET, "/usage").query(query))
+            .await
+    }
+
+    pub async fn retry_metrics(&self) -> Result<RetryCounts, ClientError> {
+        self.json(self.request(Method::GET, "/metrics/retries"))
+            .await
+    }
+
+    pub async fn queue_metrics(&self) -> Result<Vec<QueueMetrics>, ClientError> {
+        self.json(self.request(Method::GET, "/metrics/queue")).await
+    }
+
+    /// Raw export document, in the format and compression of the query.
+    pub async fn export_trades(&self, query: &ExportQuery) -> Result<Bytes, ClientError> {
+        Ok(self
+            .send(self.request(Method::GET, "/export/trades").query(query))
+            .await?
+            .bytes()
+            .await?)
+    }
+
+    pub async fn feature_flags(&self) -> Result<Vec<FeatureFlag>, ClientError> {
+        self.json(self.request(Method::GET, "/feature-flags")).await
+    }
+
+    pub async fn update_feature_flag(
+        &self,
+        name: &str,
+        update: &UpdateFeatureFlag,
+    ) -> Result<FeatureFlag, ClientError> {
+        self.json(
+            self.request(Method::PUT, &format!("/feature-flags/{name}"))
+                .json(update),
+        )
+        .await
+    }
+
+    pub async fn api_keys(&self) -> Result<Vec<ApiKey>, ClientError> {
+        self.json(self.request(Method::GET, "/api-keys")).await
+    }
+
+    pub async fn create_api_key(&self, new_key: &NewApiKey) -> Result<CreatedApiKey, ClientError> {
+        self.json(self.request(Method::POST, "/api-keys").json(new_key))
+            .await
+    }
+
+    pub async fn revoke_api_key(&self, id: Uuid) -> Result<ApiKey, ClientError> {
+        self.json(self.request(Method::DELETE, &format!("/api-keys/{id}")))
+            .await
+    }
+
+    pub async fn users(&self) -> Result<Vec<User>, ClientError> {
+        self.json(self.request(Method::GET, "/users")).await
+    }
+
+    pub async fn create_user(&self, new_user: &NewUser) -> Result<User, ClientError> {
+        self.json(self.request(Method::POST, "/users").json(new_user))
+            .await
+    }
+
+    pub async fn invalidate_broker_cache(&self, broker: &Broker) -> Result<(), ClientError> {
+        self.json(
+            self.request(Method::DELETE, "/broker-cache")
+                .query(&BrokerQuery { broker }),
+        )
+        .await
+    }
+}
+
+fn api_error(status: StatusCode, body: String) -> ClientError {
+    let (code, message) = match serde_json::from_str::<ErrorBody>(&body) {
+        Ok(body) => (Some(body.code), body.message),
+        Err(_) => (None, body),
+    };
+    ClientError::ApiError {
+        status,
+        code,
+        message,
+    }
+}
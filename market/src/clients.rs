@@ -1,44 +1,348 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, OnceLock, RwLock},
+};
 
 use anyhow::Result;
 use apca::{
     api::v2::{
         account as apca_account, account_activities as apca_activities, asset as apca_asset,
-        assets as apca_assets,
+        assets as apca_assets, clock as apca_clock,
         order::{self as apca_order, Patch},
         orders as apca_orders, position as apca_position, positions as apca_positions,
     },
-    Client as AlpacaClient,
+    ApiInfo, Client as AlpacaClient, RequestError,
 };
+use num_decimal::Num;
+use rust_decimal::Decimal;
+use serde::Serialize;
 use thiserror::Error as ThisError;
 use uuid::Uuid;
 
-use crate::api::objects::{Account, Activity, Asset, AssetClass, Order, Position};
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Broker, Clock, Order, Position},
+    app_config::{BrokerAccount, TradingEnvironment},
+    cache::BrokerCache,
+    oanda::{OandaClient, OandaOrderRequest},
+    order::{NewOrder, OrderSide, TimeInForce},
+    scheduler::{RequestScheduler, ScheduledClient},
+    tradier::{TradierClient, TradierOrderRequest},
+};
 
 pub struct Clients {
-    pub alpaca: Arc<AlpacaClient>,
+    /// Environment of the broker accounts the clients trade on
+    pub environment: TradingEnvironment,
+    alpaca: RwLock<Arc<AlpacaClient>>,
+    pub alpaca_cache: BrokerCache,
+    alpaca_scheduler: Arc<RequestScheduler>,
+    /// Clients of the credential sets strategies reference, by name
+    accounts: HashMap<String, AccountClient>,
+    oanda: Option<VenueAccount<OandaClient>>,
+    tradier: Option<VenueAccount<TradierClient>>,
+}
+
+/// Client of a broker traded on a single account, see `Clients::with_oanda`.
+struct VenueAccount<C> {
+    client: Arc<C>,
+    cache: BrokerCache,
+    scheduler: Arc<RequestScheduler>,
+}
+
+impl<C> VenueAccount<C> {
+    fn new(client: C, cache: BrokerCache, scheduler: RequestScheduler) -> Self {
+        Self {
+            client: Arc::new(client),
+            cache,
+            scheduler: Arc::new(scheduler),
+        }
+    }
+
+    fn scheduled(&self) -> (ScheduledClient<Arc<C>>, &BrokerCache) {
+        (
+            ScheduledClient::new(Arc::clone(&self.client), Arc::clone(&self.scheduler)),
+            &self.cache,
+        )
+    }
+}
+
+/// Client of a credential set, created when a strategy first trades on the account.
+struct AccountClient {
+    credentials: BrokerAccount,
+    cache: BrokerCache,
+    scheduler: Arc<RequestScheduler>,
+    alpaca: OnceLock<Arc<AlpacaClient>>,
 }
 
 impl Clients {
-    pub fn new(alpaca: AlpacaClient) -> Self {
+    pub fn new(
+        environment: TradingEnvironment,
+        alpaca: AlpacaClient,
+        alpaca_cache: BrokerCache,
+        alpaca_scheduler: RequestScheduler,
+    ) -> Self {
         Self {
-            alpaca: Arc::new(alpaca),
+            environment,
+            alpaca: RwLock::new(Arc::new(alpaca)),
+            alpaca_cache,
+            alpaca_scheduler: Arc::new(alpaca_scheduler),
+            accounts: HashMap::new(),
+            oanda: None,
+            tradier: None,
+        }
+    }
+
+    /// Trade forex on the OANDA account of `client`.
+    pub fn with_oanda(
+        mut self,
+        client: OandaClient,
+        cache: BrokerCache,
+        scheduler: RequestScheduler,
+    ) -> Self {
+        self.oanda = Some(VenueAccount::new(client, cache, scheduler));
+        self
+    }
+
+    /// Trade equities and options on the Tradier account of `client`.
+    pub fn with_tradier(
+        mut self,
+        client: TradierClient,
+        cache: BrokerCache,
+        scheduler: RequestScheduler,
+    ) -> Self {
+        self.tradier = Some(VenueAccount::new(client, cache, scheduler));
+        self
+    }
+
+    /// OANDA client and cache, fails when no OANDA account is configured.
+    pub fn oanda(
+        &self,
+    ) -> Result<(ScheduledClient<Arc<OandaClient>>, &BrokerCache), BrokerClientError> {
+        self.oanda
+            .as_ref()
+            .map(VenueAccount::scheduled)
+            .ok_or_else(|| BrokerClientError::NotConfigured(Broker::Oanda.as_ref().to_owned()))
+    }
+
+    /// Tradier client and cache, fails when no Tradier account is configured.
+    pub fn tradier(
+        &self,
+    ) -> Result<(ScheduledClient<Arc<TradierClient>>, &BrokerCache), BrokerClientError> {
+        self.tradier
+            .as_ref()
+            .map(VenueAccount::scheduled)
+            .ok_or_else(|| BrokerClientError::NotConfigured(Broker::Tradier.as_ref().to_owned()))
+    }
+
+    /// Client and cache of the credential set `account` of `broker`, the global ones when `None`.
+    ///
+    /// NOTE: credential sets are only supported for Alpaca, OANDA and Tradier trade on a single
+    /// account
+    pub fn venue(
+        &self,
+        broker: &Broker,
+        account: Option<&str>,
+    ) -> Result<(VenueClient, &BrokerCache), BrokerClientError> {
+        match (broker, account) {
+            (Broker::Alpaca, _) => {
+                let (client, cache) = self.alpaca_account(account)?;
+                Ok((VenueClient::Alpaca(client), cache))
+            }
+            (Broker::Oanda, None) => {
+                let (client, cache) = self.oanda()?;
+                Ok((VenueClient::Oanda(client), cache))
+            }
+            (Broker::Tradier, None) => {
+                let (client, cache) = self.tradier()?;
+                Ok((VenueClient::Tradier(client), cache))
+            }
+            (Broker::Oanda | Broker::Tradier, Some(name)) => {
+                Err(BrokerClientError::UnknownAccount(name.to_owned()))
+            }
+        }
+    }
+
+    /// Register the credential set `name`, its client is only created on first use.
+    pub fn add_account(&mut self, name: String, credentials: BrokerAccount, cache: BrokerCache) {
+        let requests_per_minute = match &credentials {
+            BrokerAccount::Alpaca(alpaca) => alpaca.requests_per_minute,
+        };
+        self.accounts.insert(
+            name,
+            AccountClient {
+                credentials,
+                cache,
+                scheduler: Arc::new(RequestScheduler::new(requests_per_minute)),
+                alpaca: OnceLock::new(),
+            },
+        );
+    }
+
+    /// Alpaca client of the global credentials, sending its requests through their scheduler.
+    pub fn scheduled_alpaca(&self) -> ScheduledClient<Arc<AlpacaClient>> {
+        ScheduledClient::new(self.alpaca(), Arc::clone(&self.alpaca_scheduler))
+    }
+
+    /// Create the clients of every credential set up front instead of at their first use.
+    pub fn initialize_accounts(&self) -> Result<(), BrokerClientError> {
+        self.accounts
+            .keys()
+            .try_for_each(|name| self.alpaca_account(Some(name)).map(|_| ()))
+    }
+
+    /// Alpaca client and cache of the credential set `account`, the global ones when `None`.
+    pub fn alpaca_account(
+        &self,
+        account: Option<&str>,
+    ) -> Result<(ScheduledClient<Arc<AlpacaClient>>, &BrokerCache), BrokerClientError> {
+        let Some(name) = account else {
+            return Ok((self.scheduled_alpaca(), &self.alpaca_cache));
+        };
+        let account = self
+            .accounts
+            .get(name)
+            .ok_or_else(|| BrokerClientError::UnknownAccount(name.to_owned()))?;
+
+        match &account.credentials {
+            BrokerAccount::Alpaca(alpaca) => {
+                let scheduled = |client: &Arc<AlpacaClient>| {
+                    ScheduledClient::new(Arc::clone(client), Arc::clone(&account.scheduler))
+                };
+                if let Some(client) = account.alpaca.get() {
+                    return Ok((scheduled(client), &account.cache));
+                }
+                let api_info = ApiInfo::from_parts(
+                    alpaca.base_url(self.environment),
+                    &alpaca.apca_api_key_id,
+                    &alpaca.apca_api_secret_key,
+                )
+                .map_err(|err| BrokerClientError::AlpacaError(format!("{err:?}")))?;
+                let client = account
+                    .alpaca
+                    .get_or_init(|| Arc::new(AlpacaClient::new(api_info)));
+                Ok((scheduled(client), &account.cache))
+            }
+        }
+    }
+
+    /// Current Alpaca client, see `set_alpaca`.
+    pub fn alpaca(&self) -> Arc<AlpacaClient> {
+        Arc::clone(&self.alpaca.read().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    /// Replace the Alpaca client, e.g. after its credentials were rotated. Calls in flight finish
+    /// with the previous client.
+    pub fn set_alpaca(&self, alpaca: AlpacaClient) {
+        *self.alpaca.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(alpaca);
+    }
+
+    /// Cache of the broker lookups of the credential set `account`, the global one when `None`.
+    pub fn cache(&self, broker: &Broker, account: Option<&str>) -> Option<&BrokerCache> {
+        match account {
+            Some(name) => self
+                .accounts
+                .get(name)
+                .filter(|account| account.credentials.broker().as_ref() == broker.as_ref())
+                .map(|account| &account.cache),
+            None => match broker {
+                Broker::Alpaca => Some(&self.alpaca_cache),
+                Broker::Oanda => self.oanda.as_ref().map(|oanda| &oanda.cache),
+                Broker::Tradier => self.tradier.as_ref().map(|tradier| &tradier.cache),
+            },
         }
     }
+
+    /// Caches of the global credentials and every credential set of the broker.
+    pub fn caches<'a>(&'a self, broker: &'a Broker) -> impl Iterator<Item = &'a BrokerCache> {
+        let global = match broker {
+            Broker::Alpaca => Some(&self.alpaca_cache),
+            Broker::Oanda => self.oanda.as_ref().map(|oanda| &oanda.cache),
+            Broker::Tradier => self.tradier.as_ref().map(|tradier| &tradier.cache),
+        };
+        global.into_iter().chain(
+            self.accounts
+                .values()
+                .filter(|account| account.credentials.broker().as_ref() == broker.as_ref())
+                .map(|account| &account.cache),
+        )
+    }
 }
 
 #[derive(Debug, ThisError)]
 pub enum BrokerClientError {
     #[error("Alpaca request error: {0}")]
     AlpacaError(String),
+    /// Failure which may not happen again, e.g. a rate limited or dropped request
+    #[error("Alpaca is unavailable: {0}")]
+    AlpacaUnavailable(String),
+    #[error("OANDA request error: {0}")]
+    OandaError(String),
+    /// Failure which may not happen again, e.g. a rate limited request or a server error
+    #[error("OANDA is unavailable: {0}")]
+    OandaUnavailable(String),
+    #[error("Tradier request error: {0}")]
+    TradierError(String),
+    /// Failure which may not happen again, e.g. a rate limited request or a server error
+    #[error("Tradier is unavailable: {0}")]
+    TradierUnavailable(String),
+    #[error("Unknown broker account: {0}")]
+    UnknownAccount(String),
+    #[error("Broker not configured: {0}")]
+    NotConfigured(String),
+    #[error("Playback error: {0}")]
+    PlaybackError(String),
+    #[cfg(feature = "test-broker")]
+    #[error("Mock broker error: {0}")]
+    MockError(String),
+}
+
+impl BrokerClientError {
+    /// Whether the broker rejected the request for exceeding its rate limit.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            // NOTE: errors of all apca endpoints share the variant name and are kept as their debug
+            // representation
+            BrokerClientError::AlpacaError(message)
+            | BrokerClientError::AlpacaUnavailable(message) => {
+                message.contains("RateLimitExceeded")
+            }
+            // NOTE: OANDA and Tradier errors start with the status of the response
+            BrokerClientError::OandaUnavailable(message)
+            | BrokerClientError::TradierUnavailable(message) => message.starts_with("429"),
+            _ => false,
+        }
+    }
+
+    /// Whether the broker rejected the credentials of the account.
+    pub fn is_authentication_failure(&self) -> bool {
+        match self {
+            BrokerClientError::AlpacaError(message)
+            | BrokerClientError::AlpacaUnavailable(message) => {
+                message.contains("AuthenticationFailed")
+            }
+            BrokerClientError::OandaError(message) | BrokerClientError::TradierError(message) => {
+                message.starts_with("401")
+            }
+            _ => false,
+        }
+    }
 }
 
 #[axum::async_trait]
 pub trait BrokerClient: Send + Sync {
-    type ActivitiesRequest;
-    type NewOrderRequest;
-    type OrdersRequest;
-    type OrderUdateRequest;
+    // NOTE: requests are serializable so broker calls can be recorded, see `recorder`
+    type ActivitiesRequest: Serialize + Send;
+    type NewOrderRequest: Serialize + Send;
+    type OrdersRequest: Serialize + Send;
+    type OrderUdateRequest: Serialize + Send;
+
+    /// Translate a broker-agnostic order into the broker specific order request.
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest;
+
+    /// Whether orders with option legs can be translated, see `NewOrder::legs`.
+    fn supports_options(&self) -> bool {
+        false
+    }
 
     async fn get_account(&self) -> Result<Account, BrokerClientError>;
     async fn get_activities(
@@ -47,6 +351,7 @@ pub trait BrokerClient: Send + Sync {
     ) -> Result<Vec<Activity>, BrokerClientError>;
     async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError>;
     async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError>;
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError>;
     async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError>;
     async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError>;
     async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError>;
@@ -74,6 +379,10 @@ impl BrokerClient for Arc<AlpacaClient> {
     type OrdersRequest = apca_orders::OrdersReq;
     type OrderUdateRequest = apca_order::ChangeReq;
 
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        alpaca_order_request(new_order)
+    }
+
     async fn get_account(&self) -> Result<Account, BrokerClientError> {
         let result = self.issue::<apca_account::Get>(&()).await;
 
@@ -114,7 +423,7 @@ impl BrokerClient for Arc<AlpacaClient> {
     async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
         let asset_req = apca_assets::AssetsReq {
             status: apca_asset::Status::Active,
-            class: class.into(),
+            class: class.try_into()?,
         };
 
         let result = self.issue::<apca_assets::Get>(&asset_req).await;
@@ -126,6 +435,16 @@ impl BrokerClient for Arc<AlpacaClient> {
         }
     }
 
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        let result = self.issue::<apca_clock::Get>(&()).await;
+
+        if let Ok(clock) = result {
+            return Ok(Clock::AlpacaClock(clock));
+        } else {
+            return Err(BrokerClientError::AlpacaError(format!("{result:?}")));
+        }
+    }
+
     async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
         let result = self
             .issue::<apca_position::Get>(&apca_asset::Symbol::Sym(symbol))
@@ -187,10 +506,12 @@ impl BrokerClient for Arc<AlpacaClient> {
         new_order_req: Self::NewOrderRequest,
     ) -> Result<Order, BrokerClientError> {
         let result = self.issue::<apca_order::Post>(&new_order_req).await;
-        if let Ok(order) = result {
-            return Ok(Order::AlpacaOrder(order));
-        } else {
-            return Err(BrokerClientError::AlpacaError(format!("{result:?}")));
+        match result {
+            Ok(order) => Ok(Order::AlpacaOrder(order)),
+            Err(ref err) if is_transient(err) => {
+                Err(BrokerClientError::AlpacaUnavailable(format!("{result:?}")))
+            }
+            Err(_) => Err(BrokerClientError::AlpacaError(format!("{result:?}"))),
         }
     }
 
@@ -221,3 +542,267 @@ impl BrokerClient for Arc<AlpacaClient> {
         }
     }
 }
+
+/// Client of the broker a strategy trades on, see `Clients::venue`.
+pub enum VenueClient {
+    Alpaca(ScheduledClient<Arc<AlpacaClient>>),
+    Oanda(ScheduledClient<Arc<OandaClient>>),
+    Tradier(ScheduledClient<Arc<TradierClient>>),
+}
+
+impl VenueClient {
+    /// See `RequestScheduler::is_circuit_open`.
+    pub fn is_circuit_open(&self) -> bool {
+        match self {
+            VenueClient::Alpaca(client) => client.is_circuit_open(),
+            VenueClient::Oanda(client) => client.is_circuit_open(),
+            VenueClient::Tradier(client) => client.is_circuit_open(),
+        }
+    }
+}
+
+/// Order request of the broker of a `VenueClient`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum VenueOrderRequest {
+    Alpaca(Box<apca_order::OrderReq>),
+    Oanda(OandaOrderRequest),
+    Tradier(TradierOrderRequest),
+}
+
+#[axum::async_trait]
+impl BrokerClient for VenueClient {
+    // NOTE: the OANDA and Tradier clients take the Alpaca requests for everything but new orders
+    type ActivitiesRequest = apca_activities::ActivityReq;
+    type NewOrderRequest = VenueOrderRequest;
+    type OrdersRequest = apca_orders::OrdersReq;
+    type OrderUdateRequest = apca_order::ChangeReq;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        match self {
+            VenueClient::Alpaca(client) => {
+                VenueOrderRequest::Alpaca(Box::new(client.order_request(new_order)))
+            }
+            VenueClient::Oanda(client) => VenueOrderRequest::Oanda(client.order_request(new_order)),
+            VenueClient::Tradier(client) => {
+                VenueOrderRequest::Tradier(client.order_request(new_order))
+            }
+        }
+    }
+
+    fn supports_options(&self) -> bool {
+        match self {
+            VenueClient::Alpaca(client) => client.supports_options(),
+            VenueClient::Oanda(client) => client.supports_options(),
+            VenueClient::Tradier(client) => client.supports_options(),
+        }
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_account().await,
+            VenueClient::Oanda(client) => client.get_account().await,
+            VenueClient::Tradier(client) => client.get_account().await,
+        }
+    }
+
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_activities(activities_req).await,
+            VenueClient::Oanda(client) => client.get_activities(activities_req).await,
+            VenueClient::Tradier(client) => client.get_activities(activities_req).await,
+        }
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_asset(symbol).await,
+            VenueClient::Oanda(client) => client.get_asset(symbol).await,
+            VenueClient::Tradier(client) => client.get_asset(symbol).await,
+        }
+    }
+
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_assets(class).await,
+            VenueClient::Oanda(client) => client.get_assets(class).await,
+            VenueClient::Tradier(client) => client.get_assets(class).await,
+        }
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_clock().await,
+            VenueClient::Oanda(client) => client.get_clock().await,
+            VenueClient::Tradier(client) => client.get_clock().await,
+        }
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_position(symbol).await,
+            VenueClient::Oanda(client) => client.get_position(symbol).await,
+            VenueClient::Tradier(client) => client.get_position(symbol).await,
+        }
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_positions().await,
+            VenueClient::Oanda(client) => client.get_positions().await,
+            VenueClient::Tradier(client) => client.get_positions().await,
+        }
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.delete_position(symbol).await,
+            VenueClient::Oanda(client) => client.delete_position(symbol).await,
+            VenueClient::Tradier(client) => client.delete_position(symbol).await,
+        }
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_order_by_client_id(client_id).await,
+            VenueClient::Oanda(client) => client.get_order_by_client_id(client_id).await,
+            VenueClient::Tradier(client) => client.get_order_by_client_id(client_id).await,
+        }
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.get_orders(orders_req).await,
+            VenueClient::Oanda(client) => client.get_orders(orders_req).await,
+            VenueClient::Tradier(client) => client.get_orders(orders_req).await,
+        }
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        match (self, new_order_req) {
+            (VenueClient::Alpaca(client), VenueOrderRequest::Alpaca(request)) => {
+                client.create_order(*request).await
+            }
+            (VenueClient::Oanda(client), VenueOrderRequest::Oanda(request)) => {
+                client.create_order(request).await
+            }
+            (VenueClient::Tradier(client), VenueOrderRequest::Tradier(request)) => {
+                client.create_order(request).await
+            }
+            (VenueClient::Alpaca(_), request) => Err(BrokerClientError::AlpacaError(format!(
+                "Order request of another broker: {request:?}"
+            ))),
+            (VenueClient::Oanda(_), request) => Err(BrokerClientError::OandaError(format!(
+                "Order request of another broker: {request:?}"
+            ))),
+            (VenueClient::Tradier(_), request) => Err(BrokerClientError::TradierError(format!(
+                "Order request of another broker: {request:?}"
+            ))),
+        }
+    }
+
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.update_order(order_id, update_req).await,
+            VenueClient::Oanda(client) => client.update_order(order_id, update_req).await,
+            VenueClient::Tradier(client) => client.update_order(order_id, update_req).await,
+        }
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        match self {
+            VenueClient::Alpaca(client) => client.delete_order(order_id).await,
+            VenueClient::Oanda(client) => client.delete_order(order_id).await,
+            VenueClient::Tradier(client) => client.delete_order(order_id).await,
+        }
+    }
+}
+
+/// Alpaca order request of a broker-agnostic order.
+pub(crate) fn alpaca_order_request(new_order: &NewOrder) -> apca_order::OrderReq {
+    let side = match new_order.side {
+        OrderSide::Buy => apca_order::Side::Buy,
+        OrderSide::Sell => apca_order::Side::Sell,
+    };
+
+    let (class, stop_loss) = match new_order.stop_loss_price {
+        Some(price) => (
+            apca_order::Class::OneTriggersOther,
+            Some(apca_order::StopLoss::Stop(decimal_to_num(&price))),
+        ),
+        None => (apca_order::Class::Simple, None),
+    };
+
+    let amount = match new_order.notional {
+        Some(notional) => apca_order::Amount::notional(decimal_to_num(&notional)),
+        None => apca_order::Amount::quantity(decimal_to_num(&new_order.quantity)),
+    };
+
+    let time_in_force = match new_order.time_in_force {
+        TimeInForce::Day => apca_order::TimeInForce::Day,
+        TimeInForce::Gtc => apca_order::TimeInForce::UntilCanceled,
+        TimeInForce::Ioc => apca_order::TimeInForce::ImmediateOrCancel,
+        TimeInForce::Fok => apca_order::TimeInForce::FillOrKill,
+        TimeInForce::Opg => apca_order::TimeInForce::UntilMarketOpen,
+        TimeInForce::Cls => apca_order::TimeInForce::UntilMarketClose,
+    };
+
+    apca_order::OrderReqInit {
+        class,
+        type_: match new_order.limit_price {
+            Some(_) => apca_order::Type::Limit,
+            None => apca_order::Type::Market,
+        },
+        time_in_force,
+        limit_price: new_order.limit_price.as_ref().map(decimal_to_num),
+        stop_loss,
+        extended_hours: new_order.extended_hours,
+        client_order_id: Some(new_order.client_order_id.clone()),
+        ..Default::default()
+    }
+    .init(new_order.ticker.clone(), side, amount)
+}
+
+/// Whether a failed order submission may succeed when sent again, i.e. it was rate limited, hit a
+/// server error or never got a response.
+fn is_transient(err: &RequestError<apca_order::PostError>) -> bool {
+    match err {
+        RequestError::Endpoint(apca_order::PostError::RateLimitExceeded(_)) => true,
+        RequestError::Endpoint(apca_order::PostError::UnexpectedStatus(status, _)) => {
+            status.is_server_error()
+        }
+        RequestError::Endpoint(_) => false,
+        RequestError::Hyper(_) | RequestError::Io(_) => true,
+    }
+}
+
+/// Uuid of a numeric broker order id, orders are updated and canceled by Uuid at every broker.
+pub fn order_uuid(id: &str) -> Option<Uuid> {
+    id.parse().ok().map(Uuid::from_u128)
+}
+
+/// Numeric broker order id of a Uuid made by `order_uuid`.
+pub(crate) fn order_number(order_id: Uuid) -> String {
+    order_id.as_u128().to_string()
+}
+
+pub(crate) fn num_to_decimal(num: &Num) -> Decimal {
+    Decimal::from_str(&num.to_string()).unwrap_or_default()
+}
+
+pub(crate) fn decimal_to_num(decimal: &Decimal) -> Num {
+    Num::from_str(&decimal.normalize().to_string()).unwrap_or_default()
+}
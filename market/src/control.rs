@@ -0,0 +1,282 @@
+use axum::response::IntoResponse;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use strum_macros::AsRefStr;
+use uuid::Uuid;
+
+use crate::{
+    admin::StrategySwitch,
+    api::error::{ApiError, ErrorBody, ErrorCode, INTERNAL_SERVER_ERROR},
+    api_keys::Role,
+    audit::AuditEntry,
+    middleware::resolve_caller,
+    order::{Fill, OrderRecord},
+    rate_limit,
+    users::Caller,
+    App,
+};
+
+/// Command sent over the control channel, e.g.
+///
+/// ```json
+/// { "id": "1", "command": "flatten_symbol", "strategy_id": "…", "symbol": "AAPL" }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ControlRequest {
+    /// Set by the console to match the acknowledgment with the command
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub command: ControlCommand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, AsRefStr)]
+#[serde(tag = "command", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ControlCommand {
+    /// Disable the strategy like `POST /strategies/{id}/disable`
+    PauseStrategy {
+        strategy_id: Uuid,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Cancel an open order at the broker, `order_id` is the id of the order record
+    CancelOrder { order_id: Uuid },
+    /// Close the position of the strategy in `symbol` and cancel its open orders in `symbol`,
+    /// see `Core::flatten_symbol`
+    FlattenSymbol { strategy_id: Uuid, symbol: String },
+}
+
+impl ControlCommand {
+    /// Role the command requires, the one of the matching REST route.
+    pub fn required_role(&self) -> Role {
+        match self {
+            Self::PauseStrategy { .. } => Role::Admin,
+            Self::CancelOrder { .. } | Self::FlattenSymbol { .. } => Role::Trade,
+        }
+    }
+}
+
+/// Reply to every message of the control channel. `command` is missing when the message doesn't
+/// name one, `result` is set when `ok` and `error` otherwise.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ControlAck {
+    pub id: Option<String>,
+    pub command: Option<String>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+/// Command of a control channel message, or the acknowledgment of its failure when it isn't
+/// one. The id of the message is kept whenever it has one.
+pub fn parse(message: &str, request_id: &str) -> Result<ControlRequest, ControlAck> {
+    let value: Value = serde_json::from_str(message)
+        .map_err(|err| invalid_command(None, None, &err, request_id))?;
+    let id = value.get("id").and_then(Value::as_str).map(str::to_owned);
+    let command = value
+        .get("command")
+        .and_then(Value::as_str)
+        .map(str::to_owned);
+    serde_json::from_value(value).map_err(|err| invalid_command(id, command, &err, request_id))
+}
+
+fn invalid_command(
+    id: Option<String>,
+    command: Option<String>,
+    err: &serde_json::Error,
+    request_id: &str,
+) -> ControlAck {
+    ControlAck {
+        id,
+        command,
+        ok: false,
+        result: None,
+        error: Some(ErrorBody {
+            code: ErrorCode::InvalidJson,
+            message: format!("Invalid control command - {err}"),
+            request_id: Some(request_id.to_owned()),
+            details: Vec::new(),
+        }),
+    }
+}
+
+/// Caller of the API key `secret` a command is run for once it's admitted. The key is checked
+/// again for every command, so commands of revoked or demoted keys are refused, and every command
+/// takes from the bucket of the key like a request of the channel route.
+pub async fn admit(app: &App, secret: &str) -> Result<Caller, ApiError> {
+    let Some(caller) = resolve_caller(app, secret).await? else {
+        return Err(ApiError::Unauthorized(
+            "API key isn't correct or not found".to_owned(),
+        ));
+    };
+    if let Err(throttled) = app
+        .rate_limiter
+        .check(secret, rate_limit::route_group("/control"))
+    {
+        return Err(ApiError::TooManyRequests(format!(
+            "Rate limit of control commands exceeded, retry after {}s",
+            throttled.retry_after.as_secs_f64().ceil() as u64
+        )));
+    }
+
+    Ok(caller)
+}
+
+/// Run the command of a control channel message and acknowledge it.
+pub async fn handle(
+    app: &App,
+    caller: &Caller,
+    request: ControlRequest,
+    request_id: &str,
+) -> ControlAck {
+    let command = request.command.as_ref().to_owned();
+    match execute(app, caller, &request.command, request_id).await {
+        Ok(result) => ControlAck {
+            id: request.id,
+            command: Some(command),
+            ok: true,
+            result: Some(result),
+            error: None,
+        },
+        Err(err) => {
+            tracing::warn!("Control command {} failed, error: {}", command, err);
+            rejected(request.id, Some(command), err, request_id)
+        }
+    }
+}
+
+/// Acknowledgment of a message whose command failed or wasn't run.
+pub fn rejected(
+    id: Option<String>,
+    command: Option<String>,
+    err: ApiError,
+    request_id: &str,
+) -> ControlAck {
+    let mut error = error_body(err);
+    error.request_id = Some(request_id.to_owned());
+    ControlAck {
+        id,
+        command,
+        ok: false,
+        result: None,
+        error: Some(error),
+    }
+}
+
+/// Run a command of the caller, with the checks of the matching REST route.
+pub async fn execute(
+    app: &App,
+    caller: &Caller,
+    command: &ControlCommand,
+    request_id: &str,
+) -> Result<Value, ApiError> {
+    let required = command.required_role();
+    if caller.role < required {
+        return Err(ApiError::Forbidden(format!(
+            "API key with {} role can't {}, {} role is required",
+            caller.role.as_ref(),
+            command.as_ref(),
+            required.as_ref()
+        )));
+    }
+
+    match command {
+        ControlCommand::PauseStrategy {
+            strategy_id,
+            reason,
+        } => {
+            let strategy = caller.strategy(&app.config, *strategy_id)?;
+            let switch = StrategySwitch {
+                enabled: false,
+                reason: reason.clone(),
+            };
+            let status = app
+                .switch_strategy(caller, strategy, &switch, Some(request_id))
+                .await?;
+            Ok(json!(status))
+        }
+        ControlCommand::CancelOrder { order_id } => {
+            // NOTE: like `DELETE /order`, broker accounts are shared by the strategies of every
+            // user
+            if !caller.is_operator() {
+                return Err(ApiError::Forbidden(
+                    "API key of a user can't cancel orders".to_owned(),
+                ));
+            }
+            let record = OrderRecord::fetch(&app.db, *order_id)
+                .await?
+                .ok_or_else(|| ApiError::NotFound(format!("Unknown order - {order_id}")))?;
+            if !record.is_open() {
+                return Err(ApiError::BadRequest(format!(
+                    "Order {order_id} is {}, only open orders can be canceled",
+                    record.status
+                )));
+            }
+            app.core.cancel_open_order(&record).await?;
+
+            AuditEntry::record(
+                &app.db,
+                caller,
+                "control.cancel_order",
+                &format!("order:{order_id}"),
+                json!({ "strategy_id": record.strategy_id, "ticker": record.ticker }),
+                Some(request_id),
+            )
+            .await?;
+
+            Ok(json!({ "order_id": order_id }))
+        }
+        ControlCommand::FlattenSymbol {
+            strategy_id,
+            symbol,
+        } => {
+            let strategy = caller.strategy(&app.config, *strategy_id)?;
+            let symbol = symbol.trim().to_uppercase();
+            let position = Fill::position(&app.db, strategy.id, &symbol).await?;
+            let orders = OrderRecord::fetch_open(&app.db).await?;
+            let has_orders = orders
+                .iter()
+                .any(|order| order.strategy_id == strategy.id && order.ticker == symbol);
+            if position == Decimal::ZERO && !has_orders {
+                return Err(ApiError::NotFound(format!(
+                    "Strategy {} has no position or open order in {symbol}",
+                    strategy.name
+                )));
+            }
+            let client = app
+                .clients
+                .venue(&strategy.broker, strategy.account.as_deref())?
+                .0;
+            let report = app.core.flatten_symbol(client, strategy, &symbol).await?;
+
+            AuditEntry::record(
+                &app.db,
+                caller,
+                "control.flatten_symbol",
+                &format!("strategy:{strategy_id}"),
+                json!({ "name": strategy.name, "symbol": symbol, "report": report }),
+                Some(request_id),
+            )
+            .await?;
+
+            Ok(json!(report))
+        }
+    }
+}
+
+/// Body of the error response of `err`.
+fn error_body(err: ApiError) -> ErrorBody {
+    err.into_response()
+        .extensions_mut()
+        .remove::<ErrorBody>()
+        .unwrap_or_else(|| ErrorBody {
+            code: ErrorCode::InternalError,
+            message: INTERNAL_SERVER_ERROR.to_owned(),
+            request_id: None,
+            details: Vec::new(),
+        })
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    bars::normalize_timeframe,
+    order::Fill,
+    pnl::{self, ClosedTrade},
+    strategy::Strategy,
+};
+
+/// Time entry signals of a strategy are ignored for after a trade closed with a loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cooldown {
+    Minutes(u64),
+    /// Bars of the timeframe of the signal
+    Bars(u32),
+}
+
+impl Cooldown {
+    /// Length of the cooldown for signals of `timeframe`. `None` for bars of a timeframe which
+    /// isn't a number of minutes, hours, days or weeks.
+    pub fn duration(&self, timeframe: &str) -> Option<Duration> {
+        match *self {
+            Cooldown::Minutes(minutes) => Some(Duration::minutes(minutes as i64)),
+            Cooldown::Bars(bars) => timeframe_duration(timeframe).map(|bar| bar * bars as i32),
+        }
+    }
+}
+
+/// Duration of a bar of `timeframe`, see `normalize_timeframe`.
+pub fn timeframe_duration(timeframe: &str) -> Option<Duration> {
+    let timeframe = normalize_timeframe(timeframe);
+    let unit = timeframe.chars().last()?;
+    let count: i64 = timeframe.strip_suffix(unit)?.parse().ok()?;
+
+    match unit {
+        'm' => Some(Duration::minutes(count)),
+        'h' => Some(Duration::hours(count)),
+        'd' => Some(Duration::days(count)),
+        'w' => Some(Duration::weeks(count)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CooldownState {
+    /// Entries are accepted
+    Ready,
+    /// The last trade closed with a loss, entries are ignored until `until`
+    CoolingDown { until: DateTime<Utc> },
+}
+
+/// State after the closed `trades` of a strategy at `now`. Only the last trade counts, a
+/// profitable exit ends the cooldown of an earlier loss.
+pub fn state(trades: &[ClosedTrade], cooldown: Duration, now: DateTime<Utc>) -> CooldownState {
+    match trades.last() {
+        Some(trade) if trade.pnl < Decimal::ZERO && trade.closed_at + cooldown > now => {
+            CooldownState::CoolingDown {
+                until: trade.closed_at + cooldown,
+            }
+        }
+        _ => CooldownState::Ready,
+    }
+}
+
+/// Cooldown state of a strategy for signals of `timeframe`, from its fills. Strategies without a
+/// cooldown are always ready.
+pub async fn strategy_state(
+    db: &PgPool,
+    strategy: &Strategy,
+    timeframe: &str,
+    now: DateTime<Utc>,
+) -> Result<CooldownState, sqlx::Error> {
+    let Some(cooldown) = strategy.cooldown else {
+        return Ok(CooldownState::Ready);
+    };
+    let Some(cooldown) = cooldown.duration(timeframe) else {
+        tracing::warn!(
+            "Cooldown of strategy {} can't be measured in bars of {}, signal not held back",
+            strategy.name,
+            timeframe
+        );
+        return Ok(CooldownState::Ready);
+    };
+
+    let fills = Fill::fetch_for_strategy(db, strategy.id).await?;
+    Ok(state(&pnl::closed_trades(&fills), cooldown, now))
+}
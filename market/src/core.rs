@@ -1,61 +1,2698 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
-use apca::{ApiInfo, Client as AlpacaClient};
-use config::{Config, ConfigError, File};
-use serde::Deserialize;
+use apca::api::v2::{order as apca_order, orders as apca_orders};
+use config::ConfigError;
+use rand_core::OsRng;
+use rust_decimal::{Decimal, RoundingStrategy};
+use sqlx::PgPool;
 use thiserror::Error as ThisError;
 use tokio::{
-    sync::mpsc::UnboundedReceiver,
+    sync::OnceCell,
     time::{sleep, Duration},
 };
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
-use uuid7::uuid7;
 
 use crate::{
+    admin,
     api::{
-        alert::{SignalType, WebhookAlertData},
-        objects::Broker,
+        alert::SignalType,
+        objects::{Broker, Order},
+    },
+    backtest::{self, HistoricalBar, Timeframe},
+    bars,
+    clients::{decimal_to_num, order_uuid, BrokerClient, BrokerClientError, Clients, VenueClient},
+    cooldown::{self, CooldownState},
+    dca::{self, DcaEntry},
+    debounce::SignalDebouncer,
+    divergence::ShadowExecution,
+    events::{Event, EventBus, InMemoryEventBus},
+    executions::{Execution, ExecutionPublisher, PositionChange},
+    feature_flags::{FeatureFlags, ENABLE_NEW_SIZING_ENGINE, ENABLE_SHORTING},
+    fill_model::{FillModel, Quote, SimulatedFill},
+    filters,
+    hooks::{OrderAnnotation, OrderOrigin, PlacedOrder, TradeHooks},
+    ladder,
+    leases::{Leases, StrategyLease},
+    market_data::{self, QuoteBook},
+    order::{
+        client_order_id, parse_client_order_id, Fill, NewOrder, OrderExpiry, OrderRecord,
+        OrderSide, PartialFills, TimeInForce, CRYPTO_TIME_IN_FORCE,
     },
-    clients::{BrokerClient, Clients},
+    pnl::{self, TradeStatistics},
+    precision::Precision,
+    preview::{OrderPreview, OrderPreviewRequest},
+    projections,
+    rebalance::{self, RebalanceReport, RebalanceRequest},
+    recorder::{BrokerRecorder, RecordingClient},
+    retry::{Backoff, RetryMetrics},
+    risk::{self, RiskMonitor, RiskViolation},
+    schedules::ActionReport,
+    simulation::AlertSimulation,
+    sizing::{self, BuyingPowerPolicy, ExecutionPath, KellySizing},
+    stops,
+    strategy::{CurrencyType, Strategy},
+    throttle::{OrderThrottle, ThrottleLimit},
+    trade_executor::Priority,
     trade_signal::TradeSignal,
-    App,
 };
 
-pub struct Core;
+/// Latest broker orders of an account checked for orders missing locally, the most the broker
+/// lists at once.
+const RECOVERED_ORDERS_LIMIT: usize = 500;
+
+/// Broker client orders are synced and canceled with.
+type OrderClient<'a> = RecordingClient<'a, VenueClient>;
+
+/// Stage of the processing of a trade signal, the first one not passing the signal on ends its
+/// processing.
+enum Stage<T> {
+    Passed(T),
+    /// Why the signal is ignored, which the stage already logged
+    Ignored(String),
+}
+
+/// Times the cancelation of an expired order is checked before a market order replaces it.
+const CANCELATION_CHECKS: usize = 3;
+const CANCELATION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Period of the background work of the core, see `Core::run`.
+pub const ORDER_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+/// Status of orders of dry run strategies, which are never sent to the broker.
+pub const SIMULATED_STATUS: &str = "simulated";
+
+pub struct Core {
+    db: PgPool,
+    clients: Arc<Clients>,
+    feature_flags: Arc<FeatureFlags>,
+    risk_monitor: Arc<RiskMonitor>,
+    throttle: OrderThrottle,
+    fill_model: FillModel,
+    recorder: Option<BrokerRecorder>,
+    events: Arc<dyn EventBus>,
+    executions: Option<ExecutionPublisher>,
+    leases: Leases,
+    quotes: Arc<QuoteBook>,
+    retry_metrics: RetryMetrics,
+    debouncer: SignalDebouncer,
+    precision: Precision,
+    hooks: Arc<TradeHooks>,
+    /// Strategies by id, their orders are looked after by the background work
+    strategies: HashMap<Uuid, Strategy>,
+    last_run: Mutex<Option<Instant>>,
+    // NOTE: held while orders are synced, so the background work and the trade updates stream
+    // don't both record the same executions
+    syncs: tokio::sync::Mutex<()>,
+}
 
 impl Core {
-    // NOTE: ?scheduled tasks
+    pub fn new(
+        db: PgPool,
+        clients: Arc<Clients>,
+        feature_flags: Arc<FeatureFlags>,
+        risk_monitor: Arc<RiskMonitor>,
+        throttle: OrderThrottle,
+        fill_model: FillModel,
+        recorder: Option<BrokerRecorder>,
+    ) -> Self {
+        Self {
+            db,
+            clients,
+            feature_flags,
+            risk_monitor,
+            throttle,
+            fill_model,
+            recorder,
+            events: Arc::new(InMemoryEventBus::new()),
+            executions: None,
+            leases: Leases::default(),
+            quotes: Arc::default(),
+            retry_metrics: RetryMetrics::default(),
+            debouncer: SignalDebouncer::default(),
+            precision: Precision::default(),
+            hooks: Arc::default(),
+            strategies: HashMap::new(),
+            last_run: Mutex::default(),
+            syncs: tokio::sync::Mutex::default(),
+        }
+    }
+
+    /// Apply the order policies of `strategies`, partial fills and time to live, to their orders
+    /// during the background work.
+    pub fn with_strategies(mut self, strategies: &[Strategy]) -> Self {
+        self.strategies = strategies
+            .iter()
+            .map(|strategy| (strategy.id, strategy.clone()))
+            .collect();
+        self
+    }
+
+    /// Round the prices and quantities of orders with `precision` before they're submitted.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Run `hooks` on every order placed, see `TradeExecutor::add_pre_trade_hook`.
+    pub fn with_hooks(mut self, hooks: Arc<TradeHooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Publish the live events on `events` instead of keeping them in memory.
+    pub fn with_events(mut self, events: Arc<dyn EventBus>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Publish the fills of the orders and the position changes they make with `executions`.
+    pub fn with_executions(mut self, executions: Option<ExecutionPublisher>) -> Self {
+        self.executions = executions;
+        self
+    }
+
+    /// Take the leases of strategies processing a signal with `leases`.
+    pub fn with_leases(mut self, leases: Leases) -> Self {
+        self.leases = leases;
+        self
+    }
+
+    /// Live events of the signals processed by the core.
+    pub fn events(&self) -> &Arc<dyn EventBus> {
+        &self.events
+    }
+
+    /// Live quotes used to price orders, filled by the market data stream.
+    pub fn quotes(&self) -> &Arc<QuoteBook> {
+        &self.quotes
+    }
+
+    /// Retries of order submissions since startup.
+    pub fn retry_metrics(&self) -> &RetryMetrics {
+        &self.retry_metrics
+    }
+
+    /// When the background work last ran, `None` until `run` is started.
+    pub fn last_run(&self) -> Option<Instant> {
+        *self.last_run.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Background work of the core. Keeps local orders and their fills in sync with the brokers
+    /// and cancels expired ones.
     pub async fn run(&self) -> Result<(), anyhow::Error> {
-        Ok(())
+        loop {
+            *self.last_run.lock().unwrap_or_else(|err| err.into_inner()) = Some(Instant::now());
+            if let Err(err) = self.sync_orders().await {
+                error!("Failed to sync orders, error: {:?}", err);
+            }
+            if let Err(err) = self.sweep_expired_orders().await {
+                error!("Failed to sweep expired orders, error: {:?}", err);
+            }
+            if let Err(err) = self.send_dca_chunks().await {
+                error!("Failed to send chunks of entries, error: {:?}", err);
+            }
+            sleep(ORDER_SYNC_INTERVAL).await;
+        }
     }
 
     pub async fn process_trade_signal<C: BrokerClient>(
         &self,
         client: C,
         trade_signal: TradeSignal,
-    ) -> Result<(), ()> {
-        // let order_request = client.create_order_request
-        // let order = client.create_order(new_order_req);
-        // match trade_signal.signal_type {
-        //     SignalType::OpenLong => {}
-        //     SignalType::OpenShort => {}
-        //     SignalType::StopLossUpdate => {}
-        // };
+    ) -> Result<(), TradeError> {
+        let trade_signal = match trade_signal.strategy.debounce_window {
+            Some(window) => {
+                let window = Duration::from_secs(window);
+                match self.debouncer.coalesce(trade_signal, window).await {
+                    Some(trade_signal) => trade_signal,
+                    None => return Ok(()),
+                }
+            }
+            None => trade_signal,
+        };
+
+        // NOTE: signals of a strategy are processed one at a time by all instances, so two of
+        // them can't both find the position open and order it
+        let Some(lease) =
+            StrategyLease::acquire(&self.db, trade_signal.strategy.id, &self.leases).await?
+        else {
+            return Err(TradeError::StrategyBusy(trade_signal.strategy.name.clone()));
+        };
+        let result = self.execute_trade_signal(client, &trade_signal).await;
+        if let Err(err) = lease.release(&self.db).await {
+            error!(
+                "Failed to release lease of strategy {}, error: {:?}",
+                trade_signal.strategy.name, err
+            );
+        }
+
+        result
+    }
+
+    /// Place the order of a signal holding the lease of its strategy.
+    async fn execute_trade_signal<C: BrokerClient>(
+        &self,
+        client: C,
+        trade_signal: &TradeSignal,
+    ) -> Result<(), TradeError> {
+        let client = RecordingClient::new(
+            client,
+            trade_signal.strategy.broker.clone(),
+            self.recorder.as_ref(),
+        );
+
+        let Stage::Passed((side, stop_loss)) = self.screen_signal(trade_signal).await? else {
+            return Ok(());
+        };
+        self.cancel_dca_entries(trade_signal, side).await?;
+
+        let Stage::Passed((mut new_order, price)) = self
+            .build_order(&client, trade_signal, side, stop_loss)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        if let Some(violation) = self
+            .check_risk(&trade_signal.strategy, &new_order, price)
+            .await?
+        {
+            warn!(
+                "Signal for {} of strategy {} rejected, {}",
+                trade_signal.ticker, trade_signal.strategy.name, violation.details
+            );
+            return Err(TradeError::RiskViolation(violation.details));
+        }
+
+        let entry_id = self.slice_entry(trade_signal, &mut new_order).await?;
+        let origin = OrderOrigin::Signal {
+            trade_signal,
+            entry_id,
+        };
+        let result = self
+            .place_order(
+                &client,
+                &trade_signal.strategy.broker,
+                &trade_signal.strategy,
+                origin,
+                &new_order,
+            )
+            .await;
+        // An entry whose first chunk failed isn't continued by the background work
+        if let (Err(_), Some(entry_id)) = (&result, entry_id) {
+            DcaEntry::cancel(&self.db, entry_id).await?;
+        }
+
+        result.map(|_| ())
+    }
+
+    /// Record and send an order, the one way orders of every origin reach the broker. The
+    /// pre-trade hooks run on the order first, any of them can veto it, then orders of signals
+    /// entering a position and chunks of entries take a slot of the throttle. The post-trade hooks
+    /// run on what became of the order. `broker` is the one of `client`. Returns the status of
+    /// the order.
+    async fn place_order<C: BrokerClient>(
+        &self,
+        client: &C,
+        broker: &Broker,
+        strategy: &Strategy,
+        origin: OrderOrigin<'_>,
+        new_order: &NewOrder,
+    ) -> Result<String, TradeError> {
+        let annotations = self.hooks.before_order(&origin, new_order).await?;
+
+        // NOTE: exits are never held back, they only take risk off
+        let enters = match origin {
+            OrderOrigin::Signal { trade_signal, .. } => {
+                Priority::of_signal(&self.db, trade_signal).await? == Priority::Entry
+            }
+            OrderOrigin::Chunk { .. } => true,
+            _ => false,
+        };
+        if enters {
+            if let Err(limit) = self.throttle.try_acquire(&new_order.ticker, strategy.id) {
+                warn!(
+                    "Order limit of {} reached for {}, order of strategy {} rejected",
+                    limit, new_order.ticker, strategy.name
+                );
+                return Err(TradeError::Throttled(limit));
+            }
+        }
+
+        OrderRecord::insert(&self.db, new_order, broker).await?;
+        match origin {
+            OrderOrigin::Signal {
+                entry_id: Some(entry_id),
+                ..
+            }
+            | OrderOrigin::Chunk { entry_id } => {
+                OrderRecord::set_entry(&self.db, new_order.id, entry_id).await?
+            }
+            OrderOrigin::TakeProfit { parent_order_id } => {
+                OrderRecord::set_parent(&self.db, new_order.id, parent_order_id).await?
+            }
+            _ => {}
+        }
+        for annotation in &annotations {
+            OrderAnnotation::insert(&self.db, annotation).await?;
+        }
+
+        if let Some(trade_signal) = origin.trade_signal().filter(|_| strategy.shadow) {
+            if let Err(err) = self.shadow_execute(new_order, trade_signal).await {
+                error!(
+                    "Failed to simulate shadow execution of order {}, error: {:?}",
+                    new_order.id, err
+                );
+            }
+        }
+
+        let result = self.send_order(client, new_order, strategy).await;
+        let placed = PlacedOrder {
+            origin,
+            order: new_order,
+            annotations: &annotations,
+            result: result.as_deref(),
+        };
+        self.hooks.after_order(&placed).await;
+
+        result
+    }
+
+    /// Split the order of an entry signal into the chunks of the time slicing of its strategy,
+    /// leaving the first one in `new_order`. Returns the id of the entry the chunks belong to,
+    /// `None` when the order is sent at once.
+    async fn slice_entry(
+        &self,
+        trade_signal: &TradeSignal,
+        new_order: &mut NewOrder,
+    ) -> Result<Option<Uuid>, TradeError> {
+        let Some(dca) = trade_signal.strategy.dca else {
+            return Ok(None);
+        };
+        if new_order.notional.is_some() || !new_order.legs.is_empty() {
+            return Ok(None);
+        }
+        if Priority::of_signal(&self.db, trade_signal).await? != Priority::Entry {
+            return Ok(None);
+        }
+
+        // Orders with a stop loss are in whole shares, so are their chunks
+        let lot_size = match new_order.stop_loss_price {
+            Some(_) => Decimal::ONE,
+            None => self.precision.lot_size(&new_order.ticker),
+        };
+        let Some(chunk_quantity) = dca::chunk_quantity(new_order.quantity, dca.chunks, lot_size)
+        else {
+            return Ok(None);
+        };
+
+        let entry_id: Uuid = uuid7::uuid7().into();
+        DcaEntry::insert(
+            &self.db,
+            entry_id,
+            new_order,
+            chunk_quantity,
+            dca,
+            &trade_signal.timeframe,
+        )
+        .await?;
+        info!(
+            "Entry {} for {} {} of strategy {} split into {} chunks every {}s",
+            entry_id,
+            new_order.quantity,
+            new_order.ticker,
+            trade_signal.strategy.name,
+            dca.chunks,
+            dca.interval
+        );
+        new_order.quantity = chunk_quantity;
+
+        Ok(Some(entry_id))
+    }
+
+    /// Cancel the time-sliced entries of the strategy of a signal in its ticker on the other
+    /// side, which the signal exits. Chunks still open are canceled at the broker, executed ones
+    /// are left to the exit.
+    async fn cancel_dca_entries(
+        &self,
+        trade_signal: &TradeSignal,
+        side: OrderSide,
+    ) -> Result<(), TradeError> {
+        if trade_signal.strategy.dca.is_none() {
+            return Ok(());
+        }
+        let entry_side = match side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+
+        let entries = DcaEntry::cancel_active(
+            &self.db,
+            trade_signal.strategy.id,
+            &trade_signal.ticker,
+            entry_side,
+        )
+        .await?;
+        for entry in entries {
+            for record in OrderRecord::fetch_for_entry(&self.db, entry.entry_id).await? {
+                if !record.is_open() {
+                    continue;
+                }
+                let (_, client) = self.order_client(&record)?;
+                if let Err(err) = client.delete_order(broker_order_id(&record)?).await {
+                    error!(
+                        "Failed to cancel chunk order {} of entry {}, error: {:?}",
+                        record.order_id, entry.entry_id, err
+                    );
+                }
+            }
+            info!(
+                "Entry {} of strategy {} exited after {} of {} chunks, chunks left canceled",
+                entry.entry_id, trade_signal.strategy.name, entry.chunks_sent, entry.chunks
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Run a trade signal through the processing as a dry run and report the order it would
+    /// place. Nothing is recorded or submitted, signals aren't held by the debouncer and don't
+    /// take slots of the throttle. Every risk rule the order violates is reported.
+    pub async fn simulate_trade_signal<C: BrokerClient>(
+        &self,
+        client: C,
+        mut trade_signal: TradeSignal,
+    ) -> Result<AlertSimulation, TradeError> {
+        trade_signal.strategy.dry_run = true;
+        let mut simulation = AlertSimulation::new(&trade_signal);
+
+        let (side, stop_loss) = match self.screen_signal(&trade_signal).await? {
+            Stage::Passed(screened) => screened,
+            Stage::Ignored(reason) => {
+                simulation.ignored = Some(reason);
+                return Ok(simulation);
+            }
+        };
+        let (new_order, price) = match self
+            .build_order(&client, &trade_signal, side, stop_loss)
+            .await?
+        {
+            Stage::Passed(built) => built,
+            Stage::Ignored(reason) => {
+                simulation.ignored = Some(reason);
+                return Ok(simulation);
+            }
+        };
+
+        simulation.risk_violations = self
+            .risk_monitor
+            .order_violations(&trade_signal.strategy, &new_order, price)
+            .await?;
+        let fills = self.paper_fills(&new_order, &trade_signal).await;
+        if let Some(execution) = ShadowExecution::from_fills(new_order.id, &fills) {
+            simulation.fill_price = Some(execution.avg_price);
+            simulation.fees = Some(fills.iter().map(|fill| fill.fee).sum());
+        }
+        simulation.price = Some(price);
+        simulation.order = Some(new_order);
+        Ok(simulation)
+    }
+
+    /// Side and stop loss of the order of a signal, unless trading, the strategy or its filters
+    /// keep the signal from trading.
+    async fn screen_signal(
+        &self,
+        trade_signal: &TradeSignal,
+    ) -> Result<Stage<(OrderSide, Decimal)>, TradeError> {
+        if let Some(reason) = self
+            .trading_block(&trade_signal.strategy, &trade_signal.timeframe)
+            .await?
+        {
+            warn!(
+                "Signal for {} of strategy {} ignored, {}",
+                trade_signal.ticker, trade_signal.strategy.name, reason
+            );
+            return Ok(Stage::Ignored(reason));
+        }
+
+        let (side, stop_loss) = match &trade_signal.signal_type {
+            SignalType::OpenLong(stop_loss) => (OrderSide::Buy, stop_loss.0),
+            SignalType::OpenShort(stop_loss) => (OrderSide::Sell, stop_loss.0),
+            SignalType::StopLossUpdate(_) => {
+                info!(
+                    "Stop loss updates are not supported yet, signal for {} ignored",
+                    trade_signal.ticker
+                );
+                return Ok(Stage::Ignored(
+                    "Stop loss updates are not supported yet".to_owned(),
+                ));
+            }
+        };
+
+        // NOTE: sells closing a held long stay possible, see `build_order`
+        if side == OrderSide::Sell
+            && !self.feature_flags.is_enabled(ENABLE_SHORTING).await
+            && Fill::position(&self.db, trade_signal.strategy.id, &trade_signal.ticker).await?
+                <= Decimal::ZERO
+        {
+            info!(
+                "Shorting is disabled, signal for {} of strategy {} ignored",
+                trade_signal.ticker, trade_signal.strategy.name
+            );
+            return Ok(Stage::Ignored("Shorting is disabled".to_owned()));
+        }
+
+        if let Some(reason) = filters::rejection(&self.db, trade_signal).await? {
+            info!(
+                "Signal for {} of strategy {} filtered out, {}",
+                trade_signal.ticker, trade_signal.strategy.name, reason
+            );
+            return Ok(Stage::Ignored(format!("Signal filtered out, {reason}")));
+        }
+
+        Ok(Stage::Passed((side, stop_loss)))
+    }
+
+    /// Why the strategy can't trade now, trading being halted, its daily loss limit or the one of
+    /// its portfolio reached, or its cooldown after a loss measured in bars of `timeframe`.
+    /// Checked for signals and the chunks of their entries alike.
+    async fn trading_block(
+        &self,
+        strategy: &Strategy,
+        timeframe: &str,
+    ) -> Result<Option<String>, TradeError> {
+        if self.feature_flags.is_halted().await {
+            return Ok(Some("Trading is halted".to_owned()));
+        }
+
+        if self.risk_monitor.has_daily_loss_limit(strategy)
+            && risk::is_strategy_halted(&self.db, strategy.id, chrono::Utc::now().date_naive())
+                .await?
+        {
+            return Ok(Some(
+                "Daily loss limit of the strategy or of its portfolio is reached".to_owned(),
+            ));
+        }
+
+        if let CooldownState::CoolingDown { until } =
+            cooldown::strategy_state(&self.db, strategy, timeframe, chrono::Utc::now()).await?
+        {
+            return Ok(Some(format!(
+                "Strategy is cooling down after a loss until {until}"
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// Order of a screened signal and the price it's sized at.
+    async fn build_order<C: BrokerClient>(
+        &self,
+        client: &C,
+        trade_signal: &TradeSignal,
+        side: OrderSide,
+        stop_loss: Decimal,
+    ) -> Result<Stage<(NewOrder, Decimal)>, TradeError> {
+        // Signals are routed to the canary by the id of the order they produce
+        let order_id: Uuid = uuid7::uuid7().into();
+        let execution_path = if self
+            .feature_flags
+            .is_enabled_for(ENABLE_NEW_SIZING_ENGINE, order_id)
+            .await
+        {
+            ExecutionPath::Canary
+        } else {
+            ExecutionPath::Stable
+        };
+
+        // The account is fetched once for the sizing and the buying power check of the signal
+        let account = OnceCell::new();
+
+        // Prefer the live price, the signal bar may be minutes old
+        let price = match self.quotes.latest(&trade_signal.ticker).await {
+            Some(quote) => quote.mid(),
+            None => *trade_signal.bar_data.close.as_ref(),
+        };
+
+        // Amounts given by the signal are taken as they are, sizing only applies without one
+        let quantity = match (trade_signal.notional, trade_signal.quantity) {
+            (Some(notional), _) => match sizing::notional_quantity(notional, price) {
+                Some(quantity) => quantity,
+                None => {
+                    info!(
+                        "Notional {} buys no {} at {}, signal of strategy {} ignored",
+                        notional, trade_signal.ticker, price, trade_signal.strategy.name
+                    );
+                    return Ok(Stage::Ignored(format!(
+                        "Notional {notional} buys no shares at {price}"
+                    )));
+                }
+            },
+            (None, Some(quantity)) => quantity,
+            (None, None) => {
+                let quantity = match execution_path {
+                    ExecutionPath::Stable => match &trade_signal.strategy.volatility_sizing {
+                        None => match &trade_signal.strategy.kelly_sizing {
+                            None => trade_signal.strategy.order_quantity,
+                            Some(kelly) => {
+                                let statistics =
+                                    self.trade_statistics(&trade_signal.strategy, kelly).await?;
+                                if statistics.trades < kelly.min_trades {
+                                    trade_signal.strategy.order_quantity
+                                } else {
+                                    let equity = account
+                                        .get_or_try_init(|| client.get_account())
+                                        .await?
+                                        .equity()
+                                        .amount;
+                                    match sizing::kelly_quantity(
+                                        &trade_signal.strategy,
+                                        kelly,
+                                        &statistics,
+                                        equity,
+                                        price,
+                                    ) {
+                                        Some(quantity) => quantity,
+                                        None => {
+                                            info!(
+                                                "Kelly sizing produced no quantity from {:?}, \
+                                                 signal for {} of strategy {} ignored",
+                                                statistics,
+                                                trade_signal.ticker,
+                                                trade_signal.strategy.name
+                                            );
+                                            return Ok(Stage::Ignored(
+                                                "Kelly sizing produced no quantity".to_owned(),
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        Some(volatility) => {
+                            let bars = self
+                                .recent_bars(
+                                    &trade_signal.ticker,
+                                    volatility.timeframe,
+                                    volatility.atr_period,
+                                )
+                                .await?;
+                            let equity = account
+                                .get_or_try_init(|| client.get_account())
+                                .await?
+                                .equity()
+                                .amount;
+                            let quantity = sizing::average_true_range(&bars, volatility.atr_period)
+                                .and_then(|atr| {
+                                    sizing::volatility_quantity(
+                                        &trade_signal.strategy,
+                                        volatility,
+                                        equity,
+                                        atr,
+                                    )
+                                });
+                            match quantity {
+                                Some(quantity) => quantity,
+                                None => {
+                                    info!(
+                                        "Volatility sizing produced no quantity from {} bars, \
+                                         signal for {} of strategy {} ignored",
+                                        bars.len(),
+                                        trade_signal.ticker,
+                                        trade_signal.strategy.name
+                                    );
+                                    return Ok(Stage::Ignored(
+                                        "Volatility sizing produced no quantity".to_owned(),
+                                    ));
+                                }
+                            }
+                        }
+                    },
+                    ExecutionPath::Canary => {
+                        let equity = account
+                            .get_or_try_init(|| client.get_account())
+                            .await?
+                            .equity();
+                        // Currency pairs risk their pips at the pip value in the account currency
+                        let quantity = match trade_signal.strategy.currency_type {
+                            CurrencyType::Forex => sizing::pip_risk_quantity(
+                                &trade_signal.strategy,
+                                equity.amount,
+                                &equity.currency,
+                                &trade_signal.ticker,
+                                price,
+                                stop_loss,
+                            ),
+                            _ => sizing::risk_based_quantity(
+                                &trade_signal.strategy,
+                                equity.amount,
+                                price,
+                                stop_loss,
+                            ),
+                        };
+                        match quantity {
+                            Some(quantity) => quantity,
+                            None => {
+                                info!(
+                                    "Sizing engine produced no quantity, signal for {} of \
+                                     strategy {} ignored",
+                                    trade_signal.ticker, trade_signal.strategy.name
+                                );
+                                return Ok(Stage::Ignored(
+                                    "Sizing engine produced no quantity".to_owned(),
+                                ));
+                            }
+                        }
+                    }
+                };
+
+                let Some(quantity) = sizing::weekend_adjusted_quantity(
+                    &trade_signal.strategy,
+                    quantity,
+                    chrono::Utc::now(),
+                ) else {
+                    info!(
+                        "Weekend size reduction left no quantity, signal for {} of strategy {} \
+                         ignored",
+                        trade_signal.ticker, trade_signal.strategy.name
+                    );
+                    return Ok(Stage::Ignored(
+                        "Weekend size reduction left no quantity".to_owned(),
+                    ));
+                };
+                quantity
+            }
+        };
+
+        // Orders are kept within the buying power instead of being rejected by the broker, short
+        // positions are opened in whole shares. Dry runs don't reach the broker.
+        let (quantity, notional) = match side {
+            OrderSide::Buy if trade_signal.strategy.dry_run => (quantity, trade_signal.notional),
+            OrderSide::Buy => {
+                let buying_power = account
+                    .get_or_try_init(|| client.get_account())
+                    .await?
+                    .buying_power()
+                    .amount;
+                let cost_price = trade_signal.limit_price.unwrap_or(price);
+                let cost = trade_signal.notional.unwrap_or(quantity * cost_price);
+                if cost <= buying_power {
+                    (quantity, trade_signal.notional)
+                } else if trade_signal.strategy.buying_power_policy == BuyingPowerPolicy::Reject {
+                    return Err(TradeError::InsufficientFunds(format!(
+                        "Order of {} {} costs {}, buying power is {}",
+                        quantity, trade_signal.ticker, cost, buying_power
+                    )));
+                } else {
+                    let fractional =
+                        trade_signal.notional.is_some() || sizing::is_fractional(quantity);
+                    match sizing::affordable_quantity(
+                        &trade_signal.strategy,
+                        quantity,
+                        buying_power,
+                        cost_price,
+                        fractional,
+                    ) {
+                        Some(affordable) => {
+                            info!(
+                                "Order of {} {} costs {} with buying power of {}, downsized to {}",
+                                quantity, trade_signal.ticker, cost, buying_power, affordable
+                            );
+                            let notional = trade_signal.notional.map(|_| {
+                                buying_power.round_dp_with_strategy(2, RoundingStrategy::ToZero)
+                            });
+                            (affordable, notional)
+                        }
+                        None => {
+                            info!(
+                                "Buying power {} buys no {} at {}, signal of strategy {} ignored",
+                                buying_power,
+                                trade_signal.ticker,
+                                cost_price,
+                                trade_signal.strategy.name
+                            );
+                            return Ok(Stage::Ignored(format!(
+                                "Buying power {buying_power} buys no shares at {cost_price}"
+                            )));
+                        }
+                    }
+                }
+            }
+            OrderSide::Sell => {
+                // NOTE: sells of a held long only close or reduce it, the rules of short positions
+                // apply to the quantity beyond it
+                let held = Fill::position(&self.db, trade_signal.strategy.id, &trade_signal.ticker)
+                    .await?
+                    .max(Decimal::ZERO);
+                let short = quantity - held;
+                if short <= Decimal::ZERO {
+                    (quantity, trade_signal.notional)
+                } else if !self.feature_flags.is_enabled(ENABLE_SHORTING).await {
+                    info!(
+                        "Shorting is disabled, only the long of strategy {} in {} is closed",
+                        trade_signal.strategy.name, trade_signal.ticker
+                    );
+                    (held, None)
+                } else {
+                    if trade_signal.notional.is_some() {
+                        return Err(TradeError::InvalidOrder(
+                            "Short positions can't be opened with notional orders".to_string(),
+                        ));
+                    }
+                    let asset = client.get_asset(trade_signal.ticker.clone()).await?;
+                    if !asset.shortable() {
+                        return Err(TradeError::NotShortable(
+                            trade_signal.ticker.clone(),
+                            "not shortable",
+                        ));
+                    }
+                    if !asset.easy_to_borrow() {
+                        return Err(TradeError::NotShortable(
+                            trade_signal.ticker.clone(),
+                            "hard to borrow",
+                        ));
+                    }
+                    let buying_power = account
+                        .get_or_try_init(|| client.get_account())
+                        .await?
+                        .buying_power()
+                        .amount;
+                    match sizing::short_quantity(short, buying_power, price) {
+                        Some(short) => (held + short, None),
+                        None if held > Decimal::ZERO => {
+                            info!(
+                                "Buying power {} shorts no {} at {}, only the long of strategy {} \
+                                 is closed",
+                                buying_power,
+                                trade_signal.ticker,
+                                price,
+                                trade_signal.strategy.name
+                            );
+                            (held, None)
+                        }
+                        None => {
+                            info!(
+                                "Buying power {} shorts no {} at {}, signal of strategy {} ignored",
+                                buying_power,
+                                trade_signal.ticker,
+                                price,
+                                trade_signal.strategy.name
+                            );
+                            return Ok(Stage::Ignored(format!(
+                                "Buying power {buying_power} shorts no shares at {price}"
+                            )));
+                        }
+                    }
+                }
+            }
+        };
+
+        let crypto = trade_signal.strategy.currency_type == CurrencyType::Crypto;
+        if crypto && quantity * price < sizing::MIN_CRYPTO_NOTIONAL {
+            info!(
+                "Order of {} {} at {} is below the crypto minimum of {}, signal of strategy {} \
+                 ignored",
+                quantity,
+                trade_signal.ticker,
+                price,
+                sizing::MIN_CRYPTO_NOTIONAL,
+                trade_signal.strategy.name
+            );
+            return Ok(Stage::Ignored(format!(
+                "Order is below the crypto minimum of {}",
+                sizing::MIN_CRYPTO_NOTIONAL
+            )));
+        }
+
+        let fractional = !crypto && (notional.is_some() || sizing::is_fractional(quantity));
+        if fractional {
+            let asset = client.get_asset(trade_signal.ticker.clone()).await?;
+            if !asset.fractionable() {
+                return Err(TradeError::NotFractionable(trade_signal.ticker.clone()));
+            }
+        }
+        let extended_hours = trade_signal
+            .extended_hours
+            .unwrap_or(trade_signal.strategy.extended_hours);
+        let mut new_order = NewOrder {
+            id: order_id,
+            strategy_id: trade_signal.strategy.id,
+            client_order_id: client_order_id(trade_signal.strategy.id, trade_signal.signal_id()),
+            ticker: trade_signal.ticker.clone(),
+            side,
+            quantity,
+            notional,
+            // Alpaca only accepts simple orders for crypto, fractional shares and in extended hours
+            stop_loss_price: (!crypto && !fractional && !extended_hours).then_some(stop_loss),
+            limit_price: trade_signal.limit_price,
+            time_in_force: trade_signal
+                .time_in_force
+                .unwrap_or(trade_signal.strategy.order_time_in_force()),
+            extended_hours,
+            execution_path,
+            request_id: trade_signal.request_id.clone(),
+            environment: self.clients.environment,
+            account: trade_signal.strategy.account.clone(),
+            legs: trade_signal.legs.clone(),
+        };
+        self.precision
+            .round_order(&mut new_order, trade_signal.strategy.currency_type);
+        if new_order.notional.is_none() && new_order.quantity.is_zero() {
+            return Err(TradeError::InvalidOrder(format!(
+                "Quantity {} of {} rounds down to zero",
+                quantity, trade_signal.ticker
+            )));
+        }
+
+        if !new_order.legs.is_empty() && !client.supports_options() {
+            return Err(TradeError::InvalidOrder(format!(
+                "{} doesn't support options orders",
+                trade_signal.strategy.broker.as_ref()
+            )));
+        }
+        if crypto && !CRYPTO_TIME_IN_FORCE.contains(&new_order.time_in_force) {
+            return Err(TradeError::InvalidOrder(format!(
+                "Crypto orders must be gtc or ioc orders, not {}",
+                new_order.time_in_force.as_ref()
+            )));
+        }
+        new_order.validate().map_err(TradeError::InvalidOrder)?;
+        if new_order.stop_loss_price.is_none() {
+            warn!(
+                "Order for {} of strategy {} is sent without its stop loss, crypto: {}, \
+                 fractional: {}, extended hours: {}",
+                trade_signal.ticker, trade_signal.strategy.name, crypto, fractional, extended_hours
+            );
+        }
+
+        Ok(Stage::Passed((new_order, price)))
+    }
+
+    /// Rebalance the account of the strategy to the target weights of the request. Every order
+    /// passes the risk checks of signal orders, orders violating them are rejected while the rest
+    /// of the batch goes on. Previews only run the checks.
+    pub async fn rebalance<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+        request: &RebalanceRequest,
+        request_id: Option<String>,
+    ) -> Result<RebalanceReport, TradeError> {
+        let client = RecordingClient::new(client, strategy.broker.clone(), self.recorder.as_ref());
+
+        if !request.dry_run && self.feature_flags.is_halted().await {
+            return Err(TradeError::TradingHalted);
+        }
+
+        let equity = client.get_account().await?.equity().amount;
+        let held = client.get_positions().await?;
+        let positions: HashMap<String, Decimal> = held
+            .iter()
+            .map(|position| (position.symbol().to_owned(), position.quantity()))
+            .collect();
+
+        // Live quotes first, then the last price of held positions and the latest broker quote
+        let mut prices = HashMap::new();
+        for ticker in request.weights.keys() {
+            let mut price = self.quotes.latest(ticker).await.map(|quote| quote.mid());
+            if price.is_none() {
+                price = held
+                    .iter()
+                    .find(|position| position.symbol() == ticker)
+                    .and_then(|position| position.current_price())
+                    .map(|price| price.amount);
+            }
+            if price.is_none() {
+                price = market_data::fetch_broker_quote(&self.clients, &strategy.broker, ticker)
+                    .await?
+                    .map(|quote| quote.mid());
+            }
+            let price = price
+                .filter(|price| price.is_sign_positive() && !price.is_zero())
+                .ok_or_else(|| TradeError::InvalidOrder(format!("No price for {ticker}")))?;
+            prices.insert(ticker.clone(), price);
+        }
+
+        let mut orders = rebalance::plan(strategy, equity, &request.weights, &positions, &prices);
+        for order in &mut orders {
+            // Rebalance orders have no signal, they're identified by their order id instead
+            let id: Uuid = uuid7::uuid7().into();
+            let mut new_order = NewOrder {
+                id,
+                strategy_id: strategy.id,
+                client_order_id: client_order_id(strategy.id, id),
+                ticker: order.ticker.clone(),
+                side: order.side,
+                quantity: order.quantity,
+                notional: None,
+                stop_loss_price: None,
+                limit_price: None,
+                time_in_force: strategy.order_time_in_force(),
+                extended_hours: false,
+                execution_path: ExecutionPath::Stable,
+                request_id: request_id.clone(),
+                environment: self.clients.environment,
+                account: strategy.account.clone(),
+                legs: Vec::new(),
+            };
+            self.precision
+                .round_order(&mut new_order, strategy.currency_type);
+            order.quantity = new_order.quantity;
+
+            if let Err(err) = new_order.validate() {
+                order.status = rebalance::REJECTED_STATUS.to_owned();
+                order.error = Some(err);
+                continue;
+            }
+            if let Some(violation) = self.check_risk(strategy, &new_order, order.price).await? {
+                warn!(
+                    "Rebalance order for {} of strategy {} rejected, {}",
+                    order.ticker, strategy.name, violation.details
+                );
+                order.status = rebalance::REJECTED_STATUS.to_owned();
+                order.error = Some(violation.details);
+                continue;
+            }
+            if request.dry_run {
+                continue;
+            }
+
+            let result = self
+                .place_order(
+                    &client,
+                    &strategy.broker,
+                    strategy,
+                    OrderOrigin::Rebalance,
+                    &new_order,
+                )
+                .await;
+            // Vetoed orders are never recorded
+            if !matches!(result, Err(TradeError::Vetoed(..))) {
+                order.order_id = Some(new_order.id);
+            }
+            match result {
+                Ok(status) => order.status = status,
+                Err(err) => {
+                    error!(
+                        "Rebalance order {} of strategy {} failed, error: {}",
+                        new_order.id, strategy.name, err
+                    );
+                    order.status = rebalance::REJECTED_STATUS.to_owned();
+                    order.error = Some(err.to_string());
+                }
+            }
+        }
+
+        Ok(RebalanceReport {
+            strategy_id: strategy.id,
+            equity,
+            dry_run: request.dry_run,
+            orders,
+        })
+    }
+
+    /// Close the positions of the strategy projected from its fills with market orders, after
+    /// canceling its open orders so their stop losses can't open positions the other way. Orders
+    /// skip the risk checks and go on while trading is halted as they only reduce risk. `client` is
+    /// the one of the venue of the strategy, positions held by another venue are closed on the
+    /// venue of their last fill. Positions whose order fails, or with an order in their ticker
+    /// the broker didn't confirm canceled, are reported while the rest are closed. Fails when
+    /// the strategy is busy processing a signal.
+    pub async fn flatten_strategy<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+    ) -> Result<ActionReport, TradeError> {
+        self.flatten(client, strategy, None).await
+    }
+
+    /// Close the position of the strategy in `ticker` like `flatten_strategy`, only the open
+    /// orders of the strategy in `ticker` are canceled.
+    pub async fn flatten_symbol<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+        ticker: &str,
+    ) -> Result<ActionReport, TradeError> {
+        self.flatten(client, strategy, Some(ticker)).await
+    }
+
+    async fn flatten<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+        ticker: Option<&str>,
+    ) -> Result<ActionReport, TradeError> {
+        // NOTE: a signal processed meanwhile could otherwise enter the position again
+        let Some(lease) = StrategyLease::acquire(&self.db, strategy.id, &self.leases).await? else {
+            return Err(TradeError::StrategyBusy(strategy.name.clone()));
+        };
+        let result = self.flatten_leased(client, strategy, ticker).await;
+        if let Err(err) = lease.release(&self.db).await {
+            error!(
+                "Failed to release lease of strategy {}, error: {:?}",
+                strategy.name, err
+            );
+        }
+
+        result
+    }
+
+    async fn flatten_leased<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+        ticker: Option<&str>,
+    ) -> Result<ActionReport, TradeError> {
+        let client = RecordingClient::new(client, strategy.broker.clone(), self.recorder.as_ref());
+
+        let mut report = ActionReport::default();
+        let mut uncanceled = HashSet::new();
+        for (record, result) in self.cancel_matching_orders(strategy, ticker, true).await? {
+            let result = match result {
+                Ok(()) => self.confirm_cancelation(&record).await,
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(Some(_)) => continue,
+                Ok(None) => report.errors.push(format!(
+                    "{}: cancelation not confirmed by the broker",
+                    record.order_id
+                )),
+                Err(err) => report.errors.push(format!("{}: {}", record.order_id, err)),
+            }
+            uncanceled.insert(record.ticker);
+        }
+
+        let positions = projections::fetch_open_for_strategy(&self.db, strategy.id)
+            .await?
+            .into_iter()
+            .filter(|position| ticker.is_none_or(|ticker| position.ticker == ticker));
+        for position in positions {
+            if uncanceled.contains(&position.ticker) {
+                warn!(
+                    "Orders of strategy {} in {} not canceled, position left open",
+                    strategy.name, position.ticker
+                );
+                report.errors.push(format!(
+                    "{}: open orders not canceled, position not closed",
+                    position.ticker
+                ));
+                continue;
+            }
+
+            // NOTE: after a failover or routing the position is held by another venue than the
+            // one of the strategy, closing it anywhere else would open a position the other way
+            let holding = OrderRecord::fetch_last_filled(&self.db, strategy.id, &position.ticker)
+                .await?
+                .filter(|holding| {
+                    holding.broker != strategy.broker.as_ref()
+                        || holding.broker_account != strategy.account
+                });
+
+            let id: Uuid = uuid7::uuid7().into();
+            let mut new_order = NewOrder {
+                id,
+                strategy_id: strategy.id,
+                client_order_id: client_order_id(strategy.id, id),
+                ticker: position.ticker.clone(),
+                side: if position.quantity.is_sign_positive() {
+                    OrderSide::Sell
+                } else {
+                    OrderSide::Buy
+                },
+                quantity: position.quantity.abs(),
+                notional: None,
+                stop_loss_price: None,
+                limit_price: None,
+                time_in_force: strategy.order_time_in_force(),
+                extended_hours: false,
+                execution_path: ExecutionPath::Stable,
+                request_id: None,
+                environment: self.clients.environment,
+                account: match &holding {
+                    Some(holding) => holding.broker_account.clone(),
+                    None => strategy.account.clone(),
+                },
+                legs: Vec::new(),
+            };
+            self.precision
+                .round_order(&mut new_order, strategy.currency_type);
+            if let Err(err) = new_order.validate() {
+                report.errors.push(format!("{}: {}", position.ticker, err));
+                continue;
+            }
+
+            let result = match &holding {
+                Some(holding) => match self.order_client(holding) {
+                    Ok((broker, holding_client)) => {
+                        info!(
+                            "Position of strategy {} in {} closed on {} {} holding it",
+                            strategy.name,
+                            position.ticker,
+                            broker.as_ref(),
+                            holding.broker_account.as_deref().unwrap_or("default")
+                        );
+                        self.place_order(
+                            &holding_client,
+                            &broker,
+                            strategy,
+                            OrderOrigin::Flatten,
+                            &new_order,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                },
+                None => {
+                    self.place_order(
+                        &client,
+                        &strategy.broker,
+                        strategy,
+                        OrderOrigin::Flatten,
+                        &new_order,
+                    )
+                    .await
+                }
+            };
+            match result {
+                Ok(_) => report.orders.push(new_order.id),
+                Err(err) => {
+                    error!(
+                        "Order closing {} of strategy {} failed, error: {}",
+                        position.ticker, strategy.name, err
+                    );
+                    report.errors.push(format!("{}: {}", position.ticker, err));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Cancel the open orders of the strategy at the broker, its stop losses too with `stops`.
+    /// Orders failing to cancel are reported while the rest are canceled.
+    pub async fn cancel_strategy_orders(&self, strategy: &Strategy, stops: bool) -> ActionReport {
+        self.cancel_orders(strategy, None, stops).await
+    }
+
+    /// Cancel the open orders of the strategy, in `ticker` only when set, see
+    /// `cancel_strategy_orders`.
+    async fn cancel_orders(
+        &self,
+        strategy: &Strategy,
+        ticker: Option<&str>,
+        stops: bool,
+    ) -> ActionReport {
+        let mut report = ActionReport::default();
+        match self.cancel_matching_orders(strategy, ticker, stops).await {
+            Ok(canceled) => {
+                for (record, result) in canceled {
+                    match result {
+                        Ok(()) => report.orders.push(record.order_id),
+                        Err(err) => report.errors.push(format!("{}: {}", record.order_id, err)),
+                    }
+                }
+            }
+            Err(err) => report.errors.push(err.to_string()),
+        }
+
+        report
+    }
+
+    /// Cancel the open orders of the strategy at the broker like `cancel_orders`, returning each
+    /// of them with the result of its cancelation.
+    async fn cancel_matching_orders(
+        &self,
+        strategy: &Strategy,
+        ticker: Option<&str>,
+        stops: bool,
+    ) -> Result<Vec<(OrderRecord, Result<(), TradeError>)>, TradeError> {
+        let _syncs = self.syncs.lock().await;
+        let mut canceled = Vec::new();
+        for record in OrderRecord::fetch_open(&self.db).await? {
+            if record.strategy_id != strategy.id
+                || ticker.is_some_and(|ticker| record.ticker != ticker)
+                || (record.parent_order_id.is_some() && !stops)
+            {
+                continue;
+            }
+            let result = self.cancel_order(&record).await;
+            if let Err(err) = &result {
+                warn!(
+                    "Failed to cancel order {} of strategy {}, error: {}",
+                    record.order_id, strategy.name, err
+                );
+            }
+            canceled.push((record, result));
+        }
+
+        Ok(canceled)
+    }
+
+    /// Wait for the broker to confirm an order it was asked to cancel is done, checking it up to
+    /// `CANCELATION_CHECKS` times. Returns the synced record, `None` when the order is still
+    /// open. The syncs are only held while the order is synced, not between the checks.
+    async fn confirm_cancelation(
+        &self,
+        record: &OrderRecord,
+    ) -> Result<Option<OrderRecord>, TradeError> {
+        for check in 0..CANCELATION_CHECKS {
+            if check > 0 {
+                sleep(CANCELATION_CHECK_INTERVAL).await;
+            }
+            let _syncs = self.syncs.lock().await;
+            let Some(current) = OrderRecord::fetch(&self.db, record.order_id).await? else {
+                return Ok(None);
+            };
+            if !current.is_open() {
+                return Ok(Some(current));
+            }
+            self.sync_order(&current).await?;
+        }
+
+        Ok(OrderRecord::fetch(&self.db, record.order_id)
+            .await?
+            .filter(|record| !record.is_open()))
+    }
+
+    /// Cancel an open order at the broker and sync its record.
+    pub async fn cancel_open_order(&self, record: &OrderRecord) -> Result<(), TradeError> {
+        let _syncs = self.syncs.lock().await;
+        self.cancel_order(record).await
+    }
+
+    async fn cancel_order(&self, record: &OrderRecord) -> Result<(), TradeError> {
+        let (_, client) = self.order_client(record)?;
+        client.delete_order(broker_order_id(record)?).await?;
+        self.sync_order(record).await
+    }
+
+    /// Order the request would place for the strategy, with its estimated cost, the buying power
+    /// it takes, the risk rules it violates and the request the broker would get. Nothing is
+    /// recorded or submitted.
+    pub async fn preview_order<C: BrokerClient>(
+        &self,
+        client: C,
+        strategy: &Strategy,
+        request: &OrderPreviewRequest,
+        request_id: Option<String>,
+    ) -> Result<OrderPreview, TradeError> {
+        let client = RecordingClient::new(client, strategy.broker.clone(), self.recorder.as_ref());
+
+        // Limit orders cost at most their limit, market orders are estimated at the latest quote
+        let mut price = match request.limit_price {
+            Some(limit_price) => Some(limit_price),
+            None => self
+                .quotes
+                .latest(&request.ticker)
+                .await
+                .map(|quote| quote.mid()),
+        };
+        if price.is_none() {
+            price =
+                market_data::fetch_broker_quote(&self.clients, &strategy.broker, &request.ticker)
+                    .await?
+                    .map(|quote| quote.mid());
+        }
+        let price = price
+            .filter(|price| price.is_sign_positive() && !price.is_zero())
+            .ok_or_else(|| TradeError::InvalidOrder(format!("No price for {}", request.ticker)))?;
+
+        let quantity = match (request.quantity, request.notional) {
+            (Some(quantity), _) => quantity,
+            (None, Some(notional)) => {
+                sizing::notional_quantity(notional, price).ok_or_else(|| {
+                    TradeError::InvalidOrder(format!(
+                        "Notional {notional} buys no {} at {price}",
+                        request.ticker
+                    ))
+                })?
+            }
+            (None, None) => {
+                return Err(TradeError::InvalidOrder(
+                    "Either a quantity or a notional is required".to_string(),
+                ))
+            }
+        };
+
+        let id: Uuid = uuid7::uuid7().into();
+        let mut new_order = NewOrder {
+            id,
+            strategy_id: strategy.id,
+            client_order_id: client_order_id(strategy.id, id),
+            ticker: request.ticker.clone(),
+            side: request.side,
+            quantity,
+            notional: request.notional,
+            stop_loss_price: request.stop_loss_price,
+            limit_price: request.limit_price,
+            time_in_force: request
+                .time_in_force
+                .unwrap_or(strategy.order_time_in_force()),
+            extended_hours: request.extended_hours.unwrap_or(strategy.extended_hours),
+            execution_path: ExecutionPath::Stable,
+            request_id,
+            environment: self.clients.environment,
+            account: strategy.account.clone(),
+            legs: Vec::new(),
+        };
+        self.precision
+            .round_order(&mut new_order, strategy.currency_type);
+        if strategy.currency_type == CurrencyType::Crypto
+            && !CRYPTO_TIME_IN_FORCE.contains(&new_order.time_in_force)
+        {
+            return Err(TradeError::InvalidOrder(format!(
+                "Crypto orders must be gtc or ioc orders, not {}",
+                new_order.time_in_force.as_ref()
+            )));
+        }
+        new_order.validate().map_err(TradeError::InvalidOrder)?;
+
+        let price = new_order.limit_price.unwrap_or(price);
+        let estimated_cost = new_order.notional.unwrap_or(new_order.quantity * price);
+        let buying_power = client.get_account().await?.buying_power().amount;
+        // NOTE: sales of held shares take no buying power, only the part going short does
+        let buying_power_used = match new_order.side {
+            OrderSide::Buy => estimated_cost,
+            OrderSide::Sell => {
+                let held = client
+                    .get_positions()
+                    .await?
+                    .iter()
+                    .find(|position| position.symbol() == new_order.ticker)
+                    .map_or(Decimal::ZERO, |position| position.quantity())
+                    .max(Decimal::ZERO);
+                (new_order.quantity - held).max(Decimal::ZERO) * price
+            }
+        };
+
+        Ok(OrderPreview {
+            risk_violations: self
+                .risk_monitor
+                .order_violations(strategy, &new_order, price)
+                .await?,
+            broker_request: serde_json::to_value(client.order_request(&new_order))
+                .unwrap_or_default(),
+            order: new_order,
+            price,
+            estimated_cost,
+            buying_power: Some(buying_power),
+            buying_power_used: Some(buying_power_used),
+            buying_power_after: Some(buying_power - buying_power_used),
+        })
+    }
+
+    /// Statistics of the latest closed trades of a strategy, see `KellySizing::lookback_trades`.
+    async fn trade_statistics(
+        &self,
+        strategy: &Strategy,
+        kelly: &KellySizing,
+    ) -> Result<TradeStatistics, TradeError> {
+        let fills = Fill::fetch_for_strategy(&self.db, strategy.id).await?;
+        let trades: Vec<Decimal> = pnl::closed_trades(&fills)
+            .iter()
+            .map(|trade| trade.pnl)
+            .collect();
+        Ok(TradeStatistics::from_trades(
+            &trades[trades.len().saturating_sub(kelly.lookback_trades)..],
+        ))
+    }
+
+    /// Latest bars the average true range of a symbol over `period` bars is taken from, the ones
+    /// stored from alerts when there are enough of them, otherwise fetched from the data API.
+    async fn recent_bars(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        period: usize,
+    ) -> Result<Vec<HistoricalBar>, TradeError> {
+        let count = period + 1;
+        let stored = bars::fetch_latest(&self.db, symbol, timeframe, count).await?;
+        if stored.len() >= count {
+            return Ok(stored);
+        }
+
+        // NOTE: the lookback spans weekends and sessions closed overnight
+        let to = chrono::Utc::now();
+        let lookback = (timeframe.duration() * count as i32 * 3).max(chrono::Duration::days(4));
+        let mut fetched =
+            backtest::fetch_bars(&self.clients.alpaca(), symbol, timeframe, to - lookback, to)
+                .await?;
+        Ok(fetched.split_off(fetched.len().saturating_sub(count)))
+    }
+
+    /// Risk limit the order would violate, see `RiskMonitor`.
+    async fn check_risk(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        if let Some(violation) = self
+            .risk_monitor
+            .check_open_positions(strategy, new_order)
+            .await?
+        {
+            return Ok(Some(violation));
+        }
+        if let Some(violation) = self
+            .risk_monitor
+            .check_duplicate_position(strategy, new_order)
+            .await?
+        {
+            return Ok(Some(violation));
+        }
+        if let Some(violation) = self.risk_monitor.check_exposure(new_order, price).await? {
+            return Ok(Some(violation));
+        }
+        self.risk_monitor
+            .check_portfolio_exposure(strategy, new_order, price)
+            .await
+    }
+
+    /// Send a recorded order to the broker, or only simulate it for dry run strategies. Returns
+    /// the status of the order.
+    async fn send_order<C: BrokerClient>(
+        &self,
+        client: &C,
+        new_order: &NewOrder,
+        strategy: &Strategy,
+    ) -> Result<String, TradeError> {
+        if strategy.dry_run {
+            OrderRecord::update_status(&self.db, new_order.id, None, SIMULATED_STATUS).await?;
+            self.events.publish(Event::OrderSubmitted {
+                order_id: new_order.id,
+                strategy_id: new_order.strategy_id,
+                ticker: new_order.ticker.clone(),
+                side: new_order.side,
+                quantity: new_order.quantity,
+                status: SIMULATED_STATUS.to_owned(),
+            });
+            info!(
+                "Order {} of dry run strategy {} simulated",
+                new_order.id, strategy.name
+            );
+            return Ok(SIMULATED_STATUS.to_owned());
+        }
+
+        let backoff = Backoff::for_strategy(strategy);
+        match self.submit_order(client, new_order, backoff).await {
+            Ok(order) => {
+                OrderRecord::update_status(
+                    &self.db,
+                    new_order.id,
+                    Some(&order.broker_order_id()),
+                    &order.status(),
+                )
+                .await?;
+                // The order reserves buying power, so the cached account is outdated
+                if let Some(cache) = self
+                    .clients
+                    .cache(&strategy.broker, strategy.account.as_deref())
+                {
+                    cache.invalidate_account().await;
+                }
+                self.events.publish(Event::OrderSubmitted {
+                    order_id: new_order.id,
+                    strategy_id: new_order.strategy_id,
+                    ticker: new_order.ticker.clone(),
+                    side: new_order.side,
+                    quantity: new_order.quantity,
+                    status: order.status(),
+                });
+                info!(
+                    "Order {} submitted for strategy {} via {} path",
+                    new_order.id,
+                    strategy.name,
+                    new_order.execution_path.as_ref()
+                );
+                Ok(order.status())
+            }
+            Err(err) => {
+                OrderRecord::update_status(&self.db, new_order.id, None, "rejected").await?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Submit the order, retrying transient failures with the backoff of its strategy. Fatal
+    /// failures aren't retried.
+    async fn submit_order<C: BrokerClient>(
+        &self,
+        client: &C,
+        new_order: &NewOrder,
+        backoff: Backoff,
+    ) -> Result<Order, TradeError> {
+        let mut retries = 0;
+        loop {
+            let err = match client.create_order(client.order_request(new_order)).await {
+                Ok(order) => {
+                    if retries > 0 {
+                        self.retry_metrics.record_recovered();
+                    }
+                    return Ok(order);
+                }
+                Err(err) => TradeError::from(err),
+            };
+
+            if retries > 0 {
+                // A failed attempt may have reached the broker before its response was lost, the
+                // client order id makes the order findable instead of submitting it twice
+                if let Ok(order) = client
+                    .get_order_by_client_id(new_order.client_order_id.clone())
+                    .await
+                {
+                    self.retry_metrics.record_recovered();
+                    return Ok(order);
+                }
+            }
+            if !err.is_retryable() {
+                self.retry_metrics.record_fatal();
+                return Err(err);
+            }
+            if retries >= backoff.max_retries() {
+                self.retry_metrics.record_exhausted();
+                return Err(TradeError::MaxRetriesReached(
+                    new_order.id,
+                    retries,
+                    Box::new(err),
+                ));
+            }
+
+            retries += 1;
+            self.retry_metrics.record_retry();
+            let delay = backoff.delay(retries, &mut OsRng);
+            warn!(
+                "Order {} submission failed, retry {}/{} in {:?}, error: {}",
+                new_order.id,
+                retries,
+                backoff.max_retries(),
+                delay,
+                err
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Simulate the order with the paper fill model and record it as its shadow execution.
+    async fn shadow_execute(
+        &self,
+        new_order: &NewOrder,
+        trade_signal: &TradeSignal,
+    ) -> Result<(), sqlx::Error> {
+        let fills = self.paper_fills(new_order, trade_signal).await;
+        match ShadowExecution::from_fills(new_order.id, &fills) {
+            Some(execution) => execution.insert(&self.db).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Fills of the order by the paper fill model, against the live quote when there's a fresh
+    /// one and against the signal bar otherwise.
+    async fn paper_fills(
+        &self,
+        new_order: &NewOrder,
+        trade_signal: &TradeSignal,
+    ) -> Vec<SimulatedFill> {
+        let (price, spread) = match self.quotes.latest(&trade_signal.ticker).await {
+            Some(quote) => (quote.mid(), quote.spread()),
+            None => (*trade_signal.bar_data.close.as_ref(), Decimal::ZERO),
+        };
+        let quote = Quote {
+            price,
+            spread,
+            volume: trade_signal.bar_data.volume,
+        };
+        self.fill_model
+            .simulate(new_order.side, new_order.quantity, &quote, &mut OsRng)
+    }
+
+    /// Recreate the local records of orders of `strategies` the broker knows but the database
+    /// doesn't, e.g. after it was restored from a backup. Orders are attributed by their client
+    /// order id and synced right away, which records their executions as a fill. Returns the
+    /// number of recovered orders.
+    pub async fn recover_orders(&self, strategies: &[Strategy]) -> Result<usize, TradeError> {
+        let mut accounts: Vec<(&str, Option<&str>)> = strategies
+            .iter()
+            .flat_map(|strategy| {
+                let broker = strategy.broker.as_ref();
+                strategy
+                    .accounts()
+                    .into_iter()
+                    .map(move |account| (broker, account))
+            })
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+
+        let mut recovered = 0;
+        for (broker, account) in accounts {
+            let broker = broker
+                .parse::<Broker>()
+                .map_err(|_| TradeError::UnknownBroker(broker.to_owned()))?;
+            let client = RecordingClient::new(
+                self.clients.venue(&broker, account)?.0,
+                broker,
+                self.recorder.as_ref(),
+            );
+            let orders = client
+                .get_orders(apca_orders::OrdersReq {
+                    status: apca_orders::Status::All,
+                    limit: Some(RECOVERED_ORDERS_LIMIT),
+                    ..Default::default()
+                })
+                .await?;
+
+            for order in orders {
+                let Some((strategy_id, _)) = parse_client_order_id(order.client_order_id()) else {
+                    continue;
+                };
+                let Some(strategy) = strategies.iter().find(|strategy| {
+                    strategy.id == strategy_id && strategy.accounts().contains(&account)
+                }) else {
+                    continue;
+                };
+                if OrderRecord::fetch_by_client_order_id(&self.db, order.client_order_id())
+                    .await?
+                    .is_some()
+                {
+                    continue;
+                }
+
+                let new_order = NewOrder {
+                    id: uuid7::uuid7().into(),
+                    strategy_id,
+                    client_order_id: order.client_order_id().to_owned(),
+                    ticker: order.symbol().to_owned(),
+                    side: order.side(),
+                    quantity: order.quantity(),
+                    notional: order.notional().map(|notional| notional.amount),
+                    stop_loss_price: None,
+                    limit_price: order.limit_price().map(|price| price.amount),
+                    time_in_force: order.time_in_force(),
+                    extended_hours: order.extended_hours(),
+                    execution_path: ExecutionPath::Stable,
+                    request_id: None,
+                    environment: self.clients.environment,
+                    account: account.map(str::to_owned),
+                    legs: Vec::new(),
+                };
+                OrderRecord::insert(&self.db, &new_order, &strategy.broker).await?;
+                OrderRecord::update_status(
+                    &self.db,
+                    new_order.id,
+                    Some(&order.broker_order_id()),
+                    "pending",
+                )
+                .await?;
+                if let Some(record) = OrderRecord::fetch(&self.db, new_order.id).await? {
+                    self.sync_order(&record).await?;
+                }
+                warn!(
+                    "Order {} of strategy {} recovered from the broker",
+                    order.broker_order_id(),
+                    strategy.name
+                );
+                recovered += 1;
+            }
+        }
+
+        Ok(recovered)
+    }
+
+    /// Reconcile open local orders with their broker state, done every `ORDER_SYNC_INTERVAL`.
+    pub async fn sync_orders(&self) -> Result<(), TradeError> {
+        let _syncs = self.syncs.lock().await;
+        for record in OrderRecord::fetch_open(&self.db).await? {
+            if let Err(err) = self.sync_order(&record).await {
+                error!("Failed to sync order {}, error: {:?}", record.order_id, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sync the local order submitted as `client_order_id` with its broker state, e.g. when the
+    /// broker reports a change of it. Returns whether there is an open local order of the id.
+    pub async fn sync_order_update(&self, client_order_id: &str) -> Result<bool, TradeError> {
+        let _syncs = self.syncs.lock().await;
+        let Some(record) = OrderRecord::fetch_by_client_order_id(&self.db, client_order_id)
+            .await?
+            .filter(OrderRecord::is_open)
+        else {
+            return Ok(false);
+        };
+        self.sync_order(&record).await?;
+
+        Ok(true)
+    }
+
+    /// Cancel open limit orders without any execution older than the `order_ttl` of their
+    /// strategy, done every `ORDER_SYNC_INTERVAL`. Take profit orders wait for their target.
+    pub async fn sweep_expired_orders(&self) -> Result<(), TradeError> {
+        let _syncs = self.syncs.lock().await;
+        let now = chrono::Utc::now();
+        for record in OrderRecord::fetch_open(&self.db).await? {
+            if record.parent_order_id.is_some() {
+                continue;
+            }
+            let Some(strategy) = self.strategies.get(&record.strategy_id) else {
+                continue;
+            };
+            let Some(ttl) = strategy.order_ttl else {
+                continue;
+            };
+            if !record.is_expired(ttl.duration(), now) {
+                continue;
+            }
+            if let Err(err) = self.expire_order(strategy, &record, ttl.on_expiry).await {
+                error!(
+                    "Failed to expire order {}, error: {:?}",
+                    record.order_id, err
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the chunks of time-sliced entries which are due, done every `ORDER_SYNC_INTERVAL`
+    /// unless trading is halted.
+    pub async fn send_dca_chunks(&self) -> Result<(), TradeError> {
+        if self.feature_flags.is_halted().await {
+            return Ok(());
+        }
+        for entry in DcaEntry::fetch_due(&self.db).await? {
+            if let Err(err) = self.send_dca_chunk(&entry).await {
+                error!(
+                    "Failed to send chunk of entry {}, error: {:?}",
+                    entry.entry_id, err
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the next chunk of an entry as a market order, unless another instance sent it or
+    /// another signal of the strategy is processed. Chunks pass the checks of signal orders at
+    /// the current price, the entry is canceled when one fails them. Chunks stay due while the
+    /// venue is unavailable, the ticker has no price or the broker fails them, and are sent again
+    /// on the next run.
+    async fn send_dca_chunk(&self, entry: &DcaEntry) -> Result<(), TradeError> {
+        let Some(strategy) = self.strategies.get(&entry.strategy_id) else {
+            return Ok(());
+        };
+        // Busy strategies get their chunk sent on the next run
+        let Some(lease) = StrategyLease::acquire(&self.db, strategy.id, &self.leases).await? else {
+            return Ok(());
+        };
+        let result = self.execute_dca_chunk(strategy, entry).await;
+        if let Err(err) = lease.release(&self.db).await {
+            error!(
+                "Failed to release lease of strategy {}, error: {:?}",
+                strategy.name, err
+            );
+        }
+
+        result
+    }
+
+    async fn execute_dca_chunk(
+        &self,
+        strategy: &Strategy,
+        entry: &DcaEntry,
+    ) -> Result<(), TradeError> {
+        let side = entry
+            .side
+            .parse::<OrderSide>()
+            .map_err(|_| TradeError::InvalidOrder(format!("unknown side {}", entry.side)))?;
+        let quantity = entry.next_chunk_quantity();
+        let client = self
+            .clients
+            .venue(&strategy.broker, strategy.account.as_deref())?
+            .0;
+        if client.is_circuit_open() {
+            warn!(
+                "Chunk of entry {} of strategy {} held back, circuit breaker of {} is open",
+                entry.entry_id,
+                strategy.name,
+                strategy.broker.as_ref()
+            );
+            return Ok(());
+        }
+        let Some(price) = self.latest_price(&strategy.broker, &entry.ticker).await? else {
+            warn!(
+                "Chunk of entry {} of strategy {} held back, no price for {}",
+                entry.entry_id, strategy.name, entry.ticker
+            );
+            return Ok(());
+        };
+        if !entry.claim_chunk(&self.db).await? {
+            return Ok(());
+        }
+
+        let result = self
+            .place_dca_chunk(strategy, entry, client, side, quantity, price)
+            .await;
+        let reason = match result {
+            Ok(Stage::Passed(())) => {
+                info!(
+                    "Chunk {} of {} of entry {} of strategy {} sent",
+                    entry.chunks_sent + 1,
+                    entry.chunks,
+                    entry.entry_id,
+                    strategy.name
+                );
+                return Ok(());
+            }
+            Ok(Stage::Ignored(reason)) => reason,
+            Err(err) if err.is_rejection() => err.to_string(),
+            Err(err) => {
+                entry.release_chunk(&self.db).await?;
+                warn!(
+                    "Chunk {} of {} of entry {} of strategy {} failed and is sent again, error: {}",
+                    entry.chunks_sent + 1,
+                    entry.chunks,
+                    entry.entry_id,
+                    strategy.name,
+                    err
+                );
+                return Ok(());
+            }
+        };
+        DcaEntry::cancel(&self.db, entry.entry_id).await?;
+        warn!(
+            "Entry {} of strategy {} canceled after {} of {} chunks, {}",
+            entry.entry_id, strategy.name, entry.chunks_sent, entry.chunks, reason
+        );
+
+        Ok(())
+    }
+
+    /// Check and place a claimed chunk of an entry at `price` like the order of a signal.
+    async fn place_dca_chunk(
+        &self,
+        strategy: &Strategy,
+        entry: &DcaEntry,
+        client: VenueClient,
+        side: OrderSide,
+        quantity: Decimal,
+        price: Decimal,
+    ) -> Result<Stage<()>, TradeError> {
+        if admin::is_strategy_disabled(&self.db, strategy.id).await? {
+            return Ok(Stage::Ignored("Strategy is disabled".to_owned()));
+        }
+        let timeframe = entry.timeframe.as_deref().unwrap_or_default();
+        if let Some(reason) = self.trading_block(strategy, timeframe).await? {
+            return Ok(Stage::Ignored(reason));
+        }
+
+        let order_id: Uuid = uuid7::uuid7().into();
+        let mut new_order = NewOrder {
+            id: order_id,
+            strategy_id: strategy.id,
+            client_order_id: client_order_id(strategy.id, order_id),
+            ticker: entry.ticker.clone(),
+            side,
+            quantity,
+            notional: None,
+            stop_loss_price: entry.stop_loss_price,
+            limit_price: None,
+            time_in_force: strategy.order_time_in_force(),
+            extended_hours: false,
+            execution_path: ExecutionPath::Stable,
+            request_id: None,
+            environment: self.clients.environment,
+            account: strategy.account.clone(),
+            legs: Vec::new(),
+        };
+        self.precision
+            .round_order(&mut new_order, strategy.currency_type);
+        if let Some(violation) = self.check_risk(strategy, &new_order, price).await? {
+            return Err(TradeError::RiskViolation(violation.details));
+        }
+
+        let client = RecordingClient::new(client, strategy.broker.clone(), self.recorder.as_ref());
+        let origin = OrderOrigin::Chunk {
+            entry_id: entry.entry_id,
+        };
+        self.place_order(&client, &strategy.broker, strategy, origin, &new_order)
+            .await?;
+
+        Ok(Stage::Passed(()))
+    }
+
+    /// Latest price of `ticker`, the live quote or else the latest quote of the broker.
+    async fn latest_price(
+        &self,
+        broker: &Broker,
+        ticker: &str,
+    ) -> Result<Option<Decimal>, TradeError> {
+        let mut price = self.quotes.latest(ticker).await.map(|quote| quote.mid());
+        if price.is_none() {
+            price = market_data::fetch_broker_quote(&self.clients, broker, ticker)
+                .await?
+                .map(|quote| quote.mid());
+        }
+        Ok(price.filter(|price| price.is_sign_positive() && !price.is_zero()))
+    }
+
+    /// Move the protective stops of the open positions of strategies with stop management, see
+    /// `stops::next_stop`. Positions are tracked by the latest filled order of their ticker, its
+    /// stop loss leg is replaced at the broker. Returns the number of stops moved.
+    pub async fn manage_stops(&self) -> Result<usize, TradeError> {
+        let mut moved = 0;
+        for strategy in self.strategies.values() {
+            if strategy.stop_management.is_none() || strategy.dry_run {
+                continue;
+            }
+            for record in OrderRecord::fetch_latest_filled(&self.db, strategy.id).await? {
+                match self.manage_stop(strategy, &record).await {
+                    Ok(true) => moved += 1,
+                    Ok(false) => {}
+                    Err(err) => error!(
+                        "Failed to manage stop of order {}, error: {:?}",
+                        record.order_id, err
+                    ),
+                }
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Move the stop loss leg of an order entering a position still held, when the price moved
+    /// far enough in its favor.
+    async fn manage_stop(
+        &self,
+        strategy: &Strategy,
+        record: &OrderRecord,
+    ) -> Result<bool, TradeError> {
+        let Some(management) = &strategy.stop_management else {
+            return Ok(false);
+        };
+        let side = record
+            .side
+            .parse::<OrderSide>()
+            .map_err(|_| TradeError::InvalidOrder(format!("unknown side {}", record.side)))?;
+        let Some(entry) = record.filled_avg_price else {
+            return Ok(false);
+        };
+        // The latest order closed or reduced the position instead of entering it
+        let position = Fill::position(&self.db, strategy.id, &record.ticker).await?;
+        let held = match side {
+            OrderSide::Buy => position > Decimal::ZERO,
+            OrderSide::Sell => position < Decimal::ZERO,
+        };
+        if !held {
+            return Ok(false);
+        }
+
+        let (_, client) = self.order_client(record)?;
+        let order = client
+            .get_order_by_client_id(record.client_order_id.clone())
+            .await?;
+        let Some((leg_id, stop)) = order.stop_loss_leg() else {
+            return Ok(false);
+        };
+
+        let Some(price) = self.latest_price(&strategy.broker, &record.ticker).await? else {
+            return Ok(false);
+        };
+        let atr = match &management.trailing {
+            Some(stops::TrailingStop::Atr {
+                period, timeframe, ..
+            }) => {
+                let bars = self
+                    .recent_bars(&record.ticker, *timeframe, *period)
+                    .await?;
+                sizing::average_true_range(&bars, *period)
+            }
+            _ => None,
+        };
+
+        let Some(next) = stops::next_stop(management, side, entry, stop, price, atr) else {
+            return Ok(false);
+        };
+        let next = self
+            .precision
+            .round_stop_price(&record.ticker, strategy.currency_type, next);
+        let tighter = match side {
+            OrderSide::Buy => next > stop,
+            OrderSide::Sell => next < stop,
+        };
+        if !tighter {
+            return Ok(false);
+        }
+
+        client
+            .update_order(
+                leg_id,
+                apca_order::ChangeReqInit {
+                    stop_price: Some(decimal_to_num(&next)),
+                    ..Default::default()
+                }
+                .init(),
+            )
+            .await?;
+        info!(
+            "Stop of {} {} of strategy {} moved from {} to {} at {}",
+            record.side, record.ticker, strategy.name, stop, next, price
+        );
+
+        Ok(true)
+    }
+
+    /// Cancel an expired order and, depending on the policy, submit a market order for what's
+    /// left of it once the broker confirmed the cancelation.
+    async fn expire_order(
+        &self,
+        strategy: &Strategy,
+        record: &OrderRecord,
+        on_expiry: OrderExpiry,
+    ) -> Result<(), TradeError> {
+        let (broker, client) = self.order_client(record)?;
+        client.delete_order(broker_order_id(record)?).await?;
+        info!(
+            "Order {} of strategy {} unfilled after its time to live, canceled",
+            record.order_id, strategy.name
+        );
+        if on_expiry == OrderExpiry::Cancel {
+            return Ok(());
+        }
+
+        // Executions may have happened before the cancelation reached the broker, only the rest
+        // is submitted again
+        let mut canceled = None;
+        for _ in 0..CANCELATION_CHECKS {
+            self.sync_order(record).await?;
+            match OrderRecord::fetch(&self.db, record.order_id).await? {
+                Some(record) if record.status == "canceled" => {
+                    canceled = Some(record);
+                    break;
+                }
+                Some(record) if record.status == "filled" => return Ok(()),
+                _ => sleep(CANCELATION_CHECK_INTERVAL).await,
+            }
+        }
+        let Some(record) = canceled else {
+            warn!(
+                "Cancelation of order {} not confirmed, no market order submitted",
+                record.order_id
+            );
+            return Ok(());
+        };
+        if record.remaining_quantity() == Decimal::ZERO {
+            return Ok(());
+        }
+
+        let side = record
+            .side
+            .parse::<OrderSide>()
+            .map_err(|_| TradeError::InvalidOrder(format!("unknown side {}", record.side)))?;
+        let new_order = self.follow_up_order(&record, side, None);
+        let origin = OrderOrigin::Expiry {
+            order_id: record.order_id,
+        };
+        self.place_order(&client, &broker, strategy, origin, &new_order)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Client of the broker and credential set a local order was submitted with.
+    fn order_client(&self, record: &OrderRecord) -> Result<(Broker, OrderClient<'_>), TradeError> {
+        let broker = record
+            .broker
+            .parse::<Broker>()
+            .map_err(|_| TradeError::UnknownBroker(record.broker.clone()))?;
+        let client = self
+            .clients
+            .venue(&broker, record.broker_account.as_deref())?
+            .0;
+
+        Ok((
+            broker.clone(),
+            RecordingClient::new(client, broker, self.recorder.as_ref()),
+        ))
+    }
+
+    /// Publish a recorded fill and the position it leaves the strategy with, when executions are
+    /// published.
+    async fn publish_execution(&self, fill: &Fill) {
+        let Some(executions) = &self.executions else {
+            return;
+        };
+        executions.publish(Execution::Fill(fill.clone()));
+        match Fill::position(&self.db, fill.strategy_id, &fill.ticker).await {
+            Ok(quantity) => executions.publish(Execution::PositionChanged(PositionChange::new(
+                fill, quantity,
+            ))),
+            Err(err) => error!(
+                "Failed to fetch position of {} after fill {}, error: {:?}",
+                fill.ticker, fill.fill_id, err
+            ),
+        }
+    }
+
+    /// Fetch the broker state of a local order and record executions happened since the last
+    /// sync as a new fill.
+    async fn sync_order(&self, record: &OrderRecord) -> Result<(), TradeError> {
+        let (broker, client) = self.order_client(record)?;
+
+        let order = client
+            .get_order_by_client_id(record.client_order_id.clone())
+            .await?;
+        let status = order.status();
+        let filled_quantity = order.filled_quantity();
+        let filled_avg_price = order.filled_avg_price().map(|price| price.amount);
+        let delta = filled_quantity - record.filled_quantity;
+
+        let fill = (delta > Decimal::ZERO).then(|| {
+            // Broker reports only the average price of all executions, so the price of the latest
+            // one is derived from the change of the filled notional.
+            let prev_notional =
+                record.filled_quantity * record.filled_avg_price.unwrap_or_default();
+            let notional = filled_quantity * filled_avg_price.unwrap_or_default();
+
+            Fill {
+                fill_id: uuid7::uuid7().into(),
+                order_id: record.order_id,
+                strategy_id: record.strategy_id,
+                ticker: record.ticker.clone(),
+                side: record.side.clone(),
+                quantity: delta,
+                price: (notional - prev_notional) / delta,
+                fee: Decimal::ZERO,
+                // NOTE: the order may have been filled well before the sync
+                filled_at: order.filled_at().unwrap_or_else(chrono::Utc::now),
+            }
+        });
+
+        // NOTE: the fill and the filled quantity it's derived from are written together, so a
+        // failed sync doesn't record the same executions again
+        if delta != Decimal::ZERO || status != record.status {
+            let mut tx = self.db.begin().await?;
+            if let Some(fill) = &fill {
+                Fill::insert_in(&mut tx, fill).await?;
+            }
+            OrderRecord::update_fill(
+                &mut *tx,
+                record.order_id,
+                filled_quantity,
+                filled_avg_price,
+                &status,
+            )
+            .await?;
+            tx.commit().await?;
+        }
+
+        if let Some(fill) = fill {
+            self.publish_execution(&fill).await;
+            self.events.publish(Event::OrderFilled(fill));
+
+            match client.get_account().await {
+                Ok(account) => {
+                    let equity = account.equity().amount;
+                    if let Err(err) = self.risk_monitor.evaluate(&broker, equity).await {
+                        error!("Failed to evaluate drawdown, error: {:?}", err);
+                    }
+                }
+                Err(err) => error!(
+                    "Failed to fetch account to evaluate drawdown, error: {:?}",
+                    err
+                ),
+            }
+        }
+
+        match record.parent_order_id {
+            Some(parent_order_id) => {
+                self.sync_ladder_order(&client, &broker, record, parent_order_id)
+                    .await?
+            }
+            None if status == "filled" && record.status != "filled" => {
+                self.place_ladder(&client, record, &order).await?
+            }
+            None => {}
+        }
+
+        let policy = self
+            .strategies
+            .get(&record.strategy_id)
+            .map(|strategy| strategy.partial_fills)
+            .unwrap_or_default();
+        if let Some(stale_after) = policy.stale_after() {
+            if record.parent_order_id.is_none()
+                && delta == Decimal::ZERO
+                && status == record.status
+                && record.is_stale(stale_after, chrono::Utc::now())
+            {
+                self.resolve_stale_order(&client, &broker, record, policy)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Place the take profit orders of the ladder of the strategy of an entry order which just
+    /// filled, see `ladder::plan`. Entries without a stop loss have no risk to take multiples of
+    /// and get no ladder. The broker holds the quantity of resting orders, so the stop loss is
+    /// cut to the part of the position the targets leave to it before they're placed, and takes
+    /// back the part of the targets that failed.
+    pub async fn place_ladder<C: BrokerClient<OrderUdateRequest = apca_order::ChangeReq>>(
+        &self,
+        client: &C,
+        record: &OrderRecord,
+        order: &Order,
+    ) -> Result<(), TradeError> {
+        let Some(strategy) = self.strategies.get(&record.strategy_id) else {
+            return Ok(());
+        };
+        let Some(ladder) = &strategy.take_profit_ladder else {
+            return Ok(());
+        };
+        let broker = record
+            .broker
+            .parse::<Broker>()
+            .map_err(|_| TradeError::UnknownBroker(record.broker.clone()))?;
+        let side = record
+            .side
+            .parse::<OrderSide>()
+            .map_err(|_| TradeError::InvalidOrder(format!("unknown side {}", record.side)))?;
+        let (Some(entry), Some((leg_id, stop))) = (order.filled_avg_price(), order.stop_loss_leg())
+        else {
+            info!(
+                "Order {} of strategy {} has no stop loss, no take profit ladder placed",
+                record.order_id, strategy.name
+            );
+            return Ok(());
+        };
+        let exit_side = match side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+
+        let filled = order.filled_quantity();
+        let mut targets = Vec::new();
+        for (index, leg) in ladder::plan(ladder, side, entry.amount, stop, filled)
+            .into_iter()
+            .enumerate()
+        {
+            let mut new_order = self.follow_up_order(record, exit_side, Some(leg.limit_price));
+            // Syncs of the entry repeated after a failure place the same targets, which the
+            // broker and the orders table reject as duplicates
+            new_order.id =
+                Uuid::new_v5(&record.order_id, format!("take_profit.{index}").as_bytes());
+            new_order.client_order_id = client_order_id(record.strategy_id, new_order.id);
+            // Targets stay open until they're reached or the position is stopped out
+            new_order.quantity = leg.quantity;
+            new_order.time_in_force = TimeInForce::Gtc;
+            new_order.extended_hours = false;
+            self.precision
+                .round_order(&mut new_order, strategy.currency_type);
+            if new_order.quantity > Decimal::ZERO {
+                targets.push(new_order);
+            }
+        }
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let targeted: Decimal = targets.iter().map(|target| target.quantity).sum();
+        self.resize_stop(client, record.order_id, leg_id, filled - targeted)
+            .await?;
+
+        for new_order in &targets {
+            let origin = OrderOrigin::TakeProfit {
+                parent_order_id: record.order_id,
+            };
+            match self
+                .place_order(client, &broker, strategy, origin, new_order)
+                .await
+            {
+                Ok(_) => info!(
+                    "Take profit order {} for {} {} at {} placed for order {}",
+                    new_order.id,
+                    new_order.quantity,
+                    record.ticker,
+                    new_order.limit_price.unwrap_or_default(),
+                    record.order_id
+                ),
+                Err(err) => error!(
+                    "Failed to place take profit order {} for order {}, error: {:?}",
+                    new_order.id, record.order_id, err
+                ),
+            }
+        }
+
+        let children = OrderRecord::fetch_children(&self.db, record.order_id).await?;
+        let held = ladder::held_quantity(&children);
+        if held < targeted {
+            self.resize_stop(client, record.order_id, leg_id, filled - held)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep the stop loss of an entry in line with the take profit orders of its ladder: the
+    /// part of a target closed before it was reached goes back to the stop, or is closed at the
+    /// market once the position was stopped out. The take profit orders are canceled in turn
+    /// once the stop executed.
+    async fn sync_ladder_order<C: BrokerClient<OrderUdateRequest = apca_order::ChangeReq>>(
+        &self,
+        client: &C,
+        broker: &Broker,
+        record: &OrderRecord,
+        parent_order_id: Uuid,
+    ) -> Result<(), TradeError> {
+        let Some(parent) = OrderRecord::fetch(&self.db, parent_order_id).await? else {
+            return Ok(());
+        };
+        let Some(child) = OrderRecord::fetch(&self.db, record.order_id).await? else {
+            return Ok(());
+        };
+        let parent_order = client
+            .get_order_by_client_id(parent.client_order_id.clone())
+            .await?;
+
+        if parent_order.stopped_out() && child.is_open() {
+            client.delete_order(broker_order_id(&child)?).await?;
+            info!(
+                "Order {} stopped out, take profit order {} canceled",
+                parent.order_id, child.order_id
+            );
+            return Ok(());
+        }
+        // Only targets just closed with a part left unfilled change what the stop covers
+        if !record.is_open() || child.is_open() || child.remaining_quantity() <= Decimal::ZERO {
+            return Ok(());
+        }
+
+        if parent_order.stopped_out() {
+            let Some(strategy) = self.strategies.get(&child.strategy_id) else {
+                return Ok(());
+            };
+            let side = child
+                .side
+                .parse::<OrderSide>()
+                .map_err(|_| TradeError::InvalidOrder(format!("unknown side {}", child.side)))?;
+            let mut new_order = self.follow_up_order(&child, side, None);
+            new_order.id = Uuid::new_v5(&child.order_id, b"stopped_out");
+            new_order.client_order_id = client_order_id(child.strategy_id, new_order.id);
+            self.place_order(client, broker, strategy, OrderOrigin::Flatten, &new_order)
+                .await?;
+            warn!(
+                "Order {} stopped out, remaining {} of take profit order {} closed by order {}",
+                parent.order_id, new_order.quantity, child.order_id, new_order.id
+            );
+            return Ok(());
+        }
+
+        let Some((leg_id, _)) = parent_order.stop_loss_leg() else {
+            warn!(
+                "Take profit order {} of order {} closed with {} left, no stop loss to take it",
+                child.order_id,
+                parent.order_id,
+                child.remaining_quantity()
+            );
+            return Ok(());
+        };
+        let children = OrderRecord::fetch_children(&self.db, parent.order_id).await?;
+        let remaining = parent.filled_quantity - ladder::held_quantity(&children);
+        self.resize_stop(client, parent.order_id, leg_id, remaining)
+            .await
+    }
+
+    /// Resize the stop loss leg `leg_id` of entry order `order_id` to `quantity`, cancel it when
+    /// nothing is left to it.
+    async fn resize_stop<C: BrokerClient<OrderUdateRequest = apca_order::ChangeReq>>(
+        &self,
+        client: &C,
+        order_id: Uuid,
+        leg_id: Uuid,
+        quantity: Decimal,
+    ) -> Result<(), TradeError> {
+        if quantity <= Decimal::ZERO {
+            client.delete_order(leg_id).await?;
+            info!(
+                "Position of order {} taken by its take profit orders, stop loss canceled",
+                order_id
+            );
+        } else {
+            client
+                .update_order(
+                    leg_id,
+                    apca_order::ChangeReqInit {
+                        quantity: Some(decimal_to_num(&quantity)),
+                        ..Default::default()
+                    }
+                    .init(),
+                )
+                .await?;
+            info!("Stop loss of order {} resized to {}", order_id, quantity);
+        }
+
         Ok(())
     }
 
-    async fn order_request(&self, broker: Broker) -> Result<(), ()> {
+    /// Order for the remaining quantity of `record` at `limit_price`, a market order when `None`,
+    /// rounded to the increments of its ticker.
+    fn follow_up_order(
+        &self,
+        record: &OrderRecord,
+        side: OrderSide,
+        limit_price: Option<Decimal>,
+    ) -> NewOrder {
+        let order_id = uuid7::uuid7().into();
+        let mut new_order = NewOrder {
+            id: order_id,
+            strategy_id: record.strategy_id,
+            client_order_id: client_order_id(record.strategy_id, order_id),
+            ticker: record.ticker.clone(),
+            side,
+            quantity: record.remaining_quantity(),
+            notional: None,
+            stop_loss_price: None,
+            limit_price,
+            time_in_force: record
+                .time_in_force
+                .as_deref()
+                .and_then(|time_in_force| time_in_force.parse::<TimeInForce>().ok())
+                .unwrap_or_default(),
+            // Outside the regular session the broker accepts only limit orders
+            extended_hours: record.extended_hours && limit_price.is_some(),
+            execution_path: record
+                .execution_path
+                .parse()
+                .unwrap_or(ExecutionPath::Stable),
+            request_id: record.request_id.clone(),
+            environment: self.clients.environment,
+            account: record.broker_account.clone(),
+            legs: Vec::new(),
+        };
+        let currency_type = self
+            .strategies
+            .get(&record.strategy_id)
+            .map_or(CurrencyType::Stock, |strategy| strategy.currency_type);
+        self.precision.round_order(&mut new_order, currency_type);
+        new_order
+    }
+
+    /// Cancel the rest of a stale partially filled order or replace it by an order for the rest
+    /// at the latest quote, depending on the policy of its strategy. Executions of the replaced
+    /// order stay with its record, the replacement is tracked as an order of its own.
+    async fn resolve_stale_order<C: BrokerClient<OrderUdateRequest = apca_order::ChangeReq>>(
+        &self,
+        client: &C,
+        broker: &Broker,
+        record: &OrderRecord,
+        policy: PartialFills,
+    ) -> Result<(), TradeError> {
+        let broker_order_id = broker_order_id(record)?;
+
+        match policy {
+            PartialFills::Wait => {}
+            PartialFills::CancelRemainder { .. } => {
+                client.delete_order(broker_order_id).await?;
+                warn!(
+                    "Remaining {} of stale partially filled order {} canceled",
+                    record.remaining_quantity(),
+                    record.order_id
+                );
+            }
+            PartialFills::Reprice { .. } => {
+                let side = record.side.parse::<OrderSide>().map_err(|_| {
+                    TradeError::InvalidOrder(format!("unknown side {}", record.side))
+                })?;
+                let (Some(limit_price), Some(quote)) =
+                    (record.limit_price, self.quotes.latest(&record.ticker).await)
+                else {
+                    return Ok(());
+                };
+                let price = match side {
+                    OrderSide::Buy => quote.ask,
+                    OrderSide::Sell => quote.bid,
+                };
+                let new_order = self.follow_up_order(record, side, Some(price));
+                let Some(price) = new_order.limit_price else {
+                    return Ok(());
+                };
+                if price == limit_price {
+                    return Ok(());
+                }
+
+                let order = client
+                    .update_order(
+                        broker_order_id,
+                        apca_order::ChangeReqInit {
+                            quantity: Some(decimal_to_num(&new_order.quantity)),
+                            limit_price: Some(decimal_to_num(&price)),
+                            client_order_id: Some(new_order.client_order_id.clone()),
+                            ..Default::default()
+                        }
+                        .init(),
+                    )
+                    .await?;
+
+                OrderRecord::insert(&self.db, &new_order, broker).await?;
+                OrderRecord::update_status(
+                    &self.db,
+                    new_order.id,
+                    Some(&order.broker_order_id()),
+                    &order.status(),
+                )
+                .await?;
+                warn!(
+                    "Stale partially filled order {} repriced from {} to {}, remaining {} \
+                     continue as order {}",
+                    record.order_id, limit_price, price, new_order.quantity, new_order.id
+                );
+            }
+        }
+
         Ok(())
     }
 }
 
-// #[derive(Debug)]
-// pub struct Order {
-//     pub id: Uuid,
-//     pub ticker: String,
-//     pub order_type: AlertType,
-// }
+/// Id the broker knows a local order by, numeric ids are mapped to a Uuid, see
+/// `clients::order_uuid`.
+fn broker_order_id(record: &OrderRecord) -> Result<Uuid, TradeError> {
+    record
+        .broker_order_id
+        .as_deref()
+        .and_then(|id| id.parse().ok().or_else(|| order_uuid(id)))
+        .ok_or_else(|| {
+            TradeError::InvalidOrder(format!("order {} has no broker order id", record.order_id))
+        })
+}
 
 #[derive(Debug, ThisError)]
 pub enum StrategyManagerError {
@@ -75,12 +2712,65 @@ pub enum StrategyManagerError {
 pub enum TradeError {
     #[error("{0}")]
     InsufficientFunds(String),
-    // #[error("Order max retries reached. {0}")]
-    // MaxRetriesReached(Order),
+    #[error("Unknown broker - {0}")]
+    UnknownBroker(String),
+    #[error(transparent)]
+    BrokerClientError(#[from] BrokerClientError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Order {0} failed after {1} retries, {2}")]
+    MaxRetriesReached(Uuid, u8, Box<TradeError>),
+    #[error("Risk limit violated - {0}")]
+    RiskViolation(String),
+    #[error("Asset {0} is not fractionable")]
+    NotFractionable(String),
+    #[error("Invalid order - {0}")]
+    InvalidOrder(String),
+    #[error("{0} can't be sold short, it is {1}")]
+    NotShortable(String, &'static str),
+    #[error("Trading is halted")]
+    TradingHalted,
+    #[error("No venue of strategy {1} trades {0}")]
+    NoVenue(String, String),
+    #[error("Venue {0} holding the position in {1} is unavailable")]
+    VenueUnavailable(String, String),
+    #[error("Strategy {0} is busy processing another signal")]
+    StrategyBusy(String),
+    #[error("Strategy {0} was disabled while its signal was queued")]
+    StrategyDisabled(String),
+    #[error("Order vetoed by hook {0} - {1}")]
+    Vetoed(String, String),
+    #[error("Order limit of {0} reached")]
+    Throttled(ThrottleLimit),
 }
 
-// impl std::fmt::Display for Order {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         write!(f, "{{ id: {} }}", self.id)
-//     }
-// }
+impl TradeError {
+    /// Whether the order failed the checks of signal orders, rather than failing at the broker
+    /// or the database.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            TradeError::InsufficientFunds(_)
+                | TradeError::RiskViolation(_)
+                | TradeError::NotFractionable(_)
+                | TradeError::InvalidOrder(_)
+                | TradeError::NotShortable(..)
+                | TradeError::TradingHalted
+                | TradeError::StrategyDisabled(_)
+                | TradeError::Vetoed(..)
+                | TradeError::Throttled(_)
+        )
+    }
+
+    /// Whether the failure may not happen again, only broker unavailability is retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            TradeError::BrokerClientError(
+                BrokerClientError::AlpacaUnavailable(_)
+                    | BrokerClientError::OandaUnavailable(_)
+                    | BrokerClientError::TradierUnavailable(_)
+            )
+        )
+    }
+}
@@ -0,0 +1,409 @@
+use std::sync::Arc;
+
+use apca::Client as AlpacaClient;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use thiserror::Error as ThisError;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::{market_data, projections, strategy::Strategy, App};
+
+/// Announcements fetched at most per page
+const PAGE_LIMIT: usize = 1000;
+
+/// Splits and symbol changes of the traded symbols, fetched from the corporate actions endpoint
+/// of the Alpaca market data API and applied to the stored fills, orders and bars.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorporateActionsConfig {
+    /// Seconds between two checks
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// Days before today actions are fetched for
+    #[serde(default = "default_lookback_days")]
+    pub lookback_days: i64,
+}
+
+fn default_interval() -> u64 {
+    6 * 3600
+}
+
+fn default_lookback_days() -> i64 {
+    7
+}
+
+#[derive(Debug, ThisError)]
+pub enum CorporateActionsError {
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Corporate action changing the shares or the symbol of a position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// `new_rate` shares for every `old_rate` shares held before `ex_date`, a reverse split when
+    /// `new_rate` is the smaller one
+    Split {
+        id: String,
+        symbol: String,
+        old_rate: Decimal,
+        new_rate: Decimal,
+        ex_date: NaiveDate,
+    },
+    /// `old_symbol` trades as `new_symbol` from `process_date`
+    NameChange {
+        id: String,
+        old_symbol: String,
+        new_symbol: String,
+        process_date: NaiveDate,
+    },
+}
+
+impl CorporateAction {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Split { id, .. } | Self::NameChange { id, .. } => id,
+        }
+    }
+
+    /// Symbol the action is announced for.
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Split { symbol, .. } => symbol,
+            Self::NameChange { old_symbol, .. } => old_symbol,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            Self::Split { ex_date, .. } => *ex_date,
+            Self::NameChange { process_date, .. } => *process_date,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Split { .. } => "split",
+            Self::NameChange { .. } => "name_change",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionsResponse {
+    corporate_actions: Actions,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Actions {
+    #[serde(default)]
+    forward_splits: Vec<SplitAction>,
+    #[serde(default)]
+    reverse_splits: Vec<SplitAction>,
+    #[serde(default)]
+    name_changes: Vec<NameChangeAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SplitAction {
+    id: String,
+    symbol: String,
+    old_rate: Decimal,
+    new_rate: Decimal,
+    ex_date: NaiveDate,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameChangeAction {
+    id: String,
+    old_symbol: String,
+    new_symbol: String,
+    process_date: NaiveDate,
+}
+
+/// Splits and symbol changes of `symbols` effective from `start` to `end`, ordered by the date
+/// they take effect.
+pub async fn fetch(
+    client: &AlpacaClient,
+    symbols: &[String],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<CorporateAction>, CorporateActionsError> {
+    let api_info = client.api_info();
+    let url = format!(
+        "{}/v1beta1/corporate-actions",
+        api_info.data_base_url.as_str().trim_end_matches('/')
+    );
+    let http = reqwest::Client::new();
+
+    let mut actions = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut query = vec![
+            ("symbols", symbols.join(",")),
+            (
+                "types",
+                "forward_split,reverse_split,name_change".to_owned(),
+            ),
+            ("start", start.to_string()),
+            ("end", end.to_string()),
+            ("limit", PAGE_LIMIT.to_string()),
+        ];
+        if let Some(page_token) = page_token.take() {
+            query.push(("page_token", page_token));
+        }
+        let response: ActionsResponse = http
+            .get(&url)
+            .header("APCA-API-KEY-ID", &api_info.key_id)
+            .header("APCA-API-SECRET-KEY", &api_info.secret)
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let page = response.corporate_actions;
+        actions.extend(
+            page.forward_splits
+                .into_iter()
+                .chain(page.reverse_splits)
+                .map(|split| CorporateAction::Split {
+                    id: split.id,
+                    symbol: split.symbol,
+                    old_rate: split.old_rate,
+                    new_rate: split.new_rate,
+                    ex_date: split.ex_date,
+                }),
+        );
+        actions.extend(
+            page.name_changes
+                .into_iter()
+                .map(|change| CorporateAction::NameChange {
+                    id: change.id,
+                    old_symbol: change.old_symbol,
+                    new_symbol: change.new_symbol,
+                    process_date: change.process_date,
+                }),
+        );
+
+        match response.next_page_token {
+            Some(token) if !token.is_empty() => page_token = Some(token),
+            _ => break,
+        }
+    }
+
+    actions.sort_by_key(|action| action.date());
+    Ok(actions)
+}
+
+/// Adjust the stored fills, orders, time-sliced entries and bars to `action`, once. Quantities
+/// before the ex-date of a split are multiplied by its ratio and prices divided by it, so
+/// positions and P&L carry over, the positions are projected from the adjusted fills again.
+/// Returns whether it was applied, `false` when it was before.
+///
+/// NOTE: orders open at the broker are adjusted or canceled by the broker itself, only their
+/// local records are adjusted here
+pub async fn apply(db: &PgPool, action: &CorporateAction) -> Result<bool, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO corporate_actions (action_id, kind, symbol, effective_on, applied_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (action_id) DO NOTHING
+        "#,
+    )
+    .bind(action.id())
+    .bind(action.kind())
+    .bind(action.symbol())
+    .bind(action.date())
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if recorded == 0 {
+        return Ok(false);
+    }
+
+    match action {
+        CorporateAction::Split {
+            symbol,
+            old_rate,
+            new_rate,
+            ex_date,
+            ..
+        } => {
+            if old_rate.is_zero() || new_rate.is_zero() {
+                return Ok(false);
+            }
+            let ratio = new_rate / old_rate;
+            let before = ex_date.and_time(NaiveTime::MIN).and_utc();
+            split(&mut tx, symbol, ratio, before).await?;
+            projections::project(&mut tx, Some(symbol)).await?;
+        }
+        CorporateAction::NameChange {
+            old_symbol,
+            new_symbol,
+            ..
+        } => {
+            rename(&mut tx, old_symbol, new_symbol).await?;
+            projections::project(&mut tx, Some(old_symbol)).await?;
+            projections::project(&mut tx, Some(new_symbol)).await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn split(
+    conn: &mut PgConnection,
+    symbol: &str,
+    ratio: Decimal,
+    before: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    for statement in [
+        r#"
+        UPDATE fills SET quantity = quantity * $2, price = price / $2
+        WHERE ticker = $1 AND filled_at < $3
+        "#,
+        r#"
+        UPDATE orders
+        SET quantity = quantity * $2,
+            filled_quantity = filled_quantity * $2,
+            filled_avg_price = filled_avg_price / $2,
+            limit_price = limit_price / $2,
+            modified_at = NOW()
+        WHERE ticker = $1 AND created_at < $3
+        "#,
+        r#"
+        UPDATE dca_entries
+        SET quantity = quantity * $2,
+            chunk_quantity = chunk_quantity * $2,
+            stop_loss_price = stop_loss_price / $2,
+            modified_at = NOW()
+        WHERE ticker = $1 AND created_at < $3
+        "#,
+        r#"
+        UPDATE bars
+        SET open = open / $2,
+            high = high / $2,
+            low = low / $2,
+            close = close / $2,
+            volume = volume * $2,
+            updated_at = NOW()
+        WHERE symbol = $1 AND bar_time < $3
+        "#,
+    ] {
+        sqlx::query(statement)
+            .bind(symbol)
+            .bind(ratio)
+            .bind(before)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn rename(
+    conn: &mut PgConnection,
+    old_symbol: &str,
+    new_symbol: &str,
+) -> Result<(), sqlx::Error> {
+    for statement in [
+        "UPDATE fills SET ticker = $2 WHERE ticker = $1",
+        "UPDATE orders SET ticker = $2, modified_at = NOW() WHERE ticker = $1",
+        "UPDATE dca_entries SET ticker = $2, modified_at = NOW() WHERE ticker = $1",
+        // Bars stored under both symbols are kept as stored under the new one
+        r#"
+        UPDATE bars SET symbol = $2
+        WHERE symbol = $1 AND NOT EXISTS (
+            SELECT 1 FROM bars AS renamed
+            WHERE renamed.symbol = $2
+                AND renamed.timeframe = bars.timeframe
+                AND renamed.bar_time = bars.bar_time
+        )
+        "#,
+        "DELETE FROM bars WHERE symbol = $1",
+    ] {
+        sqlx::query(statement)
+            .bind(old_symbol)
+            .bind(new_symbol)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Fetch the actions of the symbols of the strategies and of the positions ever held and apply
+/// the ones not applied yet. Returns the number of actions applied.
+pub async fn check(
+    db: &PgPool,
+    client: &AlpacaClient,
+    strategies: &[Strategy],
+    config: &CorporateActionsConfig,
+) -> Result<usize, CorporateActionsError> {
+    let mut symbols = market_data::strategy_symbols(strategies);
+    let traded: Vec<String> = sqlx::query_scalar("SELECT DISTINCT ticker FROM fills")
+        .fetch_all(db)
+        .await?;
+    symbols.extend(traded);
+    symbols.sort();
+    symbols.dedup();
+    if symbols.is_empty() {
+        return Ok(0);
+    }
+
+    let end = Utc::now().date_naive();
+    let start = end - Duration::days(config.lookback_days);
+    let mut applied = 0;
+    for action in fetch(client, &symbols, start, end).await? {
+        if !apply(db, &action).await? {
+            continue;
+        }
+        applied += 1;
+        tracing::info!("Corporate action {:?} applied", action);
+        if let CorporateAction::NameChange {
+            old_symbol,
+            new_symbol,
+            ..
+        } = &action
+        {
+            for strategy in strategies
+                .iter()
+                .filter(|strategy| strategy.symbols.contains(old_symbol))
+            {
+                tracing::warn!(
+                    "Symbol {} of strategy {} changed to {}, update its configuration",
+                    old_symbol,
+                    strategy.name,
+                    new_symbol
+                );
+            }
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Check for corporate actions every configured interval until the process stops.
+pub async fn run_corporate_actions(app: Arc<App>, config: CorporateActionsConfig) {
+    let mut ticker = interval(std::time::Duration::from_secs(config.interval));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        let client = app.clients.alpaca();
+        if let Err(err) = check(&app.db, &client, &app.config.strategies, &config).await {
+            tracing::error!("Failed to check corporate actions, error: {:?}", err);
+        }
+    }
+}
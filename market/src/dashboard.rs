@@ -0,0 +1,125 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use thiserror::Error as ThisError;
+
+use crate::{
+    alert_writer::AlertRecord,
+    api::objects::{Broker, Money, Position},
+    clients::{BrokerClient, BrokerClientError},
+    portfolio,
+};
+
+/// Alerts the summary lists at most
+const RECENT_ALERTS: i64 = 10;
+
+#[derive(Debug, ThisError)]
+pub enum DashboardError {
+    #[error(transparent)]
+    BrokerClientError(#[from] BrokerClientError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Failures of the UTC day so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorCounts {
+    /// Trade signals which failed processing
+    pub dead_letters: i64,
+    pub risk_violations: i64,
+    /// Orders the broker or the service rejected
+    pub rejected_orders: i64,
+}
+
+/// Everything the dashboard shows, read in one call.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DashboardSummary {
+    pub broker: String,
+    pub equity: Money,
+    pub cash: Money,
+    pub positions: Vec<Position>,
+    /// Change of the equity since the first portfolio snapshot of the UTC day, `None` before
+    /// one is taken
+    pub todays_pnl: Option<Decimal>,
+    /// Latest alerts received, newest first
+    pub recent_alerts: Vec<AlertRecord>,
+    pub errors: ErrorCounts,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Summary of the account of `broker` and of the alerts and failures of the day at `now`.
+pub async fn summary<C: BrokerClient>(
+    db: &PgPool,
+    broker: &Broker,
+    client: &C,
+    now: DateTime<Utc>,
+) -> Result<DashboardSummary, DashboardError> {
+    let account = client.get_account().await?;
+    let positions = client.get_positions().await?;
+
+    let today = now.date_naive();
+    let equity = account.equity();
+    let todays_pnl = portfolio::day_start_equity(db, broker, today)
+        .await?
+        .map(|day_start| equity.amount - day_start);
+
+    let recent_alerts = sqlx::query_as::<_, AlertRecord>(
+        "SELECT * FROM alerts ORDER BY created_at DESC, alert_id DESC LIMIT $1",
+    )
+    .bind(RECENT_ALERTS)
+    .fetch_all(db)
+    .await?;
+
+    let from = today.and_time(NaiveTime::MIN).and_utc();
+    let errors = error_counts(db, from, from + Duration::days(1)).await?;
+
+    Ok(DashboardSummary {
+        broker: broker.as_ref().to_owned(),
+        equity,
+        cash: account.cash(),
+        positions,
+        todays_pnl,
+        recent_alerts,
+        errors,
+        generated_at: now,
+    })
+}
+
+async fn error_counts(
+    db: &PgPool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ErrorCounts, sqlx::Error> {
+    let (dead_letters, risk_violations, rejected_orders) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM dead_letters WHERE failed_at >= $1 AND failed_at < $2),
+            (SELECT COUNT(*) FROM risk_violations WHERE created_at >= $1 AND created_at < $2),
+            (
+                SELECT COUNT(*) FROM orders
+                WHERE status = 'rejected' AND created_at >= $1 AND created_at < $2
+            )
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_one(db)
+    .await?;
+
+    Ok(ErrorCounts {
+        dead_letters,
+        risk_violations,
+        rejected_orders,
+    })
+}
+
+/// Page of the bundled dashboard, it reads `/dashboard/summary` with the API key entered in it.
+#[cfg(feature = "dashboard")]
+const INDEX_HTML: &str = include_str!("../dashboard/index.html");
+
+/// Bundled dashboard at `/ui`, see `DashboardSummary`.
+#[cfg(feature = "dashboard")]
+pub async fn ui() -> axum::response::Html<&'static str> {
+    axum::response::Html(INDEX_HTML)
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::order::{NewOrder, OrderSide};
+
+/// Entries of a strategy executed as equal chunks spaced in time instead of a single order. The
+/// first chunk is sent with the signal, the rest by the background work of the core after the
+/// checks of signal orders. Chunks left are canceled when a chunk fails them or a signal on the
+/// other side of the ticker arrives.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct DcaExecution {
+    /// Orders the entry is split into
+    pub chunks: u32,
+    /// Seconds between two chunks
+    pub interval: u64,
+}
+
+/// Quantity of each chunk of an entry of `quantity` split into `chunks` in multiples of
+/// `lot_size`, the last chunk takes what rounding leaves. `None` when the chunks would round
+/// down to zero and the entry is better sent at once.
+pub fn chunk_quantity(quantity: Decimal, chunks: u32, lot_size: Decimal) -> Option<Decimal> {
+    if chunks < 2 || lot_size <= Decimal::ZERO {
+        return None;
+    }
+    let chunk = (quantity / Decimal::from(chunks) / lot_size)
+        .round_dp_with_strategy(0, RoundingStrategy::ToZero)
+        * lot_size;
+    (chunk > Decimal::ZERO).then(|| chunk.normalize())
+}
+
+/// Entry of a position executed in chunks, one logical entry its chunk orders refer to by
+/// `entry_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DcaEntry {
+    pub entry_id: Uuid,
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub side: String,
+    /// Quantity of the whole entry
+    pub quantity: Decimal,
+    pub chunk_quantity: Decimal,
+    pub chunks: i32,
+    pub chunks_sent: i32,
+    /// Seconds between two chunks
+    pub chunk_interval: i64,
+    /// Stop loss every chunk is sent with
+    pub stop_loss_price: Option<Decimal>,
+    /// Timeframe of the signal of the entry
+    pub timeframe: Option<String>,
+    /// `active` while chunks are left, then `completed` or `canceled`
+    pub status: String,
+    /// Time the next chunk is due at
+    pub next_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+impl DcaEntry {
+    /// Quantity of the next chunk, the remainder of the entry for the last one.
+    pub fn next_chunk_quantity(&self) -> Decimal {
+        if self.chunks_sent + 1 >= self.chunks {
+            self.quantity - self.chunk_quantity * Decimal::from(self.chunks - 1)
+        } else {
+            self.chunk_quantity
+        }
+    }
+
+    /// Record the entry of `order`, whose quantity is the one of the whole entry, with its first
+    /// chunk sent. `timeframe` is the one of the signal of the entry.
+    pub async fn insert(
+        db: &PgPool,
+        entry_id: Uuid,
+        order: &NewOrder,
+        chunk_quantity: Decimal,
+        dca: DcaExecution,
+        timeframe: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO dca_entries (
+                entry_id,
+                strategy_id,
+                ticker,
+                side,
+                quantity,
+                chunk_quantity,
+                chunks,
+                chunks_sent,
+                chunk_interval,
+                stop_loss_price,
+                timeframe,
+                status,
+                next_at,
+                created_at,
+                modified_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, 1, $8, $9, $10, 'active',
+                NOW() + $8 * INTERVAL '1 second', NOW(), NOW()
+            )
+            "#,
+        )
+        .bind(entry_id)
+        .bind(order.strategy_id)
+        .bind(&order.ticker)
+        .bind(order.side.as_ref())
+        .bind(order.quantity)
+        .bind(chunk_quantity)
+        .bind(dca.chunks as i32)
+        .bind(dca.interval as i64)
+        .bind(order.stop_loss_price)
+        .bind(timeframe)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Active entries with a chunk due.
+    pub async fn fetch_due(db: &PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM dca_entries
+            WHERE status = 'active' AND next_at <= NOW()
+            ORDER BY next_at
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    /// Take the next chunk of the entry, `false` when another instance took it first or the
+    /// entry was canceled meanwhile. The entry completes with its last chunk.
+    pub async fn claim_chunk(&self, db: &PgPool) -> Result<bool, sqlx::Error> {
+        let claimed = sqlx::query(
+            r#"
+            UPDATE dca_entries
+            SET chunks_sent = chunks_sent + 1,
+                next_at = NOW() + chunk_interval * INTERVAL '1 second',
+                status = CASE WHEN chunks_sent + 1 >= chunks THEN 'completed' ELSE status END,
+                modified_at = NOW()
+            WHERE entry_id = $1 AND chunks_sent = $2 AND status = 'active'
+            "#,
+        )
+        .bind(self.entry_id)
+        .bind(self.chunks_sent)
+        .execute(db)
+        .await?
+        .rows_affected();
+
+        Ok(claimed == 1)
+    }
+
+    /// Give back the chunk of the entry claimed with `claim_chunk` after it failed at the broker,
+    /// the chunk is due again right away.
+    pub async fn release_chunk(&self, db: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE dca_entries
+            SET chunks_sent = $2, next_at = NOW(), status = 'active', modified_at = NOW()
+            WHERE entry_id = $1 AND chunks_sent = $2 + 1 AND status <> 'canceled'
+            "#,
+        )
+        .bind(self.entry_id)
+        .bind(self.chunks_sent)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Cancel the entry after one of its chunks failed, the last one too though claiming it
+    /// completed the entry.
+    pub async fn cancel(db: &PgPool, entry_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE dca_entries
+            SET status = 'canceled', modified_at = NOW()
+            WHERE entry_id = $1 AND status <> 'canceled'
+            "#,
+        )
+        .bind(entry_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Cancel the active entries of the strategy in the ticker on `side`, returning them.
+    pub async fn cancel_active(
+        db: &PgPool,
+        strategy_id: Uuid,
+        ticker: &str,
+        side: OrderSide,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            UPDATE dca_entries
+            SET status = 'canceled', modified_at = NOW()
+            WHERE strategy_id = $1 AND ticker = $2 AND side = $3 AND status = 'active'
+            RETURNING *
+            "#,
+        )
+        .bind(strategy_id)
+        .bind(ticker)
+        .bind(side.as_ref())
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::api::alert::WebhookAlertData;
+
+/// Accepted alert whose trade signal failed processing, kept so it can be inspected and replayed.
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct DeadLetter {
+    pub dead_letter_id: Uuid,
+    pub strategy_id: Uuid,
+    pub request_id: Option<String>,
+    pub alert: Json<WebhookAlertData>,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+    pub replayed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DeadLetterQuery {
+    pub strategy_id: Option<Uuid>,
+    /// Leave out dead letters replayed already
+    #[serde(default)]
+    pub pending: bool,
+    pub limit: Option<i64>,
+}
+
+const DEFAULT_LIMIT: i64 = 100;
+
+impl DeadLetter {
+    pub async fn record(
+        db: &PgPool,
+        alert: &WebhookAlertData,
+        request_id: Option<&str>,
+        error: &str,
+    ) -> Result<Uuid, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO dead_letters (dead_letter_id, strategy_id, request_id, alert, error, failed_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            RETURNING dead_letter_id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(alert.strategy_id)
+        .bind(request_id)
+        .bind(Json(alert))
+        .bind(error)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn fetch(db: &PgPool, id: Uuid) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT * FROM dead_letters WHERE dead_letter_id = $1")
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Latest dead letters matching the query, newest first.
+    pub async fn fetch_recent(
+        db: &PgPool,
+        query: &DeadLetterQuery,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        Self::fetch_recent_for(db, query, None).await
+    }
+
+    /// Latest dead letters matching the query of the given strategies, or of all strategies when
+    /// not set.
+    pub async fn fetch_recent_for(
+        db: &PgPool,
+        query: &DeadLetterQuery,
+        strategy_ids: Option<&[Uuid]>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM dead_letters
+            WHERE ($1::uuid IS NULL OR strategy_id = $1)
+                AND ($4::uuid[] IS NULL OR strategy_id = ANY($4))
+                AND (NOT $2 OR replayed_at IS NULL)
+            ORDER BY failed_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(query.strategy_id)
+        .bind(query.pending)
+        .bind(query.limit.unwrap_or(DEFAULT_LIMIT))
+        .bind(strategy_ids)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Dead letters of signals which failed from `from` until before `to`, oldest first.
+    pub async fn fetch_between(
+        db: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM dead_letters
+            WHERE failed_at >= $1 AND failed_at < $2
+            ORDER BY failed_at
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Record the outcome of a replay, the error is kept when the replay failed again.
+    pub async fn replayed(db: &PgPool, id: Uuid, error: Option<&str>) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            UPDATE dead_letters
+            SET replayed_at = CASE WHEN $2::text IS NULL THEN NOW() ELSE replayed_at END,
+                error = COALESCE($2, error)
+            WHERE dead_letter_id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .fetch_one(db)
+        .await
+    }
+}
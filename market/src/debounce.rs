@@ -0,0 +1,73 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tokio::time::{sleep, Duration};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{api::alert::SignalType, order::OrderSide, trade_signal::TradeSignal};
+
+struct Pending {
+    trade_signal: TradeSignal,
+    coalesced: usize,
+}
+
+/// Coalesces signals of a strategy opening positions in the same symbol and direction within its
+/// `debounce_window`, so indicators firing several alerts within seconds place a single order.
+#[derive(Default)]
+pub struct SignalDebouncer {
+    pending: Mutex<HashMap<(Uuid, String, OrderSide), Pending>>,
+}
+
+impl SignalDebouncer {
+    /// Hold the signal for `window`, signals arriving meanwhile replace it. The first signal of
+    /// the window resolves to the latest one, the later ones resolve to `None` as they're
+    /// coalesced into it. Stop loss updates aren't held.
+    pub async fn coalesce(
+        &self,
+        trade_signal: TradeSignal,
+        window: Duration,
+    ) -> Option<TradeSignal> {
+        let side = match trade_signal.signal_type {
+            SignalType::OpenLong(_) => OrderSide::Buy,
+            SignalType::OpenShort(_) => OrderSide::Sell,
+            SignalType::StopLossUpdate(_) => return Some(trade_signal),
+        };
+        let key = (
+            trade_signal.strategy.id,
+            trade_signal.ticker.to_uppercase(),
+            side,
+        );
+
+        {
+            let mut pending = self.pending();
+            if let Some(pending) = pending.get_mut(&key) {
+                pending.trade_signal = trade_signal;
+                pending.coalesced += 1;
+                return None;
+            }
+            pending.insert(
+                key.clone(),
+                Pending {
+                    trade_signal,
+                    coalesced: 0,
+                },
+            );
+        }
+
+        sleep(window).await;
+        let pending = self.pending().remove(&key)?;
+        if pending.coalesced > 0 {
+            info!(
+                "{} signals for {} of strategy {} coalesced into one",
+                pending.coalesced + 1,
+                pending.trade_signal.ticker,
+                pending.trade_signal.strategy.name
+            );
+        }
+        Some(pending.trade_signal)
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<(Uuid, String, OrderSide), Pending>> {
+        self.pending.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
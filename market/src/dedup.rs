@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Claimed alert, kept for the dedup window.
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct ProcessedAlert {
+    pub idempotency_key: String,
+    pub strategy_id: Uuid,
+    pub request_id: Option<String>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Claim an alert for processing. Returns `false` when an alert with the same idempotency key was
+/// already claimed within the last `window_secs`. Claims are atomic, so concurrent retries of an
+/// alert can't both succeed. The claim keeps the id of the request which made it.
+pub async fn claim_alert(
+    db: &PgPool,
+    idempotency_key: &str,
+    strategy_id: Uuid,
+    request_id: Option<&str>,
+    window_secs: u64,
+) -> Result<bool, sqlx::Error> {
+    let window = window_secs as f64;
+
+    sqlx::query(
+        "DELETE FROM processed_alerts WHERE received_at < NOW() - make_interval(secs => $1)",
+    )
+    .bind(window)
+    .execute(db)
+    .await?;
+
+    let claimed = sqlx::query(
+        r#"
+        INSERT INTO processed_alerts (idempotency_key, strategy_id, request_id, received_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (idempotency_key) DO UPDATE
+        SET received_at = EXCLUDED.received_at, request_id = EXCLUDED.request_id
+        WHERE processed_alerts.received_at < NOW() - make_interval(secs => $4)
+        "#,
+    )
+    .bind(idempotency_key)
+    .bind(strategy_id)
+    .bind(request_id)
+    .bind(window)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(claimed == 1)
+}
+
+/// Drop the claim `request_id` made on an alert it couldn't take in, so a retry of the alert is
+/// processed.
+pub async fn release_alert(
+    db: &PgPool,
+    idempotency_key: &str,
+    request_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM processed_alerts
+        WHERE idempotency_key = $1 AND request_id IS NOT DISTINCT FROM $2
+        "#,
+    )
+    .bind(idempotency_key)
+    .bind(request_id)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Alerts of a strategy claimed within the dedup window, most recent first.
+pub async fn recent_alerts(
+    db: &PgPool,
+    strategy_id: Uuid,
+) -> Result<Vec<ProcessedAlert>, sqlx::Error> {
+    sqlx::query_as::<_, ProcessedAlert>(
+        "SELECT * FROM processed_alerts WHERE strategy_id = $1 ORDER BY received_at DESC",
+    )
+    .bind(strategy_id)
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{fill_model::SimulatedFill, order::OrderSide};
+
+/// Paper execution of a live order, simulated with the fill model when the order is placed.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ShadowExecution {
+    pub order_id: Uuid,
+    pub quantity: Decimal,
+    pub avg_price: Decimal,
+    pub simulated_at: DateTime<Utc>,
+}
+
+impl ShadowExecution {
+    pub fn from_fills(order_id: Uuid, fills: &[SimulatedFill]) -> Option<Self> {
+        let quantity: Decimal = fills.iter().map(|fill| fill.quantity).sum();
+        if quantity.is_zero() {
+            return None;
+        }
+        let notional: Decimal = fills.iter().map(|fill| fill.quantity * fill.price).sum();
+
+        Some(Self {
+            order_id,
+            quantity,
+            avg_price: notional / quantity,
+            simulated_at: Utc::now(),
+        })
+    }
+
+    pub async fn insert(&self, db: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO shadow_executions (order_id, quantity, avg_price, simulated_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(self.order_id)
+        .bind(self.quantity)
+        .bind(self.avg_price)
+        .bind(self.simulated_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DivergenceQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SignalDivergence {
+    pub order_id: Uuid,
+    pub ticker: String,
+    pub side: String,
+    pub created_at: DateTime<Utc>,
+    pub live_quantity: Decimal,
+    pub live_price: Option<Decimal>,
+    pub shadow_quantity: Decimal,
+    pub shadow_price: Decimal,
+    /// Cost of the live execution compared to the shadow one over the quantity both filled.
+    /// Positive when live execution was worse.
+    #[sqlx(skip)]
+    pub shortfall: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    pub strategy_id: Uuid,
+    pub signals: usize,
+    /// Signals the shadow filled but live didn't fill completely
+    pub underfilled: usize,
+    /// Sum of shortfalls, the P&L lost to live execution
+    pub total_shortfall: Decimal,
+    pub divergences: Vec<SignalDivergence>,
+}
+
+pub async fn report(
+    db: &PgPool,
+    strategy_id: Uuid,
+    query: &DivergenceQuery,
+) -> Result<DivergenceReport, sqlx::Error> {
+    let mut divergences = sqlx::query_as::<_, SignalDivergence>(
+        r#"
+        SELECT
+            orders.order_id,
+            orders.ticker,
+            orders.side,
+            orders.created_at,
+            orders.filled_quantity AS live_quantity,
+            orders.filled_avg_price AS live_price,
+            shadow_executions.quantity AS shadow_quantity,
+            shadow_executions.avg_price AS shadow_price
+        FROM orders
+        JOIN shadow_executions ON shadow_executions.order_id = orders.order_id
+        WHERE orders.strategy_id = $1
+            AND ($2::timestamptz IS NULL OR orders.created_at >= $2)
+            AND ($3::timestamptz IS NULL OR orders.created_at <= $3)
+        ORDER BY orders.created_at
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(query.from)
+    .bind(query.to)
+    .fetch_all(db)
+    .await?;
+
+    for divergence in &mut divergences {
+        let sign = match divergence.side.parse::<OrderSide>() {
+            Ok(OrderSide::Sell) => Decimal::NEGATIVE_ONE,
+            _ => Decimal::ONE,
+        };
+        let quantity = divergence.live_quantity.min(divergence.shadow_quantity);
+        divergence.shortfall = divergence.live_price.map_or(Decimal::ZERO, |live_price| {
+            (live_price - divergence.shadow_price) * quantity * sign
+        });
+    }
+
+    Ok(DivergenceReport {
+        strategy_id,
+        signals: divergences.len(),
+        underfilled: divergences
+            .iter()
+            .filter(|divergence| divergence.live_quantity < divergence.shadow_quantity)
+            .count(),
+        total_shortfall: divergences
+            .iter()
+            .map(|divergence| divergence.shortfall)
+            .sum(),
+        divergences,
+    })
+}
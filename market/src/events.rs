@@ -0,0 +1,366 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{
+    api::alert::SignalType,
+    order::{Fill, OrderSide},
+};
+
+/// Events kept for subscribers which fall behind. Slower subscribers skip the missed events.
+const EVENT_CAPACITY: usize = 1024;
+/// Delay before reconnecting a bus shared through a server which can't be reached
+#[cfg(any(feature = "redis", feature = "nats"))]
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Live events published to the `/ws` subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    AlertReceived {
+        strategy_id: Uuid,
+        ticker: String,
+        signal_type: SignalType,
+        request_id: Option<String>,
+        received_at: DateTime<Utc>,
+    },
+    OrderSubmitted {
+        order_id: Uuid,
+        strategy_id: Uuid,
+        ticker: String,
+        side: OrderSide,
+        quantity: Decimal,
+        status: String,
+    },
+    OrderFilled(Fill),
+}
+
+impl Event {
+    pub fn strategy_id(&self) -> Uuid {
+        match self {
+            Event::AlertReceived { strategy_id, .. }
+            | Event::OrderSubmitted { strategy_id, .. } => *strategy_id,
+            Event::OrderFilled(fill) => fill.strategy_id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventQuery {
+    /// Only events of the strategy, every event when missing
+    pub strategy_id: Option<Uuid>,
+}
+
+impl EventQuery {
+    pub fn matches(&self, event: &Event) -> bool {
+        self.strategy_id
+            .is_none_or(|strategy_id| strategy_id == event.strategy_id())
+    }
+}
+
+/// Bus the live events are published on. Subscribers get the events published after they
+/// subscribed, by any instance sharing the bus unless it's kept in memory.
+pub trait EventBus: Send + Sync {
+    /// Events published without subscribers are dropped.
+    fn publish(&self, event: Event);
+
+    fn subscribe(&self) -> broadcast::Receiver<Event>;
+}
+
+/// Events of this instance only.
+pub struct InMemoryEventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl InMemoryEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl Default for InMemoryEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus for InMemoryEventBus {
+    fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_bus::RedisEventBus;
+
+#[cfg(feature = "redis")]
+mod redis_bus {
+    use std::sync::Arc;
+
+    use redis::{
+        aio::ConnectionManager,
+        streams::{StreamMaxlen, StreamReadOptions, StreamReadReply},
+        AsyncCommands, Client, RedisError,
+    };
+    use tokio::sync::{broadcast, mpsc, Mutex};
+
+    use super::{Event, EventBus, EVENT_CAPACITY, RECONNECT_DELAY};
+    use crate::supervisor::TaskSupervisor;
+
+    /// Entries kept in the stream, older ones are trimmed
+    const STREAM_MAX_LEN: usize = 10_000;
+    /// Milliseconds a read of the stream waits for new entries
+    const READ_BLOCK: usize = 5_000;
+
+    /// Events shared through a Redis stream. Published events are appended to the stream, which
+    /// every instance reads back into its subscribers, so subscribers get the events of this
+    /// instance once they went through Redis. Events published while Redis is unavailable are
+    /// dropped.
+    pub struct RedisEventBus {
+        sender: broadcast::Sender<Event>,
+        outbox: mpsc::UnboundedSender<Event>,
+    }
+
+    impl RedisEventBus {
+        /// Bus of `stream` of the Redis server at `url`, connected in the background on `tasks`.
+        pub fn open(url: &str, stream: &str, tasks: &TaskSupervisor) -> Result<Self, RedisError> {
+            let client = Client::open(url)?;
+            let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+            let (outbox, pending) = mpsc::unbounded_channel();
+            let pending = Arc::new(Mutex::new(pending));
+            let (appending, stream_name) = (client.clone(), stream.to_owned());
+            tasks.spawn("events.redis.append", move || {
+                let (client, stream) = (appending.clone(), stream_name.clone());
+                let pending = Arc::clone(&pending);
+                async move { append_events(client, stream, &mut *pending.lock().await).await }
+            });
+            let (stream_name, readers) = (stream.to_owned(), sender.clone());
+            tasks.spawn("events.redis.read", move || {
+                read_events(client.clone(), stream_name.clone(), readers.clone())
+            });
+
+            Ok(Self { sender, outbox })
+        }
+    }
+
+    impl EventBus for RedisEventBus {
+        fn publish(&self, event: Event) {
+            let _ = self.outbox.send(event);
+        }
+
+        fn subscribe(&self) -> broadcast::Receiver<Event> {
+            self.sender.subscribe()
+        }
+    }
+
+    async fn append_events(
+        client: Client,
+        stream: String,
+        pending: &mut mpsc::UnboundedReceiver<Event>,
+    ) {
+        let mut connection: Option<ConnectionManager> = None;
+        while let Some(event) = pending.recv().await {
+            let Ok(payload) = serde_json::to_string(&event) else {
+                continue;
+            };
+            if connection.is_none() {
+                match ConnectionManager::new(client.clone()).await {
+                    Ok(manager) => connection = Some(manager),
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to connect to Redis, dropping event, error: {}",
+                            err
+                        );
+                        continue;
+                    }
+                }
+            }
+            let Some(connection) = connection.as_mut() else {
+                continue;
+            };
+
+            let appended = connection
+                .xadd_maxlen::<_, _, _, _, ()>(
+                    &stream,
+                    StreamMaxlen::Approx(STREAM_MAX_LEN),
+                    "*",
+                    &[("event", payload)],
+                )
+                .await;
+            if let Err(err) = appended {
+                tracing::warn!("Failed to append event to {}, error: {}", stream, err);
+            }
+        }
+    }
+
+    /// Forward the entries appended to the stream to the subscribers until the process stops.
+    /// Reads need a connection of their own as they block until entries are appended.
+    async fn read_events(client: Client, stream: String, sender: broadcast::Sender<Event>) {
+        // NOTE: `$` starts after the last entry, later reads resume after the last entry read
+        let mut last_id = "$".to_owned();
+        let options = StreamReadOptions::default()
+            .block(READ_BLOCK)
+            .count(EVENT_CAPACITY);
+
+        loop {
+            let mut connection = match client.get_async_connection().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    tracing::warn!("Failed to connect to Redis, retrying, error: {}", err);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+
+            loop {
+                let reply: Option<StreamReadReply> = match connection
+                    .xread_options(&[&stream], &[&last_id], &options)
+                    .await
+                {
+                    Ok(reply) => reply,
+                    Err(err) => {
+                        tracing::warn!("Failed to read {}, reconnecting, error: {}", stream, err);
+                        break;
+                    }
+                };
+                let entries = reply
+                    .into_iter()
+                    .flat_map(|reply| reply.keys)
+                    .flat_map(|key| key.ids);
+                for entry in entries {
+                    match entry
+                        .get::<String>("event")
+                        .map(|payload| serde_json::from_str::<Event>(&payload))
+                    {
+                        Some(Ok(event)) => {
+                            let _ = sender.send(event);
+                        }
+                        _ => tracing::warn!("Skipping invalid event {} of {}", entry.id, stream),
+                    }
+                    last_id = entry.id;
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+pub use nats_bus::NatsEventBus;
+
+#[cfg(feature = "nats")]
+mod nats_bus {
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use tokio::sync::{broadcast, mpsc, Mutex};
+
+    use super::{Event, EventBus, EVENT_CAPACITY, RECONNECT_DELAY};
+    use crate::supervisor::TaskSupervisor;
+
+    /// Events shared through a NATS subject. Published events are sent to the subject, which
+    /// every instance is subscribed to, so subscribers get the events of this instance once they
+    /// went through NATS. Events published before the first connection are sent once connected,
+    /// afterwards the client reconnects by itself.
+    pub struct NatsEventBus {
+        sender: broadcast::Sender<Event>,
+        outbox: mpsc::UnboundedSender<Event>,
+    }
+
+    impl NatsEventBus {
+        /// Bus of `subject` of the NATS server at `url`, connected in the background on `tasks`.
+        pub fn connect(url: &str, subject: &str, tasks: &TaskSupervisor) -> Self {
+            let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+            let (outbox, pending) = mpsc::unbounded_channel();
+            let pending = Arc::new(Mutex::new(pending));
+            let (url, subject, subscribers) = (url.to_owned(), subject.to_owned(), sender.clone());
+            tasks.spawn("events.nats", move || {
+                let (url, subject, subscribers) = (url.clone(), subject.clone(), subscribers.clone());
+                let pending = Arc::clone(&pending);
+                async move {
+                    relay_events(url, subject, &mut *pending.lock().await, subscribers).await
+                }
+            });
+
+            Self { sender, outbox }
+        }
+    }
+
+    impl EventBus for NatsEventBus {
+        fn publish(&self, event: Event) {
+            let _ = self.outbox.send(event);
+        }
+
+        fn subscribe(&self) -> broadcast::Receiver<Event> {
+            self.sender.subscribe()
+        }
+    }
+
+    /// Send the pending events to the subject and forward the events of the subject to the
+    /// subscribers, until the bus is dropped.
+    async fn relay_events(
+        url: String,
+        subject: String,
+        pending: &mut mpsc::UnboundedReceiver<Event>,
+        sender: broadcast::Sender<Event>,
+    ) {
+        let (client, mut subscriber) = loop {
+            let subscribed = match async_nats::connect(url.as_str()).await {
+                Ok(client) => client
+                    .subscribe(subject.clone())
+                    .await
+                    .map(|subscriber| (client, subscriber))
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            match subscribed {
+                Ok(subscribed) => break subscribed,
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to subscribe to {} of NATS, retrying, error: {}",
+                        subject,
+                        err
+                    );
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        };
+
+        loop {
+            tokio::select! {
+                event = pending.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    let Ok(payload) = serde_json::to_vec(&event) else {
+                        continue;
+                    };
+                    if let Err(err) = client.publish(subject.clone(), payload.into()).await {
+                        tracing::warn!("Failed to publish event to {}, error: {}", subject, err);
+                    }
+                }
+                message = subscriber.next() => {
+                    let Some(message) = message else {
+                        tracing::warn!("Subscription to {} of NATS ended", subject);
+                        break;
+                    };
+                    match serde_json::from_slice::<Event>(&message.payload) {
+                        Ok(event) => {
+                            let _ = sender.send(event);
+                        }
+                        Err(err) => {
+                            tracing::warn!("Skipping invalid event of {}, error: {}", subject, err)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
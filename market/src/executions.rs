@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use uuid::Uuid;
+
+use crate::order::Fill;
+#[cfg(feature = "kafka")]
+use crate::supervisor::TaskSupervisor;
+
+/// Version of the message schema, raised on changes consumers have to handle.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Kafka topic the executions of the strategies are published to, requires the `kafka` feature.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Kafka {
+    /// Bootstrap brokers as `host:port`
+    pub brokers: Vec<String>,
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Partitions of the topic, the messages of a strategy go to the same one so they stay in
+    /// order
+    #[serde(default = "default_partitions")]
+    pub partitions: i32,
+    /// Messages waiting to be published at most, further ones are dropped until there's room
+    #[serde(default = "default_buffer")]
+    pub buffer: usize,
+}
+
+fn default_topic() -> String {
+    "market.executions".to_owned()
+}
+
+fn default_partitions() -> i32 {
+    1
+}
+
+fn default_buffer() -> usize {
+    10_000
+}
+
+/// Message of the executions topic. Messages are JSON, keyed by the strategy id and published
+/// in the order the executions happened:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "type": "fill",
+///   "fill_id": "018cbb3a-…",
+///   "order_id": "018cbb39-…",
+///   "strategy_id": "6f1c2a4e-…",
+///   "ticker": "AAPL",
+///   "side": "buy",
+///   "quantity": 2,
+///   "price": 190.5,
+///   "fee": 0,
+///   "filled_at": "2023-12-30T14:31:02.518Z"
+/// }
+/// {
+///   "version": 1,
+///   "type": "position_changed",
+///   "strategy_id": "6f1c2a4e-…",
+///   "ticker": "AAPL",
+///   "quantity": 5,
+///   "change": 2,
+///   "fill_id": "018cbb3a-…",
+///   "changed_at": "2023-12-30T14:31:02.518Z"
+/// }
+/// ```
+///
+/// Decimals are JSON numbers of arbitrary precision, times are RFC 3339 in UTC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionMessage {
+    /// `SCHEMA_VERSION` the message was published with
+    pub version: u32,
+    #[serde(flatten)]
+    pub execution: Execution,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Execution {
+    /// Execution of an order, orders filled in several steps publish several fills
+    Fill(Fill),
+    /// Position of a strategy in a ticker changed by a fill, published right after the fill
+    PositionChanged(PositionChange),
+}
+
+impl Execution {
+    pub fn strategy_id(&self) -> Uuid {
+        match self {
+            Execution::Fill(fill) => fill.strategy_id,
+            Execution::PositionChanged(change) => change.strategy_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionChange {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    /// Position after the fill, negative when short and zero once closed
+    pub quantity: Decimal,
+    /// Quantity the fill changed the position by, negative for sells
+    pub change: Decimal,
+    pub fill_id: Uuid,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl PositionChange {
+    /// Change of the position by `fill`, which left it at `quantity`.
+    pub fn new(fill: &Fill, quantity: Decimal) -> Self {
+        Self {
+            strategy_id: fill.strategy_id,
+            ticker: fill.ticker.clone(),
+            quantity,
+            change: fill.signed_quantity(),
+            fill_id: fill.fill_id,
+            changed_at: fill.filled_at,
+        }
+    }
+}
+
+/// Hands executions to a background task publishing them to Kafka, so fills aren't held up by
+/// the brokers of the topic.
+pub struct ExecutionPublisher {
+    sender: mpsc::Sender<ExecutionMessage>,
+}
+
+impl ExecutionPublisher {
+    /// Start the background task publishing to the topic of `config` on `tasks`, connected on
+    /// the first execution. It stops once the publisher is dropped.
+    #[cfg(feature = "kafka")]
+    pub fn spawn(config: &Kafka, tasks: &TaskSupervisor) -> Self {
+        let (sender, receiver) = mpsc::channel(config.buffer);
+        let receiver = std::sync::Arc::new(tokio::sync::Mutex::new(receiver));
+        let config = config.clone();
+        tasks.spawn("kafka_producer", move || {
+            let config = config.clone();
+            let receiver = std::sync::Arc::clone(&receiver);
+            async move { kafka_producer::run(config, &mut *receiver.lock().await).await }
+        });
+
+        Self { sender }
+    }
+
+    /// Queue an execution to be published. Executions are dropped while the buffer is full.
+    pub fn publish(&self, execution: Execution) {
+        let message = ExecutionMessage {
+            version: SCHEMA_VERSION,
+            execution,
+        };
+        match self.sender.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(message)) => tracing::warn!(
+                "Execution buffer full, execution of strategy {} not published",
+                message.execution.strategy_id()
+            ),
+            Err(TrySendError::Closed(_)) => {
+                tracing::error!("Execution publisher stopped, execution not published")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "kafka")]
+mod kafka_producer {
+    use std::collections::{btree_map::Entry, BTreeMap};
+
+    use chrono::Utc;
+    use rskafka::{
+        client::{
+            partition::{Compression, PartitionClient, UnknownTopicHandling},
+            ClientBuilder,
+        },
+        record::Record,
+    };
+    use tokio::{sync::mpsc::Receiver, time::Duration};
+
+    use super::{ExecutionMessage, Kafka};
+
+    /// Messages of one produce request at most
+    const BATCH_SIZE: usize = 100;
+    /// Delay before reconnecting to brokers which can't be reached
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    /// Publish the queued messages in batches, a batch per partition. Batches failing to publish
+    /// are logged and dropped, the connection is made again for the next one.
+    pub(super) async fn run(config: Kafka, receiver: &mut Receiver<ExecutionMessage>) {
+        let mut partitions: BTreeMap<i32, PartitionClient> = BTreeMap::new();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+        while let Some(message) = receiver.recv().await {
+            batch.push(message);
+            while batch.len() < BATCH_SIZE {
+                match receiver.try_recv() {
+                    Ok(message) => batch.push(message),
+                    Err(_) => break,
+                }
+            }
+
+            let mut records: BTreeMap<i32, Vec<Record>> = BTreeMap::new();
+            for message in batch.drain(..) {
+                let strategy_id = message.execution.strategy_id();
+                let Ok(value) = serde_json::to_vec(&message) else {
+                    continue;
+                };
+                // NOTE: partitioned by the strategy id so partitions don't change across restarts
+                let partition = (strategy_id.as_u128() % config.partitions.max(1) as u128) as i32;
+                records.entry(partition).or_default().push(Record {
+                    key: Some(strategy_id.to_string().into_bytes()),
+                    value: Some(value),
+                    headers: BTreeMap::new(),
+                    timestamp: Utc::now(),
+                });
+            }
+
+            for (partition, records) in records {
+                let count = records.len();
+                let client = match partitions.entry(partition) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => match connect(&config, partition).await {
+                        Ok(client) => entry.insert(client),
+                        Err(err) => {
+                            tracing::error!(
+                                "Failed to connect to partition {} of {}, {} executions not \
+                                 published, error: {}",
+                                partition,
+                                config.topic,
+                                count,
+                                err
+                            );
+                            tokio::time::sleep(RECONNECT_DELAY).await;
+                            continue;
+                        }
+                    },
+                };
+                if let Err(err) = client.produce(records, Compression::NoCompression).await {
+                    tracing::error!(
+                        "Failed to publish {} executions to {}, error: {}",
+                        count,
+                        config.topic,
+                        err
+                    );
+                    partitions.remove(&partition);
+                }
+            }
+        }
+    }
+
+    async fn connect(
+        config: &Kafka,
+        partition: i32,
+    ) -> Result<PartitionClient, rskafka::client::error::Error> {
+        let client = ClientBuilder::new(config.brokers.clone()).build().await?;
+        client
+            .partition_client(config.topic.clone(), partition, UnknownTopicHandling::Error)
+            .await
+    }
+}
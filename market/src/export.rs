@@ -0,0 +1,385 @@
+use std::{collections::HashMap, io, path::PathBuf, pin::pin, process::Stdio, sync::Arc};
+
+use async_compression::tokio::bufread::{GzipEncoder, ZstdEncoder};
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{future, stream, stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use thiserror::Error as ThisError;
+use tokio::{io::AsyncWriteExt, process::Command, sync::mpsc};
+use tokio_util::io::{ReaderStream, StreamReader};
+use uuid::Uuid;
+
+use crate::{app_config::AppConfig, order::Fill, pnl::Lots, users::Caller, App};
+
+/// Header carrying base64 of the detached signature of a signed export.
+pub const SIGNATURE_HEADER: &str = "x-export-signature";
+/// Header with the name the decoded signature should be saved under, next to the export.
+pub const SIGNATURE_FILE_HEADER: &str = "x-export-signature-file";
+
+/// Journal entries read ahead of the encoder.
+const JOURNAL_BUFFER: usize = 256;
+/// Rows of a Parquet row group.
+#[cfg(feature = "parquet")]
+const PARQUET_BATCH_ROWS: usize = 8192;
+/// Scale of the decimal columns of Parquet exports.
+#[cfg(feature = "parquet")]
+const PARQUET_SCALE: u32 = 10;
+
+const CSV_HEADER: [&str; 9] = [
+    "filled_at",
+    "strategy_id",
+    "strategy",
+    "symbol",
+    "side",
+    "quantity",
+    "price",
+    "fee",
+    "realized_pnl",
+];
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Gzip => "application/gzip",
+            Self::Zstd => "application/zstd",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub compression: Option<Compression>,
+    /// Return a detached signature of the export, see `Signing`
+    #[serde(default)]
+    pub sign: bool,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, ThisError)]
+pub enum ExportError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+    #[error(transparent)]
+    MinisignError(#[from] minisign::PError),
+    #[error("gpg failed to sign the export: {0}")]
+    GpgError(String),
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
+}
+
+/// Key detached export signatures are made with. Signed exports are buffered, as the signature
+/// is only known once the whole export is written.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Signing {
+    /// Minisign secret key file, `password` is required for encrypted keys
+    Minisign {
+        secret_key: PathBuf,
+        password: Option<String>,
+    },
+    /// Key of the local GnuPG keyring
+    Gpg { key_id: String },
+}
+
+impl Signing {
+    /// Extension of the signature file used by the signing tool.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Minisign { .. } => "minisig",
+            Self::Gpg { .. } => "asc",
+        }
+    }
+
+    /// Detached signature of `data`, in the text format of the signing tool.
+    pub async fn sign(&self, data: &[u8]) -> Result<String, ExportError> {
+        match self {
+            Self::Minisign {
+                secret_key,
+                password,
+            } => {
+                // NOTE: empty password reads an unencrypted key, `None` would prompt for one
+                let secret_key = minisign::SecretKey::from_file(
+                    secret_key,
+                    Some(password.clone().unwrap_or_default()),
+                )?;
+                Ok(minisign::sign(None, &secret_key, data, None, None)?.to_string())
+            }
+            Self::Gpg { key_id } => {
+                let mut child = Command::new("gpg")
+                    .args([
+                        "--batch",
+                        "--armor",
+                        "--detach-sign",
+                        "--local-user",
+                        key_id,
+                    ])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+
+                let mut stdin = child.stdin.take().ok_or_else(|| {
+                    ExportError::GpgError("stdin of gpg is not available".to_string())
+                })?;
+                let (written, output) = tokio::join!(
+                    async move {
+                        stdin.write_all(data).await?;
+                        stdin.shutdown().await
+                    },
+                    child.wait_with_output()
+                );
+                written?;
+                let output = output?;
+
+                if !output.status.success() {
+                    return Err(ExportError::GpgError(
+                        String::from_utf8_lossy(&output.stderr).into_owned(),
+                    ));
+                }
+                String::from_utf8(output.stdout)
+                    .map_err(|err| ExportError::GpgError(err.to_string()))
+            }
+        }
+    }
+}
+
+pub type ExportStream = BoxStream<'static, Result<Bytes, io::Error>>;
+
+/// Compress the export as it's streamed.
+pub fn compress(export: ExportStream, compression: Option<Compression>) -> ExportStream {
+    match compression {
+        None => export,
+        Some(Compression::Gzip) => {
+            ReaderStream::new(GzipEncoder::new(StreamReader::new(export))).boxed()
+        }
+        Some(Compression::Zstd) => {
+            ReaderStream::new(ZstdEncoder::new(StreamReader::new(export))).boxed()
+        }
+    }
+}
+
+/// Executed trade as it appears in the journal. Field order matches `CSV_HEADER`.
+#[derive(Debug, Serialize)]
+pub struct TradeJournalEntry {
+    pub filled_at: DateTime<Utc>,
+    pub strategy_id: Uuid,
+    pub strategy: String,
+    pub symbol: String,
+    pub side: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fee: Decimal,
+    /// P&L realized by the fill, fees excluded
+    pub realized_pnl: Decimal,
+}
+
+/// Executed trades within the query range, read from the fills as the stream is polled. Fills
+/// before `from` are still replayed, so P&L of positions opened before the range is realized
+/// correctly.
+pub fn trade_journal<'a>(
+    db: &'a PgPool,
+    config: &'a AppConfig,
+    query: &'a ExportQuery,
+) -> impl Stream<Item = Result<TradeJournalEntry, sqlx::Error>> + 'a {
+    let mut lots: HashMap<Uuid, Lots> = HashMap::new();
+
+    Fill::stream_until(db, query.to).try_filter_map(move |fill| {
+        let realized_pnl = lots.entry(fill.strategy_id).or_default().apply(&fill);
+        if query.from.is_some_and(|from| fill.filled_at < from) {
+            return future::ready(Ok(None));
+        }
+
+        let strategy = config
+            .strategies
+            .iter()
+            .find(|strategy| strategy.id == fill.strategy_id)
+            .map(|strategy| strategy.name.clone())
+            .unwrap_or_default();
+
+        future::ready(Ok(Some(TradeJournalEntry {
+            filled_at: fill.filled_at,
+            strategy_id: fill.strategy_id,
+            strategy,
+            symbol: fill.ticker,
+            side: fill.side,
+            quantity: fill.quantity,
+            price: fill.price,
+            fee: fill.fee,
+            realized_pnl,
+        })))
+    })
+}
+
+/// `trade_journal` of the strategies the caller sees. It's read by a task of its own, so the
+/// stream doesn't borrow the app, and stops once the stream is dropped.
+pub fn spawn_trade_journal(
+    app: Arc<App>,
+    query: ExportQuery,
+    caller: Caller,
+) -> BoxStream<'static, Result<TradeJournalEntry, io::Error>> {
+    let (sender, receiver) = mpsc::channel(JOURNAL_BUFFER);
+    tokio::spawn(async move {
+        let mut entries = pin!(trade_journal(&app.db, &app.config, &query));
+        while let Some(entry) = entries.next().await {
+            if entry
+                .as_ref()
+                .is_ok_and(|entry| !caller.sees(&app.config, entry.strategy_id))
+            {
+                continue;
+            }
+            let failed = entry.is_err();
+            if sender.send(entry.map_err(io::Error::other)).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|entry| (entry, receiver))
+    })
+    .boxed()
+}
+
+/// CSV document as a stream of lines, the header comes first.
+pub fn csv_stream<S>(entries: S) -> impl Stream<Item = Result<Bytes, io::Error>>
+where
+    S: Stream<Item = Result<TradeJournalEntry, io::Error>>,
+{
+    let header = csv_line(|writer| writer.write_record(CSV_HEADER)).map_err(io::Error::from);
+    let rows = entries.map(|entry| {
+        entry.and_then(|entry| {
+            csv_line(move |writer| writer.serialize(entry)).map_err(io::Error::from)
+        })
+    });
+
+    stream::once(future::ready(header)).chain(rows)
+}
+
+fn csv_line<F>(write: F) -> Result<Bytes, csv::Error>
+where
+    F: FnOnce(&mut csv::Writer<Vec<u8>>) -> Result<(), csv::Error>,
+{
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    write(&mut writer)?;
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| csv::Error::from(err.into_error()))?;
+    Ok(Bytes::from(bytes))
+}
+
+/// Parquet document of the entries, encoded a row group at a time as they're read. Amounts are
+/// written as decimals of `PARQUET_SCALE` digits.
+#[cfg(feature = "parquet")]
+pub async fn to_parquet<S>(entries: S) -> Result<Vec<u8>, ExportError>
+where
+    S: Stream<Item = Result<TradeJournalEntry, io::Error>>,
+{
+    use arrow_schema::{DataType, Field, Schema, TimeUnit, DECIMAL128_MAX_PRECISION};
+    use futures::stream::TryChunksError;
+    use parquet::arrow::ArrowWriter;
+
+    let decimal = DataType::Decimal128(DECIMAL128_MAX_PRECISION, PARQUET_SCALE as i8);
+    let timestamp = DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()));
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("filled_at", timestamp, false),
+        Field::new("strategy_id", DataType::Utf8, false),
+        Field::new("strategy", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("quantity", decimal.clone(), false),
+        Field::new("price", decimal.clone(), false),
+        Field::new("fee", decimal.clone(), false),
+        Field::new("realized_pnl", decimal, false),
+    ]));
+
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, Arc::clone(&schema), None)?;
+    let mut batches = pin!(entries.try_chunks(PARQUET_BATCH_ROWS));
+    while let Some(entries) = batches
+        .try_next()
+        .await
+        .map_err(|TryChunksError(_, err)| err)?
+    {
+        writer.write(&record_batch(Arc::clone(&schema), &entries)?)?;
+    }
+    writer.close()?;
+
+    Ok(buffer)
+}
+
+#[cfg(feature = "parquet")]
+fn record_batch(
+    schema: arrow_schema::SchemaRef,
+    entries: &[TradeJournalEntry],
+) -> Result<arrow_array::RecordBatch, parquet::errors::ParquetError> {
+    use arrow_array::{
+        ArrayRef, Decimal128Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    };
+    use arrow_schema::DECIMAL128_MAX_PRECISION;
+
+    let decimals = |value: fn(&TradeJournalEntry) -> Decimal| {
+        Decimal128Array::from_iter_values(entries.iter().map(|entry| {
+            let mut value = value(entry).round_dp(PARQUET_SCALE);
+            value.rescale(PARQUET_SCALE);
+            value.mantissa()
+        }))
+        .with_precision_and_scale(DECIMAL128_MAX_PRECISION, PARQUET_SCALE as i8)
+        .map(|array| Arc::new(array) as ArrayRef)
+    };
+    let strings = |value: fn(&TradeJournalEntry) -> String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(entries.iter().map(value)))
+    };
+
+    Ok(RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(
+                TimestampMillisecondArray::from_iter_values(
+                    entries
+                        .iter()
+                        .map(|entry| entry.filled_at.timestamp_millis()),
+                )
+                .with_timezone("UTC"),
+            ),
+            strings(|entry| entry.strategy_id.to_string()),
+            strings(|entry| entry.strategy.clone()),
+            strings(|entry| entry.symbol.clone()),
+            strings(|entry| entry.side.clone()),
+            decimals(|entry| entry.quantity)?,
+            decimals(|entry| entry.price)?,
+            decimals(|entry| entry.fee)?,
+            decimals(|entry| entry.realized_pnl)?,
+        ],
+    )?)
+}
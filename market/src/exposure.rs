@@ -0,0 +1,129 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, Utc, Weekday};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{app_config::Session, order::Fill, pnl::Lots};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExposureQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Time-weighted exposure of a strategy, in notional-hours: cost basis of open positions
+/// multiplied by the hours they were held.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StrategyExposure {
+    pub strategy_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total: Decimal,
+    /// Held between two weekday sessions
+    pub overnight: Decimal,
+    /// Held between the Friday and Monday sessions
+    pub weekend: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OffSession {
+    Overnight,
+    Weekend,
+}
+
+/// Periods outside of the trading session overlapping `from..to`.
+fn off_session_periods(
+    session: &Session,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>, OffSession)> {
+    let at = |date: NaiveDate, time: NaiveTime| date.and_time(time).and_utc();
+    let mut periods = Vec::new();
+
+    // Weekends start on Friday, so a period containing `from` may start up to 3 days earlier
+    let mut date = from.date_naive() - Days::new(3);
+    while date <= to.date_naive() {
+        let period = match date.weekday() {
+            Weekday::Sat | Weekday::Sun => None,
+            Weekday::Fri => Some((
+                at(date, session.close),
+                at(date + Days::new(3), session.open),
+                OffSession::Weekend,
+            )),
+            _ => Some((
+                at(date, session.close),
+                at(date + Days::new(1), session.open),
+                OffSession::Overnight,
+            )),
+        };
+        if let Some((start, end, kind)) = period {
+            if start < to && end > from {
+                periods.push((start.max(from), end.min(to), kind));
+            }
+        }
+        date = date + Days::new(1);
+    }
+
+    periods
+}
+
+fn hours(from: DateTime<Utc>, to: DateTime<Utc>) -> Decimal {
+    Decimal::from((to - from).num_seconds()) / Decimal::from(3600)
+}
+
+/// Integrate exposure of the positions built by `fills` over `from..to`. Fills before `from` only
+/// establish the positions held at its start. Without a `session` the assets trade around the
+/// clock and are never held off session.
+pub fn compute_exposure(
+    strategy_id: Uuid,
+    fills: &[Fill],
+    session: Option<&Session>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> StrategyExposure {
+    let mut exposure = StrategyExposure {
+        strategy_id,
+        from,
+        to,
+        total: Decimal::ZERO,
+        overnight: Decimal::ZERO,
+        weekend: Decimal::ZERO,
+    };
+
+    let mut accumulate = |start: DateTime<Utc>, end: DateTime<Utc>, notional: Decimal| {
+        let (start, end) = (start.max(from), end.min(to));
+        if notional.is_zero() || start >= end {
+            return;
+        }
+
+        exposure.total += notional * hours(start, end);
+        let periods = session
+            .map(|session| off_session_periods(session, start, end))
+            .unwrap_or_default();
+        for (period_start, period_end, kind) in periods {
+            let notional_hours = notional * hours(period_start, period_end);
+            match kind {
+                OffSession::Overnight => exposure.overnight += notional_hours,
+                OffSession::Weekend => exposure.weekend += notional_hours,
+            }
+        }
+    };
+
+    let mut lots = Lots::default();
+    let mut held_since: Option<DateTime<Utc>> = None;
+    for fill in fills.iter().take_while(|fill| fill.filled_at < to) {
+        if let Some(since) = held_since {
+            accumulate(since, fill.filled_at, lots.notional());
+        }
+        lots.apply(fill);
+        held_since = Some(fill.filled_at);
+    }
+    if let Some(since) = held_since {
+        accumulate(since, to, lots.notional());
+    }
+
+    exposure.total = exposure.total.round_dp(2);
+    exposure.overnight = exposure.overnight.round_dp(2);
+    exposure.weekend = exposure.weekend.round_dp(2);
+    exposure
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use tokio::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Allow strategies to open short positions.
+pub const ENABLE_SHORTING: &str = "enable_shorting";
+/// Size orders with the new sizing engine instead of a fixed strategy quantity.
+/// Rolled out as a canary, see `FeatureFlags::is_enabled_for`.
+pub const ENABLE_NEW_SIZING_ENGINE: &str = "enable_new_sizing_engine";
+/// Stop processing trade signals. Enabled by the risk monitor when the drawdown breaches the hard
+/// limit, disabled manually.
+pub const HALT_TRADING: &str = "halt_trading";
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub enabled: bool,
+    pub description: Option<String>,
+    /// Share of keys the flag is enabled for, see `FeatureFlags::is_enabled_for`
+    pub rollout_percentage: i16,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateFeatureFlag {
+    pub enabled: bool,
+    pub description: Option<String>,
+    pub rollout_percentage: Option<i16>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FlagState {
+    enabled: bool,
+    rollout_percentage: i16,
+}
+
+struct Cache {
+    flags: HashMap<String, FlagState>,
+    loaded_at: Instant,
+}
+
+/// Runtime toggles stored in the database. Reads are served from a cache refreshed every
+/// `CACHE_TTL`, so flags flipped directly in the database are picked up without a restart.
+pub struct FeatureFlags {
+    db: PgPool,
+    cache: RwLock<Option<Cache>>,
+}
+
+impl FeatureFlags {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Unknown flags and flags which can't be loaded are considered disabled. Rollout percentage is
+    /// not taken into account.
+    pub async fn is_enabled(&self, name: &str) -> bool {
+        self.state(name).await.is_some_and(|state| state.enabled)
+    }
+
+    /// Whether the flag is enabled for `key`. Keys are spread evenly over 100 buckets and the flag
+    /// is enabled for buckets below its rollout percentage, so the same key always gets the same
+    /// answer for a given rollout.
+    pub async fn is_enabled_for(&self, name: &str, key: Uuid) -> bool {
+        let bucket = (key.as_u128() % 100) as i16;
+        self.state(name)
+            .await
+            .is_some_and(|state| state.enabled && bucket < state.rollout_percentage)
+    }
+
+    /// Whether trading is halted, see `HALT_TRADING`. Unlike other flags the kill switch fails
+    /// closed: when flags can't be loaded the last loaded state holds, and trading is halted when
+    /// none was loaded yet.
+    pub async fn is_halted(&self) -> bool {
+        match self.lookup(HALT_TRADING).await {
+            Ok(state) => state.is_some_and(|state| state.enabled),
+            Err(err) => {
+                tracing::error!("Failed to load feature flags, error: {:?}", err);
+                self.cache.read().await.as_ref().is_none_or(|cache| {
+                    cache
+                        .flags
+                        .get(HALT_TRADING)
+                        .is_some_and(|state| state.enabled)
+                })
+            }
+        }
+    }
+
+    async fn state(&self, name: &str) -> Option<FlagState> {
+        match self.lookup(name).await {
+            Ok(state) => state,
+            Err(err) => {
+                tracing::error!("Failed to load feature flags, error: {:?}", err);
+                None
+            }
+        }
+    }
+
+    async fn lookup(&self, name: &str) -> Result<Option<FlagState>, sqlx::Error> {
+        {
+            let cache = self.cache.read().await;
+            if let Some(cache) = cache.as_ref().filter(|c| c.loaded_at.elapsed() < CACHE_TTL) {
+                return Ok(cache.flags.get(name).copied());
+            }
+        }
+
+        Ok(self.reload().await?.get(name).copied())
+    }
+
+    pub async fn list(&self) -> Result<Vec<FeatureFlag>, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>("SELECT * FROM feature_flags ORDER BY name")
+            .fetch_all(&self.db)
+            .await
+    }
+
+    pub async fn set(
+        &self,
+        name: &str,
+        update: &UpdateFeatureFlag,
+    ) -> Result<FeatureFlag, sqlx::Error> {
+        let flag = sqlx::query_as::<_, FeatureFlag>(
+            r#"
+            INSERT INTO feature_flags (name, enabled, description, rollout_percentage, modified_at)
+            VALUES ($1, $2, $3, COALESCE($4, 100), NOW())
+            ON CONFLICT (name) DO UPDATE
+            SET enabled = EXCLUDED.enabled,
+                description = COALESCE(EXCLUDED.description, feature_flags.description),
+                rollout_percentage = COALESCE($4, feature_flags.rollout_percentage),
+                modified_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(update.enabled)
+        .bind(&update.description)
+        .bind(update.rollout_percentage)
+        .fetch_one(&self.db)
+        .await?;
+
+        // Drop the cache so the change takes effect immediately
+        *self.cache.write().await = None;
+
+        Ok(flag)
+    }
+
+    async fn reload(&self) -> Result<HashMap<String, FlagState>, sqlx::Error> {
+        let flags: HashMap<String, FlagState> = self
+            .list()
+            .await?
+            .into_iter()
+            .map(|flag| {
+                let state = FlagState {
+                    enabled: flag.enabled,
+                    rollout_percentage: flag.rollout_percentage,
+                };
+                (flag.name, state)
+            })
+            .collect();
+
+        *self.cache.write().await = Some(Cache {
+            flags: flags.clone(),
+            loaded_at: Instant::now(),
+        });
+
+        Ok(flags)
+    }
+}
@@ -0,0 +1,197 @@
+use rand_core::RngCore;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde::Deserialize;
+use tokio::time::Duration;
+
+use crate::order::OrderSide;
+
+const BPS: Decimal = Decimal::from_parts(1, 0, 0, false, 4);
+
+/// Price impact of a simulated execution, as a fraction of the reference price.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum SlippageModel {
+    #[default]
+    None,
+    /// Constant number of basis points
+    FixedBps { bps: Decimal },
+    /// Share of the bid/ask spread, half of it crosses from mid to the touch
+    Spread {
+        #[serde(default = "default_spread_share")]
+        share: Decimal,
+    },
+    /// Square root market impact, `coefficient * sqrt(quantity / volume)`
+    VolumeImpact { coefficient: Decimal },
+}
+
+/// Commission charged on every simulated fill.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum CommissionModel {
+    #[default]
+    None,
+    /// Flat amount per fill
+    PerFill { amount: Decimal },
+    /// Amount per share or unit, at least `minimum` per fill
+    PerShare {
+        rate: Decimal,
+        #[serde(default)]
+        minimum: Decimal,
+    },
+    /// Basis points of the notional of the fill, at least `minimum` per fill
+    Bps {
+        bps: Decimal,
+        #[serde(default)]
+        minimum: Decimal,
+    },
+}
+
+impl CommissionModel {
+    /// Commission of a fill of `quantity` at `price`.
+    pub fn commission(&self, quantity: Decimal, price: Decimal) -> Decimal {
+        match self {
+            Self::None => Decimal::ZERO,
+            Self::PerFill { amount } => *amount,
+            Self::PerShare { rate, minimum } => (rate * quantity).max(*minimum),
+            Self::Bps { bps, minimum } => (bps * BPS * quantity * price).max(*minimum),
+        }
+    }
+}
+
+/// Delay between an order submission and its execution.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum LatencyModel {
+    Fixed {
+        millis: u64,
+    },
+    /// Uniformly distributed between both bounds
+    Random {
+        min_millis: u64,
+        max_millis: u64,
+    },
+}
+
+impl Default for LatencyModel {
+    fn default() -> Self {
+        Self::Fixed { millis: 0 }
+    }
+}
+
+/// Execution model of simulated orders. The defaults fill everything at the reference price
+/// without delay or commission, which is what paper results were based on so far.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FillModel {
+    #[serde(default)]
+    pub slippage: SlippageModel,
+    #[serde(default)]
+    pub latency: LatencyModel,
+    #[serde(default)]
+    pub commission: CommissionModel,
+    /// Largest share of the market volume a single fill can take. Larger orders are split into
+    /// several partial fills.
+    pub max_participation: Option<Decimal>,
+}
+
+/// Market state a simulated order executes against.
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub price: Decimal,
+    pub spread: Decimal,
+    /// Volume traded over the period a partial fill represents, e.g. a bar
+    pub volume: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedFill {
+    /// Time since the order submission
+    pub delay: Duration,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    /// Commission of the fill
+    pub fee: Decimal,
+}
+
+impl FillModel {
+    /// Simulate executions of an order. Every partial fill takes one more latency period and is
+    /// priced with its own slippage and commission.
+    pub fn simulate<R: RngCore>(
+        &self,
+        side: OrderSide,
+        quantity: Decimal,
+        quote: &Quote,
+        rng: &mut R,
+    ) -> Vec<SimulatedFill> {
+        let max_fill = self
+            .max_participation
+            .map(|participation| (quote.volume * participation).floor())
+            .filter(|max_fill| *max_fill > Decimal::ZERO)
+            .unwrap_or(quantity);
+
+        let mut fills = Vec::new();
+        let mut delay = Duration::ZERO;
+        let mut remaining = quantity;
+        while remaining > Decimal::ZERO {
+            let fill_quantity = remaining.min(max_fill);
+            delay += self.latency(rng);
+
+            let slippage = self.slippage(fill_quantity, quote) * quote.price;
+            let price = match side {
+                OrderSide::Buy => quote.price + slippage,
+                OrderSide::Sell => quote.price - slippage,
+            };
+
+            fills.push(SimulatedFill {
+                delay,
+                quantity: fill_quantity,
+                price,
+                fee: self.commission.commission(fill_quantity, price),
+            });
+            remaining -= fill_quantity;
+        }
+
+        fills
+    }
+
+    fn slippage(&self, quantity: Decimal, quote: &Quote) -> Decimal {
+        match &self.slippage {
+            SlippageModel::None => Decimal::ZERO,
+            SlippageModel::FixedBps { bps } => bps * BPS,
+            SlippageModel::Spread { share } => {
+                if quote.price.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    quote.spread * share / quote.price
+                }
+            }
+            SlippageModel::VolumeImpact { coefficient } => {
+                if quote.volume.is_zero() {
+                    return Decimal::ZERO;
+                }
+                let ratio = (quantity / quote.volume).to_f64().unwrap_or_default();
+                coefficient * Decimal::from_f64(ratio.sqrt()).unwrap_or_default()
+            }
+        }
+    }
+
+    fn latency<R: RngCore>(&self, rng: &mut R) -> Duration {
+        let millis = match self.latency {
+            LatencyModel::Fixed { millis } => millis,
+            LatencyModel::Random {
+                min_millis,
+                max_millis,
+            } => {
+                let range = max_millis.saturating_sub(min_millis);
+                min_millis + rng.next_u64() % (range + 1)
+            }
+        };
+        Duration::from_millis(millis)
+    }
+}
+
+fn default_spread_share() -> Decimal {
+    Decimal::new(5, 1)
+}
@@ -0,0 +1,92 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{api::alert::SignalType, order::OrderSide, projections, trade_signal::TradeSignal};
+
+/// Predicate signals of a strategy must pass before an order is created for them, see
+/// `Strategy::filters`. Stop loss updates only go through the bar filters.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignalFilter {
+    /// Signal bar traded at least `volume`
+    MinVolume { volume: Decimal },
+    /// Signal bar closed within the range
+    PriceRange {
+        #[serde(default)]
+        min: Option<Decimal>,
+        #[serde(default)]
+        max: Option<Decimal>,
+    },
+    /// Only signals opening positions on `side`, e.g. long only strategies
+    Side { side: OrderSide },
+    /// Only signals of the timeframes, e.g. `["1h", "4h"]`
+    Timeframes { timeframes: Vec<String> },
+    /// No signal opening a position while the strategy holds one on the other side of the symbol
+    NoOppositePosition,
+}
+
+impl SignalFilter {
+    /// Why the signal is filtered out, `None` when it passes. `position` is the quantity of the
+    /// signal symbol held by the strategy, negative for short positions.
+    pub fn rejection(&self, trade_signal: &TradeSignal, position: Decimal) -> Option<String> {
+        let side = match trade_signal.signal_type {
+            SignalType::OpenLong(_) => Some(OrderSide::Buy),
+            SignalType::OpenShort(_) => Some(OrderSide::Sell),
+            SignalType::StopLossUpdate(_) => None,
+        };
+        let bar = &trade_signal.bar_data;
+        let close = *bar.close.as_ref();
+
+        match self {
+            Self::MinVolume { volume } if bar.volume < *volume => {
+                Some(format!("bar volume {} is below {}", bar.volume, volume))
+            }
+            Self::PriceRange { min, max }
+                if min.is_some_and(|min| close < min) || max.is_some_and(|max| close > max) =>
+            {
+                Some(format!("bar close {close} is out of the price range"))
+            }
+            Self::Side { side: only } if side.is_some_and(|side| side != *only) => {
+                Some(format!("only {} signals are traded", only.as_ref()))
+            }
+            Self::Timeframes { timeframes }
+                if !timeframes
+                    .iter()
+                    .any(|timeframe| timeframe.eq_ignore_ascii_case(&trade_signal.timeframe)) =>
+            {
+                Some(format!(
+                    "timeframe {} is not traded",
+                    trade_signal.timeframe
+                ))
+            }
+            Self::NoOppositePosition
+                if match side {
+                    Some(OrderSide::Buy) => position.is_sign_negative() && !position.is_zero(),
+                    Some(OrderSide::Sell) => position.is_sign_positive() && !position.is_zero(),
+                    None => false,
+                } =>
+            {
+                Some(format!("a position of {position} is open"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Why the first filter of the signal strategy rejecting it does, `None` when all pass.
+pub async fn rejection(
+    db: &PgPool,
+    trade_signal: &TradeSignal,
+) -> Result<Option<String>, sqlx::Error> {
+    let filters = &trade_signal.strategy.filters;
+    let position = if filters.contains(&SignalFilter::NoOppositePosition) {
+        projections::position(db, trade_signal.strategy.id, &trade_signal.ticker).await?
+    } else {
+        Decimal::ZERO
+    };
+
+    Ok(filters
+        .iter()
+        .find_map(|filter| filter.rejection(trade_signal, position)))
+}
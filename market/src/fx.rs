@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use tokio::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::api::objects::{Money, USD};
+
+/// Exchange rates amounts of accounts in different currencies are converted with for reporting.
+/// Rates are the units of a currency one unit of the base currency buys.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FxConfig {
+    /// Currency P&L across accounts is aggregated in
+    #[serde(default = "default_base")]
+    pub base: String,
+    /// Rates API answering `GET {url}?base={base}` with `{"rates": {"EUR": 0.92, ...}}`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Seconds rates fetched from the API are used for
+    #[serde(default = "default_ttl")]
+    pub ttl: u64,
+    /// Fixed rates by currency, used over the ones of the API
+    #[serde(default)]
+    pub rates: HashMap<String, Decimal>,
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        Self {
+            base: default_base(),
+            url: None,
+            ttl: default_ttl(),
+            rates: HashMap::new(),
+        }
+    }
+}
+
+fn default_base() -> String {
+    USD.to_owned()
+}
+
+fn default_ttl() -> u64 {
+    3600
+}
+
+/// Whether `code` has the shape of an ISO 4217 currency code.
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|byte| byte.is_ascii_uppercase())
+}
+
+#[derive(Debug, ThisError)]
+pub enum FxError {
+    #[error("No exchange rate of {currency} to {base}")]
+    MissingRate { currency: String, base: String },
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct RatesResponse {
+    rates: HashMap<String, Decimal>,
+}
+
+/// Exchange rates of the configuration, fetched from its API when they aren't fixed.
+pub struct FxRates {
+    config: FxConfig,
+    http: reqwest::Client,
+    /// Rates of the API and the time they were fetched at
+    fetched: Mutex<Option<(Instant, HashMap<String, Decimal>)>>,
+}
+
+impl FxRates {
+    pub fn new(config: FxConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            fetched: Mutex::new(None),
+        }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.config.base
+    }
+
+    /// Units of `currency` one unit of the base currency buys.
+    pub async fn rate(&self, currency: &str) -> Result<Decimal, FxError> {
+        if currency == self.config.base {
+            return Ok(Decimal::ONE);
+        }
+        if let Some(rate) = self.config.rates.get(currency) {
+            return Ok(*rate);
+        }
+        let missing = || FxError::MissingRate {
+            currency: currency.to_owned(),
+            base: self.config.base.clone(),
+        };
+        let Some(url) = &self.config.url else {
+            return Err(missing());
+        };
+
+        let ttl = Duration::from_secs(self.config.ttl);
+        let mut fetched = self.fetched.lock().await;
+        if !matches!(&*fetched, Some((fetched_at, _)) if fetched_at.elapsed() < ttl) {
+            let response: RatesResponse = self
+                .http
+                .get(url)
+                .query(&[("base", &self.config.base)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            *fetched = Some((Instant::now(), response.rates));
+        }
+
+        fetched
+            .as_ref()
+            .and_then(|(_, rates)| rates.get(currency))
+            .copied()
+            // NOTE: a zero rate can't be converted with, treated as a missing one
+            .filter(|rate| *rate > Decimal::ZERO)
+            .ok_or_else(missing)
+    }
+
+    /// `money` in the base currency.
+    pub async fn convert(&self, money: &Money) -> Result<Money, FxError> {
+        let rate = self.rate(&money.currency).await?;
+        Ok(Money::new(money.amount / rate, self.config.base.clone()))
+    }
+}
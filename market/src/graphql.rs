@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{extract::State, Extension};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{
+    api::error::ApiError,
+    dedup::{self, ProcessedAlert},
+    order::{Fill, OrderRecord},
+    pnl::{self, PnlQuery, StrategyPnl},
+    strategy::Strategy,
+    users::Caller,
+    App,
+};
+
+/// Deepest selection a query may make, enough for strategy > orders > fields.
+const MAX_DEPTH: usize = 6;
+const MAX_COMPLEXITY: usize = 500;
+const DEFAULT_ORDERS_LIMIT: i64 = 100;
+const MAX_ORDERS_LIMIT: i64 = 1000;
+
+pub type ReportingSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema() -> ReportingSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+pub async fn graphql(
+    State(app): State<Arc<App>>,
+    Extension(schema): Extension<ReportingSchema>,
+    Extension(caller): Extension<Caller>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    schema
+        .execute(request.into_inner().data(app).data(caller))
+        .await
+        .into()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn strategies(&self, ctx: &Context<'_>) -> Result<Vec<StrategyNode>> {
+        let app = ctx.data::<Arc<App>>()?;
+        let caller = ctx.data::<Caller>()?;
+        Ok(app
+            .config
+            .strategies
+            .iter()
+            .filter(|strategy| caller.owns(strategy))
+            .cloned()
+            .map(StrategyNode)
+            .collect())
+    }
+
+    async fn strategy(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<StrategyNode>> {
+        let app = ctx.data::<Arc<App>>()?;
+        let caller = ctx.data::<Caller>()?;
+        Ok(app
+            .config
+            .strategies
+            .iter()
+            .find(|strategy| strategy.id == id && caller.owns(strategy))
+            .cloned()
+            .map(StrategyNode))
+    }
+
+    /// Most recent orders, of every strategy when `strategy_id` isn't set.
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        strategy_id: Option<Uuid>,
+        limit: Option<i64>,
+    ) -> Result<Vec<OrderRecord>> {
+        let app = ctx.data::<Arc<App>>()?;
+        let caller = ctx.data::<Caller>()?;
+        let strategy_ids = caller.scope(&app.config, strategy_id)?;
+        fetch_orders(app, strategy_ids.as_deref(), limit).await
+    }
+
+    async fn order(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<OrderRecord>> {
+        let app = ctx.data::<Arc<App>>()?;
+        let caller = ctx.data::<Caller>()?;
+        let order = OrderRecord::fetch(&app.db, id)
+            .await
+            .map_err(ApiError::from)?;
+        Ok(order.filter(|order| caller.strategy(&app.config, order.strategy_id).is_ok()))
+    }
+}
+
+/// Strategy of the configuration, with its reporting data.
+pub struct StrategyNode(Strategy);
+
+#[Object(name = "Strategy")]
+impl StrategyNode {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn enabled(&self) -> bool {
+        self.0.enabled
+    }
+
+    async fn broker(&self) -> &str {
+        self.0.broker.as_ref()
+    }
+
+    async fn tenant_id(&self) -> &str {
+        &self.0.tenant_id
+    }
+
+    async fn shadow(&self) -> bool {
+        self.0.shadow
+    }
+
+    async fn dry_run(&self) -> bool {
+        self.0.dry_run
+    }
+
+    /// Alerts received within the dedup window, most recent first.
+    async fn alerts(&self, ctx: &Context<'_>) -> Result<Vec<ProcessedAlert>> {
+        let app = ctx.data::<Arc<App>>()?;
+        Ok(dedup::recent_alerts(&app.db, self.0.id)
+            .await
+            .map_err(ApiError::from)?)
+    }
+
+    async fn orders(&self, ctx: &Context<'_>, limit: Option<i64>) -> Result<Vec<OrderRecord>> {
+        let app = ctx.data::<Arc<App>>()?;
+        fetch_orders(app, Some(&[self.0.id]), limit).await
+    }
+
+    async fn fills(&self, ctx: &Context<'_>) -> Result<Vec<Fill>> {
+        let app = ctx.data::<Arc<App>>()?;
+        Ok(Fill::fetch_for_strategy(&app.db, self.0.id)
+            .await
+            .map_err(ApiError::from)?)
+    }
+
+    /// P&L of the strategy, daily P&L limited to the days between `from` and `to`.
+    async fn pnl(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<StrategyPnl> {
+        let app = ctx.data::<Arc<App>>()?;
+        let client = self
+            .0
+            .broker
+            .get_account_client(app, self.0.account.as_deref())?;
+        let mut pnl = pnl::strategy_pnl(&app.db, &client, self.0.id).await?;
+        pnl.retain_days(&PnlQuery { from, to });
+        Ok(pnl)
+    }
+}
+
+async fn fetch_orders(
+    app: &App,
+    strategy_ids: Option<&[Uuid]>,
+    limit: Option<i64>,
+) -> Result<Vec<OrderRecord>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_ORDERS_LIMIT)
+        .clamp(0, MAX_ORDERS_LIMIT);
+    Ok(OrderRecord::fetch_recent(&app.db, strategy_ids, limit)
+        .await
+        .map_err(ApiError::from)?)
+}
@@ -0,0 +1,402 @@
+// `Status` is large, but it's the error type of every tonic call
+#![allow(clippy::result_large_err)]
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use tonic::{transport::Server, Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        alert::{BarData, SignalType, TrailStopPrice, WebhookAlertData},
+        error::ApiError,
+        objects::Broker,
+        price::Price,
+    },
+    api_keys::Role,
+    clients::BrokerClient,
+    middleware::{resolve_caller, REQUEST_ID_HEADER},
+    order::OrderRecord,
+    strategy::Strategy,
+    usage::{self, UsageKind},
+    users::Caller,
+    App,
+};
+
+pub mod proto {
+    tonic::include_proto!("market.v1");
+}
+
+use proto::market_server::{Market, MarketServer};
+
+const DEFAULT_ORDERS_LIMIT: u32 = 100;
+
+/// gRPC counterpart of the REST routes, backed by the same application state.
+pub struct MarketService {
+    app: Arc<App>,
+}
+
+impl MarketService {
+    pub fn new(app: Arc<App>) -> Self {
+        Self { app }
+    }
+
+    /// Check the API key of the request like the `auth` middleware does for REST routes.
+    async fn authorize<T>(&self, request: &Request<T>, required: Role) -> Result<Caller, Status> {
+        let Some(secret) = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Err(Status::unauthenticated(
+                "API key isn't correct or not found",
+            ));
+        };
+
+        let Some(caller) = resolve_caller(&self.app, secret).await? else {
+            return Err(Status::unauthenticated(
+                "API key isn't correct or not found",
+            ));
+        };
+        if caller.role < required {
+            return Err(Status::permission_denied(format!(
+                "API key with {} role can't access this call, {} role is required",
+                caller.role.as_ref(),
+                required.as_ref()
+            )));
+        }
+
+        Ok(caller)
+    }
+
+    fn strategy(&self, caller: &Caller, strategy_id: Uuid) -> Result<&Strategy, Status> {
+        Ok(caller.strategy(&self.app.config, strategy_id)?)
+    }
+}
+
+pub async fn serve(app: Arc<App>, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    tracing::info!("gRPC listening on {}", addr);
+    Server::builder()
+        .add_service(MarketServer::new(MarketService::new(app)))
+        .serve(addr)
+        .await
+}
+
+#[tonic::async_trait]
+impl Market for MarketService {
+    async fn submit_signal(
+        &self,
+        request: Request<proto::SubmitSignalRequest>,
+    ) -> Result<Response<proto::SubmitSignalResponse>, Status> {
+        let caller = self.authorize(&request, Role::Trade).await?;
+
+        let request_id = request
+            .metadata()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 128)
+            .map_or_else(|| Uuid::new_v4().to_string(), str::to_owned);
+        let alert_data = WebhookAlertData::try_from(request.into_inner())?;
+        let tenant_id = self
+            .strategy(&caller, alert_data.strategy_id)?
+            .tenant_id
+            .clone();
+
+        usage::check_quotas(&self.app.db, &self.app.config, &tenant_id).await?;
+        let strategy_id = alert_data.strategy_id;
+        let accepted = self
+            .app
+            .accept_alert(alert_data, request_id.clone())
+            .await?;
+        if let Err(err) = usage::record(
+            &self.app.db,
+            &tenant_id,
+            UsageKind::Signal,
+            Some(strategy_id),
+        )
+        .await
+        {
+            tracing::error!("Failed to record usage, error: {:?}", err);
+        }
+
+        Ok(Response::new(proto::SubmitSignalResponse {
+            request_id,
+            duplicate: !accepted,
+        }))
+    }
+
+    async fn list_orders(
+        &self,
+        request: Request<proto::ListOrdersRequest>,
+    ) -> Result<Response<proto::ListOrdersResponse>, Status> {
+        let caller = self.authorize(&request, Role::ReadOnly).await?;
+
+        let request = request.into_inner();
+        let strategy_id = request
+            .strategy_id
+            .as_deref()
+            .map(|id| parse_uuid("strategy_id", id))
+            .transpose()?;
+        let limit = request.limit.unwrap_or(DEFAULT_ORDERS_LIMIT);
+        let strategy_ids = caller.scope(&self.app.config, strategy_id)?;
+        let orders = OrderRecord::fetch_recent(&self.app.db, strategy_ids.as_deref(), limit.into())
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(Response::new(proto::ListOrdersResponse {
+            orders: orders.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_order(
+        &self,
+        request: Request<proto::GetOrderRequest>,
+    ) -> Result<Response<proto::Order>, Status> {
+        let caller = self.authorize(&request, Role::ReadOnly).await?;
+
+        let order_id = parse_uuid("order_id", &request.into_inner().order_id)?;
+        let order = OrderRecord::fetch(&self.app.db, order_id)
+            .await
+            .map_err(ApiError::from)?
+            .filter(|order| caller.strategy(&self.app.config, order.strategy_id).is_ok())
+            .ok_or_else(|| Status::not_found(format!("Unknown order - {order_id}")))?;
+
+        Ok(Response::new(order.into()))
+    }
+
+    async fn list_positions(
+        &self,
+        request: Request<proto::ListPositionsRequest>,
+    ) -> Result<Response<proto::ListPositionsResponse>, Status> {
+        let caller = self.authorize(&request, Role::ReadOnly).await?;
+        if !caller.is_operator() {
+            return Err(Status::permission_denied(
+                "API key of a user can't list broker positions",
+            ));
+        }
+
+        let broker = request.into_inner().broker;
+        let broker = Broker::from_str(&broker)
+            .map_err(|_| Status::invalid_argument(format!("Unknown broker - {broker}")))?;
+        let positions = broker
+            .get_client(&self.app)
+            .map_err(ApiError::from)?
+            .get_positions()
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(Response::new(proto::ListPositionsResponse {
+            positions: positions
+                .iter()
+                .map(|position| proto::Position {
+                    symbol: position.symbol().to_owned(),
+                    quantity: position.quantity().to_string(),
+                    average_entry_price: position.average_entry_price().amount.to_string(),
+                    current_price: position
+                        .current_price()
+                        .map(|price| price.amount.to_string()),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn list_strategies(
+        &self,
+        request: Request<proto::ListStrategiesRequest>,
+    ) -> Result<Response<proto::ListStrategiesResponse>, Status> {
+        let caller = self.authorize(&request, Role::ReadOnly).await?;
+
+        Ok(Response::new(proto::ListStrategiesResponse {
+            strategies: self
+                .app
+                .config
+                .strategies
+                .iter()
+                .filter(|strategy| caller.owns(strategy))
+                .map(Into::into)
+                .collect(),
+        }))
+    }
+
+    async fn get_strategy(
+        &self,
+        request: Request<proto::GetStrategyRequest>,
+    ) -> Result<Response<proto::Strategy>, Status> {
+        let caller = self.authorize(&request, Role::ReadOnly).await?;
+
+        let strategy_id = parse_uuid("strategy_id", &request.into_inner().strategy_id)?;
+        let strategy = self.strategy(&caller, strategy_id)?;
+        Ok(Response::new(strategy.into()))
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(err: ApiError) -> Self {
+        let message = err.to_string();
+        match err {
+            ApiError::ValidationError(violations) => Status::invalid_argument(
+                violations
+                    .iter()
+                    .map(|violation| format!("{}: {}", violation.field, violation.message))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ApiError::BadRequest(_)
+            | ApiError::IOError(_)
+            | ApiError::JsonExtractorRejection(_) => Status::invalid_argument(message),
+            ApiError::NotFound(_) => Status::not_found(message),
+            ApiError::ConstraintError(_) => Status::failed_precondition(message),
+            ApiError::Unauthorized(_) => Status::unauthenticated(message),
+            ApiError::Forbidden(_) => Status::permission_denied(message),
+            ApiError::TooManyRequests(_) => Status::resource_exhausted(message),
+            ApiError::PayloadTooLarge => Status::resource_exhausted(message),
+            ApiError::ServiceUnavailable => Status::unavailable(message),
+            ApiError::InternalServerError | ApiError::TradingClientError(_) => {
+                Status::internal(message)
+            }
+        }
+    }
+}
+
+impl TryFrom<proto::SubmitSignalRequest> for WebhookAlertData {
+    type Error = Status;
+
+    fn try_from(request: proto::SubmitSignalRequest) -> Result<Self, Self::Error> {
+        let bar = request
+            .bar
+            .ok_or_else(|| Status::invalid_argument("bar is required"))?;
+        let trail_stop_price = TrailStopPrice(parse_decimal(
+            "trail_stop_price",
+            &request.trail_stop_price,
+        )?);
+        let signal_type = match proto::SignalType::try_from(request.signal_type) {
+            Ok(proto::SignalType::OpenLong) => SignalType::OpenLong(trail_stop_price),
+            Ok(proto::SignalType::OpenShort) => SignalType::OpenShort(trail_stop_price),
+            Ok(proto::SignalType::StopLossUpdate) => SignalType::StopLossUpdate(trail_stop_price),
+            Ok(proto::SignalType::Unspecified) | Err(_) => {
+                return Err(Status::invalid_argument("signal_type is required"))
+            }
+        };
+
+        Ok(Self {
+            alert_id: request.alert_id,
+            strategy_id: parse_uuid("strategy_id", &request.strategy_id)?,
+            ticker: request.ticker,
+            timeframe: request.timeframe,
+            exchange: request.exchange,
+            trail_stop_price: Some(signal_type.trail_stop_price()),
+            signal_type,
+            bar_data: BarData {
+                time: parse_timestamp("bar.time", bar.time)?,
+                open: Price::new(parse_decimal("bar.open", &bar.open)?),
+                high: Price::new(parse_decimal("bar.high", &bar.high)?),
+                low: Price::new(parse_decimal("bar.low", &bar.low)?),
+                close: Price::new(parse_decimal("bar.close", &bar.close)?),
+                volume: parse_decimal("bar.volume", &bar.volume)?,
+            },
+            time: parse_timestamp("time", request.time)?,
+            quantity: request
+                .quantity
+                .map(|quantity| parse_decimal("quantity", &quantity))
+                .transpose()?,
+            notional: request
+                .notional
+                .map(|notional| parse_decimal("notional", &notional))
+                .transpose()?,
+            time_in_force: request
+                .time_in_force
+                .map(|time_in_force| {
+                    time_in_force.parse().map_err(|_| {
+                        Status::invalid_argument(format!(
+                            "time_in_force {time_in_force} is unknown"
+                        ))
+                    })
+                })
+                .transpose()?,
+            limit_price: request
+                .limit_price
+                .map(|price| parse_decimal("limit_price", &price))
+                .transpose()?,
+            extended_hours: request.extended_hours,
+            legs: Vec::new(),
+        })
+    }
+}
+
+impl From<OrderRecord> for proto::Order {
+    fn from(order: OrderRecord) -> Self {
+        Self {
+            order_id: order.order_id.to_string(),
+            strategy_id: order.strategy_id.to_string(),
+            broker: order.broker,
+            broker_order_id: order.broker_order_id,
+            ticker: order.ticker,
+            side: order.side,
+            quantity: order.quantity.to_string(),
+            notional: order.notional.map(|notional| notional.to_string()),
+            time_in_force: order.time_in_force,
+            limit_price: order.limit_price.map(|price| price.to_string()),
+            extended_hours: order.extended_hours,
+            filled_quantity: order.filled_quantity.to_string(),
+            filled_avg_price: order.filled_avg_price.map(|price| price.to_string()),
+            last_filled_at: order.last_filled_at.map(timestamp),
+            status: order.status,
+            execution_path: order.execution_path,
+            request_id: order.request_id,
+            created_at: Some(timestamp(order.created_at)),
+            modified_at: Some(timestamp(order.modified_at)),
+            environment: order.environment,
+            broker_account: order.broker_account,
+        }
+    }
+}
+
+impl From<&Strategy> for proto::Strategy {
+    fn from(strategy: &Strategy) -> Self {
+        Self {
+            strategy_id: strategy.id.to_string(),
+            name: strategy.name.clone(),
+            enabled: strategy.enabled,
+            broker: strategy.broker.as_ref().to_owned(),
+            tenant_id: strategy.tenant_id.clone(),
+            order_quantity: strategy.order_quantity.to_string(),
+            risk_per_trade: strategy.risk_per_trade.to_string(),
+            shadow: strategy.shadow,
+            dry_run: strategy.dry_run,
+            time_in_force: strategy.order_time_in_force().as_ref().to_owned(),
+        }
+    }
+}
+
+fn parse_uuid(field: &str, value: &str) -> Result<Uuid, Status> {
+    value
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("{field} is not a valid uuid")))
+}
+
+fn parse_decimal(field: &str, value: &str) -> Result<Decimal, Status> {
+    value
+        .parse()
+        .map_err(|_| Status::invalid_argument(format!("{field} is not a valid decimal")))
+}
+
+fn parse_timestamp(
+    field: &str,
+    value: Option<prost_types::Timestamp>,
+) -> Result<DateTime<Utc>, Status> {
+    value
+        .and_then(|value| {
+            Utc.timestamp_opt(value.seconds, value.nanos.try_into().ok()?)
+                .single()
+        })
+        .ok_or_else(|| Status::invalid_argument(format!("{field} is not a valid timestamp")))
+}
+
+fn timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
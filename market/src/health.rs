@@ -0,0 +1,277 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time::{timeout, Duration},
+};
+
+use crate::{
+    api::objects::Broker,
+    clients::BrokerClient,
+    core::{Core, ORDER_SYNC_INTERVAL},
+    migrators,
+    supervisor::{TaskState, TaskStatus},
+    App,
+};
+
+/// Time a dependency has to answer before it's reported down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Runs of the core background work which may be missed before it's reported down.
+const MISSED_RUNS: u32 = 3;
+/// Time the readiness probe has to reach the database, probes are frequent and should fail fast.
+const READY_TIMEOUT: Duration = Duration::from_secs(1);
+/// Time between failing readiness and closing the listener, so the orchestrator stops routing
+/// traffic here before connections are refused.
+pub const DRAIN_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyCheck {
+    /// `database`, `event_queue`, `tasks`, `broker.alpaca` or `broker.accounts.<name>`
+    pub name: String,
+    pub status: CheckStatus,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Status of every dependency, up only when all of them are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeepHealth {
+    pub status: CheckStatus,
+    pub checks: Vec<DependencyCheck>,
+    /// Background tasks of the supervisor, see `TaskSupervisor`
+    #[serde(default)]
+    pub tasks: Vec<TaskStatus>,
+}
+
+async fn check(
+    name: String,
+    dependency: impl Future<Output = Result<(), String>>,
+) -> DependencyCheck {
+    let started_at = Instant::now();
+    let result = timeout(CHECK_TIMEOUT, dependency)
+        .await
+        .unwrap_or_else(|_| Err(format!("no answer within {}s", CHECK_TIMEOUT.as_secs())));
+
+    DependencyCheck {
+        name,
+        status: match result {
+            Ok(()) => CheckStatus::Up,
+            Err(_) => CheckStatus::Down,
+        },
+        latency_ms: started_at.elapsed().as_millis() as u64,
+        error: result.err(),
+    }
+}
+
+/// Round-trip the database, authenticate with every broker account and check the core
+/// background work publishing order events is running and no background task is restarting
+/// after a crash. Dependencies are checked concurrently.
+pub async fn deep_health(app: &App) -> DeepHealth {
+    let oanda = app
+        .config
+        .brokers
+        .oanda
+        .is_some()
+        .then_some((Broker::Oanda, None));
+    let tradier = app
+        .config
+        .brokers
+        .tradier
+        .is_some()
+        .then_some((Broker::Tradier, None));
+    let accounts = std::iter::once((Broker::Alpaca, None))
+        .chain(
+            app.config
+                .brokers
+                .accounts
+                .keys()
+                .map(|account| (Broker::Alpaca, Some(account))),
+        )
+        .chain(oanda)
+        .chain(tradier);
+    let brokers = accounts.map(|(broker, account)| {
+        let name = match account {
+            None => format!("broker.{}", broker.as_ref()),
+            Some(account) => format!("broker.accounts.{account}"),
+        };
+        check(name, async move {
+            let (client, _) = app
+                .clients
+                .venue(&broker, account.map(String::as_str))
+                .map_err(|err| err.to_string())?;
+            client.get_account().await.map_err(|err| err.to_string())?;
+            Ok(())
+        })
+    });
+
+    let tasks = app.tasks.statuses();
+    let (database, brokers, event_queue, tasks_check) = tokio::join!(
+        check("database".to_owned(), async {
+            sqlx::query("SELECT 1")
+                .execute(&app.db)
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(())
+        }),
+        join_all(brokers),
+        check("event_queue".to_owned(), async { event_queue(&app.core) }),
+        check("tasks".to_owned(), async { crashed_tasks(&tasks) }),
+    );
+
+    let checks: Vec<DependencyCheck> = std::iter::once(database)
+        .chain(brokers)
+        .chain([tasks_check, event_queue])
+        .collect();
+    DeepHealth {
+        status: if checks.iter().all(|check| check.status == CheckStatus::Up) {
+            CheckStatus::Up
+        } else {
+            CheckStatus::Down
+        },
+        checks,
+        tasks,
+    }
+}
+
+fn event_queue(core: &Core) -> Result<(), String> {
+    let last_run = core
+        .last_run()
+        .ok_or_else(|| "core background work is not running".to_owned())?;
+    if last_run.elapsed() > ORDER_SYNC_INTERVAL * MISSED_RUNS {
+        return Err(format!(
+            "core background work last ran {}s ago",
+            last_run.elapsed().as_secs()
+        ));
+    }
+
+    Ok(())
+}
+
+fn crashed_tasks(tasks: &[TaskStatus]) -> Result<(), String> {
+    let restarting: Vec<&str> = tasks
+        .iter()
+        .filter(|task| task.state == TaskState::Restarting)
+        .map(|task| task.name.as_str())
+        .collect();
+    if !restarting.is_empty() {
+        return Err(format!(
+            "restarting after a crash: {}",
+            restarting.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Set once the process is asked to stop. Readiness fails from then on while the requests in
+/// flight finish.
+#[derive(Debug, Default)]
+pub struct Shutdown(AtomicBool);
+
+impl Shutdown {
+    pub fn begin(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Whether the process should receive traffic, with the reasons when it shouldn't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Readiness {
+    pub ready: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<String>,
+}
+
+/// Ready once every migration is applied, the database pool hands out connections, the broker
+/// clients of every credential set are created and the process isn't shutting down. Unlike the
+/// deep health check the brokers aren't called.
+pub async fn readiness(app: &App) -> Readiness {
+    let mut reasons = Vec::new();
+
+    if app.shutdown.is_draining() {
+        reasons.push("shutting down".to_owned());
+    }
+    if let Err(err) = app.clients.initialize_accounts() {
+        reasons.push(format!("broker clients not initialized: {err}"));
+    }
+    match database(&app.db).await {
+        Ok(0) => {}
+        Ok(pending) => reasons.push(format!("{pending} migrations pending")),
+        Err(err) => reasons.push(format!("database unavailable: {err}")),
+    }
+
+    Readiness {
+        ready: reasons.is_empty(),
+        reasons,
+    }
+}
+
+/// Number of embedded migrations not applied successfully yet.
+async fn database(db: &PgPool) -> Result<usize, String> {
+    if db.is_closed() {
+        return Err("pool is closed".to_owned());
+    }
+
+    let applied: Vec<i64> = timeout(
+        READY_TIMEOUT,
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success").fetch_all(db),
+    )
+    .await
+    .map_err(|_| format!("no answer within {}s", READY_TIMEOUT.as_secs()))?
+    .map_err(|err| err.to_string())?;
+    let applied: HashSet<i64> = applied.into_iter().collect();
+
+    Ok(migrators()
+        .iter()
+        .flat_map(|migrator| migrator.iter())
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .count())
+}
+
+/// Resolve once the process is asked to stop by SIGTERM or Ctrl-C, after failing readiness for
+/// [`DRAIN_DELAY`]. Meant for the graceful shutdown of the server.
+pub async fn drain_on_terminate(app: Arc<App>) {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM, error: {:?}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        _ = terminate => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    tracing::info!("Shutting down, draining for {}s", DRAIN_DELAY.as_secs());
+    app.shutdown.begin();
+    tokio::time::sleep(DRAIN_DELAY).await;
+}
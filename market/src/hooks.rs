@@ -0,0 +1,194 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{core::TradeError, order::NewOrder, trade_signal::TradeSignal};
+
+/// What a pre-trade hook makes of an order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreTradeDecision {
+    /// Pass the order on to the next hook
+    Allow,
+    /// Pass the order on with a note recorded next to it
+    Annotate(String),
+    /// Reject the order, its signal fails with the reason
+    Veto(String),
+}
+
+/// What an order is placed for.
+#[derive(Debug, Clone, Copy)]
+pub enum OrderOrigin<'a> {
+    /// Order of a trade signal, the first chunk of the entry `entry_id` when it's time-sliced
+    Signal {
+        trade_signal: &'a TradeSignal,
+        entry_id: Option<Uuid>,
+    },
+    /// Later chunk of a time-sliced entry
+    Chunk { entry_id: Uuid },
+    /// Order of a rebalance of the account of a strategy
+    Rebalance,
+    /// Market order closing a position of a strategy
+    Flatten,
+    /// Take profit order of the ladder of a filled entry order
+    TakeProfit { parent_order_id: Uuid },
+    /// Market order for what's left of an order canceled after its time to live
+    Expiry { order_id: Uuid },
+}
+
+impl OrderOrigin<'_> {
+    /// Signal the order is placed for, only orders of signals have one.
+    pub fn trade_signal(&self) -> Option<&TradeSignal> {
+        match self {
+            Self::Signal { trade_signal, .. } => Some(trade_signal),
+            _ => None,
+        }
+    }
+}
+
+/// Check run on every order after the checks of its origin, before it's recorded and submitted,
+/// e.g. a compliance check of its own.
+#[axum::async_trait]
+pub trait PreTradeHook: Send + Sync {
+    /// Name the notes and vetoes of the hook are recorded with
+    fn name(&self) -> &str;
+
+    async fn before_order(&self, origin: &OrderOrigin<'_>, order: &NewOrder) -> PreTradeDecision;
+}
+
+/// Order the pre-trade hooks passed, and what became of it.
+#[derive(Debug)]
+pub struct PlacedOrder<'a> {
+    pub origin: OrderOrigin<'a>,
+    pub order: &'a NewOrder,
+    /// Notes of the pre-trade hooks
+    pub annotations: &'a [OrderAnnotation],
+    /// Status of the submitted order, or why it failed
+    pub result: Result<&'a str, &'a TradeError>,
+}
+
+/// Action run once an order was submitted or failed to, e.g. logging of its
+/// own or syncing the position to another system. Failures are logged, the order stays as it is.
+#[axum::async_trait]
+pub trait PostTradeHook: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn after_order(&self, placed: &PlacedOrder<'_>) -> Result<(), String>;
+}
+
+/// Note of a pre-trade hook on an order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct OrderAnnotation {
+    pub order_id: Uuid,
+    /// Name of the hook
+    pub hook: String,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrderAnnotation {
+    pub async fn insert(db: &PgPool, annotation: &Self) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO order_annotations (order_id, hook, note, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(annotation.order_id)
+        .bind(&annotation.hook)
+        .bind(&annotation.note)
+        .bind(annotation.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Notes on an order in the order the hooks made them.
+    pub async fn fetch_for(db: &PgPool, order_id: Uuid) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM order_annotations WHERE order_id = $1 ORDER BY created_at, hook",
+        )
+        .bind(order_id)
+        .fetch_all(db)
+        .await
+    }
+}
+
+/// Hooks of the orders of the core, run in the order they were added. Shared by the
+/// `TradeExecutor` they're added to and the core running them.
+#[derive(Default)]
+pub struct TradeHooks {
+    pre_trade: RwLock<Vec<Arc<dyn PreTradeHook>>>,
+    post_trade: RwLock<Vec<Arc<dyn PostTradeHook>>>,
+}
+
+impl TradeHooks {
+    pub fn add_pre_trade(&self, hook: Arc<dyn PreTradeHook>) {
+        self.pre_trade
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(hook);
+    }
+
+    pub fn add_post_trade(&self, hook: Arc<dyn PostTradeHook>) {
+        self.post_trade
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(hook);
+    }
+
+    /// Run the pre-trade hooks on `order` until one vetoes it. Returns the notes of the hooks,
+    /// the error of the veto when there is one.
+    pub async fn before_order(
+        &self,
+        origin: &OrderOrigin<'_>,
+        order: &NewOrder,
+    ) -> Result<Vec<OrderAnnotation>, TradeError> {
+        let hooks = self
+            .pre_trade
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+
+        let mut annotations = Vec::new();
+        for hook in hooks {
+            match hook.before_order(origin, order).await {
+                PreTradeDecision::Allow => {}
+                PreTradeDecision::Annotate(note) => annotations.push(OrderAnnotation {
+                    order_id: order.id,
+                    hook: hook.name().to_owned(),
+                    note,
+                    created_at: Utc::now(),
+                }),
+                PreTradeDecision::Veto(reason) => {
+                    return Err(TradeError::Vetoed(hook.name().to_owned(), reason))
+                }
+            }
+        }
+
+        Ok(annotations)
+    }
+
+    /// Run the post-trade hooks on `placed`, all of them whether some fail or not.
+    pub async fn after_order(&self, placed: &PlacedOrder<'_>) {
+        let hooks = self
+            .post_trade
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+
+        for hook in hooks {
+            if let Err(err) = hook.after_order(placed).await {
+                tracing::error!(
+                    "Post-trade hook {} failed on order {}, error: {}",
+                    hook.name(),
+                    placed.order.id,
+                    err
+                );
+            }
+        }
+    }
+}
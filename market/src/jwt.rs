@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use thiserror::Error as ThisError;
+use tokio::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use crate::{api_keys::Role, app_config::Jwt, users::Caller};
+
+/// Keys are refetched after the TTL or when a token is signed with an unknown key.
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+/// Keys are refetched for unknown keys at most once per interval.
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, ThisError)]
+pub enum JwtError {
+    #[error(transparent)]
+    InvalidToken(#[from] jsonwebtoken::errors::Error),
+    #[error(transparent)]
+    JwksError(#[from] reqwest::Error),
+    #[error("Token is signed with an unknown key")]
+    UnknownKey,
+    #[error("Token is signed with {0:?}, which isn't accepted for its key")]
+    UnacceptedAlgorithm(Algorithm),
+    #[error("Token doesn't grant any role")]
+    NoRole,
+    #[error("Token doesn't name a user")]
+    NoUser,
+}
+
+struct Keys {
+    set: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Verifies tokens issued by the configured identity provider against its JWKS.
+pub struct JwtVerifier {
+    config: Jwt,
+    http: reqwest::Client,
+    keys: RwLock<Option<Keys>>,
+}
+
+impl JwtVerifier {
+    pub fn new(config: Jwt) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            keys: RwLock::new(None),
+        }
+    }
+
+    /// Role granted by the token. With several roles in the claim the highest one is granted.
+    pub async fn role(&self, token: &str) -> Result<Role, JwtError> {
+        Ok(self.caller(token).await?.role)
+    }
+
+    /// Role and user of the token, the user is read from the user claim when one is configured.
+    pub async fn caller(&self, token: &str) -> Result<Caller, JwtError> {
+        let header = decode_header(token)?;
+        let jwk = self.key(header.kid.as_deref()).await?;
+
+        // NOTE: the algorithm of the token header isn't trusted, it has to be the one of the key
+        let accepted = match &jwk.common.key_algorithm {
+            Some(algorithm) => vec![algorithm
+                .to_string()
+                .parse()
+                .map_err(|_| JwtError::UnacceptedAlgorithm(header.alg))?],
+            None => self.config.algorithms.clone(),
+        };
+        if !accepted.contains(&header.alg) {
+            return Err(JwtError::UnacceptedAlgorithm(header.alg));
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let claims = decode::<HashMap<String, serde_json::Value>>(
+            token,
+            &DecodingKey::from_jwk(&jwk)?,
+            &validation,
+        )?
+        .claims;
+
+        let values = match claims.get(&self.config.role_claim) {
+            Some(serde_json::Value::String(value)) => vec![value.as_str()],
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().filter_map(|value| value.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let role = values
+            .into_iter()
+            .filter_map(|value| {
+                if self.config.roles.is_empty() {
+                    value.parse().ok()
+                } else {
+                    self.config.roles.get(value).copied()
+                }
+            })
+            .max()
+            .ok_or(JwtError::NoRole)?;
+
+        let user_id = match &self.config.user_claim {
+            Some(claim) => match claims.get(claim) {
+                Some(serde_json::Value::String(user_id)) => Some(user_id.clone()),
+                _ => return Err(JwtError::NoUser),
+            },
+            None => None,
+        };
+
+        Ok(Caller { role, user_id })
+    }
+
+    async fn key(&self, kid: Option<&str>) -> Result<Jwk, JwtError> {
+        if let Some(jwk) = cached_key(self.keys.read().await.as_ref(), kid) {
+            return jwk;
+        }
+
+        // NOTE: the provider may have rotated its keys, refetch before rejecting the token. The
+        // lock is held while fetching, so concurrent requests wait for a single fetch
+        let mut keys = self.keys.write().await;
+        if let Some(jwk) = cached_key(keys.as_ref(), kid) {
+            return jwk;
+        }
+        let set = self
+            .http
+            .get(&self.config.jwks_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+        let jwk = find_key(&set, kid);
+
+        *keys = Some(Keys {
+            set,
+            fetched_at: Instant::now(),
+        });
+
+        jwk.ok_or(JwtError::UnknownKey)
+    }
+}
+
+/// Key of the cached set, `None` when the set has to be fetched. Tokens with unknown keys don't
+/// refetch the set within `JWKS_REFETCH_INTERVAL` of the last fetch.
+fn cached_key(keys: Option<&Keys>, kid: Option<&str>) -> Option<Result<Jwk, JwtError>> {
+    let keys = keys?;
+    let age = keys.fetched_at.elapsed();
+    match find_key(&keys.set, kid) {
+        Some(jwk) if age < JWKS_TTL => Some(Ok(jwk)),
+        None if age < JWKS_REFETCH_INTERVAL => Some(Err(JwtError::UnknownKey)),
+        _ => None,
+    }
+}
+
+/// Tokens without a key id are accepted only when the set has a single key.
+fn find_key(set: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => set.find(kid).cloned(),
+        None if set.keys.len() == 1 => set.keys.first().cloned(),
+        None => None,
+    }
+}
@@ -0,0 +1,74 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::order::{OrderRecord, OrderSide};
+
+/// Exits of the positions of a strategy split across price targets, placed as limit orders once
+/// an entry with a stop loss fills. Targets are multiples of the risk of the entry, the distance
+/// from its price to its stop. The share of the position no target takes is left to the stop, see
+/// `stops`, which covers only that share while the targets rest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TakeProfitLadder {
+    pub targets: Vec<ProfitTarget>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ProfitTarget {
+    /// Gain in multiples of the risk of the entry the target is placed at, e.g. `2` for +2R
+    pub r_multiple: Decimal,
+    /// Share of the entry quantity taken at the target, e.g. `0.5`
+    pub share: Decimal,
+}
+
+/// Limit order taking profit at a target of a ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LadderLeg {
+    pub limit_price: Decimal,
+    pub quantity: Decimal,
+}
+
+/// Legs of `ladder` for `quantity` entered on `side` at `entry` with a stop at `stop`, empty when
+/// the stop carries no risk. Legs are in the order of the targets and exit on the other side.
+pub fn plan(
+    ladder: &TakeProfitLadder,
+    side: OrderSide,
+    entry: Decimal,
+    stop: Decimal,
+    quantity: Decimal,
+) -> Vec<LadderLeg> {
+    let risk = match side {
+        OrderSide::Buy => entry - stop,
+        OrderSide::Sell => stop - entry,
+    };
+    if risk <= Decimal::ZERO {
+        return Vec::new();
+    }
+
+    ladder
+        .targets
+        .iter()
+        .map(|target| LadderLeg {
+            limit_price: match side {
+                OrderSide::Buy => entry + risk * target.r_multiple,
+                OrderSide::Sell => entry - risk * target.r_multiple,
+            },
+            quantity: quantity * target.share,
+        })
+        // Targets of shorts at or beyond a 100% gain can't be reached
+        .filter(|leg| leg.limit_price > Decimal::ZERO && leg.quantity > Decimal::ZERO)
+        .collect()
+}
+
+/// Part of a position the take profit orders of its entry took or hold, all of the open ones.
+pub fn held_quantity(take_profits: &[OrderRecord]) -> Decimal {
+    take_profits
+        .iter()
+        .map(|order| {
+            if order.is_open() {
+                order.quantity
+            } else {
+                order.filled_quantity
+            }
+        })
+        .sum()
+}
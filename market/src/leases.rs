@@ -0,0 +1,100 @@
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::time::{sleep, Duration, Instant};
+use uuid::Uuid;
+
+/// Interval a signal waiting for the lease of its strategy retries taking it at
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Leases of the `strategy_leases` table, which let one signal of a strategy at a time place
+/// orders across the instances sharing the database.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Leases {
+    /// Milliseconds a lease is held at most, leases of crashed instances expire after it
+    #[serde(default = "default_ttl")]
+    pub ttl: u64,
+    /// Milliseconds a signal waits for the lease of its strategy before it fails
+    #[serde(default = "default_wait")]
+    pub wait: u64,
+}
+
+impl Default for Leases {
+    fn default() -> Self {
+        Self {
+            ttl: default_ttl(),
+            wait: default_wait(),
+        }
+    }
+}
+
+fn default_ttl() -> u64 {
+    60_000
+}
+
+fn default_wait() -> u64 {
+    10_000
+}
+
+/// Exclusive lease of a strategy taken by one signal. It's released explicitly, leases which
+/// aren't, e.g. as their instance stopped, expire after their time to live.
+#[derive(Debug)]
+pub struct StrategyLease {
+    pub strategy_id: Uuid,
+    holder: Uuid,
+}
+
+impl StrategyLease {
+    /// Take the lease of the strategy once it's released or expired, `None` when it's still held
+    /// after the configured wait.
+    pub async fn acquire(
+        db: &PgPool,
+        strategy_id: Uuid,
+        config: &Leases,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let lease = Self {
+            strategy_id,
+            holder: Uuid::new_v4(),
+        };
+        let deadline = Instant::now() + Duration::from_millis(config.wait);
+        loop {
+            if lease.try_acquire(db, config.ttl).await? {
+                return Ok(Some(lease));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            sleep(RETRY_INTERVAL).await;
+        }
+    }
+
+    async fn try_acquire(&self, db: &PgPool, ttl: u64) -> Result<bool, sqlx::Error> {
+        let acquired: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO strategy_leases (strategy_id, holder, expires_at)
+            VALUES ($1, $2, NOW() + $3 * INTERVAL '1 millisecond')
+            ON CONFLICT (strategy_id) DO UPDATE
+            SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at
+            WHERE strategy_leases.expires_at <= NOW()
+            RETURNING holder
+            "#,
+        )
+        .bind(self.strategy_id)
+        .bind(self.holder)
+        .bind(ttl as f64)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(acquired.is_some())
+    }
+
+    /// Release the lease unless it expired and was taken by another signal meanwhile.
+    pub async fn release(self, db: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM strategy_leases WHERE strategy_id = $1 AND holder = $2")
+            .bind(self.strategy_id)
+            .bind(self.holder)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
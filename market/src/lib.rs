@@ -1,56 +1,571 @@
+pub mod activities;
+pub mod admin;
+pub mod alert_writer;
+pub mod allowlist;
 pub mod api;
+pub mod api_keys;
 pub mod app_config;
+pub mod audit;
+pub mod backtest;
+pub mod bars;
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod clients;
+pub mod control;
+pub mod cooldown;
+pub mod corporate_actions;
+pub mod dashboard;
+pub mod dca;
+pub mod dead_letters;
+pub mod debounce;
+pub mod dedup;
+pub mod divergence;
+pub mod events;
+pub mod executions;
+pub mod export;
+pub mod exposure;
+pub mod feature_flags;
+pub mod fill_model;
+pub mod filters;
+pub mod fx;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod hooks;
+pub mod jwt;
+pub mod ladder;
+pub mod leases;
+pub mod logging;
+pub mod mapping;
+pub mod market_data;
 pub mod middleware;
+#[cfg(feature = "test-broker")]
+pub mod mock_broker;
+pub mod nonces;
+pub mod notifications;
+pub mod oanda;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod options;
+pub mod order;
+pub mod pnl;
+pub mod portfolio;
+pub mod precision;
+pub mod preview;
+pub mod projections;
+pub mod rate_limit;
+pub mod rebalance;
+pub mod recorder;
+pub mod replay;
+pub mod reports;
+pub mod retry;
+pub mod risk;
+pub mod routing;
+pub mod scheduler;
+pub mod schedules;
+pub mod secrets;
+pub mod signal_source;
+pub mod simulation;
+pub mod sizing;
+pub mod stats;
+pub mod status;
+pub mod stops;
 pub mod strategy;
+pub mod streams;
+pub mod supervisor;
+pub mod throttle;
 pub mod core;
+pub mod trade_executor;
 pub mod trade_signal;
+pub mod trade_updates;
+pub mod tradier;
+pub mod usage;
+pub mod users;
 
+use core::{Core, TradeError};
 use std::{error::Error, sync::Arc, time::Duration};
 
+use admin::{StrategyStatus, StrategySwitch};
+use alert::WebhookAlertData;
+use alert_writer::AlertWriter;
+use allowlist::IpAllowlist;
 use apca::{ApiInfo, Client as AlpacaClient};
 use api::*;
-use app_config::AppConfig;
+use app_config::{AppConfig, Events, Notifications};
+use audit::AuditEntry;
 use axum::{
     middleware::{from_fn, from_fn_with_state},
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
+use cache::BrokerCache;
+use chrono::Utc;
 use clients::Clients;
-use sqlx::{postgres::PgConnectOptions, Error as SqlxError, PgPool};
-use core::Core;
+use dead_letters::DeadLetter;
+use error::ApiError;
+#[cfg(feature = "nats")]
+use events::NatsEventBus;
+#[cfg(feature = "redis")]
+use events::RedisEventBus;
+use events::{Event, EventBus, InMemoryEventBus};
+use executions::ExecutionPublisher;
+use feature_flags::FeatureFlags;
+use fx::FxRates;
+use health::Shutdown;
+use hooks::TradeHooks;
+use jwt::JwtVerifier;
+use notifications::Notifier;
+use oanda::OandaClient;
+use objects::Broker;
+use precision::Precision;
+use rate_limit::RateLimiter;
+use recorder::BrokerRecorder;
+use risk::RiskMonitor;
+use scheduler::RequestScheduler;
+use serde_json::json;
+use simulation::AlertSimulation;
+use sqlx::{
+    migrate::Migrator, postgres::PgConnectOptions, ConnectOptions, Error as SqlxError, PgPool,
+};
+use strategy::Strategy;
+use supervisor::TaskSupervisor;
+use throttle::OrderThrottle;
 use tower::ServiceBuilder;
+use tracing::Instrument;
+use trade_executor::{Priority, TradeExecutor};
+use trade_signal::TradeSignal;
+use tradier::TradierClient;
+use users::Caller;
+use uuid::Uuid;
 
 pub struct App {
     pub db: PgPool,
     pub clients: Arc<Clients>,
     pub core: Arc<Core>,
+    pub feature_flags: Arc<FeatureFlags>,
+    pub risk_monitor: Arc<RiskMonitor>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub webhook_allowlist: Arc<IpAllowlist>,
+    pub jwt: Option<JwtVerifier>,
+    pub alert_writer: AlertWriter,
+    pub executor: Arc<TradeExecutor>,
+    pub fx: Arc<FxRates>,
+    /// Background tasks of the app, see `TaskSupervisor`
+    pub tasks: TaskSupervisor,
+    pub shutdown: Shutdown,
     pub config: AppConfig,
 }
 
+impl App {
+    /// Validate an alert, store it and queue the trade signal to be processed by the executor,
+    /// see `TradeExecutor`. Returns `false` for duplicates of an alert accepted within the dedup
+    /// window, those aren't processed again. Alerts whose trade signal fails processing, finds
+    /// the queue of its broker full or whose strategy is disabled while it's queued are kept as
+    /// dead letters.
+    pub async fn accept_alert(
+        &self,
+        alert_data: WebhookAlertData,
+        request_id: String,
+    ) -> Result<bool, ApiError> {
+        alert_data
+            .validate(&self.config.webhook, Utc::now())
+            .map_err(ApiError::ValidationError)?;
+
+        let mut trade_signal = TradeSignal::from_alert_data(alert_data.clone(), &self.config)?;
+        trade_signal.request_id = Some(request_id.clone());
+        self.check_switch(&trade_signal).await?;
+
+        let idempotency_key = alert_data.idempotency_key();
+        if !dedup::claim_alert(
+            &self.db,
+            &idempotency_key,
+            trade_signal.strategy.id,
+            Some(&request_id),
+            self.config.webhook.dedup_window,
+        )
+        .await?
+        {
+            tracing::info!("Duplicate alert {} ignored", idempotency_key);
+            return Ok(false);
+        }
+        // NOTE: only claimed alerts are stored, duplicates would count twice in the bars
+        if let Err(err) = self
+            .alert_writer
+            .write(&alert_data, &trade_signal.ticker)
+            .await
+        {
+            if let Err(err) =
+                dedup::release_alert(&self.db, &idempotency_key, Some(&request_id)).await
+            {
+                tracing::error!(
+                    "Failed to release alert {}, error: {:?}",
+                    idempotency_key,
+                    err
+                );
+            }
+            return Err(err);
+        }
+
+        self.core.events().publish(Event::AlertReceived {
+            strategy_id: trade_signal.strategy.id,
+            ticker: trade_signal.ticker.clone(),
+            signal_type: trade_signal.signal_type.clone(),
+            request_id: Some(request_id.clone()),
+            received_at: Utc::now(),
+        });
+
+        let priority = Priority::of_signal(&self.db, &trade_signal).await?;
+        let broker = trade_signal.strategy.broker.clone();
+        let core = Arc::clone(&self.core);
+        let db = self.db.clone();
+        let clients = Arc::clone(&self.clients);
+        let notifications = self.config.notifications.clone();
+        let dead_letter = alert_data.clone();
+        let dead_letter_request_id = request_id.clone();
+
+        let job = Box::pin(
+            async move {
+                let result = match admin::is_strategy_disabled(&db, trade_signal.strategy.id).await
+                {
+                    Ok(true) => Err(TradeError::StrategyDisabled(
+                        trade_signal.strategy.name.clone(),
+                    )),
+                    Ok(false) => {
+                        execute_trade_signal(&core, &db, &clients, &notifications, trade_signal)
+                            .await
+                    }
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = result {
+                    tracing::error!("Failed to process trade signal, error: {:?}", err);
+                    if let Err(err) =
+                        DeadLetter::record(&db, &alert_data, Some(&request_id), &err.to_string())
+                            .await
+                    {
+                        tracing::error!("Failed to record dead letter, error: {:?}", err);
+                    }
+                };
+            }
+            .instrument(tracing::Span::current()),
+        );
+        if let Err(err) = self.executor.submit(&broker, priority, job) {
+            tracing::error!("Trade signal not queued, error: {}", err);
+            DeadLetter::record(
+                &self.db,
+                &dead_letter,
+                Some(&dead_letter_request_id),
+                &err.to_string(),
+            )
+            .await?;
+            return Err(ApiError::ServiceUnavailable);
+        }
+
+        Ok(true)
+    }
+
+    /// Run the alert through validation, routing and the processing of its trade signal as a dry
+    /// run, see `Core::simulate_trade_signal`. The alert isn't stored, deduplicated or published.
+    pub async fn simulate_alert(
+        &self,
+        alert_data: WebhookAlertData,
+        request_id: String,
+    ) -> Result<AlertSimulation, ApiError> {
+        alert_data
+            .validate(&self.config.webhook, Utc::now())
+            .map_err(ApiError::ValidationError)?;
+        self.simulate(alert_data, Some(request_id)).await
+    }
+
+    /// Run a persisted alert through the current processing as a dry run like `simulate_alert`,
+    /// skipping the age checks it passed when it was received, see `replay`.
+    pub async fn replay_alert(
+        &self,
+        alert_data: WebhookAlertData,
+    ) -> Result<AlertSimulation, ApiError> {
+        self.simulate(alert_data, None).await
+    }
+
+    async fn simulate(
+        &self,
+        alert_data: WebhookAlertData,
+        request_id: Option<String>,
+    ) -> Result<AlertSimulation, ApiError> {
+        let mut trade_signal = TradeSignal::from_alert_data(alert_data, &self.config)?;
+        trade_signal.request_id = request_id;
+        self.check_switch(&trade_signal).await?;
+
+        let client =
+            match routing::holding_client(&self.clients, &self.db, &mut trade_signal).await? {
+                Some(client) => client,
+                None => routing::signal_client(&self.clients, &mut trade_signal).await?,
+            };
+        Ok(self
+            .core
+            .simulate_trade_signal(client, trade_signal)
+            .await?)
+    }
+
+    /// Process the alert of a dead letter again, skipping the dedup and age checks it passed when
+    /// it was accepted. Waits for the trade signal to be processed. Dead letters the caller
+    /// doesn't see are reported unknown.
+    pub async fn replay_dead_letter(
+        &self,
+        caller: &Caller,
+        id: Uuid,
+        request_id: String,
+    ) -> Result<DeadLetter, ApiError> {
+        let dead_letter = DeadLetter::fetch(&self.db, id)
+            .await?
+            .filter(|dead_letter| caller.sees(&self.config, dead_letter.strategy_id))
+            .ok_or_else(|| ApiError::NotFound(format!("Unknown dead letter - {id}")))?;
+
+        let mut trade_signal = TradeSignal::from_alert_data(dead_letter.alert.0, &self.config)?;
+        trade_signal.request_id = Some(request_id);
+        self.check_switch(&trade_signal).await?;
+
+        let result = execute_trade_signal(
+            &self.core,
+            &self.db,
+            &self.clients,
+            &self.config.notifications,
+            trade_signal,
+        )
+        .await;
+        let error = result.as_ref().err().map(ToString::to_string);
+        let dead_letter = DeadLetter::replayed(&self.db, id, error.as_deref()).await?;
+
+        result?;
+        Ok(dead_letter)
+    }
+
+    /// Recover orders of the configured strategies missing locally, then sync the open ones with
+    /// the broker, see `Core::recover_orders`.
+    pub async fn reconcile(&self) -> Result<(), TradeError> {
+        self.core.recover_orders(&self.config.strategies).await?;
+        self.core.sync_orders().await
+    }
+
+    /// Enable or disable a strategy at runtime, see `admin::switch_strategy`. The switch is
+    /// recorded in the audit log and sent to the strategy switch channels. Signals of a disabled
+    /// strategy still queued are dead-lettered instead of processed, see `accept_alert`.
+    pub async fn switch_strategy(
+        &self,
+        caller: &Caller,
+        strategy: &Strategy,
+        switch: &StrategySwitch,
+        request_id: Option<&str>,
+    ) -> Result<StrategyStatus, ApiError> {
+        let status = admin::switch_strategy(&self.db, strategy, switch).await?;
+
+        let action = if switch.enabled {
+            "strategy.enable"
+        } else {
+            "strategy.disable"
+        };
+        AuditEntry::record(
+            &self.db,
+            caller,
+            action,
+            &format!("strategy:{}", strategy.id),
+            json!({ "name": strategy.name, "reason": switch.reason }),
+            request_id,
+        )
+        .await?;
+
+        self.notify_switch(strategy, switch).await;
+
+        Ok(status)
+    }
+
+    /// Tell the strategy switch channels a strategy was enabled or disabled.
+    pub(crate) async fn notify_switch(&self, strategy: &Strategy, switch: &StrategySwitch) {
+        let channels = &self.config.notifications.strategy_switches;
+        if !channels.is_empty() {
+            let mut message = format!(
+                "Strategy {} {}",
+                strategy.name,
+                if switch.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+            if let Some(reason) = &switch.reason {
+                message.push_str(&format!(" - {reason}"));
+            }
+            Notifier::new(self.config.notifications.clone())
+                .notify(channels, &message)
+                .await;
+        }
+    }
+
+    /// Reject signals of strategies disabled at runtime, see `admin::switch_strategy`.
+    async fn check_switch(&self, trade_signal: &TradeSignal) -> Result<(), ApiError> {
+        if admin::is_strategy_disabled(&self.db, trade_signal.strategy.id).await? {
+            return Err(ApiError::BadRequest(format!(
+                "Strategy {} with id {} is disabled",
+                trade_signal.strategy.name, trade_signal.strategy.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Process the trade signal on the venue of its strategy, see `routing::signal_client`, signals
+/// reducing a position on the venue holding it, see `routing::holding_client`. Other signals of
+/// strategies with a failover go to the failover venue instead while the circuit breaker of the
+/// account is open, operators are told on the channels of the failover.
+async fn execute_trade_signal(
+    core: &Core,
+    db: &PgPool,
+    clients: &Clients,
+    notifications: &Notifications,
+    mut trade_signal: TradeSignal,
+) -> Result<(), TradeError> {
+    if let Some(client) = routing::holding_client(clients, db, &mut trade_signal).await? {
+        return core.process_trade_signal(client, trade_signal).await;
+    }
+
+    let mut client = routing::signal_client(clients, &mut trade_signal).await?;
+    if let Some(failover) = trade_signal
+        .strategy
+        .failover
+        .clone()
+        .filter(|_| client.is_circuit_open())
+    {
+        let strategy = &trade_signal.strategy;
+        let message = format!(
+            "Signal for {} of strategy {} failed over from failing {} {} to {} {}",
+            trade_signal.ticker,
+            strategy.name,
+            strategy.broker.as_ref(),
+            strategy.account.as_deref().unwrap_or("default"),
+            failover.broker.as_ref(),
+            failover.account.as_deref().unwrap_or("default")
+        );
+        client = routing::failover_client(clients, &failover, &mut trade_signal)?;
+        Notifier::new(notifications.clone())
+            .notify(&failover.channels, &message)
+            .await;
+    }
+
+    core.process_trade_signal(client, trade_signal).await
+}
+
+/// Migrations embedded at build time, run in order when the app is built. The TimescaleDB ones
+/// of the `timescale` feature share the migrations table, so each set ignores the other's.
+pub(crate) fn migrators() -> Vec<Migrator> {
+    #[allow(unused_mut)]
+    let mut migrators = vec![sqlx::migrate!("./migrations")];
+    #[cfg(feature = "timescale")]
+    {
+        migrators.push(sqlx::migrate!("./migrations_timescale"));
+        for migrator in &mut migrators {
+            migrator.set_ignore_missing(true);
+        }
+    }
+    migrators
+}
+
 pub async fn build_app(config: AppConfig, clients: Arc<Clients>) -> Result<App, SqlxError> {
-    let opts = config.database.url.parse::<PgConnectOptions>()?;
+    let database = &config.database;
+    let mut opts = database
+        .url
+        .parse::<PgConnectOptions>()?
+        .log_slow_statements(
+            log::LevelFilter::Warn,
+            Duration::from_millis(database.slow_statement_threshold),
+        );
+    if let Some(timeout) = database.statement_timeout {
+        // NOTE: postgres reads unitless timeouts as milliseconds
+        opts = opts.options([("statement_timeout", timeout * 1000)]);
+    }
 
     let pool = sqlx::pool::PoolOptions::new()
-        .max_connections(10)
-        .acquire_timeout(Duration::from_secs(5))
+        .max_connections(database.max_connections)
+        .acquire_timeout(Duration::from_secs(database.acquire_timeout))
         .idle_timeout(None)
-        .min_connections(1)
+        .min_connections(database.min_connections)
         .connect_with(opts)
         .await?;
 
-    match sqlx::migrate!("./migrations").run(&pool).await {
-        Ok(_) => tracing::info!("successfully run db migrations"),
-        Err(err) => {
-            tracing::error!("failed to run db migrations, error: {:?}", err);
-            std::process::exit(1);
+    for migrator in migrators() {
+        match migrator.run(&pool).await {
+            Ok(_) => tracing::info!("successfully run db migrations"),
+            Err(err) => {
+                tracing::error!("failed to run db migrations, error: {:?}", err);
+                std::process::exit(1);
+            }
         }
     }
 
+    // Positions are projected from the fills again, so a crash can't leave them behind the fills
+    for drift in projections::rebuild(&pool).await? {
+        tracing::warn!(
+            "Projected position of strategy {} in {} was {}, rebuilt as {}",
+            drift.strategy_id,
+            drift.ticker,
+            drift.projected.unwrap_or_default(),
+            drift.rebuilt.unwrap_or_default()
+        );
+    }
+
+    let feature_flags = Arc::new(FeatureFlags::new(pool.clone()));
+    let risk_monitor = Arc::new(
+        RiskMonitor::new(
+            pool.clone(),
+            Notifier::new(config.notifications.clone()),
+            Arc::clone(&feature_flags),
+            config.notifications.escalation.clone(),
+        )
+        .with_daily_loss(config.daily_loss.clone())
+        .with_exposure_limits(config.exposure_limits.clone())
+        .with_portfolios(config.portfolios.clone(), &config.strategies),
+    );
+    let tasks = TaskSupervisor::new();
+    let alert_writer = AlertWriter::spawn(pool.clone(), &config.webhook.persistence, &tasks);
+    let hooks = Arc::new(TradeHooks::default());
     let app = App {
+        core: Arc::new(
+            Core::new(
+                pool.clone(),
+                Arc::clone(&clients),
+                Arc::clone(&feature_flags),
+                Arc::clone(&risk_monitor),
+                OrderThrottle::new(
+                    config.throttle.orders_per_symbol_per_minute,
+                    config.throttle.orders_per_strategy_per_hour,
+                ),
+                config.paper.clone(),
+                config
+                    .recording
+                    .clone()
+                    .map(|recording| BrokerRecorder::new(pool.clone(), recording)),
+            )
+            .with_strategies(&config.strategies)
+            .with_events(build_events(&config.events, &tasks))
+            .with_executions(build_executions(&config, &tasks))
+            .with_leases(config.leases.clone())
+            .with_precision(Precision::new(config.precision.clone()))
+            .with_hooks(Arc::clone(&hooks)),
+        ),
         db: pool,
         clients,
-        core: Arc::new(Core),
+        feature_flags,
+        risk_monitor,
+        rate_limiter: Arc::new(RateLimiter::new(config.rate_limits.clone())),
+        webhook_allowlist: Arc::new(IpAllowlist::new(config.webhook.clone())),
+        jwt: config.jwt.clone().map(JwtVerifier::new),
+        alert_writer,
+        executor: Arc::new(
+            TradeExecutor::new(config.executor.clone(), tasks.clone()).with_hooks(hooks),
+        ),
+        fx: Arc::new(FxRates::new(config.fx.clone())),
+        tasks,
+        shutdown: Shutdown::default(),
         config,
     };
 
@@ -59,25 +574,160 @@ pub async fn build_app(config: AppConfig, clients: Arc<Clients>) -> Result<App,
 
 pub fn build_clients(config: &AppConfig) -> Result<Arc<Clients>, Box<dyn Error>> {
     let alpaca = AlpacaClient::new(ApiInfo::from_parts(
-        &config.brokers.alpaca.apca_api_base_url,
+        config.brokers.alpaca.base_url(config.environment),
         &config.brokers.alpaca.apca_api_key_id,
         &config.brokers.alpaca.apca_api_secret_key,
     )?);
 
-    Ok(Arc::new(Clients {
-        alpaca: Arc::new(alpaca),
-    }))
+    let mut clients = Clients::new(
+        config.environment,
+        alpaca,
+        build_cache(config, Broker::Alpaca.as_ref())?,
+        RequestScheduler::new(config.brokers.alpaca.requests_per_minute),
+    );
+    for (name, account) in &config.brokers.accounts {
+        let cache = build_cache(config, &format!("{}:{name}", account.broker().as_ref()))?;
+        clients.add_account(name.clone(), account.clone(), cache);
+    }
+    if let Some(oanda) = &config.brokers.oanda {
+        clients = clients.with_oanda(
+            OandaClient::new(oanda, config.environment),
+            build_cache(config, Broker::Oanda.as_ref())?,
+            RequestScheduler::new(oanda.requests_per_minute),
+        );
+    }
+    if let Some(tradier) = &config.brokers.tradier {
+        clients = clients.with_tradier(
+            TradierClient::new(tradier, config.environment),
+            build_cache(config, Broker::Tradier.as_ref())?,
+            RequestScheduler::new(tradier.requests_per_minute),
+        );
+    }
+
+    Ok(Arc::new(clients))
+}
+
+/// Bus of the live events, connected in the background on `tasks` when it's shared through a
+/// server.
+#[cfg_attr(not(any(feature = "redis", feature = "nats")), allow(unused_variables))]
+fn build_events(events: &Events, tasks: &TaskSupervisor) -> Arc<dyn EventBus> {
+    match events {
+        Events::InMemory => Arc::new(InMemoryEventBus::new()),
+        #[cfg(feature = "redis")]
+        Events::Redis { url, stream } => match RedisEventBus::open(url, stream, tasks) {
+            Ok(bus) => Arc::new(bus),
+            Err(err) => {
+                tracing::error!("invalid redis url of the event bus, error: {:?}", err);
+                std::process::exit(1);
+            }
+        },
+        #[cfg(not(feature = "redis"))]
+        Events::Redis { .. } => {
+            tracing::warn!("Redis event bus requires the redis feature, keeping events in memory");
+            Arc::new(InMemoryEventBus::new())
+        }
+        #[cfg(feature = "nats")]
+        Events::Nats { url, subject } => Arc::new(NatsEventBus::connect(url, subject, tasks)),
+        #[cfg(not(feature = "nats"))]
+        Events::Nats { .. } => {
+            tracing::warn!("NATS event bus requires the nats feature, keeping events in memory");
+            Arc::new(InMemoryEventBus::new())
+        }
+    }
+}
+
+/// Publisher of the executions when Kafka is configured, publishing on `tasks`.
+#[cfg_attr(not(feature = "kafka"), allow(unused_variables))]
+fn build_executions(config: &AppConfig, tasks: &TaskSupervisor) -> Option<ExecutionPublisher> {
+    match &config.kafka {
+        #[cfg(feature = "kafka")]
+        Some(kafka) => Some(ExecutionPublisher::spawn(kafka, tasks)),
+        #[cfg(not(feature = "kafka"))]
+        Some(_) => {
+            tracing::warn!("Kafka publisher requires the kafka feature, executions not published");
+            None
+        }
+        None => None,
+    }
+}
+
+/// Cache of the broker lookups, entries are stored under `prefix`.
+fn build_cache(config: &AppConfig, prefix: &str) -> Result<BrokerCache, Box<dyn Error>> {
+    let ttl = Duration::from_secs(config.cache.ttl);
+    match &config.cache.redis_url {
+        #[cfg(feature = "redis")]
+        Some(url) => Ok(BrokerCache::redis(prefix, ttl, url)?),
+        #[cfg(not(feature = "redis"))]
+        Some(_) => {
+            tracing::warn!("Redis cache requires the redis feature, caching in memory");
+            Ok(BrokerCache::in_memory(prefix, ttl))
+        }
+        None => Ok(BrokerCache::in_memory(prefix, ttl)),
+    }
 }
 
 pub fn build_routes(app_state: Arc<App>) -> Router {
-    Router::new()
+    let webhooks = Router::new()
         .route("/webhook", post(handlers::receive_webhook_alert))
+        .route(
+            "/webhook/alert/:version",
+            post(handlers::receive_versioned_alert),
+        )
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::enforce_quotas,
+        ))
+        // Before quotas, so tampered alerts don't count towards them
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::verify_signature,
+        ))
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::allow_webhook_sources,
+        ));
+
+    // NOTE: simulated alerts place no orders, so they don't count towards the quotas
+    let simulations = Router::new()
+        .route("/webhook/alert/test", post(handlers::simulate_alert))
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::verify_signature,
+        ))
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::allow_webhook_sources,
+        ));
+
+    let sources = Router::new()
+        .route(
+            "/webhook/source/:name",
+            post(handlers::receive_source_alert),
+        )
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            middleware::allow_webhook_sources,
+        ));
+
+    let router = Router::new()
+        .merge(webhooks)
+        .merge(simulations)
+        // NOTE: sources authenticate and count their alerts themselves, see `signal_source`
+        .merge(sources)
         .route("/account", get(handlers::get_account))
+        .route("/account/usage", get(handlers::get_account_usage))
+        .route("/account/activities", get(handlers::get_account_activities))
+        .route("/account/cash-flow", get(handlers::get_account_cash_flow))
         .route("/activities", post(handlers::get_activities))
+        .route("/clock", get(handlers::get_clock))
         // .route("/asset/:symbol", get(handlers::get_asset)) // NOTE: Algorithmically get assets
         // .route("/assets", get(handlers::get_assets)) // NOTE: Algorithmically get assets
         // .route("/order", post(handlers::create_order)) // NOTE: Alogrithmically create orders
-        .route("/orders", post(handlers::get_orders))
+        .route(
+            "/orders",
+            get(handlers::get_order_history).post(handlers::get_orders),
+        )
+        .route("/orders/preview", post(handlers::preview_order))
         .route(
             "/order/:id",
             get(handlers::get_order)
@@ -89,12 +739,77 @@ pub fn build_routes(app_state: Arc<App>) -> Router {
         //     get(handlers::get_position).delete(handlers::delete_position),
         // ) // NOTE: Get specific position algorithmically
         .route("/positions", get(handlers::get_positions))
+        .route("/positions/history", get(handlers::get_position_history))
+        .route("/strategies/:id/pnl", get(handlers::get_strategy_pnl))
+        .route("/strategies/:id/exposure", get(handlers::get_strategy_exposure))
+        .route(
+            "/strategies/:id/divergence",
+            get(handlers::get_strategy_divergence),
+        )
+        .route("/strategies/:id/stats", get(handlers::get_strategy_stats))
+        .route("/backtests", post(handlers::run_backtest))
+        .route("/marketdata/quote/:symbol", get(handlers::get_quote))
+        .route("/marketdata/bars/:symbol", get(handlers::get_bars))
+        .route("/portfolio/equity", get(handlers::get_equity_curve))
+        .route("/dashboard/summary", get(handlers::get_dashboard_summary))
+        .route("/rebalance", post(handlers::rebalance))
+        .route("/reconcile", post(handlers::reconcile))
+        .route("/strategies", get(handlers::get_strategies))
+        .route("/strategies/:id/enabled", put(handlers::switch_strategy))
+        .route("/strategies/:id/enable", post(handlers::enable_strategy))
+        .route("/strategies/:id/disable", post(handlers::disable_strategy))
+        .route("/reports/daily/:date", get(handlers::get_daily_report))
+        .route("/alerts", get(handlers::get_alerts))
+        .route("/dead-letters", get(handlers::get_dead_letters))
+        .route("/dead-letters/:id/replay", post(handlers::replay_dead_letter))
+        .route("/usage", get(handlers::get_usage))
+        .route("/metrics/retries", get(handlers::get_retry_metrics))
+        .route("/metrics/queue", get(handlers::get_queue_metrics))
+        .route("/feature-flags", get(handlers::get_feature_flags))
+        .route("/feature-flags/:name", put(handlers::update_feature_flag))
+        .route(
+            "/api-keys",
+            get(handlers::get_api_keys).post(handlers::create_api_key),
+        )
+        .route("/api-keys/:id", delete(handlers::revoke_api_key))
+        .route("/broker-cache", delete(handlers::invalidate_broker_cache))
         .route("/health", get(handlers::check_health))
+        .route("/health/deep", get(handlers::check_deep_health))
+        .route("/health/live", get(handlers::check_liveness))
+        .route("/health/ready", get(handlers::check_readiness))
+        .route("/public/status", get(handlers::get_public_status))
+        .route("/users", get(handlers::get_users).post(handlers::create_user))
+        .route("/ws", get(handlers::stream_events))
+        .route("/control", get(handlers::control_channel));
+    #[cfg(feature = "graphql")]
+    let router = router.route(
+        "/graphql",
+        post(graphql::graphql).layer(axum::Extension(graphql::schema())),
+    );
+    #[cfg(feature = "openapi")]
+    let router = router.merge(openapi::swagger_ui());
+    #[cfg(feature = "dashboard")]
+    let router = router.route("/ui", get(dashboard::ui));
+
+    router
         .layer(
             ServiceBuilder::new()
-                .layer(from_fn_with_state(app_state.clone(), middleware::auth))
                 .layer(from_fn(middleware::log_request))
                 .layer(from_fn(middleware::log_response)),
         )
+        // NOTE: added after the logging layers, which would buffer the whole export
+        .route("/export/trades", get(handlers::export_trades))
+        .layer(
+            ServiceBuilder::new()
+                .layer(from_fn_with_state(
+                    app_state.clone(),
+                    middleware::request_id,
+                ))
+                .layer(from_fn_with_state(app_state.clone(), middleware::auth))
+                .layer(from_fn_with_state(
+                    app_state.clone(),
+                    middleware::rate_limit,
+                )),
+        )
         .with_state(Arc::clone(&app_state))
 }
@@ -0,0 +1,427 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    os::unix::net::UnixDatagram,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use axum::http::{HeaderMap, HeaderValue};
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use tracing::{Level, Metadata};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use crate::recorder::{sanitize, REDACTED, SENSITIVE_FIELDS};
+
+/// Headers redacted from logged requests and responses next to the ones matching
+/// `SENSITIVE_FIELDS`.
+const SENSITIVE_HEADERS: [&str; 4] = ["authorization", "cookie", "signature", "credential"];
+
+/// Socket of the local syslog daemon, used when no address is configured.
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+#[derive(Debug, ThisError)]
+pub enum LoggingError {
+    #[error("Invalid log filter: {0}")]
+    Filter(String),
+    #[error("Failed to open log file in {0}: {1}")]
+    File(PathBuf, String),
+    #[error("Failed to connect to syslog: {0}")]
+    Syslog(#[from] io::Error),
+    #[error("Failed to install the logger: {0}")]
+    Install(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One line per event
+    #[default]
+    Full,
+    /// Multi-line events with their fields and source location, for development
+    Pretty,
+    /// One JSON object per event, for log shippers
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFacility {
+    #[default]
+    User,
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    fn code(self) -> u8 {
+        match self {
+            Self::User => 1,
+            Self::Daemon => 3,
+            Self::Local0 => 16,
+            Self::Local1 => 17,
+            Self::Local2 => 18,
+            Self::Local3 => 19,
+            Self::Local4 => 20,
+            Self::Local5 => 21,
+            Self::Local6 => 22,
+            Self::Local7 => 23,
+        }
+    }
+}
+
+/// Destination of the logs. Sinks without a format of their own use the one of `LoggingConfig`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LogSink {
+    Stdout {
+        #[serde(default)]
+        format: Option<LogFormat>,
+    },
+    /// Files named `{prefix}.{date}.log` in `dir`, rotated every `rotation`. Only the latest
+    /// `max_files` are kept when set.
+    File {
+        dir: PathBuf,
+        #[serde(default = "default_prefix")]
+        prefix: String,
+        #[serde(default)]
+        rotation: LogRotation,
+        #[serde(default)]
+        max_files: Option<usize>,
+        #[serde(default)]
+        format: Option<LogFormat>,
+    },
+    /// RFC 3164 messages sent over UDP to `addr`, to the local daemon when it's left out
+    Syslog {
+        #[serde(default)]
+        addr: Option<String>,
+        #[serde(default)]
+        facility: SyslogFacility,
+        #[serde(default = "default_ident")]
+        ident: String,
+    },
+}
+
+/// Format, levels and destinations of the logs. `RUST_LOG` takes precedence over the levels when
+/// it's set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Level of the modules without one of their own
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Levels keyed by module path, e.g. `sqlx = "warn"`. Requests and responses are logged with
+    /// `http = "debug"`.
+    #[serde(default)]
+    pub modules: BTreeMap<String, String>,
+    #[serde(default = "default_sinks")]
+    pub sinks: Vec<LogSink>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_level(),
+            modules: BTreeMap::new(),
+            sinks: default_sinks(),
+        }
+    }
+}
+
+fn default_level() -> String {
+    "info".to_owned()
+}
+
+fn default_sinks() -> Vec<LogSink> {
+    vec![LogSink::Stdout { format: None }]
+}
+
+fn default_prefix() -> String {
+    "market".to_owned()
+}
+
+fn default_ident() -> String {
+    "market".to_owned()
+}
+
+impl LoggingConfig {
+    /// Filter directives of the configured levels, the default level first.
+    pub fn directives(&self) -> String {
+        let mut directives = vec![self.level.clone()];
+        directives.extend(
+            self.modules
+                .iter()
+                .map(|(module, level)| format!("{module}={level}")),
+        );
+        directives.join(",")
+    }
+
+    /// Configured levels which aren't levels, keyed by their field.
+    pub fn invalid_levels(&self) -> Vec<(String, String)> {
+        let levels = std::iter::once(("logging.level".to_owned(), &self.level)).chain(
+            self.modules
+                .iter()
+                .map(|(module, level)| (format!("logging.modules.{module}"), level)),
+        );
+        levels
+            .filter_map(|(field, level)| {
+                let err = LevelFilter::from_str(level).err()?;
+                Some((field, format!("{level} is not a level, {err}")))
+            })
+            .collect()
+    }
+
+    fn filter(&self) -> Result<EnvFilter, LoggingError> {
+        if std::env::var_os(EnvFilter::DEFAULT_ENV).is_some() {
+            return EnvFilter::try_from_default_env()
+                .map_err(|err| LoggingError::Filter(err.to_string()));
+        }
+        EnvFilter::try_new(self.directives()).map_err(|err| LoggingError::Filter(err.to_string()))
+    }
+}
+
+/// Keeps the writers of the file sinks flushing in the background, logs written after it's
+/// dropped are lost.
+#[must_use]
+pub struct LogGuard {
+    _guards: Vec<WorkerGuard>,
+}
+
+/// Install the global logger writing to every configured sink.
+pub fn init(config: &LoggingConfig) -> Result<LogGuard, LoggingError> {
+    let mut guards = Vec::new();
+    let mut layers = Vec::new();
+    for sink in &config.sinks {
+        match sink {
+            LogSink::Stdout { format } => {
+                layers.push(layer(format.unwrap_or(config.format), io::stdout, true));
+            }
+            LogSink::File {
+                dir,
+                prefix,
+                rotation,
+                max_files,
+                format,
+            } => {
+                let mut builder = RollingFileAppender::builder()
+                    .rotation((*rotation).into())
+                    .filename_prefix(prefix)
+                    .filename_suffix("log");
+                if let Some(max_files) = max_files {
+                    builder = builder.max_log_files(*max_files);
+                }
+                let appender = builder
+                    .build(dir)
+                    .map_err(|err| LoggingError::File(dir.clone(), err.to_string()))?;
+                let (writer, guard) = tracing_appender::non_blocking(appender);
+                guards.push(guard);
+                layers.push(layer(format.unwrap_or(config.format), writer, false));
+            }
+            LogSink::Syslog {
+                addr,
+                facility,
+                ident,
+            } => {
+                let syslog = Syslog::connect(addr.as_deref(), *facility, ident.clone())?;
+                layers.push(
+                    fmt::layer()
+                        .without_time()
+                        .with_ansi(false)
+                        .with_writer(syslog)
+                        .boxed(),
+                );
+            }
+        }
+    }
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(config.filter()?)
+        .try_init()
+        .map_err(|err| LoggingError::Install(err.to_string()))?;
+    Ok(LogGuard { _guards: guards })
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Full => fmt::layer().with_ansi(ansi).with_writer(writer).boxed(),
+        LogFormat::Pretty => fmt::layer()
+            .pretty()
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Json => fmt::layer().json().with_writer(writer).boxed(),
+    }
+}
+
+enum SyslogSocket {
+    Udp(UdpSocket),
+    Unix(UnixDatagram),
+}
+
+/// Writer sending every event as one syslog message, with the severity of its level.
+struct Syslog {
+    socket: SyslogSocket,
+    facility: SyslogFacility,
+    ident: String,
+}
+
+impl Syslog {
+    fn connect(
+        addr: Option<&str>,
+        facility: SyslogFacility,
+        ident: String,
+    ) -> Result<Self, LoggingError> {
+        let socket = match addr {
+            Some(addr) => {
+                let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{addr} doesn't resolve"))
+                })?;
+                let local: SocketAddr = match addr {
+                    SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                    SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                SyslogSocket::Udp(socket)
+            }
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(SYSLOG_SOCKET)?;
+                SyslogSocket::Unix(socket)
+            }
+        };
+        Ok(Self {
+            socket,
+            facility,
+            ident,
+        })
+    }
+}
+
+impl<'writer> MakeWriter<'writer> for Syslog {
+    type Writer = SyslogMessage<'writer>;
+
+    fn make_writer(&'writer self) -> Self::Writer {
+        SyslogMessage {
+            syslog: self,
+            severity: 6,
+        }
+    }
+
+    fn make_writer_for(&'writer self, meta: &Metadata<'_>) -> Self::Writer {
+        let severity = match *meta.level() {
+            Level::ERROR => 3,
+            Level::WARN => 4,
+            Level::INFO => 6,
+            Level::DEBUG | Level::TRACE => 7,
+        };
+        SyslogMessage {
+            syslog: self,
+            severity,
+        }
+    }
+}
+
+struct SyslogMessage<'writer> {
+    syslog: &'writer Syslog,
+    severity: u8,
+}
+
+impl Write for SyslogMessage<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message = format!(
+            "<{}>{}[{}]: {}",
+            self.syslog.facility.code() * 8 + self.severity,
+            self.syslog.ident,
+            std::process::id(),
+            String::from_utf8_lossy(buf).trim_end()
+        );
+        match &self.syslog.socket {
+            SyslogSocket::Udp(socket) => socket.send(message.as_bytes())?,
+            SyslogSocket::Unix(socket) => socket.send(message.as_bytes())?,
+        };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Copy of `headers` with the values of credentials replaced, see `SENSITIVE_HEADERS`.
+pub fn redact_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for (name, value) in headers.iter_mut() {
+        let name = name.as_str();
+        if SENSITIVE_FIELDS
+            .iter()
+            .chain(SENSITIVE_HEADERS.iter())
+            .any(|field| name.contains(field))
+        {
+            *value = HeaderValue::from_static(REDACTED);
+        }
+    }
+    headers
+}
+
+/// Body as it's logged, JSON bodies are pretty printed with their sensitive fields redacted.
+/// Other bodies can't be redacted field by field, so only their length is logged.
+pub fn redact_body(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::new();
+    }
+    match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(mut json) => {
+            sanitize(&mut json);
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        Err(_) => format!("{REDACTED} ({} bytes)", bytes.len()),
+    }
+}
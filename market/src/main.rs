@@ -2,30 +2,201 @@ use std::{
     error::Error,
     net::{Ipv4Addr, SocketAddr},
     sync::Arc,
+    time::Duration,
 };
 
-use market::{app_config::AppConfig, build_clients, build_routes, build_app, App};
+use market::{
+    allowlist,
+    app_config::{AppConfig, TradingEnvironment},
+    build_app, build_clients, build_routes, corporate_actions, health, logging, market_data,
+    portfolio, reports, risk, schedules, secrets, stops, trade_updates, App,
+};
+use tracing::Instrument;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenvy::dotenv().ok();
-    tracing_subscriber::fmt::init();
 
     // Build apps config
-    let config = AppConfig::build()?;
+    let mut config = AppConfig::build()?;
+
+    // Logs written before are lost, the file sinks stop writing once the guard is dropped
+    let _log_guard = logging::init(&config.logging)?;
+
+    // Replace configured credentials with the ones of the secrets manager
+    if let Some(secrets) = &config.secrets {
+        let values = secrets::fetch(secrets).await?;
+        values.apply(&mut config)?;
+        tracing::info!("Secrets loaded");
+    }
+
+    // Report every configuration mistake at once instead of failing at the first trade
+    if let Err(err) = config.check().await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+
+    // Background tasks log under a span naming the trading environment
+    let span = tracing::info_span!("market", environment = config.environment.as_ref());
+    if config.environment == TradingEnvironment::Live {
+        tracing::warn!("Trading environment is live, orders use real money");
+    }
 
     // Initialize clients
     let clients = build_clients(&config)?;
 
     // Build app state
     let app: Arc<App> = build_app(config, clients).await?.into();
+    if let Err(err) = app.clients.initialize_accounts() {
+        tracing::error!("Failed to initialize broker clients, error: {:?}", err);
+    }
+
+    // Background tasks are restarted when they crash, see `TaskSupervisor`
+    let tasks = &app.tasks;
+
+    // Start core background tasks
+    let (core, task_span) = (Arc::clone(&app.core), span.clone());
+    tasks.spawn("core", move || {
+        let core = Arc::clone(&core);
+        async move { core.run().await }.instrument(task_span.clone())
+    });
+
+    // Start portfolio snapshots scheduler
+    let (task_app, task_span) = (Arc::clone(&app), span.clone());
+    tasks.spawn("portfolio_snapshots", move || {
+        portfolio::run_snapshots(
+            task_app.db.clone(),
+            Arc::clone(&task_app.clients),
+            Arc::clone(&task_app.risk_monitor),
+            Duration::from_secs(task_app.config.snapshots.interval),
+        )
+        .instrument(task_span.clone())
+    });
+
+    // Disable strategies reaching their daily loss limit or the one of their portfolio
+    if app
+        .config
+        .strategies
+        .iter()
+        .any(|strategy| app.risk_monitor.has_daily_loss_limit(strategy))
+    {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("daily_loss_checks", move || {
+            risk::run_daily_loss_checks(
+                Arc::clone(&task_app),
+                Duration::from_secs(task_app.config.snapshots.interval),
+            )
+            .instrument(task_span.clone())
+        });
+    }
+
+    // Move protective stops of open positions as prices move in their favor
+    if app
+        .config
+        .strategies
+        .iter()
+        .any(|strategy| strategy.stop_management.is_some())
+    {
+        let (core, task_span) = (Arc::clone(&app.core), span.clone());
+        tasks.spawn("stop_manager", move || {
+            stops::run_stop_manager(Arc::clone(&core)).instrument(task_span.clone())
+        });
+    }
+
+    // Adjust stored positions to splits and symbol changes
+    if let Some(config) = app.config.corporate_actions.clone() {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("corporate_actions", move || {
+            corporate_actions::run_corporate_actions(Arc::clone(&task_app), config.clone())
+                .instrument(task_span.clone())
+        });
+    }
+
+    // Run the scheduled actions of the strategies
+    if app
+        .config
+        .strategies
+        .iter()
+        .any(|strategy| !strategy.schedules.is_empty())
+    {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("schedules", move || {
+            schedules::run_schedules(Arc::clone(&task_app)).instrument(task_span.clone())
+        });
+    }
+
+    // Compile the daily summary report at the end of every day
+    if let Some(config) = app.config.reports.clone() {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("daily_reports", move || {
+            reports::run_daily_reports(Arc::clone(&task_app), config.clone())
+                .instrument(task_span.clone())
+        });
+    }
+
+    // Pick up rotated credentials
+    if let Some(secrets) = app.config.secrets.clone() {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("secrets_rotation", move || {
+            secrets::run_rotation(
+                secrets.clone(),
+                task_app.config.clone(),
+                Arc::clone(&task_app.clients),
+                task_app.db.clone(),
+            )
+            .instrument(task_span.clone())
+        });
+    }
+
+    // Reload webhook allowlist on SIGHUP
+    let (allowlist, task_span) = (Arc::clone(&app.webhook_allowlist), span.clone());
+    tasks.spawn("allowlist_reload", move || {
+        allowlist::reload_on_hangup(Arc::clone(&allowlist)).instrument(task_span.clone())
+    });
+
+    // Stream live quotes of the strategy symbols
+    if let Some(config) = &app.config.market_data {
+        let (task_app, feed, task_span) = (Arc::clone(&app), config.feed, span.clone());
+        tasks.spawn("market_data", move || {
+            market_data::run_quotes(
+                Arc::clone(&task_app.clients),
+                feed,
+                market_data::strategy_symbols(&task_app.config.strategies),
+                Arc::clone(task_app.core.quotes()),
+            )
+            .instrument(task_span.clone())
+        });
+    }
+
+    // Stream order updates to record executions as they happen
+    if app.config.trade_updates {
+        let (task_app, task_span) = (Arc::clone(&app), span.clone());
+        tasks.spawn("trade_updates", move || {
+            trade_updates::run_trade_updates(
+                Arc::clone(&task_app.core),
+                Arc::clone(&task_app.clients),
+            )
+            .instrument(task_span.clone())
+        });
+    }
+
+    // Start gRPC server next to the REST API
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = &app.config.grpc {
+        let (task_app, addr, task_span) = (Arc::clone(&app), grpc.addr, span.clone());
+        tasks.spawn("grpc", move || {
+            market::grpc::serve(Arc::clone(&task_app), addr).instrument(task_span.clone())
+        });
+    }
 
-    // Start server
+    // Start server, requests in flight are finished when asked to stop
+    let shutdown = health::drain_on_terminate(Arc::clone(&app));
     let routes = build_routes(app);
     let addr = SocketAddr::from((Ipv4Addr::new(0, 0, 0, 0), 8000));
     tracing::info!("Listening on {}", addr);
     axum::Server::bind(&addr)
-        .serve(routes.into_make_service())
+        .serve(routes.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown)
         .await?;
 
     Ok(())
@@ -0,0 +1,457 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error as ThisError;
+use uuid::Uuid;
+
+use crate::api::{
+    alert::{BarData, SignalType, TrailStopPrice, WebhookAlertData},
+    payload::SignalAction,
+    price::Price,
+};
+
+#[derive(Debug, ThisError, PartialEq, Eq)]
+pub enum MappingError {
+    #[error("Invalid mapping expression {0} - {1}")]
+    InvalidExpression(String, String),
+    #[error("Alert has no {0} field")]
+    MissingField(String),
+    #[error("{0} is not a number")]
+    NotANumber(String),
+    #[error("{0} is not a time")]
+    NotATime(String),
+    #[error("Division by zero in {0}")]
+    DivisionByZero(String),
+    #[error("Action {0} is not mapped to a signal")]
+    UnmappedAction(String),
+}
+
+// NOTE: Mapping of a Pine script alert example:
+// [strategies.alert_mapping]
+// ticker = "ticker"
+// close = "close"
+// stop_loss = "{{plot_1}}"
+// quantity = "{{plot_0}} * 10"
+// [strategies.alert_mapping.action]
+// field = "{{strategy.order.action}}"
+// values = { buy = "open_long", sell = "open_short" }
+
+/// Translates alerts of any shape into order parameters, every parameter is an `Expression` over
+/// the fields of the alert. Alerts of the `mapped` version are read with the mapping of their
+/// strategy. Only `ticker`, `action`, `stop_loss` and `close` are required, the bar prices
+/// default to `close` and the times to the time the alert is received.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertMapping {
+    #[serde(default)]
+    pub alert_id: Option<Expression>,
+    pub ticker: Expression,
+    #[serde(default)]
+    pub exchange: Option<Expression>,
+    #[serde(default)]
+    pub timeframe: Option<Expression>,
+    pub action: ActionMapping,
+    pub stop_loss: Expression,
+    #[serde(default)]
+    pub time: Option<Expression>,
+    #[serde(default)]
+    pub bar_time: Option<Expression>,
+    #[serde(default)]
+    pub open: Option<Expression>,
+    #[serde(default)]
+    pub high: Option<Expression>,
+    #[serde(default)]
+    pub low: Option<Expression>,
+    pub close: Expression,
+    #[serde(default)]
+    pub volume: Option<Expression>,
+    #[serde(default)]
+    pub quantity: Option<Expression>,
+    #[serde(default)]
+    pub notional: Option<Expression>,
+    #[serde(default)]
+    pub limit_price: Option<Expression>,
+}
+
+/// Signal of each value of an alert field, e.g. `buy` and `sell` of TradingView order actions.
+/// Values are matched ignoring case.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActionMapping {
+    pub field: Expression,
+    pub values: HashMap<String, SignalAction>,
+}
+
+impl AlertMapping {
+    pub fn map(
+        &self,
+        strategy_id: Uuid,
+        alert: &Value,
+        now: DateTime<Utc>,
+    ) -> Result<WebhookAlertData, MappingError> {
+        let text = |expression: &Option<Expression>| {
+            expression
+                .as_ref()
+                .map(|expression| expression.text(alert))
+                .transpose()
+        };
+        let decimal = |expression: &Option<Expression>| {
+            expression
+                .as_ref()
+                .map(|expression| expression.decimal(alert))
+                .transpose()
+        };
+        let timestamp = |expression: &Option<Expression>| {
+            expression
+                .as_ref()
+                .map(|expression| expression.time(alert))
+                .transpose()
+        };
+
+        let action = self.action.field.text(alert)?;
+        let stop_loss = TrailStopPrice(self.stop_loss.decimal(alert)?);
+        let signal_type = match self
+            .action
+            .values
+            .iter()
+            .find(|(value, _)| value.eq_ignore_ascii_case(&action))
+            .map(|(_, action)| action)
+        {
+            Some(SignalAction::OpenLong) => SignalType::OpenLong(stop_loss),
+            Some(SignalAction::OpenShort) => SignalType::OpenShort(stop_loss),
+            Some(SignalAction::StopLossUpdate) => SignalType::StopLossUpdate(stop_loss),
+            None => return Err(MappingError::UnmappedAction(action)),
+        };
+
+        let close = self.close.decimal(alert)?;
+        let time = timestamp(&self.time)?.unwrap_or(now);
+        Ok(WebhookAlertData {
+            alert_id: text(&self.alert_id)?,
+            strategy_id,
+            ticker: self.ticker.text(alert)?,
+            timeframe: text(&self.timeframe)?.unwrap_or_default(),
+            exchange: text(&self.exchange)?.unwrap_or_default(),
+            signal_type,
+            trail_stop_price: None,
+            bar_data: BarData {
+                time: timestamp(&self.bar_time)?.unwrap_or(time),
+                open: Price::new(decimal(&self.open)?.unwrap_or(close)),
+                high: Price::new(decimal(&self.high)?.unwrap_or(close)),
+                low: Price::new(decimal(&self.low)?.unwrap_or(close)),
+                close: Price::new(close),
+                volume: decimal(&self.volume)?.unwrap_or_default(),
+            },
+            time,
+            quantity: decimal(&self.quantity)?,
+            notional: decimal(&self.notional)?,
+            time_in_force: None,
+            limit_price: decimal(&self.limit_price)?,
+            extended_hours: None,
+            legs: Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(Decimal),
+    Text(String),
+    /// Dotted path of an alert field, array elements are addressed by their index
+    Field(String),
+    Neg(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+}
+
+/// Value of an order parameter computed from alert fields. Fields are referenced by their dotted
+/// path, optionally in TradingView placeholder braces, e.g. `{{plot_0}}` or `order.size`. Numbers,
+/// `'quoted'` text, `+ - * /` and parentheses combine them, e.g. `{{plot_0}} * 2`. Numeric alert
+/// fields may be sent as strings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    fn evaluate(&self, alert: &Value) -> Result<Value, MappingError> {
+        evaluate(&self.root, alert, &self.source)
+    }
+
+    pub fn text(&self, alert: &Value) -> Result<String, MappingError> {
+        Ok(match self.evaluate(alert)? {
+            Value::String(text) => text,
+            value => value.to_string(),
+        })
+    }
+
+    pub fn decimal(&self, alert: &Value) -> Result<Decimal, MappingError> {
+        to_decimal(&self.evaluate(alert)?)
+            .ok_or_else(|| MappingError::NotANumber(self.source.clone()))
+    }
+
+    /// RFC 3339 text or milliseconds since the epoch, as TradingView sends `{{time}}` and Pine
+    /// scripts compute times.
+    pub fn time(&self, alert: &Value) -> Result<DateTime<Utc>, MappingError> {
+        let value = self.evaluate(alert)?;
+        if let Some(time) = value
+            .as_str()
+            .and_then(|text| DateTime::parse_from_rfc3339(text.trim()).ok())
+        {
+            return Ok(time.with_timezone(&Utc));
+        }
+
+        to_decimal(&value)
+            .filter(|millis| millis.fract().is_zero())
+            .and_then(|millis| millis.to_i64())
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+            .ok_or_else(|| MappingError::NotATime(self.source.clone()))
+    }
+}
+
+fn to_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Number(number) => Decimal::from_str(&number.to_string())
+            .or_else(|_| Decimal::from_scientific(&number.to_string()))
+            .ok(),
+        Value::String(text) => Decimal::from_str(text.trim()).ok(),
+        _ => None,
+    }
+}
+
+fn number(decimal: Decimal) -> Value {
+    // Decimals are kept as strings, floats would lose their precision
+    Value::String(decimal.normalize().to_string())
+}
+
+fn evaluate(node: &Node, alert: &Value, source: &str) -> Result<Value, MappingError> {
+    let operand = |node: &Node| {
+        let value = evaluate(node, alert, source)?;
+        to_decimal(&value).ok_or_else(|| MappingError::NotANumber(source.to_owned()))
+    };
+
+    Ok(match node {
+        Node::Number(decimal) => number(*decimal),
+        Node::Text(text) => Value::String(text.clone()),
+        Node::Field(path) => field(alert, path)
+            .cloned()
+            .ok_or_else(|| MappingError::MissingField(path.clone()))?,
+        Node::Neg(node) => number(-operand(node)?),
+        Node::Binary(operator, left, right) => {
+            let (left, right) = (operand(left)?, operand(right)?);
+            number(match operator {
+                Operator::Add => left + right,
+                Operator::Sub => left - right,
+                Operator::Mul => left * right,
+                Operator::Div => left
+                    .checked_div(right)
+                    .ok_or_else(|| MappingError::DivisionByZero(source.to_owned()))?,
+            })
+        }
+    })
+}
+
+/// Field of the alert at the dotted `path`. Keys containing dots, like the ones named after
+/// TradingView placeholders, are found as they are.
+fn field<'a>(alert: &'a Value, path: &str) -> Option<&'a Value> {
+    if let Some(value) = alert.get(path) {
+        return Some(value);
+    }
+    path.split('.')
+        .try_fold(alert, |value, segment| match value {
+            Value::Array(values) => values.get(segment.parse::<usize>().ok()?),
+            value => value.get(segment),
+        })
+}
+
+impl FromStr for Expression {
+    type Err = MappingError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let root = parser.expression()?;
+        if parser.position < parser.tokens.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+
+        Ok(Self {
+            source: source.to_owned(),
+            root,
+        })
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = MappingError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl From<Expression> for String {
+    fn from(expression: Expression) -> Self {
+        expression.source
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(Decimal),
+    Text(String),
+    Field(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, MappingError> {
+    let invalid =
+        |message: &str| MappingError::InvalidExpression(source.to_owned(), message.to_owned());
+    let is_path = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Operator(match c {
+                    '+' => Operator::Add,
+                    '-' => Operator::Sub,
+                    '*' => Operator::Mul,
+                    _ => Operator::Div,
+                }));
+                1
+            }
+            '(' | ')' => {
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+                1
+            }
+            '\'' => {
+                let end = rest[1..]
+                    .find('\'')
+                    .ok_or_else(|| invalid("unterminated text"))?;
+                tokens.push(Token::Text(rest[1..=end].to_owned()));
+                end + 2
+            }
+            '{' => {
+                let end = rest
+                    .find("}}")
+                    .filter(|_| rest.starts_with("{{"))
+                    .ok_or_else(|| invalid("unterminated placeholder"))?;
+                let path = rest[2..end].trim();
+                if path.is_empty() || !path.chars().all(is_path) {
+                    return Err(invalid("invalid placeholder"));
+                }
+                tokens.push(Token::Field(path.to_owned()));
+                end + 2
+            }
+            c if c.is_ascii_digit() => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit() && c != '.')
+                    .unwrap_or(rest.len());
+                let number =
+                    Decimal::from_str(&rest[..len]).map_err(|_| invalid("invalid number"))?;
+                tokens.push(Token::Number(number));
+                len
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let len = rest.find(|c: char| !is_path(c)).unwrap_or(rest.len());
+                tokens.push(Token::Field(rest[..len].to_owned()));
+                len
+            }
+            _ => return Err(invalid("unexpected character")),
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    if tokens.is_empty() {
+        return Err(invalid("empty expression"));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser of expressions, `*` and `/` bind tighter than `+` and `-`.
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> MappingError {
+        MappingError::InvalidExpression(self.source.to_owned(), message.to_owned())
+    }
+
+    fn next_operator(&mut self, operators: [Operator; 2]) -> Option<Operator> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) if operators.contains(operator) => {
+                self.position += 1;
+                Some(*operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, MappingError> {
+        let mut node = self.term()?;
+        while let Some(operator) = self.next_operator([Operator::Add, Operator::Sub]) {
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, MappingError> {
+        let mut node = self.factor()?;
+        while let Some(operator) = self.next_operator([Operator::Mul, Operator::Div]) {
+            node = Node::Binary(operator, Box::new(node), Box::new(self.factor()?));
+        }
+        Ok(node)
+    }
+
+    fn factor(&mut self) -> Result<Node, MappingError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end"))?;
+        self.position += 1;
+
+        match token {
+            Token::Number(number) => Ok(Node::Number(number)),
+            Token::Text(text) => Ok(Node::Text(text)),
+            Token::Field(path) => Ok(Node::Field(path)),
+            Token::Operator(Operator::Sub) => Ok(Node::Neg(Box::new(self.factor()?))),
+            Token::Open => {
+                let node = self.expression()?;
+                match self.tokens.get(self.position) {
+                    Some(Token::Close) => {
+                        self.position += 1;
+                        Ok(node)
+                    }
+                    _ => Err(self.error("unclosed parenthesis")),
+                }
+            }
+            Token::Operator(_) | Token::Close => Err(self.error("unexpected operator")),
+        }
+    }
+}
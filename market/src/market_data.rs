@@ -0,0 +1,240 @@
+use std::{collections::HashMap, sync::Arc};
+
+use apca::{
+    data::v2::{
+        last_quotes::{self, LastQuotesReqInit},
+        stream::{drive, Data, MarketData, Quote, RealtimeData, Source, IEX, SIP},
+    },
+    Client as AlpacaClient,
+};
+use chrono::{DateTime, Utc};
+use futures::{FutureExt, StreamExt};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    api::objects::Broker,
+    backtest::Timeframe,
+    clients::{num_to_decimal, BrokerClientError, Clients},
+    strategy::{CurrencyType, Strategy},
+    streams::{self, Connection, Gap, SupervisedStream},
+};
+
+/// Quotes older than this are considered stale and not used for pricing.
+const MAX_QUOTE_AGE: chrono::Duration = chrono::Duration::seconds(10);
+
+/// Source of the streamed market data, SIP requires the unlimited data plan.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Feed {
+    #[default]
+    Iex,
+    Sip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LiveQuote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub time: DateTime<Utc>,
+}
+
+impl LiveQuote {
+    pub fn mid(&self) -> Decimal {
+        (self.bid + self.ask) / Decimal::TWO
+    }
+
+    pub fn spread(&self) -> Decimal {
+        self.ask - self.bid
+    }
+}
+
+/// Latest streamed quote of every subscribed symbol.
+#[derive(Default)]
+pub struct QuoteBook {
+    quotes: RwLock<HashMap<String, LiveQuote>>,
+}
+
+impl QuoteBook {
+    pub async fn update(&self, symbol: &str, quote: LiveQuote) {
+        self.quotes.write().await.insert(symbol.to_owned(), quote);
+    }
+
+    /// Latest quote of the symbol, `None` when there is none or it's stale.
+    pub async fn latest(&self, symbol: &str) -> Option<LiveQuote> {
+        self.quotes
+            .read()
+            .await
+            .get(symbol)
+            .filter(|quote| Utc::now() - quote.time <= MAX_QUOTE_AGE)
+            .copied()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BarsQuery {
+    pub timeframe: Timeframe,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Latest quote of the symbol from the data API, `None` when Alpaca has none.
+pub async fn fetch_quote(
+    client: &AlpacaClient,
+    symbol: &str,
+) -> Result<Option<LiveQuote>, BrokerClientError> {
+    Ok(fetch_quotes(client, &[symbol.to_owned()])
+        .await?
+        .remove(symbol))
+}
+
+/// Latest quote of the symbol at `broker`, from the data API of Alpaca, the pricing of the OANDA
+/// account or the market data of Tradier.
+pub async fn fetch_broker_quote(
+    clients: &Clients,
+    broker: &Broker,
+    symbol: &str,
+) -> Result<Option<LiveQuote>, BrokerClientError> {
+    match broker {
+        Broker::Alpaca => fetch_quote(&clients.alpaca(), symbol).await,
+        Broker::Oanda => clients.oanda()?.0.inner().fetch_quote(symbol).await,
+        Broker::Tradier => clients.tradier()?.0.inner().fetch_quote(symbol).await,
+    }
+}
+
+/// Latest quotes of the symbols from the data API, symbols Alpaca has none of are missing.
+pub async fn fetch_quotes(
+    client: &AlpacaClient,
+    symbols: &[String],
+) -> Result<HashMap<String, LiveQuote>, BrokerClientError> {
+    let request = LastQuotesReqInit::default().init(symbols.iter().map(String::as_str));
+    let quotes = client
+        .issue::<last_quotes::Get>(&request)
+        .await
+        .map_err(|err| BrokerClientError::AlpacaError(format!("{err:?}")))?;
+
+    Ok(quotes
+        .into_iter()
+        .map(|(symbol, quote)| {
+            let live_quote = LiveQuote {
+                bid: num_to_decimal(&quote.bid_price),
+                ask: num_to_decimal(&quote.ask_price),
+                time: quote.time,
+            };
+            (symbol, live_quote)
+        })
+        .collect())
+}
+
+/// Symbols of the enabled strategies, the ones worth streaming quotes for. Currency pairs aren't
+/// streamed, Alpaca has no quotes of them.
+pub fn strategy_symbols(strategies: &[Strategy]) -> Vec<String> {
+    let mut symbols: Vec<String> = strategies
+        .iter()
+        .filter(|strategy| strategy.enabled && strategy.currency_type != CurrencyType::Forex)
+        .flat_map(|strategy| strategy.symbols.iter().cloned())
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    symbols
+}
+
+/// Stream quotes of the symbols into the quote book until the process stops, see
+/// `streams::supervise`.
+pub async fn run_quotes(
+    clients: Arc<Clients>,
+    feed: Feed,
+    symbols: Vec<String>,
+    quotes: Arc<QuoteBook>,
+) {
+    if symbols.is_empty() {
+        tracing::info!("No strategy symbols, market data isn't streamed");
+        return;
+    }
+
+    let stream = QuoteStream {
+        clients,
+        feed,
+        symbols,
+        quotes,
+    };
+    streams::supervise("market_data", stream).await
+}
+
+/// Quotes of the symbols streamed into the quote book, resynced with their latest quotes from the
+/// data API.
+struct QuoteStream {
+    clients: Arc<Clients>,
+    feed: Feed,
+    symbols: Vec<String>,
+    quotes: Arc<QuoteBook>,
+}
+
+#[axum::async_trait]
+impl SupervisedStream for QuoteStream {
+    type Message = Quote;
+
+    async fn connect(&self) -> Result<Connection<Quote>, String> {
+        // Taken on every connect, so rotated credentials are picked up
+        let client = self.clients.alpaca();
+        match self.feed {
+            Feed::Iex => subscribe_quotes::<IEX>(&client, &self.symbols).await,
+            Feed::Sip => subscribe_quotes::<SIP>(&client, &self.symbols).await,
+        }
+    }
+
+    async fn handle(&self, quote: Quote) {
+        let live_quote = LiveQuote {
+            bid: num_to_decimal(&quote.bid_price),
+            ask: num_to_decimal(&quote.ask_price),
+            time: quote.timestamp,
+        };
+        self.quotes.update(&quote.symbol, live_quote).await;
+    }
+
+    async fn resync(&self, _gap: Gap) -> Result<(), String> {
+        let client = self.clients.alpaca();
+        let latest = fetch_quotes(&client, &self.symbols)
+            .await
+            .map_err(|err| err.to_string())?;
+        for (symbol, quote) in latest {
+            self.quotes.update(&symbol, quote).await;
+        }
+
+        Ok(())
+    }
+}
+
+async fn subscribe_quotes<S: Source>(
+    client: &AlpacaClient,
+    symbols: &[String],
+) -> Result<Connection<Quote>, String> {
+    let (mut stream, mut subscription) = client
+        .subscribe::<RealtimeData<S>>()
+        .await
+        .map_err(|err| format!("{err:?}"))?;
+
+    let mut data = MarketData::default();
+    data.set_quotes(symbols.to_vec());
+    let subscribe = subscription.subscribe(&data).boxed();
+    drive(subscribe, &mut stream)
+        .await
+        .map_err(|_| "Stream ended while subscribing".to_owned())?
+        .map_err(|err| format!("{err:?}"))?
+        .map_err(|err| format!("{err:?}"))?;
+    tracing::info!("Streaming quotes of {}", symbols.join(", "));
+
+    let quotes = stream.filter_map(|message| async move {
+        match message {
+            Ok(Ok(Data::Quote(quote))) => Some(Ok(quote)),
+            Ok(Ok(_)) => None,
+            Ok(Err(err)) => {
+                tracing::warn!("Malformed market data message, error: {}", err);
+                None
+            }
+            Err(err) => Some(Err(format!("{err:?}"))),
+        }
+    });
+    Ok(Connection::new(quotes, subscription))
+}
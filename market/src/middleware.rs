@@ -1,167 +1,473 @@
-use std::{io::Write, sync::Arc};
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
-    body::Body,
-    extract::State,
-    http::{header, Request, StatusCode},
+    body::{Body, Bytes, HttpBody},
+    extract::{ConnectInfo, State},
+    http::{header, request::Parts, HeaderMap, Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use chrono::{DateTime, FixedOffset, Utc};
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tracing::Instrument;
+use uuid::Uuid;
 
-use crate::{error::ApiError, App};
+use crate::{
+    api_keys::{self, Role},
+    error::{ApiError, ErrorBody},
+    jwt::JwtError,
+    logging,
+    nonces::{self, AlertNonce},
+    rate_limit::{self, Quota},
+    strategy::Strategy,
+    usage::{self, UsageKind},
+    users::{self, Caller},
+    App,
+};
+
+/// Header correlating a request with the orders it caused. Taken from the client when present,
+/// generated otherwise.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Id of the current request, available to handlers as an extension.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Headers of the rate limited responses, see `rate_limit::Quota`
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+pub const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Header carrying hex encoded HMAC-SHA256 of the raw webhook body, optionally prefixed with
+/// `sha256=`.
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Largest response body `log_response` buffers to log it
+const MAX_LOGGED_BODY: u64 = 64 * 1024;
+
+/// Attach the request id to the request, its tracing span and the response, error responses also
+/// carry it in their body. The span also carries the trading environment, so every log record of
+/// a live request says so.
+pub async fn request_id<B>(
+    State(app): State<Arc<App>>,
+    mut req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_owned);
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next
+        .run(req)
+        .instrument(tracing::info_span!(
+            "request",
+            request_id = %id,
+            environment = app.clients.environment.as_ref()
+        ))
+        .await;
+
+    if let Some(mut body) = response.extensions_mut().remove::<ErrorBody>() {
+        body.request_id = Some(id.clone());
+        let (parts, _) = response.into_parts();
+        response = Response::from_parts(parts, body.into_response().into_body());
+    }
+    if let Ok(value) = header::HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// Webhook routes, authenticated by the webhook secret of their strategy or by their signal
+/// source.
+fn is_webhook(path: &str) -> bool {
+    path == "/webhook" || path.starts_with("/webhook/")
+}
+
+/// Orchestrator probes, which can't present an API key.
+fn is_probe(path: &str) -> bool {
+    path == "/health/live" || path == "/health/ready"
+}
 
 pub async fn auth<B>(
     State(app): State<Arc<App>>,
-    req: Request<B>,
+    mut req: Request<B>,
     next: Next<B>,
 ) -> Result<impl IntoResponse, ApiError> {
     // NOTE: skip auth for post /alert endpoint. We don't need to check auth for tradingview
     // webhook as we use it's own secret key
-    if req.uri().path() == "/webhook" && req.method() == axum::http::Method::POST {
+    if is_webhook(req.uri().path()) && req.method() == axum::http::Method::POST {
+        return Ok(next.run(req).await);
+    }
+    if is_probe(req.uri().path()) {
+        return Ok(next.run(req).await);
+    }
+    // NOTE: public routes only expose coarse status and are rate limited per client address
+    if rate_limit::route_group(req.uri().path()) == rate_limit::PUBLIC {
         return Ok(next.run(req).await);
     }
 
-    if let Some(auth_value) = req.headers().get(header::AUTHORIZATION) {
-        if let Ok(header) = header::HeaderValue::from_str(&app.config.api_key) {
-            if auth_value == header {
-                return Ok(next.run(req).await);
+    let Some(secret) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Err(ApiError::Unauthorized(
+            "API key isn't correct or not found".to_string(),
+        ));
+    };
+
+    let Some(caller) = resolve_caller(&app, secret).await? else {
+        return Err(ApiError::Unauthorized(
+            "API key isn't correct or not found".to_string(),
+        ));
+    };
+
+    let required = api_keys::required_role(req.method(), req.uri().path());
+    if caller.role < required {
+        return Err(ApiError::Forbidden(format!(
+            "API key with {} role can't access {}, {} role is required",
+            caller.role.as_ref(),
+            req.uri().path(),
+            required.as_ref()
+        )));
+    }
+    // NOTE: broker accounts are shared by the strategies of every user
+    if !caller.is_operator() && users::is_operator_route(req.method(), req.uri().path()) {
+        return Err(ApiError::Forbidden(format!(
+            "API key of a user can't access {}",
+            req.uri().path()
+        )));
+    }
+
+    req.extensions_mut().insert(caller);
+    Ok(next.run(req).await)
+}
+
+/// Role and user of an API key or identity provider token, `None` when it isn't valid.
+pub(crate) async fn resolve_caller(app: &App, secret: &str) -> Result<Option<Caller>, ApiError> {
+    // NOTE: key from the config is the bootstrap admin key, other keys are stored in the database
+    match (secret.strip_prefix("Bearer "), &app.jwt) {
+        (Some(token), Some(jwt)) => match jwt.caller(token).await {
+            Ok(caller) => Ok(Some(caller)),
+            Err(JwtError::JwksError(err)) => {
+                tracing::error!("Failed to fetch JWKS, error: {:?}", err);
+                Err(ApiError::InternalServerError)
             }
+            Err(err) => {
+                tracing::warn!("Token rejected, error: {:?}", err);
+                Ok(None)
+            }
+        },
+        _ if secret == app.config.api_key => Ok(Some(Caller::operator(Role::Admin))),
+        _ => Ok(api_keys::find_caller(&app.db, secret).await?),
+    }
+}
+
+/// Limit requests per API key and route group. Runs after `auth`, so only requests with a valid
+/// key reach it, apart from the public routes which are limited per client address instead.
+/// Responses of limited groups carry the remaining quota in the `x-ratelimit-*` headers.
+pub async fn rate_limit<B>(
+    State(app): State<Arc<App>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ApiError> {
+    let group = rate_limit::route_group(req.uri().path());
+    let key = if group == rate_limit::PUBLIC {
+        app.webhook_allowlist
+            .client_ip(req.headers(), peer.map(|ConnectInfo(peer)| peer))
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string())
+    } else {
+        let Some(api_key) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Ok(next.run(req).await);
+        };
+        api_key.to_string()
+    };
+
+    let quota = match app.rate_limiter.check(&key, group) {
+        Ok(quota) => quota,
+        Err(throttled) => {
+            let retry_after = throttled.retry_after.as_secs_f64().ceil() as u64;
+            tracing::warn!("Rate limit of {group} routes exceeded, retry after {retry_after}s");
+
+            let mut response = ApiError::TooManyRequests(format!(
+                "Rate limit of {group} routes exceeded, retry after {retry_after}s"
+            ))
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from(retry_after));
+            insert_quota(response.headers_mut(), &throttled.quota);
+            return Ok(response);
         }
+    };
+
+    let mut response = next.run(req).await;
+    if let Some(quota) = quota {
+        insert_quota(response.headers_mut(), &quota);
     }
+    Ok(response)
+}
 
-    Err(ApiError::Unauthorized(
-        "API key isn't correct or not found".to_string(),
-    ))
+fn insert_quota(headers: &mut HeaderMap, quota: &Quota) {
+    headers.insert(
+        RATE_LIMIT_LIMIT_HEADER,
+        header::HeaderValue::from(quota.limit),
+    );
+    headers.insert(
+        RATE_LIMIT_REMAINING_HEADER,
+        header::HeaderValue::from(quota.remaining),
+    );
+    headers.insert(
+        RATE_LIMIT_RESET_HEADER,
+        header::HeaderValue::from(quota.reset),
+    );
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
-struct JsonResponse {
-    message: String,
+/// Reject webhook requests coming from addresses outside of the configured allowlist.
+pub async fn allow_webhook_sources<B>(
+    State(app): State<Arc<App>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<impl IntoResponse, ApiError> {
+    let ip = app
+        .webhook_allowlist
+        .client_ip(request.headers(), peer.map(|ConnectInfo(peer)| peer));
+
+    if !app.webhook_allowlist.is_allowed(ip) {
+        let ip = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        tracing::warn!("Webhook request from {ip} rejected, address is not allowed");
+        return Err(ApiError::Forbidden(format!("Address {ip} is not allowed")));
+    }
+
+    Ok(next.run(request).await)
 }
 
-pub async fn log_request(
-    mut request: Request<Body>,
-    next: Next<Body>,
-) -> Result<impl IntoResponse, Response> {
-    let method = request.method().clone();
+#[derive(Debug, Deserialize)]
+struct AlertStrategy {
+    strategy_id: Uuid,
+}
+
+/// Buffer the body of a webhook request and find the strategy the alert is addressed to. The
+/// body is read like the alert handlers read it, as a JSON value keeping the last of duplicate
+/// keys, so the strategy checked here is the one the alert is traded for. Alerts which don't name
+/// a known strategy are rejected.
+async fn buffer_alert(
+    app: &App,
+    request: Request<Body>,
+) -> Result<(Parts, Bytes, serde_json::Value, &Strategy), ApiError> {
     let (parts, body) = request.into_parts();
-    let mut bytes = vec![];
-    let mut body_string = String::new();
-
-    if method == axum::http::Method::POST {
-        bytes = body_to_bytes(body).await?;
-        body_string = match serde_json::from_slice::<serde_json::Value>(&bytes) {
-            Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_default(),
-            Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
-        };
+    let bytes = hyper::body::to_bytes(body)
+        .await
+        .map_err(ApiError::internal_error)?;
+
+    let alert = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid alert: {err}")))?;
+    let strategy_id = AlertStrategy::deserialize(&alert)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid alert: {err}")))?
+        .strategy_id;
+    let strategy = app
+        .config
+        .strategies
+        .iter()
+        .find(|strategy| strategy.id == strategy_id)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown strategy - {strategy_id}")))?;
+
+    Ok((parts, bytes, alert, strategy))
+}
+
+/// Reject webhook alerts of strategies with a shared secret unless they carry a valid signature
+/// of the raw body. Signed bodies also carry a `timestamp` within the signature skew of the
+/// server time and a `nonce` not seen before, so a captured alert can't be posted again.
+pub async fn verify_signature(
+    State(app): State<Arc<App>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (parts, bytes, alert, strategy) = buffer_alert(&app, request).await?;
+
+    if let Some(secret) = strategy.webhook_secret.as_deref() {
+        verify_hmac(secret, &parts.headers, &bytes)?;
+        let alert =
+            AlertNonce::deserialize(&alert).map_err(|err| ApiError::BadRequest(err.to_string()))?;
+        verify_nonce(&app, strategy, alert).await?;
     }
 
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    // Log separator for request
-    let separator = "\n-----------------------request-----------------------";
-    stdout
-        .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))
-        .unwrap();
-    writeln!(&mut stdout, "{}", separator).unwrap();
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
 
-    let time: DateTime<FixedOffset> =
-        Utc::now().with_timezone(&FixedOffset::east_opt(8 * 3600).unwrap());
-    writeln!(&mut stdout, "Time (UTC+8): {time}").unwrap();
+/// Check the signature header is the HMAC-SHA256 of `body` with `secret`.
+pub(crate) fn verify_hmac(secret: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), ApiError> {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_start_matches("sha256="))
+        .and_then(|value| hex::decode(value).ok())
+        .ok_or_else(|| ApiError::Unauthorized("Webhook signature not found".to_string()))?;
 
-    writeln!(&mut stdout, "\n").unwrap();
-    // Log method and URI
-    stdout
-        .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))
-        .unwrap();
-    writeln!(&mut stdout, "{} {}", parts.method, parts.uri).unwrap();
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(ApiError::internal_error)?;
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| ApiError::Unauthorized("Webhook signature isn't correct".to_string()))
+}
 
-    // Log headers
-    stdout
-        .set_color(ColorSpec::new().set_fg(Some(Color::Magenta)))
-        .unwrap();
-    writeln!(&mut stdout, "{:#?}", parts.headers).unwrap();
+/// Check the signed alert is fresh and claim its nonce, see `verify_signature`.
+pub(crate) async fn verify_nonce(
+    app: &App,
+    strategy: &Strategy,
+    alert: AlertNonce,
+) -> Result<(), ApiError> {
+    let (Some(timestamp), Some(nonce)) = (alert.timestamp, alert.nonce) else {
+        return Err(ApiError::Unauthorized(
+            "Signed alerts require a timestamp and a nonce".to_string(),
+        ));
+    };
 
-    if method == axum::http::Method::POST {
-        // Log JSON body
-        stdout
-            .set_color(ColorSpec::new().set_fg(Some(Color::White)))
-            .unwrap();
-        writeln!(&mut stdout, "{}", body_string).unwrap();
+    let max_skew = app.config.webhook.max_signature_skew;
+    if !nonces::is_fresh(timestamp, Utc::now(), max_skew) {
+        tracing::warn!(
+            "Signed alert of strategy {} with timestamp {} rejected as stale",
+            strategy.name,
+            timestamp
+        );
+        return Err(ApiError::Unauthorized(format!(
+            "Alert timestamp is more than {max_skew}s off the server time"
+        )));
+    }
+    if !nonces::claim_nonce(&app.db, strategy.id, &nonce, 2 * max_skew).await? {
+        tracing::warn!(
+            "Signed alert of strategy {} with nonce {} rejected as a replay",
+            strategy.name,
+            nonce
+        );
+        return Err(ApiError::Unauthorized(
+            "Alert nonce was used before".to_string(),
+        ));
     }
 
-    stdout.reset().unwrap();
-
-    request = Request::from_parts(parts, Body::from(bytes));
-    Ok(next.run(request).await)
+    Ok(())
 }
 
-pub async fn log_response(
+/// Enforce tenant quotas for incoming webhook alerts and account accepted ones as tenant usage.
+pub async fn enforce_quotas(
+    State(app): State<Arc<App>>,
     request: Request<Body>,
     next: Next<Body>,
-) -> Result<impl IntoResponse, Response> {
-    let response = next.run(request).await;
-    let status = response.status();
+) -> Result<impl IntoResponse, ApiError> {
+    let (parts, bytes, _, strategy) = buffer_alert(&app, request).await?;
+    let request = Request::from_parts(parts, Body::from(bytes));
 
-    let (parts, body) = response.into_parts();
-    let bytes = hyper::body::to_bytes(body)
-        .await
-        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response())?;
+    usage::check_quotas(&app.db, &app.config, &strategy.tenant_id).await?;
 
-    let pretty_json: String;
-    if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&bytes) {
-        pretty_json = serde_json::to_string_pretty(&json).unwrap_or_default();
-    } else {
-        pretty_json = String::from_utf8_lossy(&bytes).to_string();
-    }
-
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    // Log separator for response
-    let separator = "\n-----------------------response-----------------------\n";
-    stdout
-        .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))
-        .unwrap();
-    write!(&mut stdout, "{}", separator).unwrap();
-    let time: DateTime<FixedOffset> =
-        Utc::now().with_timezone(&FixedOffset::east_opt(8 * 3600).unwrap());
-    writeln!(&mut stdout, "Time (UTC+8): {time}").unwrap();
-    writeln!(&mut stdout, "\n").unwrap();
-
-    match status.as_u16() {
-        200..=299 => {
-            stdout
-                .set_color(ColorSpec::new().set_fg(Some(Color::Green)))
-                .unwrap();
-            writeln!(&mut stdout, "response: {}", status).unwrap();
-        }
-        _ => {
-            stdout
-                .set_color(ColorSpec::new().set_fg(Some(Color::Red)))
-                .unwrap();
-            writeln!(&mut stdout, "response: {}", status).unwrap();
+    let response = next.run(request).await;
+    if response.status().is_success() {
+        if let Err(err) = usage::record(
+            &app.db,
+            &strategy.tenant_id,
+            UsageKind::Signal,
+            Some(strategy.id),
+        )
+        .await
+        {
+            tracing::error!("Failed to record usage, error: {:?}", err);
         }
     }
 
-    stdout.reset().unwrap();
+    Ok(response)
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct JsonResponse {
+    message: String,
+}
+
+/// Log the method, URI, headers and body of requests as `http` debug events, credentials and
+/// sensitive body fields redacted.
+pub async fn log_request(request: Request<Body>, next: Next<Body>) -> Result<Response, Response> {
+    if !tracing::enabled!(target: "http", tracing::Level::DEBUG) {
+        return Ok(next.run(request).await);
+    }
+
+    let (parts, body) = request.into_parts();
+    let has_body = [Method::POST, Method::PUT, Method::PATCH].contains(&parts.method);
+    let bytes = if has_body {
+        body_to_bytes(body).await?
+    } else {
+        Vec::new()
+    };
+
+    tracing::debug!(
+        target: "http",
+        method = %parts.method,
+        uri = %parts.uri,
+        headers = ?logging::redact_headers(&parts.headers),
+        body = %logging::redact_body(&bytes),
+        "Request"
+    );
 
-    // Log headers
-    stdout
-        .set_color(ColorSpec::new().set_fg(Some(Color::Magenta)))
-        .unwrap();
-    writeln!(&mut stdout, "{:#?}", parts.headers).unwrap();
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
 
-    // Log JSON body
-    if !pretty_json.is_empty() {
-        stdout
-            .set_color(ColorSpec::new().set_fg(Some(Color::White)))
-            .unwrap();
-        writeln!(&mut stdout, "{}", pretty_json).unwrap();
+/// Log the status, headers and body of responses as `http` debug events like `log_request`.
+pub async fn log_response(request: Request<Body>, next: Next<Body>) -> Result<Response, Response> {
+    let response = next.run(request).await;
+    if !tracing::enabled!(target: "http", tracing::Level::DEBUG) {
+        return Ok(response);
     }
 
-    stdout.reset().unwrap();
+    let (parts, body) = response.into_parts();
+    // NOTE: streamed bodies and downloads are passed through as they are, buffering them would
+    // hold whole files in memory
+    let (body, logged) = if is_logged_body(&parts.headers, body.size_hint().upper()) {
+        let bytes = hyper::body::to_bytes(body)
+            .await
+            .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response())?;
+        let logged = logging::redact_body(&bytes);
+        (axum::body::boxed(Body::from(bytes)), logged)
+    } else {
+        (body, String::new())
+    };
+
+    tracing::debug!(
+        target: "http",
+        status = parts.status.as_u16(),
+        headers = ?logging::redact_headers(&parts.headers),
+        body = %logged,
+        "Response"
+    );
+
+    Ok(Response::from_parts(parts, body))
+}
 
-    Ok(Response::from_parts(parts, Body::from(bytes)))
+/// Whether a response body is buffered to be logged, only small JSON bodies which aren't
+/// downloads are.
+fn is_logged_body(headers: &HeaderMap, len: Option<u64>) -> bool {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    header(header::CONTENT_TYPE).starts_with("application/json")
+        && !header(header::CONTENT_DISPOSITION).starts_with("attachment")
+        && len.is_some_and(|len| len <= MAX_LOGGED_BODY)
 }
 
 async fn body_to_bytes(body: Body) -> Result<Vec<u8>, Response> {
@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Clock, Order, Position},
+    clients::{alpaca_order_request, BrokerClient, BrokerClientError},
+    order::NewOrder,
+};
+
+/// Call made to the mock, with its request as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockCall {
+    pub operation: String,
+    pub request: Value,
+}
+
+/// Answer of the next call of an operation.
+enum Scripted {
+    Response(Value),
+    Failure(BrokerClientError),
+}
+
+#[derive(Default)]
+struct MockState {
+    scripted: HashMap<String, VecDeque<Scripted>>,
+    /// Responses of operations once their scripted answers are used up
+    defaults: HashMap<String, Value>,
+    calls: Vec<MockCall>,
+}
+
+/// Alpaca client answering with scripted responses and failures instead of calling the broker.
+/// Operations are named after the methods of `BrokerClient`, responses are the JSON of the
+/// response types. Every call takes the oldest answer scripted for its operation, then the
+/// default response of the operation, calls without either fail. Clones share their script, so
+/// a test keeps a clone to script answers and inspect the calls of the client it hands out.
+#[derive(Clone, Default)]
+pub struct MockBrokerClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockBrokerClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Answer the next call of `operation` with `response`.
+    pub fn respond(&self, operation: &str, response: Value) {
+        self.script(operation, Scripted::Response(response));
+    }
+
+    /// Fail the next call of `operation` with `error`.
+    pub fn fail(&self, operation: &str, error: BrokerClientError) {
+        self.script(operation, Scripted::Failure(error));
+    }
+
+    /// Answer calls of `operation` with `response` once the scripted answers are used up.
+    pub fn set_default(&self, operation: &str, response: Value) {
+        self.state().defaults.insert(operation.to_owned(), response);
+    }
+
+    /// Calls made so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state().calls.clone()
+    }
+
+    /// Calls made so far of `operation`.
+    pub fn calls_of(&self, operation: &str) -> Vec<MockCall> {
+        self.state()
+            .calls
+            .iter()
+            .filter(|call| call.operation == operation)
+            .cloned()
+            .collect()
+    }
+
+    fn script(&self, operation: &str, scripted: Scripted) {
+        self.state()
+            .scripted
+            .entry(operation.to_owned())
+            .or_default()
+            .push_back(scripted);
+    }
+
+    fn answer<R: Serialize, T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        request: &R,
+    ) -> Result<T, BrokerClientError> {
+        let scripted = {
+            let mut state = self.state();
+            state.calls.push(MockCall {
+                operation: operation.to_owned(),
+                request: serde_json::to_value(request).unwrap_or_default(),
+            });
+            match state
+                .scripted
+                .get_mut(operation)
+                .and_then(VecDeque::pop_front)
+            {
+                Some(scripted) => Some(scripted),
+                None => state
+                    .defaults
+                    .get(operation)
+                    .cloned()
+                    .map(Scripted::Response),
+            }
+        };
+
+        match scripted {
+            // NOTE: the Alpaca types borrow some of their strings, which a `Value` can't lend
+            Some(Scripted::Response(response)) => serde_json::from_str(&response.to_string())
+                .map_err(|err| {
+                    BrokerClientError::MockError(format!(
+                        "Invalid scripted {operation} response: {err}"
+                    ))
+                }),
+            Some(Scripted::Failure(error)) => Err(error),
+            None => Err(BrokerClientError::MockError(format!(
+                "No {operation} response scripted"
+            ))),
+        }
+    }
+}
+
+#[axum::async_trait]
+impl BrokerClient for MockBrokerClient {
+    type ActivitiesRequest = apca::api::v2::account_activities::ActivityReq;
+    type NewOrderRequest = apca::api::v2::order::OrderReq;
+    type OrdersRequest = apca::api::v2::orders::OrdersReq;
+    type OrderUdateRequest = apca::api::v2::order::ChangeReq;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        alpaca_order_request(new_order)
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        self.answer("get_account", &())
+    }
+
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        self.answer("get_activities", &activities_req)
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        self.answer("get_asset", &symbol)
+    }
+
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        self.answer("get_assets", &class)
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        self.answer("get_clock", &())
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        self.answer("get_position", &symbol)
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        self.answer("get_positions", &())
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        self.answer("delete_position", &symbol)
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        self.answer("get_order_by_client_id", &client_id)
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        self.answer("get_orders", &orders_req)
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.answer("create_order", &new_order_req)
+    }
+
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.answer("update_order", &(order_id, update_req))
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        self.answer("delete_order", &order_id)
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Fields of signed alerts protecting them against replays, see `middleware::verify_signature`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertNonce {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub nonce: Option<String>,
+}
+
+/// Whether the timestamp of a signed alert is within `max_skew_secs` of `now` either way.
+pub fn is_fresh(timestamp: DateTime<Utc>, now: DateTime<Utc>, max_skew_secs: u64) -> bool {
+    (now - timestamp).num_seconds().unsigned_abs() <= max_skew_secs
+}
+
+/// Claim the nonce of a signed alert of a strategy. Returns `false` when it was claimed before,
+/// the alert is a replay. Nonces are forgotten after `window_secs`, by then the timestamp of an
+/// alert with them is stale anyway.
+pub async fn claim_nonce(
+    db: &PgPool,
+    strategy_id: Uuid,
+    nonce: &str,
+    window_secs: u64,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM webhook_nonces WHERE received_at < NOW() - make_interval(secs => $1)")
+        .bind(window_secs as f64)
+        .execute(db)
+        .await?;
+
+    let claimed = sqlx::query(
+        r#"
+        INSERT INTO webhook_nonces (strategy_id, nonce, received_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (strategy_id, nonce) DO NOTHING
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(nonce)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(claimed == 1)
+}
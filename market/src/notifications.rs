@@ -0,0 +1,104 @@
+use serde::Deserialize;
+use serde_json::json;
+use strum_macros::AsRefStr;
+use thiserror::Error as ThisError;
+
+use crate::app_config::Notifications;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Slack,
+    Telegram,
+    Sms,
+    Pushover,
+}
+
+#[derive(Debug, ThisError)]
+pub enum NotificationError {
+    #[error("Channel {0} is not configured")]
+    NotConfigured(String),
+    #[error(transparent)]
+    RequestError(#[from] reqwest::Error),
+}
+
+/// Sends alerts to the configured notification channels.
+pub struct Notifier {
+    http: reqwest::Client,
+    config: Notifications,
+}
+
+impl Notifier {
+    pub fn new(config: Notifications) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// Send `message` to every channel. Failures are logged, so one broken channel doesn't prevent
+    /// delivery to the others.
+    pub async fn notify(&self, channels: &[Channel], message: &str) {
+        for channel in channels {
+            if let Err(err) = self.send(*channel, message).await {
+                tracing::error!(
+                    "Failed to send notification to {}, error: {:?}",
+                    channel.as_ref(),
+                    err
+                );
+            }
+        }
+    }
+
+    async fn send(&self, channel: Channel, message: &str) -> Result<(), NotificationError> {
+        let not_configured = || NotificationError::NotConfigured(channel.as_ref().to_owned());
+
+        let request = match channel {
+            Channel::Slack => {
+                let url = self
+                    .config
+                    .slack_webhook_url
+                    .as_ref()
+                    .ok_or_else(not_configured)?;
+                self.http.post(url).json(&json!({ "text": message }))
+            }
+            Channel::Telegram => {
+                let telegram = self.config.telegram.as_ref().ok_or_else(not_configured)?;
+                self.http
+                    .post(format!(
+                        "https://api.telegram.org/bot{}/sendMessage",
+                        telegram.bot_token
+                    ))
+                    .json(&json!({ "chat_id": telegram.chat_id, "text": message }))
+            }
+            Channel::Sms => {
+                let sms = self.config.sms.as_ref().ok_or_else(not_configured)?;
+                self.http
+                    .post(format!(
+                        "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+                        sms.account_sid
+                    ))
+                    .basic_auth(&sms.account_sid, Some(&sms.auth_token))
+                    .form(&[
+                        ("From", &sms.from),
+                        ("To", &sms.to),
+                        ("Body", &message.to_owned()),
+                    ])
+            }
+            Channel::Pushover => {
+                let pushover = self.config.pushover.as_ref().ok_or_else(not_configured)?;
+                self.http
+                    .post("https://api.pushover.net/1/messages.json")
+                    .form(&[
+                        ("token", pushover.api_token.as_str()),
+                        ("user", pushover.user_key.as_str()),
+                        ("message", message),
+                    ])
+            }
+        };
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
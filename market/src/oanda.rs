@@ -0,0 +1,800 @@
+use std::sync::Arc;
+
+use apca::api::v2::{
+    account_activities::{ActivityReq, Direction},
+    order::ChangeReq,
+    orders::{OrdersReq, Status as OrdersStatus},
+};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Clock, Order, Position},
+    app_config::{Oanda, TradingEnvironment},
+    clients::{num_to_decimal, order_number, BrokerClient, BrokerClientError},
+    market_data::LiveQuote,
+    order::{NewOrder, OrderSide, TimeInForce},
+};
+
+/// Transactions returned for an activities request without a page size
+const TRANSACTIONS_PAGE_SIZE: usize = 100;
+
+/// Summary of an OANDA account, amounts are in its home currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OandaAccount {
+    pub id: String,
+    pub currency: String,
+    pub balance: Decimal,
+    /// Net asset value, the balance and the unrealized P&L of the open trades
+    #[serde(rename = "NAV")]
+    pub nav: Decimal,
+    #[serde(rename = "unrealizedPL")]
+    pub unrealized_pl: Decimal,
+    pub margin_available: Decimal,
+    /// Share of the value of positions held as margin, e.g. `0.02` for a leverage of 50
+    pub margin_rate: Decimal,
+    pub open_position_count: u32,
+    pub pending_order_count: u32,
+}
+
+/// Instrument the account can trade, e.g. `EUR_USD`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OandaInstrument {
+    pub name: String,
+    /// `CURRENCY`, `CFD` or `METAL`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub display_name: String,
+    /// Exponent of the size of a pip, `-4` for a pip of `0.0001`
+    pub pip_location: i32,
+    pub display_precision: u32,
+    /// Decimals of the units orders are accepted with
+    pub trade_units_precision: u32,
+    pub minimum_trade_size: Decimal,
+    pub margin_rate: Decimal,
+}
+
+impl OandaInstrument {
+    pub fn pip_size(&self) -> Decimal {
+        Decimal::new(1, self.pip_location.unsigned_abs())
+    }
+}
+
+/// Weekly forex session, trading around the clock from Sunday to Friday evening.
+///
+/// NOTE: the session is fixed in UTC, holidays and the daylight saving shift of New York aren't
+/// accounted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OandaClock {
+    pub open: bool,
+    pub next_open: DateTime<Utc>,
+    pub next_close: DateTime<Utc>,
+}
+
+impl OandaClock {
+    pub fn at(now: DateTime<Utc>) -> Self {
+        let week_start = Utc.from_utc_datetime(
+            &(now.date_naive() - Duration::days(now.weekday().num_days_from_sunday().into()))
+                .and_time(week_boundary()),
+        );
+        let close = week_start + Duration::days(5);
+        let (next_open, next_close) = if now < week_start {
+            (week_start, close)
+        } else if now < close {
+            (week_start + Duration::weeks(1), close)
+        } else {
+            (week_start + Duration::weeks(1), close + Duration::weeks(1))
+        };
+
+        Self {
+            open: week_start <= now && now < close,
+            next_open,
+            next_close,
+        }
+    }
+}
+
+/// Time of the week forex trading opens on Sundays and closes on Fridays, 5pm in New York.
+fn week_boundary() -> NaiveTime {
+    NaiveTime::from_hms_opt(21, 0, 0).unwrap_or_default()
+}
+
+/// Long and short side of a position, OANDA keeps both of an instrument apart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OandaPositionSide {
+    /// Units held, negative on the short side
+    pub units: Decimal,
+    #[serde(default)]
+    pub average_price: Option<Decimal>,
+    #[serde(rename = "unrealizedPL")]
+    pub unrealized_pl: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OandaPosition {
+    pub instrument: String,
+    pub long: OandaPositionSide,
+    pub short: OandaPositionSide,
+    #[serde(rename = "unrealizedPL")]
+    pub unrealized_pl: Decimal,
+}
+
+impl OandaPosition {
+    /// Net units held, negative when short.
+    pub fn units(&self) -> Decimal {
+        self.long.units + self.short.units
+    }
+
+    /// Average price of the side held, `None` when flat.
+    pub fn average_price(&self) -> Option<Decimal> {
+        if self.short.units.is_zero() {
+            self.long.average_price
+        } else {
+            self.short.average_price
+        }
+    }
+}
+
+/// Stop loss order OANDA creates for the trade a filled order opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OandaStopLoss {
+    pub id: String,
+    pub price: Decimal,
+    /// `PENDING`, `FILLED` or `CANCELLED`
+    pub state: String,
+}
+
+/// Market or limit order of an OANDA account with its execution, resolved from the order, its
+/// filling transaction and the trade it opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OandaOrder {
+    /// Numeric id of the order, see `clients::order_uuid`
+    pub id: String,
+    pub client_order_id: String,
+    pub instrument: String,
+    /// Ordered units, negative for sells
+    pub units: Decimal,
+    /// `MARKET` or `LIMIT`
+    pub kind: String,
+    /// `PENDING`, `FILLED`, `TRIGGERED` or `CANCELLED`
+    pub state: String,
+    pub time_in_force: String,
+    /// Limit price
+    pub price: Option<Decimal>,
+    pub create_time: DateTime<Utc>,
+    /// Units filled, negative for sells
+    pub filled_units: Decimal,
+    pub average_fill_price: Option<Decimal>,
+    /// Time of the filling transaction
+    pub filled_time: Option<DateTime<Utc>>,
+    pub stop_loss: Option<OandaStopLoss>,
+}
+
+impl OandaOrder {
+    /// State in the snake case status format of Alpaca orders, which local orders are kept in.
+    pub fn status(&self) -> &'static str {
+        match self.state.as_str() {
+            "PENDING" => "new",
+            "FILLED" | "TRIGGERED" => "filled",
+            "CANCELLED" => "canceled",
+            _ => "unknown",
+        }
+    }
+
+    pub fn side(&self) -> OrderSide {
+        if self.units.is_sign_negative() {
+            OrderSide::Sell
+        } else {
+            OrderSide::Buy
+        }
+    }
+}
+
+/// Order request of the v20 API, see `order_request`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OandaOrderRequest {
+    pub order: OandaOrderSpec,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OandaOrderSpec {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub instrument: String,
+    /// Negative for sells
+    pub units: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Decimal>,
+    pub time_in_force: String,
+    pub position_fill: String,
+    pub client_extensions: ClientExtensions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_loss_on_fill: Option<StopLossDetails>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientExtensions {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopLossDetails {
+    pub price: Decimal,
+    pub time_in_force: String,
+}
+
+/// OANDA order request of a broker-agnostic order. Market orders fill or are canceled right away,
+/// so they're sent fill or kill unless they're immediate or cancel. Orders valid for the day are
+/// good for the day at OANDA, the auction time in force of stocks doesn't apply to forex.
+pub fn order_request(new_order: &NewOrder) -> OandaOrderRequest {
+    let units = match new_order.side {
+        OrderSide::Buy => new_order.quantity,
+        OrderSide::Sell => -new_order.quantity,
+    };
+    let time_in_force = match (new_order.limit_price, new_order.time_in_force) {
+        (_, TimeInForce::Ioc) => "IOC",
+        (_, TimeInForce::Fok) | (None, _) => "FOK",
+        (Some(_), TimeInForce::Gtc) => "GTC",
+        (Some(_), TimeInForce::Day | TimeInForce::Opg | TimeInForce::Cls) => "GFD",
+    };
+
+    OandaOrderRequest {
+        order: OandaOrderSpec {
+            kind: match new_order.limit_price {
+                Some(_) => "LIMIT",
+                None => "MARKET",
+            }
+            .to_owned(),
+            instrument: new_order.ticker.clone(),
+            units,
+            price: new_order.limit_price,
+            time_in_force: time_in_force.to_owned(),
+            position_fill: "DEFAULT".to_owned(),
+            client_extensions: ClientExtensions {
+                id: new_order.client_order_id.clone(),
+            },
+            stop_loss_on_fill: new_order.stop_loss_price.map(|price| StopLossDetails {
+                price,
+                time_in_force: "GTC".to_owned(),
+            }),
+        },
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireOrder {
+    id: String,
+    create_time: DateTime<Utc>,
+    state: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    instrument: Option<String>,
+    #[serde(default)]
+    units: Option<Decimal>,
+    #[serde(default)]
+    time_in_force: Option<String>,
+    #[serde(default)]
+    price: Option<Decimal>,
+    #[serde(default)]
+    client_extensions: Option<ClientExtensions>,
+    #[serde(default, rename = "fillingTransactionID")]
+    filling_transaction_id: Option<String>,
+    #[serde(default, rename = "tradeOpenedID")]
+    trade_opened_id: Option<String>,
+    /// Trade of a stop loss order
+    #[serde(default, rename = "tradeID")]
+    trade_id: Option<String>,
+    #[serde(default)]
+    stop_loss_on_fill: Option<StopLossDetails>,
+}
+
+#[derive(Deserialize)]
+struct OrderResponse {
+    order: WireOrder,
+}
+
+#[derive(Deserialize)]
+struct OrdersResponse {
+    orders: Vec<WireOrder>,
+}
+
+#[derive(Deserialize)]
+struct FillTransaction {
+    #[serde(default)]
+    units: Option<Decimal>,
+    #[serde(default)]
+    price: Option<Decimal>,
+    #[serde(default)]
+    time: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct TransactionResponse {
+    transaction: FillTransaction,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireTrade {
+    #[serde(default)]
+    stop_loss_order: Option<OandaStopLoss>,
+}
+
+#[derive(Deserialize)]
+struct TradeResponse {
+    trade: WireTrade,
+}
+
+#[derive(Deserialize)]
+struct Transaction {
+    id: String,
+}
+
+/// Response of requests creating an order, the order created by replacing or closing is the new
+/// one.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateResponse {
+    #[serde(default)]
+    order_create_transaction: Option<Transaction>,
+    #[serde(default)]
+    long_order_create_transaction: Option<Transaction>,
+    #[serde(default)]
+    short_order_create_transaction: Option<Transaction>,
+}
+
+impl CreateResponse {
+    fn order_id(self) -> Result<String, BrokerClientError> {
+        self.order_create_transaction
+            .or(self.long_order_create_transaction)
+            .or(self.short_order_create_transaction)
+            .map(|transaction| transaction.id)
+            .ok_or_else(|| BrokerClientError::OandaError("No order created".to_owned()))
+    }
+}
+
+#[derive(Deserialize)]
+struct AccountResponse {
+    account: OandaAccount,
+}
+
+#[derive(Deserialize)]
+struct InstrumentsResponse {
+    instruments: Vec<OandaInstrument>,
+}
+
+#[derive(Deserialize)]
+struct PositionResponse {
+    position: OandaPosition,
+}
+
+#[derive(Deserialize)]
+struct PositionsResponse {
+    positions: Vec<OandaPosition>,
+}
+
+#[derive(Deserialize)]
+struct PriceBucket {
+    price: Decimal,
+}
+
+#[derive(Deserialize)]
+struct Price {
+    instrument: String,
+    time: DateTime<Utc>,
+    bids: Vec<PriceBucket>,
+    asks: Vec<PriceBucket>,
+}
+
+#[derive(Deserialize)]
+struct PricingResponse {
+    prices: Vec<Price>,
+}
+
+#[derive(Deserialize)]
+struct TransactionPages {
+    pages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TransactionPage {
+    transactions: Vec<Value>,
+}
+
+/// Client of the v20 REST API of an OANDA account.
+pub struct OandaClient {
+    http: reqwest::Client,
+    base_url: String,
+    account_id: String,
+    api_token: String,
+}
+
+impl OandaClient {
+    pub fn new(config: &Oanda, environment: TradingEnvironment) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: config
+                .base_url(environment)
+                .trim_end_matches('/')
+                .to_owned(),
+            account_id: config.account_id.clone(),
+            api_token: config.api_token.clone(),
+        }
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.http
+            .request(method, url)
+            .bearer_auth(&self.api_token)
+            .header("Accept-Datetime-Format", "RFC3339")
+    }
+
+    /// Request of `path` below the account.
+    fn account_request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/v3/accounts/{}{}", self.base_url, self.account_id, path);
+        self.request(method, &url)
+    }
+
+    /// Response of `request`. Rate limited requests, server errors and requests without a
+    /// response fail as unavailable, so they count towards the circuit breaker and are retried.
+    async fn json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, BrokerClientError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|err| BrokerClientError::OandaUnavailable(err.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return response
+                .json()
+                .await
+                .map_err(|err| BrokerClientError::OandaError(err.to_string()));
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let message = format!("{status}: {body}");
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Err(BrokerClientError::OandaUnavailable(message))
+        } else {
+            Err(BrokerClientError::OandaError(message))
+        }
+    }
+
+    /// Order `specifier`, an order id or `@` and a client order id, with its execution.
+    async fn fetch_order(&self, specifier: &str) -> Result<OandaOrder, BrokerClientError> {
+        let OrderResponse { order } = self
+            .json(self.account_request(Method::GET, &format!("/orders/{specifier}")))
+            .await?;
+        self.resolve_order(order).await
+    }
+
+    async fn resolve_order(&self, order: WireOrder) -> Result<OandaOrder, BrokerClientError> {
+        let fill = match &order.filling_transaction_id {
+            Some(id) => {
+                let TransactionResponse { transaction } = self
+                    .json(self.account_request(Method::GET, &format!("/transactions/{id}")))
+                    .await?;
+                Some(transaction)
+            }
+            None => None,
+        };
+        let stop_loss = match (&order.trade_opened_id, &order.stop_loss_on_fill) {
+            (Some(trade_id), Some(_)) => {
+                let TradeResponse { trade } = self
+                    .json(self.account_request(Method::GET, &format!("/trades/{trade_id}")))
+                    .await?;
+                trade.stop_loss_order
+            }
+            _ => None,
+        };
+
+        Ok(OandaOrder {
+            client_order_id: order
+                .client_extensions
+                .map(|extensions| extensions.id)
+                .unwrap_or_default(),
+            instrument: order.instrument.unwrap_or_default(),
+            units: order.units.unwrap_or_default(),
+            kind: order.kind,
+            state: order.state,
+            time_in_force: order.time_in_force.unwrap_or_default(),
+            price: order.price,
+            create_time: order.create_time,
+            filled_units: fill
+                .as_ref()
+                .and_then(|fill| fill.units)
+                .unwrap_or_default(),
+            average_fill_price: fill.as_ref().and_then(|fill| fill.price),
+            filled_time: fill.and_then(|fill| fill.time),
+            stop_loss,
+            id: order.id,
+        })
+    }
+
+    /// Latest quote of `instrument`, `None` when OANDA has no prices of it.
+    pub async fn fetch_quote(
+        &self,
+        instrument: &str,
+    ) -> Result<Option<LiveQuote>, BrokerClientError> {
+        let PricingResponse { prices } = self
+            .json(
+                self.account_request(Method::GET, "/pricing")
+                    .query(&[("instruments", instrument)]),
+            )
+            .await?;
+
+        Ok(prices
+            .into_iter()
+            .find(|price| price.instrument == instrument)
+            .and_then(|price| {
+                Some(LiveQuote {
+                    bid: price.bids.first()?.price,
+                    ask: price.asks.first()?.price,
+                    time: price.time,
+                })
+            }))
+    }
+}
+
+#[axum::async_trait]
+impl BrokerClient for Arc<OandaClient> {
+    // NOTE: requests other than new orders are the ones of Alpaca, so callers querying orders
+    // and changing them work with both brokers
+    type ActivitiesRequest = ActivityReq;
+    type NewOrderRequest = OandaOrderRequest;
+    type OrdersRequest = OrdersReq;
+    type OrderUdateRequest = ChangeReq;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        order_request(new_order)
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        let AccountResponse { account } = self
+            .json(self.account_request(Method::GET, "/summary"))
+            .await?;
+        Ok(Account::OandaAccount(account))
+    }
+
+    /// Transactions of the account, of every type, in the time range of the request.
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        let page_size = activities_req.page_size.unwrap_or(TRANSACTIONS_PAGE_SIZE);
+        let mut query = vec![("pageSize", page_size.to_string())];
+        query.extend(
+            activities_req
+                .after
+                .map(|after| ("from", after.to_rfc3339())),
+        );
+        query.extend(activities_req.until.map(|until| ("to", until.to_rfc3339())));
+        let TransactionPages { pages } = self
+            .json(
+                self.account_request(Method::GET, "/transactions")
+                    .query(&query),
+            )
+            .await?;
+
+        let mut transactions = Vec::new();
+        for page in pages {
+            let TransactionPage {
+                transactions: page_transactions,
+            } = self.json(self.request(Method::GET, &page)).await?;
+            transactions.extend(page_transactions);
+        }
+        if activities_req.direction == Direction::Descending {
+            transactions.reverse();
+        }
+        transactions.truncate(page_size);
+
+        Ok(transactions
+            .into_iter()
+            .map(Activity::OandaTransaction)
+            .collect())
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        let InstrumentsResponse { instruments } = self
+            .json(
+                self.account_request(Method::GET, "/instruments")
+                    .query(&[("instruments", &symbol)]),
+            )
+            .await?;
+
+        instruments
+            .into_iter()
+            .find(|instrument| instrument.name == symbol)
+            .map(Asset::OandaInstrument)
+            .ok_or_else(|| BrokerClientError::OandaError(format!("Unknown instrument {symbol}")))
+    }
+
+    /// Currency pairs of the account, OANDA trades no stocks or crypto.
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        if class != AssetClass::Forex {
+            return Ok(Vec::new());
+        }
+        let InstrumentsResponse { instruments } = self
+            .json(self.account_request(Method::GET, "/instruments"))
+            .await?;
+
+        Ok(instruments
+            .into_iter()
+            .filter(|instrument| instrument.kind == "CURRENCY")
+            .map(Asset::OandaInstrument)
+            .collect())
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        Ok(Clock::OandaClock(OandaClock::at(Utc::now())))
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        let PositionResponse { position } = self
+            .json(self.account_request(Method::GET, &format!("/positions/{symbol}")))
+            .await?;
+        Ok(Position::OandaPosition(position))
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        let PositionsResponse { positions } = self
+            .json(self.account_request(Method::GET, "/openPositions"))
+            .await?;
+        Ok(positions.into_iter().map(Position::OandaPosition).collect())
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        let PositionResponse { position } = self
+            .json(self.account_request(Method::GET, &format!("/positions/{symbol}")))
+            .await?;
+        let side = if position.units().is_sign_negative() {
+            "shortUnits"
+        } else {
+            "longUnits"
+        };
+        let created: CreateResponse = self
+            .json(
+                self.account_request(Method::PUT, &format!("/positions/{symbol}/close"))
+                    .json(&json!({ side: "ALL" })),
+            )
+            .await?;
+
+        let order = self.fetch_order(&created.order_id()?).await?;
+        Ok(Order::OandaOrder(order))
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        let order = self.fetch_order(&format!("@{client_id}")).await?;
+        Ok(Order::OandaOrder(order))
+    }
+
+    /// Market and limit orders of the account, the stop losses OANDA creates for trades are
+    /// reported with the orders which opened them.
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        let state = match orders_req.status {
+            OrdersStatus::Open => "PENDING",
+            OrdersStatus::Closed | OrdersStatus::All => "ALL",
+        };
+        let mut query = vec![("state", state.to_owned())];
+        query.extend(orders_req.limit.map(|limit| ("count", limit.to_string())));
+        if let [symbol] = orders_req.symbols.as_slice() {
+            query.push(("instrument", symbol.clone()));
+        }
+        let OrdersResponse { orders } = self
+            .json(self.account_request(Method::GET, "/orders").query(&query))
+            .await?;
+
+        let mut resolved = Vec::new();
+        for order in orders {
+            let listed = matches!(order.kind.as_str(), "MARKET" | "LIMIT")
+                && (orders_req.symbols.is_empty()
+                    || order
+                        .instrument
+                        .as_ref()
+                        .is_some_and(|instrument| orders_req.symbols.contains(instrument)))
+                && (orders_req.status != OrdersStatus::Closed || order.state != "PENDING");
+            if listed {
+                resolved.push(Order::OandaOrder(self.resolve_order(order).await?));
+            }
+        }
+        Ok(resolved)
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let created: CreateResponse = self
+            .json(
+                self.account_request(Method::POST, "/orders")
+                    .json(&new_order_req),
+            )
+            .await?;
+
+        let order = self.fetch_order(&created.order_id()?).await?;
+        Ok(Order::OandaOrder(order))
+    }
+
+    /// Replace a pending limit order or the stop loss of a trade with one at the prices of
+    /// `update_req`. OANDA replaces orders by new ones, the new order is returned.
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let specifier = order_number(order_id);
+        let OrderResponse { order } = self
+            .json(self.account_request(Method::GET, &format!("/orders/{specifier}")))
+            .await?;
+
+        let replacement = match order.kind.as_str() {
+            "STOP_LOSS" => json!({
+                "type": "STOP_LOSS",
+                "tradeID": order.trade_id,
+                "price": update_req.stop_price.as_ref().map(num_to_decimal).or(order.price),
+                "timeInForce": order.time_in_force,
+            }),
+            "LIMIT" => {
+                let units = order.units.unwrap_or_default();
+                let units = match &update_req.quantity {
+                    Some(quantity) if units.is_sign_negative() => -num_to_decimal(quantity),
+                    Some(quantity) => num_to_decimal(quantity),
+                    None => units,
+                };
+                json!({
+                    "type": "LIMIT",
+                    "instrument": order.instrument,
+                    "units": units,
+                    "price": update_req.limit_price.as_ref().map(num_to_decimal).or(order.price),
+                    "timeInForce": order.time_in_force,
+                    "positionFill": "DEFAULT",
+                    "clientExtensions": update_req
+                        .client_order_id
+                        .map(|id| ClientExtensions { id })
+                        .or(order.client_extensions),
+                })
+            }
+            kind => {
+                return Err(BrokerClientError::OandaError(format!(
+                    "{kind} orders can't be changed"
+                )))
+            }
+        };
+        let created: CreateResponse = self
+            .json(
+                self.account_request(Method::PUT, &format!("/orders/{specifier}"))
+                    .json(&json!({ "order": replacement })),
+            )
+            .await?;
+
+        let order = self.fetch_order(&created.order_id()?).await?;
+        Ok(Order::OandaOrder(order))
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        let _: Value = self
+            .json(self.account_request(
+                Method::PUT,
+                &format!("/orders/{}/cancel", order_number(order_id)),
+            ))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! OpenAPI document of the HTTP API, served with Swagger UI at `/docs`. Clients of the orders,
+//! positions and strategies routes can be generated from `/docs/openapi.json`.
+
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    activities::{AccountActivity, ActivityKind, CashFlow},
+    admin::{StrategyDisable, StrategyStatus, StrategySwitch},
+    api::{
+        error::{ErrorBody, ErrorCode, FieldError},
+        handlers,
+        objects::{Account, Broker, Order, OrdersRequest, Position},
+        pagination::{FillPage, OrderPage},
+    },
+    order::{Fill, OrderRecord},
+    pnl::{DailyPnl, OpenPosition, StrategyPnl, TradeStatistics},
+    rate_limit::{GroupUsage, Quota},
+};
+
+/// Name of the security scheme of the API key, see `middleware::auth`
+const API_KEY: &str = "api_key";
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        handlers::get_account,
+        handlers::get_account_usage,
+        handlers::get_account_activities,
+        handlers::get_account_cash_flow,
+        handlers::get_orders,
+        handlers::get_order_history,
+        handlers::get_order,
+        handlers::get_positions,
+        handlers::get_position_history,
+        handlers::get_strategies,
+        handlers::switch_strategy,
+        handlers::enable_strategy,
+        handlers::disable_strategy,
+        handlers::get_strategy_pnl,
+    ),
+    components(schemas(
+        Account,
+        AccountActivity,
+        ActivityKind,
+        Broker,
+        CashFlow,
+        DailyPnl,
+        ErrorBody,
+        ErrorCode,
+        FieldError,
+        Fill,
+        FillPage,
+        GroupUsage,
+        OpenPosition,
+        Order,
+        OrderPage,
+        OrderRecord,
+        OrdersRequest,
+        Position,
+        Quota,
+        StrategyPnl,
+        StrategyDisable,
+        StrategyStatus,
+        StrategySwitch,
+        TradeStatistics,
+    )),
+    modifiers(&ApiKeyAuth),
+    security(("api_key" = [])),
+    tags(
+        (name = "account", description = "Broker account"),
+        (name = "orders", description = "Orders placed with the broker"),
+        (name = "positions", description = "Positions of the broker account and their fills"),
+        (name = "strategies", description = "Configured strategies and their performance"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Requests authenticate with an API key or a `Bearer` token in the `Authorization` header.
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            API_KEY,
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Authorization",
+                "API key, or `Bearer` followed by an identity provider token",
+            ))),
+        );
+    }
+}
+
+/// Swagger UI at `/docs`, serving the document at `/docs/openapi.json`.
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/docs").url("/docs/openapi.json", ApiDoc::openapi())
+}
@@ -0,0 +1,130 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+use thiserror::Error as ThisError;
+
+use crate::order::OrderSide;
+
+/// Longest root symbol of an OCC option symbol
+const MAX_ROOT_LEN: usize = 6;
+/// Strikes are encoded in thousandths of a dollar
+const STRIKE_SCALE: u32 = 3;
+
+#[derive(Debug, ThisError, PartialEq, Eq)]
+pub enum OptionsError {
+    #[error("Invalid OCC option symbol - {0}")]
+    InvalidSymbol(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+/// Listed option contract. Serialized as its OCC symbol without padding, e.g.
+/// `AAPL240119C00190000` for the AAPL 190 call expiring on 2024-01-19.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OptionContract {
+    pub underlying: String,
+    pub expiry: NaiveDate,
+    pub strike: Decimal,
+    pub right: OptionRight,
+}
+
+impl OptionContract {
+    pub fn occ_symbol(&self) -> String {
+        let right = match self.right {
+            OptionRight::Call => 'C',
+            OptionRight::Put => 'P',
+        };
+        let strike = (self.strike * Decimal::from(10u32.pow(STRIKE_SCALE)))
+            .to_u64()
+            .unwrap_or_default();
+        format!(
+            "{}{}{}{:08}",
+            self.underlying,
+            self.expiry.format("%y%m%d"),
+            right,
+            strike
+        )
+    }
+}
+
+impl FromStr for OptionContract {
+    type Err = OptionsError;
+
+    fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+        let invalid = || OptionsError::InvalidSymbol(symbol.to_owned());
+        // Root, 6 digits of expiry, right and 8 digits of strike
+        let root_len = symbol.len().checked_sub(15).ok_or_else(invalid)?;
+        if !symbol.is_ascii() || root_len > MAX_ROOT_LEN {
+            return Err(invalid());
+        }
+        // The root is padded with spaces to 6 characters in the strict format
+        let (underlying, rest) = symbol.split_at(root_len);
+        let underlying = underlying.trim_end();
+        if underlying.is_empty() {
+            return Err(invalid());
+        }
+        let (expiry, rest) = rest.split_at(6);
+        let (right, strike) = rest.split_at(1);
+
+        let expiry = NaiveDate::parse_from_str(expiry, "%y%m%d").map_err(|_| invalid())?;
+        let right = match right {
+            "C" => OptionRight::Call,
+            "P" => OptionRight::Put,
+            _ => return Err(invalid()),
+        };
+        if !strike.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let strike = Decimal::new(strike.parse().map_err(|_| invalid())?, STRIKE_SCALE);
+
+        Ok(Self {
+            underlying: underlying.to_owned(),
+            expiry,
+            strike: strike.normalize(),
+            right,
+        })
+    }
+}
+
+impl TryFrom<String> for OptionContract {
+    type Error = OptionsError;
+
+    fn try_from(symbol: String) -> Result<Self, Self::Error> {
+        symbol.parse()
+    }
+}
+
+impl From<OptionContract> for String {
+    fn from(contract: OptionContract) -> Self {
+        contract.occ_symbol()
+    }
+}
+
+impl fmt::Display for OptionContract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.occ_symbol())
+    }
+}
+
+/// Leg of a multi-leg options order. The order quantity is multiplied by `ratio` for every leg.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderLeg {
+    pub contract: OptionContract,
+    pub side: OrderSide,
+    #[serde(default = "default_ratio")]
+    pub ratio: u32,
+}
+
+fn default_ratio() -> u32 {
+    1
+}
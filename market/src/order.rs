@@ -1 +1,642 @@
+use chrono::{DateTime, Duration, Utc};
+use futures::Stream;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection, PgExecutor, PgPool};
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
 
+use crate::{
+    api::{
+        objects::Broker,
+        pagination::{ListSource, Listed},
+    },
+    app_config::TradingEnvironment,
+    options::OrderLeg,
+    projections,
+    sizing::ExecutionPath,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, AsRefStr, EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// How long an order stays active before the broker cancels it.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TimeInForce {
+    /// Until the end of the regular trading session
+    #[default]
+    Day,
+    /// Until canceled
+    Gtc,
+    /// Filled immediately as far as possible, the rest is canceled
+    Ioc,
+    /// Filled immediately and completely or not at all
+    Fok,
+    /// Only in the opening auction
+    Opg,
+    /// Only in the closing auction
+    Cls,
+}
+
+/// Time in force Alpaca accepts for crypto orders
+pub const CRYPTO_TIME_IN_FORCE: [TimeInForce; 2] = [TimeInForce::Gtc, TimeInForce::Ioc];
+
+/// What's done with an order which is still partially filled after no execution happened for a
+/// while, see `OrderRecord::is_stale`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum PartialFills {
+    /// Leave the order open until it's filled, canceled or expires
+    #[default]
+    Wait,
+    /// Cancel the rest of the order after `stale_after` seconds without an execution
+    CancelRemainder { stale_after: u64 },
+    /// Replace a limit order by one for the rest of its quantity at the latest quote after
+    /// `stale_after` seconds without an execution. Market orders and orders of symbols without
+    /// a fresh quote keep waiting.
+    Reprice { stale_after: u64 },
+}
+
+impl PartialFills {
+    /// Time without an execution after which the policy applies, `None` when it waits.
+    pub fn stale_after(&self) -> Option<Duration> {
+        match *self {
+            PartialFills::Wait => None,
+            PartialFills::CancelRemainder { stale_after }
+            | PartialFills::Reprice { stale_after } => Some(Duration::seconds(stale_after as i64)),
+        }
+    }
+}
+
+/// Time unfilled limit orders of a strategy stay open for, see `Strategy::order_ttl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct OrderTtl {
+    /// Seconds after the order was created
+    pub seconds: u64,
+    #[serde(default)]
+    pub on_expiry: OrderExpiry,
+}
+
+impl OrderTtl {
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.seconds as i64)
+    }
+}
+
+/// What's done with an unfilled limit order once its time to live passed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderExpiry {
+    #[default]
+    Cancel,
+    /// Cancel it and submit a market order for its quantity instead
+    Market,
+}
+
+/// Id an order is submitted to the broker with, `<strategy id>.<signal id>` in the simple uuid
+/// format. The same signal always produces the same id, so the broker rejects it when submitted
+/// twice, and orders can be attributed to their strategy without a local record.
+pub fn client_order_id(strategy_id: Uuid, signal_id: Uuid) -> String {
+    format!("{}.{}", strategy_id.simple(), signal_id.simple())
+}
+
+/// Strategy and signal id of a client order id made by `client_order_id`, `None` for orders
+/// submitted by other means.
+pub fn parse_client_order_id(client_order_id: &str) -> Option<(Uuid, Uuid)> {
+    let (strategy_id, signal_id) = client_order_id.split_once('.')?;
+    Some((strategy_id.parse().ok()?, signal_id.parse().ok()?))
+}
+
+/// Broker-agnostic order produced by the core from a trade signal. Every broker client knows how to
+/// turn it into its own request type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewOrder {
+    pub id: Uuid,
+    pub strategy_id: Uuid,
+    /// See `client_order_id`
+    pub client_order_id: String,
+    pub ticker: String,
+    pub side: OrderSide,
+    /// Estimated quantity of notional orders
+    pub quantity: Decimal,
+    /// Dollar amount of notional orders, which the broker turns into a fractional quantity
+    pub notional: Option<Decimal>,
+    pub stop_loss_price: Option<Decimal>,
+    /// Limit price, market orders when `None`
+    pub limit_price: Option<Decimal>,
+    pub time_in_force: TimeInForce,
+    /// Eligible for execution in the pre- and after-market sessions
+    pub extended_hours: bool,
+    pub execution_path: ExecutionPath,
+    pub request_id: Option<String>,
+    pub environment: TradingEnvironment,
+    /// Credential set of the broker the order is submitted with, see `Strategy::account`
+    pub account: Option<String>,
+    /// Option contracts of options orders, `ticker` is their underlying. Orders of the
+    /// underlying itself have no legs.
+    pub legs: Vec<OrderLeg>,
+}
+
+/// Local copy of an order submitted to a broker, tagged with the strategy it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderRecord {
+    pub order_id: Uuid,
+    pub strategy_id: Uuid,
+    pub broker: String,
+    pub broker_order_id: Option<String>,
+    /// Id the order was submitted with, see `client_order_id`
+    pub client_order_id: String,
+    /// Credential set of the broker the order was submitted with, the global one when `None`
+    pub broker_account: Option<String>,
+    pub ticker: String,
+    pub side: String,
+    pub quantity: Decimal,
+    /// Dollar amount of notional orders, `quantity` is an estimate for them
+    pub notional: Option<Decimal>,
+    pub filled_quantity: Decimal,
+    pub filled_avg_price: Option<Decimal>,
+    /// Time of the latest execution, `None` before the first one
+    pub last_filled_at: Option<DateTime<Utc>>,
+    pub status: String,
+    /// Time in force the order was submitted with, see `TimeInForce`
+    pub time_in_force: Option<String>,
+    pub limit_price: Option<Decimal>,
+    pub extended_hours: bool,
+    /// Code path which produced the order, see `ExecutionPath`
+    pub execution_path: String,
+    /// Id of the webhook request which caused the order
+    pub request_id: Option<String>,
+    /// Trading environment, `paper` or `live`, the order was submitted in
+    pub environment: Option<String>,
+    /// Entry order whose position the order takes profit of, see `ladder`
+    pub parent_order_id: Option<Uuid>,
+    /// Time-sliced entry the order is a chunk of, see `dca`
+    pub entry_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Single execution of an order. Orders filled in several steps produce several fills.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Fill {
+    pub fill_id: Uuid,
+    pub order_id: Uuid,
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub side: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fee: Decimal,
+    pub filled_at: DateTime<Utc>,
+}
+
+impl NewOrder {
+    /// Combinations of order options the broker rejects, checked before the order is sent.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.extended_hours && self.limit_price.is_none() {
+            return Err("Extended hours orders must be limit orders".to_string());
+        }
+        if self.extended_hours && self.time_in_force != TimeInForce::Day {
+            return Err(format!(
+                "Extended hours orders must be day orders, not {}",
+                self.time_in_force.as_ref()
+            ));
+        }
+        if self.notional.is_some() && self.time_in_force != TimeInForce::Day {
+            return Err(format!(
+                "Notional orders must be day orders, not {}",
+                self.time_in_force.as_ref()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl OrderRecord {
+    /// Quantity the broker hasn't filled yet.
+    pub fn remaining_quantity(&self) -> Decimal {
+        (self.quantity - self.filled_quantity).max(Decimal::ZERO)
+    }
+
+    /// Whether the broker may still execute the order, see `fetch_open`.
+    pub fn is_open(&self) -> bool {
+        self.broker_order_id.is_some()
+            && !matches!(
+                self.status.as_str(),
+                "filled" | "canceled" | "expired" | "rejected" | "replaced"
+            )
+    }
+
+    /// Whether the order is a limit order without any execution created `ttl` or longer before
+    /// `now`.
+    pub fn is_expired(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        self.limit_price.is_some()
+            && self.filled_quantity == Decimal::ZERO
+            && self.created_at + ttl <= now
+    }
+
+    /// Whether the order is partially filled and had no execution for `after` at `now`.
+    pub fn is_stale(&self, after: Duration, now: DateTime<Utc>) -> bool {
+        self.status == "partially_filled"
+            && self.last_filled_at.unwrap_or(self.created_at) + after <= now
+    }
+
+    pub async fn insert(db: &PgPool, order: &NewOrder, broker: &Broker) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO orders (
+                order_id,
+                strategy_id,
+                broker,
+                ticker,
+                side,
+                quantity,
+                status,
+                execution_path,
+                request_id,
+                environment,
+                broker_account,
+                notional,
+                time_in_force,
+                limit_price,
+                extended_hours,
+                client_order_id,
+                created_at,
+                modified_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, 'pending', $7, $8, $9, $10, $11, $12, $13, $14, $15, NOW(),
+                NOW()
+            )
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.strategy_id)
+        .bind(broker.as_ref())
+        .bind(&order.ticker)
+        .bind(order.side.as_ref())
+        .bind(order.quantity)
+        .bind(order.execution_path.as_ref())
+        .bind(&order.request_id)
+        .bind(order.environment.as_ref())
+        .bind(&order.account)
+        .bind(order.notional)
+        .bind(order.time_in_force.as_ref())
+        .bind(order.limit_price)
+        .bind(order.extended_hours)
+        .bind(&order.client_order_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_status(
+        db: &PgPool,
+        order_id: Uuid,
+        broker_order_id: Option<&str>,
+        status: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE orders
+            SET broker_order_id = COALESCE($2, broker_order_id), status = $3, modified_at = NOW()
+            WHERE order_id = $1
+            "#,
+        )
+        .bind(order_id)
+        .bind(broker_order_id)
+        .bind(status)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_parent(
+        db: &PgPool,
+        order_id: Uuid,
+        parent_order_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE orders SET parent_order_id = $2 WHERE order_id = $1")
+            .bind(order_id)
+            .bind(parent_order_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_entry(db: &PgPool, order_id: Uuid, entry_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE orders SET entry_id = $2 WHERE order_id = $1")
+            .bind(order_id)
+            .bind(entry_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_fill(
+        db: impl PgExecutor<'_>,
+        order_id: Uuid,
+        filled_quantity: Decimal,
+        filled_avg_price: Option<Decimal>,
+        status: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE orders
+            SET last_filled_at = CASE
+                    WHEN $2 > filled_quantity THEN NOW()
+                    ELSE last_filled_at
+                END,
+                filled_quantity = $2,
+                filled_avg_price = $3,
+                status = $4,
+                modified_at = NOW()
+            WHERE order_id = $1
+            "#,
+        )
+        .bind(order_id)
+        .bind(filled_quantity)
+        .bind(filled_avg_price)
+        .bind(status)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch(db: &PgPool, order_id: Uuid) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT * FROM orders WHERE order_id = $1")
+            .bind(order_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub async fn fetch_by_client_order_id(
+        db: &PgPool,
+        client_order_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT * FROM orders WHERE client_order_id = $1")
+            .bind(client_order_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Most recent orders of the given strategies, or of all strategies when not set.
+    pub async fn fetch_recent(
+        db: &PgPool,
+        strategy_ids: Option<&[Uuid]>,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM orders
+            WHERE $1::uuid[] IS NULL OR strategy_id = ANY($1)
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(strategy_ids)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Take profit orders of the ladder of an entry order.
+    pub async fn fetch_children(
+        db: &PgPool,
+        parent_order_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM orders WHERE parent_order_id = $1 ORDER BY created_at",
+        )
+        .bind(parent_order_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Chunk orders of a time-sliced entry.
+    pub async fn fetch_for_entry(db: &PgPool, entry_id: Uuid) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT * FROM orders WHERE entry_id = $1 ORDER BY created_at")
+            .bind(entry_id)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Latest order with executions of the strategy in every ticker, take profit orders aside.
+    pub async fn fetch_latest_filled(
+        db: &PgPool,
+        strategy_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT DISTINCT ON (ticker) * FROM orders
+            WHERE strategy_id = $1
+                AND broker_order_id IS NOT NULL
+                AND filled_quantity > 0
+                AND parent_order_id IS NULL
+            ORDER BY ticker, created_at DESC
+            "#,
+        )
+        .bind(strategy_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Latest order of the strategy in the ticker with an execution, the one on the broker and
+    /// account holding the position it built.
+    pub async fn fetch_last_filled(
+        db: &PgPool,
+        strategy_id: Uuid,
+        ticker: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM orders
+            WHERE strategy_id = $1 AND ticker = $2 AND filled_quantity > 0
+            ORDER BY last_filled_at DESC NULLS LAST, created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(strategy_id)
+        .bind(ticker)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Orders the broker may still report changes for.
+    pub async fn fetch_open(db: &PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM orders
+            WHERE broker_order_id IS NOT NULL
+                AND status NOT IN ('filled', 'canceled', 'expired', 'rejected', 'replaced')
+            ORDER BY created_at
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+}
+
+impl Listed for OrderRecord {
+    const SOURCE: ListSource = ListSource {
+        table: "orders",
+        id: "order_id",
+        time: "created_at",
+        sort_fields: &["created_at", "modified_at"],
+    };
+
+    fn id(&self) -> Uuid {
+        self.order_id
+    }
+
+    fn sort_key(&self, field: &str) -> DateTime<Utc> {
+        match field {
+            "modified_at" => self.modified_at,
+            _ => self.created_at,
+        }
+    }
+}
+
+/// Fills are the history of the positions of the strategies.
+impl Listed for Fill {
+    const SOURCE: ListSource = ListSource {
+        table: "fills",
+        id: "fill_id",
+        time: "filled_at",
+        sort_fields: &["filled_at"],
+    };
+
+    fn id(&self) -> Uuid {
+        self.fill_id
+    }
+
+    fn sort_key(&self, _field: &str) -> DateTime<Utc> {
+        self.filled_at
+    }
+}
+
+impl Fill {
+    /// Record the fill and add it to the projection of its position, see `projections`.
+    pub async fn insert(db: &PgPool, fill: &Fill) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+        Self::insert_in(&mut tx, fill).await?;
+        tx.commit().await
+    }
+
+    /// `insert` within the transaction of `conn`.
+    pub async fn insert_in(conn: &mut PgConnection, fill: &Fill) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO fills (
+                fill_id,
+                order_id,
+                strategy_id,
+                ticker,
+                side,
+                quantity,
+                price,
+                fee,
+                filled_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(fill.fill_id)
+        .bind(fill.order_id)
+        .bind(fill.strategy_id)
+        .bind(&fill.ticker)
+        .bind(&fill.side)
+        .bind(fill.quantity)
+        .bind(fill.price)
+        .bind(fill.fee)
+        .bind(fill.filled_at)
+        .execute(&mut *conn)
+        .await?;
+        projections::apply(conn, fill).await
+    }
+
+    /// All fills of a strategy in execution order.
+    pub async fn fetch_for_strategy(
+        db: &PgPool,
+        strategy_id: Uuid,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM fills WHERE strategy_id = $1 ORDER BY filled_at, fill_id",
+        )
+        .bind(strategy_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Fills of all strategies executed until `to` (all of them when not set), in execution order,
+    /// read as the stream is polled.
+    pub fn stream_until(
+        db: &PgPool,
+        to: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = Result<Self, sqlx::Error>> + '_ {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM fills
+            WHERE $1::timestamptz IS NULL OR filled_at <= $1
+            ORDER BY filled_at, fill_id
+            "#,
+        )
+        .bind(to)
+        .fetch(db)
+    }
+
+    /// Fills of all strategies executed until `to` (all of them when not set), in execution order.
+    pub async fn fetch_until(
+        db: &PgPool,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM fills
+            WHERE $1::timestamptz IS NULL OR filled_at <= $1
+            ORDER BY filled_at, fill_id
+            "#,
+        )
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Net quantity of `ticker` the fills of a strategy add up to, negative when short. Read
+    /// from the projection of the position, see `projections`.
+    pub async fn position(
+        db: &PgPool,
+        strategy_id: Uuid,
+        ticker: &str,
+    ) -> Result<Decimal, sqlx::Error> {
+        projections::position(db, strategy_id, ticker).await
+    }
+
+    /// Quantity signed by side: positive for buys, negative for sells.
+    pub fn signed_quantity(&self) -> Decimal {
+        if self.side == OrderSide::Sell.as_ref() {
+            -self.quantity
+        } else {
+            self.quantity
+        }
+    }
+}
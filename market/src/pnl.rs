@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{api::error::ApiError, clients::BrokerClient, order::Fill};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct PnlQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DailyPnl {
+    pub date: NaiveDate,
+    /// Realized P&L of positions closed during the day, fees excluded
+    pub realized: Decimal,
+    pub fees: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OpenPosition {
+    pub ticker: String,
+    /// Positive for long and negative for short positions
+    pub quantity: Decimal,
+    pub avg_entry_price: Decimal,
+    pub current_price: Option<Decimal>,
+    pub unrealized: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StrategyPnl {
+    pub strategy_id: Uuid,
+    /// Realized P&L net of fees
+    pub realized: Decimal,
+    pub unrealized: Decimal,
+    pub total: Decimal,
+    pub open_positions: Vec<OpenPosition>,
+    pub daily: Vec<DailyPnl>,
+    /// Statistics of all closed trades of the strategy
+    pub statistics: TradeStatistics,
+}
+
+/// Win rate and payoff ratio of closed trades.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TradeStatistics {
+    pub trades: usize,
+    /// Share of trades closed with a profit, `None` without trades
+    pub win_rate: Option<Decimal>,
+    /// Average profit of winning trades over the average loss of losing trades, `None` without
+    /// either of them
+    pub payoff_ratio: Option<Decimal>,
+}
+
+impl TradeStatistics {
+    /// Statistics of the P&L of closed trades.
+    pub fn from_trades(trades: &[Decimal]) -> Self {
+        let (wins, losses): (Vec<Decimal>, Vec<Decimal>) = trades
+            .iter()
+            .copied()
+            .filter(|pnl| !pnl.is_zero())
+            .partition(|pnl| pnl.is_sign_positive());
+        let average = |pnls: &[Decimal]| pnls.iter().sum::<Decimal>() / Decimal::from(pnls.len());
+
+        Self {
+            trades: trades.len(),
+            win_rate: (!trades.is_empty())
+                .then(|| Decimal::from(wins.len()) / Decimal::from(trades.len())),
+            payoff_ratio: (!wins.is_empty() && !losses.is_empty())
+                .then(|| average(&wins) / average(&losses).abs()),
+        }
+    }
+}
+
+impl StrategyPnl {
+    /// Realized P&L of `date` net of fees.
+    pub fn realized_on(&self, date: NaiveDate) -> Decimal {
+        self.daily
+            .iter()
+            .filter(|day| day.date == date)
+            .map(|day| day.realized - day.fees)
+            .sum()
+    }
+
+    /// Loss of `date`, its realized P&L net of fees and the whole unrealized P&L of open
+    /// positions. Negative for a gain.
+    pub fn loss_on(&self, date: NaiveDate) -> Decimal {
+        -(self.realized_on(date) + self.unrealized)
+    }
+
+    pub fn retain_days(&mut self, query: &PnlQuery) {
+        self.daily.retain(|day| {
+            query.from.is_none_or(|from| day.date >= from)
+                && query.to.is_none_or(|to| day.date <= to)
+        });
+    }
+}
+
+/// P&L of all fills of a strategy, open positions valued at the current broker prices.
+pub async fn strategy_pnl<C: BrokerClient>(
+    db: &PgPool,
+    client: &C,
+    strategy_id: Uuid,
+) -> Result<StrategyPnl, ApiError> {
+    let fills = Fill::fetch_for_strategy(db, strategy_id).await?;
+    let prices = client
+        .get_positions()
+        .await?
+        .iter()
+        .filter_map(|position| {
+            position
+                .current_price()
+                .map(|price| (position.symbol().to_owned(), price.amount))
+        })
+        .collect();
+
+    Ok(compute_pnl(strategy_id, &fills, &prices))
+}
+
+#[derive(Debug)]
+struct Lot {
+    quantity: Decimal,
+    price: Decimal,
+}
+
+/// Open lots per ticker. Closing fills are matched against them in FIFO order.
+#[derive(Debug, Default)]
+pub struct Lots(BTreeMap<String, VecDeque<Lot>>);
+
+impl Lots {
+    /// Apply a fill to the open lots and return P&L it realized, fees excluded.
+    pub fn apply(&mut self, fill: &Fill) -> Decimal {
+        let ticker_lots = self.0.entry(fill.ticker.clone()).or_default();
+        let mut remaining = fill.signed_quantity();
+        let mut realized = Decimal::ZERO;
+
+        while remaining != Decimal::ZERO {
+            let Some(lot) = ticker_lots.front_mut() else {
+                break;
+            };
+            // Fill on the same side as open lots extends the position
+            if lot.quantity.is_sign_positive() == remaining.is_sign_positive() {
+                break;
+            }
+
+            let matched = remaining.abs().min(lot.quantity.abs());
+            let lot_sign = if lot.quantity.is_sign_positive() {
+                Decimal::ONE
+            } else {
+                Decimal::NEGATIVE_ONE
+            };
+            realized += matched * (fill.price - lot.price) * lot_sign;
+
+            lot.quantity -= matched * lot_sign;
+            remaining += matched * lot_sign;
+
+            if lot.quantity == Decimal::ZERO {
+                ticker_lots.pop_front();
+            }
+        }
+
+        if remaining != Decimal::ZERO {
+            ticker_lots.push_back(Lot {
+                quantity: remaining,
+                price: fill.price,
+            });
+        }
+
+        realized
+    }
+
+    /// Whether the fill reduces an open position instead of opening or extending one.
+    fn closes(&self, fill: &Fill) -> bool {
+        self.0
+            .get(&fill.ticker)
+            .and_then(|ticker_lots| ticker_lots.front())
+            .is_some_and(|lot| {
+                lot.quantity.is_sign_positive() != fill.signed_quantity().is_sign_positive()
+            })
+    }
+
+    /// Cost basis of all open lots, longs and shorts alike.
+    pub fn notional(&self) -> Decimal {
+        self.0
+            .values()
+            .flatten()
+            .map(|lot| (lot.quantity * lot.price).abs())
+            .sum()
+    }
+}
+
+/// Compute P&L of a strategy from its fills. Closing fills are matched against open lots of the
+/// same ticker in FIFO order, open lots are valued with `prices` (latest price per ticker).
+pub fn compute_pnl(
+    strategy_id: Uuid,
+    fills: &[Fill],
+    prices: &HashMap<String, Decimal>,
+) -> StrategyPnl {
+    let mut lots = Lots::default();
+    let mut daily: BTreeMap<NaiveDate, DailyPnl> = BTreeMap::new();
+
+    for fill in fills {
+        let date = fill.filled_at.date_naive();
+        let day = daily.entry(date).or_insert_with(|| DailyPnl {
+            date,
+            realized: Decimal::ZERO,
+            fees: Decimal::ZERO,
+        });
+        day.fees += fill.fee;
+        day.realized += lots.apply(fill);
+    }
+
+    let open_positions: Vec<OpenPosition> = lots
+        .0
+        .into_iter()
+        .filter(|(_, ticker_lots)| !ticker_lots.is_empty())
+        .map(|(ticker, ticker_lots)| {
+            let quantity: Decimal = ticker_lots.iter().map(|lot| lot.quantity).sum();
+            let cost: Decimal = ticker_lots.iter().map(|lot| lot.quantity * lot.price).sum();
+            let current_price = prices.get(&ticker).copied();
+            OpenPosition {
+                ticker,
+                quantity,
+                avg_entry_price: cost / quantity,
+                current_price,
+                unrealized: current_price.map_or(Decimal::ZERO, |price| quantity * price - cost),
+            }
+        })
+        .collect();
+
+    let daily: Vec<DailyPnl> = daily.into_values().collect();
+    let realized = daily.iter().map(|day| day.realized - day.fees).sum();
+    let unrealized = open_positions
+        .iter()
+        .map(|position| position.unrealized)
+        .sum();
+
+    StrategyPnl {
+        strategy_id,
+        realized,
+        unrealized,
+        total: realized + unrealized,
+        open_positions,
+        daily,
+        statistics: TradeStatistics::from_trades(
+            &closed_trades(fills)
+                .iter()
+                .map(|trade| trade.pnl)
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+/// Position reduced or closed by the fills of an order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedTrade {
+    pub ticker: String,
+    /// Realized P&L net of the fees of the closing fills
+    pub pnl: Decimal,
+    /// Time of the last closing fill
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Trades closed by the fills, in execution order. Partial fills of an order closing a position
+/// count as a single trade.
+pub fn closed_trades(fills: &[Fill]) -> Vec<ClosedTrade> {
+    let mut lots = Lots::default();
+    let mut trades: Vec<ClosedTrade> = Vec::new();
+    let mut order_trades: HashMap<Uuid, usize> = HashMap::new();
+
+    for fill in fills {
+        let closes = lots.closes(fill);
+        let realized = lots.apply(fill);
+        if !closes {
+            continue;
+        }
+
+        let pnl = realized - fill.fee;
+        match order_trades.get(&fill.order_id) {
+            Some(&index) => {
+                let trade = &mut trades[index];
+                trade.pnl += pnl;
+                trade.closed_at = fill.filled_at;
+            }
+            None => {
+                order_trades.insert(fill.order_id, trades.len());
+                trades.push(ClosedTrade {
+                    ticker: fill.ticker.clone(),
+                    pnl,
+                    closed_at: fill.filled_at,
+                });
+            }
+        }
+    }
+
+    trades
+}
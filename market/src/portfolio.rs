@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use thiserror::Error as ThisError;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::{
+    api::objects::Broker,
+    clients::{BrokerClient, BrokerClientError, Clients},
+    risk::RiskMonitor,
+};
+
+#[derive(Debug, ThisError)]
+pub enum SnapshotError {
+    #[error(transparent)]
+    BrokerClientError(#[from] BrokerClientError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    SerializationError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PortfolioSnapshot {
+    pub snapshot_id: Uuid,
+    pub broker: String,
+    pub equity: Decimal,
+    pub cash: Decimal,
+    /// Currency of the account, `equity` and `cash` are in it
+    pub currency: String,
+    /// Broker positions at the time of the snapshot
+    pub positions: serde_json::Value,
+    pub taken_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EquityCurveQuery {
+    pub broker: Broker,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Keep the last snapshot of every bucket instead of all of them
+    #[serde(default)]
+    pub bucket: Option<EquityBucket>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EquityBucket {
+    Hour,
+    Day,
+}
+
+impl EquityBucket {
+    fn interval(self) -> &'static str {
+        match self {
+            Self::Hour => "1 hour",
+            Self::Day => "1 day",
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct EquitySample {
+    taken_at: DateTime<Utc>,
+    equity: Decimal,
+    cash: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub taken_at: DateTime<Utc>,
+    pub equity: Decimal,
+    pub cash: Decimal,
+    /// Decline from the running equity peak as a fraction of the peak
+    pub drawdown: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EquityCurve {
+    pub broker: String,
+    pub points: Vec<EquityPoint>,
+    pub max_drawdown: Decimal,
+    pub current_drawdown: Decimal,
+}
+
+pub async fn take_snapshot<C: BrokerClient>(
+    db: &PgPool,
+    broker: &Broker,
+    client: &C,
+) -> Result<PortfolioSnapshot, SnapshotError> {
+    let account = client.get_account().await?;
+    let positions = client.get_positions().await?;
+
+    let snapshot = sqlx::query_as::<_, PortfolioSnapshot>(
+        r#"
+        INSERT INTO portfolio_snapshots (
+            snapshot_id, broker, equity, cash, currency, positions, taken_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(broker.as_ref())
+    .bind(account.equity().amount)
+    .bind(account.cash().amount)
+    .bind(account.currency())
+    .bind(serde_json::to_value(&positions)?)
+    .fetch_one(db)
+    .await?;
+
+    Ok(snapshot)
+}
+
+/// Snapshot every broker account each `period` until the process stops. Every snapshot is
+/// evaluated by the risk monitor.
+pub async fn run_snapshots(
+    db: PgPool,
+    clients: Arc<Clients>,
+    risk_monitor: Arc<RiskMonitor>,
+    period: Duration,
+) {
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        match take_snapshot(&db, &Broker::Alpaca, &clients.scheduled_alpaca()).await {
+            Ok(snapshot) => {
+                if let Err(err) = risk_monitor
+                    .evaluate(&Broker::Alpaca, snapshot.equity)
+                    .await
+                {
+                    tracing::error!("Failed to evaluate drawdown, error: {:?}", err);
+                }
+            }
+            Err(err) => tracing::error!("Failed to take portfolio snapshot, error: {:?}", err),
+        }
+    }
+}
+
+/// Highest equity of all snapshots of the broker account.
+pub async fn peak_equity(db: &PgPool, broker: &Broker) -> Result<Option<Decimal>, sqlx::Error> {
+    sqlx::query_scalar("SELECT MAX(equity) FROM portfolio_snapshots WHERE broker = $1")
+        .bind(broker.as_ref())
+        .fetch_one(db)
+        .await
+}
+
+/// Equity of the first snapshot taken on the UTC day `date`.
+pub async fn day_start_equity(
+    db: &PgPool,
+    broker: &Broker,
+    date: NaiveDate,
+) -> Result<Option<Decimal>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT equity FROM portfolio_snapshots
+        WHERE broker = $1 AND taken_at >= $2
+        ORDER BY taken_at
+        LIMIT 1
+        "#,
+    )
+    .bind(broker.as_ref())
+    .bind(date.and_time(chrono::NaiveTime::MIN).and_utc())
+    .fetch_optional(db)
+    .await
+}
+
+/// Equity of the first snapshot of every UTC day between `from` and `to` that has snapshots,
+/// in date order.
+pub async fn day_start_equities(
+    db: &PgPool,
+    broker: &Broker,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<(NaiveDate, Decimal)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT DISTINCT ON ((taken_at AT TIME ZONE 'UTC')::date)
+            (taken_at AT TIME ZONE 'UTC')::date, equity
+        FROM portfolio_snapshots
+        WHERE broker = $1
+            AND ($2::date IS NULL OR (taken_at AT TIME ZONE 'UTC')::date >= $2)
+            AND ($3::date IS NULL OR (taken_at AT TIME ZONE 'UTC')::date <= $3)
+        ORDER BY (taken_at AT TIME ZONE 'UTC')::date, taken_at
+        "#,
+    )
+    .bind(broker.as_ref())
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await
+}
+
+/// Drawdown of every value from the running peak of the series, as a fraction of the peak.
+pub fn drawdowns(equities: &[Decimal]) -> Vec<Decimal> {
+    let mut peak = Decimal::ZERO;
+    equities
+        .iter()
+        .map(|equity| {
+            peak = peak.max(*equity);
+            if peak.is_zero() {
+                Decimal::ZERO
+            } else {
+                (peak - equity) / peak
+            }
+        })
+        .collect()
+}
+
+/// Snapshots of the equity curve. Buckets are rolled up from the hourly continuous aggregate
+/// with the `timescale` feature, from the snapshots themselves otherwise.
+fn equity_query(bucket: Option<EquityBucket>) -> &'static str {
+    if bucket.is_none() {
+        return r#"
+            SELECT taken_at, equity, cash FROM portfolio_snapshots
+            WHERE broker = $1
+                AND ($2::timestamptz IS NULL OR taken_at >= $2)
+                AND ($3::timestamptz IS NULL OR taken_at <= $3)
+            ORDER BY taken_at
+            "#;
+    }
+
+    if cfg!(feature = "timescale") {
+        r#"
+        SELECT MAX(taken_at) AS taken_at, last(equity, taken_at) AS equity, last(cash, taken_at) AS cash
+        FROM portfolio_equity_hourly
+        WHERE broker = $1
+            AND ($2::timestamptz IS NULL OR taken_at >= $2)
+            AND ($3::timestamptz IS NULL OR taken_at <= $3)
+        GROUP BY time_bucket($4::interval, bucket)
+        ORDER BY MAX(taken_at)
+        "#
+    } else {
+        r#"
+        SELECT DISTINCT ON (date_bin($4::interval, taken_at, 'epoch'))
+            taken_at, equity, cash
+        FROM portfolio_snapshots
+        WHERE broker = $1
+            AND ($2::timestamptz IS NULL OR taken_at >= $2)
+            AND ($3::timestamptz IS NULL OR taken_at <= $3)
+        ORDER BY date_bin($4::interval, taken_at, 'epoch'), taken_at DESC
+        "#
+    }
+}
+
+pub async fn equity_curve(
+    db: &PgPool,
+    query: &EquityCurveQuery,
+) -> Result<EquityCurve, sqlx::Error> {
+    let mut snapshots = sqlx::query_as::<_, EquitySample>(equity_query(query.bucket))
+        .bind(query.broker.as_ref())
+        .bind(query.from)
+        .bind(query.to);
+    if let Some(bucket) = query.bucket {
+        snapshots = snapshots.bind(bucket.interval());
+    }
+    let snapshots = snapshots.fetch_all(db).await?;
+
+    let equities: Vec<Decimal> = snapshots.iter().map(|snapshot| snapshot.equity).collect();
+    let drawdowns = drawdowns(&equities);
+
+    Ok(EquityCurve {
+        broker: query.broker.as_ref().to_owned(),
+        max_drawdown: drawdowns.iter().copied().max().unwrap_or_default(),
+        current_drawdown: drawdowns.last().copied().unwrap_or_default(),
+        points: snapshots
+            .into_iter()
+            .zip(drawdowns)
+            .map(|(snapshot, drawdown)| EquityPoint {
+                taken_at: snapshot.taken_at,
+                equity: snapshot.equity,
+                cash: snapshot.cash,
+                drawdown,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+
+use crate::{
+    order::{NewOrder, OrderSide},
+    sizing::FRACTIONAL_DECIMALS,
+    strategy::CurrencyType,
+};
+
+/// Price increment of stocks priced at a dollar or more
+const STOCK_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+/// Price increment of stocks priced below a dollar
+const SUB_DOLLAR_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 4);
+/// Price increment of currency pairs, a tenth of a pip
+const FOREX_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 5);
+/// Price increment of currency pairs quoted in yen, a tenth of a pip
+const YEN_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 3);
+/// Smallest quantity increment of fractional orders and crypto pairs
+const FRACTIONAL_LOT_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, FRACTIONAL_DECIMALS);
+
+/// Increments of the prices and quantities the broker accepts for an asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Increments {
+    /// Limit and stop prices are multiples of it
+    pub tick_size: Decimal,
+    /// Quantities are multiples of it
+    pub lot_size: Decimal,
+}
+
+/// Increments orders are rounded to before they're submitted. Symbols without an entry use the
+/// ones of their asset class.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrecisionConfig {
+    /// Increments by symbol, e.g. `BTC/USD`
+    #[serde(default)]
+    pub symbols: HashMap<String, Increments>,
+}
+
+/// Rounds the prices and quantities of orders to the increments of their asset, so the broker
+/// doesn't reject them for sub-penny prices or invalid crypto quantities.
+#[derive(Debug, Clone, Default)]
+pub struct Precision {
+    config: PrecisionConfig,
+}
+
+impl Precision {
+    pub fn new(config: PrecisionConfig) -> Self {
+        Self { config }
+    }
+
+    /// Increments of `ticker` at `price`. Stocks are quoted in cents from a dollar up and in
+    /// hundredths of a cent below, crypto pairs in as many decimals as orders are stored with and
+    /// currency pairs in tenths of a pip.
+    pub fn increments(
+        &self,
+        ticker: &str,
+        currency_type: CurrencyType,
+        price: Decimal,
+    ) -> Increments {
+        if let Some(increments) = self.config.symbols.get(ticker) {
+            return *increments;
+        }
+        let tick_size = match currency_type {
+            CurrencyType::Stock if price >= Decimal::ONE => STOCK_TICK_SIZE,
+            CurrencyType::Stock => SUB_DOLLAR_TICK_SIZE,
+            CurrencyType::Crypto => FRACTIONAL_LOT_SIZE,
+            CurrencyType::Forex if ticker.ends_with("JPY") => YEN_TICK_SIZE,
+            CurrencyType::Forex => FOREX_TICK_SIZE,
+        };
+        Increments {
+            tick_size,
+            lot_size: self.lot_size(ticker),
+        }
+    }
+
+    /// Quantity increment of `ticker`.
+    pub fn lot_size(&self, ticker: &str) -> Decimal {
+        self.config
+            .symbols
+            .get(ticker)
+            .map_or(FRACTIONAL_LOT_SIZE, |increments| increments.lot_size)
+    }
+
+    /// Round the prices and the quantity of `order` to the increments of its ticker. Limit
+    /// prices are rounded in favor of the order, down for buys and up for sells, so it never
+    /// trades at a worse price than asked for. Stop prices are rounded to the nearest tick and
+    /// quantities down, so the order never grows. Quantities of notional orders are estimates
+    /// the broker doesn't get, they're left as they are, as are options orders.
+    pub fn round_order(&self, order: &mut NewOrder, currency_type: CurrencyType) {
+        if !order.legs.is_empty() {
+            return;
+        }
+
+        if let Some(limit_price) = order.limit_price {
+            let strategy = match order.side {
+                OrderSide::Buy => RoundingStrategy::ToNegativeInfinity,
+                OrderSide::Sell => RoundingStrategy::ToPositiveInfinity,
+            };
+            let increments = self.increments(&order.ticker, currency_type, limit_price);
+            order.limit_price = Some(round_to(limit_price, increments.tick_size, strategy));
+        }
+        if let Some(stop_loss_price) = order.stop_loss_price {
+            order.stop_loss_price =
+                Some(self.round_stop_price(&order.ticker, currency_type, stop_loss_price));
+        }
+        if order.notional.is_none() {
+            let lot_size = self.lot_size(&order.ticker);
+            order.quantity = round_to(order.quantity, lot_size, RoundingStrategy::ToZero);
+        }
+    }
+
+    /// `price` of a stop of `ticker` rounded to the nearest tick.
+    pub fn round_stop_price(
+        &self,
+        ticker: &str,
+        currency_type: CurrencyType,
+        price: Decimal,
+    ) -> Decimal {
+        let increments = self.increments(ticker, currency_type, price);
+        round_to(
+            price,
+            increments.tick_size,
+            RoundingStrategy::MidpointAwayFromZero,
+        )
+    }
+}
+
+/// `value` rounded to a multiple of `increment` with `strategy`, as it is without an increment.
+fn round_to(value: Decimal, increment: Decimal, strategy: RoundingStrategy) -> Decimal {
+    if increment <= Decimal::ZERO {
+        return value;
+    }
+    ((value / increment).round_dp_with_strategy(0, strategy) * increment).normalize()
+}
@@ -0,0 +1,88 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    order::{NewOrder, OrderSide, TimeInForce},
+    risk::RiskViolation,
+};
+
+/// Order of a strategy to preview, priced and checked like the orders of its signals.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OrderPreviewRequest {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub side: OrderSide,
+    /// Required without `notional`
+    #[serde(default)]
+    pub quantity: Option<Decimal>,
+    /// Dollar amount of the order instead of a quantity
+    #[serde(default)]
+    pub notional: Option<Decimal>,
+    /// Limit price, a market order when missing
+    #[serde(default)]
+    pub limit_price: Option<Decimal>,
+    #[serde(default)]
+    pub stop_loss_price: Option<Decimal>,
+    /// Time in force of the strategy when missing
+    #[serde(default)]
+    pub time_in_force: Option<TimeInForce>,
+    /// Extended hours setting of the strategy when missing
+    #[serde(default)]
+    pub extended_hours: Option<bool>,
+}
+
+impl OrderPreviewRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.quantity.is_some() == self.notional.is_some() {
+            return Err("Either a quantity or a notional is required".to_string());
+        }
+        for (field, value) in [
+            ("Quantity", self.quantity),
+            ("Notional", self.notional),
+            ("Limit price", self.limit_price),
+            ("Stop loss price", self.stop_loss_price),
+        ] {
+            if let Some(value) = value.filter(|value| *value <= Decimal::ZERO) {
+                return Err(format!("{field} must be positive, got {value}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Order a preview request would place and what it would cost, nothing is recorded or sent.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OrderPreview {
+    /// Order as it would be submitted, rounded to the increments of its ticker
+    pub order: NewOrder,
+    /// Price the cost is estimated at, the limit price or the latest quote
+    pub price: Decimal,
+    /// Notional of the order, or its quantity at `price`
+    pub estimated_cost: Decimal,
+    /// Buying power of the broker account, missing for users, see `OrderPreview::redact_account`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buying_power: Option<Decimal>,
+    /// Buying power the order takes, the cost of buys and of the part of sells shorting the
+    /// ticker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buying_power_used: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buying_power_after: Option<Decimal>,
+    /// Rules the order violates, it's rejected unless there are none
+    pub risk_violations: Vec<RiskViolation>,
+    /// Request the broker client would send for the order
+    pub broker_request: Value,
+}
+
+impl OrderPreview {
+    /// Drop what the preview tells of the broker account, shared by the strategies of every user:
+    /// its buying power and, through the part of sells going short, its positions.
+    pub fn redact_account(&mut self) {
+        self.buying_power = None;
+        self.buying_power_used = None;
+        self.buying_power_after = None;
+    }
+}
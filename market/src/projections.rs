@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::order::Fill;
+
+/// Position of a strategy in a ticker projected from its fills, which are the log positions are
+/// derived from. Projections are updated with every fill recorded and rebuilt from the log on
+/// startup and after corporate actions changed it, see `rebuild`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct PositionProjection {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    /// Net quantity, negative when short and zero once closed
+    pub quantity: Decimal,
+    /// Fills the position was projected from
+    pub fills: i64,
+    pub last_filled_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Position whose projection didn't match its fills when it was rebuilt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionDrift {
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    /// Quantity of the projection before, `None` when it was missing
+    pub projected: Option<Decimal>,
+    /// Quantity the fills add up to, `None` when there are none
+    pub rebuilt: Option<Decimal>,
+}
+
+/// Add `fill` to the projection of its position, in the transaction recording it.
+pub async fn apply(conn: &mut PgConnection, fill: &Fill) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (
+            strategy_id, ticker, quantity, fills, last_filled_at, updated_at
+        )
+        VALUES ($1, $2, $3, 1, $4, NOW())
+        ON CONFLICT (strategy_id, ticker) DO UPDATE
+        SET quantity = position_projections.quantity + EXCLUDED.quantity,
+            fills = position_projections.fills + 1,
+            last_filled_at = GREATEST(position_projections.last_filled_at, EXCLUDED.last_filled_at),
+            updated_at = NOW()
+        "#,
+    )
+    .bind(fill.strategy_id)
+    .bind(&fill.ticker)
+    .bind(fill.signed_quantity())
+    .bind(fill.filled_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Project the positions in `ticker`, every ticker when `None`, from the fills again, replacing
+/// their projections. Returns the projections made.
+pub async fn project(
+    conn: &mut PgConnection,
+    ticker: Option<&str>,
+) -> Result<Vec<PositionProjection>, sqlx::Error> {
+    sqlx::query("DELETE FROM position_projections WHERE $1::text IS NULL OR ticker = $1")
+        .bind(ticker)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query_as::<_, PositionProjection>(
+        r#"
+        INSERT INTO position_projections (
+            strategy_id, ticker, quantity, fills, last_filled_at, updated_at
+        )
+        SELECT
+            strategy_id,
+            ticker,
+            SUM(CASE WHEN side = 'sell' THEN -quantity ELSE quantity END),
+            COUNT(*),
+            MAX(filled_at),
+            NOW()
+        FROM fills
+        WHERE $1::text IS NULL OR ticker = $1
+        GROUP BY strategy_id, ticker
+        RETURNING *
+        "#,
+    )
+    .bind(ticker)
+    .fetch_all(conn)
+    .await
+}
+
+/// Rebuild every projection from the fills, so positions don't depend on projections made
+/// before, e.g. by an instance which crashed. Returns the positions whose projections differed.
+pub async fn rebuild(db: &PgPool) -> Result<Vec<PositionDrift>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    // NOTE: fills recorded meanwhile wait for the rebuild to add them to their projection, the
+    // rebuild waits for fills whose projection was updated already
+    sqlx::query("LOCK TABLE position_projections IN EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut projected: HashMap<(Uuid, String), Decimal> =
+        sqlx::query_as::<_, (Uuid, String, Decimal)>(
+            "SELECT strategy_id, ticker, quantity FROM position_projections",
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|(strategy_id, ticker, quantity)| ((strategy_id, ticker), quantity))
+        .collect();
+    let rebuilt = project(&mut tx, None).await?;
+    tx.commit().await?;
+
+    let mut drifts: Vec<PositionDrift> = rebuilt
+        .into_iter()
+        .filter_map(|projection| {
+            let key = (projection.strategy_id, projection.ticker);
+            let before = projected.remove(&key);
+            (before != Some(projection.quantity)).then_some(PositionDrift {
+                strategy_id: key.0,
+                ticker: key.1,
+                projected: before,
+                rebuilt: Some(projection.quantity),
+            })
+        })
+        .collect();
+    drifts.extend(
+        projected
+            .into_iter()
+            .map(|((strategy_id, ticker), quantity)| PositionDrift {
+                strategy_id,
+                ticker,
+                projected: Some(quantity),
+                rebuilt: None,
+            }),
+    );
+    drifts.sort_by(|a, b| (a.strategy_id, &a.ticker).cmp(&(b.strategy_id, &b.ticker)));
+
+    Ok(drifts)
+}
+
+/// Projected position of a strategy in `ticker`, zero without one.
+pub async fn position(
+    db: &PgPool,
+    strategy_id: Uuid,
+    ticker: &str,
+) -> Result<Decimal, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE((
+            SELECT quantity FROM position_projections WHERE strategy_id = $1 AND ticker = $2
+        ), 0)
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(ticker)
+    .fetch_one(db)
+    .await
+}
+
+/// Open projected positions of a strategy, by ticker.
+pub async fn fetch_open_for_strategy(
+    db: &PgPool,
+    strategy_id: Uuid,
+) -> Result<Vec<PositionProjection>, sqlx::Error> {
+    sqlx::query_as::<_, PositionProjection>(
+        "SELECT * FROM position_projections WHERE strategy_id = $1 AND quantity <> 0 ORDER BY \
+         ticker",
+    )
+    .bind(strategy_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Projected positions of every strategy, including closed ones.
+pub async fn fetch_all(db: &PgPool) -> Result<Vec<PositionProjection>, sqlx::Error> {
+    sqlx::query_as::<_, PositionProjection>(
+        "SELECT * FROM position_projections ORDER BY strategy_id, ticker",
+    )
+    .fetch_all(db)
+    .await
+}
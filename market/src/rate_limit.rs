@@ -0,0 +1,231 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::time::{Duration, Instant};
+
+/// Token bucket limits of a route group. Buckets hold up to `capacity` requests and regain
+/// `refill_per_second` of them every second.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+/// Group of the unauthenticated routes, limited per client address.
+pub const PUBLIC: &str = "public";
+
+/// Limit of the public routes when none is configured.
+const DEFAULT_PUBLIC_LIMIT: RateLimit = RateLimit {
+    capacity: 5,
+    refill_per_second: 0.1,
+};
+
+/// Route group a request path belongs to. Limits are configured per group.
+pub fn route_group(path: &str) -> &'static str {
+    // NOTE: checking the usage doesn't take from the broker calls it's meant to plan
+    if path == "/account/usage" {
+        return "analytics";
+    }
+    let segment = path
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    match segment {
+        "account" | "activities" | "asset" | "assets" | "control" | "marketdata" | "order"
+        | "orders" | "position" | "positions" | "rebalance" | "reconcile" => "broker",
+        "broker-cache" | "dead-letters" | "feature-flags" => "admin",
+        // NOTE: the OpenAPI document and the dashboard page are the same for every caller
+        "public" | "docs" | "ui" => PUBLIC,
+        _ => "analytics",
+    }
+}
+
+/// Remaining requests of the bucket of an API key and route group, also reported in the
+/// `x-ratelimit-*` headers of the responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Quota {
+    /// Requests the bucket holds at most
+    pub limit: u32,
+    /// Requests which can be made right away
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset: u64,
+}
+
+/// Request of an API key rejected as its bucket is empty.
+#[derive(Debug, Clone, Copy)]
+pub struct Throttled {
+    /// Time until the next token is available
+    pub retry_after: Duration,
+    pub quota: Quota,
+}
+
+/// Requests of an API key to a route group since startup, or since its bucket was dropped after
+/// being idle until full.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GroupUsage {
+    pub group: String,
+    pub requests: u64,
+    /// Requests rejected as the limit of the group was reached
+    pub throttled: u64,
+    /// Remaining quota, `None` for groups without limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<Quota>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    requests: u64,
+    throttled: u64,
+}
+
+impl Bucket {
+    fn new(limit: Option<&RateLimit>, now: Instant) -> Self {
+        Self {
+            tokens: limit.map_or(0.0, |limit| f64::from(limit.capacity)),
+            refilled_at: now,
+            requests: 0,
+            throttled: 0,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * limit.refill_per_second).min(f64::from(limit.capacity));
+        self.refilled_at = now;
+    }
+
+    /// Whether the bucket is full again at `now` without taking a token meanwhile.
+    fn is_full_at(&self, limit: &RateLimit, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens + elapsed * limit.refill_per_second >= f64::from(limit.capacity)
+    }
+
+    fn quota(&self, limit: &RateLimit) -> Quota {
+        let missing = f64::from(limit.capacity) - self.tokens;
+        let reset = if missing <= 0.0 {
+            0
+        } else if limit.refill_per_second <= 0.0 {
+            u64::MAX
+        } else {
+            (missing / limit.refill_per_second).ceil() as u64
+        };
+        Quota {
+            limit: limit.capacity,
+            remaining: self.tokens.floor() as u32,
+            reset,
+        }
+    }
+}
+
+/// Rate limiter keyed on API key and route group, also counting the requests of every key.
+/// Groups without configured limits are not limited, except for the public group which always
+/// is. Keys are only held as their SHA-256 digest, and buckets of limited groups are dropped once
+/// they're full again, so idle keys and client addresses don't pile up.
+pub struct RateLimiter {
+    limits: HashMap<String, RateLimit>,
+    buckets: Mutex<HashMap<(String, &'static str), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(mut limits: HashMap<String, RateLimit>) -> Self {
+        limits
+            .entry(PUBLIC.to_string())
+            .or_insert(DEFAULT_PUBLIC_LIMIT);
+
+        Self {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a request of `api_key` to `group`, returning the remaining quota of
+    /// limited groups. When the bucket is empty, the request is throttled until the next token
+    /// is available.
+    pub fn check(&self, api_key: &str, group: &'static str) -> Result<Option<Quota>, Throttled> {
+        let limit = self.limits.get(group);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let key = (fingerprint(api_key), group);
+        if !buckets.contains_key(&key) {
+            // NOTE: a full bucket is the same as a new one, apart from the request counts
+            buckets.retain(|(_, group), bucket| {
+                self.limits
+                    .get(*group)
+                    .is_none_or(|limit| !bucket.is_full_at(limit, now))
+            });
+        }
+        let bucket = buckets
+            .entry(key)
+            .or_insert_with(|| Bucket::new(limit, now));
+        bucket.requests += 1;
+
+        let Some(limit) = limit else {
+            return Ok(None);
+        };
+        bucket.refill(limit, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(Some(bucket.quota(limit)));
+        }
+
+        bucket.throttled += 1;
+        let retry_after = if limit.refill_per_second <= 0.0 {
+            Duration::MAX
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / limit.refill_per_second)
+        };
+        Err(Throttled {
+            retry_after,
+            quota: bucket.quota(limit),
+        })
+    }
+
+    /// Usage of `api_key` of every route group it made requests to or which is limited, by
+    /// group. The public group is left out as it's limited per client address.
+    pub fn usage(&self, api_key: &str) -> Vec<GroupUsage> {
+        let now = Instant::now();
+        let api_key = fingerprint(api_key);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        let mut usage: Vec<GroupUsage> = buckets
+            .iter_mut()
+            .filter(|((key, group), _)| *key == api_key && *group != PUBLIC)
+            .map(|((_, group), bucket)| {
+                let limit = self.limits.get(*group);
+                if let Some(limit) = limit {
+                    bucket.refill(limit, now);
+                }
+                GroupUsage {
+                    group: (*group).to_owned(),
+                    requests: bucket.requests,
+                    throttled: bucket.throttled,
+                    quota: limit.map(|limit| bucket.quota(limit)),
+                }
+            })
+            .collect();
+
+        for (group, limit) in &self.limits {
+            if group != PUBLIC && !usage.iter().any(|usage| &usage.group == group) {
+                usage.push(GroupUsage {
+                    group: group.clone(),
+                    requests: 0,
+                    throttled: 0,
+                    quota: Some(Bucket::new(Some(limit), now).quota(limit)),
+                });
+            }
+        }
+        usage.sort_by(|a, b| a.group.cmp(&b.group));
+        usage
+    }
+}
+
+fn fingerprint(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    order::OrderSide,
+    sizing::{FRACTIONAL_DECIMALS, MIN_CRYPTO_NOTIONAL},
+    strategy::{CurrencyType, Strategy},
+};
+
+/// Status of rebalance orders of a preview, which are checked but neither recorded nor sent.
+pub const PLANNED_STATUS: &str = "planned";
+/// Status of rebalance orders rejected before they were recorded, e.g. by a risk limit.
+pub const REJECTED_STATUS: &str = "rejected";
+
+/// Target allocation of the account of a strategy.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RebalanceRequest {
+    pub strategy_id: Uuid,
+    /// Share of the account equity per symbol. Symbols held but missing here are left as they
+    /// are, a weight of 0 closes the position.
+    pub weights: HashMap<String, Decimal>,
+    /// Preview the orders without recording or submitting them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl RebalanceRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.weights.is_empty() {
+            return Err("At least one target weight is required".to_string());
+        }
+        if let Some((ticker, _)) = self
+            .weights
+            .iter()
+            .find(|(_, weight)| weight.is_sign_negative())
+        {
+            return Err(format!("Weight of {ticker} must not be negative"));
+        }
+        let total: Decimal = self.weights.values().sum();
+        if total > Decimal::ONE {
+            return Err(format!(
+                "Weights must not add up to more than 1, got {total}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RebalanceOrder {
+    pub ticker: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    /// Price the target quantity was derived from
+    pub price: Decimal,
+    pub current_quantity: Decimal,
+    pub target_quantity: Decimal,
+    /// Local order id, once the order is recorded
+    pub order_id: Option<Uuid>,
+    /// `planned` in previews, the order status otherwise
+    pub status: String,
+    /// Why the order was rejected
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RebalanceReport {
+    pub strategy_id: Uuid,
+    pub equity: Decimal,
+    pub dry_run: bool,
+    pub orders: Vec<RebalanceOrder>,
+}
+
+/// Orders moving `positions` to the target `weights` of `equity`, sells first so they free the
+/// buying power of the buys. Stocks are traded in whole shares, deltas rounding down to nothing
+/// and crypto orders below the minimum notional are left out.
+///
+/// NOTE: every weighted symbol must have a price
+pub fn plan(
+    strategy: &Strategy,
+    equity: Decimal,
+    weights: &HashMap<String, Decimal>,
+    positions: &HashMap<String, Decimal>,
+    prices: &HashMap<String, Decimal>,
+) -> Vec<RebalanceOrder> {
+    let mut orders: Vec<RebalanceOrder> = weights
+        .iter()
+        .filter_map(|(ticker, weight)| {
+            let price = *prices.get(ticker)?;
+            let current_quantity = positions.get(ticker).copied().unwrap_or_default();
+            let target_quantity = round_quantity(strategy, (equity * weight).checked_div(price)?);
+            let delta = target_quantity - current_quantity;
+            let quantity = round_quantity(strategy, delta.abs());
+            if quantity.is_zero() {
+                return None;
+            }
+            if strategy.currency_type == CurrencyType::Crypto
+                && quantity * price < MIN_CRYPTO_NOTIONAL
+            {
+                return None;
+            }
+
+            Some(RebalanceOrder {
+                ticker: ticker.clone(),
+                side: if delta.is_sign_positive() {
+                    OrderSide::Buy
+                } else {
+                    OrderSide::Sell
+                },
+                quantity,
+                price,
+                current_quantity,
+                target_quantity,
+                order_id: None,
+                status: PLANNED_STATUS.to_string(),
+                error: None,
+            })
+        })
+        .collect();
+
+    orders.sort_by(|a, b| {
+        (a.side == OrderSide::Buy, &a.ticker).cmp(&(b.side == OrderSide::Buy, &b.ticker))
+    });
+    orders
+}
+
+fn round_quantity(strategy: &Strategy, quantity: Decimal) -> Decimal {
+    match strategy.currency_type {
+        CurrencyType::Stock | CurrencyType::Forex => quantity.floor(),
+        CurrencyType::Crypto => {
+            quantity.round_dp_with_strategy(FRACTIONAL_DECIMALS, RoundingStrategy::ToZero)
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use std::{collections::VecDeque, path::PathBuf, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{types::Json, FromRow, PgPool};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Broker, Clock, Order, Position},
+    clients::{alpaca_order_request, BrokerClient, BrokerClientError},
+    order::NewOrder,
+};
+
+/// Fields redacted from recorded requests and responses. Matched as a substring of the field name.
+pub(crate) const SENSITIVE_FIELDS: [&str; 6] = [
+    "account_number",
+    "key",
+    "secret",
+    "token",
+    "password",
+    "passphrase",
+];
+pub(crate) const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RecordingSink {
+    /// `broker_interactions` table
+    Database,
+    /// JSON lines file per day in `dir`, readable by `PlaybackClient::from_file`
+    File { dir: PathBuf },
+}
+
+/// Recording of broker interactions made by the core. Recording starts with the app and stops
+/// after `window` seconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recording {
+    pub sink: RecordingSink,
+    pub window: u64,
+}
+
+/// Broker call with its sanitized request and response.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Interaction {
+    pub interaction_id: Uuid,
+    pub broker: String,
+    pub operation: String,
+    pub request: Json<Value>,
+    pub response: Option<Json<Value>>,
+    pub error: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Replace values of sensitive fields, see `SENSITIVE_FIELDS`.
+pub fn sanitize(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                let name = name.to_lowercase();
+                if SENSITIVE_FIELDS.iter().any(|field| name.contains(field)) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    sanitize(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(sanitize),
+        _ => {}
+    }
+}
+
+pub struct BrokerRecorder {
+    db: PgPool,
+    sink: RecordingSink,
+    until: Instant,
+}
+
+impl BrokerRecorder {
+    pub fn new(db: PgPool, recording: Recording) -> Self {
+        Self {
+            db,
+            sink: recording.sink,
+            until: Instant::now() + Duration::from_secs(recording.window),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        Instant::now() < self.until
+    }
+
+    /// Store a broker call. Failures are logged, recording never fails the call itself.
+    pub async fn record<T: Serialize>(
+        &self,
+        broker: &Broker,
+        operation: &str,
+        mut request: Value,
+        result: &Result<T, BrokerClientError>,
+    ) {
+        if !self.is_recording() {
+            return;
+        }
+
+        sanitize(&mut request);
+        let (response, error) = match result {
+            Ok(response) => {
+                let mut response = serde_json::to_value(response).unwrap_or_default();
+                sanitize(&mut response);
+                (Some(Json(response)), None)
+            }
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let interaction = Interaction {
+            interaction_id: uuid7::uuid7().into(),
+            broker: broker.as_ref().to_owned(),
+            operation: operation.to_owned(),
+            request: Json(request),
+            response,
+            error,
+            recorded_at: Utc::now(),
+        };
+
+        if let Err(err) = self.store(&interaction).await {
+            tracing::error!("Failed to record broker interaction, error: {:?}", err);
+        }
+    }
+
+    async fn store(&self, interaction: &Interaction) -> Result<(), anyhow::Error> {
+        match &self.sink {
+            RecordingSink::Database => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO broker_interactions (interaction_id, broker, operation, request, response, error, recorded_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                )
+                .bind(interaction.interaction_id)
+                .bind(&interaction.broker)
+                .bind(&interaction.operation)
+                .bind(&interaction.request)
+                .bind(&interaction.response)
+                .bind(&interaction.error)
+                .bind(interaction.recorded_at)
+                .execute(&self.db)
+                .await?;
+            }
+            RecordingSink::File { dir } => {
+                let path = dir.join(format!(
+                    "broker-{}.jsonl",
+                    interaction.recorded_at.format("%Y-%m-%d")
+                ));
+                let mut line = serde_json::to_vec(interaction)?;
+                line.push(b'\n');
+
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(&line).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Broker client recording every call with the recorder, if there is one.
+pub struct RecordingClient<'a, C> {
+    inner: C,
+    broker: Broker,
+    recorder: Option<&'a BrokerRecorder>,
+}
+
+impl<'a, C: BrokerClient> RecordingClient<'a, C> {
+    pub fn new(inner: C, broker: Broker, recorder: Option<&'a BrokerRecorder>) -> Self {
+        Self {
+            inner,
+            broker,
+            recorder,
+        }
+    }
+
+    async fn record<T: Serialize>(
+        &self,
+        operation: &str,
+        request: Value,
+        result: &Result<T, BrokerClientError>,
+    ) {
+        if let Some(recorder) = self.recorder {
+            recorder
+                .record(&self.broker, operation, request, result)
+                .await;
+        }
+    }
+}
+
+/// Request as recorded, requests which can't be serialized are recorded as `null`.
+fn request_value<T: Serialize>(request: &T) -> Value {
+    serde_json::to_value(request).unwrap_or_default()
+}
+
+#[axum::async_trait]
+impl<C: BrokerClient> BrokerClient for RecordingClient<'_, C> {
+    type ActivitiesRequest = C::ActivitiesRequest;
+    type NewOrderRequest = C::NewOrderRequest;
+    type OrdersRequest = C::OrdersRequest;
+    type OrderUdateRequest = C::OrderUdateRequest;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        self.inner.order_request(new_order)
+    }
+
+    fn supports_options(&self) -> bool {
+        self.inner.supports_options()
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        let result = self.inner.get_account().await;
+        self.record("get_account", Value::Null, &result).await;
+        result
+    }
+
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        let request = request_value(&activities_req);
+        let result = self.inner.get_activities(activities_req).await;
+        self.record("get_activities", request, &result).await;
+        result
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        let request = request_value(&symbol);
+        let result = self.inner.get_asset(symbol).await;
+        self.record("get_asset", request, &result).await;
+        result
+    }
+
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        let request = request_value(&class);
+        let result = self.inner.get_assets(class).await;
+        self.record("get_assets", request, &result).await;
+        result
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        let result = self.inner.get_clock().await;
+        self.record("get_clock", Value::Null, &result).await;
+        result
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        let request = request_value(&symbol);
+        let result = self.inner.get_position(symbol).await;
+        self.record("get_position", request, &result).await;
+        result
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        let result = self.inner.get_positions().await;
+        self.record("get_positions", Value::Null, &result).await;
+        result
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        let request = request_value(&symbol);
+        let result = self.inner.delete_position(symbol).await;
+        self.record("delete_position", request, &result).await;
+        result
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        let request = request_value(&client_id);
+        let result = self.inner.get_order_by_client_id(client_id).await;
+        self.record("get_order_by_client_id", request, &result)
+            .await;
+        result
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        let request = request_value(&orders_req);
+        let result = self.inner.get_orders(orders_req).await;
+        self.record("get_orders", request, &result).await;
+        result
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let request = request_value(&new_order_req);
+        let result = self.inner.create_order(new_order_req).await;
+        self.record("create_order", request, &result).await;
+        result
+    }
+
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let request = request_value(&(order_id, &update_req));
+        let result = self.inner.update_order(order_id, update_req).await;
+        self.record("update_order", request, &result).await;
+        result
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        let request = request_value(&order_id);
+        let result = self.inner.delete_order(order_id).await;
+        self.record("delete_order", request, &result).await;
+        result
+    }
+}
+
+/// Alpaca client answering with recorded interactions instead of calling the broker. Every call
+/// takes the oldest interaction recorded for its operation.
+pub struct PlaybackClient {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl PlaybackClient {
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        Self {
+            interactions: Mutex::new(interactions.into()),
+        }
+    }
+
+    /// Interactions of a file written by the file sink.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, anyhow::Error> {
+        let interactions = std::fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Interaction>, _>>()?;
+
+        Ok(Self::new(interactions))
+    }
+
+    fn next<T: DeserializeOwned>(&self, operation: &str) -> Result<T, BrokerClientError> {
+        let interaction = {
+            let mut interactions = self
+                .interactions
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            interactions
+                .iter()
+                .position(|interaction| interaction.operation == operation)
+                .and_then(|index| interactions.remove(index))
+        };
+        let Some(interaction) = interaction else {
+            return Err(BrokerClientError::PlaybackError(format!(
+                "No recorded {operation} interaction left"
+            )));
+        };
+
+        if let Some(error) = interaction.error {
+            return Err(BrokerClientError::AlpacaError(error));
+        }
+        let response = interaction.response.map(|Json(response)| response);
+        serde_json::from_value(response.unwrap_or_default()).map_err(|err| {
+            BrokerClientError::PlaybackError(format!(
+                "Invalid recorded {operation} response: {err}"
+            ))
+        })
+    }
+}
+
+#[axum::async_trait]
+impl BrokerClient for PlaybackClient {
+    type ActivitiesRequest = apca::api::v2::account_activities::ActivityReq;
+    type NewOrderRequest = apca::api::v2::order::OrderReq;
+    type OrdersRequest = apca::api::v2::orders::OrdersReq;
+    type OrderUdateRequest = apca::api::v2::order::ChangeReq;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        alpaca_order_request(new_order)
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        self.next("get_account")
+    }
+
+    async fn get_activities(
+        &self,
+        _activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        self.next("get_activities")
+    }
+
+    async fn get_asset(&self, _symbol: String) -> Result<Asset, BrokerClientError> {
+        self.next("get_asset")
+    }
+
+    async fn get_assets(&self, _class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        self.next("get_assets")
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        self.next("get_clock")
+    }
+
+    async fn get_position(&self, _symbol: String) -> Result<Position, BrokerClientError> {
+        self.next("get_position")
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        self.next("get_positions")
+    }
+
+    async fn delete_position(&self, _symbol: String) -> Result<Order, BrokerClientError> {
+        self.next("delete_position")
+    }
+
+    async fn get_order_by_client_id(&self, _client_id: String) -> Result<Order, BrokerClientError> {
+        self.next("get_order_by_client_id")
+    }
+
+    async fn get_orders(
+        &self,
+        _orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        self.next("get_orders")
+    }
+
+    async fn create_order(
+        &self,
+        _new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.next("create_order")
+    }
+
+    async fn update_order(
+        &self,
+        _order_id: Uuid,
+        _update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.next("update_order")
+    }
+
+    async fn delete_order(&self, _order_id: Uuid) -> Result<(), BrokerClientError> {
+        self.next("delete_order")
+    }
+}
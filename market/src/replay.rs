@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use futures::{Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{api::alert::WebhookAlertData, simulation::AlertSimulation, App};
+
+/// Persisted alerts to replay, by the time they were received.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ReplayQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub strategy_id: Option<Uuid>,
+}
+
+/// Alert of the `alerts` table stored with its payload.
+#[derive(Debug, Clone, FromRow)]
+pub struct StoredAlert {
+    pub alert_id: Uuid,
+    pub received_at: DateTime<Utc>,
+    pub payload: Json<WebhookAlertData>,
+}
+
+/// Outcome of a replayed alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayedAlert {
+    pub alert_id: Uuid,
+    pub received_at: DateTime<Utc>,
+    pub simulation: Option<AlertSimulation>,
+    /// Why the alert failed processing, e.g. as its strategy is no longer configured
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySummary {
+    pub alerts: usize,
+    /// Alerts placing an order
+    pub orders: usize,
+    /// Alerts whose order violates risk rules
+    pub rejected: usize,
+    /// Alerts placing no order, see `AlertSimulation::ignored`
+    pub ignored: usize,
+    pub errors: usize,
+}
+
+impl ReplaySummary {
+    fn add(&mut self, replayed: &ReplayedAlert) {
+        self.alerts += 1;
+        match &replayed.simulation {
+            None => self.errors += 1,
+            Some(simulation) if simulation.ignored.is_some() => self.ignored += 1,
+            Some(simulation) if !simulation.risk_violations.is_empty() => self.rejected += 1,
+            Some(_) => self.orders += 1,
+        }
+    }
+}
+
+/// Stored alerts of the query, in the order they were received. Alerts stored before their
+/// payloads were kept are left out.
+pub fn stored_alerts<'a>(
+    db: &'a PgPool,
+    query: &'a ReplayQuery,
+) -> impl Stream<Item = Result<StoredAlert, sqlx::Error>> + 'a {
+    sqlx::query_as::<_, StoredAlert>(
+        r#"
+        SELECT alert_id, created_at AS received_at, payload
+        FROM alerts
+        WHERE payload IS NOT NULL
+            AND ($1::timestamptz IS NULL OR created_at >= $1)
+            AND ($2::timestamptz IS NULL OR created_at < $2)
+            AND ($3::uuid IS NULL OR strategy_id = $3)
+        ORDER BY created_at, alert_id
+        "#,
+    )
+    .bind(query.from)
+    .bind(query.to)
+    .bind(query.strategy_id)
+    .fetch(db)
+}
+
+/// Stream the stored alerts of the query through the current processing of the app as dry runs,
+/// see `App::replay_alert`. Every outcome is handed to `on_alert` as soon as it's known.
+/// Alerts failing processing are reported and the replay goes on.
+pub async fn replay(
+    app: &App,
+    query: &ReplayQuery,
+    mut on_alert: impl FnMut(&ReplayedAlert),
+) -> Result<ReplaySummary, sqlx::Error> {
+    let mut summary = ReplaySummary::default();
+    let mut alerts = Box::pin(stored_alerts(&app.db, query));
+    while let Some(alert) = alerts.try_next().await? {
+        let (simulation, error) = match app.replay_alert(alert.payload.0).await {
+            Ok(simulation) => (Some(simulation), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let replayed = ReplayedAlert {
+            alert_id: alert.alert_id,
+            received_at: alert.received_at,
+            simulation,
+            error,
+        };
+        summary.add(&replayed);
+        on_alert(&replayed);
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgPool};
+use thiserror::Error as ThisError;
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Money, USD},
+    app_config::{AppConfig, Reports},
+    dead_letters::DeadLetter,
+    fx::{FxError, FxRates},
+    notifications::Notifier,
+    order::Fill,
+    pnl::{self, closed_trades},
+    risk::RiskViolation,
+    App,
+};
+
+#[derive(Debug, ThisError)]
+pub enum ReportError {
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    FxError(#[from] FxError),
+}
+
+/// Activity of a strategy during the day of a report.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StrategySummary {
+    pub strategy_id: Uuid,
+    pub name: String,
+    pub fills: usize,
+    /// Trades closed during the day
+    pub trades: usize,
+    /// Realized P&L net of fees
+    pub realized: Decimal,
+    pub fees: Decimal,
+    /// Currency of the account of the strategy, `realized` and `fees` are in it
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+/// Summary of a UTC day, compiled at its end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyReport {
+    pub date: NaiveDate,
+    /// Strategies with fills during the day
+    pub strategies: Vec<StrategySummary>,
+    /// Realized P&L of all strategies net of fees, converted to `currency`
+    pub realized: Decimal,
+    /// Base currency of the exchange rates, see `FxConfig::base`
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub risk_violations: Vec<RiskViolation>,
+    /// Alerts whose trade signal failed processing
+    pub errors: Vec<DeadLetter>,
+    pub compiled_at: DateTime<Utc>,
+}
+
+impl DailyReport {
+    /// Text of the notification sent for the report.
+    pub fn message(&self) -> String {
+        let trades: usize = self.strategies.iter().map(|summary| summary.trades).sum();
+        let mut message = format!(
+            "Daily report {}: realized P&L {} {} over {} trades, {} risk violations, {} failed \
+             signals",
+            self.date,
+            self.realized,
+            self.currency,
+            trades,
+            self.risk_violations.len(),
+            self.errors.len()
+        );
+        for summary in &self.strategies {
+            let _ = write!(
+                message,
+                "\n{}: {} trades, realized P&L {} {}",
+                summary.name, summary.trades, summary.realized, summary.currency
+            );
+        }
+        message
+    }
+}
+
+fn default_currency() -> String {
+    USD.to_owned()
+}
+
+/// Report of `date` from the fills of the configured strategies, the risk violations and the
+/// dead letters of the day. P&L of strategies trading in other currencies is converted to the
+/// base currency of `fx` before it's added up.
+pub async fn compile(
+    db: &PgPool,
+    config: &AppConfig,
+    fx: &FxRates,
+    date: NaiveDate,
+) -> Result<DailyReport, ReportError> {
+    let from = date.and_time(NaiveTime::MIN).and_utc();
+    let to = from + Duration::days(1);
+
+    // NOTE: earlier fills are needed to match the trades closed during the day
+    let mut fills: HashMap<Uuid, Vec<Fill>> = HashMap::new();
+    for fill in Fill::fetch_until(db, Some(to)).await? {
+        if fill.filled_at < to {
+            fills.entry(fill.strategy_id).or_default().push(fill);
+        }
+    }
+
+    let summaries: Vec<StrategySummary> = config
+        .strategies
+        .iter()
+        .filter_map(|strategy| {
+            let fills = fills.get(&strategy.id)?;
+            let day_fills = fills
+                .iter()
+                .filter(|fill| fill.filled_at.date_naive() == date)
+                .count();
+            if day_fills == 0 {
+                return None;
+            }
+
+            let pnl = pnl::compute_pnl(strategy.id, fills, &HashMap::new());
+            Some(StrategySummary {
+                strategy_id: strategy.id,
+                name: strategy.name.clone(),
+                fills: day_fills,
+                trades: closed_trades(fills)
+                    .iter()
+                    .filter(|trade| trade.closed_at.date_naive() == date)
+                    .count(),
+                realized: pnl.realized_on(date),
+                fees: pnl
+                    .daily
+                    .iter()
+                    .filter(|day| day.date == date)
+                    .map(|day| day.fees)
+                    .sum(),
+                currency: config.currency(strategy).to_owned(),
+            })
+        })
+        .collect();
+
+    let mut realized = Decimal::ZERO;
+    for summary in &summaries {
+        realized += fx
+            .convert(&Money::new(summary.realized, summary.currency.as_str()))
+            .await?
+            .amount;
+    }
+
+    Ok(DailyReport {
+        date,
+        realized,
+        currency: fx.base().to_owned(),
+        strategies: summaries,
+        risk_violations: RiskViolation::fetch_between(db, from, to).await?,
+        errors: DeadLetter::fetch_between(db, from, to).await?,
+        compiled_at: Utc::now(),
+    })
+}
+
+/// Store the report, replacing an earlier report of its day.
+pub async fn save(db: &PgPool, report: &DailyReport) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO daily_reports (report_date, report, created_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (report_date) DO UPDATE
+        SET report = EXCLUDED.report, created_at = EXCLUDED.created_at
+        "#,
+    )
+    .bind(report.date)
+    .bind(Json(report))
+    .bind(report.compiled_at)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fetch(db: &PgPool, date: NaiveDate) -> Result<Option<DailyReport>, sqlx::Error> {
+    let report: Option<Json<DailyReport>> =
+        sqlx::query_scalar("SELECT report FROM daily_reports WHERE report_date = $1")
+            .bind(date)
+            .fetch_optional(db)
+            .await?;
+
+    Ok(report.map(|Json(report)| report))
+}
+
+/// First time the report is compiled at after `now`.
+pub fn next_run(now: DateTime<Utc>, time: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive().and_time(time).and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Compile, store and send the report of every day at the configured time until the process
+/// stops.
+pub async fn run_daily_reports(app: Arc<App>, config: Reports) {
+    let notifier = Notifier::new(app.config.notifications.clone());
+
+    loop {
+        let now = Utc::now();
+        let next = next_run(now, config.time);
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        let date = next.date_naive();
+        let report = match compile(&app.db, &app.config, &app.fx, date).await {
+            Ok(report) => report,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to compile daily report of {}, error: {:?}",
+                    date,
+                    err
+                );
+                continue;
+            }
+        };
+        match save(&app.db, &report).await {
+            Ok(()) => tracing::info!("Daily report of {} compiled", date),
+            Err(err) => {
+                tracing::error!("Failed to store daily report of {}, error: {:?}", date, err)
+            }
+        }
+
+        if !config.channels.is_empty() {
+            notifier.notify(&config.channels, &report.message()).await;
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+
+use crate::strategy::Strategy;
+
+/// Longest delay between two attempts, however many retries preceded them
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff between order submission attempts. Retry `n` waits a random delay between
+/// half and all of `base * 2^(n - 1)`, so orders failing together don't retry in lockstep.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    base: Duration,
+    max_retries: u8,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max_retries: u8) -> Self {
+        Self { base, max_retries }
+    }
+
+    /// Backoff of the strategy, `order_retry_delay` seconds before the first retry.
+    pub fn for_strategy(strategy: &Strategy) -> Self {
+        let base = Duration::try_from_secs_f64(strategy.order_retry_delay).unwrap_or_default();
+        Self::new(base, strategy.max_order_retries)
+    }
+
+    pub fn max_retries(&self) -> u8 {
+        self.max_retries
+    }
+
+    /// Delay before retry `retry`, counted from 1.
+    pub fn delay<R: RngCore>(&self, retry: u8, rng: &mut R) -> Duration {
+        let factor = 1u32 << u32::from(retry.saturating_sub(1)).min(16);
+        let exponential = self.base.saturating_mul(factor).min(MAX_DELAY);
+        let jitter = rng.next_u32() as f64 / u32::MAX as f64;
+        exponential.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+/// Counters of order submission retries since startup.
+#[derive(Debug, Default)]
+pub struct RetryMetrics {
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+    fatal: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RetryCounts {
+    /// Submissions sent again after a transient failure
+    pub retries: u64,
+    /// Orders submitted after at least one retry
+    pub recovered: u64,
+    /// Orders rejected after all retries of their strategy failed
+    pub exhausted: u64,
+    /// Orders rejected by a failure retrying can't fix
+    pub fatal: u64,
+}
+
+impl RetryMetrics {
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_recovered(&self) {
+        self.recovered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_exhausted(&self) {
+        self.exhausted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_fatal(&self) {
+        self.fatal.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn counts(&self) -> RetryCounts {
+        RetryCounts {
+            retries: self.retries.load(Ordering::Relaxed),
+            recovered: self.recovered.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+            fatal: self.fatal.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,894 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::{
+    api::{error::ApiError, objects::Broker},
+    app_config::{DailyLoss, EscalationLevel, ExposureLimits, RiskBudget},
+    core::SIMULATED_STATUS,
+    feature_flags::{FeatureFlags, UpdateFeatureFlag, HALT_TRADING},
+    notifications::Notifier,
+    order::{NewOrder, OrderSide},
+    pnl::{self, StrategyPnl},
+    portfolio,
+    strategy::Strategy,
+    App,
+};
+
+/// Evaluates the portfolio drawdown against the escalation ladder after every fill and equity
+/// snapshot. Every level notifies once per broker account when reached, levels are re-armed when
+/// the drawdown of the account recovers below them. Daily losses of the account and of strategies
+/// are checked against their limits, see `DailyLoss`. New orders are checked against the exposure
+/// limits of their symbol, the open position limit and duplicate position rule of their strategy
+/// and the risk budget of the portfolio of their strategy.
+pub struct RiskMonitor {
+    db: PgPool,
+    notifier: Notifier,
+    feature_flags: Arc<FeatureFlags>,
+    ladder: Vec<EscalationLevel>,
+    /// Level of the ladder reached by each broker account
+    reached: Mutex<HashMap<String, usize>>,
+    daily_loss: DailyLoss,
+    /// Day the daily loss limit of each broker account was last reached
+    daily_loss_reached: Mutex<HashMap<String, NaiveDate>>,
+    exposure_limits: ExposureLimits,
+    portfolios: BTreeMap<String, Portfolio>,
+}
+
+/// Strategies grouped into a portfolio and their shared budget, see `Strategy::portfolio`.
+struct Portfolio {
+    budget: RiskBudget,
+    strategy_ids: Vec<Uuid>,
+}
+
+impl RiskMonitor {
+    pub fn new(
+        db: PgPool,
+        notifier: Notifier,
+        feature_flags: Arc<FeatureFlags>,
+        mut ladder: Vec<EscalationLevel>,
+    ) -> Self {
+        ladder.sort_by_key(|level| level.drawdown);
+        Self {
+            db,
+            notifier,
+            feature_flags,
+            ladder,
+            reached: Mutex::new(HashMap::new()),
+            daily_loss: DailyLoss::default(),
+            daily_loss_reached: Mutex::new(HashMap::new()),
+            exposure_limits: ExposureLimits::default(),
+            portfolios: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_daily_loss(mut self, daily_loss: DailyLoss) -> Self {
+        self.daily_loss = daily_loss;
+        self
+    }
+
+    pub fn with_exposure_limits(mut self, exposure_limits: ExposureLimits) -> Self {
+        self.exposure_limits = exposure_limits;
+        self
+    }
+
+    pub fn with_portfolios(
+        mut self,
+        budgets: BTreeMap<String, RiskBudget>,
+        strategies: &[Strategy],
+    ) -> Self {
+        self.portfolios = budgets
+            .into_iter()
+            .map(|(name, budget)| {
+                let strategy_ids = strategies
+                    .iter()
+                    .filter(|strategy| strategy.portfolio.as_ref() == Some(&name))
+                    .map(|strategy| strategy.id)
+                    .collect();
+                (
+                    name,
+                    Portfolio {
+                        budget,
+                        strategy_ids,
+                    },
+                )
+            })
+            .collect();
+        self
+    }
+
+    /// Portfolio of the strategy when its budget limits the daily loss.
+    pub fn daily_loss_portfolio<'a>(&self, strategy: &'a Strategy) -> Option<&'a str> {
+        strategy.portfolio.as_deref().filter(|name| {
+            self.portfolios
+                .get(*name)
+                .is_some_and(|portfolio| portfolio.budget.max_daily_loss.is_some())
+        })
+    }
+
+    /// Whether the strategy is disabled for the day by a loss limit of its own or of its
+    /// portfolio, see `is_strategy_halted`.
+    pub fn has_daily_loss_limit(&self, strategy: &Strategy) -> bool {
+        strategy.max_daily_loss.is_some() || self.daily_loss_portfolio(strategy).is_some()
+    }
+
+    /// Highest level of the ladder reached by `drawdown`.
+    pub fn level_for(&self, drawdown: Decimal) -> Option<usize> {
+        self.ladder
+            .iter()
+            .rposition(|level| drawdown >= level.drawdown)
+    }
+
+    pub async fn evaluate(&self, broker: &Broker, equity: Decimal) -> Result<(), sqlx::Error> {
+        self.evaluate_daily_loss(broker, equity).await?;
+
+        let peak = portfolio::peak_equity(&self.db, broker)
+            .await?
+            .unwrap_or_default()
+            .max(equity);
+        let drawdown = if peak.is_zero() {
+            Decimal::ZERO
+        } else {
+            (peak - equity) / peak
+        };
+
+        let level = self.level_for(drawdown);
+        let escalated = {
+            let mut reached = self.reached.lock().unwrap_or_else(|err| err.into_inner());
+            let escalated = level.filter(|level| {
+                reached
+                    .get(broker.as_ref())
+                    .is_none_or(|reached| level > reached)
+            });
+            match level {
+                Some(level) => reached.insert(broker.as_ref().to_owned(), level),
+                None => reached.remove(broker.as_ref()),
+            };
+            escalated
+        };
+        let Some(index) = escalated else {
+            return Ok(());
+        };
+
+        let level = &self.ladder[index];
+        let mut message = format!(
+            "{} drawdown reached {}% (equity {}, peak {})",
+            broker.as_ref(),
+            (drawdown * Decimal::ONE_HUNDRED).round_dp(2),
+            equity.round_dp(2),
+            peak.round_dp(2)
+        );
+
+        if level.kill_switch {
+            self.halt_trading().await?;
+            message.push_str(", kill switch triggered and trading halted");
+        }
+
+        tracing::warn!("{}", message);
+        self.notifier.notify(&level.channels, &message).await;
+
+        Ok(())
+    }
+
+    /// Trip the kill switch once the equity declined by the daily loss limit since the first
+    /// snapshot of the day.
+    async fn evaluate_daily_loss(
+        &self,
+        broker: &Broker,
+        equity: Decimal,
+    ) -> Result<(), sqlx::Error> {
+        let Some(max_loss) = self.daily_loss.max_loss else {
+            return Ok(());
+        };
+        let today = Utc::now().date_naive();
+        let Some(day_start) = portfolio::day_start_equity(&self.db, broker, today).await? else {
+            return Ok(());
+        };
+        let loss = day_start - equity;
+        if loss < max_loss {
+            return Ok(());
+        }
+        {
+            let mut reached = self
+                .daily_loss_reached
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            if reached.insert(broker.as_ref().to_owned(), today) == Some(today) {
+                return Ok(());
+            }
+        }
+
+        self.halt_trading().await?;
+        let mut message = format!(
+            "{} lost {} today (equity {}, day start {}), daily loss limit {} reached",
+            broker.as_ref(),
+            loss.round_dp(2),
+            equity.round_dp(2),
+            day_start.round_dp(2),
+            max_loss
+        );
+        message.push_str(", kill switch triggered and trading halted");
+        tracing::warn!("{}", message);
+        self.notifier
+            .notify(&self.daily_loss.channels, &message)
+            .await;
+
+        Ok(())
+    }
+
+    /// Disable the strategy for the rest of the day once its realized and unrealized loss of the
+    /// day reaches `max_daily_loss`. Open positions count with their whole unrealized P&L.
+    pub async fn evaluate_strategy(
+        &self,
+        strategy: &Strategy,
+        pnl: &StrategyPnl,
+        today: NaiveDate,
+    ) -> Result<(), sqlx::Error> {
+        let Some(max_loss) = strategy.max_daily_loss else {
+            return Ok(());
+        };
+        let loss = pnl.loss_on(today);
+        if loss < max_loss {
+            return Ok(());
+        }
+
+        let reason = format!(
+            "Daily loss {} reached the limit {}",
+            loss.round_dp(2),
+            max_loss
+        );
+        if !halt_strategy(&self.db, strategy.id, today, &reason).await? {
+            return Ok(());
+        }
+        let message = format!(
+            "Strategy {} lost {} today, daily loss limit {} reached and strategy disabled for the \
+             day",
+            strategy.name,
+            loss.round_dp(2),
+            max_loss
+        );
+        tracing::warn!("{}", message);
+        self.notifier
+            .notify(&self.daily_loss.channels, &message)
+            .await;
+
+        Ok(())
+    }
+
+    /// Disable every strategy of the portfolio for the rest of the day once their combined loss
+    /// of the day, see `evaluate_strategy`, reaches the `max_daily_loss` of its budget.
+    pub async fn evaluate_portfolio(
+        &self,
+        name: &str,
+        loss: Decimal,
+        today: NaiveDate,
+    ) -> Result<(), sqlx::Error> {
+        let Some(portfolio) = self.portfolios.get(name) else {
+            return Ok(());
+        };
+        let Some(max_loss) = portfolio.budget.max_daily_loss else {
+            return Ok(());
+        };
+        if loss < max_loss {
+            return Ok(());
+        }
+
+        let reason = format!(
+            "Daily loss {} of portfolio {} reached the limit {}",
+            loss.round_dp(2),
+            name,
+            max_loss
+        );
+        let mut halted = false;
+        for strategy_id in &portfolio.strategy_ids {
+            halted |= halt_strategy(&self.db, *strategy_id, today, &reason).await?;
+        }
+        if !halted {
+            return Ok(());
+        }
+        let message = format!(
+            "Portfolio {} lost {} today, daily loss limit {} reached and its strategies disabled \
+             for the day",
+            name,
+            loss.round_dp(2),
+            max_loss
+        );
+        tracing::warn!("{}", message);
+        self.notifier
+            .notify(&self.daily_loss.channels, &message)
+            .await;
+
+        Ok(())
+    }
+
+    /// Check the exposure the order would leave in its symbol, valued at `price`, against the
+    /// limit of the symbol. Orders raising the exposure above the limit are rejected and the
+    /// violation is recorded, orders reducing it are always accepted.
+    pub async fn check_exposure(
+        &self,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let violation = self.exposure_violation(new_order, price).await?;
+        self.record(violation).await
+    }
+
+    async fn exposure_violation(
+        &self,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let Some(limit) = self.exposure_limits.limit_for(&new_order.ticker) else {
+            return Ok(None);
+        };
+        let position = symbol_position(&self.db, &new_order.ticker).await?;
+        let quantity = match new_order.side {
+            OrderSide::Buy => new_order.quantity,
+            OrderSide::Sell => -new_order.quantity,
+        };
+        let exposure = ((position + quantity) * price).abs();
+        if exposure <= limit || (position + quantity).abs() <= position.abs() {
+            return Ok(None);
+        }
+
+        let violation = RiskViolation {
+            violation_id: uuid7::uuid7().into(),
+            strategy_id: new_order.strategy_id,
+            ticker: new_order.ticker.clone(),
+            rule: SYMBOL_EXPOSURE.to_owned(),
+            details: format!(
+                "Order of {} {} at {} would raise the exposure to {}, limit {}",
+                new_order.side.as_ref(),
+                new_order.quantity,
+                price.round_dp(2),
+                exposure.round_dp(2),
+                limit
+            ),
+            created_at: Utc::now(),
+        };
+        Ok(Some(violation))
+    }
+
+    /// Check the exposure the order would leave in the positions of all strategies of the
+    /// portfolio of its strategy against the `max_exposure` of its budget. Positions are valued at
+    /// `price` in the symbol of the order and at the price of their latest fill otherwise. Like
+    /// `check_exposure`, only orders raising the exposure are rejected.
+    pub async fn check_portfolio_exposure(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let violation = self
+            .portfolio_exposure_violation(strategy, new_order, price)
+            .await?;
+        self.record(violation).await
+    }
+
+    async fn portfolio_exposure_violation(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let Some((name, portfolio)) = strategy
+            .portfolio
+            .as_ref()
+            .and_then(|name| self.portfolios.get_key_value(name))
+        else {
+            return Ok(None);
+        };
+        let Some(limit) = portfolio.budget.max_exposure else {
+            return Ok(None);
+        };
+        let mut positions = portfolio_positions(&self.db, &portfolio.strategy_ids).await?;
+        let position = positions
+            .remove(&new_order.ticker)
+            .map_or(Decimal::ZERO, |(quantity, _)| quantity);
+        let quantity = match new_order.side {
+            OrderSide::Buy => new_order.quantity,
+            OrderSide::Sell => -new_order.quantity,
+        };
+        let exposure = positions
+            .values()
+            .map(|(quantity, price)| (quantity * price.unwrap_or_default()).abs())
+            .sum::<Decimal>()
+            + ((position + quantity) * price).abs();
+        if exposure <= limit || (position + quantity).abs() <= position.abs() {
+            return Ok(None);
+        }
+
+        let violation = RiskViolation {
+            violation_id: uuid7::uuid7().into(),
+            strategy_id: new_order.strategy_id,
+            ticker: new_order.ticker.clone(),
+            rule: PORTFOLIO_EXPOSURE.to_owned(),
+            details: format!(
+                "Order of {} {} at {} would raise the exposure of portfolio {} to {}, limit {}",
+                new_order.side.as_ref(),
+                new_order.quantity,
+                price.round_dp(2),
+                name,
+                exposure.round_dp(2),
+                limit
+            ),
+            created_at: Utc::now(),
+        };
+        Ok(Some(violation))
+    }
+
+    /// Reject orders opening a position in a further symbol once the strategy holds
+    /// `max_open_positions`, the violation is recorded. Orders in symbols the strategy already
+    /// holds add to or exit a position and are always accepted.
+    pub async fn check_open_positions(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let violation = self.open_positions_violation(strategy, new_order).await?;
+        self.record(violation).await
+    }
+
+    async fn open_positions_violation(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let Some(max_open_positions) = strategy.max_open_positions else {
+            return Ok(None);
+        };
+        let held = strategy_symbols(&self.db, strategy.id).await?;
+        if held.len() < max_open_positions || held.contains(&new_order.ticker) {
+            return Ok(None);
+        }
+
+        let violation = RiskViolation {
+            violation_id: uuid7::uuid7().into(),
+            strategy_id: strategy.id,
+            ticker: new_order.ticker.clone(),
+            rule: MAX_OPEN_POSITIONS.to_owned(),
+            details: format!(
+                "Strategy {} holds {} positions ({}), limit {}",
+                strategy.name,
+                held.len(),
+                held.join(", "),
+                max_open_positions
+            ),
+            created_at: Utc::now(),
+        };
+        Ok(Some(violation))
+    }
+
+    /// Reject entries in the direction of a position the strategy already holds in the symbol,
+    /// or which scale it in beyond its maximum, see `DuplicatePositions`. The violation is
+    /// recorded.
+    pub async fn check_duplicate_position(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let violation = self
+            .duplicate_position_violation(strategy, new_order)
+            .await?;
+        self.record(violation).await
+    }
+
+    async fn duplicate_position_violation(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        let max_quantity = match strategy.duplicate_positions {
+            DuplicatePositions::Allow => return Ok(None),
+            DuplicatePositions::Reject => None,
+            DuplicatePositions::ScaleIn { max_quantity } => Some(max_quantity),
+        };
+        let position = strategy_position(&self.db, strategy.id, &new_order.ticker).await?;
+        let order_quantity = match new_order.side {
+            OrderSide::Buy => new_order.quantity,
+            OrderSide::Sell => -new_order.quantity,
+        };
+        // Orders against the position exit or reverse it
+        if position.is_zero() || position.is_sign_positive() != order_quantity.is_sign_positive() {
+            return Ok(None);
+        }
+
+        let details = match max_quantity {
+            None => format!(
+                "Strategy {} already holds a position of {} {}",
+                strategy.name, position, new_order.ticker
+            ),
+            Some(max_quantity) if (position + order_quantity).abs() <= max_quantity => {
+                return Ok(None);
+            }
+            Some(max_quantity) => format!(
+                "Strategy {} holds a position of {} {}, adding {} exceeds the maximum of {}",
+                strategy.name, position, new_order.ticker, new_order.quantity, max_quantity
+            ),
+        };
+        let violation = RiskViolation {
+            violation_id: uuid7::uuid7().into(),
+            strategy_id: strategy.id,
+            ticker: new_order.ticker.clone(),
+            rule: DUPLICATE_POSITION.to_owned(),
+            details,
+            created_at: Utc::now(),
+        };
+        Ok(Some(violation))
+    }
+
+    /// Violations of every order rule the order breaks, none are recorded.
+    pub async fn order_violations(
+        &self,
+        strategy: &Strategy,
+        new_order: &NewOrder,
+        price: Decimal,
+    ) -> Result<Vec<RiskViolation>, sqlx::Error> {
+        Ok([
+            self.open_positions_violation(strategy, new_order).await?,
+            self.duplicate_position_violation(strategy, new_order)
+                .await?,
+            self.exposure_violation(new_order, price).await?,
+            self.portfolio_exposure_violation(strategy, new_order, price)
+                .await?,
+        ]
+        .into_iter()
+        .flatten()
+        .collect())
+    }
+
+    async fn record(
+        &self,
+        violation: Option<RiskViolation>,
+    ) -> Result<Option<RiskViolation>, sqlx::Error> {
+        if let Some(violation) = &violation {
+            violation.insert(&self.db).await?;
+        }
+        Ok(violation)
+    }
+
+    async fn halt_trading(&self) -> Result<(), sqlx::Error> {
+        self.feature_flags
+            .set(
+                HALT_TRADING,
+                &UpdateFeatureFlag {
+                    enabled: true,
+                    description: None,
+                    rollout_percentage: None,
+                },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Rule of orders raising the exposure of a symbol above its limit, see `ExposureLimits`
+pub const SYMBOL_EXPOSURE: &str = "symbol_exposure";
+
+/// Rule of orders raising the exposure of the strategies of a portfolio above its budget, see
+/// `RiskBudget::max_exposure`
+pub const PORTFOLIO_EXPOSURE: &str = "portfolio_exposure";
+
+/// Rule of orders opening more positions than `Strategy::max_open_positions`
+pub const MAX_OPEN_POSITIONS: &str = "max_open_positions";
+
+/// Rule of entries adding to a position of the strategy, see `Strategy::duplicate_positions`
+pub const DUPLICATE_POSITION: &str = "duplicate_position";
+
+/// Entries in the direction of a position the strategy already holds in the symbol.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum DuplicatePositions {
+    /// Entries add to the position
+    #[default]
+    Allow,
+    /// Entries are rejected until the position is closed
+    Reject,
+    /// Entries add to the position up to `max_quantity`, larger ones are rejected
+    ScaleIn { max_quantity: Decimal },
+}
+
+/// Order rejected by a risk rule.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RiskViolation {
+    pub violation_id: Uuid,
+    pub strategy_id: Uuid,
+    pub ticker: String,
+    pub rule: String,
+    pub details: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RiskViolation {
+    async fn insert(&self, db: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO risk_violations (violation_id, strategy_id, ticker, rule, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(self.violation_id)
+        .bind(self.strategy_id)
+        .bind(&self.ticker)
+        .bind(&self.rule)
+        .bind(&self.details)
+        .bind(self.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Latest violations, newest first.
+    pub async fn fetch_recent(db: &PgPool, limit: i64) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT * FROM risk_violations ORDER BY created_at DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Violations recorded from `from` until before `to`, oldest first.
+    pub async fn fetch_between(
+        db: &PgPool,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM risk_violations
+            WHERE created_at >= $1 AND created_at < $2
+            ORDER BY created_at
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+}
+
+/// Signed position of all strategies in `ticker`: filled quantities plus the remaining quantity
+/// of orders which may still fill, positive when long.
+async fn symbol_position(db: &PgPool, ticker: &str) -> Result<Decimal, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT
+            COALESCE((
+                SELECT SUM(quantity) FROM position_projections WHERE ticker = $1
+            ), 0)
+            + COALESCE((
+                SELECT SUM(CASE
+                    WHEN side = 'sell' THEN filled_quantity - quantity
+                    ELSE quantity - filled_quantity
+                END)
+                FROM orders
+                WHERE ticker = $1
+                    AND status NOT IN ('filled', 'canceled', 'expired', 'rejected', 'replaced', $2)
+            ), 0)
+        "#,
+    )
+    .bind(ticker)
+    .bind(SIMULATED_STATUS)
+    .fetch_one(db)
+    .await
+}
+
+/// Signed position of the strategy in `ticker`, including orders which may still fill like
+/// `symbol_position`.
+async fn strategy_position(
+    db: &PgPool,
+    strategy_id: Uuid,
+    ticker: &str,
+) -> Result<Decimal, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT
+            COALESCE((
+                SELECT quantity FROM position_projections
+                WHERE strategy_id = $1 AND ticker = $2
+            ), 0)
+            + COALESCE((
+                SELECT SUM(CASE
+                    WHEN side = 'sell' THEN filled_quantity - quantity
+                    ELSE quantity - filled_quantity
+                END)
+                FROM orders
+                WHERE strategy_id = $1
+                    AND ticker = $2
+                    AND status NOT IN ('filled', 'canceled', 'expired', 'rejected', 'replaced', $3)
+            ), 0)
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(ticker)
+    .bind(SIMULATED_STATUS)
+    .fetch_one(db)
+    .await
+}
+
+/// Signed positions of the strategies by symbol, including orders which may still fill like
+/// `symbol_position`, with the price of the latest fill of the symbol.
+async fn portfolio_positions(
+    db: &PgPool,
+    strategy_ids: &[Uuid],
+) -> Result<HashMap<String, (Decimal, Option<Decimal>)>, sqlx::Error> {
+    let rows: Vec<(String, Decimal, Option<Decimal>)> = sqlx::query_as(
+        r#"
+        SELECT
+            positions.ticker,
+            SUM(positions.quantity),
+            (
+                SELECT price FROM fills
+                WHERE fills.ticker = positions.ticker
+                ORDER BY filled_at DESC
+                LIMIT 1
+            )
+        FROM (
+            SELECT ticker, quantity
+            FROM position_projections
+            WHERE strategy_id = ANY($1)
+            UNION ALL
+            SELECT
+                ticker,
+                CASE
+                    WHEN side = 'sell' THEN filled_quantity - quantity
+                    ELSE quantity - filled_quantity
+                END
+            FROM orders
+            WHERE strategy_id = ANY($1)
+                AND status NOT IN ('filled', 'canceled', 'expired', 'rejected', 'replaced', $2)
+        ) AS positions
+        GROUP BY positions.ticker
+        HAVING SUM(positions.quantity) <> 0
+        "#,
+    )
+    .bind(strategy_ids)
+    .bind(SIMULATED_STATUS)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(ticker, quantity, price)| (ticker, (quantity, price)))
+        .collect())
+}
+
+/// Symbols the strategy holds a position in or has orders open for which would open one.
+async fn strategy_symbols(db: &PgPool, strategy_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT ticker FROM (
+            SELECT ticker, quantity
+            FROM position_projections
+            WHERE strategy_id = $1
+            UNION ALL
+            SELECT
+                ticker,
+                CASE
+                    WHEN side = 'sell' THEN filled_quantity - quantity
+                    ELSE quantity - filled_quantity
+                END
+            FROM orders
+            WHERE strategy_id = $1
+                AND status NOT IN ('filled', 'canceled', 'expired', 'rejected', 'replaced', $2)
+        ) AS positions
+        GROUP BY ticker
+        HAVING SUM(quantity) <> 0
+        ORDER BY ticker
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(SIMULATED_STATUS)
+    .fetch_all(db)
+    .await
+}
+
+/// Whether the strategy reached its daily loss limit on `day`.
+pub async fn is_strategy_halted(
+    db: &PgPool,
+    strategy_id: Uuid,
+    day: NaiveDate,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM strategy_halts WHERE strategy_id = $1 AND trading_day = $2)",
+    )
+    .bind(strategy_id)
+    .bind(day)
+    .fetch_one(db)
+    .await
+}
+
+/// Halt the strategy for `day`, `false` when it already was.
+async fn halt_strategy(
+    db: &PgPool,
+    strategy_id: Uuid,
+    day: NaiveDate,
+    reason: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO strategy_halts (strategy_id, trading_day, reason, halted_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(day)
+    .bind(reason)
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check the daily loss of every strategy with a limit each `period` until the process stops.
+pub async fn run_daily_loss_checks(app: Arc<App>, period: Duration) {
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        let today = Utc::now().date_naive();
+        // NOTE: strategies of portfolios count towards their loss also when disabled
+        let mut portfolio_losses: BTreeMap<&str, Decimal> = BTreeMap::new();
+        for strategy in &app.config.strategies {
+            let portfolio = app.risk_monitor.daily_loss_portfolio(strategy);
+            let limited = strategy.enabled && strategy.max_daily_loss.is_some();
+            if !limited && portfolio.is_none() {
+                continue;
+            }
+            match check_daily_loss(&app, strategy, limited, today).await {
+                Ok(loss) => {
+                    if let Some(portfolio) = portfolio {
+                        *portfolio_losses.entry(portfolio).or_default() += loss;
+                    }
+                }
+                Err(err) => tracing::error!(
+                    "Failed to check daily loss of strategy {}, error: {:?}",
+                    strategy.name,
+                    err
+                ),
+            }
+        }
+        for (portfolio, loss) in portfolio_losses {
+            if let Err(err) = app
+                .risk_monitor
+                .evaluate_portfolio(portfolio, loss, today)
+                .await
+            {
+                tracing::error!(
+                    "Failed to check daily loss of portfolio {}, error: {:?}",
+                    portfolio,
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Loss of the day of the strategy, which is checked against its own limit when `limited`.
+async fn check_daily_loss(
+    app: &App,
+    strategy: &Strategy,
+    limited: bool,
+    today: NaiveDate,
+) -> Result<Decimal, ApiError> {
+    let client = strategy
+        .broker
+        .get_account_client(app, strategy.account.as_deref())?;
+    let pnl = pnl::strategy_pnl(&app.db, &client, strategy.id).await?;
+    if limited {
+        app.risk_monitor
+            .evaluate_strategy(strategy, &pnl, today)
+            .await?;
+    }
+    Ok(pnl.loss_on(today))
+}
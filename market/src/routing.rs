@@ -0,0 +1,201 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    api::objects::Broker,
+    clients::{BrokerClient, Clients, VenueClient},
+    core::TradeError,
+    notifications::Channel,
+    order::{Fill, OrderRecord},
+    trade_executor::Priority,
+    trade_signal::TradeSignal,
+};
+
+/// Broker and credential set orders can be executed with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Venue {
+    pub broker: Broker,
+    /// Credential set of `brokers.accounts`, the global credentials of the broker when unset
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Fee in basis points of the order value, compared by the `fees` policy
+    #[serde(default)]
+    pub fee_bps: Decimal,
+}
+
+/// Backup broker and credential set the orders of a strategy go to while the circuit breaker of
+/// its own account is open, after the broker was unavailable for several requests in a row or
+/// rejected the credentials, see `RequestScheduler::is_circuit_open`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Failover {
+    pub broker: Broker,
+    /// Credential set of `brokers.accounts`, the global credentials of the broker when unset
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Channels operators are told on when signals fail over
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+}
+
+/// How the venue of an order is picked among the venues trading its asset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingPolicy {
+    /// First venue in the configured order, a venue which can't be asked fails the signal
+    #[default]
+    Preferred,
+    /// Venue with the lowest fee, the configured order breaks ties
+    Fees,
+    /// First venue in the configured order, venues which can't be asked are skipped
+    Failover,
+}
+
+/// Venues the orders of a strategy are routed to, see `Strategy::routing`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Routing {
+    #[serde(default)]
+    pub policy: RoutingPolicy,
+    pub venues: Vec<Venue>,
+}
+
+impl Routing {
+    /// Venues in the order they're tried.
+    pub fn candidates(&self) -> Vec<&Venue> {
+        let mut venues: Vec<&Venue> = self.venues.iter().collect();
+        if self.policy == RoutingPolicy::Fees {
+            venues.sort_by_key(|venue| venue.fee_bps);
+        }
+        venues
+    }
+}
+
+/// Client orders of trade signals are executed with.
+pub type SignalClient = VenueClient;
+
+/// Client of the venue the order of a trade signal is executed on. Signals of strategies with a
+/// routing go to the first candidate venue trading their ticker, the broker and account of the
+/// strategy of the signal are replaced by the ones of that venue.
+pub async fn signal_client(
+    clients: &Clients,
+    trade_signal: &mut TradeSignal,
+) -> Result<SignalClient, TradeError> {
+    let strategy = &trade_signal.strategy;
+    let Some(routing) = &strategy.routing else {
+        return venue_client(clients, &strategy.broker, strategy.account.as_deref());
+    };
+
+    let mut routed = None;
+    for venue in routing.candidates() {
+        let client = venue_client(clients, &venue.broker, venue.account.as_deref())?;
+        match client.get_asset(trade_signal.ticker.clone()).await {
+            Ok(asset) if asset.tradable() => {
+                routed = Some((venue.clone(), client));
+                break;
+            }
+            Ok(_) => {}
+            Err(err) if routing.policy == RoutingPolicy::Failover => {
+                tracing::warn!(
+                    "Venue {} {} of strategy {} skipped, error: {:?}",
+                    venue.broker.as_ref(),
+                    venue.account.as_deref().unwrap_or("default"),
+                    strategy.name,
+                    err
+                );
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    let Some((venue, client)) = routed else {
+        return Err(TradeError::NoVenue(
+            trade_signal.ticker.clone(),
+            strategy.name.clone(),
+        ));
+    };
+    tracing::info!(
+        "Signal for {} of strategy {} routed to {} {}",
+        trade_signal.ticker,
+        strategy.name,
+        venue.broker.as_ref(),
+        venue.account.as_deref().unwrap_or("default")
+    );
+    trade_signal.strategy.broker = venue.broker;
+    trade_signal.strategy.account = venue.account;
+    Ok(client)
+}
+
+/// Client of the venue holding the position of the strategy of the trade signal in its ticker,
+/// when the signal reduces the position or updates its stop loss. Such signals only go to that
+/// venue, neither the routing nor the failover of the strategy apply, and fail while its circuit
+/// breaker is open. The broker and account of the strategy of the signal are replaced by the
+/// ones of the venue.
+pub async fn holding_client(
+    clients: &Clients,
+    db: &PgPool,
+    trade_signal: &mut TradeSignal,
+) -> Result<Option<SignalClient>, TradeError> {
+    let strategy = &trade_signal.strategy;
+    let position = Fill::position(db, strategy.id, &trade_signal.ticker).await?;
+    if Priority::of(trade_signal, position) == Priority::Entry {
+        return Ok(None);
+    }
+    let Some(holding) =
+        OrderRecord::fetch_last_filled(db, strategy.id, &trade_signal.ticker).await?
+    else {
+        return Ok(None);
+    };
+
+    let broker = holding
+        .broker
+        .parse::<Broker>()
+        .map_err(|_| TradeError::UnknownBroker(holding.broker.clone()))?;
+    let client = venue_client(clients, &broker, holding.broker_account.as_deref())?;
+    let account = holding.broker_account.as_deref().unwrap_or("default");
+    if client.is_circuit_open() {
+        return Err(TradeError::VenueUnavailable(
+            format!("{} {}", broker.as_ref(), account),
+            trade_signal.ticker.clone(),
+        ));
+    }
+    if broker.as_ref() != strategy.broker.as_ref() || holding.broker_account != strategy.account {
+        tracing::info!(
+            "Signal for {} of strategy {} routed to {} {} holding the position",
+            trade_signal.ticker,
+            strategy.name,
+            broker.as_ref(),
+            account
+        );
+    }
+    trade_signal.strategy.broker = broker;
+    trade_signal.strategy.account = holding.broker_account;
+    Ok(Some(client))
+}
+
+fn venue_client(
+    clients: &Clients,
+    broker: &Broker,
+    account: Option<&str>,
+) -> Result<SignalClient, TradeError> {
+    Ok(clients.venue(broker, account)?.0)
+}
+
+/// Client of the failover venue of the strategy of the trade signal, whose broker and account are
+/// replaced by the ones of the venue.
+pub fn failover_client(
+    clients: &Clients,
+    failover: &Failover,
+    trade_signal: &mut TradeSignal,
+) -> Result<SignalClient, TradeError> {
+    let client = venue_client(clients, &failover.broker, failover.account.as_deref())?;
+    tracing::warn!(
+        "Signal for {} of strategy {} failed over to {} {}",
+        trade_signal.ticker,
+        trade_signal.strategy.name,
+        failover.broker.as_ref(),
+        failover.account.as_deref().unwrap_or("default")
+    );
+    trade_signal.strategy.broker = failover.broker.clone();
+    trade_signal.strategy.account = failover.account.clone();
+    Ok(client)
+}
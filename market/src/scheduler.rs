@@ -0,0 +1,304 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::time::{sleep, Duration, Instant};
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Clock, Order, Position},
+    clients::{BrokerClient, BrokerClientError},
+    order::NewOrder,
+};
+
+/// How long requests are held back after the broker rejected one for exceeding its rate limit
+const RATE_LIMITED_PAUSE: Duration = Duration::from_secs(3);
+/// Consecutive failed requests after which the circuit breaker of an account opens
+const BREAKER_FAILURES: u32 = 5;
+/// How long the circuit breaker of an account stays open
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Requests changing orders or positions are sent before queued read-only queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Order,
+    Query,
+}
+
+struct State {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+    waiting_orders: usize,
+    /// Requests failed in a row for the broker being unavailable
+    failures: u32,
+    breaker_open_until: Option<Instant>,
+}
+
+/// Request budget of a broker account. Requests wait for a token of a bucket refilled at the
+/// rate limit of the broker, queries also wait for every queued order request.
+///
+/// NOTE: apca doesn't expose response headers, so the remaining budget is tracked locally and a
+/// rate limited response drains it instead of reading the `X-RateLimit-*` headers
+pub struct RequestScheduler {
+    /// Tokens per second
+    rate: f64,
+    capacity: f64,
+    state: Mutex<State>,
+}
+
+impl RequestScheduler {
+    pub fn new(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            rate: capacity / 60.0,
+            capacity,
+            state: Mutex::new(State {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: None,
+                waiting_orders: 0,
+                failures: 0,
+                breaker_open_until: None,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Wait until a request of `priority` may be sent.
+    pub async fn acquire(&self, priority: Priority) {
+        let _waiting = (priority == Priority::Order).then(|| WaitingOrder::new(self));
+
+        loop {
+            let wait = {
+                let mut state = self.state();
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+                state.refilled_at = now;
+
+                // Queries leave a token for every order request queued before them
+                let needed = match priority {
+                    Priority::Order => 1.0,
+                    Priority::Query => 1.0 + state.waiting_orders as f64,
+                };
+                match state.paused_until.filter(|until| *until > now) {
+                    Some(until) => until - now,
+                    None if state.tokens >= needed => {
+                        state.tokens -= 1.0;
+                        return;
+                    }
+                    None => Duration::from_secs_f64((needed - state.tokens) / self.rate),
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    /// The broker rejected a request for exceeding its rate limit, hold back all requests for a
+    /// while and restart with an empty budget.
+    pub fn rate_limited(&self) {
+        let mut state = self.state();
+        state.tokens = 0.0;
+        state.paused_until = Some(Instant::now() + RATE_LIMITED_PAUSE);
+        tracing::warn!(
+            "Broker rate limit exceeded, requests paused for {:?}",
+            RATE_LIMITED_PAUSE
+        );
+    }
+
+    /// Whether the circuit breaker of the account is open, it failed too many requests in a row
+    /// or rejected the credentials recently.
+    pub fn is_circuit_open(&self) -> bool {
+        self.state()
+            .breaker_open_until
+            .is_some_and(|until| until > Instant::now())
+    }
+
+    /// Count the outcome of a request towards the circuit breaker. Unavailability opens it after
+    /// `BREAKER_FAILURES` requests in a row and rejected credentials right away, a successful
+    /// request closes it again. Rate limited requests don't count, the pause handles them.
+    fn record(&self, error: Option<&BrokerClientError>) {
+        let mut state = self.state();
+        let trips = match error {
+            None => {
+                state.failures = 0;
+                state.breaker_open_until = None;
+                return;
+            }
+            Some(err) if err.is_rate_limited() => return,
+            Some(err) if err.is_authentication_failure() => true,
+            Some(
+                BrokerClientError::AlpacaUnavailable(_)
+                | BrokerClientError::OandaUnavailable(_)
+                | BrokerClientError::TradierUnavailable(_),
+            ) => {
+                state.failures += 1;
+                state.failures >= BREAKER_FAILURES
+            }
+            Some(_) => return,
+        };
+        if !trips {
+            return;
+        }
+        if state.breaker_open_until.is_none() {
+            tracing::warn!(
+                "Broker account failing, circuit breaker open for {:?}",
+                BREAKER_COOLDOWN
+            );
+        }
+        state.breaker_open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+    }
+}
+
+/// Counts an order request as queued until it's sent or dropped.
+struct WaitingOrder<'a>(&'a RequestScheduler);
+
+impl<'a> WaitingOrder<'a> {
+    fn new(scheduler: &'a RequestScheduler) -> Self {
+        scheduler.state().waiting_orders += 1;
+        Self(scheduler)
+    }
+}
+
+impl Drop for WaitingOrder<'_> {
+    fn drop(&mut self) {
+        self.0.state().waiting_orders -= 1;
+    }
+}
+
+/// Broker client sending its requests through the scheduler of the account.
+pub struct ScheduledClient<C> {
+    inner: C,
+    scheduler: Arc<RequestScheduler>,
+}
+
+impl<C> ScheduledClient<C> {
+    pub fn new(inner: C, scheduler: Arc<RequestScheduler>) -> Self {
+        Self { inner, scheduler }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Whether the circuit breaker of the account of the client is open, see
+    /// `RequestScheduler::is_circuit_open`.
+    pub fn is_circuit_open(&self) -> bool {
+        self.scheduler.is_circuit_open()
+    }
+
+    async fn send<T, F>(&self, priority: Priority, request: F) -> Result<T, BrokerClientError>
+    where
+        F: std::future::Future<Output = Result<T, BrokerClientError>>,
+    {
+        self.scheduler.acquire(priority).await;
+        let result = request.await;
+        if result
+            .as_ref()
+            .is_err_and(BrokerClientError::is_rate_limited)
+        {
+            self.scheduler.rate_limited();
+        }
+        self.scheduler.record(result.as_ref().err());
+        result
+    }
+}
+
+#[axum::async_trait]
+impl<C: BrokerClient> BrokerClient for ScheduledClient<C> {
+    type ActivitiesRequest = C::ActivitiesRequest;
+    type NewOrderRequest = C::NewOrderRequest;
+    type OrdersRequest = C::OrdersRequest;
+    type OrderUdateRequest = C::OrderUdateRequest;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        self.inner.order_request(new_order)
+    }
+
+    fn supports_options(&self) -> bool {
+        self.inner.supports_options()
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_account()).await
+    }
+
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_activities(activities_req))
+            .await
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_asset(symbol))
+            .await
+    }
+
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_assets(class))
+            .await
+    }
+
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_clock()).await
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_position(symbol))
+            .await
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_positions()).await
+    }
+
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        self.send(Priority::Order, self.inner.delete_position(symbol))
+            .await
+    }
+
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        self.send(
+            Priority::Query,
+            self.inner.get_order_by_client_id(client_id),
+        )
+        .await
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        self.send(Priority::Query, self.inner.get_orders(orders_req))
+            .await
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.send(Priority::Order, self.inner.create_order(new_order_req))
+            .await
+    }
+
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        self.send(
+            Priority::Order,
+            self.inner.update_order(order_id, update_req),
+        )
+        .await
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        self.send(Priority::Order, self.inner.delete_order(order_id))
+            .await
+    }
+}
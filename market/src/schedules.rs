@@ -0,0 +1,446 @@
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use strum_macros::AsRefStr;
+use thiserror::Error as ThisError;
+use uuid::Uuid;
+
+use crate::{
+    admin::{self, StrategySwitch},
+    audit::AuditEntry,
+    error::ApiError,
+    strategy::Strategy,
+    App,
+};
+
+/// Actor of the audit entries of scheduled actions.
+pub const SCHEDULER_ACTOR: &str = "scheduler";
+/// Times a scheduled action is run before it's given up on, `ACTION_RETRY_DELAY` apart.
+const ACTION_ATTEMPTS: u32 = 3;
+const ACTION_RETRY_DELAY: Duration = Duration::minutes(1);
+/// Days searched for the next time of a schedule, enough for a leap day.
+const SEARCH_DAYS: i64 = 366 * 4 + 1;
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Debug, ThisError, PartialEq, Eq)]
+#[error("Invalid cron expression {expression}, {reason}")]
+pub struct InvalidCron {
+    pub expression: String,
+    pub reason: String,
+}
+
+/// Minute, hour, day of month, month and day of week of the times a schedule is due, as in
+/// crontab. Fields are `*`, values, ranges and steps separated by commas, e.g. `55 15 * * 1-5`.
+/// Days of the week and months can be named, `0` and `7` are Sunday. A day is due when it
+/// matches either of its fields if both are restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for Cron {
+    type Err = InvalidCron;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| InvalidCron {
+            expression: expression.to_owned(),
+            reason,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields, got {}", fields.len())));
+        };
+        let (weekdays_mask, weekdays_restricted) =
+            field(weekdays, 0, 7, &WEEKDAYS, 0).map_err(invalid)?;
+        let (days_mask, days_restricted) = field(days, 1, 31, &[], 1).map_err(invalid)?;
+        Ok(Self {
+            expression: expression.to_owned(),
+            minutes: field(minutes, 0, 59, &[], 0).map_err(invalid)?.0,
+            hours: field(hours, 0, 23, &[], 0).map_err(invalid)?.0,
+            days: days_mask,
+            months: field(months, 1, 12, &MONTHS, 1).map_err(invalid)?.0,
+            // Sunday is both 0 and 7
+            weekdays: (weekdays_mask | weekdays_mask >> 7) & 0x7f,
+            days_restricted,
+            weekdays_restricted,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Cron {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Values of a field as a mask with the bit of every value set, whether the field is restricted.
+/// Names are matched case-insensitively, the first one standing for `first_name`.
+fn field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    first_name: u32,
+) -> Result<(u64, bool), String> {
+    let value = |value: &str| -> Result<u32, String> {
+        let lowercase = value.to_lowercase();
+        let value = match names.iter().position(|name| *name == lowercase) {
+            Some(index) => index as u32 + first_name,
+            None => value
+                .parse()
+                .map_err(|_| format!("{value} is not a number"))?,
+        };
+        if value < min || value > max {
+            return Err(format!("{value} is out of {min}-{max}"));
+        }
+        Ok(value)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("step {step} is not a positive number"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // A start with a step runs to the end of the field
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return Err(format!("range {range} is reversed"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok((mask, field != "*"))
+}
+
+impl Cron {
+    pub fn as_str(&self) -> &str {
+        &self.expression
+    }
+
+    fn due_on(&self, date: NaiveDate) -> bool {
+        let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        let day_due = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        day_due && bit(self.months, date.month())
+    }
+
+    /// First time due after `after` for the wall clock of `zone`, `None` when the expression
+    /// names days which don't exist, e.g. February 30th.
+    pub fn next_after(&self, after: DateTime<Utc>, zone: ScheduleZone) -> Option<DateTime<Utc>> {
+        // Days start up to a day apart in UTC and in the wall clock
+        let mut date = (after - Duration::days(1)).date_naive();
+        for _ in 0..SEARCH_DAYS {
+            if self.due_on(date) {
+                for hour in (0..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                    for minute in (0..60).filter(|minute| self.minutes & (1 << minute) != 0) {
+                        let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+                        let at = zone.at(date, time);
+                        if at > after {
+                            return Some(at);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// Wall clock schedules are evaluated in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleZone {
+    #[default]
+    Utc,
+    /// New York, where US equities trade
+    Eastern,
+}
+
+impl ScheduleZone {
+    /// UTC time of `time` on `date` in the zone.
+    pub fn at(self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        match self {
+            Self::Utc => date.and_time(time).and_utc(),
+            Self::Eastern => eastern_time(date, time),
+        }
+    }
+}
+
+/// UTC time of `time` in New York on `date`. Daylight saving time runs from the second Sunday of
+/// March to the first Sunday of November, the shift at 2am is ignored.
+pub fn eastern_time(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    let sunday =
+        |month, n| NaiveDate::from_weekday_of_month_opt(date.year(), month, Weekday::Sun, n);
+    let daylight_saving = sunday(3, 2)
+        .zip(sunday(11, 1))
+        .is_some_and(|(start, end)| start <= date && date < end);
+    let offset = if daylight_saving { 4 } else { 5 };
+    Utc.from_utc_datetime(&date.and_time(time)) + Duration::hours(offset)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ScheduledAction {
+    /// Cancel the open orders and close the positions of the strategy, see
+    /// `Core::flatten_strategy`
+    Flatten,
+    /// Cancel the open entry orders of the strategy, stop losses of open positions are kept
+    CancelOrders,
+    /// Disable the strategy until it's enabled again, see `admin::switch_strategy`
+    Disable,
+    /// Lift a runtime disable of the strategy
+    Enable,
+}
+
+/// Action run on a strategy whenever its cron expression is due, e.g. flatten at 15:55 in New
+/// York before the close.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Schedule {
+    pub cron: Cron,
+    #[serde(default)]
+    pub zone: ScheduleZone,
+    pub action: ScheduledAction,
+    /// Reason a scheduled disable is recorded with
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Schedule {
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.cron.next_after(after, self.zone)
+    }
+}
+
+/// Orders placed or canceled by a scheduled action and the errors of the ones which failed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionReport {
+    pub orders: Vec<Uuid>,
+    pub errors: Vec<String>,
+}
+
+/// Claim the run of an action due at `due_at`, the one of a failed run too. Returns `false` when
+/// another instance sharing the database claimed it first or the action already ran.
+pub async fn claim_run(
+    db: &PgPool,
+    strategy_id: Uuid,
+    action: ScheduledAction,
+    due_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let claimed = sqlx::query(
+        r#"
+        INSERT INTO schedule_runs (strategy_id, action, due_at, ran_at, status)
+        VALUES ($1, $2, $3, NOW(), 'running')
+        ON CONFLICT (strategy_id, action, due_at) DO UPDATE
+        SET ran_at = NOW(), status = 'running', error = NULL
+        WHERE schedule_runs.status = 'failed'
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(action.as_ref())
+    .bind(due_at)
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(claimed == 1)
+}
+
+/// Record the outcome of a claimed run, a failed one releases the claim for a retry.
+async fn finish_run(
+    db: &PgPool,
+    strategy_id: Uuid,
+    action: ScheduledAction,
+    due_at: DateTime<Utc>,
+    error: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE schedule_runs
+        SET status = CASE WHEN $4::text IS NULL THEN 'succeeded' ELSE 'failed' END, error = $4
+        WHERE strategy_id = $1 AND action = $2 AND due_at = $3
+        "#,
+    )
+    .bind(strategy_id)
+    .bind(action.as_ref())
+    .bind(due_at)
+    .bind(error)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Scheduled action due at a time, with the attempts made to run it so far.
+type Run<'a> = (u32, DateTime<Utc>, &'a Strategy, &'a Schedule);
+
+/// Run the scheduled actions of the strategies when they're due until the process stops. Actions
+/// are recorded in the audit log as `schedule.<action>`, failed ones with their error, and are
+/// retried up to `ACTION_ATTEMPTS` times.
+pub async fn run_schedules(app: Arc<App>) {
+    // Failed runs by the time of their next attempt
+    let mut retries: Vec<(DateTime<Utc>, Run)> = Vec::new();
+    loop {
+        let now = Utc::now();
+        let due: Vec<(DateTime<Utc>, &Strategy, &Schedule)> = app
+            .config
+            .strategies
+            .iter()
+            .flat_map(|strategy| {
+                strategy.schedules.iter().filter_map(move |schedule| {
+                    Some((schedule.next_after(now)?, strategy, schedule))
+                })
+            })
+            .collect();
+        let Some(next) = due
+            .iter()
+            .map(|(at, _, _)| *at)
+            .chain(retries.iter().map(|(at, ..)| *at))
+            .min()
+        else {
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        let mut runs: Vec<Run> = due
+            .into_iter()
+            .filter(|(at, _, _)| *at == next)
+            .map(|(at, strategy, schedule)| (0, at, strategy, schedule))
+            .collect();
+        let (ready, waiting) = retries.into_iter().partition(|(at, ..)| *at <= next);
+        retries = waiting;
+        runs.extend(ready.into_iter().map(|(_, run)| run));
+
+        for (attempts, due_at, strategy, schedule) in runs {
+            if let Err(err) = run_action(&app, strategy, schedule, due_at).await {
+                let attempts = attempts + 1;
+                tracing::error!(
+                    "Failed to run scheduled {} of strategy {}, attempt {}/{}, error: {:?}",
+                    schedule.action.as_ref(),
+                    strategy.name,
+                    attempts,
+                    ACTION_ATTEMPTS,
+                    err
+                );
+                if attempts < ACTION_ATTEMPTS {
+                    retries.push((
+                        Utc::now() + ACTION_RETRY_DELAY,
+                        (attempts, due_at, strategy, schedule),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Run the action of `schedule` due at `due_at` unless another instance did, recording its
+/// outcome.
+pub async fn run_action(
+    app: &App,
+    strategy: &Strategy,
+    schedule: &Schedule,
+    due_at: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    if !claim_run(&app.db, strategy.id, schedule.action, due_at).await? {
+        return Ok(());
+    }
+
+    let result = execute(app, strategy, schedule).await;
+    let error = result.as_ref().err().map(ToString::to_string);
+    finish_run(&app.db, strategy.id, schedule.action, due_at, error).await?;
+    let mut details = json!({
+        "name": strategy.name,
+        "cron": schedule.cron.as_str(),
+        "due_at": due_at,
+    });
+    match &result {
+        Ok(report) => {
+            details["orders"] = json!(report.orders);
+            details["errors"] = json!(report.errors);
+        }
+        Err(err) => details["error"] = json!(err.to_string()),
+    }
+    AuditEntry::record_by(
+        &app.db,
+        SCHEDULER_ACTOR,
+        &format!("schedule.{}", schedule.action.as_ref()),
+        &format!("strategy:{}", strategy.id),
+        details,
+        None,
+    )
+    .await?;
+
+    let report = result?;
+    tracing::info!(
+        "Scheduled {} of strategy {} run, orders: {}, errors: {}",
+        schedule.action.as_ref(),
+        strategy.name,
+        report.orders.len(),
+        report.errors.len()
+    );
+    Ok(())
+}
+
+async fn execute(
+    app: &App,
+    strategy: &Strategy,
+    schedule: &Schedule,
+) -> Result<ActionReport, ApiError> {
+    match schedule.action {
+        ScheduledAction::Flatten => {
+            let client = app
+                .clients
+                .venue(&strategy.broker, strategy.account.as_deref())?
+                .0;
+            Ok(app.core.flatten_strategy(client, strategy).await?)
+        }
+        ScheduledAction::CancelOrders => Ok(app.core.cancel_strategy_orders(strategy, false).await),
+        ScheduledAction::Disable | ScheduledAction::Enable => {
+            let switch = StrategySwitch {
+                enabled: schedule.action == ScheduledAction::Enable,
+                reason: schedule.reason.clone(),
+            };
+            admin::switch_strategy(&app.db, strategy, &switch).await?;
+            app.notify_switch(strategy, &switch).await;
+            Ok(ActionReport::default())
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use apca::{ApiInfo, Client as AlpacaClient};
+use reqwest::Url;
+use serde::Deserialize;
+use sqlx::{postgres::PgConnectOptions, PgPool};
+use thiserror::Error as ThisError;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::{app_config::AppConfig, clients::Clients};
+
+/// Where broker credentials and the database password are loaded from. The secret is a JSON
+/// object with any of the `apca_api_key_id`, `apca_api_secret_key` and `database_password`
+/// keys, values missing from it are taken from the configuration files.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Secrets {
+    #[serde(flatten)]
+    pub provider: SecretsProvider,
+    /// Seconds between two reloads picking up rotated secrets, secrets are only loaded at
+    /// startup when unset
+    pub refresh_interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum SecretsProvider {
+    /// Secret of the Vault KV engine, version 1 or 2
+    Vault {
+        addr: String,
+        token: String,
+        /// Path of the secret under `/v1`, e.g. `secret/data/market`
+        path: String,
+    },
+    /// Secret of AWS Secrets Manager, credentials and region come from the default AWS
+    /// provider chain. Requires the `aws` feature.
+    Aws {
+        secret_id: String,
+        region: Option<String>,
+    },
+}
+
+#[derive(Debug, ThisError)]
+pub enum SecretsError {
+    #[error("Secret request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Secret is not a JSON object of strings: {0}")]
+    InvalidSecret(#[from] serde_json::Error),
+    #[error("Secrets provider error: {0}")]
+    ProviderError(String),
+    #[error("Database url is invalid: {0}")]
+    InvalidDatabaseUrl(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SecretValues {
+    pub apca_api_key_id: Option<String>,
+    pub apca_api_secret_key: Option<String>,
+    pub database_password: Option<String>,
+}
+
+impl SecretValues {
+    /// Replace the configured credentials with the loaded ones.
+    pub fn apply(&self, config: &mut AppConfig) -> Result<(), SecretsError> {
+        let alpaca = &mut config.brokers.alpaca;
+        if let Some(key_id) = &self.apca_api_key_id {
+            alpaca.apca_api_key_id = key_id.clone();
+        }
+        if let Some(secret_key) = &self.apca_api_secret_key {
+            alpaca.apca_api_secret_key = secret_key.clone();
+        }
+        if let Some(password) = &self.database_password {
+            let mut url = Url::parse(&config.database.url)
+                .map_err(|err| SecretsError::InvalidDatabaseUrl(err.to_string()))?;
+            url.set_password(Some(password)).map_err(|_| {
+                SecretsError::InvalidDatabaseUrl("url can't have a password".to_string())
+            })?;
+            config.database.url = url.into();
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn fetch(secrets: &Secrets) -> Result<SecretValues, SecretsError> {
+    match &secrets.provider {
+        SecretsProvider::Vault { addr, token, path } => fetch_vault(addr, token, path).await,
+        SecretsProvider::Aws { secret_id, region } => fetch_aws(secret_id, region.clone()).await,
+    }
+}
+
+async fn fetch_vault(addr: &str, token: &str, path: &str) -> Result<SecretValues, SecretsError> {
+    #[derive(Deserialize)]
+    struct VaultResponse {
+        data: serde_json::Value,
+    }
+
+    let url = format!(
+        "{}/v1/{}",
+        addr.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    let response: VaultResponse = reqwest::Client::new()
+        .get(url)
+        .header("X-Vault-Token", token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    // KV version 2 nests the secret in a second `data` next to its metadata
+    let data = match response.data.get("data") {
+        Some(data) if data.is_object() => data.clone(),
+        _ => response.data,
+    };
+    Ok(serde_json::from_value(data)?)
+}
+
+#[cfg(feature = "aws")]
+async fn fetch_aws(secret_id: &str, region: Option<String>) -> Result<SecretValues, SecretsError> {
+    let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+    if let Some(region) = region {
+        loader = loader.region(aws_config::Region::new(region));
+    }
+    let client = aws_sdk_secretsmanager::Client::new(&loader.load().await);
+
+    let secret = client
+        .get_secret_value()
+        .secret_id(secret_id)
+        .send()
+        .await
+        .map_err(|err| SecretsError::ProviderError(format!("{err:?}")))?;
+    let secret = secret.secret_string().ok_or_else(|| {
+        SecretsError::ProviderError(format!("Secret {secret_id} has no string value"))
+    })?;
+    Ok(serde_json::from_str(secret)?)
+}
+
+#[cfg(not(feature = "aws"))]
+async fn fetch_aws(
+    _secret_id: &str,
+    _region: Option<String>,
+) -> Result<SecretValues, SecretsError> {
+    Err(SecretsError::ProviderError(
+        "AWS Secrets Manager requires the aws feature".to_string(),
+    ))
+}
+
+/// Reload the secrets every `refresh_interval` and switch the Alpaca client and the database
+/// connections over to rotated credentials. `config` holds the credentials in use.
+pub async fn run_rotation(
+    secrets: Secrets,
+    mut config: AppConfig,
+    clients: Arc<Clients>,
+    db: PgPool,
+) {
+    let Some(refresh_interval) = secrets.refresh_interval else {
+        return;
+    };
+    let mut ticker = interval(Duration::from_secs(refresh_interval));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // The first tick completes immediately, the secrets were just loaded at startup
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = rotate(&secrets, &mut config, &clients, &db).await {
+            tracing::error!("Failed to reload secrets, error: {}", err);
+        }
+    }
+}
+
+async fn rotate(
+    secrets: &Secrets,
+    config: &mut AppConfig,
+    clients: &Clients,
+    db: &PgPool,
+) -> Result<(), SecretsError> {
+    let previous = (config.brokers.alpaca.clone(), config.database.url.clone());
+    fetch(secrets).await?.apply(config)?;
+
+    let alpaca = &config.brokers.alpaca;
+    if alpaca.apca_api_key_id != previous.0.apca_api_key_id
+        || alpaca.apca_api_secret_key != previous.0.apca_api_secret_key
+    {
+        let api_info = ApiInfo::from_parts(
+            alpaca.base_url(config.environment),
+            &alpaca.apca_api_key_id,
+            &alpaca.apca_api_secret_key,
+        )
+        .map_err(|err| SecretsError::ProviderError(format!("{err:?}")))?;
+        clients.set_alpaca(AlpacaClient::new(api_info));
+        tracing::info!("Alpaca credentials rotated");
+    }
+
+    if config.database.url != previous.1 {
+        let options = config
+            .database
+            .url
+            .parse::<PgConnectOptions>()
+            .map_err(|err| SecretsError::InvalidDatabaseUrl(err.to_string()))?;
+        // New connections use the rotated password, open ones are kept until they're recycled
+        db.set_connect_options(options);
+        tracing::info!("Database password rotated");
+    }
+
+    Ok(())
+}
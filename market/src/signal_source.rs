@@ -0,0 +1,319 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use strum_macros::AsRefStr;
+use thiserror::Error as ThisError;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        alert::{BarData, SignalType, TrailStopPrice, WebhookAlertData},
+        payload::AlertPayload,
+    },
+    app_config::AppConfig,
+    middleware,
+    nonces::AlertNonce,
+};
+
+/// Header carrying the shared token of sources which can't sign their requests.
+pub const TOKEN_HEADER: &str = "x-source-token";
+
+/// Kind of alert producer, each with its own body schema and authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// `Notify.Web` calls of QuantConnect algorithms, see `QuantConnectAlert`. Authenticated by
+    /// the token header.
+    QuantConnect,
+    /// Custom scanners posting alerts of any payload version. Authenticated by the HMAC-SHA256
+    /// signature header of the body.
+    Scanner,
+    /// Email-to-webhook bridges forwarding alert emails, see `EmailMessage`. Authenticated by
+    /// the token header and the sender.
+    EmailBridge,
+}
+
+/// Alert producer other than TradingView, served at `/webhook/source/:name`. Its alerts carry a
+/// `timestamp` and a `nonce` like signed webhook alerts, so captured ones can't be posted again.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignalSourceConfig {
+    pub name: String,
+    pub kind: SourceKind,
+    /// Token or signing secret shared with the producer
+    pub secret: String,
+    /// Addresses emails are accepted from, any when empty. Email bridges only.
+    #[serde(default)]
+    pub allowed_senders: Vec<String>,
+    /// Ids of the strategies the alerts of the source can trade, the webhook secrets of the
+    /// strategies don't apply to them
+    #[serde(default)]
+    pub strategies: Vec<Uuid>,
+}
+
+impl SignalSourceConfig {
+    pub fn adapter(&self) -> Box<dyn SignalSource + '_> {
+        match self.kind {
+            SourceKind::QuantConnect => Box::new(QuantConnect {
+                token: &self.secret,
+            }),
+            SourceKind::Scanner => Box::new(Scanner {
+                secret: &self.secret,
+            }),
+            SourceKind::EmailBridge => Box::new(EmailBridge {
+                token: &self.secret,
+                allowed_senders: &self.allowed_senders,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum SourceError {
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("Invalid {0} alert: {1}")]
+    InvalidAlert(&'static str, String),
+}
+
+/// Adapter of an alert producer, turning its requests into the alerts all webhooks are traded
+/// as.
+pub trait SignalSource {
+    /// Check the request was sent by the producer.
+    fn authenticate(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), SourceError>;
+
+    fn parse(&self, body: &[u8], config: &AppConfig) -> Result<WebhookAlertData, SourceError>;
+
+    /// `timestamp` and `nonce` of the alert of the request, carried next to its fields. Source
+    /// alerts are rejected as replays by them like signed webhook alerts, see
+    /// `middleware::verify_signature`.
+    fn nonce(&self, body: &[u8]) -> Result<AlertNonce, SourceError>;
+
+    fn receive(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        config: &AppConfig,
+    ) -> Result<WebhookAlertData, SourceError> {
+        self.authenticate(headers, body)?;
+        self.parse(body, config)
+    }
+}
+
+fn verify_token(headers: &HeaderMap, token: &str) -> Result<(), SourceError> {
+    let given = headers
+        .get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| SourceError::Unauthorized("Source token not found".to_string()))?;
+
+    // NOTE: digests are compared so the comparison time doesn't depend on the matching prefix
+    if Sha256::digest(given) != Sha256::digest(token) {
+        return Err(SourceError::Unauthorized(
+            "Source token isn't correct".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InsightDirection {
+    #[serde(alias = "Up")]
+    Up,
+    #[serde(alias = "Down")]
+    Down,
+    #[serde(alias = "Flat")]
+    Flat,
+}
+
+// NOTE: QuantConnect algorithm example:
+// self.notify.web(url, json.dumps({
+//     "strategy_id": "C6557FC3-0D9A-447A-9D87-E417D98F2114",
+//     "insight_id": insight.id,
+//     "symbol": "AAPL",
+//     "market": "usa",
+//     "resolution": "hour",
+//     "time": self.utc_time.isoformat(),
+//     "direction": "up",
+//     "bar": {"time": bar.end_time.isoformat(), "open": bar.open, ...},
+//     "stop_loss": stop,
+//     "timestamp": self.utc_time.isoformat(),
+//     "nonce": str(uuid.uuid4()),
+// }), {"x-source-token": token})
+
+/// Insight of a QuantConnect algorithm. Flat insights aren't traded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuantConnectAlert {
+    pub strategy_id: Uuid,
+    #[serde(default)]
+    pub insight_id: Option<String>,
+    pub symbol: String,
+    pub market: String,
+    pub resolution: String,
+    pub time: DateTime<Utc>,
+    pub direction: InsightDirection,
+    pub bar: BarData,
+    pub stop_loss: Decimal,
+    /// Traded instead of the quantity sized for the strategy when set
+    #[serde(default)]
+    pub quantity: Option<Decimal>,
+}
+
+struct QuantConnect<'a> {
+    token: &'a str,
+}
+
+impl SignalSource for QuantConnect<'_> {
+    fn authenticate(&self, headers: &HeaderMap, _body: &[u8]) -> Result<(), SourceError> {
+        verify_token(headers, self.token)
+    }
+
+    fn parse(&self, body: &[u8], _config: &AppConfig) -> Result<WebhookAlertData, SourceError> {
+        let invalid = |message: String| SourceError::InvalidAlert("quant_connect", message);
+        let alert: QuantConnectAlert =
+            serde_json::from_slice(body).map_err(|err| invalid(err.to_string()))?;
+
+        let stop_loss = TrailStopPrice(alert.stop_loss);
+        let signal_type = match alert.direction {
+            InsightDirection::Up => SignalType::OpenLong(stop_loss),
+            InsightDirection::Down => SignalType::OpenShort(stop_loss),
+            InsightDirection::Flat => {
+                return Err(invalid("flat insights are not traded".to_string()))
+            }
+        };
+
+        Ok(WebhookAlertData {
+            alert_id: alert.insight_id,
+            strategy_id: alert.strategy_id,
+            ticker: alert.symbol,
+            timeframe: alert.resolution,
+            exchange: alert.market,
+            signal_type,
+            trail_stop_price: None,
+            bar_data: alert.bar,
+            time: alert.time,
+            quantity: alert.quantity,
+            notional: None,
+            time_in_force: None,
+            limit_price: None,
+            extended_hours: None,
+            legs: Vec::new(),
+        })
+    }
+
+    fn nonce(&self, body: &[u8]) -> Result<AlertNonce, SourceError> {
+        serde_json::from_slice(body)
+            .map_err(|err| SourceError::InvalidAlert("quant_connect", err.to_string()))
+    }
+}
+
+struct Scanner<'a> {
+    secret: &'a str,
+}
+
+impl SignalSource for Scanner<'_> {
+    fn authenticate(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), SourceError> {
+        middleware::verify_hmac(self.secret, headers, body)
+            .map_err(|err| SourceError::Unauthorized(err.to_string()))
+    }
+
+    fn parse(&self, body: &[u8], config: &AppConfig) -> Result<WebhookAlertData, SourceError> {
+        let invalid = |message: String| SourceError::InvalidAlert("scanner", message);
+        let body = serde_json::from_slice(body).map_err(|err| invalid(err.to_string()))?;
+
+        AlertPayload::from_value(body, None)
+            .and_then(|payload| payload.into_alert_data(config))
+            .map_err(|err| invalid(err.to_string()))
+    }
+
+    fn nonce(&self, body: &[u8]) -> Result<AlertNonce, SourceError> {
+        // NOTE: read like the alert, so duplicate keys can't hide the nonce checked
+        let invalid = |message: String| SourceError::InvalidAlert("scanner", message);
+        let body: serde_json::Value =
+            serde_json::from_slice(body).map_err(|err| invalid(err.to_string()))?;
+        AlertNonce::deserialize(&body).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+/// Email forwarded by a bridge as JSON. The text carries the alert as a JSON object of any
+/// payload version, e.g. the message of a TradingView email alert.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EmailMessage {
+    #[serde(alias = "sender")]
+    pub from: String,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(alias = "body", alias = "body-plain")]
+    pub text: String,
+}
+
+impl EmailMessage {
+    /// Address of the sender, without its display name.
+    pub fn sender(&self) -> &str {
+        match (self.from.rfind('<'), self.from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &self.from[start + 1..end],
+            _ => self.from.trim(),
+        }
+    }
+
+    /// Alert between the first opening and the last closing brace of the text.
+    pub fn alert(&self) -> Option<&str> {
+        let start = self.text.find('{')?;
+        let end = self.text.rfind('}')?;
+        (start < end).then(|| &self.text[start..=end])
+    }
+}
+
+struct EmailBridge<'a> {
+    token: &'a str,
+    allowed_senders: &'a [String],
+}
+
+impl SignalSource for EmailBridge<'_> {
+    fn authenticate(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), SourceError> {
+        verify_token(headers, self.token)?;
+        if self.allowed_senders.is_empty() {
+            return Ok(());
+        }
+
+        let email: EmailMessage = serde_json::from_slice(body)
+            .map_err(|err| SourceError::InvalidAlert("email_bridge", err.to_string()))?;
+        if !self
+            .allowed_senders
+            .iter()
+            .any(|sender| sender.eq_ignore_ascii_case(email.sender()))
+        {
+            return Err(SourceError::Unauthorized(format!(
+                "Emails from {} are not accepted",
+                email.sender()
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse(&self, body: &[u8], config: &AppConfig) -> Result<WebhookAlertData, SourceError> {
+        let alert = email_alert(body)?;
+        AlertPayload::from_value(alert, None)
+            .and_then(|payload| payload.into_alert_data(config))
+            .map_err(|err| SourceError::InvalidAlert("email_bridge", err.to_string()))
+    }
+
+    fn nonce(&self, body: &[u8]) -> Result<AlertNonce, SourceError> {
+        let alert = email_alert(body)?;
+        AlertNonce::deserialize(&alert)
+            .map_err(|err| SourceError::InvalidAlert("email_bridge", err.to_string()))
+    }
+}
+
+/// Alert in the text of a forwarded email.
+fn email_alert(body: &[u8]) -> Result<serde_json::Value, SourceError> {
+    let invalid = |message: String| SourceError::InvalidAlert("email_bridge", message);
+    let email: EmailMessage =
+        serde_json::from_slice(body).map_err(|err| invalid(err.to_string()))?;
+    let alert = email
+        .alert()
+        .ok_or_else(|| invalid("no alert found in the email text".to_string()))?;
+    serde_json::from_str(alert).map_err(|err| invalid(err.to_string()))
+}
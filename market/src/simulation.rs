@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        alert::{BarData, SignalType},
+        objects::Broker,
+    },
+    options::OrderLeg,
+    order::{NewOrder, TimeInForce},
+    risk::RiskViolation,
+    trade_signal::TradeSignal,
+};
+
+/// Trade signal an alert is parsed into.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedSignal {
+    pub ticker: String,
+    pub timeframe: String,
+    pub exchange: String,
+    pub signal_type: SignalType,
+    pub bar_data: BarData,
+    pub time: DateTime<Utc>,
+    pub quantity: Option<Decimal>,
+    pub notional: Option<Decimal>,
+    pub time_in_force: Option<TimeInForce>,
+    pub limit_price: Option<Decimal>,
+    pub extended_hours: Option<bool>,
+    pub legs: Vec<OrderLeg>,
+}
+
+/// Strategy an alert is addressed to, with the venue its order is routed to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedStrategy {
+    pub id: Uuid,
+    pub name: String,
+    pub broker: Broker,
+    /// Credential set of the broker, see `Strategy::account`
+    pub account: Option<String>,
+}
+
+/// Outcome of an alert run through the processing as a dry run, see
+/// `Core::simulate_trade_signal`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertSimulation {
+    pub signal: SimulatedSignal,
+    pub strategy: SimulatedStrategy,
+    /// Why the signal places no order
+    pub ignored: Option<String>,
+    /// Price the order is sized at
+    pub price: Option<Decimal>,
+    /// Average price the paper fill model executes the order at, slippage included
+    pub fill_price: Option<Decimal>,
+    /// Commissions of the paper fill model for the order
+    pub fees: Option<Decimal>,
+    /// Rules the order violates, it's rejected unless there are none
+    pub risk_violations: Vec<RiskViolation>,
+    /// Order the signal places, unless it's ignored
+    pub order: Option<NewOrder>,
+}
+
+impl AlertSimulation {
+    pub fn new(trade_signal: &TradeSignal) -> Self {
+        Self {
+            signal: SimulatedSignal {
+                ticker: trade_signal.ticker.clone(),
+                timeframe: trade_signal.timeframe.clone(),
+                exchange: trade_signal.exchange.clone(),
+                signal_type: trade_signal.signal_type.clone(),
+                bar_data: trade_signal.bar_data.clone(),
+                time: trade_signal.time,
+                quantity: trade_signal.quantity,
+                notional: trade_signal.notional,
+                time_in_force: trade_signal.time_in_force,
+                limit_price: trade_signal.limit_price,
+                extended_hours: trade_signal.extended_hours,
+                legs: trade_signal.legs.clone(),
+            },
+            strategy: SimulatedStrategy {
+                id: trade_signal.strategy.id,
+                name: trade_signal.strategy.name.clone(),
+                broker: trade_signal.strategy.broker.clone(),
+                account: trade_signal.strategy.account.clone(),
+            },
+            ignored: None,
+            price: None,
+            fill_price: None,
+            fees: None,
+            risk_violations: Vec::new(),
+            order: None,
+        }
+    }
+}
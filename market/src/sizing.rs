@@ -0,0 +1,316 @@
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+
+use crate::{
+    backtest::{HistoricalBar, Timeframe},
+    pnl::TradeStatistics,
+    strategy::{CurrencyType, Strategy},
+};
+
+/// Code path which sized an order. Orders are tagged with it, so the canary can be compared with
+/// the stable path on live executions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPath {
+    /// Fixed quantity configured for the strategy
+    Stable,
+    /// New sizing engine, risking a fixed share of the account equity on every trade
+    Canary,
+}
+
+/// What happens to entries costing more than the buying power of the account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuyingPowerPolicy {
+    /// Reduce the order to what the buying power covers
+    #[default]
+    Downsize,
+    /// Reject the signal
+    Reject,
+}
+
+/// Decimals of fractional quantities, as many as orders and fills are stored with
+pub const FRACTIONAL_DECIMALS: u32 = 8;
+
+/// Smallest order value Alpaca accepts for crypto pairs
+pub const MIN_CRYPTO_NOTIONAL: Decimal = Decimal::ONE;
+
+/// Entries sized from the volatility of the symbol instead of the fixed strategy quantity. A trade
+/// risks `risk_per_trade` of the equity on a stop `atr_multiple` average true ranges away.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolatilitySizing {
+    /// Share of the account equity risked by a trade, e.g. `0.005`
+    pub risk_per_trade: Decimal,
+    /// Average true ranges between the entry and the assumed stop
+    #[serde(default = "default_atr_multiple")]
+    pub atr_multiple: Decimal,
+    /// Bars the average true range is taken over
+    #[serde(default = "default_atr_period")]
+    pub atr_period: usize,
+    /// Timeframe of the bars, stored bars of alerts are used when there are enough of them
+    #[serde(default = "default_atr_timeframe")]
+    pub timeframe: Timeframe,
+}
+
+fn default_atr_multiple() -> Decimal {
+    Decimal::ONE
+}
+
+fn default_atr_period() -> usize {
+    14
+}
+
+fn default_atr_timeframe() -> Timeframe {
+    Timeframe::OneDay
+}
+
+/// Entries worth a share of the equity given by the Kelly criterion of the latest closed trades
+/// of the strategy, instead of the fixed strategy quantity.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KellySizing {
+    /// Share of the Kelly fraction used, e.g. `0.5` for half Kelly
+    #[serde(default = "default_kelly_multiplier")]
+    pub multiplier: Decimal,
+    /// Share of the equity an entry is worth at most
+    #[serde(default = "default_kelly_max_fraction")]
+    pub max_fraction: Decimal,
+    /// Latest closed trades the win rate and payoff ratio are taken from
+    #[serde(default = "default_kelly_lookback_trades")]
+    pub lookback_trades: usize,
+    /// Closed trades needed before the Kelly fraction is used, entries are sized by
+    /// `order_quantity` until then
+    #[serde(default = "default_kelly_min_trades")]
+    pub min_trades: usize,
+}
+
+fn default_kelly_multiplier() -> Decimal {
+    Decimal::new(5, 1)
+}
+
+fn default_kelly_max_fraction() -> Decimal {
+    Decimal::new(2, 1)
+}
+
+fn default_kelly_lookback_trades() -> usize {
+    50
+}
+
+fn default_kelly_min_trades() -> usize {
+    20
+}
+
+/// Kelly fraction `W - (1 - W) / R` of the win rate `W` and payoff ratio `R`, scaled by
+/// `sizing.multiplier` and capped at `sizing.max_fraction`. Zero without an edge, the cap when
+/// none of the trades lost.
+pub fn kelly_fraction(sizing: &KellySizing, statistics: &TradeStatistics) -> Decimal {
+    let Some(win_rate) = statistics.win_rate else {
+        return Decimal::ZERO;
+    };
+    let fraction = match statistics.payoff_ratio {
+        Some(payoff_ratio) => win_rate - (Decimal::ONE - win_rate) / payoff_ratio,
+        None if win_rate.is_zero() => Decimal::ZERO,
+        None => return sizing.max_fraction,
+    };
+
+    (fraction * sizing.multiplier).clamp(Decimal::ZERO, sizing.max_fraction)
+}
+
+/// Quantity worth the capped Kelly fraction of `equity` at `price`. `None` without an edge or
+/// when it rounds down to zero.
+pub fn kelly_quantity(
+    strategy: &Strategy,
+    sizing: &KellySizing,
+    statistics: &TradeStatistics,
+    equity: Decimal,
+    price: Decimal,
+) -> Option<Decimal> {
+    let notional = equity * kelly_fraction(sizing, statistics);
+    round_quantity(strategy, notional.checked_div(price)?)
+}
+
+/// Average of the true ranges of the last `period` bars. The true range of a bar includes the gap
+/// from the close of the bar before, so `period + 1` bars are needed.
+pub fn average_true_range(bars: &[HistoricalBar], period: usize) -> Option<Decimal> {
+    if period == 0 || bars.len() <= period {
+        return None;
+    }
+
+    let bars = &bars[bars.len() - period - 1..];
+    let total: Decimal = bars
+        .windows(2)
+        .map(|pair| {
+            let (previous, bar) = (&pair[0], &pair[1]);
+            (bar.high - bar.low)
+                .max((bar.high - previous.close).abs())
+                .max((bar.low - previous.close).abs())
+        })
+        .sum();
+
+    Some(total / Decimal::from(period))
+}
+
+/// Quantity which loses `sizing.risk_per_trade` of `equity` when the price moves
+/// `sizing.atr_multiple` times `atr` against it. `None` when it rounds down to zero.
+pub fn volatility_quantity(
+    strategy: &Strategy,
+    sizing: &VolatilitySizing,
+    equity: Decimal,
+    atr: Decimal,
+) -> Option<Decimal> {
+    let risk_per_unit = atr * sizing.atr_multiple;
+    if risk_per_unit <= Decimal::ZERO {
+        return None;
+    }
+
+    round_quantity(strategy, equity * sizing.risk_per_trade / risk_per_unit)
+}
+
+/// Quantity which loses `strategy.risk_per_trade` of `equity` when the stop loss is hit. Stocks
+/// are sized in whole shares. `None` when the quantity can't be derived from the prices or rounds
+/// down to zero.
+pub fn risk_based_quantity(
+    strategy: &Strategy,
+    equity: Decimal,
+    entry_price: Decimal,
+    stop_loss_price: Decimal,
+) -> Option<Decimal> {
+    let risk_per_unit = (entry_price - stop_loss_price).abs();
+    if risk_per_unit.is_zero() {
+        return None;
+    }
+
+    round_quantity(strategy, equity * strategy.risk_per_trade / risk_per_unit)
+}
+
+/// Price move of a pip of the currency `pair`, e.g. `EUR_USD`. Pairs quoted in yen move in
+/// hundredths, all others in ten thousandths.
+pub fn pip_size(pair: &str) -> Decimal {
+    match currency_pair(pair) {
+        Some((_, "JPY")) => Decimal::new(1, 2),
+        _ => Decimal::new(1, 4),
+    }
+}
+
+/// Pips between the prices `from` and `to` of `pair`.
+pub fn pips(pair: &str, from: Decimal, to: Decimal) -> Decimal {
+    (to - from).abs() / pip_size(pair)
+}
+
+/// Value of a pip of a unit of `pair` at `price`, in `account_currency`. `None` when neither
+/// currency of the pair is the account currency, a cross rate would be needed then.
+pub fn pip_value(pair: &str, price: Decimal, account_currency: &str) -> Option<Decimal> {
+    pip_size(pair).checked_div(quote_rate(pair, price, account_currency)?)
+}
+
+/// Units of `pair` which lose `strategy.risk_per_trade` of `equity`, in `account_currency`, when
+/// the stop loss is hit. Forex is sized in whole units. `None` when the pip value isn't known, see
+/// `pip_value`, or the units round down to zero.
+pub fn pip_risk_quantity(
+    strategy: &Strategy,
+    equity: Decimal,
+    account_currency: &str,
+    pair: &str,
+    entry_price: Decimal,
+    stop_loss_price: Decimal,
+) -> Option<Decimal> {
+    let risk_per_unit = pips(pair, entry_price, stop_loss_price) * pip_size(pair);
+    if risk_per_unit.is_zero() {
+        return None;
+    }
+
+    // The risked amount is converted to the quote currency instead of the pip value to the
+    // account currency, so yen pairs don't lose units to the rounding of a tiny pip value
+    let risk = equity * strategy.risk_per_trade * quote_rate(pair, entry_price, account_currency)?;
+    round_quantity(strategy, risk / risk_per_unit)
+}
+
+/// Price of a unit of `account_currency` in the quote currency of `pair` at `price`.
+fn quote_rate(pair: &str, price: Decimal, account_currency: &str) -> Option<Decimal> {
+    let (base, quote) = currency_pair(pair)?;
+    if quote.eq_ignore_ascii_case(account_currency) {
+        Some(Decimal::ONE)
+    } else if base.eq_ignore_ascii_case(account_currency) {
+        Some(price)
+    } else {
+        None
+    }
+}
+
+/// Base and quote currency of `pair`, e.g. `EUR` and `USD` of `EUR_USD` or `EUR/USD`.
+fn currency_pair(pair: &str) -> Option<(&str, &str)> {
+    pair.split_once(['_', '/'])
+}
+
+/// Apply `strategy.weekend_size_factor` to orders placed on Fridays. `None` when the reduced
+/// quantity rounds down to zero.
+pub fn weekend_adjusted_quantity(
+    strategy: &Strategy,
+    quantity: Decimal,
+    now: DateTime<Utc>,
+) -> Option<Decimal> {
+    match strategy.weekend_size_factor {
+        Some(factor) if now.weekday() == Weekday::Fri => {
+            round_quantity(strategy, quantity * factor)
+        }
+        _ => Some(quantity),
+    }
+}
+
+fn round_quantity(strategy: &Strategy, quantity: Decimal) -> Option<Decimal> {
+    let quantity = match strategy.currency_type {
+        CurrencyType::Stock | CurrencyType::Forex => quantity.floor(),
+        CurrencyType::Crypto => {
+            quantity.round_dp_with_strategy(FRACTIONAL_DECIMALS, RoundingStrategy::ToZero)
+        }
+    };
+
+    (quantity > Decimal::ZERO).then_some(quantity)
+}
+
+/// Quantity a `notional` order buys at `price`, rounded down to the fractional share decimals.
+/// `None` when it rounds down to zero.
+pub fn notional_quantity(notional: Decimal, price: Decimal) -> Option<Decimal> {
+    let quantity = notional
+        .checked_div(price)?
+        .round_dp_with_strategy(FRACTIONAL_DECIMALS, RoundingStrategy::ToZero);
+
+    (quantity > Decimal::ZERO).then_some(quantity)
+}
+
+/// Long `quantity` capped at what `buying_power` covers at `price`. Stocks are reduced to whole
+/// shares unless `fractional`. `None` when nothing is left.
+pub fn affordable_quantity(
+    strategy: &Strategy,
+    quantity: Decimal,
+    buying_power: Decimal,
+    price: Decimal,
+    fractional: bool,
+) -> Option<Decimal> {
+    let affordable = buying_power.checked_div(price)?;
+    if quantity <= affordable {
+        return Some(quantity);
+    }
+
+    if fractional {
+        notional_quantity(buying_power, price)
+    } else {
+        round_quantity(strategy, affordable)
+    }
+}
+
+/// Short `quantity` capped at what `buying_power` covers at `price`, in whole shares as fractional
+/// shares can't be sold short. `None` when not a single share is left.
+pub fn short_quantity(quantity: Decimal, buying_power: Decimal, price: Decimal) -> Option<Decimal> {
+    let affordable = buying_power.checked_div(price)?;
+    let quantity = quantity.min(affordable).floor();
+
+    (quantity > Decimal::ZERO).then_some(quantity)
+}
+
+/// Whether the quantity includes a fraction of a share.
+pub fn is_fractional(quantity: Decimal) -> bool {
+    !quantity.fract().is_zero()
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    order::Fill,
+    pnl::{self, closed_trades},
+    portfolio::{self, drawdowns},
+    strategy::Strategy,
+};
+
+/// Days a year returns are annualized with.
+const TRADING_DAYS: f64 = 252.0;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StatsQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl StatsQuery {
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+/// Performance of a strategy over the days between `from` and `to`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StrategyStats {
+    pub strategy_id: Uuid,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Trades closed during the period
+    pub trades: usize,
+    /// Share of trades closed with a profit, `None` without trades
+    pub win_rate: Option<Decimal>,
+    /// Average P&L of winning trades net of fees, `None` without them
+    pub average_win: Option<Decimal>,
+    /// Average P&L of losing trades net of fees, negative, `None` without them
+    pub average_loss: Option<Decimal>,
+    /// Profit of winning trades over the loss of losing trades, `None` without losing trades
+    pub profit_factor: Option<Decimal>,
+    /// Days with a snapshot of the broker account, returns are measured for them
+    pub days: usize,
+    /// Largest decline of the compounded daily returns from their running peak, as a fraction of
+    /// the peak
+    pub max_drawdown: Decimal,
+    /// Annualized Sharpe ratio of daily returns, `None` for less than two days or returns without
+    /// variance
+    pub sharpe_ratio: Option<Decimal>,
+}
+
+/// Statistics of a strategy from its fills and the account equity at the start of every day.
+/// Daily returns are the realized P&L of the day net of fees over the equity the day started
+/// with, days without positive equity aren't measured.
+pub fn compute_stats(
+    strategy_id: Uuid,
+    fills: &[Fill],
+    equities: &[(NaiveDate, Decimal)],
+    query: &StatsQuery,
+) -> StrategyStats {
+    // NOTE: trades are matched against lots opened before the period too
+    let trades: Vec<Decimal> = closed_trades(fills)
+        .into_iter()
+        .filter(|trade| query.contains(trade.closed_at.date_naive()))
+        .map(|trade| trade.pnl)
+        .collect();
+    let (wins, losses): (Vec<Decimal>, Vec<Decimal>) = trades
+        .iter()
+        .copied()
+        .filter(|pnl| !pnl.is_zero())
+        .partition(|pnl| pnl.is_sign_positive());
+    let average = |pnls: &[Decimal]| {
+        (!pnls.is_empty()).then(|| pnls.iter().sum::<Decimal>() / Decimal::from(pnls.len()))
+    };
+    let profit: Decimal = wins.iter().sum();
+    let loss: Decimal = losses.iter().sum();
+
+    let pnl = pnl::compute_pnl(strategy_id, fills, &HashMap::new());
+    let returns: Vec<Decimal> = equities
+        .iter()
+        .filter(|(date, equity)| query.contains(*date) && *equity > Decimal::ZERO)
+        .map(|(date, equity)| pnl.realized_on(*date) / equity)
+        .collect();
+
+    StrategyStats {
+        strategy_id,
+        from: query.from,
+        to: query.to,
+        trades: trades.len(),
+        win_rate: (!trades.is_empty())
+            .then(|| Decimal::from(wins.len()) / Decimal::from(trades.len())),
+        average_win: average(&wins),
+        average_loss: average(&losses),
+        profit_factor: (!losses.is_empty()).then(|| profit / loss.abs()),
+        days: returns.len(),
+        max_drawdown: max_drawdown(&returns),
+        sharpe_ratio: sharpe_ratio(&returns),
+    }
+}
+
+/// Largest drawdown of the series compounded from the returns, starting at 1.
+pub fn max_drawdown(returns: &[Decimal]) -> Decimal {
+    let mut value = Decimal::ONE;
+    let values: Vec<Decimal> = std::iter::once(value)
+        .chain(returns.iter().map(|daily| {
+            value *= Decimal::ONE + daily;
+            value
+        }))
+        .collect();
+
+    drawdowns(&values).into_iter().max().unwrap_or_default()
+}
+
+/// Mean of the daily returns over their sample standard deviation, annualized.
+pub fn sharpe_ratio(returns: &[Decimal]) -> Option<Decimal> {
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let returns: Vec<f64> = returns
+        .iter()
+        .map(|daily| daily.to_f64().unwrap_or_default())
+        .collect();
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns
+        .iter()
+        .map(|daily| (daily - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    if variance <= f64::EPSILON {
+        return None;
+    }
+
+    Decimal::from_f64(mean / variance.sqrt() * TRADING_DAYS.sqrt()).map(|sharpe| sharpe.round_dp(4))
+}
+
+/// Statistics of the strategy from its persisted fills and the snapshots of its broker account.
+pub async fn strategy_stats(
+    db: &PgPool,
+    strategy: &Strategy,
+    query: &StatsQuery,
+) -> Result<StrategyStats, sqlx::Error> {
+    let fills = Fill::fetch_for_strategy(db, strategy.id).await?;
+    let equities =
+        portfolio::day_start_equities(db, &strategy.broker, query.from, query.to).await?;
+
+    Ok(compute_stats(strategy.id, &fills, &equities, query))
+}
@@ -0,0 +1,69 @@
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{app_config::Session, feature_flags::FeatureFlags};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Up,
+    /// Database is unreachable or trading is halted
+    Degraded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketSession {
+    Open,
+    Closed,
+}
+
+/// Coarse status safe to expose publicly. Nothing identifying strategies, symbols or positions
+/// is included.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicStatus {
+    pub status: Health,
+    /// Minutes since the last trade signal resulted in an order
+    pub last_signal_minutes_ago: Option<i64>,
+    pub market_session: MarketSession,
+}
+
+pub fn market_session(session: &Session, now: DateTime<Utc>) -> MarketSession {
+    let time = now.time();
+    match now.weekday() {
+        Weekday::Sat | Weekday::Sun => MarketSession::Closed,
+        _ if time >= session.open && time < session.close => MarketSession::Open,
+        _ => MarketSession::Closed,
+    }
+}
+
+pub async fn public_status(
+    db: &PgPool,
+    feature_flags: &FeatureFlags,
+    session: &Session,
+    now: DateTime<Utc>,
+) -> PublicStatus {
+    let last_signal_at =
+        sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT MAX(created_at) FROM orders")
+            .fetch_one(db)
+            .await;
+
+    let status = match &last_signal_at {
+        Ok(_) if !feature_flags.is_halted().await => Health::Up,
+        Ok(_) => Health::Degraded,
+        Err(err) => {
+            tracing::error!("Failed to read the last signal, error: {:?}", err);
+            Health::Degraded
+        }
+    };
+
+    PublicStatus {
+        status,
+        last_signal_minutes_ago: last_signal_at
+            .ok()
+            .flatten()
+            .map(|at| (now - at).num_minutes()),
+        market_session: market_session(session, now),
+    }
+}
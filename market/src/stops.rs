@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+
+use crate::{backtest::Timeframe, core::Core, order::OrderSide};
+
+/// Time between two checks of the protective stops
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Protective stops of the positions of a strategy moved by the stop manager as the price moves
+/// in their favor. Stops are only ever tightened, the stop closest to the price wins.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopManagement {
+    /// Gain as a share of the entry price, e.g. `0.02`, after which the stop moves to the entry
+    #[serde(default)]
+    pub break_even_gain: Option<Decimal>,
+    /// Distance the stop trails the price at
+    #[serde(default)]
+    pub trailing: Option<TrailingStop>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum TrailingStop {
+    /// Share of the price, e.g. `0.03`
+    Percent { percent: Decimal },
+    /// Average true ranges of the symbol
+    Atr {
+        multiple: Decimal,
+        /// Bars the average true range is taken over
+        #[serde(default = "default_atr_period")]
+        period: usize,
+        #[serde(default = "default_atr_timeframe")]
+        timeframe: Timeframe,
+    },
+}
+
+fn default_atr_period() -> usize {
+    14
+}
+
+fn default_atr_timeframe() -> Timeframe {
+    Timeframe::OneDay
+}
+
+/// Stop a position entered on `side` at `entry` should have at `price`, `None` when it keeps
+/// `stop`. `atr` is only used by ATR trailing stops, which keep the stop without it. Stops never
+/// move away from the price or past it.
+pub fn next_stop(
+    management: &StopManagement,
+    side: OrderSide,
+    entry: Decimal,
+    stop: Decimal,
+    price: Decimal,
+    atr: Option<Decimal>,
+) -> Option<Decimal> {
+    // Distances in favor of the position are positive on both sides
+    let sign = match side {
+        OrderSide::Buy => Decimal::ONE,
+        OrderSide::Sell => Decimal::NEGATIVE_ONE,
+    };
+
+    let break_even = management
+        .break_even_gain
+        .filter(|gain| (price - entry) * sign >= entry * gain)
+        .map(|_| entry);
+    let trailing = match &management.trailing {
+        Some(TrailingStop::Percent { percent }) => Some(price - price * percent * sign),
+        Some(TrailingStop::Atr { multiple, .. }) => atr.map(|atr| price - atr * multiple * sign),
+        None => None,
+    };
+
+    let next = [break_even, trailing]
+        .into_iter()
+        .flatten()
+        .max_by_key(|candidate| candidate * sign)?;
+    ((next - stop) * sign > Decimal::ZERO && (price - next) * sign > Decimal::ZERO).then_some(next)
+}
+
+/// Move the protective stops of the strategies with stop management until the process stops,
+/// see `Core::manage_stops`.
+pub async fn run_stop_manager(core: Arc<Core>) {
+    let mut ticker = interval(CHECK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = core.manage_stops().await {
+            tracing::error!("Failed to manage protective stops, error: {:?}", err);
+        }
+    }
+}
@@ -1,7 +1,24 @@
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::objects::Broker;
+use crate::{
+    app_config::Session,
+    cooldown::Cooldown,
+    dca::DcaExecution,
+    filters::SignalFilter,
+    ladder::TakeProfitLadder,
+    mapping::AlertMapping,
+    objects::Broker,
+    order::{OrderTtl, PartialFills, TimeInForce},
+    risk::DuplicatePositions,
+    routing::{Failover, Routing},
+    schedules::Schedule,
+    sizing::{BuyingPowerPolicy, KellySizing, VolatilitySizing},
+    stops::StopManagement,
+};
+
+pub const DEFAULT_TENANT_ID: &str = "default";
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Strategy {
@@ -9,14 +26,200 @@ pub struct Strategy {
     pub name: String,
     pub enabled: bool,
     pub broker: Broker,
+    /// Credential set of `brokers.accounts` the strategy trades with, the global credentials of
+    /// its broker when unset
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Family of related strategies, they trade with the credential set of their family in
+    /// `brokers.families` unless `account` is set
+    #[serde(default)]
+    pub family: Option<String>,
     pub currency_type: CurrencyType,
     pub max_order_retries: u8,
     pub order_retry_delay: f64,
+    /// Quantity used for every entry order of the strategy
+    #[serde(default = "default_order_quantity")]
+    pub order_quantity: Decimal,
+    /// Share of the account equity risked by a trade, used by the new sizing engine
+    #[serde(default = "default_risk_per_trade")]
+    pub risk_per_trade: Decimal,
+    /// Size entries from the average true range of the symbol instead of `order_quantity`
+    #[serde(default)]
+    pub volatility_sizing: Option<VolatilitySizing>,
+    /// Size entries by the Kelly criterion of the latest closed trades instead of
+    /// `order_quantity`, can't be combined with `volatility_sizing`
+    #[serde(default)]
+    pub kelly_sizing: Option<KellySizing>,
+    /// Whether entries costing more than the buying power are downsized or rejected before
+    /// they're sent to the broker
+    #[serde(default)]
+    pub buying_power_policy: BuyingPowerPolicy,
+    /// Tenant owning the strategy, usage and quotas are accounted per tenant
+    #[serde(default = "default_tenant_id")]
+    pub tenant_id: String,
+    /// Shared secret webhook alerts of the strategy are signed with, signed alerts also carry a
+    /// `timestamp` and a `nonce`. Alerts of strategies without a secret are accepted unsigned.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Multiplier applied to order quantities on Fridays, to carry less gap risk over weekends
+    #[serde(default)]
+    pub weekend_size_factor: Option<Decimal>,
+    /// Loss of a UTC day, realized and unrealized, disabling the strategy for the rest of the day
+    #[serde(default)]
+    pub max_daily_loss: Option<Decimal>,
+    /// Portfolio of `portfolios` the strategy is grouped into, its strategies share the risk
+    /// budget of the portfolio on top of their own limits
+    #[serde(default)]
+    pub portfolio: Option<String>,
+    /// Symbols the strategy may hold positions in at the same time, signals opening a position
+    /// in a further symbol are rejected until one is closed
+    #[serde(default)]
+    pub max_open_positions: Option<usize>,
+    /// Whether entries in the direction of a position the strategy already holds in the symbol
+    /// are accepted, rejected or scaled in up to a maximum
+    #[serde(default)]
+    pub duplicate_positions: DuplicatePositions,
+    /// Whether orders left partially filled without an execution for a while keep waiting, have
+    /// the rest canceled or are repriced at the latest quote
+    #[serde(default)]
+    pub partial_fills: PartialFills,
+    /// Time unfilled limit orders stay open for before they're canceled or replaced by market
+    /// orders
+    #[serde(default)]
+    pub order_ttl: Option<OrderTtl>,
+    /// Move the stop losses of open positions to break-even or trail them behind the price,
+    /// see `stops`
+    #[serde(default)]
+    pub stop_management: Option<StopManagement>,
+    /// Take profit of positions at several targets once their entry fills, see `ladder`
+    #[serde(default)]
+    pub take_profit_ladder: Option<TakeProfitLadder>,
+    /// Execute entries as chunks spaced in time, see `dca`
+    #[serde(default)]
+    pub dca: Option<DcaExecution>,
+    /// Time entry signals are ignored for after a trade closed with a loss, in minutes or bars
+    /// of the signal timeframe
+    #[serde(default)]
+    pub cooldown: Option<Cooldown>,
+    /// Seconds signals opening positions are held for, signals of the same symbol and direction
+    /// arriving meanwhile are coalesced into a single order with the latest of them
+    #[serde(default)]
+    pub debounce_window: Option<u64>,
+    /// Venues orders are routed to instead of the broker and account of the strategy, picked
+    /// per order among the ones trading its asset
+    #[serde(default)]
+    pub routing: Option<Routing>,
+    /// Venue orders go to while the account of the strategy is failing
+    #[serde(default)]
+    pub failover: Option<Failover>,
+    /// Predicates signals must pass before an order is created for them, all of them in order
+    #[serde(default)]
+    pub filters: Vec<SignalFilter>,
+    /// Time in force of orders, alerts may override it. Day orders for stocks and good until
+    /// canceled for crypto when unset, see `Strategy::order_time_in_force`
+    #[serde(default)]
+    pub time_in_force: Option<TimeInForce>,
+    /// Submit orders eligible for the pre- and after-market sessions, only for limit orders with
+    /// the `day` time in force. Alerts may override it.
+    #[serde(default)]
+    pub extended_hours: bool,
+    /// Simulate every order with the paper fill model next to its live execution, see the
+    /// divergence report
+    #[serde(default)]
+    pub shadow: bool,
+    /// Run the whole signal pipeline without submitting orders to the broker. Orders are recorded
+    /// with the `simulated` status.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Symbols the strategy trades, live quotes are streamed for them when market data is
+    /// configured
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Where the order parameters of `mapped` alerts are read from, for alerts of any shape
+    #[serde(default)]
+    pub alert_mapping: Option<AlertMapping>,
+    /// Actions run at the times of their cron expressions, e.g. flatten before the close
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Strategy {
+    /// Credential sets orders of the strategy may be submitted with, its own and the ones of its
+    /// routing venues.
+    pub fn accounts(&self) -> Vec<Option<&str>> {
+        let mut accounts = vec![self.account.as_deref()];
+        if let Some(routing) = &self.routing {
+            accounts.extend(routing.venues.iter().map(|venue| venue.account.as_deref()));
+        }
+        accounts.sort();
+        accounts.dedup();
+        accounts
+    }
+
+    /// Time in force of orders without one in their alert.
+    pub fn order_time_in_force(&self) -> TimeInForce {
+        self.time_in_force.unwrap_or(match self.currency_type {
+            CurrencyType::Crypto | CurrencyType::Forex => TimeInForce::Gtc,
+            CurrencyType::Stock => TimeInForce::Day,
+        })
+    }
+
+    /// Trading session of the assets of the strategy, `None` for crypto which trades around the
+    /// clock and forex which trades around the clock on weekdays, see `OandaClock`.
+    pub fn session<'a>(&self, session: &'a Session) -> Option<&'a Session> {
+        match self.currency_type {
+            CurrencyType::Crypto | CurrencyType::Forex => None,
+            CurrencyType::Stock => Some(session),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum CurrencyType {
     Crypto,
     Stock,
+    /// Currency pairs, traded on OANDA
+    Forex,
+}
+
+/// Quote currencies crypto pairs are traded against, longest first so `USDT` isn't split as
+/// `USD`
+const CRYPTO_QUOTE_CURRENCIES: [&str; 4] = ["USDT", "USDC", "USD", "BTC"];
+
+/// Symbol of `ticker` at the broker. TradingView sends crypto pairs without a separator, e.g.
+/// `BTCUSD`, Alpaca expects `BTC/USD`. OANDA expects currency pairs as `EUR_USD`.
+pub fn normalize_symbol(ticker: &str, currency_type: CurrencyType) -> String {
+    if currency_type == CurrencyType::Forex {
+        let ticker = ticker.replace('/', "_");
+        return match ticker.split_at_checked(3) {
+            Some((base, quote)) if !ticker.contains('_') && quote.len() == 3 => {
+                format!("{base}_{quote}")
+            }
+            _ => ticker,
+        };
+    }
+    if currency_type == CurrencyType::Stock || ticker.contains('/') {
+        return ticker.to_owned();
+    }
+    let pair = CRYPTO_QUOTE_CURRENCIES.iter().find_map(|quote| {
+        ticker
+            .strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| format!("{base}/{quote}"))
+    });
+
+    pair.unwrap_or_else(|| ticker.to_owned())
+}
+
+fn default_order_quantity() -> Decimal {
+    Decimal::ONE
+}
+
+fn default_risk_per_trade() -> Decimal {
+    Decimal::new(1, 2)
+}
+
+fn default_tenant_id() -> String {
+    DEFAULT_TENANT_ID.to_owned()
 }
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use rand_core::OsRng;
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::retry::Backoff;
+
+/// Delay before the first reconnect of a dropped stream, doubled by every further drop
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Time a connection has to stay up for its next drop to be reconnected after `RECONNECT_DELAY`
+/// again
+const STABLE_CONNECTION: Duration = Duration::from_secs(60);
+
+/// Time a stream may have missed messages in, from the time its state was last known to be in
+/// sync, the last message or resync, to the time it was subscribed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> chrono::Duration {
+        self.to - self.from
+    }
+}
+
+/// Messages of a subscribed connection of a stream, the connection ends with the first error.
+pub struct Connection<M> {
+    messages: BoxStream<'static, Result<M, String>>,
+    // NOTE: kept as long as messages are read, the connection is closed with it
+    _subscription: Box<dyn Send>,
+}
+
+impl<M> Connection<M> {
+    pub fn new(
+        messages: impl Stream<Item = Result<M, String>> + Send + 'static,
+        subscription: impl Send + 'static,
+    ) -> Self {
+        Self {
+            messages: messages.boxed(),
+            _subscription: Box::new(subscription),
+        }
+    }
+}
+
+/// Websocket stream of a broker kept connected by `supervise`.
+#[axum::async_trait]
+pub trait SupervisedStream: Send + Sync {
+    type Message: Send;
+
+    /// Connect and subscribe, failing when the stream can't be reached or refuses the
+    /// subscription.
+    async fn connect(&self) -> Result<Connection<Self::Message>, String>;
+
+    /// Apply a message of the stream.
+    async fn handle(&self, message: Self::Message);
+
+    /// Bring the state the messages keep up to date in sync through the REST API after `gap`.
+    /// Messages received meanwhile are handled once it's done.
+    async fn resync(&self, gap: Gap) -> Result<(), String>;
+}
+
+/// Keep `stream` connected until the process stops. Dropped connections are made again with
+/// exponential backoff and every connection is resynced once subscribed, so nothing happened while
+/// the stream was down is missed. Connections failing to resync are dropped as well.
+pub async fn supervise<S: SupervisedStream>(name: &str, stream: S) {
+    let backoff = Backoff::new(RECONNECT_DELAY, u8::MAX);
+    let mut drops: u8 = 0;
+    let mut in_sync_at = Utc::now();
+
+    loop {
+        let connected_at = Instant::now();
+        let error = match stream.connect().await {
+            Ok(mut connection) => {
+                let gap = Gap {
+                    from: in_sync_at,
+                    to: Utc::now(),
+                };
+                match stream.resync(gap).await {
+                    Ok(()) => {
+                        tracing::info!(
+                            "Stream {} connected, resynced a gap of {}s",
+                            name,
+                            gap.duration().num_seconds()
+                        );
+                        in_sync_at = gap.to;
+                        loop {
+                            match connection.messages.next().await {
+                                Some(Ok(message)) => {
+                                    in_sync_at = Utc::now();
+                                    stream.handle(message).await;
+                                }
+                                Some(Err(err)) => break err,
+                                None => break "stream ended".to_owned(),
+                            }
+                        }
+                    }
+                    Err(err) => format!("resync failed, {err}"),
+                }
+            }
+            Err(err) => err,
+        };
+
+        if connected_at.elapsed() >= STABLE_CONNECTION {
+            drops = 0;
+        }
+        drops = drops.saturating_add(1);
+        let delay = backoff.delay(drops, &mut OsRng);
+        tracing::warn!(
+            "Stream {} dropped, reconnecting in {}ms, error: {}",
+            name,
+            delay.as_millis(),
+            error
+        );
+        sleep(delay).await;
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fmt::Display,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+
+use crate::retry::Backoff;
+
+/// Delay before the first restart of a crashed task, doubled by every further crash
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Time a task has to run for its next crash to be restarted after `RESTART_DELAY` again
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Crashed, waiting for its restart
+    Restarting,
+    /// Returned without an error, finished tasks aren't restarted
+    Finished,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// Restarts since startup
+    pub restarts: u32,
+    /// Error or panic message of the last crash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Time the task was last started
+    pub started_at: DateTime<Utc>,
+}
+
+/// Result of a run of a supervised task, tasks failing with an error crashed.
+pub trait TaskOutcome {
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl TaskOutcome for () {
+    fn into_result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<E: Display> TaskOutcome for Result<(), E> {
+    fn into_result(self) -> Result<(), String> {
+        self.map_err(|err| err.to_string())
+    }
+}
+
+/// Owner of the background tasks of the process. Tasks crashing, by panicking or failing with
+/// an error, are logged and started again with exponential backoff, their status is reported by
+/// the deep health check. Clones share their tasks.
+#[derive(Clone, Default)]
+pub struct TaskSupervisor {
+    tasks: Arc<Mutex<BTreeMap<String, TaskStatus>>>,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the task made by `task` in the background under `name`, which should be unique.
+    /// `task` is called again for every restart.
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: TaskOutcome,
+    {
+        let name = name.into();
+        let tasks = Arc::clone(&self.tasks);
+        // NOTE: registered right away so the task is reported before it first runs
+        update(&tasks, &name, |_| {});
+        tokio::spawn(async move {
+            let backoff = Backoff::new(RESTART_DELAY, u8::MAX);
+            let mut crashes: u8 = 0;
+            loop {
+                let started_at = Instant::now();
+                update(&tasks, &name, |status| {
+                    status.state = TaskState::Running;
+                    status.started_at = Utc::now();
+                });
+
+                // NOTE: run on a task of its own so panics are caught as errors of its handle
+                let run = task();
+                let error = match tokio::spawn(async move { run.await.into_result() }).await {
+                    Ok(Ok(())) => {
+                        update(&tasks, &name, |status| status.state = TaskState::Finished);
+                        return;
+                    }
+                    Ok(Err(err)) => err,
+                    Err(err) if err.is_panic() => panic_message(err.into_panic()),
+                    Err(err) => err.to_string(),
+                };
+
+                if started_at.elapsed() >= STABLE_RUN {
+                    crashes = 0;
+                }
+                crashes = crashes.saturating_add(1);
+                let delay = backoff.delay(crashes, &mut OsRng);
+                tracing::error!(
+                    "Task {} crashed, restarting in {}ms, error: {}",
+                    name,
+                    delay.as_millis(),
+                    error
+                );
+                update(&tasks, &name, |status| {
+                    status.state = TaskState::Restarting;
+                    status.restarts += 1;
+                    status.last_error = Some(error);
+                });
+                tokio::time::sleep(delay).await;
+            }
+        });
+    }
+
+    /// Status of every task spawned so far, by name.
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        let tasks = self.tasks.lock().unwrap_or_else(|err| err.into_inner());
+        tasks.values().cloned().collect()
+    }
+}
+
+fn update(
+    tasks: &Mutex<BTreeMap<String, TaskStatus>>,
+    name: &str,
+    change: impl FnOnce(&mut TaskStatus),
+) {
+    let mut tasks = tasks.lock().unwrap_or_else(|err| err.into_inner());
+    let status = tasks.entry(name.to_owned()).or_insert_with(|| TaskStatus {
+        name: name.to_owned(),
+        state: TaskState::Running,
+        restarts: 0,
+        last_error: None,
+        started_at: Utc::now(),
+    });
+    change(status);
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => format!("panicked: {message}"),
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => format!("panicked: {message}"),
+            Err(_) => "panicked".to_owned(),
+        },
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    sync::Mutex,
+};
+
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+const SYMBOL_WINDOW: Duration = Duration::from_secs(60);
+const STRATEGY_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Cap of the throttle an order was held back by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleLimit {
+    /// Orders per symbol over a sliding minute
+    Symbol(usize),
+    /// Orders per strategy over a sliding hour
+    Strategy(usize),
+}
+
+impl fmt::Display for ThrottleLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Symbol(limit) => write!(f, "{limit} orders per symbol per minute"),
+            Self::Strategy(limit) => write!(f, "{limit} orders per strategy per hour"),
+        }
+    }
+}
+
+/// Times of the orders taken per key within `period`.
+struct Window<K> {
+    limit: Option<usize>,
+    period: Duration,
+    orders: HashMap<K, VecDeque<Instant>>,
+}
+
+impl<K: Eq + Hash> Window<K> {
+    fn new(limit: Option<usize>, period: Duration) -> Self {
+        Self {
+            limit,
+            period,
+            orders: HashMap::new(),
+        }
+    }
+
+    /// Limit of `key` when it reached it, dropping the orders which left the window first.
+    fn is_full(&mut self, key: K, now: Instant) -> Option<usize> {
+        let limit = self.limit?;
+        let orders = self.orders.entry(key).or_default();
+        while orders
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= self.period)
+        {
+            orders.pop_front();
+        }
+        (orders.len() >= limit).then_some(limit)
+    }
+
+    fn push(&mut self, key: K, now: Instant) {
+        if self.limit.is_some() {
+            self.orders.entry(key).or_default().push_back(now);
+        }
+    }
+}
+
+/// Limits orders per symbol over a sliding minute, across all strategies, and per strategy over a
+/// sliding hour. Protects against correlated strategies firing on the same candle and stacking
+/// exposure in a single symbol, and against a misconfigured alert loop spamming the broker.
+/// Orders are counted in memory, by every instance on its own.
+pub struct OrderThrottle {
+    windows: Mutex<(Window<String>, Window<Uuid>)>,
+}
+
+impl OrderThrottle {
+    /// No limit is applied where `orders_per_symbol_per_minute` or
+    /// `orders_per_strategy_per_hour` is not set.
+    pub fn new(
+        orders_per_symbol_per_minute: Option<usize>,
+        orders_per_strategy_per_hour: Option<usize>,
+    ) -> Self {
+        Self {
+            windows: Mutex::new((
+                Window::new(orders_per_symbol_per_minute, SYMBOL_WINDOW),
+                Window::new(orders_per_strategy_per_hour, STRATEGY_WINDOW),
+            )),
+        }
+    }
+
+    /// Take a slot for a new order of `strategy_id` in `symbol`. Fails with the limit reached
+    /// when the symbol or the strategy has no slot left, no slot is taken then.
+    pub fn try_acquire(&self, symbol: &str, strategy_id: Uuid) -> Result<(), ThrottleLimit> {
+        let now = Instant::now();
+        let symbol = symbol.to_uppercase();
+        let mut windows = self.windows.lock().unwrap_or_else(|err| err.into_inner());
+        let (symbols, strategies) = &mut *windows;
+
+        if let Some(limit) = symbols.is_full(symbol.clone(), now) {
+            return Err(ThrottleLimit::Symbol(limit));
+        }
+        if let Some(limit) = strategies.is_full(strategy_id, now) {
+            return Err(ThrottleLimit::Strategy(limit));
+        }
+
+        symbols.push(symbol, now);
+        strategies.push(strategy_id, now);
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use strum_macros::AsRefStr;
+use thiserror::Error as ThisError;
+use tokio::sync::Notify;
+
+use crate::{
+    api::{alert::SignalType, objects::Broker},
+    hooks::{PostTradeHook, PreTradeHook, TradeHooks},
+    order::Fill,
+    supervisor::TaskSupervisor,
+    trade_signal::TradeSignal,
+};
+
+/// Processing of a trade signal run by the executor.
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Queues of the trade signals waiting to be processed, an entry and an exit lane per broker.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutorConfig {
+    /// Entry signals waiting per broker at most, further entries are rejected until there's room
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    /// Entry signals of a broker processed at the same time
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Entry concurrency of the brokers it differs for, keyed by broker
+    #[serde(default)]
+    pub brokers: HashMap<String, usize>,
+    /// Exit and flatten signals waiting per broker at most, see `Lane::Exit`
+    #[serde(default = "default_capacity")]
+    pub exit_capacity: usize,
+    /// Exit and flatten signals of a broker processed at the same time
+    #[serde(default = "default_exit_concurrency")]
+    pub exit_concurrency: usize,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_capacity(),
+            concurrency: default_concurrency(),
+            brokers: HashMap::new(),
+            exit_capacity: default_capacity(),
+            exit_concurrency: default_exit_concurrency(),
+        }
+    }
+}
+
+impl ExecutorConfig {
+    pub fn concurrency(&self, broker: &Broker) -> usize {
+        self.brokers
+            .get(broker.as_ref())
+            .copied()
+            .unwrap_or(self.concurrency)
+    }
+}
+
+fn default_capacity() -> usize {
+    1000
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_exit_concurrency() -> usize {
+    2
+}
+
+/// Class of a signal by what it does to the position of its strategy. Exits and flattens are
+/// processed on the exit lane of their broker, flattens first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Closes the whole position, the signal is for at least the quantity held
+    Flatten,
+    /// Reduces a position or changes its protective orders
+    Exit,
+    /// Opens or extends a position
+    Entry,
+}
+
+impl Priority {
+    /// Priority of a signal given the position of its strategy in the ticker, negative when
+    /// short.
+    pub fn of(trade_signal: &TradeSignal, position: Decimal) -> Self {
+        let exits = match trade_signal.signal_type {
+            SignalType::StopLossUpdate(_) => return Self::Exit,
+            SignalType::OpenLong(_) => position < Decimal::ZERO,
+            SignalType::OpenShort(_) => position > Decimal::ZERO,
+        };
+        let flattens = trade_signal
+            .quantity
+            .is_some_and(|quantity| quantity >= position.abs());
+        match (exits, flattens) {
+            (true, true) => Self::Flatten,
+            (true, false) => Self::Exit,
+            (false, _) => Self::Entry,
+        }
+    }
+
+    pub fn lane(self) -> Lane {
+        match self {
+            Self::Flatten | Self::Exit => Lane::Exit,
+            Self::Entry => Lane::Entry,
+        }
+    }
+
+    /// Priority of a signal given the position its strategy built with its fills.
+    pub async fn of_signal(db: &PgPool, trade_signal: &TradeSignal) -> Result<Self, sqlx::Error> {
+        let position = Fill::position(db, trade_signal.strategy.id, &trade_signal.ticker).await?;
+        Ok(Self::of(trade_signal, position))
+    }
+}
+
+/// Queue of the signals of a broker with workers of its own. Exits never wait for the workers
+/// or the capacity of the entries, so protective exits aren't stuck behind a backlog of them.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, AsRefStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Lane {
+    Exit,
+    Entry,
+}
+
+#[derive(Debug, ThisError)]
+#[error("Execution queue of {0} is full")]
+pub struct QueueFull(pub String);
+
+/// Depth and throughput of a lane of a broker since startup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    pub broker: String,
+    pub lane: Lane,
+    /// Signals waiting
+    pub waiting: usize,
+    /// Signals being processed
+    pub running: usize,
+    pub concurrency: usize,
+    pub processed: u64,
+    /// Signals rejected as the lane was full
+    pub rejected: u64,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Signals waiting in the order of their priority, in the order they came within one
+    jobs: VecDeque<(Priority, Job)>,
+    running: usize,
+    processed: u64,
+    rejected: u64,
+}
+
+struct Queue {
+    broker: String,
+    lane: Lane,
+    capacity: usize,
+    concurrency: usize,
+    state: Mutex<QueueState>,
+    /// Notified once per queued signal, so one idle worker takes it
+    queued: Notify,
+}
+
+impl Queue {
+    fn state(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Queue `job` behind the signals of its priority and of more urgent ones.
+    fn push(&self, priority: Priority, job: Job) -> Result<(), QueueFull> {
+        {
+            let mut state = self.state();
+            if state.jobs.len() >= self.capacity {
+                state.rejected += 1;
+                return Err(QueueFull(format!("{} {}", self.broker, self.lane.as_ref())));
+            }
+            let index = state
+                .jobs
+                .iter()
+                .position(|(queued, _)| *queued > priority)
+                .unwrap_or(state.jobs.len());
+            state.jobs.insert(index, (priority, job));
+        }
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// Next signal to process, the most urgent first, counted as running.
+    fn next(&self) -> Option<Job> {
+        let mut state = self.state();
+        let (_, job) = state.jobs.pop_front()?;
+        state.running += 1;
+        Some(job)
+    }
+
+    fn finished(&self) {
+        let mut state = self.state();
+        state.running -= 1;
+        state.processed += 1;
+    }
+
+    fn metrics(&self) -> QueueMetrics {
+        let state = self.state();
+        QueueMetrics {
+            broker: self.broker.clone(),
+            lane: self.lane,
+            waiting: state.jobs.len(),
+            running: state.running,
+            concurrency: self.concurrency,
+            processed: state.processed,
+            rejected: state.rejected,
+        }
+    }
+}
+
+/// Processes the trade signals of every broker with bounded queues and a fixed number of
+/// workers per broker and lane, so a burst of alerts can't flood the broker or the database with
+/// concurrent orders.
+pub struct TradeExecutor {
+    config: ExecutorConfig,
+    tasks: TaskSupervisor,
+    hooks: Arc<TradeHooks>,
+    /// Queues by broker and lane, started on the first signal of their lane
+    queues: Mutex<HashMap<(String, Lane), Arc<Queue>>>,
+}
+
+impl TradeExecutor {
+    /// Executor running its workers on `tasks`.
+    pub fn new(config: ExecutorConfig, tasks: TaskSupervisor) -> Self {
+        Self {
+            config,
+            tasks,
+            hooks: Arc::default(),
+            queues: Mutex::default(),
+        }
+    }
+
+    /// Add the hooks to `hooks`, the ones the core processing the signals runs.
+    pub fn with_hooks(mut self, hooks: Arc<TradeHooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Run `hook` on every order before it's recorded and submitted, after the hooks added
+    /// before it. Any hook can veto the order.
+    pub fn add_pre_trade_hook(&self, hook: Arc<dyn PreTradeHook>) {
+        self.hooks.add_pre_trade(hook);
+    }
+
+    /// Run `hook` on every order once it's submitted or failed to, after the hooks added before
+    /// it.
+    pub fn add_post_trade_hook(&self, hook: Arc<dyn PostTradeHook>) {
+        self.hooks.add_post_trade(hook);
+    }
+
+    fn queue(&self, broker: &Broker, lane: Lane) -> Arc<Queue> {
+        let mut queues = self.queues.lock().unwrap_or_else(|err| err.into_inner());
+        let key = (broker.as_ref().to_owned(), lane);
+        let queue = queues.entry(key).or_insert_with(|| {
+            let (capacity, concurrency) = match lane {
+                Lane::Exit => (self.config.exit_capacity, self.config.exit_concurrency),
+                Lane::Entry => (self.config.capacity, self.config.concurrency(broker)),
+            };
+            let queue = Arc::new(Queue {
+                broker: broker.as_ref().to_owned(),
+                lane,
+                capacity,
+                concurrency: concurrency.max(1),
+                state: Mutex::default(),
+                queued: Notify::new(),
+            });
+            for worker in 0..queue.concurrency {
+                let queue = Arc::clone(&queue);
+                self.tasks.spawn(
+                    format!(
+                        "trade_executor.{}.{}.{}",
+                        queue.broker,
+                        lane.as_ref(),
+                        worker
+                    ),
+                    move || work(Arc::clone(&queue)),
+                );
+            }
+            queue
+        });
+        Arc::clone(queue)
+    }
+
+    /// Queue the processing of a signal of `broker` on the lane of its priority, rejected while
+    /// the lane is full.
+    pub fn submit(&self, broker: &Broker, priority: Priority, job: Job) -> Result<(), QueueFull> {
+        self.queue(broker, priority.lane()).push(priority, job)
+    }
+
+    /// Metrics of the lanes started so far, by broker, exit lanes first.
+    pub fn metrics(&self) -> Vec<QueueMetrics> {
+        let queues = self.queues.lock().unwrap_or_else(|err| err.into_inner());
+        let mut metrics: Vec<QueueMetrics> = queues.values().map(|queue| queue.metrics()).collect();
+        metrics.sort_by(|a, b| (&a.broker, a.lane).cmp(&(&b.broker, b.lane)));
+        metrics
+    }
+}
+
+/// Process the signals of the queue one at a time until the process stops. Signals run on a task
+/// of their own, so a panicking one doesn't take the worker down.
+async fn work(queue: Arc<Queue>) {
+    loop {
+        let Some(job) = queue.next() else {
+            queue.queued.notified().await;
+            continue;
+        };
+        if let Err(err) = tokio::spawn(job).await {
+            tracing::error!(
+                "Trade signal processing of {} on the {} lane failed, error: {:?}",
+                queue.broker,
+                queue.lane.as_ref(),
+                err
+            );
+        }
+        queue.finished();
+    }
+}
@@ -1,13 +1,16 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
 
 use crate::{
     api::{
-        alert::{SignalType, BarData, WebhookAlertData, SignalData},
+        alert::{BarData, SignalType, WebhookAlertData},
         error::ApiError,
     },
     app_config::AppConfig,
-    clients::BrokerClient,
-    strategy::Strategy,
+    options::OrderLeg,
+    order::TimeInForce,
+    strategy::{normalize_symbol, Strategy},
 };
 
 #[derive(Debug, Clone)]
@@ -20,9 +23,37 @@ pub struct TradeSignal {
     pub trail_stop_price: Option<Decimal>,
     pub bar_data: BarData,
     pub time: DateTime<Utc>,
+    /// Id of the webhook request the signal came with
+    pub request_id: Option<String>,
+    /// Quantity overriding the sizing of the strategy
+    pub quantity: Option<Decimal>,
+    /// Dollar amount of a notional order, overrides the sizing of the strategy
+    pub notional: Option<Decimal>,
+    /// Time in force overriding the one of the strategy
+    pub time_in_force: Option<TimeInForce>,
+    /// Limit price, market orders are submitted without one
+    pub limit_price: Option<Decimal>,
+    /// Extended hours eligibility overriding the one of the strategy
+    pub extended_hours: Option<bool>,
+    /// Option contracts to trade instead of the ticker, which is their underlying
+    pub legs: Vec<OrderLeg>,
 }
 
 impl TradeSignal {
+    /// Id of the signal derived from the strategy, ticker, kind and time of its alert and the
+    /// request it came with. Replays of dead letters come with a request of their own.
+    pub fn signal_id(&self) -> Uuid {
+        let name = format!(
+            "{}|{}|{}|{}|{}",
+            self.ticker,
+            self.signal_type.as_ref(),
+            self.bar_data.time.to_rfc3339(),
+            self.time.to_rfc3339(),
+            self.request_id.as_deref().unwrap_or_default()
+        );
+        Uuid::new_v5(&self.strategy.id, name.as_bytes())
+    }
+
     pub fn from_alert_data(
         alert_data: WebhookAlertData,
         config: &AppConfig,
@@ -50,12 +81,20 @@ impl TradeSignal {
 
         Ok(Self {
             strategy: validated_strategy.clone(),
-            ticker: alert_data.ticker,
+            ticker: normalize_symbol(&alert_data.ticker, validated_strategy.currency_type),
             timeframe: alert_data.timeframe,
             exchange: alert_data.exchange,
-            signal_data: alert_data.signal_data,
+            signal_type: alert_data.signal_type,
+            trail_stop_price: alert_data.trail_stop_price,
             bar_data: alert_data.bar_data,
             time: alert_data.time,
+            request_id: None,
+            quantity: alert_data.quantity,
+            notional: alert_data.notional,
+            time_in_force: alert_data.time_in_force,
+            limit_price: alert_data.limit_price,
+            extended_hours: alert_data.extended_hours,
+            legs: alert_data.legs,
         })
     }
 }
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use apca::api::v2::updates::{OrderUpdate, OrderUpdates};
+use futures::StreamExt;
+
+use crate::{
+    clients::Clients,
+    core::Core,
+    streams::{self, Connection, Gap, SupervisedStream},
+};
+
+/// Order updates of the global Alpaca account, so executions are recorded as they happen instead
+/// of at the next order sync of the core. Resynced by syncing every open local order.
+///
+/// NOTE: orders of the credential sets strategies reference are only synced by the core
+pub struct TradeUpdates {
+    core: Arc<Core>,
+    clients: Arc<Clients>,
+}
+
+impl TradeUpdates {
+    pub fn new(core: Arc<Core>, clients: Arc<Clients>) -> Self {
+        Self { core, clients }
+    }
+}
+
+#[axum::async_trait]
+impl SupervisedStream for TradeUpdates {
+    type Message = OrderUpdate;
+
+    async fn connect(&self) -> Result<Connection<OrderUpdate>, String> {
+        // Taken on every connect, so rotated credentials are picked up
+        let (stream, subscription) = self
+            .clients
+            .alpaca()
+            .subscribe::<OrderUpdates>()
+            .await
+            .map_err(|err| format!("{err:?}"))?;
+
+        let updates = stream.filter_map(|message| async move {
+            match message {
+                Ok(Ok(update)) => Some(Ok(update)),
+                Ok(Err(err)) => {
+                    tracing::warn!("Malformed trade update, error: {}", err);
+                    None
+                }
+                Err(err) => Some(Err(format!("{err:?}"))),
+            }
+        });
+        Ok(Connection::new(updates, subscription))
+    }
+
+    async fn handle(&self, update: OrderUpdate) {
+        let client_order_id = &update.order.client_order_id;
+        if let Err(err) = self.core.sync_order_update(client_order_id).await {
+            tracing::error!(
+                "Failed to sync order {} after a trade update, error: {:?}",
+                client_order_id,
+                err
+            );
+        }
+    }
+
+    async fn resync(&self, _gap: Gap) -> Result<(), String> {
+        self.core.sync_orders().await.map_err(|err| err.to_string())
+    }
+}
+
+/// Stream the order updates of the global Alpaca account until the process stops, see
+/// `streams::supervise`.
+pub async fn run_trade_updates(core: Arc<Core>, clients: Arc<Clients>) {
+    streams::supervise("trade_updates", TradeUpdates::new(core, clients)).await
+}
@@ -0,0 +1,1047 @@
+use std::sync::Arc;
+
+use apca::api::v2::{
+    account_activities::{ActivityReq, Direction},
+    order::ChangeReq,
+    orders::{OrdersReq, Status as OrdersStatus},
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use rust_decimal::Decimal;
+use serde::{
+    de::{self, DeserializeOwned},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    api::objects::{Account, Activity, Asset, AssetClass, Clock, Order, Position},
+    app_config::{Tradier, TradingEnvironment},
+    clients::{num_to_decimal, order_number, BrokerClient, BrokerClientError},
+    market_data::LiveQuote,
+    options::{OptionContract, OrderLeg},
+    order::{NewOrder, OrderSide, TimeInForce},
+    schedules::eastern_time,
+};
+
+/// Shares of the underlying an option contract is for, premiums are quoted per share
+const OPTION_MULTIPLIER: u32 = 100;
+/// Events of the account history fetched when the request has no page size
+const HISTORY_PAGE_SIZE: usize = 100;
+
+/// Balances of a Tradier account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierAccount {
+    pub account_number: String,
+    /// `margin`, `cash` or `pdt`
+    pub account_type: String,
+    pub total_equity: Decimal,
+    pub total_cash: Decimal,
+    /// Balances of margin accounts
+    #[serde(default)]
+    pub margin: Option<TradierMargin>,
+    /// Balances of pattern day trader accounts
+    #[serde(default)]
+    pub pdt: Option<TradierMargin>,
+    /// Balances of cash accounts
+    #[serde(default)]
+    pub cash: Option<TradierCash>,
+}
+
+impl TradierAccount {
+    /// Buying power of stocks, the cash available to trade for cash accounts.
+    pub fn buying_power(&self) -> Decimal {
+        self.margin
+            .as_ref()
+            .or(self.pdt.as_ref())
+            .map(|margin| margin.stock_buying_power)
+            .or(self.cash.as_ref().map(|cash| cash.cash_available))
+            .unwrap_or(self.total_cash)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierMargin {
+    pub stock_buying_power: Decimal,
+    pub option_buying_power: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierCash {
+    pub cash_available: Decimal,
+}
+
+/// Equity or option Tradier has quotes of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierSecurity {
+    pub symbol: String,
+    pub description: String,
+    #[serde(rename = "exch")]
+    pub exchange: String,
+    /// `stock`, `etf`, `index` or `option`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Tradier only sells short securities which are easy to borrow
+    #[serde(default)]
+    pub easy_to_borrow: bool,
+}
+
+/// Market clock of the regular session, resolved from the Tradier market calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierClock {
+    pub open: bool,
+    pub next_open: DateTime<Utc>,
+    pub next_close: DateTime<Utc>,
+}
+
+impl TradierClock {
+    /// Clock at `now` of the market days of `calendar`, in the order of their dates. `None` when
+    /// the calendar has no session opening after `now`.
+    pub fn from_calendar(calendar: &[TradierMarketDay], now: DateTime<Utc>) -> Option<Self> {
+        let sessions: Vec<_> = calendar
+            .iter()
+            .filter_map(TradierMarketDay::session)
+            .collect();
+
+        Some(Self {
+            open: sessions
+                .iter()
+                .any(|(open, close)| *open <= now && now < *close),
+            next_open: sessions
+                .iter()
+                .map(|(open, _)| *open)
+                .find(|open| *open > now)?,
+            next_close: sessions
+                .iter()
+                .map(|(_, close)| *close)
+                .find(|close| *close > now)?,
+        })
+    }
+}
+
+/// Day of the Tradier market calendar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierMarketDay {
+    pub date: NaiveDate,
+    /// `open` or `closed`
+    pub status: String,
+    /// Regular session of open days, in the time of New York
+    #[serde(default)]
+    pub open: Option<TradierHours>,
+}
+
+impl TradierMarketDay {
+    /// Open and close of the regular session, `None` when the market is closed.
+    fn session(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let hours = self.open.as_ref().filter(|_| self.status == "open")?;
+        let time = |time: &str| NaiveTime::parse_from_str(time, "%H:%M").ok();
+        Some((
+            eastern_time(self.date, time(&hours.start)?),
+            eastern_time(self.date, time(&hours.end)?),
+        ))
+    }
+}
+
+/// Start and end of a session as `HH:MM`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierHours {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierPosition {
+    /// Equity symbol or OCC symbol of an option contract
+    pub symbol: String,
+    /// Shares or contracts held, negative when short
+    pub quantity: Decimal,
+    /// Cost of the whole position, negative when short
+    pub cost_basis: Decimal,
+    pub date_acquired: DateTime<Utc>,
+}
+
+impl TradierPosition {
+    /// Average price the position was entered at, per share of the underlying for options.
+    pub fn average_price(&self) -> Decimal {
+        self.cost_basis
+            .checked_div(self.quantity * multiplier(&self.symbol))
+            .unwrap_or_default()
+    }
+}
+
+/// Shares of `symbol` a unit of its quantity is, contracts of options are for 100 shares.
+fn multiplier(symbol: &str) -> Decimal {
+    match symbol.parse::<OptionContract>() {
+        Ok(_) => Decimal::from(OPTION_MULTIPLIER),
+        Err(_) => Decimal::ONE,
+    }
+}
+
+/// Stop leg of an order submitted with a stop loss, see `TradierOrderRequest::form`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierStopLoss {
+    pub id: u64,
+    pub stop_price: Decimal,
+    /// Status of the leg, `open` until it triggers, see `TradierOrder::status`
+    pub status: String,
+}
+
+/// Equity or option order of a Tradier account. Orders with a stop loss are one triggers other
+/// orders at Tradier, they're resolved to their entry leg and its stop.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierOrder {
+    /// Numeric id of the order, see `clients::order_uuid`
+    pub id: u64,
+    pub client_order_id: String,
+    /// `equity`, `option` or `oto`
+    pub class: String,
+    /// Symbol of equity orders, the underlying of options orders
+    pub symbol: String,
+    /// OCC symbol of the contract of options orders
+    pub option_symbol: Option<String>,
+    /// `buy`, `sell`, `sell_short` or `buy_to_cover`, of options `buy_to_open`, `buy_to_close`,
+    /// `sell_to_open` or `sell_to_close`
+    pub side: String,
+    /// `market`, `limit`, `stop` or `stop_limit`
+    pub kind: String,
+    /// Ordered shares or contracts
+    pub quantity: Decimal,
+    pub status: String,
+    /// `day`, `gtc`, `pre` or `post`
+    pub duration: String,
+    /// Limit price
+    pub price: Option<Decimal>,
+    pub filled_quantity: Decimal,
+    pub average_fill_price: Option<Decimal>,
+    pub create_date: DateTime<Utc>,
+    /// Time of the latest change of the order, of its last execution once it's filled
+    pub transaction_date: Option<DateTime<Utc>>,
+    pub stop_loss: Option<TradierStopLoss>,
+}
+
+impl TradierOrder {
+    /// Status in the snake case status format of Alpaca orders, which local orders are kept in.
+    pub fn status(&self) -> &'static str {
+        status(&self.status)
+    }
+
+    pub fn side(&self) -> OrderSide {
+        if self.side.starts_with("buy") {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        }
+    }
+}
+
+/// Tradier status `status` in the status format of Alpaca orders.
+fn status(status: &str) -> &'static str {
+    match status {
+        "open" => "new",
+        "pending" => "pending_new",
+        "partially_filled" => "partially_filled",
+        "filled" => "filled",
+        "expired" => "expired",
+        "canceled" => "canceled",
+        "rejected" | "error" => "rejected",
+        _ => "unknown",
+    }
+}
+
+/// Tag of an order submitted with `client_order_id`. Tags only take letters, digits and dashes,
+/// the dot of client order ids is sent as a dash.
+fn tag(client_order_id: &str) -> String {
+    client_order_id.replace('.', "-")
+}
+
+/// Client order id of an order tagged with `tag`, see `order::client_order_id`.
+fn client_order_id(tag: &str) -> String {
+    tag.replace('-', ".")
+}
+
+/// Order request of the Tradier API, see `order_request`. The side Tradier takes depends on the
+/// position held, it's only resolved when the order is sent, see `TradierOrderRequest::form`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradierOrderRequest {
+    /// Symbol of equity orders, the underlying of options orders
+    pub symbol: String,
+    /// Option contract of options orders, orders of more than one leg are rejected
+    pub legs: Vec<OrderLeg>,
+    pub side: OrderSide,
+    /// Shares or contracts
+    pub quantity: Decimal,
+    pub limit_price: Option<Decimal>,
+    pub time_in_force: TimeInForce,
+    pub stop_loss: Option<Decimal>,
+    /// See `tag`
+    pub tag: Option<String>,
+}
+
+impl TradierOrderRequest {
+    /// Symbol of the position the order trades, the OCC symbol of options orders.
+    fn position_symbol(&self) -> String {
+        match self.legs.first() {
+            Some(leg) => leg.contract.occ_symbol(),
+            None => self.symbol.clone(),
+        }
+    }
+
+    /// Form of the request when `held` shares or contracts of the position are held. Buys of
+    /// short positions cover them and sells of flat or short positions sell short. The stop
+    /// loss is only sent with orders opening or adding to a position, as the stop leg of a one
+    /// triggers other order.
+    fn form(&self, held: Decimal) -> Result<Vec<(String, String)>, BrokerClientError> {
+        let duration = match self.time_in_force {
+            TimeInForce::Day => "day",
+            TimeInForce::Gtc => "gtc",
+            time_in_force => {
+                return Err(BrokerClientError::TradierError(format!(
+                    "Orders must be day or gtc orders, not {}",
+                    time_in_force.as_ref()
+                )))
+            }
+        };
+        if self.legs.len() > 1 {
+            return Err(BrokerClientError::TradierError(format!(
+                "Options orders must have a single leg, not {}",
+                self.legs.len()
+            )));
+        }
+        let opens = match self.side {
+            OrderSide::Buy => !held.is_sign_negative() || held.is_zero(),
+            OrderSide::Sell => held <= Decimal::ZERO,
+        };
+        let option = self.legs.first();
+        let side = match (option.is_some(), self.side, opens) {
+            (false, OrderSide::Buy, true) => "buy",
+            (false, OrderSide::Buy, false) => "buy_to_cover",
+            (false, OrderSide::Sell, true) => "sell_short",
+            (false, OrderSide::Sell, false) => "sell",
+            (true, OrderSide::Buy, true) => "buy_to_open",
+            (true, OrderSide::Buy, false) => "buy_to_close",
+            (true, OrderSide::Sell, true) => "sell_to_open",
+            (true, OrderSide::Sell, false) => "sell_to_close",
+        };
+        let kind = match self.limit_price {
+            Some(_) => "limit",
+            None => "market",
+        };
+
+        let mut form = vec![("duration".to_owned(), duration.to_owned())];
+        form.extend(self.tag.clone().map(|tag| ("tag".to_owned(), tag)));
+        let stop_loss = self.stop_loss.filter(|_| opens && option.is_none());
+        let Some(stop_price) = stop_loss else {
+            let class = match option {
+                Some(leg) => {
+                    form.push(("option_symbol".to_owned(), leg.contract.occ_symbol()));
+                    "option"
+                }
+                None => "equity",
+            };
+            form.extend([
+                ("class".to_owned(), class.to_owned()),
+                ("symbol".to_owned(), self.symbol.clone()),
+                ("side".to_owned(), side.to_owned()),
+                ("quantity".to_owned(), self.quantity.to_string()),
+                ("type".to_owned(), kind.to_owned()),
+            ]);
+            form.extend(
+                self.limit_price
+                    .map(|price| ("price".to_owned(), price.to_string())),
+            );
+            return Ok(form);
+        };
+
+        let stop_side = match self.side {
+            OrderSide::Buy => "sell",
+            OrderSide::Sell => "buy_to_cover",
+        };
+        form.push(("class".to_owned(), "oto".to_owned()));
+        for (index, (kind, side)) in [(kind, side), ("stop", stop_side)].into_iter().enumerate() {
+            form.extend([
+                (format!("symbol[{index}]"), self.symbol.clone()),
+                (format!("side[{index}]"), side.to_owned()),
+                (format!("quantity[{index}]"), self.quantity.to_string()),
+                (format!("type[{index}]"), kind.to_owned()),
+            ]);
+        }
+        form.extend(
+            self.limit_price
+                .map(|price| ("price[0]".to_owned(), price.to_string())),
+        );
+        form.push(("stop[1]".to_owned(), stop_price.to_string()));
+        Ok(form)
+    }
+}
+
+/// Tradier order request of a broker-agnostic order. Options orders trade the contract of their
+/// leg, the order quantity times its ratio, on the side of the leg. They're sent without the stop
+/// loss, which is a price of the underlying, not of the premium.
+pub fn order_request(new_order: &NewOrder) -> TradierOrderRequest {
+    let (side, quantity) = match new_order.legs.as_slice() {
+        [leg] => (leg.side, new_order.quantity * Decimal::from(leg.ratio)),
+        _ => (new_order.side, new_order.quantity),
+    };
+
+    TradierOrderRequest {
+        symbol: new_order.ticker.clone(),
+        legs: new_order.legs.clone(),
+        side,
+        quantity,
+        limit_price: new_order.limit_price,
+        time_in_force: new_order.time_in_force,
+        stop_loss: new_order
+            .stop_loss_price
+            .filter(|_| new_order.legs.is_empty()),
+        tag: Some(tag(&new_order.client_order_id)),
+    }
+}
+
+/// Items of a listing, Tradier sends a single item by itself instead of in a list.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(items) => items,
+        OneOrMany::One(item) => vec![item],
+    })
+}
+
+/// Listing of a response, `None` for the string `null` Tradier sends for empty listings.
+fn or_null<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OrNull<T> {
+        Listing(T),
+        Null(String),
+    }
+
+    match Option::<OrNull<T>>::deserialize(deserializer)? {
+        Some(OrNull::Listing(listing)) => Ok(Some(listing)),
+        Some(OrNull::Null(text)) if text != "null" => {
+            Err(de::Error::custom(format!("invalid listing {text}")))
+        }
+        Some(OrNull::Null(_)) | None => Ok(None),
+    }
+}
+
+#[derive(Default, Deserialize)]
+struct WireOrder {
+    id: u64,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    option_symbol: Option<String>,
+    #[serde(default)]
+    side: Option<String>,
+    #[serde(default)]
+    quantity: Option<Decimal>,
+    status: String,
+    #[serde(default)]
+    duration: Option<String>,
+    #[serde(default)]
+    price: Option<Decimal>,
+    #[serde(default)]
+    stop_price: Option<Decimal>,
+    #[serde(default)]
+    avg_fill_price: Option<Decimal>,
+    #[serde(default)]
+    exec_quantity: Option<Decimal>,
+    create_date: DateTime<Utc>,
+    #[serde(default)]
+    transaction_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    leg: Vec<WireOrder>,
+}
+
+impl From<WireOrder> for TradierOrder {
+    fn from(mut order: WireOrder) -> Self {
+        let class = order.class.take().unwrap_or_default();
+        let (id, tag, create_date) = (order.id, order.tag.take(), order.create_date);
+        let mut legs = std::mem::take(&mut order.leg).into_iter();
+        let (entry, stop_loss) = match class.as_str() {
+            "oto" => (legs.next().unwrap_or_default(), legs.next()),
+            _ => (order, None),
+        };
+
+        TradierOrder {
+            id,
+            client_order_id: tag.as_deref().map(client_order_id).unwrap_or_default(),
+            class,
+            symbol: entry.symbol.unwrap_or_default(),
+            option_symbol: entry.option_symbol,
+            side: entry.side.unwrap_or_default(),
+            kind: entry.kind.unwrap_or_default(),
+            quantity: entry.quantity.unwrap_or_default(),
+            status: entry.status,
+            duration: entry.duration.unwrap_or_default(),
+            price: entry.price,
+            filled_quantity: entry.exec_quantity.unwrap_or_default(),
+            // NOTE: orders without fills have an average price of zero
+            average_fill_price: entry.avg_fill_price.filter(|price| !price.is_zero()),
+            create_date,
+            transaction_date: entry.transaction_date,
+            stop_loss: stop_loss.map(|leg| TradierStopLoss {
+                id: leg.id,
+                stop_price: leg.stop_price.unwrap_or_default(),
+                status: leg.status,
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OrderResponse {
+    order: WireOrder,
+}
+
+#[derive(Deserialize)]
+struct OrderList {
+    #[serde(deserialize_with = "one_or_many")]
+    order: Vec<WireOrder>,
+}
+
+#[derive(Deserialize)]
+struct OrdersResponse {
+    #[serde(default, deserialize_with = "or_null")]
+    orders: Option<OrderList>,
+}
+
+#[derive(Deserialize)]
+struct CreatedOrder {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct CreateResponse {
+    order: CreatedOrder,
+}
+
+#[derive(Deserialize)]
+struct BalancesResponse {
+    balances: TradierAccount,
+}
+
+#[derive(Deserialize)]
+struct PositionList {
+    #[serde(deserialize_with = "one_or_many")]
+    position: Vec<TradierPosition>,
+}
+
+#[derive(Deserialize)]
+struct PositionsResponse {
+    #[serde(default, deserialize_with = "or_null")]
+    positions: Option<PositionList>,
+}
+
+#[derive(Deserialize)]
+struct Quote {
+    #[serde(flatten)]
+    security: TradierSecurity,
+    #[serde(default)]
+    bid: Option<Decimal>,
+    #[serde(default)]
+    ask: Option<Decimal>,
+    /// Milliseconds since the epoch
+    #[serde(default)]
+    bid_date: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct QuoteList {
+    #[serde(default, deserialize_with = "one_or_many")]
+    quote: Vec<Quote>,
+}
+
+#[derive(Deserialize)]
+struct QuotesResponse {
+    #[serde(default, deserialize_with = "or_null")]
+    quotes: Option<QuoteList>,
+}
+
+#[derive(Deserialize)]
+struct SecurityList {
+    #[serde(deserialize_with = "one_or_many")]
+    security: Vec<TradierSecurity>,
+}
+
+#[derive(Deserialize)]
+struct SecuritiesResponse {
+    #[serde(default, deserialize_with = "or_null")]
+    securities: Option<SecurityList>,
+}
+
+#[derive(Deserialize)]
+struct CalendarDays {
+    #[serde(deserialize_with = "one_or_many")]
+    day: Vec<TradierMarketDay>,
+}
+
+#[derive(Deserialize)]
+struct Calendar {
+    days: CalendarDays,
+}
+
+#[derive(Deserialize)]
+struct CalendarResponse {
+    calendar: Calendar,
+}
+
+#[derive(Deserialize)]
+struct EventList {
+    #[serde(deserialize_with = "one_or_many")]
+    event: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct HistoryResponse {
+    #[serde(default, deserialize_with = "or_null")]
+    history: Option<EventList>,
+}
+
+/// Client of the brokerage and market data REST API of a Tradier account.
+pub struct TradierClient {
+    http: reqwest::Client,
+    base_url: String,
+    account_id: String,
+    access_token: String,
+}
+
+impl TradierClient {
+    pub fn new(config: &Tradier, environment: TradingEnvironment) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: config
+                .base_url(environment)
+                .trim_end_matches('/')
+                .to_owned(),
+            account_id: config.account_id.clone(),
+            access_token: config.access_token.clone(),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}/v1{}", self.base_url, path))
+            .bearer_auth(&self.access_token)
+            .header("Accept", "application/json")
+    }
+
+    /// Request of `path` below the account.
+    fn account_request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request(method, &format!("/accounts/{}{}", self.account_id, path))
+    }
+
+    /// Response of `request`. Rate limited requests, server errors and requests without a
+    /// response fail as unavailable, so they count towards the circuit breaker and are retried.
+    async fn json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, BrokerClientError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|err| BrokerClientError::TradierUnavailable(err.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return response
+                .json()
+                .await
+                .map_err(|err| BrokerClientError::TradierError(err.to_string()));
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let message = format!("{status}: {body}");
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Err(BrokerClientError::TradierUnavailable(message))
+        } else {
+            Err(BrokerClientError::TradierError(message))
+        }
+    }
+
+    async fn fetch_order(&self, id: &str) -> Result<TradierOrder, BrokerClientError> {
+        let OrderResponse { order } = self
+            .json(
+                self.account_request(Method::GET, &format!("/orders/{id}"))
+                    .query(&[("includeTags", "true")]),
+            )
+            .await?;
+        Ok(order.into())
+    }
+
+    /// Orders of the account of the current day and the open ones of earlier days.
+    async fn fetch_orders(&self) -> Result<Vec<TradierOrder>, BrokerClientError> {
+        let OrdersResponse { orders } = self
+            .json(
+                self.account_request(Method::GET, "/orders")
+                    .query(&[("includeTags", "true")]),
+            )
+            .await?;
+        Ok(orders
+            .map(|orders| orders.order.into_iter().map(TradierOrder::from).collect())
+            .unwrap_or_default())
+    }
+
+    async fn fetch_positions(&self) -> Result<Vec<TradierPosition>, BrokerClientError> {
+        let PositionsResponse { positions } = self
+            .json(self.account_request(Method::GET, "/positions"))
+            .await?;
+        Ok(positions
+            .map(|positions| positions.position)
+            .unwrap_or_default())
+    }
+
+    async fn fetch_position(&self, symbol: &str) -> Result<TradierPosition, BrokerClientError> {
+        self.fetch_positions()
+            .await?
+            .into_iter()
+            .find(|position| position.symbol == symbol)
+            .ok_or_else(|| BrokerClientError::TradierError(format!("No position in {symbol}")))
+    }
+
+    async fn fetch_quotes(&self, symbols: &str) -> Result<Vec<Quote>, BrokerClientError> {
+        let QuotesResponse { quotes } = self
+            .json(
+                self.request(Method::GET, "/markets/quotes")
+                    .query(&[("symbols", symbols)]),
+            )
+            .await?;
+        Ok(quotes.map(|quotes| quotes.quote).unwrap_or_default())
+    }
+
+    /// Securities which are easy to borrow, the only ones Tradier sells short.
+    async fn fetch_easy_to_borrow(&self) -> Result<Vec<TradierSecurity>, BrokerClientError> {
+        let SecuritiesResponse { securities } =
+            self.json(self.request(Method::GET, "/markets/etb")).await?;
+        Ok(securities
+            .map(|securities| securities.security)
+            .unwrap_or_default())
+    }
+
+    async fn fetch_calendar(
+        &self,
+        year: i32,
+        month: u32,
+    ) -> Result<Vec<TradierMarketDay>, BrokerClientError> {
+        let CalendarResponse { calendar } = self
+            .json(
+                self.request(Method::GET, "/markets/calendar")
+                    .query(&[("year", year.to_string()), ("month", format!("{month:02}"))]),
+            )
+            .await?;
+        Ok(calendar.days.day)
+    }
+
+    /// Latest quote of `symbol`, an equity or the OCC symbol of an option contract. `None` when
+    /// Tradier has no quote of it.
+    pub async fn fetch_quote(&self, symbol: &str) -> Result<Option<LiveQuote>, BrokerClientError> {
+        Ok(self
+            .fetch_quotes(symbol)
+            .await?
+            .into_iter()
+            .find(|quote| quote.security.symbol == symbol)
+            .and_then(|quote| {
+                Some(LiveQuote {
+                    bid: quote.bid?,
+                    ask: quote.ask?,
+                    time: quote
+                        .bid_date
+                        .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+                        .unwrap_or_else(Utc::now),
+                })
+            }))
+    }
+}
+
+#[axum::async_trait]
+impl BrokerClient for Arc<TradierClient> {
+    // NOTE: requests other than new orders are the ones of Alpaca, so callers querying orders
+    // and changing them work with every broker
+    type ActivitiesRequest = ActivityReq;
+    type NewOrderRequest = TradierOrderRequest;
+    type OrdersRequest = OrdersReq;
+    type OrderUdateRequest = ChangeReq;
+
+    fn order_request(&self, new_order: &NewOrder) -> Self::NewOrderRequest {
+        order_request(new_order)
+    }
+
+    fn supports_options(&self) -> bool {
+        true
+    }
+
+    async fn get_account(&self) -> Result<Account, BrokerClientError> {
+        let BalancesResponse { balances } = self
+            .json(self.account_request(Method::GET, "/balances"))
+            .await?;
+        Ok(Account::TradierAccount(balances))
+    }
+
+    /// Events of the account history, of every type, on the days of the time range of the
+    /// request.
+    async fn get_activities(
+        &self,
+        activities_req: Self::ActivitiesRequest,
+    ) -> Result<Vec<Activity>, BrokerClientError> {
+        let page_size = activities_req.page_size.unwrap_or(HISTORY_PAGE_SIZE);
+        let mut query = vec![("limit", page_size.to_string())];
+        query.extend(
+            activities_req
+                .after
+                .map(|after| ("start", after.date_naive().to_string())),
+        );
+        query.extend(
+            activities_req
+                .until
+                .map(|until| ("end", until.date_naive().to_string())),
+        );
+        let HistoryResponse { history } = self
+            .json(self.account_request(Method::GET, "/history").query(&query))
+            .await?;
+
+        // NOTE: Tradier lists the latest event first
+        let mut events = history.map(|history| history.event).unwrap_or_default();
+        if activities_req.direction == Direction::Ascending {
+            events.reverse();
+        }
+        Ok(events.into_iter().map(Activity::TradierEvent).collect())
+    }
+
+    async fn get_asset(&self, symbol: String) -> Result<Asset, BrokerClientError> {
+        let mut security = self
+            .fetch_quotes(&symbol)
+            .await?
+            .into_iter()
+            .find(|quote| quote.security.symbol == symbol)
+            .map(|quote| quote.security)
+            .ok_or_else(|| BrokerClientError::TradierError(format!("Unknown symbol {symbol}")))?;
+        security.easy_to_borrow = self
+            .fetch_easy_to_borrow()
+            .await?
+            .iter()
+            .any(|easy_to_borrow| easy_to_borrow.symbol == symbol);
+        Ok(Asset::TradierSecurity(security))
+    }
+
+    /// Equities which are easy to borrow, Tradier has no listing of every tradable security and
+    /// trades no crypto or forex.
+    async fn get_assets(&self, class: AssetClass) -> Result<Vec<Asset>, BrokerClientError> {
+        if class != AssetClass::UsEquity {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .fetch_easy_to_borrow()
+            .await?
+            .into_iter()
+            .map(|security| {
+                Asset::TradierSecurity(TradierSecurity {
+                    easy_to_borrow: true,
+                    ..security
+                })
+            })
+            .collect())
+    }
+
+    /// Clock of the calendars of the current and the next month, so sessions of the next month
+    /// are found at the end of the current one.
+    async fn get_clock(&self) -> Result<Clock, BrokerClientError> {
+        let now = Utc::now();
+        let next_month = (now.date_naive().with_day(1).unwrap_or_default()) + Duration::days(31);
+        let mut calendar = self.fetch_calendar(now.year(), now.month()).await?;
+        calendar.extend(
+            self.fetch_calendar(next_month.year(), next_month.month())
+                .await?,
+        );
+
+        TradierClock::from_calendar(&calendar, now)
+            .map(Clock::TradierClock)
+            .ok_or_else(|| BrokerClientError::TradierError("No upcoming market session".to_owned()))
+    }
+
+    async fn get_position(&self, symbol: String) -> Result<Position, BrokerClientError> {
+        let position = self.fetch_position(&symbol).await?;
+        Ok(Position::TradierPosition(position))
+    }
+
+    async fn get_positions(&self) -> Result<Vec<Position>, BrokerClientError> {
+        Ok(self
+            .fetch_positions()
+            .await?
+            .into_iter()
+            .map(Position::TradierPosition)
+            .collect())
+    }
+
+    /// Close the position of `symbol`, an equity or the OCC symbol of an option contract, with a
+    /// market order for the day.
+    async fn delete_position(&self, symbol: String) -> Result<Order, BrokerClientError> {
+        let position = self.fetch_position(&symbol).await?;
+        let side = if position.quantity.is_sign_negative() {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        let (symbol, legs) = match symbol.parse::<OptionContract>() {
+            Ok(contract) => (
+                contract.underlying.clone(),
+                vec![OrderLeg {
+                    contract,
+                    side,
+                    ratio: 1,
+                }],
+            ),
+            Err(_) => (symbol, Vec::new()),
+        };
+
+        self.create_order(TradierOrderRequest {
+            symbol,
+            legs,
+            side,
+            quantity: position.quantity.abs(),
+            limit_price: None,
+            time_in_force: TimeInForce::Day,
+            stop_loss: None,
+            tag: None,
+        })
+        .await
+    }
+
+    /// Order tagged with `client_id` among the orders of the current day and the open ones,
+    /// Tradier has no lookup of orders by tag.
+    async fn get_order_by_client_id(&self, client_id: String) -> Result<Order, BrokerClientError> {
+        self.fetch_orders()
+            .await?
+            .into_iter()
+            .find(|order| order.client_order_id == client_id)
+            .map(Order::TradierOrder)
+            .ok_or_else(|| {
+                BrokerClientError::TradierError(format!("No order with client id {client_id}"))
+            })
+    }
+
+    async fn get_orders(
+        &self,
+        orders_req: Self::OrdersRequest,
+    ) -> Result<Vec<Order>, BrokerClientError> {
+        let mut orders: Vec<_> = self
+            .fetch_orders()
+            .await?
+            .into_iter()
+            .filter(|order| {
+                let open = matches!(
+                    order.status.as_str(),
+                    "open" | "partially_filled" | "pending"
+                );
+                match orders_req.status {
+                    OrdersStatus::Open => open,
+                    OrdersStatus::Closed => !open,
+                    OrdersStatus::All => true,
+                }
+            })
+            .filter(|order| {
+                orders_req.symbols.is_empty() || orders_req.symbols.contains(&order.symbol)
+            })
+            .map(Order::TradierOrder)
+            .collect();
+        if let Some(limit) = orders_req.limit {
+            orders.truncate(limit);
+        }
+        Ok(orders)
+    }
+
+    async fn create_order(
+        &self,
+        new_order_req: Self::NewOrderRequest,
+    ) -> Result<Order, BrokerClientError> {
+        let held = self
+            .fetch_positions()
+            .await?
+            .into_iter()
+            .find(|position| position.symbol == new_order_req.position_symbol())
+            .map(|position| position.quantity)
+            .unwrap_or_default();
+        let CreateResponse { order } = self
+            .json(
+                self.account_request(Method::POST, "/orders")
+                    .form(&new_order_req.form(held)?),
+            )
+            .await?;
+
+        let order = self.fetch_order(&order.id.to_string()).await?;
+        Ok(Order::TradierOrder(order))
+    }
+
+    /// Change the limit or stop price and the duration of an open order or the stop leg of one,
+    /// Tradier doesn't change quantities.
+    async fn update_order(
+        &self,
+        order_id: Uuid,
+        update_req: Self::OrderUdateRequest,
+    ) -> Result<Order, BrokerClientError> {
+        if update_req.quantity.is_some() {
+            return Err(BrokerClientError::TradierError(
+                "Quantities of orders can't be changed".to_owned(),
+            ));
+        }
+        let mut form = Vec::new();
+        form.extend(
+            update_req
+                .limit_price
+                .as_ref()
+                .map(|price| ("price", num_to_decimal(price).to_string())),
+        );
+        form.extend(
+            update_req
+                .stop_price
+                .as_ref()
+                .map(|price| ("stop", num_to_decimal(price).to_string())),
+        );
+        form.extend(update_req.time_in_force.map(|time_in_force| {
+            let duration = match time_in_force {
+                apca::api::v2::order::TimeInForce::UntilCanceled => "gtc",
+                _ => "day",
+            };
+            ("duration", duration.to_owned())
+        }));
+
+        let id = order_number(order_id);
+        let _: Value = self
+            .json(
+                self.account_request(Method::PUT, &format!("/orders/{id}"))
+                    .form(&form),
+            )
+            .await?;
+        let order = self.fetch_order(&id).await?;
+        Ok(Order::TradierOrder(order))
+    }
+
+    async fn delete_order(&self, order_id: Uuid) -> Result<(), BrokerClientError> {
+        let _: Value = self
+            .json(self.account_request(
+                Method::DELETE,
+                &format!("/orders/{}", order_number(order_id)),
+            ))
+            .await?;
+        Ok(())
+    }
+}
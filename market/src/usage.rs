@@ -0,0 +1,231 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use strum_macros::AsRefStr;
+use uuid::Uuid;
+
+use crate::{api::error::ApiError, app_config::AppConfig};
+
+#[derive(Debug, Clone, Copy, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum UsageKind {
+    Signal,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UsageQuery {
+    /// Billing month in `YYYY-MM` format
+    pub month: String,
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub date: NaiveDate,
+    pub signals: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    pub month: String,
+    pub signals: i64,
+    /// Strategies which received at least one signal during the month
+    pub active_strategies: i64,
+    pub configured_strategies: usize,
+    pub configured_brokers: usize,
+    pub daily: Vec<DailyUsage>,
+}
+
+pub async fn record(
+    db: &PgPool,
+    tenant_id: &str,
+    kind: UsageKind,
+    strategy_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO usage_events (usage_event_id, tenant_id, kind, strategy_id, recorded_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(tenant_id)
+    .bind(kind.as_ref())
+    .bind(strategy_id)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+async fn count_since(
+    db: &PgPool,
+    tenant_id: &str,
+    kind: UsageKind,
+    since: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM usage_events WHERE tenant_id = $1 AND kind = $2 AND recorded_at >= \
+         $3",
+    )
+    .bind(tenant_id)
+    .bind(kind.as_ref())
+    .bind(since)
+    .fetch_one(db)
+    .await
+}
+
+fn configured_strategies(config: &AppConfig, tenant_id: &str) -> usize {
+    config
+        .strategies
+        .iter()
+        .filter(|strategy| strategy.tenant_id == tenant_id)
+        .count()
+}
+
+fn configured_brokers(config: &AppConfig, tenant_id: &str) -> usize {
+    config
+        .strategies
+        .iter()
+        .filter(|strategy| strategy.tenant_id == tenant_id)
+        .map(|strategy| strategy.broker.as_ref())
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+/// Check every quota of the tenant before a new signal is accepted.
+pub async fn check_quotas(
+    db: &PgPool,
+    config: &AppConfig,
+    tenant_id: &str,
+) -> Result<(), ApiError> {
+    let Some(quotas) = config.quotas.get(tenant_id) else {
+        return Ok(());
+    };
+
+    if let Some(max_strategies) = quotas.max_strategies {
+        let strategies = configured_strategies(config, tenant_id);
+        if strategies > max_strategies {
+            return Err(ApiError::Forbidden(format!(
+                "Tenant {tenant_id} has {strategies} strategies, quota is {max_strategies}"
+            )));
+        }
+    }
+
+    if let Some(max_brokers) = quotas.max_brokers {
+        let brokers = configured_brokers(config, tenant_id);
+        if brokers > max_brokers {
+            return Err(ApiError::Forbidden(format!(
+                "Tenant {tenant_id} uses {brokers} brokers, quota is {max_brokers}"
+            )));
+        }
+    }
+
+    if let Some(signals_per_day) = quotas.signals_per_day {
+        let day_start = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc();
+        let signals = count_since(db, tenant_id, UsageKind::Signal, day_start).await?;
+        if signals >= signals_per_day {
+            return Err(ApiError::TooManyRequests(format!(
+                "Tenant {tenant_id} reached daily quota of {signals_per_day} signals"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, FromRow)]
+struct TenantDailyUsage {
+    tenant_id: String,
+    date: NaiveDate,
+    signals: i64,
+}
+
+/// Monthly usage per tenant, suitable as an input of a billing system.
+pub async fn export(
+    db: &PgPool,
+    config: &AppConfig,
+    query: &UsageQuery,
+) -> Result<Vec<TenantUsage>, ApiError> {
+    let month_start = NaiveDate::parse_from_str(&format!("{}-01", query.month), "%Y-%m-%d")
+        .map_err(|_| ApiError::BadRequest(format!("Invalid month - {}", query.month)))?;
+    let month_end = month_start + Months::new(1);
+    let (from, to) = (
+        month_start
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc(),
+        month_end.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+    );
+
+    let daily = sqlx::query_as::<_, TenantDailyUsage>(
+        r#"
+        SELECT tenant_id, (recorded_at AT TIME ZONE 'UTC')::date AS date, COUNT(*) AS signals
+        FROM usage_events
+        WHERE kind = $1 AND recorded_at >= $2 AND recorded_at < $3
+        GROUP BY tenant_id, date
+        ORDER BY tenant_id, date
+        "#,
+    )
+    .bind(UsageKind::Signal.as_ref())
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await?;
+
+    let active_strategies = sqlx::query_as::<_, (String, i64)>(
+        r#"
+        SELECT tenant_id, COUNT(DISTINCT strategy_id)
+        FROM usage_events
+        WHERE kind = $1 AND recorded_at >= $2 AND recorded_at < $3
+        GROUP BY tenant_id
+        "#,
+    )
+    .bind(UsageKind::Signal.as_ref())
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await?;
+
+    let mut tenants: BTreeSet<&str> = config
+        .strategies
+        .iter()
+        .map(|strategy| strategy.tenant_id.as_str())
+        .collect();
+    tenants.extend(daily.iter().map(|usage| usage.tenant_id.as_str()));
+
+    Ok(tenants
+        .into_iter()
+        .filter(|tenant_id| query.tenant_id.as_deref().is_none_or(|id| id == *tenant_id))
+        .map(|tenant_id| {
+            let daily: Vec<DailyUsage> = daily
+                .iter()
+                .filter(|usage| usage.tenant_id == tenant_id)
+                .map(|usage| DailyUsage {
+                    date: usage.date,
+                    signals: usage.signals,
+                })
+                .collect();
+
+            TenantUsage {
+                tenant_id: tenant_id.to_owned(),
+                month: query.month.clone(),
+                signals: daily.iter().map(|usage| usage.signals).sum(),
+                active_strategies: active_strategies
+                    .iter()
+                    .find(|(id, _)| id == tenant_id)
+                    .map_or(0, |(_, count)| *count),
+                configured_strategies: configured_strategies(config, tenant_id),
+                configured_brokers: configured_brokers(config, tenant_id),
+                daily,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,150 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{api::error::ApiError, api_keys::Role, app_config::AppConfig, strategy::Strategy};
+
+/// Trader owning the strategies whose `tenant_id` is the id of the user.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct User {
+    pub user_id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewUser {
+    /// Tenant id the strategies of the user are configured with
+    pub user_id: String,
+    pub name: String,
+}
+
+pub async fn create(db: &PgPool, new_user: &NewUser) -> Result<User, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (user_id, name, created_at)
+        VALUES ($1, $2, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(&new_user.user_id)
+    .bind(&new_user.name)
+    .fetch_one(db)
+    .await
+}
+
+pub async fn list(db: &PgPool) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at")
+        .fetch_all(db)
+        .await
+}
+
+/// Routes on data users share, like the broker accounts and the service itself, which only
+/// operators may access. Routes scoped to the strategies of the caller are open to users, see
+/// `Caller::scope`.
+pub fn is_operator_route(method: &Method, path: &str) -> bool {
+    // NOTE: the order and fill history and order previews only see the strategies of the caller,
+    // the other order and position routes and rebalances, sized from the positions of the
+    // account, reach the broker accounts
+    if matches!(
+        (method, path.trim_end_matches('/')),
+        (&Method::GET, "/orders" | "/positions/history") | (&Method::POST, "/orders/preview")
+    ) {
+        return false;
+    }
+    let segment = path
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    matches!(
+        segment,
+        "account"
+            | "activities"
+            | "order"
+            | "orders"
+            | "position"
+            | "positions"
+            | "portfolio"
+            | "dashboard"
+            | "reconcile"
+            | "rebalance"
+            | "reports"
+            | "metrics"
+            | "feature-flags"
+            | "broker-cache"
+            | "health"
+            | "users"
+    )
+}
+
+/// Authenticated caller of a request, available to handlers as an extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    pub role: Role,
+    /// User whose data the caller sees, the data of every user when `None`
+    pub user_id: Option<String>,
+}
+
+impl Caller {
+    /// Caller seeing the data of every user.
+    pub fn operator(role: Role) -> Self {
+        Self {
+            role,
+            user_id: None,
+        }
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.user_id.is_none()
+    }
+
+    /// Whether the strategy belongs to the user of the caller.
+    pub fn owns(&self, strategy: &Strategy) -> bool {
+        self.user_id
+            .as_deref()
+            .is_none_or(|user_id| strategy.tenant_id == user_id)
+    }
+
+    /// Whether the caller sees data of strategy `id`. Operators also see the data of strategies
+    /// no longer configured.
+    pub fn sees(&self, config: &AppConfig, id: Uuid) -> bool {
+        self.is_operator() || self.strategy(config, id).is_ok()
+    }
+
+    /// Strategy `id` of the caller. Strategies of other users are reported unknown, so their ids
+    /// can't be probed.
+    pub fn strategy<'a>(&self, config: &'a AppConfig, id: Uuid) -> Result<&'a Strategy, ApiError> {
+        config
+            .strategies
+            .iter()
+            .find(|strategy| strategy.id == id && self.owns(strategy))
+            .ok_or_else(|| ApiError::NotFound(format!("Unknown strategy - {id}")))
+    }
+
+    /// Strategies whose data a query returns, strategy `id` only when it's set. `None` when
+    /// nothing is filtered out.
+    pub fn scope(
+        &self,
+        config: &AppConfig,
+        id: Option<Uuid>,
+    ) -> Result<Option<Vec<Uuid>>, ApiError> {
+        if self.is_operator() {
+            return Ok(id.map(|id| vec![id]));
+        }
+        if let Some(id) = id {
+            return Ok(Some(vec![self.strategy(config, id)?.id]));
+        }
+
+        Ok(Some(
+            config
+                .strategies
+                .iter()
+                .filter(|strategy| self.owns(strategy))
+                .map(|strategy| strategy.id)
+                .collect(),
+        ))
+    }
+}
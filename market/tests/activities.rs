@@ -0,0 +1,206 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use market::{
+    activities::{self, AccountActivity, ActivityKind, ActivityQuery, CashFlow},
+    api::objects::{Activity, Broker},
+    recorder::{Interaction, PlaybackClient},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+fn interaction(response: Option<Value>, error: Option<&str>) -> Interaction {
+    Interaction {
+        interaction_id: Uuid::new_v4(),
+        broker: "tradier".to_string(),
+        operation: "get_activities".to_string(),
+        request: Json(Value::Null),
+        response: response.map(Json),
+        error: error.map(str::to_owned),
+        recorded_at: Utc::now(),
+    }
+}
+
+fn query(kind: Option<ActivityKind>) -> ActivityQuery {
+    ActivityQuery {
+        broker: Broker::Tradier,
+        account: None,
+        from: Some(DateTime::<Utc>::from_str("2024-03-01T00:00:00Z").unwrap()),
+        to: None,
+        kind,
+    }
+}
+
+#[test]
+fn activities_of_every_broker_are_normalized() {
+    let normalize = |activity: Value, broker| {
+        let activity: Activity = serde_json::from_value(activity).unwrap();
+        AccountActivity::normalize(&activity, &broker, None, "USD").unwrap()
+    };
+
+    let fill = normalize(
+        json!({ "AlpacaActivity": {
+            "id": "20240305143000000::8e4b",
+            "activity_type": "FILL",
+            "transaction_time": "2024-03-05T14:30:00Z",
+            "type": "fill",
+            "price": "190.12",
+            "qty": "10",
+            "side": "buy",
+            "symbol": "AAPL",
+            "leaves_qty": "0",
+            "order_id": "904837e3-3b76-47ec-b432-046db621571b",
+            "cum_qty": "10"
+        }}),
+        Broker::Alpaca,
+    );
+    assert_eq!(fill.kind, "fill");
+    assert_eq!(fill.amount, Decimal::new(-19012, 1));
+
+    let dividend = normalize(
+        json!({ "AlpacaActivity": {
+            "id": "20240315000000000::1a2b",
+            "activity_type": "DIV",
+            "date": "2024-03-15",
+            "net_amount": "2.40",
+            "symbol": "AAPL",
+            "qty": "10",
+            "per_share_amount": "0.24"
+        }}),
+        Broker::Alpaca,
+    );
+    assert_eq!(
+        (dividend.kind.as_str(), dividend.activity_type.as_str()),
+        ("dividend", "DIV")
+    );
+    assert_eq!(dividend.amount, Decimal::new(240, 2));
+
+    // Commissions reduce the balance although they are sent positive
+    let fill = normalize(
+        json!({ "OandaTransaction": {
+            "id": "6410",
+            "time": "2024-03-05T14:30:00.000000000Z",
+            "type": "ORDER_FILL",
+            "instrument": "EUR_USD",
+            "units": "-1000",
+            "price": "1.08512",
+            "pl": "12.5000",
+            "financing": "-0.3000",
+            "commission": "0.2000"
+        }}),
+        Broker::Oanda,
+    );
+    assert_eq!(fill.kind, "fill");
+    assert_eq!(fill.quantity, Some(Decimal::from(-1000)));
+    assert_eq!(fill.amount, Decimal::new(120, 1));
+
+    let order = normalize(
+        json!({ "OandaTransaction": {
+            "id": "6409",
+            "time": "2024-03-05T14:30:00.000000000Z",
+            "type": "MARKET_ORDER",
+            "instrument": "EUR_USD",
+            "units": "-1000"
+        }}),
+        Broker::Oanda,
+    );
+    assert_eq!(
+        (order.kind.as_str(), order.amount),
+        ("other", Decimal::ZERO)
+    );
+
+    let event = json!({ "TradierEvent": {
+        "amount": -1.5,
+        "date": "2024-03-04T00:00:00Z",
+        "type": "fee",
+        "fee": { "description": "ORDER FEE" }
+    }});
+    let fee = normalize(event.clone(), Broker::Tradier);
+    assert_eq!(
+        (fee.kind.as_str(), fee.amount),
+        ("fee", Decimal::new(-15, 1))
+    );
+    // Tradier events have no id, theirs is derived from the event
+    assert_eq!(
+        fee.activity_id,
+        normalize(event, Broker::Tradier).activity_id
+    );
+}
+
+#[sqlx::test]
+async fn activities_are_kept_when_the_broker_is_unavailable(pool: PgPool) {
+    let events = json!([
+        { "TradierEvent": {
+            "amount": -1901.2,
+            "date": "2024-03-05T00:00:00Z",
+            "type": "trade",
+            "trade": { "commission": 0.0, "price": 190.12, "quantity": 10.0, "symbol": "AAPL" }
+        }},
+        { "TradierEvent": {
+            "amount": 2.4,
+            "date": "2024-03-15T00:00:00Z",
+            "type": "dividend",
+            "dividend": { "description": "AAPL CASH DIV", "quantity": 10.0 }
+        }},
+        { "TradierEvent": {
+            "amount": 5000.0,
+            "date": "2024-02-20T00:00:00Z",
+            "type": "ach",
+            "ach": { "description": "DEPOSIT" }
+        }}
+    ]);
+    let client = PlaybackClient::new(vec![
+        interaction(Some(events.clone()), None),
+        // Events are fetched again from the day of the latest one
+        interaction(Some(json!([events[1]])), None),
+        interaction(None, Some("service unavailable")),
+    ]);
+    let sync = || activities::sync(&pool, &client, &Broker::Tradier, None, "USD");
+
+    assert_eq!(sync().await.unwrap(), 3);
+    assert_eq!(sync().await.unwrap(), 0);
+    assert!(sync().await.is_err());
+
+    let stored = activities::fetch(&pool, &query(None)).await.unwrap();
+    let kinds: Vec<&str> = stored
+        .iter()
+        .map(|activity| activity.kind.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["fill", "dividend"]);
+    assert_eq!(stored[0].symbol.as_deref(), Some("AAPL"));
+    assert_eq!(stored[0].quantity, Some(Decimal::from(10)));
+
+    let dividends = activities::fetch(&pool, &query(Some(ActivityKind::Dividend)))
+        .await
+        .unwrap();
+    assert_eq!(dividends.len(), 1);
+
+    let mut all_time = query(None);
+    all_time.from = None;
+    assert_eq!(
+        activities::cash_flow(&pool, &all_time).await.unwrap(),
+        vec![
+            CashFlow {
+                kind: "dividend".to_owned(),
+                currency: "USD".to_owned(),
+                activities: 1,
+                amount: Decimal::new(24, 1),
+            },
+            CashFlow {
+                kind: "fill".to_owned(),
+                currency: "USD".to_owned(),
+                activities: 1,
+                amount: Decimal::new(-19012, 1),
+            },
+            CashFlow {
+                kind: "transfer".to_owned(),
+                currency: "USD".to_owned(),
+                activities: 1,
+                amount: Decimal::from(5000),
+            },
+        ]
+    );
+}
@@ -0,0 +1,203 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use chrono::Utc;
+use market::{
+    admin::StrategyStatus,
+    api::alert::WebhookAlertData,
+    app_config::AppConfig,
+    audit::AuditEntry,
+    build_routes,
+    dead_letters::{DeadLetter, DeadLetterQuery},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_state;
+
+fn request(method: Method, uri: &str, api_key: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, api_key)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn alert(config: &AppConfig) -> WebhookAlertData {
+    let time = Utc::now();
+    serde_json::from_value(json!({
+        "strategy_id": config.strategies[0].id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+        "time": time,
+    }))
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn strategies_disabled_at_runtime(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let admin_key = config.api_key.clone();
+    let strategy_id = config.strategies[0].id;
+    let app = make_test_state(pool.clone(), config.clone()).await;
+    let routes = build_routes(app.clone());
+
+    let response = routes
+        .clone()
+        .oneshot(request(
+            Method::PUT,
+            &format!("/strategies/{strategy_id}/enabled"),
+            &admin_key,
+            json!({"enabled": false, "reason": "broker maintenance"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let status: StrategyStatus = serde_json::from_slice(&body).unwrap();
+    assert!(!status.enabled);
+    assert_eq!(
+        status.disabled_reason.as_deref(),
+        Some("broker maintenance")
+    );
+
+    // Signals of the strategy are rejected until it's enabled again
+    let result = app
+        .accept_alert(alert(&config), "request".to_string())
+        .await;
+    assert!(result.is_err());
+
+    let response = routes
+        .clone()
+        .oneshot(request(
+            Method::PUT,
+            &format!("/strategies/{strategy_id}/enabled"),
+            &admin_key,
+            json!({"enabled": true}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = routes
+        .oneshot(request(Method::GET, "/strategies", &admin_key, json!(null)))
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let statuses: Vec<StrategyStatus> = serde_json::from_slice(&body).unwrap();
+    assert!(statuses
+        .iter()
+        .any(|status| status.id == strategy_id && status.enabled));
+
+    // Audited like the enable and disable routes
+    let entries = AuditEntry::fetch_for(&pool, &format!("strategy:{strategy_id}"))
+        .await
+        .unwrap();
+    let actions: Vec<_> = entries.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(actions, ["strategy.enable", "strategy.disable"]);
+}
+
+#[sqlx::test]
+async fn strategies_switched_with_audit_entries(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let admin_key = config.api_key.clone();
+    let strategy_id = config.strategies[0].id;
+    let routes = build_routes(make_test_state(pool.clone(), config).await);
+
+    let response = routes
+        .clone()
+        .oneshot(request(
+            Method::POST,
+            &format!("/strategies/{strategy_id}/disable"),
+            &admin_key,
+            json!({"reason": "runaway losses"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let status: StrategyStatus = serde_json::from_slice(&body).unwrap();
+    assert!(!status.enabled);
+    assert_eq!(status.disabled_reason.as_deref(), Some("runaway losses"));
+
+    let response = routes
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/strategies/{strategy_id}/enable"))
+                .header(header::AUTHORIZATION, &admin_key)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let status: StrategyStatus = serde_json::from_slice(&body).unwrap();
+    assert!(status.enabled);
+
+    let entries = AuditEntry::fetch_for(&pool, &format!("strategy:{strategy_id}"))
+        .await
+        .unwrap();
+    let actions: Vec<_> = entries.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(actions, ["strategy.enable", "strategy.disable"]);
+    assert_eq!(entries[1].actor, "admin");
+    assert_eq!(entries[1].details.0["reason"], "runaway losses");
+}
+
+#[sqlx::test]
+async fn dead_letters(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let admin_key = config.api_key.clone();
+    let id = DeadLetter::record(
+        &pool,
+        &alert(&config),
+        Some("request"),
+        "broker unavailable",
+    )
+    .await
+    .unwrap();
+
+    let query = DeadLetterQuery {
+        pending: true,
+        ..Default::default()
+    };
+    let dead_letters = DeadLetter::fetch_recent(&pool, &query).await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].dead_letter_id, id);
+    assert_eq!(dead_letters[0].alert.0.ticker, "AAPL");
+
+    let replayed = DeadLetter::replayed(&pool, id, None).await.unwrap();
+    assert!(replayed.replayed_at.is_some());
+    let dead_letters = DeadLetter::fetch_recent(&pool, &query).await.unwrap();
+    assert!(dead_letters.is_empty());
+
+    let routes = build_routes(make_test_state(pool, config).await);
+    let response = routes
+        .oneshot(request(
+            Method::POST,
+            &format!("/dead-letters/{}/replay", uuid::Uuid::new_v4()),
+            &admin_key,
+            json!(null),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
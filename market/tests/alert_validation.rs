@@ -0,0 +1,118 @@
+use axum::{
+    body::Body,
+    http::{method::Method, Request, StatusCode},
+};
+use chrono::{Duration, Utc};
+use market::{api::alert::WebhookAlertData, app_config::AppConfig, order::TimeInForce};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[sqlx::test]
+async fn invalid_alert_lists_violations(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let time = Utc::now() - Duration::hours(1);
+    let body = json!({
+        "strategy_id": config.strategies[0].id,
+        "ticker": "aapl",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "-1"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "99",
+            "low": "98",
+            "close": "101",
+            "volume": "1000",
+        },
+        "time": time,
+    });
+    let app = make_test_app_with_config(pool, config).await;
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/webhook")
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    assert!(body["request_id"].is_string());
+    let fields: Vec<&str> = body["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|violation| violation["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        fields,
+        ["ticker", "trail_stop_price", "bar_data.high", "time"]
+    );
+}
+
+#[test]
+fn alert_time_in_force() {
+    let mut alert = valid_alert();
+    let parsed: WebhookAlertData = serde_json::from_value(alert.clone()).unwrap();
+    assert_eq!(parsed.time_in_force, None);
+
+    alert["time_in_force"] = json!("gtc");
+    let parsed: WebhookAlertData = serde_json::from_value(alert.clone()).unwrap();
+    assert_eq!(parsed.time_in_force, Some(TimeInForce::Gtc));
+
+    alert["time_in_force"] = json!("weekly");
+    assert!(serde_json::from_value::<WebhookAlertData>(alert).is_err());
+}
+
+#[test]
+fn extended_hours_alerts_are_day_limit_orders() {
+    let webhook = AppConfig::build_for_test().unwrap().webhook;
+    let mut alert = valid_alert();
+    alert["extended_hours"] = json!(true);
+    alert["time_in_force"] = json!("gtc");
+
+    let parsed: WebhookAlertData = serde_json::from_value(alert.clone()).unwrap();
+    let fields: Vec<&str> = parsed
+        .validate(&webhook, Utc::now())
+        .unwrap_err()
+        .iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(fields, ["limit_price", "time_in_force"]);
+
+    alert["limit_price"] = json!("99.5");
+    alert["time_in_force"] = json!("day");
+    let parsed: WebhookAlertData = serde_json::from_value(alert).unwrap();
+    assert_eq!(parsed.validate(&webhook, Utc::now()), Ok(()));
+}
+
+fn valid_alert() -> serde_json::Value {
+    let time = Utc::now();
+    json!({
+        "strategy_id": AppConfig::build_for_test().unwrap().strategies[0].id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+        "time": time,
+    })
+}
@@ -0,0 +1,138 @@
+use chrono::Utc;
+use market::{
+    alert_writer::{AlertWriter, WriterConfig},
+    api::{alert::WebhookAlertData, error::ApiError},
+    backtest::Timeframe,
+    bars::{self, normalize_timeframe},
+    supervisor::TaskSupervisor,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::time::{sleep, Duration, Instant};
+use uuid::Uuid;
+
+fn alert(ticker: &str) -> WebhookAlertData {
+    let time = Utc::now();
+    serde_json::from_value(json!({
+        "strategy_id": Uuid::new_v4(),
+        "ticker": ticker,
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "101",
+            "low": "99",
+            "close": "100.5",
+            "volume": "1000",
+        },
+        "time": time,
+    }))
+    .unwrap()
+}
+
+async fn stored_alerts(pool: &PgPool, expected: i64) -> i64 {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM alerts")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if count >= expected || Instant::now() > deadline {
+            return count;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test]
+async fn alerts_are_written_in_batches(pool: PgPool) {
+    let config = WriterConfig {
+        batch_size: 3,
+        ..WriterConfig::default()
+    };
+    let writer = AlertWriter::spawn(pool.clone(), &config, &TaskSupervisor::new());
+
+    for index in 0..7 {
+        let ticker = format!("T{index}");
+        writer.write(&alert(&ticker), &ticker).await.unwrap();
+    }
+
+    assert_eq!(stored_alerts(&pool, 7).await, 7);
+    let close: rust_decimal::Decimal =
+        sqlx::query_scalar("SELECT bar_close FROM alerts WHERE ticker = 'T0'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(close.to_string(), "100.50");
+}
+
+#[sqlx::test]
+async fn full_buffer_rejects_alerts(pool: PgPool) {
+    let config = WriterConfig {
+        buffer: 1,
+        batch_size: 1,
+        flush_interval: 0,
+        enqueue_timeout: 50,
+    };
+    let writer = AlertWriter::spawn(pool.clone(), &config, &TaskSupervisor::new());
+
+    // Inserts wait for the lock, so the writer stops draining its buffer
+    let mut lock = pool.begin().await.unwrap();
+    sqlx::query("LOCK TABLE alerts IN ACCESS EXCLUSIVE MODE")
+        .execute(&mut *lock)
+        .await
+        .unwrap();
+
+    writer.write(&alert("FIRST"), "FIRST").await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    writer.write(&alert("SECOND"), "SECOND").await.unwrap();
+    let result = writer.write(&alert("THIRD"), "THIRD").await;
+    assert!(matches!(result, Err(ApiError::ServiceUnavailable)));
+
+    lock.commit().await.unwrap();
+    assert_eq!(stored_alerts(&pool, 2).await, 2);
+}
+
+#[sqlx::test]
+async fn bars_of_alerts_are_upserted(pool: PgPool) {
+    let config = WriterConfig {
+        batch_size: 2,
+        ..WriterConfig::default()
+    };
+    let writer = AlertWriter::spawn(pool.clone(), &config, &TaskSupervisor::new());
+
+    let mut first = alert("AAPL");
+    first.timeframe = "60".to_owned();
+    // The same bar again once it closed, in the same batch and in a later one
+    let mut repeated = first.clone();
+    repeated.bar_data.volume = "1500".parse().unwrap();
+    let mut closed = first.clone();
+    closed.bar_data.volume = "2000".parse().unwrap();
+    let mut next = first.clone();
+    next.bar_data.time = first.bar_data.time + chrono::Duration::hours(1);
+
+    for alert in [&first, &repeated, &closed, &next] {
+        writer.write(alert, "AAPL").await.unwrap();
+    }
+    assert_eq!(stored_alerts(&pool, 4).await, 4);
+
+    let stored = bars::fetch(
+        &pool,
+        "AAPL",
+        Timeframe::OneHour,
+        first.bar_data.time,
+        next.bar_data.time,
+    )
+    .await
+    .unwrap();
+    let volumes: Vec<String> = stored.iter().map(|bar| bar.volume.to_string()).collect();
+    assert_eq!(volumes, vec!["2000.00000000", "1000.00000000"]);
+
+    assert_eq!(normalize_timeframe("240"), "4h");
+    assert_eq!(normalize_timeframe("15"), "15m");
+    assert_eq!(normalize_timeframe("D"), "1d");
+}
@@ -0,0 +1,57 @@
+use std::net::SocketAddr;
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{method::Method, Request, StatusCode},
+};
+use market::app_config::AppConfig;
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn webhook_request(peer: &str, forwarded_for: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("/webhook")
+        .header("Content-Type", "application/json");
+    if let Some(forwarded_for) = forwarded_for {
+        request = request.header("X-Forwarded-For", forwarded_for);
+    }
+    let mut request = request.body(Body::from("{}")).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+    request
+}
+
+#[sqlx::test]
+async fn webhook_allowlist(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.webhook.allowed_ips = vec!["52.89.214.0/24".parse().unwrap()];
+    let app = make_test_app_with_config(pool, config).await;
+
+    let response = app
+        .clone()
+        .oneshot(webhook_request("10.0.0.1:4000", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Forwarded addresses are ignored unless the proxy is trusted
+    let response = app
+        .clone()
+        .oneshot(webhook_request("10.0.0.1:4000", Some("52.89.214.238")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .oneshot(webhook_request("52.89.214.238:4000", None))
+        .await
+        .unwrap();
+    assert!(response.status() != StatusCode::FORBIDDEN);
+}
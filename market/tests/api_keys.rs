@@ -0,0 +1,83 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use market::{
+    api_keys::{self, NewApiKey, Role},
+    app_config::AppConfig,
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn request(method: Method, uri: &str, api_key: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, api_key)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_owned()))
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn api_key_roles(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let admin_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool.clone(), config).await;
+
+    let read_only = api_keys::create(
+        &pool,
+        &NewApiKey {
+            name: "dashboard".to_owned(),
+            role: Role::ReadOnly,
+            user_id: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(request(Method::GET, "/health", &read_only.secret, ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let update_flag = r#"{"enabled": false}"#;
+    let response = app
+        .clone()
+        .oneshot(request(
+            Method::PUT,
+            "/feature-flags/enable_shorting",
+            &read_only.secret,
+            update_flag,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .clone()
+        .oneshot(request(
+            Method::PUT,
+            "/feature-flags/enable_shorting",
+            &admin_key,
+            update_flag,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    api_keys::revoke(&pool, read_only.api_key.api_key_id, None)
+        .await
+        .unwrap();
+    let response = app
+        .oneshot(request(Method::GET, "/health", &read_only.secret, ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,308 @@
+use market::{
+    api::objects::Broker,
+    app_config::{
+        AppConfig, BrokerAccount, Events, Oanda, Tradier, TradingEnvironment, ALPACA_LIVE_BASE_URL,
+        TRADIER_LIVE_BASE_URL,
+    },
+    order::TimeInForce,
+    strategy::{normalize_symbol, CurrencyType},
+};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn environment_overrides_config_files() {
+    std::env::set_var("MARKET__API_KEY", "from-environment");
+    std::env::set_var("MARKET__CACHE__TTL", "5");
+    std::env::set_var("MARKET__CACHE__REDIS_URL", "redis://127.0.0.1");
+
+    let config = AppConfig::build_for_test().unwrap();
+    assert_eq!(config.api_key, "from-environment");
+    assert_eq!(config.cache.ttl, 5);
+    assert_eq!(config.cache.redis_url.as_deref(), Some("redis://127.0.0.1"));
+}
+
+#[test]
+fn event_bus_backend_is_configurable() {
+    let config = AppConfig::build_for_test().unwrap();
+    assert!(matches!(config.events, Events::InMemory));
+
+    std::env::set_var("MARKET__EVENTS__BACKEND", "nats");
+    std::env::set_var("MARKET__EVENTS__URL", "nats://127.0.0.1:4222");
+
+    let config = AppConfig::build_for_test().unwrap();
+    let Events::Nats { url, subject } = config.events else {
+        panic!("expected a NATS event bus, got {:?}", config.events);
+    };
+    assert_eq!(url, "nats://127.0.0.1:4222");
+    assert_eq!(subject, "market.events");
+}
+
+#[test]
+fn validation_reports_every_violation() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    assert!(config.validate().is_ok());
+
+    let mut duplicate = config.strategies[0].clone();
+    duplicate.max_order_retries = 3;
+    duplicate.order_retry_delay = 0.0;
+    config.strategies.push(duplicate);
+    config.brokers.alpaca.apca_api_secret_key = String::new();
+
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    let last = config.strategies.len() - 1;
+    assert_eq!(
+        fields,
+        vec![
+            "brokers.alpaca.apca_api_secret_key".to_string(),
+            format!("strategies[{last}].id"),
+            format!("strategies[{last}].order_retry_delay"),
+        ]
+    );
+}
+
+#[test]
+fn live_environment_rejects_paper_api() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    assert_eq!(config.environment, TradingEnvironment::Paper);
+    config.environment = TradingEnvironment::Live;
+
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(fields, vec!["brokers.alpaca.apca_api_base_url".to_string()]);
+
+    // Without an override the environment picks its own API
+    config.brokers.alpaca.apca_api_base_url = None;
+    assert_eq!(
+        config.brokers.alpaca.base_url(config.environment),
+        ALPACA_LIVE_BASE_URL
+    );
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn strategy_accounts_must_be_configured() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].account = Some("momentum".to_string());
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(fields, vec!["strategies[0].account".to_string()]);
+
+    let mut alpaca = config.brokers.alpaca.clone();
+    alpaca.apca_api_key_id = String::new();
+    config
+        .brokers
+        .accounts
+        .insert("momentum".to_string(), BrokerAccount::Alpaca(alpaca));
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["brokers.accounts.momentum.apca_api_key_id".to_string()]
+    );
+}
+
+#[test]
+fn strategy_families_must_be_configured() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].family = Some("momentum".to_string());
+    config
+        .brokers
+        .families
+        .insert("trend".to_string(), "trend-account".to_string());
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "brokers.families.trend".to_string(),
+            "strategies[0].family".to_string()
+        ]
+    );
+}
+
+#[test]
+fn account_currencies_need_an_exchange_rate() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.brokers.alpaca.currency = "EUR".to_string();
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(fields, vec!["fx.rates.EUR".to_string()]);
+
+    config.fx.url = Some("https://rates.example.com/latest".to_string());
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn crypto_strategies_trade_around_the_clock() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let strategy = &mut config.strategies[0];
+    strategy.currency_type = CurrencyType::Crypto;
+    assert_eq!(strategy.order_time_in_force(), TimeInForce::Gtc);
+    assert!(config.validate().is_ok());
+
+    let strategy = &mut config.strategies[0];
+    strategy.time_in_force = Some(TimeInForce::Day);
+    strategy.extended_hours = true;
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "strategies[0].time_in_force".to_string(),
+            "strategies[0].extended_hours".to_string()
+        ]
+    );
+
+    assert_eq!(normalize_symbol("BTCUSD", CurrencyType::Crypto), "BTC/USD");
+    assert_eq!(
+        normalize_symbol("ETHUSDT", CurrencyType::Crypto),
+        "ETH/USDT"
+    );
+    assert_eq!(normalize_symbol("ETH/BTC", CurrencyType::Crypto), "ETH/BTC");
+    assert_eq!(normalize_symbol("USD", CurrencyType::Crypto), "USD");
+    assert_eq!(normalize_symbol("BTCUSD", CurrencyType::Stock), "BTCUSD");
+}
+
+#[test]
+fn forex_strategies_trade_on_oanda() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let strategy = &mut config.strategies[0];
+    strategy.currency_type = CurrencyType::Forex;
+    strategy.time_in_force = Some(TimeInForce::Opg);
+    let violations = config.validate().unwrap_err().0;
+    let fields: Vec<&str> = violations
+        .iter()
+        .map(|violation| violation.field.as_str())
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["strategies[0].broker", "strategies[0].time_in_force"]
+    );
+
+    let strategy = &mut config.strategies[0];
+    strategy.broker = Broker::Oanda;
+    strategy.time_in_force = None;
+    assert_eq!(strategy.order_time_in_force(), TimeInForce::Gtc);
+    let violations = config.validate().unwrap_err().0;
+    assert_eq!(violations[0].field, "strategies[0].broker");
+    assert_eq!(
+        violations[0].message,
+        "is oanda but brokers.oanda is not configured"
+    );
+
+    config.brokers.oanda = Some(Oanda {
+        account_id: "001-001-1234567-001".to_string(),
+        api_token: "oanda-token".to_string(),
+        base_url: None,
+        requests_per_minute: 1200,
+        currency: "USD".to_string(),
+    });
+    assert!(config.validate().is_ok());
+
+    assert_eq!(normalize_symbol("EURUSD", CurrencyType::Forex), "EUR_USD");
+    assert_eq!(normalize_symbol("EUR/USD", CurrencyType::Forex), "EUR_USD");
+    assert_eq!(normalize_symbol("USD_JPY", CurrencyType::Forex), "USD_JPY");
+}
+
+#[test]
+fn tradier_strategies_trade_stocks() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let strategy = &mut config.strategies[0];
+    strategy.broker = Broker::Tradier;
+    strategy.currency_type = CurrencyType::Crypto;
+    strategy.time_in_force = Some(TimeInForce::Ioc);
+    let violations = config.validate().unwrap_err().0;
+    let fields: Vec<&str> = violations
+        .iter()
+        .map(|violation| violation.field.as_str())
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "strategies[0].broker",
+            "strategies[0].currency_type",
+            "strategies[0].time_in_force"
+        ]
+    );
+
+    let strategy = &mut config.strategies[0];
+    strategy.currency_type = CurrencyType::Stock;
+    strategy.time_in_force = Some(TimeInForce::Gtc);
+    config.brokers.tradier = Some(Tradier {
+        account_id: "VA1234".to_string(),
+        access_token: "tradier-token".to_string(),
+        base_url: Some(TRADIER_LIVE_BASE_URL.to_string()),
+        requests_per_minute: 120,
+    });
+    let violations = config.validate().unwrap_err().0;
+    assert_eq!(violations[0].field, "brokers.tradier.base_url");
+    assert_eq!(
+        violations[0].message,
+        "is not an API of the paper environment"
+    );
+
+    config.brokers.tradier.as_mut().unwrap().base_url = None;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn database_pool_defaults_and_limits() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    assert_eq!(config.database.max_connections, 10);
+    assert_eq!(config.database.min_connections, 1);
+    assert_eq!(config.database.acquire_timeout, 5);
+    assert_eq!(config.database.statement_timeout, None);
+
+    config.database.min_connections = 20;
+    config.database.statement_timeout = Some(0);
+    let fields: Vec<String> = config
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|violation| violation.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "database.min_connections".to_string(),
+            "database.statement_timeout".to_string(),
+        ]
+    );
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use market::{
+    api::alert::{SignalType, TrailStopPrice},
+    app_config::AppConfig,
+    backtest::{self, BacktestRequest, BacktestSignal, ExitReason, HistoricalBar, Timeframe},
+    fill_model::{CommissionModel, FillModel},
+};
+use pretty_assertions::assert_eq;
+use rand_core::OsRng;
+use rust_decimal::Decimal;
+
+fn time(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+fn bar(day: &str, open: i64, high: i64, low: i64, close: i64) -> HistoricalBar {
+    HistoricalBar {
+        time: time(&format!("2023-08-{day}T00:00:00Z")),
+        open: Decimal::from(open),
+        high: Decimal::from(high),
+        low: Decimal::from(low),
+        close: Decimal::from(close),
+        volume: Decimal::from(10_000),
+    }
+}
+
+#[test]
+fn backtest_stop_loss_and_end_of_data() {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.order_quantity = Decimal::TEN;
+    strategy.weekend_size_factor = None;
+
+    let request = BacktestRequest {
+        strategy_id: strategy.id,
+        ticker: "AAPL".to_string(),
+        timeframe: Timeframe::OneDay,
+        from: time("2023-08-01T00:00:00Z"),
+        to: time("2023-08-05T00:00:00Z"),
+        initial_equity: Decimal::from(100_000),
+        risk_based_sizing: false,
+        stored_bars: false,
+        signals: vec![
+            BacktestSignal {
+                time: time("2023-08-03T20:00:00Z"),
+                signal_type: SignalType::OpenShort(TrailStopPrice(Decimal::from(110))),
+            },
+            BacktestSignal {
+                time: time("2023-08-01T20:00:00Z"),
+                signal_type: SignalType::OpenLong(TrailStopPrice(Decimal::from(98))),
+            },
+        ],
+    };
+    let bars = [
+        bar("01", 100, 101, 99, 100),
+        bar("02", 100, 106, 100, 105),
+        // Long stopped out at 98
+        bar("03", 105, 106, 96, 97),
+        bar("04", 97, 98, 90, 91),
+    ];
+
+    let report = backtest::run(
+        &strategy,
+        &request,
+        &bars,
+        &FillModel::default(),
+        &mut OsRng,
+    );
+
+    assert_eq!(report.trades.len(), 2);
+    assert_eq!(report.trades[0].entry_price, Decimal::from(100));
+    assert_eq!(report.trades[0].exit_price, Decimal::from(98));
+    assert_eq!(report.trades[0].exit_reason, ExitReason::StopLoss);
+    assert_eq!(report.trades[0].pnl, Decimal::from(-20));
+    assert_eq!(report.trades[1].exit_reason, ExitReason::EndOfData);
+    assert_eq!(report.trades[1].pnl, Decimal::from(60));
+    assert_eq!(report.final_equity, Decimal::from(100_040));
+    assert_eq!(report.win_rate, Some(Decimal::new(5, 1)));
+    assert_eq!(
+        report.max_drawdown,
+        Decimal::from(70) / Decimal::from(100_050)
+    );
+}
+
+#[test]
+fn backtest_pnl_is_net_of_commissions() {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.order_quantity = Decimal::TEN;
+    strategy.weekend_size_factor = None;
+
+    let request = BacktestRequest {
+        strategy_id: strategy.id,
+        ticker: "AAPL".to_string(),
+        timeframe: Timeframe::OneDay,
+        from: time("2023-08-01T00:00:00Z"),
+        to: time("2023-08-04T00:00:00Z"),
+        initial_equity: Decimal::from(100_000),
+        risk_based_sizing: false,
+        stored_bars: false,
+        signals: vec![BacktestSignal {
+            time: time("2023-08-01T20:00:00Z"),
+            signal_type: SignalType::OpenLong(TrailStopPrice(Decimal::from(90))),
+        }],
+    };
+    let bars = [
+        bar("01", 100, 101, 99, 100),
+        bar("02", 100, 106, 100, 105),
+        bar("03", 105, 108, 104, 107),
+    ];
+    let fill_model = FillModel {
+        commission: CommissionModel::PerFill {
+            amount: Decimal::ONE,
+        },
+        ..FillModel::default()
+    };
+
+    let report = backtest::run(&strategy, &request, &bars, &fill_model, &mut OsRng);
+
+    assert_eq!(report.trades.len(), 1);
+    assert_eq!(report.trades[0].fees, Decimal::from(2));
+    assert_eq!(report.trades[0].pnl, Decimal::from(68));
+    assert_eq!(report.fees, Decimal::from(2));
+    assert_eq!(report.final_equity, Decimal::from(100_068));
+}
@@ -0,0 +1,74 @@
+use std::{sync::Arc, time::Duration};
+
+use market::{
+    api::objects::AssetClass,
+    cache::{BrokerCache, CachedClient},
+    clients::BrokerClient,
+    recorder::{Interaction, PlaybackClient},
+    scheduler::{RequestScheduler, ScheduledClient},
+};
+use serde_json::json;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn lookups_cached_until_invalidated() {
+    let playback = PlaybackClient::new(vec![Interaction {
+        interaction_id: Uuid::new_v4(),
+        broker: "alpaca".to_string(),
+        operation: "get_assets".to_string(),
+        request: Json(serde_json::Value::Null),
+        response: Some(Json(json!([]))),
+        error: None,
+        recorded_at: chrono::Utc::now(),
+    }]);
+    let cache = BrokerCache::in_memory("alpaca", Duration::from_secs(60));
+    let client = CachedClient::new(playback, &cache);
+
+    assert!(client
+        .get_assets(AssetClass::UsEquity)
+        .await
+        .unwrap()
+        .is_empty());
+    // Served from the cache, the only recorded interaction is used up
+    assert!(client
+        .get_assets(AssetClass::UsEquity)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(client.get_assets(AssetClass::Crypto).await.is_err());
+
+    cache.invalidate_all().await;
+    assert!(client.get_assets(AssetClass::UsEquity).await.is_err());
+}
+
+#[tokio::test]
+async fn concurrent_misses_load_once() {
+    let playback = PlaybackClient::new(vec![Interaction {
+        interaction_id: Uuid::new_v4(),
+        broker: "alpaca".to_string(),
+        operation: "get_clock".to_string(),
+        request: Json(serde_json::Value::Null),
+        response: Some(Json(json!({
+            "AlpacaClock": {
+                "is_open": true,
+                "timestamp": "2023-12-29T15:00:00Z",
+                "next_open": "2024-01-02T14:30:00Z",
+                "next_close": "2023-12-29T21:00:00Z"
+            }
+        }))),
+        error: None,
+        recorded_at: chrono::Utc::now(),
+    }]);
+    // NOTE: a drained budget holds the first lookup at the broker while the others miss
+    let scheduler = Arc::new(RequestScheduler::new(600));
+    scheduler.rate_limited();
+    let cache = BrokerCache::in_memory("alpaca", Duration::from_secs(60));
+    let client = CachedClient::new(ScheduledClient::new(playback, scheduler), &cache);
+
+    let clocks = futures::future::join_all((0..5).map(|_| client.get_clock())).await;
+    // The only recorded interaction serves all of them
+    assert!(clocks
+        .iter()
+        .all(|clock| clock.as_ref().is_ok_and(|clock| clock.is_open())));
+}
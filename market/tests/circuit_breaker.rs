@@ -0,0 +1,39 @@
+#![cfg(feature = "test-broker")]
+
+use std::sync::Arc;
+
+use market::{
+    clients::{BrokerClient, BrokerClientError},
+    mock_broker::MockBrokerClient,
+    scheduler::{RequestScheduler, ScheduledClient},
+};
+
+#[tokio::test]
+async fn failing_accounts_open_the_circuit_breaker() {
+    let broker = MockBrokerClient::new();
+    let client = ScheduledClient::new(broker.clone(), Arc::new(RequestScheduler::new(600)));
+    let account: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/alpaca_account.json")).unwrap();
+
+    for _ in 0..5 {
+        assert!(!client.is_circuit_open());
+        broker.fail(
+            "get_account",
+            BrokerClientError::AlpacaUnavailable("connection reset".to_string()),
+        );
+        assert!(client.get_account().await.is_err());
+    }
+    assert!(client.is_circuit_open());
+
+    broker.respond("get_account", account);
+    client.get_account().await.unwrap();
+    assert!(!client.is_circuit_open());
+
+    // Rejected credentials open it right away
+    broker.fail(
+        "get_account",
+        BrokerClientError::AlpacaError("Err(Endpoint(AuthenticationFailed(..)))".to_string()),
+    );
+    assert!(client.get_account().await.is_err());
+    assert!(client.is_circuit_open());
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "client")]
+
+use std::net::TcpListener;
+
+use market::{
+    api::error::ErrorCode,
+    api_keys::{NewApiKey, Role},
+    app_config::AppConfig,
+    client::{ClientError, MarketClient},
+    feature_flags::UpdateFeatureFlag,
+};
+use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
+use sqlx::PgPool;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[sqlx::test]
+async fn client_round_trip(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let admin_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+
+    let client = MarketClient::new(&base_url, admin_key);
+    client.health().await.unwrap();
+
+    let flag = client
+        .update_feature_flag(
+            "client_test",
+            &UpdateFeatureFlag {
+                enabled: true,
+                description: None,
+                rollout_percentage: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(flag.rollout_percentage, 100);
+    let flags = client.feature_flags().await.unwrap();
+    assert!(flags.iter().any(|flag| flag.name == "client_test"));
+
+    let created = client
+        .create_api_key(&NewApiKey {
+            name: "integrator".to_string(),
+            role: Role::ReadOnly,
+            user_id: None,
+        })
+        .await
+        .unwrap();
+
+    // Error responses of the API are surfaced with their status
+    let read_only = MarketClient::new(&base_url, created.secret);
+    let err = read_only.api_keys().await.unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::ApiError {
+            status: StatusCode::FORBIDDEN,
+            code: Some(ErrorCode::Forbidden),
+            ..
+        }
+    ));
+
+    let revoked = client
+        .revoke_api_key(created.api_key.api_key_id)
+        .await
+        .unwrap();
+    assert!(revoked.revoked_at.is_some());
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use market::{
+    app_config::{AppConfig, BrokerAccount},
+    build_clients,
+    clients::BrokerClientError,
+};
+
+#[test]
+fn strategy_accounts_get_their_own_clients() {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let mut alpaca = config.brokers.alpaca.clone();
+    alpaca.apca_api_key_id = "momentum-key-id".to_string();
+    config
+        .brokers
+        .accounts
+        .insert("momentum".to_string(), BrokerAccount::Alpaca(alpaca));
+    let clients = build_clients(&config).unwrap();
+
+    let (global, _) = clients.alpaca_account(None).unwrap();
+    let (first, _) = clients.alpaca_account(Some("momentum")).unwrap();
+    let (second, _) = clients.alpaca_account(Some("momentum")).unwrap();
+    assert!(Arc::ptr_eq(global.inner(), &clients.alpaca()));
+    assert!(!Arc::ptr_eq(global.inner(), first.inner()));
+    // Created once and pooled
+    assert!(Arc::ptr_eq(first.inner(), second.inner()));
+
+    assert!(matches!(
+        clients.alpaca_account(Some("unknown")),
+        Err(BrokerClientError::UnknownAccount(_))
+    ));
+}
@@ -0,0 +1,236 @@
+use std::net::{SocketAddr, TcpListener};
+
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use market::{
+    admin,
+    api_keys::{self, NewApiKey, Role},
+    app_config::AppConfig,
+    audit::AuditEntry,
+    build_routes,
+    control::ControlAck,
+};
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_state;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn connect(
+    addr: SocketAddr,
+    api_key: &str,
+) -> Result<Socket, tokio_tungstenite::tungstenite::Error> {
+    let mut request = format!("ws://{addr}/control")
+        .into_client_request()
+        .unwrap();
+    request
+        .headers_mut()
+        .insert("authorization", api_key.parse().unwrap());
+    Ok(tokio_tungstenite::connect_async(request).await?.0)
+}
+
+async fn send(socket: &mut Socket, message: String) -> ControlAck {
+    socket.send(Message::Text(message)).await.unwrap();
+    let Some(Ok(Message::Text(text))) = socket.next().await else {
+        panic!("expected an acknowledgment");
+    };
+    serde_json::from_str(&text).unwrap()
+}
+
+#[sqlx::test]
+async fn control_commands_are_acknowledged(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = true;
+    let strategy = config.strategies[0].clone();
+    let api_key = config.api_key.clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(build_routes(app.clone()).into_make_service()),
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'AAPL', 10, 1, NOW(), NOW()), ($1, 'MSFT', 5, 1, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let mut socket = connect(addr, &api_key).await.unwrap();
+
+    // Only the position in the symbol is closed
+    let ack = send(
+        &mut socket,
+        json!({
+            "id": "1",
+            "command": "flatten_symbol",
+            "strategy_id": strategy.id,
+            "symbol": "AAPL"
+        })
+        .to_string(),
+    )
+    .await;
+    assert!(ack.ok, "{ack:?}");
+    assert_eq!(ack.id.as_deref(), Some("1"));
+    let orders = ack.result.unwrap()["orders"].clone();
+    let tickers: Vec<String> = sqlx::query_scalar("SELECT ticker FROM orders")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders.as_array().map(Vec::len), Some(1));
+    assert_eq!(tickers, vec!["AAPL".to_owned()]);
+
+    let order_id = Uuid::new_v4();
+    let ack = send(
+        &mut socket,
+        json!({ "id": "2", "command": "cancel_order", "order_id": order_id }).to_string(),
+    )
+    .await;
+    assert!(!ack.ok);
+    assert_eq!(ack.command.as_deref(), Some("cancel_order"));
+    assert_eq!(
+        ack.error.unwrap().message,
+        format!("Unknown order - {order_id}")
+    );
+
+    let ack = send(&mut socket, "pause".to_owned()).await;
+    assert!(!ack.ok);
+    assert_eq!(ack.command, None);
+
+    // Messages which aren't commands keep their id
+    let ack = send(
+        &mut socket,
+        json!({ "id": "4", "command": "pause" }).to_string(),
+    )
+    .await;
+    assert!(!ack.ok);
+    assert_eq!(ack.id.as_deref(), Some("4"));
+    assert_eq!(ack.command.as_deref(), Some("pause"));
+
+    // Symbols are normalized, symbols without a position or open order are unknown
+    let ack = send(
+        &mut socket,
+        json!({
+            "id": "5",
+            "command": "flatten_symbol",
+            "strategy_id": strategy.id,
+            "symbol": " msft "
+        })
+        .to_string(),
+    )
+    .await;
+    assert!(ack.ok, "{ack:?}");
+    let ack = send(
+        &mut socket,
+        json!({
+            "id": "6",
+            "command": "flatten_symbol",
+            "strategy_id": strategy.id,
+            "symbol": "TSLA"
+        })
+        .to_string(),
+    )
+    .await;
+    assert!(!ack.ok);
+    assert_eq!(
+        serde_json::to_value(ack.error.unwrap().code).unwrap(),
+        "not_found"
+    );
+
+    let ack = send(
+        &mut socket,
+        json!({
+            "id": "3",
+            "command": "pause_strategy",
+            "strategy_id": strategy.id,
+            "reason": "Outage"
+        })
+        .to_string(),
+    )
+    .await;
+    assert!(ack.ok, "{ack:?}");
+    assert_eq!(ack.result.unwrap()["enabled"], Value::Bool(false));
+    let status = admin::strategy_status(&pool, &strategy, Utc::now().date_naive())
+        .await
+        .unwrap();
+    assert_eq!(status.disabled_reason.as_deref(), Some("Outage"));
+
+    let actions: Vec<String> = AuditEntry::fetch_for(&pool, &format!("strategy:{}", strategy.id))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.action)
+        .collect();
+    assert_eq!(actions.len(), 3);
+    assert!(actions.contains(&"control.flatten_symbol".to_owned()));
+    assert!(actions.contains(&"strategy.disable".to_owned()));
+
+    // Trade keys open the channel, pausing a strategy takes an admin key
+    let trade = api_keys::create(
+        &pool,
+        &NewApiKey {
+            name: "console".to_owned(),
+            role: Role::Trade,
+            user_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    let mut socket = connect(addr, &trade.secret).await.unwrap();
+    let ack = send(
+        &mut socket,
+        json!({ "command": "pause_strategy", "strategy_id": strategy.id }).to_string(),
+    )
+    .await;
+    assert!(!ack.ok);
+    assert_eq!(
+        serde_json::to_value(ack.error.unwrap().code).unwrap(),
+        "forbidden"
+    );
+
+    // Commands of a revoked key are refused and close the channel
+    api_keys::revoke(&pool, trade.api_key.api_key_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    let ack = send(
+        &mut socket,
+        json!({ "id": "7", "command": "cancel_order", "order_id": order_id }).to_string(),
+    )
+    .await;
+    assert_eq!(ack.id.as_deref(), Some("7"));
+    assert_eq!(
+        serde_json::to_value(ack.error.unwrap().code).unwrap(),
+        "unauthorized"
+    );
+    assert!(!matches!(socket.next().await, Some(Ok(Message::Text(_)))));
+
+    let read_only = api_keys::create(
+        &pool,
+        &NewApiKey {
+            name: "dashboard".to_owned(),
+            role: Role::ReadOnly,
+            user_id: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert!(connect(addr, &read_only.secret).await.is_err());
+}
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use market::{
+    cooldown::{self, timeframe_duration, Cooldown, CooldownState},
+    pnl::ClosedTrade,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+
+fn trade(pnl: i64, closed_at: DateTime<Utc>) -> ClosedTrade {
+    ClosedTrade {
+        ticker: "AAPL".to_string(),
+        pnl: Decimal::from(pnl),
+        closed_at,
+    }
+}
+
+#[test]
+fn cooldown_durations() {
+    assert_eq!(timeframe_duration("15"), Some(Duration::minutes(15)));
+    assert_eq!(timeframe_duration("240"), Some(Duration::hours(4)));
+    assert_eq!(timeframe_duration("D"), Some(Duration::days(1)));
+    assert_eq!(timeframe_duration("tick"), None);
+
+    assert_eq!(
+        Cooldown::Minutes(30).duration("tick"),
+        Some(Duration::minutes(30))
+    );
+    assert_eq!(Cooldown::Bars(3).duration("60"), Some(Duration::hours(3)));
+    assert_eq!(Cooldown::Bars(3).duration("tick"), None);
+}
+
+#[test]
+fn cooldown_after_losing_exit() {
+    let closed_at = DateTime::<Utc>::from_str("2023-08-01T15:00:00Z").unwrap();
+    let cooldown = Duration::minutes(30);
+
+    let trades = [
+        trade(100, closed_at - Duration::hours(1)),
+        trade(-50, closed_at),
+    ];
+    assert_eq!(
+        cooldown::state(&trades, cooldown, closed_at + Duration::minutes(10)),
+        CooldownState::CoolingDown {
+            until: closed_at + cooldown
+        }
+    );
+    assert_eq!(
+        cooldown::state(&trades, cooldown, closed_at + cooldown),
+        CooldownState::Ready
+    );
+
+    // A profitable exit ends the cooldown of the loss before
+    let trades = [trade(-50, closed_at), trade(20, closed_at)];
+    assert_eq!(
+        cooldown::state(&trades, cooldown, closed_at),
+        CooldownState::Ready
+    );
+    assert_eq!(
+        cooldown::state(&[], cooldown, closed_at),
+        CooldownState::Ready
+    );
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use market::{
+    corporate_actions::{apply, CorporateAction},
+    order::Fill,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const ORDER_ID: Uuid = Uuid::from_u128(1);
+const STRATEGY_ID: Uuid = Uuid::from_u128(2);
+
+async fn setup(pool: &PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, filled_quantity, filled_avg_price, limit_price, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'NVDA', 'buy', 10, 10, 1200, 1200, 'filled', '2024-06-03T14:00:00Z', NOW())
+        "#,
+    )
+    .bind(ORDER_ID)
+    .bind(STRATEGY_ID)
+    .execute(pool)
+    .await
+    .unwrap();
+    let fill = Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: ORDER_ID,
+        strategy_id: STRATEGY_ID,
+        ticker: "NVDA".to_string(),
+        side: "buy".to_string(),
+        quantity: Decimal::from(10),
+        price: Decimal::from(1200),
+        fee: Decimal::ZERO,
+        filled_at: DateTime::<Utc>::from_str("2024-06-03T14:00:00Z").unwrap(),
+    };
+    Fill::insert(pool, &fill).await.unwrap();
+}
+
+fn split() -> CorporateAction {
+    CorporateAction::Split {
+        id: "split-1".to_string(),
+        symbol: "NVDA".to_string(),
+        old_rate: Decimal::ONE,
+        new_rate: Decimal::from(10),
+        ex_date: NaiveDate::from_ymd_opt(2024, 6, 10).unwrap(),
+    }
+}
+
+#[sqlx::test]
+async fn splits_are_applied_to_positions_once(pool: PgPool) {
+    setup(&pool).await;
+
+    assert!(apply(&pool, &split()).await.unwrap());
+    // Announcements are fetched again on every check
+    assert!(!apply(&pool, &split()).await.unwrap());
+
+    let (quantity, price): (Decimal, Decimal) =
+        sqlx::query_as("SELECT quantity, price FROM fills WHERE order_id = $1")
+            .bind(ORDER_ID)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((quantity, price), (Decimal::from(100), Decimal::from(120)));
+    let (filled_quantity, limit_price): (Decimal, Option<Decimal>) =
+        sqlx::query_as("SELECT filled_quantity, limit_price FROM orders WHERE order_id = $1")
+            .bind(ORDER_ID)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(filled_quantity, Decimal::from(100));
+    assert_eq!(limit_price, Some(Decimal::from(120)));
+    assert_eq!(
+        Fill::position(&pool, STRATEGY_ID, "NVDA").await.unwrap(),
+        Decimal::from(100)
+    );
+}
+
+#[sqlx::test]
+async fn symbol_changes_move_positions(pool: PgPool) {
+    setup(&pool).await;
+
+    let change = CorporateAction::NameChange {
+        id: "name-change-1".to_string(),
+        old_symbol: "NVDA".to_string(),
+        new_symbol: "NVDX".to_string(),
+        process_date: NaiveDate::from_ymd_opt(2024, 6, 10).unwrap(),
+    };
+    assert!(apply(&pool, &change).await.unwrap());
+
+    assert_eq!(
+        Fill::position(&pool, STRATEGY_ID, "NVDX").await.unwrap(),
+        Decimal::from(10)
+    );
+    assert_eq!(
+        Fill::position(&pool, STRATEGY_ID, "NVDA").await.unwrap(),
+        Decimal::ZERO
+    );
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "test-broker")]
+
+use chrono::Utc;
+use market::{
+    api::objects::Broker,
+    app_config::AppConfig,
+    dashboard::{self, ErrorCounts},
+    dead_letters::DeadLetter,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{mock_broker, trade_signal};
+
+#[sqlx::test]
+async fn dashboard_summary(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let broker = mock_broker();
+    broker.set_default("get_positions", json!([]));
+
+    let now = Utc::now();
+    sqlx::query(
+        r#"
+        INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+        VALUES (gen_random_uuid(), 'alpaca', 99000, 0, '[]', $1)
+        "#,
+    )
+    .bind(now.date_naive().and_time(chrono::NaiveTime::MIN).and_utc())
+    .execute(&pool)
+    .await
+    .unwrap();
+    let alert = serde_json::from_value(json!({
+        "strategy_id": config.strategies[0].id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": trade_signal(&config.strategies[0]).bar_data,
+        "time": now,
+    }))
+    .unwrap();
+    DeadLetter::record(&pool, &alert, None, "broker unavailable")
+        .await
+        .unwrap();
+
+    let summary = dashboard::summary(&pool, &Broker::Alpaca, &broker, now)
+        .await
+        .unwrap();
+    assert_eq!(summary.equity.amount, Decimal::from(100000));
+    assert_eq!(summary.todays_pnl, Some(Decimal::from(1000)));
+    assert!(summary.positions.is_empty());
+    assert_eq!(
+        summary.errors,
+        ErrorCounts {
+            dead_letters: 1,
+            ..Default::default()
+        }
+    );
+}
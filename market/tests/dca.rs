@@ -0,0 +1,128 @@
+#![cfg(feature = "test-broker")]
+
+use chrono::Utc;
+use market::{
+    admin::{self, StrategySwitch},
+    app_config::AppConfig,
+    dca::{DcaEntry, DcaExecution},
+    market_data::LiveQuote,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{make_test_state, mock_broker, trade_signal};
+
+#[sqlx::test]
+async fn entries_are_executed_in_chunks(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = true;
+    config.strategies[0].dca = Some(DcaExecution {
+        chunks: 3,
+        interval: 60,
+    });
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let broker = mock_broker();
+
+    let mut signal = trade_signal(&strategy);
+    signal.quantity = Some(Decimal::from(10));
+    app.core
+        .process_trade_signal(broker.clone(), signal)
+        .await
+        .unwrap();
+
+    // The first chunk is sent with the signal, the last one takes the remainder
+    let entry: DcaEntry = sqlx::query_as("SELECT * FROM dca_entries WHERE strategy_id = $1")
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(entry.quantity, Decimal::from(10));
+    assert_eq!(entry.chunk_quantity, Decimal::from(3));
+    assert_eq!(entry.chunks_sent, 1);
+    assert_eq!(entry.status, "active");
+    let (quantity, entry_id): (Decimal, Option<uuid::Uuid>) =
+        sqlx::query_as("SELECT quantity, entry_id FROM orders WHERE strategy_id = $1")
+            .bind(strategy.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(quantity, Decimal::from(3));
+    assert_eq!(entry_id, Some(entry.entry_id));
+
+    // Later chunks pass the checks of signals at the current price
+    let due = |pool: PgPool| async move {
+        sqlx::query("UPDATE dca_entries SET next_at = NOW()")
+            .execute(&pool)
+            .await
+            .unwrap();
+    };
+    app.core
+        .quotes()
+        .update(
+            "AAPL",
+            LiveQuote {
+                bid: Decimal::from(100),
+                ask: Decimal::from(100),
+                time: Utc::now(),
+            },
+        )
+        .await;
+    due(pool.clone()).await;
+    app.core.send_dca_chunks().await.unwrap();
+    let chunks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE entry_id = $1")
+        .bind(entry.entry_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(chunks, 2);
+
+    // A chunk failing them cancels the rest of the entry
+    let switch = StrategySwitch {
+        enabled: false,
+        reason: None,
+    };
+    admin::switch_strategy(&pool, &strategy, &switch)
+        .await
+        .unwrap();
+    due(pool.clone()).await;
+    app.core.send_dca_chunks().await.unwrap();
+    let entry: DcaEntry = sqlx::query_as("SELECT * FROM dca_entries WHERE strategy_id = $1")
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(entry.status, "canceled");
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE strategy_id = $1")
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 2);
+}
+
+#[sqlx::test]
+async fn chunks_failing_at_the_broker_stay_due(pool: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO dca_entries (entry_id, strategy_id, ticker, side, quantity, chunk_quantity, chunks, chunks_sent, chunk_interval, status, next_at, created_at, modified_at)
+        VALUES (gen_random_uuid(), gen_random_uuid(), 'AAPL', 'buy', 10, 5, 2, 1, 60, 'active', NOW(), NOW(), NOW())
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let entry = DcaEntry::fetch_due(&pool).await.unwrap().remove(0);
+
+    // Claiming the last chunk completes the entry, giving it back makes it due again
+    assert!(entry.claim_chunk(&pool).await.unwrap());
+    assert!(DcaEntry::fetch_due(&pool).await.unwrap().is_empty());
+    entry.release_chunk(&pool).await.unwrap();
+    let due = DcaEntry::fetch_due(&pool).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].chunks_sent, 1);
+    assert_eq!(due[0].status, "active");
+}
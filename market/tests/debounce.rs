@@ -0,0 +1,67 @@
+use market::{
+    api::{
+        alert::{SignalType, TrailStopPrice},
+        price::Price,
+    },
+    app_config::AppConfig,
+    debounce::SignalDebouncer,
+    trade_signal::TradeSignal,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use tokio::time::Duration;
+
+mod setup;
+
+const WINDOW: Duration = Duration::from_millis(50);
+
+fn trade_signal(ticker: &str, signal_type: SignalType, close: i64) -> TradeSignal {
+    let strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    let mut trade_signal = setup::trade_signal(&strategy);
+    let price = Price::new(Decimal::from(close));
+    trade_signal.ticker = ticker.to_string();
+    trade_signal.timeframe = "1m".to_string();
+    trade_signal.signal_type = signal_type;
+    trade_signal.bar_data.open = price;
+    trade_signal.bar_data.high = price;
+    trade_signal.bar_data.low = price;
+    trade_signal.bar_data.close = price;
+    trade_signal
+}
+
+fn long() -> SignalType {
+    SignalType::OpenLong(TrailStopPrice(Decimal::from(90)))
+}
+
+#[tokio::test]
+async fn rapid_signals_are_coalesced() {
+    let debouncer = SignalDebouncer::default();
+
+    let (first, second, third, short) = tokio::join!(
+        debouncer.coalesce(trade_signal("AAPL", long(), 100), WINDOW),
+        debouncer.coalesce(trade_signal("aapl", long(), 101), WINDOW),
+        debouncer.coalesce(trade_signal("AAPL", long(), 102), WINDOW),
+        debouncer.coalesce(
+            trade_signal(
+                "AAPL",
+                SignalType::OpenShort(TrailStopPrice(Decimal::from(110))),
+                103
+            ),
+            WINDOW
+        ),
+    );
+
+    // The signal opening the window goes on with the latest data of the window
+    let first = first.unwrap();
+    assert_eq!(*first.bar_data.close.as_ref(), Decimal::from(102));
+    assert!(second.is_none());
+    assert!(third.is_none());
+    // Opposite direction has a window of its own
+    assert_eq!(*short.unwrap().bar_data.close.as_ref(), Decimal::from(103));
+
+    // The window is closed, the next signal opens a new one
+    let next = debouncer
+        .coalesce(trade_signal("AAPL", long(), 104), WINDOW)
+        .await;
+    assert_eq!(*next.unwrap().bar_data.close.as_ref(), Decimal::from(104));
+}
@@ -0,0 +1,96 @@
+use chrono::Utc;
+use market::{
+    api::alert::WebhookAlertData,
+    app_config::AppConfig,
+    dedup::{claim_alert, release_alert},
+};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_state;
+
+#[sqlx::test]
+async fn duplicate_alerts_within_window(pool: PgPool) {
+    let strategy_id = Uuid::new_v4();
+
+    assert!(claim_alert(&pool, "alert", strategy_id, None, 300)
+        .await
+        .unwrap());
+    assert!(!claim_alert(&pool, "alert", strategy_id, None, 300)
+        .await
+        .unwrap());
+    assert!(claim_alert(&pool, "other", strategy_id, None, 300)
+        .await
+        .unwrap());
+
+    // Claims older than the window are expired
+    assert!(claim_alert(&pool, "alert", strategy_id, None, 0)
+        .await
+        .unwrap());
+}
+
+#[sqlx::test]
+async fn released_alerts_are_claimed_again(pool: PgPool) {
+    let strategy_id = Uuid::new_v4();
+
+    assert!(claim_alert(&pool, "alert", strategy_id, Some("first"), 300)
+        .await
+        .unwrap());
+    // Only the request which made the claim releases it
+    release_alert(&pool, "alert", Some("second")).await.unwrap();
+    assert!(
+        !claim_alert(&pool, "alert", strategy_id, Some("second"), 300)
+            .await
+            .unwrap()
+    );
+
+    release_alert(&pool, "alert", Some("first")).await.unwrap();
+    assert!(
+        claim_alert(&pool, "alert", strategy_id, Some("second"), 300)
+            .await
+            .unwrap()
+    );
+}
+
+#[sqlx::test]
+async fn duplicate_alerts_are_not_stored(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = true;
+    let time = Utc::now();
+    let alert: WebhookAlertData = serde_json::from_value(json!({
+        "strategy_id": config.strategies[0].id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+        "time": time,
+    }))
+    .unwrap();
+    let app = make_test_state(pool.clone(), config).await;
+
+    assert!(app
+        .accept_alert(alert.clone(), "first".to_string())
+        .await
+        .unwrap());
+    assert!(!app.accept_alert(alert, "retry".to_string()).await.unwrap());
+
+    // Give the writer time to flush both alerts if it was handed both
+    sleep(Duration::from_millis(500)).await;
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM alerts")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, 1);
+}
@@ -0,0 +1,60 @@
+use chrono::Utc;
+use market::divergence::{report, DivergenceQuery, ShadowExecution};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test]
+async fn live_shortfall_against_shadow(pool: PgPool) {
+    let strategy_id = Uuid::new_v4();
+
+    for (side, filled_quantity, filled_avg_price, shadow_price) in [
+        ("buy", 10, Some(101), 100),
+        ("sell", 10, Some(99), 100),
+        ("buy", 0, None, 100),
+    ] {
+        let order_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, filled_quantity, filled_avg_price, status, created_at, modified_at)
+            VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', $3, 10, $4, $5, 'filled', NOW(), NOW())
+            "#,
+        )
+        .bind(order_id)
+        .bind(strategy_id)
+        .bind(side)
+        .bind(Decimal::from(filled_quantity))
+        .bind(filled_avg_price.map(Decimal::from))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        ShadowExecution {
+            order_id,
+            quantity: Decimal::TEN,
+            avg_price: Decimal::from(shadow_price),
+            simulated_at: Utc::now(),
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+    }
+
+    let report = report(
+        &pool,
+        strategy_id,
+        &DivergenceQuery {
+            from: None,
+            to: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.signals, 3);
+    assert_eq!(report.underfilled, 1);
+    assert_eq!(report.divergences[0].shortfall, Decimal::TEN);
+    assert_eq!(report.divergences[1].shortfall, Decimal::TEN);
+    assert_eq!(report.total_shortfall, Decimal::from(20));
+}
@@ -0,0 +1,132 @@
+use axum::{
+    body::Body,
+    http::{method::Method, Request, StatusCode},
+};
+use chrono::Utc;
+use market::{
+    app_config::AppConfig,
+    core::SIMULATED_STATUS,
+    order::{parse_client_order_id, OrderSide},
+    recorder::PlaybackClient,
+    simulation::AlertSimulation,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::{make_test_app_with_config, make_test_state, trade_signal};
+
+#[sqlx::test]
+async fn dry_run_skips_broker(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = true;
+    let app = make_test_state(pool.clone(), config).await;
+
+    let trade_signal = trade_signal(&strategy);
+
+    let signal_id = trade_signal.signal_id();
+    // Playback without interactions fails any broker call
+    app.core
+        .process_trade_signal(PlaybackClient::new(vec![]), trade_signal)
+        .await
+        .unwrap();
+
+    let (status, broker_order_id, client_order_id): (String, Option<String>, String) =
+        sqlx::query_as(
+            "SELECT status, broker_order_id, client_order_id FROM orders WHERE strategy_id = $1",
+        )
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, SIMULATED_STATUS);
+    assert_eq!(broker_order_id, None);
+    assert_eq!(
+        parse_client_order_id(&client_order_id),
+        Some((strategy.id, signal_id))
+    );
+}
+
+#[sqlx::test]
+async fn test_alerts_are_simulated(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let strategy = config.strategies[0].clone();
+    let app = make_test_app_with_config(pool.clone(), config).await;
+
+    let time = Utc::now();
+    let alert = json!({
+        "strategy_id": strategy.id,
+        "time": time,
+        "ticker": "AAPL",
+        "exchange": "NASDAQ",
+        "timeframe": "1h",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/webhook/alert/test")
+                .header("Content-Type", "application/json")
+                .body(Body::from(alert.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let simulation: AlertSimulation = serde_json::from_slice(&body).unwrap();
+    assert_eq!(simulation.strategy.id, strategy.id);
+    assert_eq!(simulation.signal.ticker, "AAPL");
+    assert_eq!(simulation.ignored, None);
+    assert!(simulation.risk_violations.is_empty());
+    let order = simulation.order.unwrap();
+    assert_eq!(order.side, OrderSide::Buy);
+    assert_eq!(order.quantity, strategy.order_quantity);
+    assert_eq!(order.stop_loss_price, Some(Decimal::from(95)));
+
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 0);
+}
+
+#[sqlx::test]
+async fn test_alerts_of_protected_strategies_are_signed(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].webhook_secret = Some("secret".to_owned());
+    let strategy = config.strategies[0].clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    // A decoy strategy ahead of the protected one doesn't skip the signature check
+    let alert = json!({ "strategy_id": strategy.id, "ticker": "AAPL" }).to_string();
+    let alert = format!(r#"{{"strategy_id": "{}", {}"#, Uuid::new_v4(), &alert[1..]);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/webhook/alert/test")
+                .header("Content-Type", "application/json")
+                .body(Body::from(alert))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,79 @@
+use std::net::TcpListener;
+
+use chrono::Utc;
+use futures::StreamExt;
+use market::{app_config::AppConfig, build_routes, events::Event, order::Fill};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_state;
+
+fn fill(strategy_id: Uuid) -> Fill {
+    Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: Uuid::new_v4(),
+        strategy_id,
+        ticker: "AAPL".to_string(),
+        side: "buy".to_string(),
+        quantity: Decimal::ONE,
+        price: Decimal::from(190),
+        fee: Decimal::ZERO,
+        filled_at: Utc::now(),
+    }
+}
+
+#[sqlx::test]
+async fn stream_events_of_strategy(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let app = make_test_state(pool, config).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(build_routes(app.clone()).into_make_service()),
+    );
+
+    let strategy_id = Uuid::new_v4();
+    let mut request = format!("ws://{addr}/ws?strategy_id={strategy_id}")
+        .into_client_request()
+        .unwrap();
+    request
+        .headers_mut()
+        .insert("authorization", api_key.parse().unwrap());
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+    app.core
+        .events()
+        .publish(Event::OrderFilled(fill(Uuid::new_v4())));
+    let expected = fill(strategy_id);
+    app.core
+        .events()
+        .publish(Event::OrderFilled(expected.clone()));
+
+    // Events of other strategies are filtered out
+    let Some(Ok(Message::Text(text))) = socket.next().await else {
+        panic!("expected a text message");
+    };
+    let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(event["type"], "order_filled");
+    assert_eq!(event["fill_id"], expected.fill_id.to_string());
+}
+
+#[test]
+fn events_round_trip_through_json() {
+    // NOTE: buses shared through Redis or NATS carry the events as JSON
+    let strategy_id = Uuid::new_v4();
+    let event = Event::OrderFilled(fill(strategy_id));
+    let json = serde_json::to_string(&event).unwrap();
+
+    let parsed: Event = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.strategy_id(), strategy_id);
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+}
@@ -0,0 +1,49 @@
+use chrono::Utc;
+use market::{
+    executions::{Execution, ExecutionMessage, PositionChange, SCHEMA_VERSION},
+    order::Fill,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+#[test]
+fn execution_messages_follow_the_schema() {
+    let fill = Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: Uuid::new_v4(),
+        strategy_id: Uuid::new_v4(),
+        ticker: "AAPL".to_string(),
+        side: "sell".to_string(),
+        quantity: Decimal::from(2),
+        price: Decimal::new(1905, 1),
+        fee: Decimal::ZERO,
+        filled_at: Utc::now(),
+    };
+    let message = ExecutionMessage {
+        version: SCHEMA_VERSION,
+        execution: Execution::PositionChanged(PositionChange::new(&fill, Decimal::from(3))),
+    };
+
+    let json = serde_json::to_value(&message).unwrap();
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["type"], "position_changed");
+    assert_eq!(json["strategy_id"], fill.strategy_id.to_string());
+    assert_eq!(json["fill_id"], fill.fill_id.to_string());
+
+    let parsed: ExecutionMessage = serde_json::from_value(json).unwrap();
+    let Execution::PositionChanged(change) = parsed.execution else {
+        panic!("expected a position change, got {:?}", parsed.execution);
+    };
+    assert_eq!(change.quantity, Decimal::from(3));
+    // Sells reduce the position
+    assert_eq!(change.change, Decimal::from(-2));
+
+    let json = serde_json::to_value(ExecutionMessage {
+        version: SCHEMA_VERSION,
+        execution: Execution::Fill(fill.clone()),
+    })
+    .unwrap();
+    assert_eq!(json["type"], "fill");
+    assert_eq!(json["order_id"], fill.order_id.to_string());
+}
@@ -0,0 +1,89 @@
+use futures::TryStreamExt;
+use market::{
+    app_config::AppConfig,
+    export::{trade_journal, ExportFormat, ExportQuery, Signing},
+};
+use minisign::{KeyPair, SignatureBox};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn trade_journal_realizes_pnl_of_earlier_fills(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let strategy_id = config.strategies[0].id;
+
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+        VALUES ('00000000-0000-0000-0000-000000000001', '00000000-0000-0000-0000-000000000001', $1, 'alpaca', 'AAPL', 'buy', 2, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    for (side, price, filled_at) in [
+        ("buy", 100, "2023-08-01 10:00:00+00"),
+        ("sell", 110, "2023-08-02 10:00:00+00"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO fills (fill_id, order_id, strategy_id, ticker, side, quantity, price, fee, filled_at)
+            VALUES (gen_random_uuid(), '00000000-0000-0000-0000-000000000001', $1, 'AAPL', $2, 2, $3, 1, $4::timestamptz)
+            "#,
+        )
+        .bind(strategy_id)
+        .bind(side)
+        .bind(Decimal::from(price))
+        .bind(filled_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let query = ExportQuery {
+        format: ExportFormat::Csv,
+        compression: None,
+        sign: false,
+        from: Some("2023-08-02T00:00:00Z".parse().unwrap()),
+        to: None,
+    };
+    let journal: Vec<_> = trade_journal(&pool, &config, &query)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(journal.len(), 1);
+    assert_eq!(journal[0].side, "sell");
+    assert_eq!(journal[0].strategy, config.strategies[0].name);
+    assert_eq!(journal[0].realized_pnl, Decimal::from(20));
+}
+
+#[tokio::test]
+async fn minisign_detached_signature() {
+    let KeyPair { pk, sk } =
+        KeyPair::generate_encrypted_keypair(Some("export".to_string())).unwrap();
+    let secret_key = std::env::temp_dir().join(format!("export-{}.key", uuid::Uuid::new_v4()));
+    std::fs::write(&secret_key, sk.to_box(None).unwrap().to_string()).unwrap();
+
+    let signing = Signing::Minisign {
+        secret_key: secret_key.clone(),
+        password: Some("export".to_string()),
+    };
+    let data = b"filled_at,strategy_id\n";
+    let signature = signing.sign(data).await.unwrap();
+    std::fs::remove_file(secret_key).unwrap();
+
+    let signature = SignatureBox::from_string(&signature).unwrap();
+    minisign::verify(
+        &pk,
+        &signature,
+        std::io::Cursor::new(data),
+        true,
+        false,
+        false,
+    )
+    .unwrap();
+}
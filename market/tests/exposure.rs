@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use market::{app_config::Session, exposure::compute_exposure, order::Fill};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+fn fill(side: &str, quantity: i64, price: i64, filled_at: &str) -> Fill {
+    Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: Uuid::new_v4(),
+        strategy_id: Uuid::nil(),
+        ticker: "AAPL".to_string(),
+        side: side.to_string(),
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        fee: Decimal::ZERO,
+        filled_at: DateTime::<Utc>::from_str(filled_at).unwrap(),
+    }
+}
+
+#[test]
+fn exposure_overnight_and_weekend() {
+    let fills = vec![
+        // Thursday
+        fill("buy", 10, 100, "2023-08-03T15:00:00Z"),
+        // Monday, half of the position is held for another night
+        fill("sell", 5, 110, "2023-08-07T15:00:00Z"),
+        fill("sell", 5, 110, "2023-08-08T15:00:00Z"),
+    ];
+
+    let exposure = compute_exposure(
+        Uuid::nil(),
+        &fills,
+        Some(&Session::default()),
+        DateTime::<Utc>::from_str("2023-08-01T00:00:00Z").unwrap(),
+        DateTime::<Utc>::from_str("2023-08-10T00:00:00Z").unwrap(),
+    );
+
+    // 1000 held for 96 hours and 500 for 24 more
+    assert_eq!(exposure.total, Decimal::from(108_000));
+    // Thursday night at 1000 and Monday night at 500, 17.5 hours each
+    assert_eq!(exposure.overnight, Decimal::from(26_250));
+    // Friday close to Monday open is 65.5 hours
+    assert_eq!(exposure.weekend, Decimal::from(65_500));
+}
@@ -0,0 +1,82 @@
+use market::feature_flags::{
+    FeatureFlags, UpdateFeatureFlag, ENABLE_NEW_SIZING_ENGINE, ENABLE_SHORTING, HALT_TRADING,
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test]
+async fn feature_flags_toggle(pool: PgPool) {
+    let flags = FeatureFlags::new(pool);
+
+    assert!(flags.is_enabled(ENABLE_SHORTING).await);
+    assert!(!flags.is_enabled(ENABLE_NEW_SIZING_ENGINE).await);
+    assert!(!flags.is_enabled("unknown_flag").await);
+
+    let flag = flags
+        .set(
+            ENABLE_SHORTING,
+            &UpdateFeatureFlag {
+                enabled: false,
+                description: None,
+                rollout_percentage: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert!(!flag.enabled);
+    assert_eq!(
+        flag.description.as_deref(),
+        Some("Allow strategies to open short positions")
+    );
+    assert!(!flags.is_enabled(ENABLE_SHORTING).await);
+    assert_eq!(flags.list().await.unwrap().len(), 3);
+}
+
+#[sqlx::test]
+async fn feature_flags_rollout(pool: PgPool) {
+    let flags = FeatureFlags::new(pool);
+
+    let flag = flags
+        .set(
+            ENABLE_NEW_SIZING_ENGINE,
+            &UpdateFeatureFlag {
+                enabled: true,
+                description: None,
+                rollout_percentage: Some(25),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(flag.rollout_percentage, 25);
+
+    // Keys are bucketed by their value modulo 100
+    assert!(
+        flags
+            .is_enabled_for(ENABLE_NEW_SIZING_ENGINE, Uuid::from_u128(124))
+            .await
+    );
+    assert!(
+        !flags
+            .is_enabled_for(ENABLE_NEW_SIZING_ENGINE, Uuid::from_u128(125))
+            .await
+    );
+    assert!(
+        !flags
+            .is_enabled_for("unknown_flag", Uuid::from_u128(0))
+            .await
+    );
+}
+
+#[sqlx::test]
+async fn halt_fails_closed(pool: PgPool) {
+    let flags = FeatureFlags::new(pool.clone());
+    assert!(!flags.is_halted().await);
+
+    // Flags can't be loaded once the pool is closed
+    let flags = FeatureFlags::new(pool.clone());
+    pool.close().await;
+    assert!(!flags.is_enabled(HALT_TRADING).await);
+    assert!(flags.is_halted().await);
+}
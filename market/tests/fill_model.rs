@@ -0,0 +1,110 @@
+use market::{
+    fill_model::{CommissionModel, FillModel, LatencyModel, Quote, SimulatedFill, SlippageModel},
+    order::OrderSide,
+};
+use pretty_assertions::assert_eq;
+use rand_core::OsRng;
+use rust_decimal::Decimal;
+use tokio::time::Duration;
+
+fn quote() -> Quote {
+    Quote {
+        price: Decimal::from(100),
+        spread: Decimal::new(10, 2),
+        volume: Decimal::from(1000),
+    }
+}
+
+#[test]
+fn fixed_slippage_and_partial_fills() {
+    let model = FillModel {
+        slippage: SlippageModel::FixedBps {
+            bps: Decimal::from(10),
+        },
+        latency: LatencyModel::Fixed { millis: 200 },
+        commission: CommissionModel::None,
+        max_participation: Some(Decimal::new(1, 1)),
+    };
+
+    let fills = model.simulate(OrderSide::Buy, Decimal::from(250), &quote(), &mut OsRng);
+    assert_eq!(
+        fills,
+        vec![
+            SimulatedFill {
+                delay: Duration::from_millis(200),
+                quantity: Decimal::from(100),
+                price: Decimal::new(1001, 1),
+                fee: Decimal::ZERO,
+            },
+            SimulatedFill {
+                delay: Duration::from_millis(400),
+                quantity: Decimal::from(100),
+                price: Decimal::new(1001, 1),
+                fee: Decimal::ZERO,
+            },
+            SimulatedFill {
+                delay: Duration::from_millis(600),
+                quantity: Decimal::from(50),
+                price: Decimal::new(1001, 1),
+                fee: Decimal::ZERO,
+            },
+        ]
+    );
+}
+
+#[test]
+fn spread_and_volume_impact_slippage() {
+    let spread = FillModel {
+        slippage: SlippageModel::Spread {
+            share: Decimal::new(5, 1),
+        },
+        ..FillModel::default()
+    };
+    let fills = spread.simulate(OrderSide::Sell, Decimal::from(10), &quote(), &mut OsRng);
+    assert_eq!(fills[0].price, Decimal::new(9995, 2));
+
+    let impact = FillModel {
+        slippage: SlippageModel::VolumeImpact {
+            coefficient: Decimal::new(1, 2),
+        },
+        latency: LatencyModel::Random {
+            min_millis: 50,
+            max_millis: 100,
+        },
+        commission: CommissionModel::None,
+        max_participation: None,
+    };
+    // 10% of the volume moves the price by 1% * sqrt(0.1)
+    let fills = impact.simulate(OrderSide::Buy, Decimal::from(100), &quote(), &mut OsRng);
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].price.round_dp(4), Decimal::new(1003162, 4));
+    assert!((50..=100).contains(&fills[0].delay.as_millis()));
+}
+
+#[test]
+fn commissions_are_charged_per_fill() {
+    let model = FillModel {
+        commission: CommissionModel::PerShare {
+            rate: Decimal::new(15, 3),
+            minimum: Decimal::ONE,
+        },
+        max_participation: Some(Decimal::new(1, 1)),
+        ..FillModel::default()
+    };
+    let fills = model.simulate(OrderSide::Buy, Decimal::from(250), &quote(), &mut OsRng);
+    let fees: Vec<Decimal> = fills.iter().map(|fill| fill.fee).collect();
+    // The last fill of 50 shares pays the minimum
+    assert_eq!(
+        fees,
+        vec![Decimal::new(15, 1), Decimal::new(15, 1), Decimal::ONE]
+    );
+
+    let bps = CommissionModel::Bps {
+        bps: Decimal::from(10),
+        minimum: Decimal::ZERO,
+    };
+    assert_eq!(
+        bps.commission(Decimal::from(50), Decimal::from(100)),
+        Decimal::from(5)
+    );
+}
@@ -0,0 +1,74 @@
+use market::{
+    api::alert::{SignalType, TrailStopPrice},
+    app_config::AppConfig,
+    filters::SignalFilter,
+    order::OrderSide,
+    trade_signal::TradeSignal,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+mod setup;
+
+fn trade_signal(signal_type: SignalType) -> TradeSignal {
+    let strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    TradeSignal {
+        signal_type,
+        ..setup::trade_signal(&strategy)
+    }
+}
+
+#[test]
+fn signal_filters() {
+    let filters: Vec<SignalFilter> = serde_json::from_value(json!([
+        {"type": "min_volume", "volume": "500"},
+        {"type": "price_range", "max": "99"},
+        {"type": "side", "side": "buy"},
+        {"type": "timeframes", "timeframes": ["4H"]},
+        {"type": "no_opposite_position"},
+    ]))
+    .unwrap();
+    let long = trade_signal(SignalType::OpenLong(TrailStopPrice(Decimal::from(95))));
+    let short = trade_signal(SignalType::OpenShort(TrailStopPrice(Decimal::from(105))));
+    let flat = Decimal::ZERO;
+
+    let passes: Vec<bool> = filters
+        .iter()
+        .map(|filter| filter.rejection(&long, flat).is_none())
+        .collect();
+    assert_eq!(passes, [true, false, true, false, true]);
+
+    let side = SignalFilter::Side {
+        side: OrderSide::Buy,
+    };
+    assert_eq!(
+        side.rejection(&short, flat),
+        Some("only buy signals are traded".to_string())
+    );
+
+    let no_opposite = SignalFilter::NoOppositePosition;
+    assert!(no_opposite.rejection(&long, Decimal::from(-3)).is_some());
+    assert!(no_opposite.rejection(&long, Decimal::from(3)).is_none());
+    assert!(no_opposite.rejection(&short, Decimal::from(3)).is_some());
+
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].filters = vec![
+        SignalFilter::PriceRange {
+            min: Some(Decimal::from(10)),
+            max: Some(Decimal::from(5)),
+        },
+        SignalFilter::Timeframes {
+            timeframes: Vec::new(),
+        },
+    ];
+    let violations = config.validate().unwrap_err().0;
+    let fields: Vec<&str> = violations
+        .iter()
+        .map(|violation| violation.field.as_str())
+        .collect();
+    assert_eq!(
+        fields,
+        ["strategies[0].filters[0]", "strategies[0].filters[1]"]
+    );
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "graphql")]
+
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use market::{app_config::AppConfig, dedup};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[sqlx::test]
+async fn strategy_report_in_one_query(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let strategy_id = config.strategies[0].id;
+
+    let order_id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', 'buy', 10, 'new', NOW(), NOW())
+        "#,
+    )
+    .bind(order_id)
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    dedup::claim_alert(&pool, "graphql", strategy_id, Some("req-1"), 60)
+        .await
+        .unwrap();
+
+    let app = make_test_app_with_config(pool, config).await;
+    let query = format!(
+        r#"{{ strategy(id: "{strategy_id}") {{ name orders {{ orderId quantity }} alerts {{ requestId }} }} }}"#
+    );
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/graphql")
+                .header(header::AUTHORIZATION, api_key)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "query": query }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let strategy = &body["data"]["strategy"];
+    assert_eq!(strategy["orders"][0]["orderId"], order_id.to_string());
+    assert_eq!(strategy["orders"][0]["quantity"], "10.00000000");
+    assert_eq!(strategy["alerts"][0]["requestId"], "req-1");
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "grpc")]
+
+use market::{
+    app_config::AppConfig,
+    grpc::{
+        proto::{
+            market_client::MarketClient, market_server::MarketServer, GetOrderRequest,
+            ListStrategiesRequest,
+        },
+        MarketService,
+    },
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Server, Code, Request};
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_state;
+
+fn authorized<T>(message: T, api_key: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("authorization", api_key.parse().unwrap());
+    request
+}
+
+#[sqlx::test]
+async fn grpc_calls_require_api_key(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let strategies = config.strategies.len();
+    let app = make_test_state(pool, config).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(MarketServer::new(MarketService::new(app)))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    let mut client = MarketClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    let status = client
+        .list_strategies(ListStrategiesRequest {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let response = client
+        .list_strategies(authorized(ListStrategiesRequest {}, &api_key))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().strategies.len(), strategies);
+
+    let status = client
+        .get_order(authorized(
+            GetOrderRequest {
+                order_id: Uuid::new_v4().to_string(),
+            },
+            &api_key,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
@@ -0,0 +1,87 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use market::{
+    app_config::AppConfig,
+    build_routes,
+    health::{CheckStatus, DeepHealth, Readiness},
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_state;
+
+#[sqlx::test]
+async fn deep_health_reports_every_dependency(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let routes = build_routes(make_test_state(pool, config).await);
+
+    let response = routes
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri("/health/deep")
+                .header(header::AUTHORIZATION, api_key)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // The background work of the core isn't started by tests
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let health: DeepHealth = serde_json::from_slice(&body).unwrap();
+    assert_eq!(health.status, CheckStatus::Down);
+
+    let names: Vec<&str> = health
+        .checks
+        .iter()
+        .map(|check| check.name.as_str())
+        .collect();
+    assert_eq!(names.first(), Some(&"database"));
+    assert_eq!(names.last(), Some(&"event_queue"));
+    assert!(names.contains(&"broker.alpaca"));
+    assert_eq!(health.checks[0].status, CheckStatus::Up);
+    assert!(health.checks.last().unwrap().error.is_some());
+}
+
+async fn probe(routes: axum::Router, uri: &str) -> (StatusCode, hyper::body::Bytes) {
+    let response = routes
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    (
+        status,
+        hyper::body::to_bytes(response.into_body()).await.unwrap(),
+    )
+}
+
+#[sqlx::test]
+async fn probes_need_no_api_key_and_readiness_fails_while_draining(pool: PgPool) {
+    let state = make_test_state(pool, AppConfig::build_for_test().unwrap()).await;
+    let routes = build_routes(state.clone());
+
+    let (status, _) = probe(routes.clone(), "/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = probe(routes.clone(), "/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    let readiness: Readiness = serde_json::from_slice(&body).unwrap();
+    assert!(readiness.ready);
+
+    state.shutdown.begin();
+    let (status, body) = probe(routes.clone(), "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let readiness: Readiness = serde_json::from_slice(&body).unwrap();
+    assert_eq!(readiness.reasons, vec!["shutting down".to_owned()]);
+
+    // Living on while draining, the process isn't to be restarted
+    let (status, _) = probe(routes, "/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "test-broker")]
+
+use std::sync::{Arc, Mutex};
+
+use market::{
+    app_config::AppConfig,
+    core::TradeError,
+    hooks::{
+        OrderAnnotation, OrderOrigin, PlacedOrder, PostTradeHook, PreTradeDecision, PreTradeHook,
+    },
+    order::NewOrder,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{make_test_state, mock_broker, trade_signal};
+
+/// Notes orders and vetoes the ones of more than 5 shares.
+struct Compliance;
+
+#[axum::async_trait]
+impl PreTradeHook for Compliance {
+    fn name(&self) -> &str {
+        "compliance"
+    }
+
+    async fn before_order(&self, _: &OrderOrigin<'_>, order: &NewOrder) -> PreTradeDecision {
+        if order.quantity > Decimal::from(5) {
+            PreTradeDecision::Veto("more than 5 shares".to_owned())
+        } else {
+            PreTradeDecision::Annotate(format!("checked {} shares", order.quantity))
+        }
+    }
+}
+
+/// Origins and statuses of the orders placed.
+#[derive(Default)]
+struct Statuses(Mutex<Vec<(String, String)>>);
+
+#[axum::async_trait]
+impl PostTradeHook for Statuses {
+    fn name(&self) -> &str {
+        "statuses"
+    }
+
+    async fn after_order(&self, placed: &PlacedOrder<'_>) -> Result<(), String> {
+        let status = placed.result.map_err(|err| err.to_string())?;
+        let origin = match placed.origin {
+            OrderOrigin::Signal { .. } => "signal",
+            OrderOrigin::Flatten => "flatten",
+            _ => "other",
+        };
+        self.0
+            .lock()
+            .unwrap()
+            .push((origin.to_owned(), status.to_owned()));
+        Ok(())
+    }
+}
+
+#[sqlx::test]
+async fn trade_hooks_veto_and_annotate_orders(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = true;
+    let app = make_test_state(pool.clone(), config).await;
+    let statuses = Arc::new(Statuses::default());
+    app.executor.add_pre_trade_hook(Arc::new(Compliance));
+    app.executor.add_post_trade_hook(statuses.clone());
+
+    let broker = mock_broker();
+
+    let mut signal = trade_signal(&strategy);
+    signal.quantity = Some(Decimal::from(10));
+    let err = app
+        .core
+        .process_trade_signal(broker.clone(), signal)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TradeError::Vetoed(hook, _) if hook == "compliance"));
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE strategy_id = $1")
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 0);
+
+    let mut signal = trade_signal(&strategy);
+    signal.quantity = Some(Decimal::from(2));
+    app.core
+        .process_trade_signal(broker.clone(), signal)
+        .await
+        .unwrap();
+
+    let order_id: uuid::Uuid =
+        sqlx::query_scalar("SELECT order_id FROM orders WHERE strategy_id = $1")
+            .bind(strategy.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    let annotations = OrderAnnotation::fetch_for(&pool, order_id).await.unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].hook, "compliance");
+    assert_eq!(annotations[0].note, "checked 2 shares");
+    // Vetoed orders never reach the post-trade hooks
+    assert_eq!(
+        *statuses.0.lock().unwrap(),
+        vec![("signal".to_owned(), "simulated".to_owned())]
+    );
+
+    // Orders of every origin go through the hooks
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'MSFT', 8, 1, NOW(), NOW()), ($1, 'NVDA', 3, 1, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let report = app
+        .core
+        .flatten_strategy(broker.clone(), &strategy)
+        .await
+        .unwrap();
+    assert_eq!(report.orders.len(), 1);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].starts_with("MSFT: Order vetoed by hook compliance"));
+    assert_eq!(
+        statuses.0.lock().unwrap().last(),
+        Some(&("flatten".to_owned(), "simulated".to_owned()))
+    );
+}
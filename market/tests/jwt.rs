@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use axum::{routing::get, Json, Router};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use market::{
+    api_keys::Role,
+    app_config::Jwt,
+    jwt::{JwtError, JwtVerifier},
+};
+use serde_json::json;
+
+const ISSUER: &str = "https://id.example.com";
+
+fn serve_jwks() -> String {
+    serve_counted_jwks(Arc::default())
+}
+
+/// Serve the JWKS, counting the fetches in `fetches`.
+fn serve_counted_jwks(fetches: Arc<AtomicUsize>) -> String {
+    let app = Router::new().route(
+        "/jwks",
+        get(move || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async {
+                // Base64url of the "secret" signing key
+                Json(json!({"keys": [{"kty": "oct", "kid": "test", "alg": "HS256", "k": "c2VjcmV0"}]}))
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+
+    format!("http://{addr}/jwks")
+}
+
+fn token(issuer: &str, roles: serde_json::Value) -> String {
+    signed_token(Algorithm::HS256, "test", issuer, roles)
+}
+
+fn signed_token(algorithm: Algorithm, kid: &str, issuer: &str, roles: serde_json::Value) -> String {
+    let mut header = Header::new(algorithm);
+    header.kid = Some(kid.to_string());
+    let claims = json!({
+        "iss": issuer,
+        "exp": Utc::now().timestamp() + 60,
+        "groups": roles,
+    });
+
+    encode(&header, &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+}
+
+#[tokio::test]
+async fn role_from_token_claims() {
+    let verifier = JwtVerifier::new(Jwt {
+        issuer: ISSUER.to_string(),
+        jwks_url: serve_jwks(),
+        audience: None,
+        role_claim: "groups".to_string(),
+        roles: HashMap::from([
+            ("traders".to_string(), Role::Trade),
+            ("ops".to_string(), Role::Admin),
+        ]),
+        user_claim: None,
+        algorithms: vec![Algorithm::RS256],
+    });
+
+    let role = verifier
+        .role(&token(ISSUER, json!(["viewers", "traders"])))
+        .await
+        .unwrap();
+    assert_eq!(role, Role::Trade);
+
+    let role = verifier.role(&token(ISSUER, json!("viewers"))).await;
+    assert!(matches!(role, Err(JwtError::NoRole)));
+
+    let role = verifier
+        .role(&token("https://other.example.com", json!("ops")))
+        .await;
+    assert!(matches!(role, Err(JwtError::InvalidToken(_))));
+}
+
+#[tokio::test]
+async fn algorithm_of_the_key_is_enforced() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let verifier = JwtVerifier::new(Jwt {
+        issuer: ISSUER.to_string(),
+        jwks_url: serve_counted_jwks(Arc::clone(&fetches)),
+        audience: None,
+        role_claim: "groups".to_string(),
+        roles: HashMap::new(),
+        user_claim: None,
+        algorithms: vec![Algorithm::RS256],
+    });
+
+    let token = signed_token(Algorithm::HS384, "test", ISSUER, json!("trade"));
+    let role = verifier.role(&token).await;
+    assert!(matches!(
+        role,
+        Err(JwtError::UnacceptedAlgorithm(Algorithm::HS384))
+    ));
+
+    // Unknown keys don't refetch the set right after a fetch
+    let token = signed_token(Algorithm::HS256, "other", ISSUER, json!("trade"));
+    for _ in 0..3 {
+        let role = verifier.role(&token).await;
+        assert!(matches!(role, Err(JwtError::UnknownKey)));
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use market::{
+    ladder::{plan, LadderLeg, ProfitTarget, TakeProfitLadder},
+    order::OrderSide,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn ladder() -> TakeProfitLadder {
+    TakeProfitLadder {
+        targets: vec![
+            ProfitTarget {
+                r_multiple: dec("1"),
+                share: dec("0.5"),
+            },
+            ProfitTarget {
+                r_multiple: dec("2"),
+                share: dec("0.25"),
+            },
+        ],
+    }
+}
+
+#[test]
+fn targets_are_multiples_of_the_risk_of_the_entry() {
+    // The last quarter is left to the stop
+    assert_eq!(
+        plan(&ladder(), OrderSide::Buy, dec("100"), dec("95"), dec("40")),
+        vec![
+            LadderLeg {
+                limit_price: dec("105"),
+                quantity: dec("20"),
+            },
+            LadderLeg {
+                limit_price: dec("110"),
+                quantity: dec("10"),
+            },
+        ]
+    );
+    assert_eq!(
+        plan(&ladder(), OrderSide::Sell, dec("50"), dec("52"), dec("8")),
+        vec![
+            LadderLeg {
+                limit_price: dec("48"),
+                quantity: dec("4"),
+            },
+            LadderLeg {
+                limit_price: dec("46"),
+                quantity: dec("2"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn entries_without_risk_get_no_targets() {
+    // Stop moved to break-even
+    assert_eq!(
+        plan(&ladder(), OrderSide::Buy, dec("100"), dec("100"), dec("40")),
+        Vec::new()
+    );
+    // Short with its stop below the entry
+    assert_eq!(
+        plan(&ladder(), OrderSide::Sell, dec("50"), dec("48"), dec("8")),
+        Vec::new()
+    );
+}
@@ -0,0 +1,40 @@
+use market::leases::{Leases, StrategyLease};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test]
+async fn strategy_lease_is_held_by_one_signal(pool: PgPool) {
+    let config = Leases {
+        ttl: 60_000,
+        wait: 0,
+    };
+    let strategy_id = Uuid::new_v4();
+
+    let lease = StrategyLease::acquire(&pool, strategy_id, &config)
+        .await
+        .unwrap()
+        .expect("a free lease");
+    assert!(StrategyLease::acquire(&pool, strategy_id, &config)
+        .await
+        .unwrap()
+        .is_none());
+    // Leases of other strategies are independent
+    assert!(StrategyLease::acquire(&pool, Uuid::new_v4(), &config)
+        .await
+        .unwrap()
+        .is_some());
+
+    lease.release(&pool).await.unwrap();
+    let expiring = Leases { ttl: 1, ..config };
+    StrategyLease::acquire(&pool, strategy_id, &expiring)
+        .await
+        .unwrap()
+        .expect("a released lease");
+
+    // The lease of a signal which never released it is taken over once it expired
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert!(StrategyLease::acquire(&pool, strategy_id, &config)
+        .await
+        .unwrap()
+        .is_some());
+}
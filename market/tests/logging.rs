@@ -0,0 +1,89 @@
+use std::{net::UdpSocket, time::Duration};
+
+use axum::http::{HeaderMap, HeaderValue};
+use market::logging::{self, LoggingConfig};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+#[test]
+fn module_levels() {
+    let config: LoggingConfig = serde_json::from_value(json!({
+        "format": "json",
+        "level": "warn",
+        "modules": { "market::core": "debug", "sqlx": "error" }
+    }))
+    .unwrap();
+    assert_eq!(config.directives(), "warn,market::core=debug,sqlx=error");
+    assert!(config.invalid_levels().is_empty());
+
+    let config: LoggingConfig = serde_json::from_value(json!({
+        "level": "loud",
+        "modules": { "market::core": "debug", "sqlx": "verbose" }
+    }))
+    .unwrap();
+    let fields: Vec<String> = config
+        .invalid_levels()
+        .into_iter()
+        .map(|(field, _)| field)
+        .collect();
+    assert_eq!(fields, vec!["logging.level", "logging.modules.sqlx"]);
+}
+
+#[test]
+fn credentials_are_redacted() {
+    let mut headers = HeaderMap::new();
+    headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+    headers.insert("apca-api-key-id", HeaderValue::from_static("PKTEST"));
+    headers.insert("x-signature", HeaderValue::from_static("5f2a"));
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    let headers = logging::redact_headers(&headers);
+    assert_eq!(headers["authorization"], "[redacted]");
+    assert_eq!(headers["apca-api-key-id"], "[redacted]");
+    assert_eq!(headers["x-signature"], "[redacted]");
+    assert_eq!(headers["content-type"], "application/json");
+
+    let body = logging::redact_body(
+        br#"{"ticker": "AAPL", "brokers": {"oanda": {"api_token": "abc", "account_id": "001"}}}"#,
+    );
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "ticker": "AAPL",
+            "brokers": { "oanda": { "api_token": "[redacted]", "account_id": "001" } }
+        })
+    );
+    assert_eq!(
+        logging::redact_body(b"api_key=abc"),
+        "[redacted] (11 bytes)"
+    );
+    assert_eq!(logging::redact_body(b""), "");
+}
+
+#[test]
+fn syslog_messages_carry_the_severity_of_their_level() {
+    let daemon = UdpSocket::bind("127.0.0.1:0").unwrap();
+    daemon
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let config: LoggingConfig = serde_json::from_value(json!({
+        "sinks": [{
+            "kind": "syslog",
+            "addr": daemon.local_addr().unwrap().to_string(),
+            "facility": "local0"
+        }]
+    }))
+    .unwrap();
+    let _guard = logging::init(&config).unwrap();
+
+    tracing::warn!("Order rejected");
+    let mut message = [0; 1024];
+    let len = daemon.recv(&mut message).unwrap();
+    let message = String::from_utf8_lossy(&message[..len]);
+    // Facility local0 is 16, severity warning is 4
+    assert!(
+        message.starts_with(&format!("<132>market[{}]: ", std::process::id())),
+        "{message}"
+    );
+    assert!(message.ends_with("Order rejected"), "{message}");
+}
@@ -0,0 +1,120 @@
+use chrono::{TimeZone, Utc};
+use market::{
+    api::alert::SignalType,
+    mapping::{AlertMapping, Expression, MappingError},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+#[test]
+fn mapping_expressions() {
+    let alert = json!({
+        "plot_0": "2.5",
+        "close": 101.25,
+        "strategy.order.action": "BUY",
+        "order": {"legs": [{"size": 4}]},
+        "time": 1700000000000i64,
+    });
+    let expression = |source: &str| source.parse::<Expression>().unwrap();
+
+    assert_eq!(
+        expression("{{plot_0}} * 10 - 1").decimal(&alert),
+        Ok(Decimal::from(24))
+    );
+    assert_eq!(
+        expression("(close - 1.25) / order.legs.0.size").decimal(&alert),
+        Ok(Decimal::from(25))
+    );
+    assert_eq!(
+        expression("-plot_0").decimal(&alert),
+        Ok(Decimal::new(-25, 1))
+    );
+    assert_eq!(
+        expression("{{strategy.order.action}}").text(&alert),
+        Ok("BUY".to_string())
+    );
+    assert_eq!(
+        expression("'NASDAQ'").text(&alert),
+        Ok("NASDAQ".to_string())
+    );
+    assert_eq!(
+        expression("time").time(&alert),
+        Ok(Utc.timestamp_millis_opt(1700000000000).unwrap())
+    );
+
+    assert_eq!(
+        expression("plot_1").decimal(&alert),
+        Err(MappingError::MissingField("plot_1".to_string()))
+    );
+    assert!(matches!(
+        expression("close / 0").decimal(&alert),
+        Err(MappingError::DivisionByZero(_))
+    ));
+    assert!(matches!(
+        expression("strategy.order.action").decimal(&alert),
+        Err(MappingError::NotANumber(_))
+    ));
+    for invalid in ["", "close *", "(close", "{{close", "close $ 2", "'open"] {
+        assert!(
+            matches!(
+                invalid.parse::<Expression>(),
+                Err(MappingError::InvalidExpression(..))
+            ),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn alerts_mapped_to_order_parameters() {
+    let mapping: AlertMapping = serde_json::from_value(json!({
+        "ticker": "{{ticker}}",
+        "exchange": "'NASDAQ'",
+        "action": {
+            "field": "{{strategy.order.action}}",
+            "values": {"buy": "open_long", "sell": "open_short"},
+        },
+        "stop_loss": "{{plot_1}}",
+        "close": "{{close}}",
+        "quantity": "{{plot_0}} * 10",
+    }))
+    .unwrap();
+    let now = Utc::now();
+    let strategy_id = Uuid::new_v4();
+
+    let alert = json!({
+        "strategy_id": strategy_id,
+        "ticker": "AAPL",
+        "strategy.order.action": "sell",
+        "close": "100",
+        "plot_0": "0.5",
+        "plot_1": "105",
+    });
+    let alert_data = mapping.map(strategy_id, &alert, now).unwrap();
+    assert_eq!(alert_data.ticker, "AAPL");
+    assert_eq!(alert_data.exchange, "NASDAQ");
+    assert!(
+        matches!(alert_data.signal_type, SignalType::OpenShort(ref stop) if stop.0 == Decimal::from(105))
+    );
+    assert_eq!(alert_data.quantity, Some(Decimal::from(5)));
+    assert_eq!(*alert_data.bar_data.high.as_ref(), Decimal::from(100));
+    assert_eq!(alert_data.time, now);
+
+    let mut unmapped = alert;
+    unmapped["strategy.order.action"] = json!("close");
+    assert_eq!(
+        mapping.map(strategy_id, &unmapped, now).unwrap_err(),
+        MappingError::UnmappedAction("close".to_string())
+    );
+
+    // Expressions are checked when the mapping is loaded
+    assert!(serde_json::from_value::<AlertMapping>(json!({
+        "ticker": "ticker",
+        "action": {"field": "action", "values": {}},
+        "stop_loss": "plot_1 +",
+        "close": "close",
+    }))
+    .is_err());
+}
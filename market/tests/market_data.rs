@@ -0,0 +1,82 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use chrono::{Duration, Utc};
+use market::{
+    app_config::AppConfig,
+    market_data::{strategy_symbols, LiveQuote, QuoteBook},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[tokio::test]
+async fn stale_quotes_are_ignored() {
+    let quotes = QuoteBook::default();
+    let fresh = LiveQuote {
+        bid: Decimal::from(99),
+        ask: Decimal::from(101),
+        time: Utc::now(),
+    };
+    quotes.update("AAPL", fresh).await;
+    quotes
+        .update(
+            "MSFT",
+            LiveQuote {
+                time: Utc::now() - Duration::minutes(5),
+                ..fresh
+            },
+        )
+        .await;
+
+    let latest = quotes.latest("AAPL").await.unwrap();
+    assert_eq!(latest.mid(), Decimal::from(100));
+    assert_eq!(latest.spread(), Decimal::TWO);
+    assert_eq!(quotes.latest("MSFT").await, None);
+    assert_eq!(quotes.latest("TSLA").await, None);
+}
+
+#[test]
+fn symbols_of_enabled_strategies() {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut first = config.strategies[0].clone();
+    first.symbols = vec!["MSFT".to_string(), "AAPL".to_string()];
+    let mut second = first.clone();
+    second.symbols = vec!["AAPL".to_string(), "TSLA".to_string()];
+    let mut disabled = first.clone();
+    disabled.enabled = false;
+    disabled.symbols = vec!["NVDA".to_string()];
+
+    assert_eq!(
+        strategy_symbols(&[first, second, disabled]),
+        vec!["AAPL", "MSFT", "TSLA"]
+    );
+}
+
+#[sqlx::test]
+async fn bars_period_must_end_after_it_starts(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(
+                    "/marketdata/bars/AAPL?timeframe=one_day&from=2023-10-02T00:00:00Z&\
+                     to=2023-10-01T00:00:00Z",
+                )
+                .header(header::AUTHORIZATION, api_key)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "test-broker")]
+
+use market::{
+    clients::{BrokerClient, BrokerClientError},
+    mock_broker::MockBrokerClient,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+#[tokio::test]
+async fn scripted_answers_are_taken_in_order() {
+    let broker = MockBrokerClient::new();
+    broker.fail(
+        "get_positions",
+        BrokerClientError::AlpacaUnavailable("timeout".to_string()),
+    );
+    broker.respond("get_positions", json!([]));
+
+    assert!(matches!(
+        broker.get_positions().await,
+        Err(BrokerClientError::AlpacaUnavailable(_))
+    ));
+    assert!(broker.get_positions().await.unwrap().is_empty());
+    assert!(matches!(
+        broker.get_positions().await,
+        Err(BrokerClientError::MockError(_))
+    ));
+
+    broker.set_default("get_account", json!("not an account"));
+    assert!(matches!(
+        broker.get_account().await,
+        Err(BrokerClientError::MockError(_))
+    ));
+    assert_eq!(broker.calls().len(), 4);
+}
@@ -0,0 +1,236 @@
+use std::{net::TcpListener, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use market::{
+    api::objects::Money,
+    app_config::{Oanda, TradingEnvironment},
+    clients::{order_uuid, BrokerClient},
+    oanda::{OandaClient, OandaClock},
+    order::{NewOrder, OrderSide, TimeInForce},
+    sizing::ExecutionPath,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const ACCOUNT: &str = "/v3/accounts/001-001-1234567-001";
+
+fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+    if headers["Authorization"] != "Bearer oanda-token" {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "errorMessage": "Insufficient authorization to perform request." })),
+        ));
+    }
+    Ok(())
+}
+
+/// v20 API of an account which fills every order right away and opens a trade with its stop loss.
+fn v20() -> Router {
+    Router::new()
+        .route(
+            &format!("{ACCOUNT}/orders"),
+            post(
+                |headers: HeaderMap, Json(request): Json<Value>| async move {
+                    authorized(&headers)?;
+                    assert_eq!(
+                        request,
+                        json!({
+                            "order": {
+                                "type": "MARKET",
+                                "instrument": "EUR_USD",
+                                "units": "1000",
+                                "timeInForce": "FOK",
+                                "positionFill": "DEFAULT",
+                                "clientExtensions": { "id": "client-id" },
+                                "stopLossOnFill": { "price": "1.09500", "timeInForce": "GTC" }
+                            }
+                        })
+                    );
+                    Ok::<_, (StatusCode, Json<Value>)>(Json(json!({
+                        "orderCreateTransaction": { "id": "6356" },
+                        "orderFillTransaction": { "id": "6357" }
+                    })))
+                },
+            ),
+        )
+        .route(
+            &format!("{ACCOUNT}/orders/:specifier"),
+            get(|Path(specifier): Path<String>| async move {
+                if specifier != "6356" && specifier != "@client-id" {
+                    return Err(StatusCode::NOT_FOUND);
+                }
+                Ok(Json(json!({
+                    "order": {
+                        "id": "6356",
+                        "createTime": "2024-03-05T14:30:00.000000000Z",
+                        "state": "FILLED",
+                        "type": "MARKET",
+                        "instrument": "EUR_USD",
+                        "units": "1000",
+                        "timeInForce": "FOK",
+                        "positionFill": "DEFAULT",
+                        "clientExtensions": { "id": "client-id" },
+                        "stopLossOnFill": { "price": "1.09500", "timeInForce": "GTC" },
+                        "fillingTransactionID": "6357",
+                        "tradeOpenedID": "6357"
+                    }
+                })))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/transactions/6357"),
+            get(|| async {
+                Json(json!({
+                    "transaction": {
+                        "id": "6357",
+                        "type": "ORDER_FILL",
+                        "orderID": "6356",
+                        "instrument": "EUR_USD",
+                        "units": "1000",
+                        "price": "1.10012",
+                        "time": "2024-03-05T14:30:01.000000000Z"
+                    }
+                }))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/trades/6357"),
+            get(|| async {
+                Json(json!({
+                    "trade": {
+                        "id": "6357",
+                        "stopLossOrder": { "id": "6358", "price": "1.09500", "state": "PENDING" }
+                    }
+                }))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/summary"),
+            get(|headers: HeaderMap| async move {
+                authorized(&headers)?;
+                Ok::<_, (StatusCode, Json<Value>)>(Json(json!({
+                    "account": {
+                        "id": "001-001-1234567-001",
+                        "currency": "EUR",
+                        "balance": "10000.0000",
+                        "NAV": "10012.5000",
+                        "unrealizedPL": "12.5000",
+                        "marginAvailable": "9800.0000",
+                        "marginRate": "0.0333",
+                        "openPositionCount": 1,
+                        "pendingOrderCount": 1
+                    }
+                })))
+            }),
+        )
+}
+
+fn oanda_client(base_url: String, api_token: &str) -> Arc<OandaClient> {
+    let config = Oanda {
+        account_id: "001-001-1234567-001".to_owned(),
+        api_token: api_token.to_owned(),
+        base_url: Some(base_url),
+        requests_per_minute: 1200,
+        currency: "EUR".to_owned(),
+    };
+    Arc::new(OandaClient::new(&config, TradingEnvironment::Paper))
+}
+
+#[tokio::test]
+async fn oanda_orders() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(v20().into_make_service()),
+    );
+    let client = oanda_client(base_url.clone(), "oanda-token");
+
+    let new_order = NewOrder {
+        id: Uuid::new_v4(),
+        strategy_id: Uuid::new_v4(),
+        client_order_id: "client-id".to_owned(),
+        ticker: "EUR_USD".to_owned(),
+        side: OrderSide::Buy,
+        quantity: Decimal::from(1000),
+        notional: None,
+        stop_loss_price: Some(Decimal::new(109500, 5)),
+        limit_price: None,
+        time_in_force: TimeInForce::Gtc,
+        extended_hours: false,
+        execution_path: ExecutionPath::Stable,
+        request_id: None,
+        environment: TradingEnvironment::Paper,
+        account: None,
+        legs: Vec::new(),
+    };
+    let order = client
+        .create_order(client.order_request(&new_order))
+        .await
+        .unwrap();
+
+    assert_eq!(order.broker_order_id(), "6356");
+    assert_eq!(order.status(), "filled");
+    assert_eq!(order.side(), OrderSide::Buy);
+    assert_eq!(order.filled_quantity(), Decimal::from(1000));
+    assert_eq!(
+        order.filled_avg_price(),
+        Some(Money::new(Decimal::new(110012, 5), "USD"))
+    );
+    assert_eq!(
+        order.stop_loss_leg(),
+        Some((order_uuid("6358").unwrap(), Decimal::new(109500, 5)))
+    );
+    assert!(!order.stopped_out());
+    assert_eq!(
+        order.filled_at(),
+        Some("2024-03-05T14:30:01Z".parse().unwrap())
+    );
+
+    let order = client
+        .get_order_by_client_id("client-id".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(order.broker_order_id(), "6356");
+
+    let account = client.get_account().await.unwrap();
+    assert_eq!(account.equity(), Money::new(Decimal::new(100125, 1), "EUR"));
+    assert_eq!(account.cash(), Money::new(Decimal::from(10_000), "EUR"));
+
+    // Rejected tokens count as authentication failures for the circuit breaker
+    let err = oanda_client(base_url, "revoked-token")
+        .get_account()
+        .await
+        .unwrap_err();
+    assert!(err.is_authentication_failure());
+}
+
+#[test]
+fn forex_session() {
+    let at = |time: &str| DateTime::<Utc>::from_str(time).unwrap();
+
+    // Wednesday
+    let clock = OandaClock::at(at("2024-03-06T12:00:00Z"));
+    assert!(clock.open);
+    assert_eq!(clock.next_close, at("2024-03-08T21:00:00Z"));
+    assert_eq!(clock.next_open, at("2024-03-10T21:00:00Z"));
+
+    // Saturday
+    let clock = OandaClock::at(at("2024-03-09T12:00:00Z"));
+    assert!(!clock.open);
+    assert_eq!(clock.next_open, at("2024-03-10T21:00:00Z"));
+    assert_eq!(clock.next_close, at("2024-03-15T21:00:00Z"));
+
+    // Sunday before and after the open
+    assert!(!OandaClock::at(at("2024-03-10T20:59:00Z")).open);
+    assert!(OandaClock::at(at("2024-03-10T21:00:00Z")).open);
+}
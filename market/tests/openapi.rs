@@ -0,0 +1,38 @@
+#![cfg(feature = "openapi")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app;
+
+#[sqlx::test]
+async fn openapi_document_is_served_without_api_key(pool: PgPool) {
+    let app = make_test_app(pool).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/docs/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let document: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    for path in ["/orders", "/order/{id}", "/positions", "/strategies"] {
+        assert!(document["paths"][path].is_object(), "{path} not documented");
+    }
+    assert!(document["components"]["securitySchemes"]["api_key"].is_object());
+    assert_eq!(
+        document["components"]["schemas"]["ErrorCode"]["enum"][0],
+        "bad_request"
+    );
+}
@@ -0,0 +1,64 @@
+use chrono::NaiveDate;
+use market::{
+    options::{OptionContract, OptionRight, OptionsError, OrderLeg},
+    order::OrderSide,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+
+#[test]
+fn occ_symbols() {
+    let contract = OptionContract {
+        underlying: "AAPL".to_string(),
+        expiry: NaiveDate::from_ymd_opt(2024, 1, 19).unwrap(),
+        strike: Decimal::from(190),
+        right: OptionRight::Call,
+    };
+    assert_eq!(contract.occ_symbol(), "AAPL240119C00190000");
+    assert_eq!("AAPL240119C00190000".parse(), Ok(contract.clone()));
+    // Strict format with the root padded to 6 characters
+    assert_eq!("AAPL  240119C00190000".parse(), Ok(contract));
+
+    let contract: OptionContract = "SPY231215P00452500".parse().unwrap();
+    assert_eq!(contract.underlying, "SPY");
+    assert_eq!(contract.strike, Decimal::new(4525, 1));
+    assert_eq!(contract.right, OptionRight::Put);
+
+    for symbol in [
+        "",
+        "240119C00190000",
+        "AAPL241319C00190000",
+        "AAPL240119X00190000",
+        "AAPL240119C0019000A",
+        "TOOLONG240119C00190000",
+    ] {
+        assert_eq!(
+            symbol.parse::<OptionContract>(),
+            Err(OptionsError::InvalidSymbol(symbol.to_string()))
+        );
+    }
+}
+
+#[test]
+fn order_legs_deserialize_from_occ_symbols() {
+    let legs: Vec<OrderLeg> = serde_json::from_str(
+        r#"[
+            {"contract": "SPY231215C00450000", "side": "buy"},
+            {"contract": "SPY231215C00460000", "side": "sell", "ratio": 2}
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(legs[0].contract.strike, Decimal::from(450));
+    assert_eq!(legs[0].side, OrderSide::Buy);
+    assert_eq!(legs[0].ratio, 1);
+    assert_eq!(legs[1].side, OrderSide::Sell);
+    assert_eq!(legs[1].ratio, 2);
+    assert_eq!(
+        serde_json::to_value(&legs[1].contract).unwrap(),
+        "SPY231215C00460000"
+    );
+
+    let invalid = serde_json::from_str::<Vec<OrderLeg>>(r#"[{"contract": "SPY", "side": "buy"}]"#);
+    assert!(invalid.is_err());
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "test-broker")]
+
+use market::{app_config::AppConfig, clients::BrokerClientError, core::TradeError};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{make_test_state, mock_broker, trade_signal};
+
+#[sqlx::test]
+async fn unavailable_broker_exhausts_order_retries(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = false;
+    strategy.max_order_retries = 2;
+    strategy.order_retry_delay = 0.0;
+    let app = make_test_state(pool.clone(), config).await;
+
+    let broker = mock_broker();
+    for _ in 0..3 {
+        broker.fail(
+            "create_order",
+            BrokerClientError::AlpacaUnavailable("service unavailable".to_string()),
+        );
+    }
+
+    let result = app
+        .core
+        .process_trade_signal(broker.clone(), trade_signal(&strategy))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TradeError::MaxRetriesReached(_, 2, _))
+    ));
+
+    let orders = broker.calls_of("create_order");
+    assert_eq!(orders.len(), 3);
+    assert_eq!(orders[0].request["symbol"], "AAPL");
+    // Every retry first looks for an order submitted by the failed attempt
+    assert_eq!(broker.calls_of("get_order_by_client_id").len(), 2);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE strategy_id = $1")
+        .bind(strategy.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "rejected");
+}
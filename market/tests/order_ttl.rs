@@ -0,0 +1,44 @@
+use chrono::{Duration, Utc};
+use market::order::{OrderExpiry, OrderRecord, OrderTtl};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[test]
+fn order_ttl_defaults_to_cancel() {
+    let ttl: OrderTtl = serde_json::from_str(r#"{"seconds": 600}"#).unwrap();
+    assert_eq!(ttl.on_expiry, OrderExpiry::Cancel);
+    assert_eq!(ttl.duration(), Duration::minutes(10));
+}
+
+#[sqlx::test]
+async fn expired_limit_orders(pool: PgPool) {
+    for (limit_price, filled_quantity) in [(Some(100), 0), (None, 0), (Some(100), 4)] {
+        sqlx::query(
+            r#"
+            INSERT INTO orders (order_id, client_order_id, strategy_id, broker, broker_order_id, ticker, side, quantity, limit_price, filled_quantity, status, created_at, modified_at)
+            VALUES ($1, $1::text, $2, 'alpaca', $1::text, 'AAPL', 'buy', 10, $3, $4, 'new', NOW() - INTERVAL '1 hour', NOW())
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(Uuid::new_v4())
+        .bind(limit_price.map(Decimal::from))
+        .bind(Decimal::from(filled_quantity))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let orders = OrderRecord::fetch_open(&pool).await.unwrap();
+    let now = Utc::now();
+    // Market orders and orders with executions don't expire
+    let expired = orders
+        .iter()
+        .filter(|order| order.is_expired(Duration::minutes(30), now))
+        .count();
+    assert_eq!(expired, 1);
+    assert!(orders
+        .iter()
+        .all(|order| !order.is_expired(Duration::hours(2), now)));
+}
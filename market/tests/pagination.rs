@@ -0,0 +1,109 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+    Router,
+};
+use market::{api::pagination::Page, app_config::AppConfig, order::Fill};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+async fn get(app: &Router, api_key: &str, uri: &str) -> (StatusCode, Vec<u8>) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::GET)
+                .uri(uri)
+                .header(header::AUTHORIZATION, api_key)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, body.to_vec())
+}
+
+#[sqlx::test]
+async fn position_history_pages_follow_the_cursor(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let strategy_id = config.strategies[0].id;
+    let api_key = config.api_key.clone();
+
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+        VALUES ('00000000-0000-0000-0000-000000000001', '00000000-0000-0000-0000-000000000001', $1, 'alpaca', 'AAPL', 'buy', 3, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    // Two fills of the same time, so the second page starts between them
+    for (fill_id, filled_at) in [
+        (
+            "00000000-0000-0000-0000-00000000000a",
+            "2023-08-01 10:00:00+00",
+        ),
+        (
+            "00000000-0000-0000-0000-00000000000b",
+            "2023-08-02 10:00:00+00",
+        ),
+        (
+            "00000000-0000-0000-0000-00000000000c",
+            "2023-08-02 10:00:00+00",
+        ),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO fills (fill_id, order_id, strategy_id, ticker, side, quantity, price, fee, filled_at)
+            VALUES ($1::uuid, '00000000-0000-0000-0000-000000000001', $2, 'AAPL', 'buy', 1, $3, 0, $4::timestamptz)
+            "#,
+        )
+        .bind(fill_id)
+        .bind(strategy_id)
+        .bind(Decimal::from(100))
+        .bind(filled_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let app = make_test_app_with_config(pool, config).await;
+
+    let mut fill_ids = Vec::new();
+    let mut uri = "/positions/history?limit=2&ticker=AAPL".to_owned();
+    loop {
+        let (status, body) = get(&app, &api_key, &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let page: Page<Fill> = serde_json::from_slice(&body).unwrap();
+        fill_ids.extend(page.items.iter().map(|fill| fill.fill_id.to_string()));
+        match page.next_cursor {
+            Some(cursor) => uri = format!("/positions/history?limit=2&ticker=AAPL&cursor={cursor}"),
+            None => break,
+        }
+    }
+    assert_eq!(
+        fill_ids,
+        vec![
+            "00000000-0000-0000-0000-00000000000c",
+            "00000000-0000-0000-0000-00000000000b",
+            "00000000-0000-0000-0000-00000000000a",
+        ]
+    );
+
+    let (status, body) = get(&app, &api_key, "/positions/history?sort=price").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["message"],
+        "Can't sort by price, expected one of filled_at"
+    );
+}
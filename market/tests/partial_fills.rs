@@ -0,0 +1,59 @@
+use chrono::{Duration, Utc};
+use market::order::{OrderRecord, PartialFills};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[test]
+fn partial_fill_policies() {
+    let policy: PartialFills =
+        serde_json::from_str(r#"{"policy": "cancel_remainder", "stale_after": 300}"#).unwrap();
+    assert_eq!(policy, PartialFills::CancelRemainder { stale_after: 300 });
+    assert_eq!(policy.stale_after(), Some(Duration::minutes(5)));
+    assert_eq!(PartialFills::default().stale_after(), None);
+}
+
+#[sqlx::test]
+async fn stale_partial_fill(pool: PgPool) {
+    let order_id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, broker_order_id, ticker, side, quantity, filled_quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', $1::text, 'AAPL', 'buy', 10, 0, 'new', NOW() - INTERVAL '1 hour', NOW())
+        "#,
+    )
+    .bind(order_id)
+    .bind(Uuid::new_v4())
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    OrderRecord::update_fill(
+        &pool,
+        order_id,
+        Decimal::from(4),
+        Some(Decimal::ONE_HUNDRED),
+        "partially_filled",
+    )
+    .await
+    .unwrap();
+    let order = OrderRecord::fetch(&pool, order_id).await.unwrap().unwrap();
+    let filled_at = order.last_filled_at.unwrap();
+    assert_eq!(order.remaining_quantity(), Decimal::from(6));
+    assert!(!order.is_stale(Duration::minutes(5), Utc::now()));
+    assert!(order.is_stale(Duration::minutes(5), filled_at + Duration::minutes(5)));
+
+    // Syncs without a further execution keep the time of the last one
+    OrderRecord::update_fill(
+        &pool,
+        order_id,
+        Decimal::from(4),
+        Some(Decimal::ONE_HUNDRED),
+        "partially_filled",
+    )
+    .await
+    .unwrap();
+    let order = OrderRecord::fetch(&pool, order_id).await.unwrap().unwrap();
+    assert_eq!(order.last_filled_at, Some(filled_at));
+}
@@ -0,0 +1,138 @@
+use axum::{
+    body::Body,
+    http::{method::Method, Request, StatusCode},
+};
+use chrono::Utc;
+use market::{
+    api::{
+        alert::SignalType,
+        payload::{AlertPayload, PayloadVersion},
+    },
+    app_config::AppConfig,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn v2_alert() -> serde_json::Value {
+    let time = Utc::now();
+    json!({
+        "version": "v2",
+        "strategy_id": AppConfig::build_for_test().unwrap().strategies[0].id,
+        "time": time,
+        "ticker": "AAPL",
+        "exchange": "NASDAQ",
+        "timeframe": "1h",
+        "action": "open_short",
+        "stop_loss": "105",
+        "bar": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+        "order": {"limit_price": "99.5", "time_in_force": "gtc"},
+        "comment": "breakdown",
+    })
+}
+
+fn tradingview_alert() -> serde_json::Value {
+    let time = Utc::now();
+    json!({
+        "strategy_id": AppConfig::build_for_test().unwrap().strategies[0].id,
+        "alert_id": "long-entry",
+        "ticker": "AAPL",
+        "exchange": "NASDAQ",
+        "interval": "5",
+        "time": time,
+        "bar_time": time,
+        "open": "100",
+        "high": "101",
+        "low": "99",
+        "close": "100.5",
+        "volume": "1000",
+        "action": "buy",
+        "market_position": "long",
+        "contracts": "3",
+        "stop_loss": "97",
+    })
+}
+
+#[test]
+fn payload_versions_convert_to_alerts() {
+    let config = AppConfig::build_for_test().unwrap();
+    let alert = AlertPayload::from_value(v2_alert(), None)
+        .unwrap()
+        .into_alert_data(&config)
+        .unwrap();
+    assert!(
+        matches!(alert.signal_type, SignalType::OpenShort(ref stop) if stop.0 == Decimal::from(105))
+    );
+    assert_eq!(alert.limit_price, Some(Decimal::new(995, 1)));
+
+    let alert = AlertPayload::from_value(tradingview_alert(), Some(PayloadVersion::TradingView))
+        .unwrap()
+        .into_alert_data(&config)
+        .unwrap();
+    assert!(
+        matches!(alert.signal_type, SignalType::OpenLong(ref stop) if stop.0 == Decimal::from(97))
+    );
+    assert_eq!(alert.alert_id.as_deref(), Some("long-entry"));
+    assert_eq!(alert.quantity, Some(Decimal::from(3)));
+    assert_eq!(alert.timeframe, "5");
+
+    // Exits of TradingView strategies aren't signals
+    let mut exit = tradingview_alert();
+    exit["action"] = json!("sell");
+    exit["market_position"] = json!("flat");
+    let payload = AlertPayload::from_value(exit, Some(PayloadVersion::TradingView)).unwrap();
+    assert!(payload.into_alert_data(&config).is_err());
+
+    let mut unknown = v2_alert();
+    unknown["version"] = json!("v9");
+    assert!(AlertPayload::from_value(unknown, None).is_err());
+}
+
+#[sqlx::test]
+async fn versioned_webhook_routes(pool: PgPool) {
+    let app = make_test_app_with_config(pool, AppConfig::build_for_test().unwrap()).await;
+    let post = |uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // Converted alerts are validated like v1 ones
+    let mut alert = v2_alert();
+    alert["stop_loss"] = json!("-1");
+    let response = app
+        .clone()
+        .oneshot(post("/webhook/alert/v2", alert))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The path version wins over the body
+    let response = app
+        .clone()
+        .oneshot(post("/webhook/alert/tradingview", v2_alert()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(post("/webhook/alert/v3", v2_alert()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,122 @@
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use market::{
+    order::Fill,
+    pnl::{closed_trades, compute_pnl, TradeStatistics},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+fn fill(ticker: &str, side: &str, quantity: i64, price: i64, fee: i64, filled_at: &str) -> Fill {
+    Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: Uuid::new_v4(),
+        strategy_id: Uuid::nil(),
+        ticker: ticker.to_string(),
+        side: side.to_string(),
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        fee: Decimal::from(fee),
+        filled_at: DateTime::<Utc>::from_str(filled_at).unwrap(),
+    }
+}
+
+#[test]
+fn pnl_fifo_daily_breakdown() {
+    let fills = vec![
+        fill("AAPL", "buy", 10, 100, 1, "2023-08-01T14:00:00Z"),
+        fill("AAPL", "buy", 10, 110, 1, "2023-08-01T15:00:00Z"),
+        // closes the first lot and half of the second one
+        fill("AAPL", "sell", 15, 120, 1, "2023-08-02T14:00:00Z"),
+        fill("TSLA", "sell", 5, 200, 0, "2023-08-02T15:00:00Z"),
+    ];
+    let prices = HashMap::from([
+        ("AAPL".to_string(), Decimal::from(130)),
+        ("TSLA".to_string(), Decimal::from(190)),
+    ]);
+
+    let pnl = compute_pnl(Uuid::nil(), &fills, &prices);
+
+    assert_eq!(pnl.daily.len(), 2);
+    assert_eq!(pnl.daily[0].realized, Decimal::ZERO);
+    assert_eq!(pnl.daily[0].fees, Decimal::from(2));
+    // 10 * (120 - 100) + 5 * (120 - 110)
+    assert_eq!(pnl.daily[1].realized, Decimal::from(250));
+    assert_eq!(pnl.realized, Decimal::from(247));
+
+    assert_eq!(pnl.open_positions.len(), 2);
+    assert_eq!(pnl.open_positions[0].ticker, "AAPL");
+    assert_eq!(pnl.open_positions[0].quantity, Decimal::from(5));
+    assert_eq!(pnl.open_positions[0].avg_entry_price, Decimal::from(110));
+    assert_eq!(pnl.open_positions[0].unrealized, Decimal::from(100));
+    assert_eq!(pnl.open_positions[1].ticker, "TSLA");
+    assert_eq!(pnl.open_positions[1].quantity, Decimal::from(-5));
+    assert_eq!(pnl.open_positions[1].unrealized, Decimal::from(50));
+
+    assert_eq!(pnl.unrealized, Decimal::from(150));
+    assert_eq!(pnl.total, Decimal::from(397));
+}
+
+#[test]
+fn pnl_position_flip() {
+    let fills = vec![
+        fill("MSFT", "buy", 10, 300, 0, "2023-08-01T14:00:00Z"),
+        // closes the long and opens a short of 5
+        fill("MSFT", "sell", 15, 290, 0, "2023-08-01T15:00:00Z"),
+    ];
+
+    let pnl = compute_pnl(Uuid::nil(), &fills, &HashMap::new());
+
+    assert_eq!(pnl.realized, Decimal::from(-100));
+    assert_eq!(pnl.open_positions.len(), 1);
+    assert_eq!(pnl.open_positions[0].quantity, Decimal::from(-5));
+    assert_eq!(pnl.open_positions[0].avg_entry_price, Decimal::from(290));
+    assert_eq!(pnl.open_positions[0].current_price, None);
+    assert_eq!(pnl.unrealized, Decimal::ZERO);
+}
+
+#[test]
+fn closed_trade_statistics() {
+    let partial = fill("AAPL", "sell", 5, 110, 1, "2023-08-01T15:00:00Z");
+    let mut rest = fill("AAPL", "sell", 5, 110, 1, "2023-08-01T15:00:01Z");
+    rest.order_id = partial.order_id;
+    let fills = vec![
+        fill("AAPL", "buy", 10, 100, 0, "2023-08-01T14:00:00Z"),
+        // both fills of one order closing the position
+        partial,
+        rest,
+        fill("AAPL", "buy", 10, 100, 0, "2023-08-02T14:00:00Z"),
+        fill("AAPL", "sell", 10, 95, 0, "2023-08-02T15:00:00Z"),
+        fill("TSLA", "sell", 10, 200, 0, "2023-08-03T14:00:00Z"),
+        fill("TSLA", "buy", 10, 180, 0, "2023-08-03T15:00:00Z"),
+    ];
+
+    let trades: Vec<Decimal> = closed_trades(&fills)
+        .iter()
+        .map(|trade| trade.pnl)
+        .collect();
+    assert_eq!(
+        trades,
+        vec![Decimal::from(98), Decimal::from(-50), Decimal::from(200)]
+    );
+
+    let statistics = compute_pnl(Uuid::nil(), &fills, &HashMap::new()).statistics;
+    assert_eq!(statistics.trades, 3);
+    assert_eq!(
+        statistics.win_rate,
+        Some(Decimal::from(2) / Decimal::from(3))
+    );
+    // Average win of 149 over the loss of 50
+    assert_eq!(statistics.payoff_ratio, Some(Decimal::new(298, 2)));
+
+    assert_eq!(
+        TradeStatistics::from_trades(&trades[..1]),
+        TradeStatistics {
+            trades: 1,
+            win_rate: Some(Decimal::ONE),
+            payoff_ratio: None,
+        }
+    );
+}
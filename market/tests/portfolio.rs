@@ -0,0 +1,85 @@
+use market::{
+    api::objects::Broker,
+    portfolio::{equity_curve, EquityBucket, EquityCurveQuery},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn equity_curve_drawdown(pool: PgPool) {
+    for (equity, taken_at) in [
+        (1000, "2023-08-01 10:00:00+00"),
+        (1200, "2023-08-01 11:00:00+00"),
+        (900, "2023-08-01 12:00:00+00"),
+        (1080, "2023-08-01 13:00:00+00"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+            VALUES (gen_random_uuid(), 'alpaca', $1, 0, '[]', $2::timestamptz)
+            "#,
+        )
+        .bind(Decimal::from(equity))
+        .bind(taken_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let curve = equity_curve(
+        &pool,
+        &EquityCurveQuery {
+            broker: Broker::Alpaca,
+            from: None,
+            to: None,
+            bucket: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(curve.points.len(), 4);
+    assert_eq!(curve.points[1].drawdown, Decimal::ZERO);
+    assert_eq!(curve.max_drawdown, Decimal::new(25, 2));
+    assert_eq!(curve.current_drawdown, Decimal::new(1, 1));
+}
+
+#[sqlx::test]
+async fn equity_curve_keeps_last_snapshot_per_bucket(pool: PgPool) {
+    for (equity, taken_at) in [
+        (1000, "2023-08-01 10:00:00+00"),
+        (1200, "2023-08-01 18:00:00+00"),
+        (900, "2023-08-02 09:00:00+00"),
+        (1080, "2023-08-02 15:00:00+00"),
+        (1100, "2023-08-03 12:00:00+00"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+            VALUES (gen_random_uuid(), 'alpaca', $1, 0, '[]', $2::timestamptz)
+            "#,
+        )
+        .bind(Decimal::from(equity))
+        .bind(taken_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let curve = equity_curve(
+        &pool,
+        &EquityCurveQuery {
+            broker: Broker::Alpaca,
+            from: None,
+            to: "2023-08-02T23:00:00Z".parse().ok(),
+            bucket: Some(EquityBucket::Day),
+        },
+    )
+    .await
+    .unwrap();
+
+    let equities: Vec<Decimal> = curve.points.iter().map(|point| point.equity).collect();
+    assert_eq!(equities, vec![Decimal::from(1200), Decimal::from(1080)]);
+    assert_eq!(curve.max_drawdown, Decimal::new(1, 1));
+}
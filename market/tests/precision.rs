@@ -0,0 +1,68 @@
+use std::{collections::HashMap, str::FromStr};
+
+use market::{
+    order::{client_order_id, NewOrder, OrderSide},
+    precision::{Increments, Precision, PrecisionConfig},
+    sizing::ExecutionPath,
+    strategy::CurrencyType,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn new_order(ticker: &str, side: OrderSide, quantity: &str, limit_price: &str) -> NewOrder {
+    let strategy_id = Uuid::new_v4();
+    let id = Uuid::new_v4();
+    NewOrder {
+        id,
+        strategy_id,
+        client_order_id: client_order_id(strategy_id, id),
+        ticker: ticker.to_string(),
+        side,
+        quantity: dec(quantity),
+        notional: None,
+        stop_loss_price: None,
+        limit_price: Some(dec(limit_price)),
+        time_in_force: Default::default(),
+        extended_hours: false,
+        execution_path: ExecutionPath::Stable,
+        request_id: None,
+        environment: Default::default(),
+        account: None,
+        legs: Vec::new(),
+    }
+}
+
+#[test]
+fn orders_are_rounded_to_the_increments_of_their_asset() {
+    let precision = Precision::new(PrecisionConfig {
+        symbols: HashMap::from([(
+            "BTC/USD".to_owned(),
+            Increments {
+                tick_size: dec("0.5"),
+                lot_size: dec("0.0001"),
+            },
+        )]),
+    });
+
+    // Limit prices of buys round down, stops to the nearest penny
+    let mut order = new_order("AAPL", OrderSide::Buy, "10", "187.256");
+    order.stop_loss_price = Some(dec("180.125"));
+    precision.round_order(&mut order, CurrencyType::Stock);
+    assert_eq!(order.limit_price, Some(dec("187.25")));
+    assert_eq!(order.stop_loss_price, Some(dec("180.13")));
+
+    // Sub-dollar stocks trade in hundredths of a cent, sells round up
+    let mut order = new_order("SNDL", OrderSide::Sell, "100", "0.123456");
+    precision.round_order(&mut order, CurrencyType::Stock);
+    assert_eq!(order.limit_price, Some(dec("0.1235")));
+
+    let mut order = new_order("BTC/USD", OrderSide::Sell, "0.123456", "43210.2");
+    precision.round_order(&mut order, CurrencyType::Crypto);
+    assert_eq!(order.limit_price, Some(dec("43210.5")));
+    assert_eq!(order.quantity, dec("0.1234"));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "test-broker")]
+
+use market::{app_config::AppConfig, order::OrderSide, preview::OrderPreviewRequest};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{make_test_state, mock_broker};
+
+#[sqlx::test]
+async fn previewed_orders_are_not_placed(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let broker = mock_broker();
+
+    let request = OrderPreviewRequest {
+        strategy_id: strategy.id,
+        ticker: "AAPL".to_string(),
+        side: OrderSide::Buy,
+        quantity: Some(Decimal::from(10)),
+        notional: None,
+        limit_price: Some(Decimal::new(187256, 3)),
+        stop_loss_price: None,
+        time_in_force: None,
+        extended_hours: None,
+    };
+    let preview = app
+        .core
+        .preview_order(broker.clone(), &strategy, &request, None)
+        .await
+        .unwrap();
+
+    assert_eq!(preview.order.limit_price, Some(Decimal::new(18725, 2)));
+    assert_eq!(preview.estimated_cost, Decimal::new(18725, 1));
+    assert_eq!(preview.buying_power, Some(Decimal::from(200000)));
+    assert_eq!(preview.buying_power_after, Some(Decimal::new(1981275, 1)));
+    assert!(preview.risk_violations.is_empty());
+    assert_eq!(preview.broker_request["symbol"], "AAPL");
+    assert_eq!(preview.broker_request["limit_price"], "187.25");
+
+    // Users don't see the broker account
+    let mut redacted = preview.clone();
+    redacted.redact_account();
+    let redacted = serde_json::to_value(&redacted).unwrap();
+    assert!(redacted.get("buying_power").is_none());
+    assert!(redacted.get("buying_power_used").is_none());
+
+    assert!(broker.calls_of("create_order").is_empty());
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 0);
+}
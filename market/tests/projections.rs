@@ -0,0 +1,97 @@
+use chrono::Utc;
+use market::{
+    order::Fill,
+    projections::{self, PositionDrift},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const ORDER_ID: Uuid = Uuid::from_u128(1);
+const STRATEGY_ID: Uuid = Uuid::from_u128(2);
+
+async fn fill(pool: &PgPool, ticker: &str, side: &str, quantity: i64) {
+    let fill = Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: ORDER_ID,
+        strategy_id: STRATEGY_ID,
+        ticker: ticker.to_string(),
+        side: side.to_string(),
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(100),
+        fee: Decimal::ZERO,
+        filled_at: Utc::now(),
+    };
+    Fill::insert(pool, &fill).await.unwrap();
+}
+
+#[sqlx::test]
+async fn positions_are_rebuilt_from_fills(pool: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, filled_quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', 'buy', 10, 10, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(ORDER_ID)
+    .bind(STRATEGY_ID)
+    .execute(&pool)
+    .await
+    .unwrap();
+    fill(&pool, "AAPL", "buy", 10).await;
+    fill(&pool, "AAPL", "sell", 4).await;
+    fill(&pool, "MSFT", "sell", 3).await;
+
+    // Projections follow every fill recorded
+    assert_eq!(
+        Fill::position(&pool, STRATEGY_ID, "AAPL").await.unwrap(),
+        Decimal::from(6)
+    );
+    assert_eq!(
+        Fill::position(&pool, STRATEGY_ID, "MSFT").await.unwrap(),
+        Decimal::from(-3)
+    );
+    assert!(projections::rebuild(&pool).await.unwrap().is_empty());
+
+    // Projections not matching the fills, e.g. left behind by a crash, are corrected
+    sqlx::query("UPDATE position_projections SET quantity = 8 WHERE ticker = 'AAPL'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM position_projections WHERE ticker = 'MSFT'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let drifts = projections::rebuild(&pool).await.unwrap();
+    assert_eq!(
+        drifts,
+        vec![
+            PositionDrift {
+                strategy_id: STRATEGY_ID,
+                ticker: "AAPL".to_string(),
+                projected: Some(Decimal::from(8)),
+                rebuilt: Some(Decimal::from(6)),
+            },
+            PositionDrift {
+                strategy_id: STRATEGY_ID,
+                ticker: "MSFT".to_string(),
+                projected: None,
+                rebuilt: Some(Decimal::from(-3)),
+            },
+        ]
+    );
+    let positions: Vec<(String, Decimal, i64)> = projections::fetch_all(&pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|projection| (projection.ticker, projection.quantity, projection.fills))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            ("AAPL".to_string(), Decimal::from(6), 2),
+            ("MSFT".to_string(), Decimal::from(-3), 1),
+        ]
+    );
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use market::{
+    app_config::AppConfig,
+    rate_limit::{GroupUsage, Quota, RateLimit, RateLimiter},
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[sqlx::test]
+async fn rate_limit_per_api_key(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.rate_limits.insert(
+        "analytics".to_owned(),
+        RateLimit {
+            capacity: 2,
+            refill_per_second: 0.1,
+        },
+    );
+    let api_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    let request = || {
+        Request::builder()
+            .method(Method::GET)
+            .uri("/health")
+            .header(header::AUTHORIZATION, &api_key)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    for _ in 0..2 {
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+    assert_eq!(response.headers()["x-ratelimit-limit"], "2");
+    assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+}
+
+#[sqlx::test]
+async fn usage_of_the_api_key(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.rate_limits.insert(
+        "analytics".to_owned(),
+        RateLimit {
+            capacity: 3,
+            refill_per_second: 0.0,
+        },
+    );
+    let api_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    let request = |uri| {
+        Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header(header::AUTHORIZATION, &api_key)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(request("/health")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-ratelimit-limit"], "3");
+    assert_eq!(response.headers()["x-ratelimit-remaining"], "2");
+
+    let response = app.oneshot(request("/account/usage")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let usage: Vec<GroupUsage> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        usage,
+        vec![GroupUsage {
+            group: "analytics".to_owned(),
+            requests: 2,
+            throttled: 0,
+            quota: Some(Quota {
+                limit: 3,
+                remaining: 1,
+                reset: u64::MAX,
+            }),
+        }]
+    );
+}
+
+#[test]
+fn idle_buckets_are_dropped_once_full() {
+    let limiter = RateLimiter::new(HashMap::from([(
+        "analytics".to_owned(),
+        RateLimit {
+            capacity: 1,
+            refill_per_second: 1000.0,
+        },
+    )]));
+
+    limiter.check("first", "analytics").unwrap();
+    assert_eq!(limiter.usage("first")[0].requests, 1);
+
+    thread::sleep(Duration::from_millis(10));
+    limiter.check("second", "analytics").unwrap();
+    assert_eq!(limiter.usage("first")[0].requests, 0);
+    assert_eq!(limiter.usage("second")[0].requests, 1);
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use market::{
+    app_config::AppConfig,
+    order::OrderSide,
+    rebalance::{plan, RebalanceRequest, PLANNED_STATUS},
+    strategy::CurrencyType,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+fn decimals(values: &[(&str, Decimal)]) -> HashMap<String, Decimal> {
+    values
+        .iter()
+        .map(|(ticker, value)| (ticker.to_string(), *value))
+        .collect()
+}
+
+#[test]
+fn rebalance_orders_move_positions_to_weights() {
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Stock;
+
+    let weights = decimals(&[
+        ("AAPL", Decimal::new(5, 1)),
+        ("MSFT", Decimal::new(2, 1)),
+        ("TSLA", Decimal::ZERO),
+        ("NVDA", Decimal::new(1, 1)),
+    ]);
+    let positions = decimals(&[
+        ("AAPL", Decimal::from(10)),
+        ("MSFT", Decimal::from(30)),
+        ("TSLA", Decimal::from(5)),
+        ("NVDA", Decimal::from(2)),
+    ]);
+    let prices = decimals(&[
+        ("AAPL", Decimal::from(150)),
+        ("MSFT", Decimal::from(300)),
+        ("TSLA", Decimal::from(200)),
+        ("NVDA", Decimal::from(450)),
+    ]);
+
+    let orders = plan(
+        &strategy,
+        Decimal::from(10_000),
+        &weights,
+        &positions,
+        &prices,
+    );
+    let summary: Vec<_> = orders
+        .iter()
+        .map(|order| (order.ticker.as_str(), order.side, order.quantity))
+        .collect();
+    // Sells first, NVDA is already at its 2 share target
+    assert_eq!(
+        summary,
+        vec![
+            ("MSFT", OrderSide::Sell, Decimal::from(24)),
+            ("TSLA", OrderSide::Sell, Decimal::from(5)),
+            ("AAPL", OrderSide::Buy, Decimal::from(23)),
+        ]
+    );
+    assert_eq!(orders[2].current_quantity, Decimal::from(10));
+    assert_eq!(orders[2].target_quantity, Decimal::from(33));
+    assert!(orders.iter().all(|order| order.status == PLANNED_STATUS));
+
+    strategy.currency_type = CurrencyType::Crypto;
+    let orders = plan(
+        &strategy,
+        Decimal::from(1_000),
+        &decimals(&[
+            ("BTC/USD", Decimal::new(5, 1)),
+            ("ETH/USD", Decimal::new(1, 4)),
+        ]),
+        &HashMap::new(),
+        &decimals(&[
+            ("BTC/USD", Decimal::from(30_000)),
+            ("ETH/USD", Decimal::from(2_000)),
+        ]),
+    );
+    // 0.10 of ETH is below the crypto minimum notional
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].quantity, Decimal::new(1666666, 8));
+}
+
+#[test]
+fn rebalance_weights_validation() {
+    let request = |weights: &[(&str, Decimal)]| RebalanceRequest {
+        strategy_id: Uuid::nil(),
+        weights: decimals(weights),
+        dry_run: true,
+    };
+
+    assert!(
+        request(&[("AAPL", Decimal::new(6, 1)), ("MSFT", Decimal::new(4, 1))])
+            .validate()
+            .is_ok()
+    );
+    assert!(request(&[]).validate().is_err());
+    assert!(request(&[("AAPL", Decimal::new(-1, 1))])
+        .validate()
+        .is_err());
+    assert!(
+        request(&[("AAPL", Decimal::new(6, 1)), ("MSFT", Decimal::new(5, 1))])
+            .validate()
+            .is_err()
+    );
+}
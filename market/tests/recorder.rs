@@ -0,0 +1,78 @@
+use chrono::Utc;
+use market::{
+    api::objects::Broker,
+    clients::BrokerClient,
+    recorder::{
+        sanitize, BrokerRecorder, Interaction, PlaybackClient, Recording, RecordingClient,
+        RecordingSink,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+fn interaction(
+    operation: &str,
+    response: Option<serde_json::Value>,
+    error: Option<&str>,
+) -> Interaction {
+    Interaction {
+        interaction_id: Uuid::new_v4(),
+        broker: "alpaca".to_string(),
+        operation: operation.to_string(),
+        request: Json(serde_json::Value::Null),
+        response: response.map(Json),
+        error: error.map(str::to_string),
+        recorded_at: Utc::now(),
+    }
+}
+
+#[sqlx::test]
+async fn record_played_back_interactions(pool: PgPool) {
+    let playback = PlaybackClient::new(vec![
+        interaction("get_positions", Some(json!([])), None),
+        interaction("delete_order", None, Some("order is not cancelable")),
+    ]);
+    let recorder = BrokerRecorder::new(
+        pool.clone(),
+        Recording {
+            sink: RecordingSink::Database,
+            window: 60,
+        },
+    );
+    let client = RecordingClient::new(playback, Broker::Alpaca, Some(&recorder));
+
+    assert!(client.get_positions().await.unwrap().is_empty());
+    assert!(client.delete_order(Uuid::new_v4()).await.is_err());
+    // Nothing recorded is left to play back
+    assert!(client.get_positions().await.is_err());
+
+    let recorded: Vec<(String, Option<String>)> = sqlx::query_as(
+        "SELECT operation, error FROM broker_interactions ORDER BY recorded_at, interaction_id",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(recorded.len(), 3);
+    assert_eq!(recorded[0], ("get_positions".to_string(), None));
+    assert_eq!(recorded[1].0, "delete_order");
+    assert!(recorded[1].1.as_deref().unwrap().contains("not cancelable"));
+}
+
+#[test]
+fn sanitize_sensitive_fields() {
+    let mut value = json!({
+        "account_number": "PA123",
+        "orders": [{"symbol": "AAPL", "api_key": "abc"}],
+    });
+    sanitize(&mut value);
+
+    assert_eq!(
+        value,
+        json!({
+            "account_number": "[redacted]",
+            "orders": [{"symbol": "AAPL", "api_key": "[redacted]"}],
+        })
+    );
+}
@@ -0,0 +1,102 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use market::{
+    api::alert::WebhookAlertData,
+    app_config::AppConfig,
+    replay::{self, ReplayQuery, ReplaySummary},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::time::{sleep, Duration, Instant};
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_state;
+
+fn alert(strategy_id: Uuid) -> WebhookAlertData {
+    // Older than the webhook accepts, replays skip the age checks
+    let time = Utc::now() - ChronoDuration::days(1);
+    serde_json::from_value(json!({
+        "strategy_id": strategy_id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_long", "trail_stop_price": "95"},
+        "trail_stop_price": null,
+        "bar_data": {
+            "time": time,
+            "open": "100",
+            "high": "100",
+            "low": "100",
+            "close": "100",
+            "volume": "1000",
+        },
+        "time": time,
+    }))
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn stored_alerts_are_replayed_as_dry_runs(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    app.alert_writer
+        .write(&alert(strategy.id), "AAPL")
+        .await
+        .unwrap();
+    app.alert_writer
+        .write(&alert(Uuid::new_v4()), "AAPL")
+        .await
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM alerts")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        if count == 2 || Instant::now() > deadline {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+
+    let mut replayed = Vec::new();
+    let summary = replay::replay(&app, &ReplayQuery::default(), |alert| {
+        replayed.push(alert.clone())
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        summary,
+        ReplaySummary {
+            alerts: 2,
+            orders: 1,
+            rejected: 0,
+            ignored: 0,
+            errors: 1,
+        }
+    );
+    let order = replayed[0]
+        .simulation
+        .as_ref()
+        .and_then(|simulation| simulation.order.as_ref())
+        .unwrap();
+    assert_eq!(order.strategy_id, strategy.id);
+    assert!(replayed[1].error.is_some());
+
+    // Replays place no orders
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 0);
+
+    let query = ReplayQuery {
+        strategy_id: Some(strategy.id),
+        ..Default::default()
+    };
+    let summary = replay::replay(&app, &query, |_| {}).await.unwrap();
+    assert_eq!(summary.alerts, 1);
+}
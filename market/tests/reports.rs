@@ -0,0 +1,158 @@
+use std::str::FromStr;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use market::{
+    app_config::AppConfig,
+    fx::FxRates,
+    order::Fill,
+    reports::{self, next_run, DailyReport},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+const ORDER_ID: Uuid = Uuid::from_u128(1);
+
+fn fill(strategy_id: Uuid, side: &str, price: i64, filled_at: &str) -> Fill {
+    Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: ORDER_ID,
+        strategy_id,
+        ticker: "AAPL".to_string(),
+        side: side.to_string(),
+        quantity: Decimal::from(10),
+        price: Decimal::from(price),
+        fee: Decimal::ONE,
+        filled_at: DateTime::<Utc>::from_str(filled_at).unwrap(),
+    }
+}
+
+#[sqlx::test]
+async fn daily_report_of_trades_and_violations(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let strategy_id = config.strategies[0].id;
+    let app = make_test_app_with_config(pool.clone(), config.clone()).await;
+
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', 'buy', 10, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(ORDER_ID)
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for fill in [
+        fill(strategy_id, "buy", 100, "2023-08-01T14:00:00Z"),
+        fill(strategy_id, "sell", 120, "2023-08-02T14:00:00Z"),
+    ] {
+        Fill::insert(&pool, &fill).await.unwrap();
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO risk_violations (violation_id, strategy_id, ticker, rule, details, created_at)
+        VALUES (gen_random_uuid(), $1, 'AAPL', 'max_open_positions', '', '2023-08-02T15:00:00Z')
+        "#,
+    )
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2023, 8, 2).unwrap();
+    let fx = FxRates::new(config.fx.clone());
+    let report = reports::compile(&pool, &config, &fx, date).await.unwrap();
+    reports::save(&pool, &report).await.unwrap();
+
+    let get = |uri: &str| {
+        Request::builder()
+            .uri(uri)
+            .header(header::AUTHORIZATION, &api_key)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(get("/reports/daily/2023-08-02"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let report: DailyReport = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(report.strategies.len(), 1);
+    assert_eq!(report.strategies[0].fills, 1);
+    assert_eq!(report.strategies[0].trades, 1);
+    // 10 * (120 - 100) less the fee of the closing fill
+    assert_eq!(report.realized, Decimal::from(199));
+    assert_eq!(report.risk_violations.len(), 1);
+    assert!(report.errors.is_empty());
+
+    let response = app.oneshot(get("/reports/daily/2023-08-03")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn daily_report_converts_pnl_to_the_base_currency(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.brokers.alpaca.currency = "EUR".to_owned();
+    config.fx.rates.insert("EUR".to_owned(), Decimal::new(5, 1));
+    let strategy_id = config.strategies[0].id;
+
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', 'buy', 10, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(ORDER_ID)
+    .bind(strategy_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    for fill in [
+        fill(strategy_id, "buy", 100, "2023-08-01T14:00:00Z"),
+        fill(strategy_id, "sell", 120, "2023-08-02T14:00:00Z"),
+    ] {
+        Fill::insert(&pool, &fill).await.unwrap();
+    }
+
+    let date = NaiveDate::from_ymd_opt(2023, 8, 2).unwrap();
+    let report = reports::compile(&pool, &config, &FxRates::new(config.fx.clone()), date)
+        .await
+        .unwrap();
+
+    assert_eq!(report.strategies[0].realized, Decimal::from(199));
+    assert_eq!(report.strategies[0].currency, "EUR");
+    // 199 EUR at 0.5 EUR per USD
+    assert_eq!(report.realized, Decimal::from(398));
+    assert_eq!(report.currency, "USD");
+}
+
+#[test]
+fn reports_run_at_the_next_configured_time() {
+    let time = NaiveTime::from_hms_opt(20, 30, 0).unwrap();
+    let now = DateTime::<Utc>::from_str("2023-08-02T14:00:00Z").unwrap();
+    assert_eq!(
+        next_run(now, time),
+        DateTime::<Utc>::from_str("2023-08-02T20:30:00Z").unwrap()
+    );
+
+    let now = DateTime::<Utc>::from_str("2023-08-02T20:30:00Z").unwrap();
+    assert_eq!(
+        next_run(now, time),
+        DateTime::<Utc>::from_str("2023-08-03T20:30:00Z").unwrap()
+    );
+}
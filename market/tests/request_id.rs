@@ -0,0 +1,37 @@
+use axum::{
+    body::Body,
+    http::{header, method::Method, Request, StatusCode},
+};
+use market::{app_config::AppConfig, middleware::REQUEST_ID_HEADER};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+#[sqlx::test]
+async fn request_id_is_propagated(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let api_key = config.api_key.clone();
+    let app = make_test_app_with_config(pool, config).await;
+
+    let request = |request_id: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::GET)
+            .uri("/health")
+            .header(header::AUTHORIZATION, &api_key);
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    let response = app.clone().oneshot(request(Some("abc-123"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc-123");
+
+    // Requests without an id get a generated one
+    let response = app.oneshot(request(None)).await.unwrap();
+    assert!(!response.headers()[REQUEST_ID_HEADER].is_empty());
+}
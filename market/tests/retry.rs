@@ -0,0 +1,18 @@
+use market::retry::Backoff;
+use rand_core::OsRng;
+use tokio::time::Duration;
+
+#[test]
+fn backoff_doubles_with_jitter_up_to_a_cap() {
+    let backoff = Backoff::new(Duration::from_secs(1), 10);
+    for _ in 0..100 {
+        let first = backoff.delay(1, &mut OsRng);
+        assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+
+        let third = backoff.delay(3, &mut OsRng);
+        assert!(third >= Duration::from_secs(2) && third <= Duration::from_secs(4));
+
+        let last = backoff.delay(10, &mut OsRng);
+        assert!(last >= Duration::from_secs(30) && last <= Duration::from_secs(60));
+    }
+}
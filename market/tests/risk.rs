@@ -0,0 +1,509 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use chrono::Utc;
+use market::{
+    api::objects::Broker,
+    app_config::{
+        AppConfig, DailyLoss, EscalationLevel, ExposureLimits, Notifications, RiskBudget,
+    },
+    feature_flags::{FeatureFlags, UpdateFeatureFlag, HALT_TRADING},
+    notifications::{Channel, Notifier},
+    order::{client_order_id, Fill, NewOrder, OrderRecord, OrderSide},
+    pnl::{DailyPnl, StrategyPnl, TradeStatistics},
+    risk::{self, DuplicatePositions, RiskMonitor, RiskViolation},
+    sizing::ExecutionPath,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test]
+async fn drawdown_escalation(pool: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+        VALUES (gen_random_uuid(), 'alpaca', 1000, 0, '[]', NOW())
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let feature_flags = Arc::new(FeatureFlags::new(pool.clone()));
+    let monitor = RiskMonitor::new(
+        pool,
+        Notifier::new(Notifications::default()),
+        Arc::clone(&feature_flags),
+        vec![
+            EscalationLevel {
+                drawdown: Decimal::new(20, 2),
+                channels: vec![Channel::Sms, Channel::Pushover],
+                kill_switch: true,
+            },
+            EscalationLevel {
+                drawdown: Decimal::new(5, 2),
+                channels: vec![Channel::Slack],
+                kill_switch: false,
+            },
+        ],
+    );
+
+    assert_eq!(monitor.level_for(Decimal::new(1, 2)), None);
+    assert_eq!(monitor.level_for(Decimal::new(10, 2)), Some(0));
+    assert_eq!(monitor.level_for(Decimal::new(25, 2)), Some(1));
+
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(900))
+        .await
+        .unwrap();
+    assert!(!feature_flags.is_halted().await);
+
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(750))
+        .await
+        .unwrap();
+    assert!(feature_flags.is_halted().await);
+}
+
+#[sqlx::test]
+async fn drawdown_escalation_per_broker(pool: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+        VALUES
+            (gen_random_uuid(), 'alpaca', 1000, 0, '[]', NOW()),
+            (gen_random_uuid(), 'oanda', 2000, 0, '[]', NOW())
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let feature_flags = Arc::new(FeatureFlags::new(pool.clone()));
+    let monitor = RiskMonitor::new(
+        pool,
+        Notifier::new(Notifications::default()),
+        Arc::clone(&feature_flags),
+        vec![EscalationLevel {
+            drawdown: Decimal::new(5, 2),
+            channels: Vec::new(),
+            kill_switch: true,
+        }],
+    );
+    let resume = UpdateFeatureFlag {
+        enabled: false,
+        description: None,
+        rollout_percentage: None,
+    };
+
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(900))
+        .await
+        .unwrap();
+    assert!(feature_flags.is_halted().await);
+    feature_flags.set(HALT_TRADING, &resume).await.unwrap();
+
+    // A recovered account doesn't re-arm the level of the other one
+    monitor
+        .evaluate(&Broker::Oanda, Decimal::from(2000))
+        .await
+        .unwrap();
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(900))
+        .await
+        .unwrap();
+    assert!(!feature_flags.is_halted().await);
+
+    // The level reached by one account doesn't hide it for the other one
+    monitor
+        .evaluate(&Broker::Oanda, Decimal::from(1800))
+        .await
+        .unwrap();
+    assert!(feature_flags.is_halted().await);
+}
+
+#[sqlx::test]
+async fn daily_loss_limits(pool: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+        VALUES (gen_random_uuid(), 'alpaca', 1000, 0, '[]', NOW())
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let feature_flags = Arc::new(FeatureFlags::new(pool.clone()));
+    let monitor = RiskMonitor::new(
+        pool.clone(),
+        Notifier::new(Notifications::default()),
+        Arc::clone(&feature_flags),
+        Vec::new(),
+    )
+    .with_daily_loss(DailyLoss {
+        max_loss: Some(Decimal::from(50)),
+        channels: Vec::new(),
+    });
+
+    // Strategy loss of the day, realized and unrealized
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies.remove(0);
+    strategy.max_daily_loss = Some(Decimal::from(100));
+    let today = Utc::now().date_naive();
+    let mut pnl = StrategyPnl {
+        strategy_id: strategy.id,
+        realized: Decimal::from(-60),
+        unrealized: Decimal::from(-30),
+        total: Decimal::from(-90),
+        open_positions: Vec::new(),
+        daily: vec![DailyPnl {
+            date: today,
+            realized: Decimal::from(-55),
+            fees: Decimal::from(5),
+        }],
+        statistics: TradeStatistics::default(),
+    };
+    monitor
+        .evaluate_strategy(&strategy, &pnl, today)
+        .await
+        .unwrap();
+    assert!(!risk::is_strategy_halted(&pool, strategy.id, today)
+        .await
+        .unwrap());
+
+    pnl.unrealized = Decimal::from(-40);
+    monitor
+        .evaluate_strategy(&strategy, &pnl, today)
+        .await
+        .unwrap();
+    assert!(risk::is_strategy_halted(&pool, strategy.id, today)
+        .await
+        .unwrap());
+    assert!(
+        !risk::is_strategy_halted(&pool, strategy.id, today.succ_opt().unwrap())
+            .await
+            .unwrap()
+    );
+
+    // Account loss since the first snapshot of the day
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(960))
+        .await
+        .unwrap();
+    assert!(!feature_flags.is_halted().await);
+    monitor
+        .evaluate(&Broker::Alpaca, Decimal::from(950))
+        .await
+        .unwrap();
+    assert!(feature_flags.is_halted().await);
+}
+
+#[sqlx::test]
+async fn symbol_exposure_limits(pool: PgPool) {
+    let monitor = RiskMonitor::new(
+        pool.clone(),
+        Notifier::new(Notifications::default()),
+        Arc::new(FeatureFlags::new(pool.clone())),
+        Vec::new(),
+    )
+    .with_exposure_limits(ExposureLimits {
+        max_notional_per_symbol: Some(Decimal::from(1000)),
+        symbols: HashMap::from([("AAPL".to_string(), Decimal::from(5000))]),
+    });
+
+    // Another strategy holds 2 TSLA and has an open order for 3 more
+    let other_strategy_id = Uuid::new_v4();
+    let filled = new_order(other_strategy_id, "TSLA", OrderSide::Buy, 2);
+    OrderRecord::insert(&pool, &filled, &Broker::Alpaca)
+        .await
+        .unwrap();
+    OrderRecord::update_status(&pool, filled.id, Some("broker-1"), "filled")
+        .await
+        .unwrap();
+    Fill::insert(
+        &pool,
+        &Fill {
+            fill_id: Uuid::new_v4(),
+            order_id: filled.id,
+            strategy_id: filled.strategy_id,
+            ticker: filled.ticker.clone(),
+            side: OrderSide::Buy.as_ref().to_string(),
+            quantity: filled.quantity,
+            price: Decimal::from(90),
+            fee: Decimal::ZERO,
+            filled_at: Utc::now(),
+        },
+    )
+    .await
+    .unwrap();
+    OrderRecord::insert(
+        &pool,
+        &new_order(other_strategy_id, "TSLA", OrderSide::Buy, 3),
+        &Broker::Alpaca,
+    )
+    .await
+    .unwrap();
+
+    let strategy_id = Uuid::new_v4();
+    let price = Decimal::from(100);
+
+    for (ticker, side, quantity, rejected) in [
+        ("TSLA", OrderSide::Buy, 5, false),
+        ("TSLA", OrderSide::Buy, 6, true),
+        // Sells offset the longs of the other strategy
+        ("TSLA", OrderSide::Sell, 6, false),
+        ("TSLA", OrderSide::Sell, 20, true),
+        ("AAPL", OrderSide::Buy, 40, false),
+    ] {
+        let violation = monitor
+            .check_exposure(&new_order(strategy_id, ticker, side, quantity), price)
+            .await
+            .unwrap();
+        assert_eq!(
+            violation.is_some(),
+            rejected,
+            "{ticker} {side:?} {quantity}"
+        );
+    }
+
+    let violations = RiskViolation::fetch_recent(&pool, 10).await.unwrap();
+    assert_eq!(violations.len(), 2);
+    assert!(violations
+        .iter()
+        .all(|violation| violation.strategy_id == strategy_id
+            && violation.ticker == "TSLA"
+            && violation.rule == risk::SYMBOL_EXPOSURE));
+}
+
+#[sqlx::test]
+async fn open_position_limits(pool: PgPool) {
+    let monitor = RiskMonitor::new(
+        pool.clone(),
+        Notifier::new(Notifications::default()),
+        Arc::new(FeatureFlags::new(pool.clone())),
+        Vec::new(),
+    );
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies.remove(0);
+    strategy.max_open_positions = Some(2);
+
+    let aapl = new_order(strategy.id, "AAPL", OrderSide::Buy, 1);
+    for order in [&aapl, &new_order(strategy.id, "TSLA", OrderSide::Sell, 1)] {
+        OrderRecord::insert(&pool, order, &Broker::Alpaca)
+            .await
+            .unwrap();
+    }
+
+    let msft = new_order(strategy.id, "MSFT", OrderSide::Buy, 1);
+    let violation = monitor
+        .check_open_positions(&strategy, &msft)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(violation.rule, risk::MAX_OPEN_POSITIONS);
+    assert_eq!(violation.ticker, "MSFT");
+    // Orders in held symbols don't open a position
+    assert!(monitor
+        .check_open_positions(
+            &strategy,
+            &new_order(strategy.id, "AAPL", OrderSide::Sell, 1)
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    OrderRecord::update_status(&pool, aapl.id, None, "canceled")
+        .await
+        .unwrap();
+    assert!(monitor
+        .check_open_positions(&strategy, &msft)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+fn new_order(strategy_id: Uuid, ticker: &str, side: OrderSide, quantity: i64) -> NewOrder {
+    let id = Uuid::new_v4();
+    NewOrder {
+        id,
+        strategy_id,
+        client_order_id: client_order_id(strategy_id, id),
+        ticker: ticker.to_string(),
+        side,
+        quantity: Decimal::from(quantity),
+        notional: None,
+        stop_loss_price: None,
+        limit_price: None,
+        time_in_force: Default::default(),
+        extended_hours: false,
+        execution_path: ExecutionPath::Stable,
+        request_id: None,
+        environment: Default::default(),
+        account: None,
+        legs: Vec::new(),
+    }
+}
+
+#[sqlx::test]
+async fn duplicate_position_rule(pool: PgPool) {
+    let monitor = RiskMonitor::new(
+        pool.clone(),
+        Notifier::new(Notifications::default()),
+        Arc::new(FeatureFlags::new(pool.clone())),
+        Vec::new(),
+    );
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies.remove(0);
+    OrderRecord::insert(
+        &pool,
+        &new_order(strategy.id, "AAPL", OrderSide::Buy, 10),
+        &Broker::Alpaca,
+    )
+    .await
+    .unwrap();
+
+    let long = new_order(strategy.id, "AAPL", OrderSide::Buy, 5);
+    assert!(monitor
+        .check_duplicate_position(&strategy, &long)
+        .await
+        .unwrap()
+        .is_none());
+
+    strategy.duplicate_positions = DuplicatePositions::Reject;
+    let violation = monitor
+        .check_duplicate_position(&strategy, &long)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(violation.rule, risk::DUPLICATE_POSITION);
+    // Entries against the position reverse it
+    assert!(monitor
+        .check_duplicate_position(
+            &strategy,
+            &new_order(strategy.id, "AAPL", OrderSide::Sell, 5)
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    strategy.duplicate_positions = DuplicatePositions::ScaleIn {
+        max_quantity: Decimal::from(15),
+    };
+    assert!(monitor
+        .check_duplicate_position(&strategy, &long)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(monitor
+        .check_duplicate_position(
+            &strategy,
+            &new_order(strategy.id, "AAPL", OrderSide::Buy, 6)
+        )
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[sqlx::test]
+async fn portfolio_risk_budgets(pool: PgPool) {
+    let mut strategies = AppConfig::build_for_test().unwrap().strategies;
+    strategies.truncate(1);
+    for _ in 0..2 {
+        let mut strategy = strategies[0].clone();
+        strategy.id = Uuid::new_v4();
+        strategy.portfolio = Some("growth".to_string());
+        strategies.push(strategy);
+    }
+    let (outside, member, other_member) = (&strategies[0], &strategies[1], &strategies[2]);
+    let monitor = RiskMonitor::new(
+        pool.clone(),
+        Notifier::new(Notifications::default()),
+        Arc::new(FeatureFlags::new(pool.clone())),
+        Vec::new(),
+    )
+    .with_portfolios(
+        BTreeMap::from([(
+            "growth".to_string(),
+            RiskBudget {
+                max_exposure: Some(Decimal::from(2000)),
+                max_daily_loss: Some(Decimal::from(100)),
+            },
+        )]),
+        &strategies,
+    );
+    assert!(monitor.has_daily_loss_limit(member));
+    assert!(!monitor.has_daily_loss_limit(outside));
+
+    // The other strategy of the portfolio holds 10 TSLA, valued at its latest fill
+    let filled = new_order(other_member.id, "TSLA", OrderSide::Buy, 10);
+    OrderRecord::insert(&pool, &filled, &Broker::Alpaca)
+        .await
+        .unwrap();
+    OrderRecord::update_status(&pool, filled.id, Some("broker-1"), "filled")
+        .await
+        .unwrap();
+    Fill::insert(
+        &pool,
+        &Fill {
+            fill_id: Uuid::new_v4(),
+            order_id: filled.id,
+            strategy_id: filled.strategy_id,
+            ticker: filled.ticker.clone(),
+            side: OrderSide::Buy.as_ref().to_string(),
+            quantity: filled.quantity,
+            price: Decimal::from(90),
+            fee: Decimal::ZERO,
+            filled_at: Utc::now(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let price = Decimal::from(100);
+    for (strategy, ticker, side, quantity, rejected) in [
+        (member, "AAPL", OrderSide::Buy, 11, false),
+        (member, "AAPL", OrderSide::Buy, 12, true),
+        // Orders reducing the exposure of the portfolio are accepted
+        (member, "TSLA", OrderSide::Sell, 5, false),
+        (outside, "AAPL", OrderSide::Buy, 50, false),
+    ] {
+        let new_order = new_order(strategy.id, ticker, side, quantity);
+        let violation = monitor
+            .check_portfolio_exposure(strategy, &new_order, price)
+            .await
+            .unwrap();
+        assert_eq!(
+            violation.is_some(),
+            rejected,
+            "{ticker} {side:?} {quantity}"
+        );
+    }
+    let violations = RiskViolation::fetch_recent(&pool, 10).await.unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].rule, risk::PORTFOLIO_EXPOSURE);
+    assert_eq!(violations[0].strategy_id, member.id);
+
+    // The combined loss of the day disables every strategy of the portfolio
+    let today = Utc::now().date_naive();
+    monitor
+        .evaluate_portfolio("growth", Decimal::from(99), today)
+        .await
+        .unwrap();
+    assert!(!risk::is_strategy_halted(&pool, member.id, today)
+        .await
+        .unwrap());
+    monitor
+        .evaluate_portfolio("growth", Decimal::from(100), today)
+        .await
+        .unwrap();
+    for (strategy, halted) in [(member, true), (other_member, true), (outside, false)] {
+        assert_eq!(
+            risk::is_strategy_halted(&pool, strategy.id, today)
+                .await
+                .unwrap(),
+            halted
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use market::{
+    api::alert::{SignalType, TrailStopPrice},
+    app_config::{AppConfig, BrokerAccount},
+    build_clients,
+    feature_flags::{UpdateFeatureFlag, HALT_TRADING},
+    recorder::PlaybackClient,
+    routing::{self, Routing, RoutingPolicy},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+mod setup;
+use setup::{make_test_state, trade_signal};
+
+fn routing(policy: &str) -> Routing {
+    serde_json::from_value(serde_json::json!({
+        "policy": policy,
+        "venues": [
+            { "broker": "alpaca", "account": "main", "fee_bps": "1.5" },
+            { "broker": "alpaca", "account": "backup", "fee_bps": "0.5" },
+            { "broker": "alpaca", "fee_bps": "0.5" },
+        ]
+    }))
+    .unwrap()
+}
+
+fn accounts(routing: &Routing) -> Vec<Option<&str>> {
+    routing
+        .candidates()
+        .iter()
+        .map(|venue| venue.account.as_deref())
+        .collect()
+}
+
+#[test]
+fn venue_candidates() {
+    let preferred = routing("preferred");
+    assert_eq!(
+        accounts(&preferred),
+        vec![Some("main"), Some("backup"), None]
+    );
+    assert_eq!(accounts(&routing("failover")), accounts(&preferred));
+
+    // The cheapest venues first, ties in the configured order
+    let fees = routing("fees");
+    assert_eq!(fees.policy, RoutingPolicy::Fees);
+    assert_eq!(accounts(&fees), vec![Some("backup"), None, Some("main")]);
+}
+
+#[sqlx::test]
+async fn reducing_signals_go_to_the_venue_holding_the_position(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let alpaca = config.brokers.alpaca.clone();
+    config
+        .brokers
+        .accounts
+        .insert("backup".to_owned(), BrokerAccount::Alpaca(alpaca));
+    let strategy = config.strategies[0].clone();
+    let clients = build_clients(&config).unwrap();
+
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, broker_account, ticker, side, quantity, filled_quantity, status, execution_path, last_filled_at, created_at, modified_at)
+        VALUES (gen_random_uuid(), 'short', $1, 'alpaca', 'backup', 'AAPL', 'sell', 10, 10, 'filled', 'stable', NOW(), NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'AAPL', -10, 1, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Covering the short goes to the account it was sold on
+    let mut signal = trade_signal(&strategy);
+    assert!(routing::holding_client(&clients, &pool, &mut signal)
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(signal.strategy.account.as_deref(), Some("backup"));
+
+    let mut signal = trade_signal(&strategy);
+    signal.signal_type = SignalType::StopLossUpdate(TrailStopPrice(Decimal::from(105)));
+    assert!(routing::holding_client(&clients, &pool, &mut signal)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Entries are routed as usual
+    let mut signal = trade_signal(&strategy);
+    signal.signal_type = SignalType::OpenShort(TrailStopPrice(Decimal::from(105)));
+    assert!(routing::holding_client(&clients, &pool, &mut signal)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(signal.strategy.account, strategy.account);
+}
+
+#[sqlx::test]
+async fn flattening_closes_positions_on_the_venue_holding_them(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let alpaca = config.brokers.alpaca.clone();
+    config
+        .brokers
+        .accounts
+        .insert("backup".to_owned(), BrokerAccount::Alpaca(alpaca));
+    config.strategies[0].dry_run = true;
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    // Bought on the backup account while the account of the strategy was failing
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, broker_account, ticker, side, quantity, filled_quantity, status, execution_path, last_filled_at, created_at, modified_at)
+        VALUES (gen_random_uuid(), 'long', $1, 'alpaca', 'backup', 'AAPL', 'buy', 10, 10, 'filled', 'stable', NOW(), NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'AAPL', 10, 1, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Playback without interactions fails any call to the venue of the strategy
+    let report = app
+        .core
+        .flatten_strategy(PlaybackClient::new(vec![]), &strategy)
+        .await
+        .unwrap();
+    assert_eq!(report.errors, Vec::<String>::new());
+    let (side, account): (String, Option<String>) =
+        sqlx::query_as("SELECT side, broker_account FROM orders WHERE order_id = $1")
+            .bind(report.orders[0])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(side, "sell");
+    assert_eq!(account.as_deref(), Some("backup"));
+}
+
+#[sqlx::test]
+async fn flattening_goes_on_while_trading_is_halted(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = true;
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'AAPL', 10, 1, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    app.feature_flags
+        .set(
+            HALT_TRADING,
+            &UpdateFeatureFlag {
+                enabled: true,
+                description: None,
+                rollout_percentage: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let report = app
+        .core
+        .flatten_strategy(PlaybackClient::new(vec![]), &strategy)
+        .await
+        .unwrap();
+    assert_eq!(report.errors, Vec::<String>::new());
+    let side: String = sqlx::query_scalar("SELECT side FROM orders WHERE order_id = $1")
+        .bind(report.orders[0])
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(side, "sell");
+}
@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+
+use market::scheduler::{Priority, RequestScheduler};
+
+#[tokio::test]
+async fn orders_are_sent_before_queued_queries() {
+    // A token every 50ms once the burst of 1200 requests is used up
+    let scheduler = Arc::new(RequestScheduler::new(1200));
+    for _ in 0..1200 {
+        scheduler.acquire(Priority::Query).await;
+    }
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let query = tokio::spawn({
+        let (scheduler, sent) = (Arc::clone(&scheduler), Arc::clone(&sent));
+        async move {
+            scheduler.acquire(Priority::Query).await;
+            sent.lock().unwrap().push(Priority::Query);
+        }
+    });
+    tokio::task::yield_now().await;
+    let order = tokio::spawn({
+        let (scheduler, sent) = (Arc::clone(&scheduler), Arc::clone(&sent));
+        async move {
+            scheduler.acquire(Priority::Order).await;
+            sent.lock().unwrap().push(Priority::Order);
+        }
+    });
+    query.await.unwrap();
+    order.await.unwrap();
+
+    assert_eq!(
+        *sent.lock().unwrap(),
+        vec![Priority::Order, Priority::Query]
+    );
+}
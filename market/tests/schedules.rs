@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use market::{
+    admin,
+    app_config::AppConfig,
+    audit::AuditEntry,
+    core::SIMULATED_STATUS,
+    leases::{Leases, StrategyLease},
+    recorder::PlaybackClient,
+    schedules::{self, Cron, Schedule, ScheduleZone, SCHEDULER_ACTOR},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+
+mod setup;
+use setup::make_test_state;
+
+fn at(time: &str) -> DateTime<Utc> {
+    DateTime::<Utc>::from_str(time).unwrap()
+}
+
+#[test]
+fn cron_times() {
+    let next = |expression: &str, zone, after: &str| {
+        Cron::from_str(expression)
+            .unwrap()
+            .next_after(at(after), zone)
+    };
+
+    // Before the close in New York, daylight saving time starts on Sunday, March 10th 2024
+    let flatten = "55 15 * * mon-fri";
+    assert_eq!(
+        next(flatten, ScheduleZone::Eastern, "2024-03-08T20:00:00Z"),
+        Some(at("2024-03-08T20:55:00Z"))
+    );
+    assert_eq!(
+        next(flatten, ScheduleZone::Eastern, "2024-03-08T21:00:00Z"),
+        Some(at("2024-03-11T19:55:00Z"))
+    );
+
+    // Times due are strictly after
+    assert_eq!(
+        next("0 22 * * 5", ScheduleZone::Utc, "2024-03-08T22:00:00Z"),
+        Some(at("2024-03-15T22:00:00Z"))
+    );
+    assert_eq!(
+        next(
+            "*/15 9-10 1,15 * *",
+            ScheduleZone::Utc,
+            "2024-03-01T10:50:00Z"
+        ),
+        Some(at("2024-03-15T09:00:00Z"))
+    );
+    // Days matching either the day of month or the day of week are due
+    assert_eq!(
+        next("0 0 13 * fri", ScheduleZone::Utc, "2024-03-01T00:00:00Z"),
+        Some(at("2024-03-08T00:00:00Z"))
+    );
+    assert_eq!(
+        next("0 12 * * 7", ScheduleZone::Utc, "2024-03-08T00:00:00Z"),
+        Some(at("2024-03-10T12:00:00Z"))
+    );
+    assert_eq!(
+        next("0 0 30 feb *", ScheduleZone::Utc, "2024-03-08T00:00:00Z"),
+        None
+    );
+
+    for expression in [
+        "60 * * * *",
+        "* * *",
+        "5-1 * * * *",
+        "*/0 * * * *",
+        "0 0 * * fry",
+    ] {
+        assert!(Cron::from_str(expression).is_err(), "{expression}");
+    }
+}
+
+#[sqlx::test]
+async fn scheduled_actions_run_once_and_are_audited(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = true;
+    let app = make_test_state(pool.clone(), config).await;
+
+    sqlx::query(
+        r#"
+        INSERT INTO position_projections (strategy_id, ticker, quantity, fills, last_filled_at, updated_at)
+        VALUES ($1, 'AAPL', 10, 1, NOW(), NOW()), ($1, 'MSFT', 0, 2, NOW(), NOW())
+        "#,
+    )
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Playback without interactions fails any broker call, dry runs don't make any
+    let report = app
+        .core
+        .flatten_strategy(PlaybackClient::new(vec![]), &strategy)
+        .await
+        .unwrap();
+    assert_eq!(report.errors, Vec::<String>::new());
+    let (ticker, side, quantity, status): (String, String, Decimal, String) =
+        sqlx::query_as("SELECT ticker, side, quantity, status FROM orders WHERE order_id = $1")
+            .bind(report.orders[0])
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        (ticker.as_str(), side.as_str(), quantity, status.as_str()),
+        ("AAPL", "sell", Decimal::from(10), SIMULATED_STATUS)
+    );
+    assert_eq!(report.orders.len(), 1);
+
+    let schedule: Schedule = serde_json::from_value(json!({
+        "cron": "0 16 * * fri",
+        "zone": "eastern",
+        "action": "disable",
+        "reason": "Weekend"
+    }))
+    .unwrap();
+    let due_at = at("2024-03-08T21:00:00Z");
+    for _ in 0..2 {
+        schedules::run_action(&app, &strategy, &schedule, due_at)
+            .await
+            .unwrap();
+    }
+
+    let status = admin::strategy_status(&pool, &strategy, Utc::now().date_naive())
+        .await
+        .unwrap();
+    assert!(!status.enabled);
+    assert_eq!(status.disabled_reason.as_deref(), Some("Weekend"));
+
+    let entries = AuditEntry::fetch_for(&pool, &format!("strategy:{}", strategy.id))
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].actor, SCHEDULER_ACTOR);
+    assert_eq!(entries[0].action, "schedule.disable");
+    assert_eq!(entries[0].details.0["cron"], "0 16 * * fri");
+}
+
+#[sqlx::test]
+async fn failed_scheduled_actions_are_claimed_again(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = true;
+    config.leases.wait = 0;
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let schedule: Schedule = serde_json::from_value(json!({
+        "cron": "55 15 * * mon-fri",
+        "zone": "eastern",
+        "action": "flatten"
+    }))
+    .unwrap();
+    let due_at = at("2024-03-08T20:55:00Z");
+    let status = || {
+        sqlx::query_as::<_, (String, Option<String>)>("SELECT status, error FROM schedule_runs")
+            .fetch_one(&pool)
+    };
+
+    // Held as if a signal of the strategy was being processed
+    let lease = StrategyLease::acquire(&pool, strategy.id, &Leases::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(schedules::run_action(&app, &strategy, &schedule, due_at)
+        .await
+        .is_err());
+    assert_eq!(
+        status().await.unwrap(),
+        (
+            "failed".to_owned(),
+            Some(format!(
+                "Strategy {} is busy processing another signal",
+                strategy.name
+            ))
+        )
+    );
+
+    lease.release(&pool).await.unwrap();
+    for _ in 0..2 {
+        schedules::run_action(&app, &strategy, &schedule, due_at)
+            .await
+            .unwrap();
+    }
+    assert_eq!(status().await.unwrap(), ("succeeded".to_owned(), None));
+    let entries = AuditEntry::fetch_for(&pool, &format!("strategy:{}", strategy.id))
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 2);
+}
@@ -1,15 +1,145 @@
+// NOTE: shared by every test binary, each one uses only some of the helpers
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
 use axum::Router;
-use market::{app_config::AppConfig, build_clients, build_routes, App};
+use chrono::Utc;
+#[cfg(feature = "test-broker")]
+use market::mock_broker::MockBrokerClient;
+use market::{
+    alert_writer::AlertWriter,
+    allowlist::IpAllowlist,
+    api::{
+        alert::{BarData, SignalType, TrailStopPrice},
+        price::Price,
+    },
+    app_config::AppConfig,
+    build_clients, build_routes,
+    core::Core,
+    feature_flags::FeatureFlags,
+    fx::FxRates,
+    health::Shutdown,
+    hooks::TradeHooks,
+    jwt::JwtVerifier,
+    notifications::Notifier,
+    rate_limit::RateLimiter,
+    recorder::BrokerRecorder,
+    risk::RiskMonitor,
+    strategy::Strategy,
+    supervisor::TaskSupervisor,
+    throttle::OrderThrottle,
+    trade_executor::TradeExecutor,
+    trade_signal::TradeSignal,
+    App,
+};
+use rust_decimal::Decimal;
 use sqlx::PgPool;
 
+/// Signal of `strategy` opening a long position in AAPL on a 1h bar closed at 100, with its stop
+/// loss at 95.
+pub fn trade_signal(strategy: &Strategy) -> TradeSignal {
+    let price = Price::new(Decimal::from(100));
+    TradeSignal {
+        strategy: strategy.clone(),
+        ticker: "AAPL".to_string(),
+        timeframe: "1h".to_string(),
+        exchange: "NASDAQ".to_string(),
+        signal_type: SignalType::OpenLong(TrailStopPrice(Decimal::from(95))),
+        trail_stop_price: None,
+        bar_data: BarData {
+            time: Utc::now(),
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::from(1000),
+        },
+        time: Utc::now(),
+        request_id: None,
+        quantity: None,
+        notional: None,
+        time_in_force: None,
+        limit_price: None,
+        extended_hours: None,
+        legs: Vec::new(),
+    }
+}
+
+/// Mock broker answering account lookups with the Alpaca account fixture.
+#[cfg(feature = "test-broker")]
+pub fn mock_broker() -> MockBrokerClient {
+    let broker = MockBrokerClient::new();
+    broker.set_default(
+        "get_account",
+        serde_json::from_str(include_str!("../fixtures/alpaca_account.json")).unwrap(),
+    );
+    broker
+}
+
 pub async fn make_test_app(pool: PgPool) -> Router {
     let config = AppConfig::build_for_test().unwrap();
 
+    make_test_app_with_config(pool, config).await
+}
+
+pub async fn make_test_app_with_config(pool: PgPool, config: AppConfig) -> Router {
+    build_routes(make_test_state(pool, config).await)
+}
+
+pub async fn make_test_state(pool: PgPool, config: AppConfig) -> Arc<App> {
     let clients = build_clients(&config).unwrap();
 
-    build_routes(std::sync::Arc::new(App {
+    let feature_flags = Arc::new(FeatureFlags::new(pool.clone()));
+    let risk_monitor = Arc::new(
+        RiskMonitor::new(
+            pool.clone(),
+            Notifier::new(config.notifications.clone()),
+            Arc::clone(&feature_flags),
+            config.notifications.escalation.clone(),
+        )
+        .with_daily_loss(config.daily_loss.clone())
+        .with_exposure_limits(config.exposure_limits.clone()),
+    );
+
+    let tasks = TaskSupervisor::new();
+    let alert_writer = AlertWriter::spawn(pool.clone(), &config.webhook.persistence, &tasks);
+    let hooks = Arc::new(TradeHooks::default());
+    Arc::new(App {
+        core: Arc::new(
+            Core::new(
+                pool.clone(),
+                Arc::clone(&clients),
+                Arc::clone(&feature_flags),
+                Arc::clone(&risk_monitor),
+                OrderThrottle::new(
+                    config.throttle.orders_per_symbol_per_minute,
+                    config.throttle.orders_per_strategy_per_hour,
+                ),
+                config.paper.clone(),
+                config
+                    .recording
+                    .clone()
+                    .map(|recording| BrokerRecorder::new(pool.clone(), recording)),
+            )
+            .with_strategies(&config.strategies)
+            .with_leases(config.leases.clone())
+            .with_hooks(Arc::clone(&hooks)),
+        ),
         db: pool,
         clients,
+        feature_flags,
+        risk_monitor,
+        rate_limiter: Arc::new(RateLimiter::new(config.rate_limits.clone())),
+        webhook_allowlist: Arc::new(IpAllowlist::new(config.webhook.clone())),
+        jwt: config.jwt.clone().map(JwtVerifier::new),
+        alert_writer,
+        executor: Arc::new(
+            TradeExecutor::new(config.executor.clone(), tasks.clone()).with_hooks(hooks),
+        ),
+        fx: Arc::new(FxRates::new(config.fx.clone())),
+        tasks,
+        shutdown: Shutdown::default(),
         config,
-    }))
+    })
 }
@@ -0,0 +1,110 @@
+#![cfg(feature = "test-broker")]
+
+use market::{
+    api::alert::{SignalType, TrailStopPrice},
+    app_config::AppConfig,
+    feature_flags::{UpdateFeatureFlag, ENABLE_SHORTING},
+    order::Fill,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod setup;
+use setup::{make_test_state, mock_broker, trade_signal};
+
+/// Record a filled buy of `quantity` AAPL for the strategy.
+async fn hold_long(pool: &PgPool, strategy_id: Uuid, quantity: Decimal) {
+    let order_id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, filled_quantity, status, created_at, modified_at)
+        VALUES ($1, $1::text, $2, 'alpaca', 'AAPL', 'buy', $3, $3, 'filled', NOW(), NOW())
+        "#,
+    )
+    .bind(order_id)
+    .bind(strategy_id)
+    .bind(quantity)
+    .execute(pool)
+    .await
+    .unwrap();
+    Fill::insert(
+        pool,
+        &Fill {
+            fill_id: Uuid::new_v4(),
+            order_id,
+            strategy_id,
+            ticker: "AAPL".to_owned(),
+            side: "buy".to_owned(),
+            quantity,
+            price: Decimal::from(100),
+            fee: Decimal::ZERO,
+            filled_at: chrono::Utc::now(),
+        },
+    )
+    .await
+    .unwrap();
+}
+
+#[sqlx::test]
+async fn selling_a_held_long_skips_the_short_checks(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = false;
+    strategy.max_order_retries = 0;
+    let app = make_test_state(pool.clone(), config).await;
+
+    hold_long(&pool, strategy.id, Decimal::from(10)).await;
+
+    let mut signal = trade_signal(&strategy);
+    signal.signal_type = SignalType::OpenShort(TrailStopPrice(Decimal::from(105)));
+    signal.quantity = Some(Decimal::from(10));
+
+    // Assets aren't scripted, so any short check fails the signal
+    let broker = mock_broker();
+    let _ = app.core.process_trade_signal(broker.clone(), signal).await;
+
+    assert!(broker.calls_of("get_asset").is_empty());
+    let orders = broker.calls_of("create_order");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].request["side"], "sell");
+    assert_eq!(orders[0].request["qty"], "10");
+}
+
+#[sqlx::test]
+async fn disabled_shorting_only_closes_longs(pool: PgPool) {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut strategy = config.strategies[0].clone();
+    strategy.dry_run = false;
+    strategy.max_order_retries = 0;
+    let app = make_test_state(pool.clone(), config).await;
+    app.feature_flags
+        .set(
+            ENABLE_SHORTING,
+            &UpdateFeatureFlag {
+                enabled: false,
+                description: None,
+                rollout_percentage: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let mut signal = trade_signal(&strategy);
+    signal.signal_type = SignalType::OpenShort(TrailStopPrice(Decimal::from(105)));
+    signal.quantity = Some(Decimal::from(15));
+
+    let broker = mock_broker();
+    let _ = app
+        .core
+        .process_trade_signal(broker.clone(), signal.clone())
+        .await;
+    assert!(broker.calls_of("create_order").is_empty());
+
+    hold_long(&pool, strategy.id, Decimal::from(10)).await;
+    let _ = app.core.process_trade_signal(broker.clone(), signal).await;
+    let orders = broker.calls_of("create_order");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].request["qty"], "10");
+}
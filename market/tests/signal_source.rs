@@ -0,0 +1,281 @@
+use axum::{
+    body::Body,
+    http::{method::Method, HeaderMap, HeaderValue, Request, StatusCode},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use market::{
+    api::alert::SignalType,
+    app_config::AppConfig,
+    middleware::SIGNATURE_HEADER,
+    signal_source::{SignalSourceConfig, SourceError, SourceKind, TOKEN_HEADER},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn source(kind: SourceKind) -> SignalSourceConfig {
+    SignalSourceConfig {
+        name: "source".to_string(),
+        kind,
+        secret: "secret".to_string(),
+        allowed_senders: Vec::new(),
+        strategies: vec![AppConfig::build_for_test().unwrap().strategies[0].id],
+    }
+}
+
+fn headers(name: &'static str, value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(name, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+fn bar() -> serde_json::Value {
+    json!({
+        "time": Utc::now(),
+        "open": "100",
+        "high": "101",
+        "low": "99",
+        "close": "100",
+        "volume": "1000",
+    })
+}
+
+fn quantconnect_alert(direction: &str) -> serde_json::Value {
+    json!({
+        "strategy_id": AppConfig::build_for_test().unwrap().strategies[0].id,
+        "insight_id": "insight",
+        "symbol": "AAPL",
+        "market": "usa",
+        "resolution": "hour",
+        "time": Utc::now(),
+        "direction": direction,
+        "bar": bar(),
+        "stop_loss": "95",
+    })
+}
+
+#[test]
+fn quantconnect_insights() {
+    let config = AppConfig::build_for_test().unwrap();
+    let source = source(SourceKind::QuantConnect);
+    let body = quantconnect_alert("Up").to_string();
+
+    let alert = source
+        .adapter()
+        .receive(&headers(TOKEN_HEADER, "secret"), body.as_bytes(), &config)
+        .unwrap();
+    assert!(
+        matches!(alert.signal_type, SignalType::OpenLong(ref stop) if stop.0 == Decimal::from(95))
+    );
+    assert_eq!(alert.alert_id.as_deref(), Some("insight"));
+    assert_eq!(alert.timeframe, "hour");
+
+    let result =
+        source
+            .adapter()
+            .receive(&headers(TOKEN_HEADER, "wrong"), body.as_bytes(), &config);
+    assert!(matches!(result, Err(SourceError::Unauthorized(_))));
+
+    let body = quantconnect_alert("flat").to_string();
+    let result =
+        source
+            .adapter()
+            .receive(&headers(TOKEN_HEADER, "secret"), body.as_bytes(), &config);
+    assert!(matches!(result, Err(SourceError::InvalidAlert(..))));
+}
+
+#[test]
+fn scanner_alerts_are_signed() {
+    let config = AppConfig::build_for_test().unwrap();
+    let source = source(SourceKind::Scanner);
+    let body = json!({
+        "strategy_id": config.strategies[0].id,
+        "ticker": "AAPL",
+        "timeframe": "1h",
+        "exchange": "NASDAQ",
+        "signal_type": {"signal_type": "open_short", "trail_stop_price": "105"},
+        "bar_data": bar(),
+        "time": Utc::now(),
+    })
+    .to_string();
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(body.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    let alert = source
+        .adapter()
+        .receive(
+            &headers(SIGNATURE_HEADER, &signature),
+            body.as_bytes(),
+            &config,
+        )
+        .unwrap();
+    assert!(matches!(alert.signal_type, SignalType::OpenShort(_)));
+
+    let result =
+        source
+            .adapter()
+            .receive(&headers(TOKEN_HEADER, "secret"), body.as_bytes(), &config);
+    assert!(matches!(result, Err(SourceError::Unauthorized(_))));
+}
+
+#[test]
+fn email_bridge_alerts() {
+    let config = AppConfig::build_for_test().unwrap();
+    let mut source = source(SourceKind::EmailBridge);
+    source.allowed_senders = vec!["noreply@tradingview.com".to_string()];
+    let email = |from: &str| {
+        json!({
+            "from": from,
+            "subject": "Alert: AAPL",
+            "text": format!("Your alert fired\n\n{}\n", json!({
+                "version": "v2",
+                "strategy_id": config.strategies[0].id,
+                "time": Utc::now(),
+                "ticker": "AAPL",
+                "exchange": "NASDAQ",
+                "timeframe": "1h",
+                "action": "open_long",
+                "stop_loss": "95",
+                "bar": bar(),
+            })),
+        })
+        .to_string()
+    };
+
+    let body = email("TradingView <NoReply@TradingView.com>");
+    let alert = source
+        .adapter()
+        .receive(&headers(TOKEN_HEADER, "secret"), body.as_bytes(), &config)
+        .unwrap();
+    assert_eq!(alert.ticker, "AAPL");
+    assert!(matches!(alert.signal_type, SignalType::OpenLong(_)));
+
+    let body = email("someone@example.com");
+    let result =
+        source
+            .adapter()
+            .receive(&headers(TOKEN_HEADER, "secret"), body.as_bytes(), &config);
+    assert!(matches!(result, Err(SourceError::Unauthorized(_))));
+}
+
+#[sqlx::test]
+async fn source_routes(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.webhook.sources = vec![SignalSourceConfig {
+        name: "qc".to_string(),
+        ..source(SourceKind::QuantConnect)
+    }];
+    let app = make_test_app_with_config(pool, config).await;
+    let post = |uri: &str, token: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header(TOKEN_HEADER, token)
+            .body(Body::from(quantconnect_alert("flat").to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post("/webhook/source/unknown", "secret"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(post("/webhook/source/qc", "wrong"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(post("/webhook/source/qc", "secret"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn replayed_source_alerts_are_rejected(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.webhook.sources = vec![SignalSourceConfig {
+        name: "qc".to_string(),
+        ..source(SourceKind::QuantConnect)
+    }];
+    let app = make_test_app_with_config(pool, config).await;
+    let post = |alert: &serde_json::Value| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/webhook/source/qc")
+            .header("Content-Type", "application/json")
+            .header(TOKEN_HEADER, "secret")
+            .body(Body::from(alert.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post(&quantconnect_alert("up")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let mut alert = quantconnect_alert("up");
+    alert["timestamp"] = json!(Utc::now());
+    alert["nonce"] = json!("first");
+    let response = app.clone().oneshot(post(&alert)).await.unwrap();
+    assert!(response.status() != StatusCode::UNAUTHORIZED);
+
+    // The same alert posted again
+    let response = app.oneshot(post(&alert)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn sources_only_trade_their_strategies(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let mut other = config.strategies[0].clone();
+    other.id = Uuid::new_v4();
+    config.strategies.push(other.clone());
+    config.webhook.sources = vec![SignalSourceConfig {
+        name: "qc".to_string(),
+        ..source(SourceKind::QuantConnect)
+    }];
+    assert!(config.validate().is_ok());
+    let app = make_test_app_with_config(pool, config.clone()).await;
+
+    let mut alert = quantconnect_alert("up");
+    alert["strategy_id"] = json!(other.id);
+    alert["timestamp"] = json!(Utc::now());
+    alert["nonce"] = json!("nonce");
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/webhook/source/qc")
+                .header("Content-Type", "application/json")
+                .header(TOKEN_HEADER, "secret")
+                .body(Body::from(alert.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    config.webhook.sources[0].strategies = vec![Uuid::new_v4()];
+    assert!(config.validate().is_err());
+    config.webhook.sources[0].strategies.clear();
+    assert!(config.validate().is_err());
+}
@@ -0,0 +1,151 @@
+use axum::{
+    body::Body,
+    http::{method::Method, Request, StatusCode},
+};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use market::{app_config::AppConfig, middleware::SIGNATURE_HEADER};
+use pretty_assertions::assert_eq;
+use sha2::Sha256;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn signed_body(strategy_id: Uuid, timestamp: DateTime<Utc>, nonce: &str) -> String {
+    format!(
+        r#"{{"strategy_id": "{strategy_id}", "timestamp": "{}", "nonce": "{nonce}"}}"#,
+        timestamp.to_rfc3339()
+    )
+}
+
+fn webhook_request(body: &str, signature: Option<String>) -> Request<Body> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("/webhook")
+        .header("Content-Type", "application/json");
+    if let Some(signature) = signature {
+        request = request.header(SIGNATURE_HEADER, signature);
+    }
+    request.body(Body::from(body.to_owned())).unwrap()
+}
+
+#[sqlx::test]
+async fn webhook_signature(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].webhook_secret = Some("secret".to_owned());
+    let body = signed_body(config.strategies[0].id, Utc::now(), "nonce");
+    let app = make_test_app_with_config(pool, config).await;
+
+    let response = app
+        .clone()
+        .oneshot(webhook_request(&body, None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let tampered = body.replace('}', r#", "ticker": "AAPL"}"#);
+    let response = app
+        .clone()
+        .oneshot(webhook_request(&tampered, Some(sign("secret", &body))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Signed alerts pass through to the handler, which rejects the incomplete body
+    let response = app
+        .oneshot(webhook_request(&body, Some(sign("secret", &body))))
+        .await
+        .unwrap();
+    assert!(response.status() != StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn replayed_alerts_are_rejected(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].webhook_secret = Some("secret".to_owned());
+    let strategy_id = config.strategies[0].id;
+    let app = make_test_app_with_config(pool, config).await;
+
+    let body = signed_body(strategy_id, Utc::now(), "first");
+    let response = app
+        .clone()
+        .oneshot(webhook_request(&body, Some(sign("secret", &body))))
+        .await
+        .unwrap();
+    assert!(response.status() != StatusCode::UNAUTHORIZED);
+
+    // The same signed body posted again
+    let response = app
+        .clone()
+        .oneshot(webhook_request(&body, Some(sign("secret", &body))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let stale = signed_body(strategy_id, Utc::now() - Duration::hours(1), "second");
+    let response = app
+        .clone()
+        .oneshot(webhook_request(&stale, Some(sign("secret", &stale))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let unprotected = format!(r#"{{"strategy_id": "{strategy_id}"}}"#);
+    let response = app
+        .oneshot(webhook_request(
+            &unprotected,
+            Some(sign("secret", &unprotected)),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn alerts_without_a_known_strategy_are_rejected(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].webhook_secret = Some("secret".to_owned());
+    let strategy_id = config.strategies[0].id;
+    let mut unprotected = config.strategies[0].clone();
+    unprotected.id = Uuid::new_v4();
+    unprotected.webhook_secret = None;
+    let unprotected_id = unprotected.id;
+    config.strategies.push(unprotected);
+    let app = make_test_app_with_config(pool, config).await;
+
+    // The last of duplicate keys is the one the handler trades for, so it's the one checked
+    for decoy in [Uuid::new_v4(), unprotected_id] {
+        let body = format!(
+            r#"{{"strategy_id": "{decoy}", "timestamp": "{}", "nonce": "{decoy}", "strategy_id": "{strategy_id}"}}"#,
+            Utc::now().to_rfc3339()
+        );
+        let response = app
+            .clone()
+            .oneshot(webhook_request(&body, None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    for body in [
+        "not an alert".to_owned(),
+        r#"{"ticker": "AAPL"}"#.to_owned(),
+        format!(r#"{{"strategy_id": "{}"}}"#, Uuid::new_v4()),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(webhook_request(&body, None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,286 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use market::{
+    app_config::AppConfig,
+    backtest::{HistoricalBar, Timeframe},
+    pnl::TradeStatistics,
+    sizing::{
+        affordable_quantity, average_true_range, is_fractional, kelly_fraction, kelly_quantity,
+        notional_quantity, pip_risk_quantity, pip_size, pip_value, pips, risk_based_quantity,
+        short_quantity, volatility_quantity, weekend_adjusted_quantity, KellySizing,
+        VolatilitySizing,
+    },
+    strategy::CurrencyType,
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+
+#[test]
+fn risk_based_quantity_rounding() {
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.risk_per_trade = Decimal::new(1, 2);
+
+    // 1% of 10_000 over a 3 wide stop
+    strategy.currency_type = CurrencyType::Stock;
+    let quantity = risk_based_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        Decimal::from(100),
+        Decimal::from(97),
+    );
+    assert_eq!(quantity, Some(Decimal::from(33)));
+
+    strategy.currency_type = CurrencyType::Crypto;
+    let quantity = risk_based_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        Decimal::from(100),
+        Decimal::from(97),
+    );
+    assert_eq!(quantity, Some(Decimal::new(3333333333, 8)));
+
+    let quantity = risk_based_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        Decimal::from(100),
+        Decimal::from(100),
+    );
+    assert_eq!(quantity, None);
+}
+
+#[test]
+fn pip_based_quantity() {
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Forex;
+    strategy.risk_per_trade = Decimal::new(1, 2);
+
+    assert_eq!(pip_size("EUR_USD"), Decimal::new(1, 4));
+    assert_eq!(pip_size("USD_JPY"), Decimal::new(1, 2));
+    assert_eq!(
+        pips("EUR_USD", Decimal::new(11000, 4), Decimal::new(10950, 4)),
+        Decimal::from(50)
+    );
+    assert_eq!(
+        pip_value("EUR_USD", Decimal::new(11, 1), "USD"),
+        Some(Decimal::new(1, 4))
+    );
+    assert_eq!(
+        pip_value("USD_JPY", Decimal::from(100), "USD"),
+        Some(Decimal::new(1, 4))
+    );
+    assert_eq!(pip_value("EUR_GBP", Decimal::new(85, 2), "USD"), None);
+
+    // 1% of 10_000 USD over a 50 pip stop, a pip of a unit is worth 0.0001 USD
+    let quantity = pip_risk_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        "USD",
+        "EUR_USD",
+        Decimal::new(11000, 4),
+        Decimal::new(10950, 4),
+    );
+    assert_eq!(quantity, Some(Decimal::from(20_000)));
+
+    // Yen pairs are sized without losing units to the pip value
+    let quantity = pip_risk_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        "USD",
+        "USD_JPY",
+        Decimal::from(150),
+        Decimal::new(1495, 1),
+    );
+    assert_eq!(quantity, Some(Decimal::from(30_000)));
+
+    // Without a cross rate to the account currency no pip value is known
+    let quantity = pip_risk_quantity(
+        &strategy,
+        Decimal::from(10_000),
+        "USD",
+        "EUR_GBP",
+        Decimal::new(8500, 4),
+        Decimal::new(8450, 4),
+    );
+    assert_eq!(quantity, None);
+}
+
+#[test]
+fn weekend_size_reduction() {
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Stock;
+    strategy.weekend_size_factor = Some(Decimal::new(5, 1));
+    let thursday = DateTime::<Utc>::from_str("2023-08-03T15:00:00Z").unwrap();
+    let friday = DateTime::<Utc>::from_str("2023-08-04T15:00:00Z").unwrap();
+
+    let quantity = Decimal::from(5);
+    assert_eq!(
+        weekend_adjusted_quantity(&strategy, quantity, thursday),
+        Some(quantity)
+    );
+    assert_eq!(
+        weekend_adjusted_quantity(&strategy, quantity, friday),
+        Some(Decimal::from(2))
+    );
+    assert_eq!(
+        weekend_adjusted_quantity(&strategy, Decimal::ONE, friday),
+        None
+    );
+}
+
+#[test]
+fn notional_quantities() {
+    // 100 / 3 is rounded down to the stored decimals, not up
+    let quantity = notional_quantity(Decimal::from(100), Decimal::from(3)).unwrap();
+    assert_eq!(quantity, Decimal::from_str("33.33333333").unwrap());
+    assert!(is_fractional(quantity));
+    assert!(!is_fractional(Decimal::from_str("2.000").unwrap()));
+
+    assert_eq!(
+        notional_quantity(Decimal::from(1), Decimal::from(1_000_000_000)),
+        None
+    );
+    assert_eq!(notional_quantity(Decimal::from(100), Decimal::ZERO), None);
+}
+
+#[test]
+fn short_quantities() {
+    let price = Decimal::from(40);
+    assert_eq!(
+        short_quantity(Decimal::from(10), Decimal::from(1000), price),
+        Some(Decimal::from(10))
+    );
+    // Capped by the buying power, in whole shares
+    assert_eq!(
+        short_quantity(Decimal::from(50), Decimal::from(1000), price),
+        Some(Decimal::from(25))
+    );
+    assert_eq!(
+        short_quantity(
+            Decimal::from_str("2.5").unwrap(),
+            Decimal::from(1000),
+            price
+        ),
+        Some(Decimal::from(2))
+    );
+    assert_eq!(
+        short_quantity(Decimal::from(10), Decimal::from(30), price),
+        None
+    );
+}
+
+#[test]
+fn volatility_based_quantity() {
+    let bar = |high: i64, low: i64, close: i64| HistoricalBar {
+        time: Utc::now(),
+        open: Decimal::from(close),
+        high: Decimal::from(high),
+        low: Decimal::from(low),
+        close: Decimal::from(close),
+        volume: Decimal::from(1000),
+    };
+    // True ranges of 4, 6 with the gap from the close before, and 2
+    let bars = [
+        bar(101, 99, 100),
+        bar(103, 99, 102),
+        bar(104, 98, 99),
+        bar(101, 100, 101),
+    ];
+    assert_eq!(average_true_range(&bars, 3), Some(Decimal::from(4)));
+    assert_eq!(average_true_range(&bars, 4), None);
+
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Stock;
+    let sizing = VolatilitySizing {
+        risk_per_trade: Decimal::new(5, 3),
+        atr_multiple: Decimal::from(2),
+        atr_period: 3,
+        timeframe: Timeframe::OneDay,
+    };
+
+    // 0.5% of 100_000 over a stop 2 ATRs of 4 wide
+    let quantity =
+        volatility_quantity(&strategy, &sizing, Decimal::from(100_000), Decimal::from(4));
+    assert_eq!(quantity, Some(Decimal::from(62)));
+    assert_eq!(
+        volatility_quantity(&strategy, &sizing, Decimal::from(100_000), Decimal::ZERO),
+        None
+    );
+}
+
+#[test]
+fn kelly_based_quantity() {
+    let sizing = KellySizing {
+        multiplier: Decimal::new(5, 1),
+        max_fraction: Decimal::new(25, 2),
+        lookback_trades: 50,
+        min_trades: 20,
+    };
+    let statistics = |win_rate: Decimal, payoff_ratio: Option<Decimal>| TradeStatistics {
+        trades: 20,
+        win_rate: Some(win_rate),
+        payoff_ratio,
+    };
+
+    // Half of 0.6 - 0.4 / 2
+    let edge = statistics(Decimal::new(6, 1), Some(Decimal::from(2)));
+    assert_eq!(kelly_fraction(&sizing, &edge), Decimal::new(2, 1));
+    // Half of 0.8 - 0.2 / 4 is above the cap
+    let strong = statistics(Decimal::new(8, 1), Some(Decimal::from(4)));
+    assert_eq!(kelly_fraction(&sizing, &strong), Decimal::new(25, 2));
+    let losing = statistics(Decimal::new(3, 1), Some(Decimal::ONE));
+    assert_eq!(kelly_fraction(&sizing, &losing), Decimal::ZERO);
+    let no_losses = statistics(Decimal::ONE, None);
+    assert_eq!(kelly_fraction(&sizing, &no_losses), Decimal::new(25, 2));
+
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Stock;
+    // 20% of 100_000 at 50
+    let quantity = kelly_quantity(
+        &strategy,
+        &sizing,
+        &edge,
+        Decimal::from(100_000),
+        Decimal::from(50),
+    );
+    assert_eq!(quantity, Some(Decimal::from(400)));
+    let quantity = kelly_quantity(
+        &strategy,
+        &sizing,
+        &losing,
+        Decimal::from(100_000),
+        Decimal::from(50),
+    );
+    assert_eq!(quantity, None);
+}
+
+#[test]
+fn buying_power_downsizing() {
+    let mut strategy = AppConfig::build_for_test().unwrap().strategies[0].clone();
+    strategy.currency_type = CurrencyType::Stock;
+    let buying_power = Decimal::from(1_050);
+    let price = Decimal::from(100);
+
+    assert_eq!(
+        affordable_quantity(&strategy, Decimal::from(5), buying_power, price, false),
+        Some(Decimal::from(5))
+    );
+    assert_eq!(
+        affordable_quantity(&strategy, Decimal::from(20), buying_power, price, false),
+        Some(Decimal::from(10))
+    );
+    assert_eq!(
+        affordable_quantity(&strategy, Decimal::from(20), buying_power, price, true),
+        Some(Decimal::new(105, 1))
+    );
+    assert_eq!(
+        affordable_quantity(
+            &strategy,
+            Decimal::from(20),
+            Decimal::from(50),
+            price,
+            false
+        ),
+        None
+    );
+}
@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use market::{
+    api::objects::Broker,
+    order::Fill,
+    portfolio::day_start_equities,
+    stats::{compute_stats, max_drawdown, StatsQuery},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn fill(side: &str, price: i64, filled_at: &str) -> Fill {
+    Fill {
+        fill_id: Uuid::new_v4(),
+        order_id: Uuid::new_v4(),
+        strategy_id: Uuid::nil(),
+        ticker: "AAPL".to_string(),
+        side: side.to_string(),
+        quantity: Decimal::from(10),
+        price: Decimal::from(price),
+        fee: Decimal::ZERO,
+        filled_at: DateTime::<Utc>::from_str(filled_at).unwrap(),
+    }
+}
+
+fn date(date: &str) -> NaiveDate {
+    NaiveDate::from_str(date).unwrap()
+}
+
+#[test]
+fn stats_of_closed_trades_and_daily_returns() {
+    let fills = vec![
+        fill("buy", 100, "2023-08-01T14:00:00Z"),
+        fill("sell", 110, "2023-08-02T14:00:00Z"),
+        fill("buy", 110, "2023-08-02T15:00:00Z"),
+        fill("sell", 105, "2023-08-03T14:00:00Z"),
+        fill("buy", 100, "2023-08-04T14:00:00Z"),
+        fill("sell", 130, "2023-08-07T14:00:00Z"),
+    ];
+    let equities = [
+        (date("2023-08-01"), Decimal::from(1000)),
+        (date("2023-08-02"), Decimal::from(1000)),
+        (date("2023-08-03"), Decimal::from(1100)),
+        (date("2023-08-07"), Decimal::from(1000)),
+    ];
+    let query = StatsQuery {
+        from: Some(date("2023-08-02")),
+        to: None,
+    };
+
+    let stats = compute_stats(Uuid::nil(), &fills, &equities, &query);
+
+    assert_eq!(stats.trades, 3);
+    assert_eq!(stats.win_rate, Some(Decimal::from(2) / Decimal::from(3)));
+    assert_eq!(stats.average_win, Some(Decimal::from(200)));
+    assert_eq!(stats.average_loss, Some(Decimal::from(-50)));
+    assert_eq!(stats.profit_factor, Some(Decimal::from(8)));
+    // 10%, -50/1100 and 30% on the days with snapshots
+    assert_eq!(stats.days, 3);
+    assert_eq!(stats.max_drawdown.round_dp(6), Decimal::new(45455, 6));
+    assert!(stats
+        .sharpe_ratio
+        .is_some_and(|sharpe| sharpe > Decimal::ZERO));
+}
+
+#[test]
+fn days_without_equity_are_not_measured() {
+    let fills = vec![
+        fill("buy", 100, "2023-08-01T14:00:00Z"),
+        fill("sell", 110, "2023-08-02T14:00:00Z"),
+    ];
+    let equities = [
+        (date("2023-08-01"), Decimal::ZERO),
+        (date("2023-08-02"), Decimal::ZERO),
+    ];
+
+    let query = StatsQuery {
+        from: None,
+        to: None,
+    };
+
+    let stats = compute_stats(Uuid::nil(), &fills, &equities, &query);
+
+    assert_eq!(stats.trades, 1);
+    assert_eq!(stats.days, 0);
+    assert_eq!(stats.sharpe_ratio, None);
+}
+
+#[test]
+fn drawdown_of_compounded_returns() {
+    let returns = [
+        Decimal::new(10, 2),
+        Decimal::new(-20, 2),
+        Decimal::new(-50, 2),
+    ];
+
+    // 1 -> 1.1 -> 0.88 -> 0.44
+    assert_eq!(max_drawdown(&returns), Decimal::new(6, 1));
+    assert_eq!(max_drawdown(&[]), Decimal::ZERO);
+}
+
+#[sqlx::test]
+async fn equity_at_the_start_of_every_day(pool: PgPool) {
+    for (equity, taken_at) in [
+        (1000, "2023-08-01T14:00:00Z"),
+        (1010, "2023-08-01T20:00:00Z"),
+        (1020, "2023-08-02T14:00:00Z"),
+        (1030, "2023-08-03T14:00:00Z"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO portfolio_snapshots (snapshot_id, broker, equity, cash, positions, taken_at)
+            VALUES (gen_random_uuid(), 'alpaca', $1, 0, '[]', $2::timestamptz)
+            "#,
+        )
+        .bind(Decimal::from(equity))
+        .bind(taken_at)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let equities = day_start_equities(&pool, &Broker::Alpaca, None, Some(date("2023-08-02")))
+        .await
+        .unwrap();
+    assert_eq!(
+        equities,
+        vec![
+            (date("2023-08-01"), Decimal::from(1000)),
+            (date("2023-08-02"), Decimal::from(1020)),
+        ]
+    );
+}
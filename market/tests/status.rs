@@ -0,0 +1,38 @@
+use axum::{
+    body::Body,
+    http::{method::Method, Request, StatusCode},
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+mod setup;
+use setup::make_test_app;
+
+#[sqlx::test]
+async fn public_status_without_api_key(pool: PgPool) {
+    let app = make_test_app(pool).await;
+
+    let request = || {
+        Request::builder()
+            .method(Method::GET)
+            .uri("/public/status")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status["status"], "up");
+    assert_eq!(status["last_signal_minutes_ago"], serde_json::Value::Null);
+
+    // The default public limit allows a burst of 5 requests
+    for _ in 0..4 {
+        app.clone().oneshot(request()).await.unwrap();
+    }
+    let response = app.oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
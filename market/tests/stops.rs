@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use market::{
+    backtest::Timeframe,
+    order::OrderSide,
+    stops::{next_stop, StopManagement, TrailingStop},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+#[test]
+fn stops_move_to_break_even_and_trail_the_price() {
+    let management = StopManagement {
+        break_even_gain: Some(dec("0.02")),
+        trailing: Some(TrailingStop::Percent {
+            percent: dec("0.05"),
+        }),
+    };
+
+    // Not far enough in favor of the position yet
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("97"),
+            dec("101"),
+            None
+        ),
+        None
+    );
+    // Break-even is tighter than the trail right after the gain
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("97"),
+            dec("102"),
+            None
+        ),
+        Some(dec("100"))
+    );
+    // The trail takes over once it passes the entry
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("100"),
+            dec("110"),
+            None
+        ),
+        Some(dec("104.50"))
+    );
+    // Stops of shorts trail above the price
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Sell,
+            dec("100"),
+            dec("105"),
+            dec("90"),
+            None
+        ),
+        Some(dec("94.50"))
+    );
+}
+
+#[test]
+fn stops_are_never_loosened() {
+    let management = StopManagement {
+        break_even_gain: None,
+        trailing: Some(TrailingStop::Atr {
+            multiple: dec("2"),
+            period: 14,
+            timeframe: Timeframe::OneDay,
+        }),
+    };
+
+    // Price pulled back, the stop stays where it is
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("106"),
+            dec("108"),
+            Some(dec("1.5"))
+        ),
+        None
+    );
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("106"),
+            dec("112"),
+            Some(dec("1.5"))
+        ),
+        Some(dec("109.0"))
+    );
+    // ATR trails keep the stop without an average true range
+    assert_eq!(
+        next_stop(
+            &management,
+            OrderSide::Buy,
+            dec("100"),
+            dec("106"),
+            dec("112"),
+            None
+        ),
+        None
+    );
+}
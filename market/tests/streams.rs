@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use futures::{stream, StreamExt};
+use market::streams::{self, Connection, Gap, SupervisedStream};
+use tokio::time::{sleep, Duration, Instant};
+
+#[derive(Default)]
+struct Calls {
+    connections: Mutex<u32>,
+    handled: Mutex<Vec<u32>>,
+    resyncs: Mutex<Vec<Gap>>,
+}
+
+/// Stream failing to connect first, then dropping after a message and staying up afterwards.
+struct FlakyStream(Arc<Calls>);
+
+#[axum::async_trait]
+impl SupervisedStream for FlakyStream {
+    type Message = u32;
+
+    async fn connect(&self) -> Result<Connection<u32>, String> {
+        let mut connections = self.0.connections.lock().unwrap();
+        *connections += 1;
+        match *connections {
+            1 => Err("connection refused".to_owned()),
+            2 => Ok(Connection::new(
+                stream::iter([Ok(1), Err("connection reset".to_owned())]),
+                (),
+            )),
+            _ => Ok(Connection::new(
+                stream::iter([Ok(2)]).chain(stream::pending()),
+                (),
+            )),
+        }
+    }
+
+    async fn handle(&self, message: u32) {
+        self.0.handled.lock().unwrap().push(message);
+    }
+
+    async fn resync(&self, gap: Gap) -> Result<(), String> {
+        self.0.resyncs.lock().unwrap().push(gap);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn dropped_streams_are_reconnected_and_resynced() {
+    let flaky = Arc::new(Calls::default());
+    tokio::spawn(streams::supervise("flaky", FlakyStream(Arc::clone(&flaky))));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while flaky.handled.lock().unwrap().len() < 2 && Instant::now() < deadline {
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    assert_eq!(*flaky.connections.lock().unwrap(), 3);
+    assert_eq!(*flaky.handled.lock().unwrap(), vec![1, 2]);
+    // Every subscribed connection is resynced, from the time the last one was in sync
+    let resyncs = flaky.resyncs.lock().unwrap();
+    assert_eq!(resyncs.len(), 2);
+    assert!(resyncs[1].from >= resyncs[0].to);
+    assert!(resyncs[1].to > resyncs[1].from);
+}
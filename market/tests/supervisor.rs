@@ -0,0 +1,44 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use market::supervisor::{TaskState, TaskSupervisor};
+use tokio::time::{sleep, Duration, Instant};
+
+#[tokio::test]
+async fn crashed_tasks_are_restarted() {
+    let tasks = TaskSupervisor::new();
+    let runs = Arc::new(AtomicU32::new(0));
+
+    let counted = Arc::clone(&runs);
+    tasks.spawn("flaky", move || {
+        let runs = Arc::clone(&counted);
+        async move {
+            if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first run fails");
+            }
+        }
+    });
+    tasks.spawn("failing", || async { Err::<(), _>("no connection") });
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while tasks.statuses()[1].state != TaskState::Finished && Instant::now() < deadline {
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let statuses = tasks.statuses();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    let (failing, flaky) = (&statuses[0], &statuses[1]);
+    assert_eq!(flaky.name, "flaky");
+    assert_eq!(flaky.state, TaskState::Finished);
+    assert_eq!(flaky.restarts, 1);
+    assert_eq!(
+        flaky.last_error.as_deref(),
+        Some("panicked: first run fails")
+    );
+    // Errors are crashes as well, the task backs off before running again
+    assert_eq!(failing.name, "failing");
+    assert_eq!(failing.state, TaskState::Restarting);
+    assert_eq!(failing.last_error.as_deref(), Some("no connection"));
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "test-broker")]
+
+use market::{
+    api::objects::Order,
+    app_config::AppConfig,
+    clients::BrokerClientError,
+    ladder::{ProfitTarget, TakeProfitLadder},
+    order::{client_order_id, OrderRecord},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod setup;
+use setup::{make_test_state, mock_broker};
+
+fn alpaca_order(id: Uuid, client_order_id: &str, status: &str, legs: Value) -> Value {
+    json!({
+        "AlpacaOrder": {
+            "id": id,
+            "client_order_id": client_order_id,
+            "status": status,
+            "created_at": "2024-03-08T15:00:00Z",
+            "updated_at": null,
+            "submitted_at": null,
+            "filled_at": null,
+            "expired_at": null,
+            "canceled_at": null,
+            "asset_class": "us_equity",
+            "asset_id": Uuid::nil(),
+            "symbol": "AAPL",
+            "qty": "40",
+            "filled_qty": "40",
+            "type": "market",
+            "order_class": "oto",
+            "side": "buy",
+            "time_in_force": "day",
+            "limit_price": null,
+            "stop_price": null,
+            "trail_price": null,
+            "trail_percent": null,
+            "filled_avg_price": "100",
+            "extended_hours": false,
+            "legs": legs
+        }
+    })
+}
+
+#[sqlx::test]
+async fn stop_loss_is_cut_before_the_targets_are_placed(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.strategies[0].dry_run = false;
+    config.strategies[0].max_order_retries = 0;
+    config.strategies[0].take_profit_ladder = Some(TakeProfitLadder {
+        targets: vec![
+            ProfitTarget {
+                r_multiple: Decimal::ONE,
+                share: Decimal::new(5, 1),
+            },
+            ProfitTarget {
+                r_multiple: Decimal::TWO,
+                share: Decimal::new(25, 2),
+            },
+        ],
+    });
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let order_id = Uuid::new_v4();
+    let entry_client_order_id = client_order_id(strategy.id, order_id);
+    sqlx::query(
+        r#"
+        INSERT INTO orders (order_id, client_order_id, strategy_id, broker, broker_order_id, ticker, side, quantity, filled_quantity, filled_avg_price, status, execution_path, created_at, modified_at)
+        VALUES ($1, $2, $3, 'alpaca', $1::text, 'AAPL', 'buy', 40, 40, 100, 'filled', 'stable', NOW(), NOW())
+        "#,
+    )
+    .bind(order_id)
+    .bind(&entry_client_order_id)
+    .bind(strategy.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let record = OrderRecord::fetch(&pool, order_id).await.unwrap().unwrap();
+
+    let leg_id = Uuid::new_v4();
+    let mut leg = alpaca_order(leg_id, "stop", "held", json!([]))["AlpacaOrder"].clone();
+    leg["type"] = json!("stop");
+    leg["side"] = json!("sell");
+    leg["stop_price"] = json!("95");
+    leg["filled_qty"] = json!("0");
+    let entry = alpaca_order(order_id, &entry_client_order_id, "filled", json!([leg]));
+    let order: Order = serde_json::from_str(&entry.to_string()).unwrap();
+
+    let broker = mock_broker();
+    broker.set_default("update_order", entry);
+    broker.respond(
+        "create_order",
+        alpaca_order(Uuid::new_v4(), "target", "new", json!([])),
+    );
+    broker.fail(
+        "create_order",
+        BrokerClientError::AlpacaError("insufficient qty available".to_owned()),
+    );
+
+    app.core
+        .place_ladder(&broker, &record, &order)
+        .await
+        .unwrap();
+
+    let operations: Vec<String> = broker
+        .calls()
+        .into_iter()
+        .map(|call| call.operation)
+        .collect();
+    assert_eq!(
+        operations,
+        vec![
+            "update_order",
+            "create_order",
+            "create_order",
+            "update_order"
+        ]
+    );
+
+    // The stop covers what no target takes, and takes back the target which failed
+    let resizes = broker.calls_of("update_order");
+    assert_eq!(resizes[0].request[0], json!(leg_id));
+    assert_eq!(resizes[0].request[1]["qty"], "10");
+    assert_eq!(resizes[1].request[1]["qty"], "20");
+
+    let targets = broker.calls_of("create_order");
+    let target_ids: Vec<String> = (0..2)
+        .map(|index| {
+            client_order_id(
+                strategy.id,
+                Uuid::new_v5(&order_id, format!("take_profit.{index}").as_bytes()),
+            )
+        })
+        .collect();
+    for (target, target_id) in targets.iter().zip(&target_ids) {
+        assert_eq!(target.request["client_order_id"], json!(target_id));
+        assert_eq!(target.request["side"], "sell");
+    }
+    assert_eq!(targets[0].request["qty"], "20");
+    assert_eq!(targets[0].request["limit_price"], "105");
+    assert_eq!(targets[1].request["qty"], "10");
+    assert_eq!(targets[1].request["limit_price"], "110");
+
+    let statuses: Vec<String> = OrderRecord::fetch_children(&pool, order_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|child| child.status)
+        .collect();
+    assert_eq!(statuses, vec!["new", "rejected"]);
+}
@@ -0,0 +1,107 @@
+use chrono::Utc;
+use market::{
+    app_config::AppConfig,
+    core::TradeError,
+    order::Fill,
+    recorder::PlaybackClient,
+    throttle::{OrderThrottle, ThrottleLimit},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod setup;
+use setup::{make_test_state, trade_signal};
+
+#[test]
+fn throttle_per_symbol() {
+    let strategy_id = Uuid::new_v4();
+    let throttle = OrderThrottle::new(Some(2), None);
+
+    assert!(throttle.try_acquire("AAPL", strategy_id).is_ok());
+    assert!(throttle.try_acquire("aapl", strategy_id).is_ok());
+    assert_eq!(
+        throttle.try_acquire("AAPL", strategy_id),
+        Err(ThrottleLimit::Symbol(2))
+    );
+    assert!(throttle.try_acquire("MSFT", strategy_id).is_ok());
+
+    let unlimited = OrderThrottle::new(None, None);
+    assert!((0..100).all(|_| unlimited.try_acquire("AAPL", strategy_id).is_ok()));
+}
+
+#[test]
+fn throttle_per_strategy() {
+    let (looping, other) = (Uuid::new_v4(), Uuid::new_v4());
+    let throttle = OrderThrottle::new(Some(2), Some(3));
+
+    assert!(throttle.try_acquire("AAPL", looping).is_ok());
+    assert!(throttle.try_acquire("MSFT", looping).is_ok());
+    assert!(throttle.try_acquire("NVDA", looping).is_ok());
+    assert_eq!(
+        throttle.try_acquire("TSLA", looping),
+        Err(ThrottleLimit::Strategy(3))
+    );
+
+    // Orders held back by the strategy limit take no slot of their symbol
+    assert!(throttle.try_acquire("TSLA", other).is_ok());
+    assert!(throttle.try_acquire("TSLA", other).is_ok());
+    assert_eq!(
+        throttle.try_acquire("TSLA", other),
+        Err(ThrottleLimit::Symbol(2))
+    );
+}
+
+#[sqlx::test]
+async fn throttled_entries_are_rejected(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    config.throttle.orders_per_symbol_per_minute = Some(1);
+    config.strategies[0].dry_run = true;
+    let strategy = config.strategies[0].clone();
+    let app = make_test_state(pool.clone(), config).await;
+
+    let mut signal = trade_signal(&strategy);
+    signal.quantity = Some(Decimal::from(2));
+    app.core
+        .process_trade_signal(PlaybackClient::new(vec![]), signal.clone())
+        .await
+        .unwrap();
+    let err = app
+        .core
+        .process_trade_signal(PlaybackClient::new(vec![]), signal.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::Throttled(ThrottleLimit::Symbol(1))
+    ));
+
+    // Exits of a position are never held back
+    let order_id: Uuid = sqlx::query_scalar("SELECT order_id FROM orders")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let fill = Fill {
+        fill_id: Uuid::new_v4(),
+        order_id,
+        strategy_id: strategy.id,
+        ticker: "AAPL".to_string(),
+        side: "sell".to_string(),
+        quantity: Decimal::from(5),
+        price: Decimal::from(100),
+        fee: Decimal::ZERO,
+        filled_at: Utc::now(),
+    };
+    Fill::insert(&pool, &fill).await.unwrap();
+    signal.time = Utc::now();
+    app.core
+        .process_trade_signal(PlaybackClient::new(vec![]), signal)
+        .await
+        .unwrap();
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 2);
+}
@@ -0,0 +1,118 @@
+use std::sync::{Arc, Mutex};
+
+use market::{
+    api::objects::Broker,
+    supervisor::TaskSupervisor,
+    trade_executor::{ExecutorConfig, Lane, Priority, QueueMetrics, TradeExecutor},
+};
+use tokio::sync::{oneshot, Notify};
+
+#[tokio::test]
+async fn exits_bypass_the_backlog_of_entries() {
+    let executor = TradeExecutor::new(
+        ExecutorConfig {
+            capacity: 1,
+            concurrency: 1,
+            exit_capacity: 2,
+            exit_concurrency: 1,
+            ..Default::default()
+        },
+        TaskSupervisor::new(),
+    );
+    let processed = Arc::new(Mutex::new(Vec::new()));
+    let done = Arc::new(Notify::new());
+    let job = |name: &'static str| {
+        let processed = Arc::clone(&processed);
+        let done = Arc::clone(&done);
+        Box::pin(async move {
+            processed.lock().unwrap().push(name);
+            done.notify_one();
+        })
+    };
+
+    // Holds the only entry worker, the backlog of entries fills up behind it
+    let (release_entries, entries_blocked) = oneshot::channel::<()>();
+    executor
+        .submit(
+            &Broker::Alpaca,
+            Priority::Entry,
+            Box::pin(async move {
+                entries_blocked.await.ok();
+            }),
+        )
+        .unwrap();
+    while executor.metrics()[0].running == 0 {
+        tokio::task::yield_now().await;
+    }
+    executor
+        .submit(&Broker::Alpaca, Priority::Entry, job("entry"))
+        .unwrap();
+    assert!(executor
+        .submit(&Broker::Alpaca, Priority::Entry, job("rejected entry"))
+        .is_err());
+
+    // Exits are processed on their own lane while the entries wait
+    executor
+        .submit(&Broker::Alpaca, Priority::Exit, job("exit"))
+        .unwrap();
+    while processed.lock().unwrap().is_empty() {
+        done.notified().await;
+    }
+    assert_eq!(*processed.lock().unwrap(), vec!["exit"]);
+
+    // Flattens are taken ahead of the exits queued before them
+    let (release_exits, exits_blocked) = oneshot::channel::<()>();
+    executor
+        .submit(
+            &Broker::Alpaca,
+            Priority::Exit,
+            Box::pin(async move {
+                exits_blocked.await.ok();
+            }),
+        )
+        .unwrap();
+    while executor.metrics()[0].running == 0 {
+        tokio::task::yield_now().await;
+    }
+    executor
+        .submit(&Broker::Alpaca, Priority::Exit, job("stop loss update"))
+        .unwrap();
+    executor
+        .submit(&Broker::Alpaca, Priority::Flatten, job("flatten"))
+        .unwrap();
+    assert_eq!(
+        executor.metrics(),
+        vec![
+            QueueMetrics {
+                broker: "alpaca".to_owned(),
+                lane: Lane::Exit,
+                waiting: 2,
+                running: 1,
+                concurrency: 1,
+                processed: 1,
+                rejected: 0,
+            },
+            QueueMetrics {
+                broker: "alpaca".to_owned(),
+                lane: Lane::Entry,
+                waiting: 1,
+                running: 1,
+                concurrency: 1,
+                processed: 0,
+                rejected: 1,
+            },
+        ]
+    );
+
+    release_exits.send(()).unwrap();
+    release_entries.send(()).unwrap();
+    while processed.lock().unwrap().len() < 4 {
+        done.notified().await;
+    }
+    let processed = processed.lock().unwrap();
+    assert_eq!(processed[0], "exit");
+    // The lanes run side by side, only the order within one is given
+    let position = |name| processed.iter().position(|job| *job == name).unwrap();
+    assert!(position("flatten") < position("stop loss update"));
+    assert!(processed.contains(&"entry"));
+}
@@ -0,0 +1,363 @@
+use std::{net::TcpListener, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use market::{
+    api::objects::Money,
+    app_config::{Tradier, TradingEnvironment},
+    clients::{order_uuid, BrokerClient},
+    options::{OptionContract, OptionRight, OrderLeg},
+    order::{NewOrder, OrderSide, TimeInForce},
+    sizing::ExecutionPath,
+    tradier::{TradierClient, TradierClock, TradierHours, TradierMarketDay},
+};
+use pretty_assertions::assert_eq;
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+const ACCOUNT: &str = "/v1/accounts/VA1234";
+const CLIENT_ORDER_ID: &str = "0f6d1a2b3c4d.9e8f7a6b5c4d";
+
+fn authorized(headers: &HeaderMap) -> Result<(), StatusCode> {
+    if headers["Authorization"] != "Bearer tradier-token" {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Brokerage API of an account holding 10 shares of MSFT, which fills the entry of orders right
+/// away and keeps their stop loss open.
+fn brokerage() -> Router {
+    Router::new()
+        .route(
+            &format!("{ACCOUNT}/positions"),
+            get(|| async {
+                Json(json!({
+                    "positions": {
+                        "position": {
+                            "cost_basis": 4150.0,
+                            "date_acquired": "2024-03-01T14:35:00.000Z",
+                            "id": 130089,
+                            "quantity": 10.0,
+                            "symbol": "MSFT"
+                        }
+                    }
+                }))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/orders"),
+            post(|Form(form): Form<Vec<(String, String)>>| async move {
+                let id = if form.contains(&("class".to_owned(), "oto".to_owned())) {
+                    assert_eq!(
+                        form,
+                        fields(&[
+                            ("duration", "day"),
+                            ("tag", "0f6d1a2b3c4d-9e8f7a6b5c4d"),
+                            ("class", "oto"),
+                            ("symbol[0]", "AAPL"),
+                            ("side[0]", "buy"),
+                            ("quantity[0]", "20"),
+                            ("type[0]", "market"),
+                            ("symbol[1]", "AAPL"),
+                            ("side[1]", "sell"),
+                            ("quantity[1]", "20"),
+                            ("type[1]", "stop"),
+                            ("stop[1]", "185.50"),
+                        ])
+                    );
+                    1001
+                } else if form.contains(&("class".to_owned(), "option".to_owned())) {
+                    // The stop loss of the underlying is left out of options orders
+                    assert_eq!(
+                        form,
+                        fields(&[
+                            ("duration", "gtc"),
+                            ("tag", "0f6d1a2b3c4d-9e8f7a6b5c4d"),
+                            ("option_symbol", "AAPL240119C00190000"),
+                            ("class", "option"),
+                            ("symbol", "AAPL"),
+                            ("side", "buy_to_open"),
+                            ("quantity", "2"),
+                            ("type", "limit"),
+                            ("price", "3.50"),
+                        ])
+                    );
+                    1002
+                } else {
+                    // Closing the position sells the shares held
+                    assert_eq!(
+                        form,
+                        fields(&[
+                            ("duration", "day"),
+                            ("class", "equity"),
+                            ("symbol", "MSFT"),
+                            ("side", "sell"),
+                            ("quantity", "10.0"),
+                            ("type", "market"),
+                        ])
+                    );
+                    1003
+                };
+                Json(json!({ "order": { "id": id, "status": "ok" } }))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/orders/:id"),
+            get(|Path(id): Path<u64>| async move {
+                let order = match id {
+                    1001 => json!({
+                        "id": 1001,
+                        "type": "market",
+                        "symbol": "AAPL",
+                        "side": "buy",
+                        "quantity": 20.0,
+                        "status": "open",
+                        "duration": "day",
+                        "avg_fill_price": 0.0,
+                        "exec_quantity": 0.0,
+                        "create_date": "2024-03-05T14:30:00.000Z",
+                        "class": "oto",
+                        "tag": "0f6d1a2b3c4d-9e8f7a6b5c4d",
+                        "num_legs": 2,
+                        "leg": [
+                            {
+                                "id": 1004,
+                                "type": "market",
+                                "symbol": "AAPL",
+                                "side": "buy",
+                                "quantity": 20.0,
+                                "status": "filled",
+                                "duration": "day",
+                                "avg_fill_price": 190.12,
+                                "exec_quantity": 20.0,
+                                "create_date": "2024-03-05T14:30:00.000Z"
+                            },
+                            {
+                                "id": 1005,
+                                "type": "stop",
+                                "symbol": "AAPL",
+                                "side": "sell",
+                                "quantity": 20.0,
+                                "status": "open",
+                                "duration": "day",
+                                "stop_price": 185.5,
+                                "avg_fill_price": 0.0,
+                                "exec_quantity": 0.0,
+                                "create_date": "2024-03-05T14:30:00.000Z"
+                            }
+                        ]
+                    }),
+                    1002 => json!({
+                        "id": 1002,
+                        "type": "limit",
+                        "symbol": "AAPL",
+                        "option_symbol": "AAPL240119C00190000",
+                        "side": "buy_to_open",
+                        "quantity": 2.0,
+                        "status": "open",
+                        "duration": "gtc",
+                        "price": 3.5,
+                        "avg_fill_price": 0.0,
+                        "exec_quantity": 0.0,
+                        "create_date": "2024-03-05T14:31:00.000Z",
+                        "class": "option",
+                        "tag": "0f6d1a2b3c4d-9e8f7a6b5c4d"
+                    }),
+                    _ => json!({
+                        "id": 1003,
+                        "type": "market",
+                        "symbol": "MSFT",
+                        "side": "sell",
+                        "quantity": 10.0,
+                        "status": "filled",
+                        "duration": "day",
+                        "avg_fill_price": 415.2,
+                        "exec_quantity": 10.0,
+                        "create_date": "2024-03-05T14:32:00.000Z",
+                        "transaction_date": "2024-03-05T14:32:01.000Z",
+                        "class": "equity"
+                    }),
+                };
+                Json(json!({ "order": order }))
+            }),
+        )
+        .route(
+            &format!("{ACCOUNT}/balances"),
+            get(|headers: HeaderMap| async move {
+                authorized(&headers)?;
+                Ok::<_, StatusCode>(Json(json!({
+                    "balances": {
+                        "account_number": "VA1234",
+                        "account_type": "margin",
+                        "total_equity": 25012.5,
+                        "total_cash": 20862.5,
+                        "margin": { "stock_buying_power": 41725.0, "option_buying_power": 20862.5 }
+                    }
+                })))
+            }),
+        )
+}
+
+fn tradier_client(base_url: String, access_token: &str) -> Arc<TradierClient> {
+    let config = Tradier {
+        account_id: "VA1234".to_owned(),
+        access_token: access_token.to_owned(),
+        base_url: Some(base_url),
+        requests_per_minute: 120,
+    };
+    Arc::new(TradierClient::new(&config, TradingEnvironment::Paper))
+}
+
+fn new_order(time_in_force: TimeInForce) -> NewOrder {
+    NewOrder {
+        id: Uuid::new_v4(),
+        strategy_id: Uuid::new_v4(),
+        client_order_id: CLIENT_ORDER_ID.to_owned(),
+        ticker: "AAPL".to_owned(),
+        side: OrderSide::Buy,
+        quantity: Decimal::from(20),
+        notional: None,
+        stop_loss_price: Some(Decimal::new(18550, 2)),
+        limit_price: None,
+        time_in_force,
+        extended_hours: false,
+        execution_path: ExecutionPath::Stable,
+        request_id: None,
+        environment: TradingEnvironment::Paper,
+        account: None,
+        legs: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn tradier_orders() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(brokerage().into_make_service()),
+    );
+    let client = tradier_client(base_url.clone(), "tradier-token");
+    assert!(client.supports_options());
+
+    // Entries with a stop loss are sent one triggers other
+    let order = client
+        .create_order(client.order_request(&new_order(TimeInForce::Day)))
+        .await
+        .unwrap();
+    assert_eq!(order.broker_order_id(), "1001");
+    assert_eq!(order.client_order_id(), CLIENT_ORDER_ID);
+    assert_eq!(order.status(), "filled");
+    assert_eq!(order.side(), OrderSide::Buy);
+    assert_eq!(order.filled_quantity(), Decimal::from(20));
+    assert_eq!(
+        order.filled_avg_price(),
+        Some(Money::new(Decimal::new(19012, 2), "USD"))
+    );
+    assert_eq!(
+        order.stop_loss_leg(),
+        Some((order_uuid("1005").unwrap(), Decimal::new(1855, 1)))
+    );
+
+    // Options orders trade the contract of their leg
+    let mut option_order = new_order(TimeInForce::Gtc);
+    option_order.quantity = Decimal::ONE;
+    option_order.limit_price = Some(Decimal::new(350, 2));
+    option_order.legs = vec![OrderLeg {
+        contract: OptionContract {
+            underlying: "AAPL".to_owned(),
+            expiry: NaiveDate::from_ymd_opt(2024, 1, 19).unwrap(),
+            strike: Decimal::from(190),
+            right: OptionRight::Call,
+        },
+        side: OrderSide::Buy,
+        ratio: 2,
+    }];
+    let order = client
+        .create_order(client.order_request(&option_order))
+        .await
+        .unwrap();
+    assert_eq!(order.broker_order_id(), "1002");
+    assert_eq!(order.status(), "new");
+    assert_eq!(order.quantity(), Decimal::from(2));
+    assert_eq!(order.time_in_force(), TimeInForce::Gtc);
+    assert_eq!(order.stop_loss_leg(), None);
+
+    let order = client.delete_position("MSFT".to_owned()).await.unwrap();
+    assert_eq!(order.broker_order_id(), "1003");
+    assert_eq!(order.side(), OrderSide::Sell);
+    assert_eq!(
+        order.filled_at(),
+        Some("2024-03-05T14:32:01Z".parse().unwrap())
+    );
+
+    // Auction orders have no duration at Tradier
+    let err = client
+        .create_order(client.order_request(&new_order(TimeInForce::Opg)))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("must be day or gtc"));
+
+    let account = client.get_account().await.unwrap();
+    assert_eq!(account.equity(), Money::new(Decimal::new(250125, 1), "USD"));
+    assert_eq!(
+        account.buying_power(),
+        Money::new(Decimal::from(41725), "USD")
+    );
+
+    let err = tradier_client(base_url, "revoked-token")
+        .get_account()
+        .await
+        .unwrap_err();
+    assert!(err.is_authentication_failure());
+}
+
+#[test]
+fn tradier_market_calendar() {
+    let at = |time: &str| DateTime::<Utc>::from_str(time).unwrap();
+    let day = |date: &str, open: bool| TradierMarketDay {
+        date: date.parse().unwrap(),
+        status: if open { "open" } else { "closed" }.to_owned(),
+        open: open.then(|| TradierHours {
+            start: "09:30".to_owned(),
+            end: "16:00".to_owned(),
+        }),
+    };
+    // Daylight saving time starts on Sunday, March 10th 2024
+    let calendar = [
+        day("2024-03-08", true),
+        day("2024-03-09", false),
+        day("2024-03-10", false),
+        day("2024-03-11", true),
+    ];
+
+    let clock = TradierClock::from_calendar(&calendar, at("2024-03-08T15:00:00Z")).unwrap();
+    assert!(clock.open);
+    assert_eq!(clock.next_close, at("2024-03-08T21:00:00Z"));
+    assert_eq!(clock.next_open, at("2024-03-11T13:30:00Z"));
+
+    let clock = TradierClock::from_calendar(&calendar, at("2024-03-09T15:00:00Z")).unwrap();
+    assert!(!clock.open);
+    assert_eq!(clock.next_open, at("2024-03-11T13:30:00Z"));
+    assert_eq!(clock.next_close, at("2024-03-11T20:00:00Z"));
+
+    assert_eq!(
+        TradierClock::from_calendar(&calendar, at("2024-03-11T21:00:00Z")),
+        None
+    );
+}
@@ -0,0 +1,54 @@
+use market::{
+    api::error::ApiError,
+    app_config::{AppConfig, Quotas},
+    usage::{self, UsageKind, UsageQuery},
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+
+#[sqlx::test]
+async fn signals_per_day_quota(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let strategy = config.strategies[0].clone();
+    config.quotas.insert(
+        strategy.tenant_id.clone(),
+        Quotas {
+            signals_per_day: Some(2),
+            ..Default::default()
+        },
+    );
+
+    for _ in 0..2 {
+        usage::check_quotas(&pool, &config, &strategy.tenant_id)
+            .await
+            .unwrap();
+        usage::record(
+            &pool,
+            &strategy.tenant_id,
+            UsageKind::Signal,
+            Some(strategy.id),
+        )
+        .await
+        .unwrap();
+    }
+
+    let result = usage::check_quotas(&pool, &config, &strategy.tenant_id).await;
+    assert!(matches!(result, Err(ApiError::TooManyRequests(_))));
+
+    let month = chrono::Utc::now().format("%Y-%m").to_string();
+    let export = usage::export(
+        &pool,
+        &config,
+        &UsageQuery {
+            month,
+            tenant_id: Some(strategy.tenant_id.clone()),
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(export.len(), 1);
+    assert_eq!(export[0].signals, 2);
+    assert_eq!(export[0].active_strategies, 1);
+    assert_eq!(export[0].daily.len(), 1);
+}
@@ -0,0 +1,181 @@
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use market::{
+    admin::StrategyStatus,
+    api_keys::{self, NewApiKey, Role},
+    app_config::AppConfig,
+    users::{self, NewUser},
+};
+use pretty_assertions::assert_eq;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod setup;
+use setup::make_test_app_with_config;
+
+fn get(uri: &str, api_key: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .header(header::AUTHORIZATION, api_key)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn users_only_see_their_strategies(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let mut own = config.strategies[0].clone();
+    own.id = Uuid::new_v4();
+    own.tenant_id = "alice".to_owned();
+    let other = config.strategies[0].id;
+    config.strategies.push(own.clone());
+    let app = make_test_app_with_config(pool.clone(), config).await;
+
+    users::create(
+        &pool,
+        &NewUser {
+            user_id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let key = api_keys::create(
+        &pool,
+        &NewApiKey {
+            name: "alice".to_owned(),
+            role: Role::ReadOnly,
+            user_id: Some("alice".to_owned()),
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(get("/strategies", &key.secret))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let strategies: Vec<StrategyStatus> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        strategies
+            .iter()
+            .map(|strategy| strategy.id)
+            .collect::<Vec<_>>(),
+        vec![own.id]
+    );
+
+    let response = app
+        .clone()
+        .oneshot(get(&format!("/strategies/{other}/divergence"), &key.secret))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(get("/positions?broker=alpaca", &key.secret))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Rebalances are sized from the positions of the broker account
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/rebalance")
+                .header(header::AUTHORIZATION, &key.secret)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "strategy_id": own.id, "weights": { "AAPL": "1" } })
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test]
+async fn users_list_their_orders(pool: PgPool) {
+    let mut config = AppConfig::build_for_test().unwrap();
+    let mut own = config.strategies[0].clone();
+    own.id = Uuid::new_v4();
+    own.tenant_id = "alice".to_owned();
+    let other = config.strategies[0].id;
+    config.strategies.push(own.clone());
+
+    for strategy_id in [own.id, other] {
+        sqlx::query(
+            r#"
+            INSERT INTO orders (order_id, client_order_id, strategy_id, broker, ticker, side, quantity, status, created_at, modified_at)
+            VALUES (gen_random_uuid(), gen_random_uuid(), $1, 'alpaca', 'AAPL', 'buy', 1, 'filled', NOW(), NOW())
+            "#,
+        )
+        .bind(strategy_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let app = make_test_app_with_config(pool.clone(), config).await;
+
+    users::create(
+        &pool,
+        &NewUser {
+            user_id: "alice".to_owned(),
+            name: "Alice".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let key = api_keys::create(
+        &pool,
+        &NewApiKey {
+            name: "alice".to_owned(),
+            role: Role::Trade,
+            user_id: Some("alice".to_owned()),
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(get("/orders", &key.secret))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|order| order["strategy_id"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>(),
+        vec![own.id.to_string()]
+    );
+
+    // Orders of the broker accounts stay with operators
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/orders")
+                .header(header::AUTHORIZATION, &key.secret)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"AlpacaOrders": {}}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}